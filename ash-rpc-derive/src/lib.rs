@@ -0,0 +1,329 @@
+//! `#[rpc_method]`: JSON-RPC methods from plain functions.
+//!
+//! Used through the `derive` feature of `ash-rpc`, which re-exports the
+//! macro as `ash_rpc::derive::rpc_method`; see that module for the full
+//! description.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{
+    Expr, FnArg, GenericArgument, ItemFn, Lit, LitStr, Meta, Pat, PathArguments, ReturnType, Type,
+};
+
+/// Turn an async (or plain) function into a JSON-RPC method
+///
+/// Generates a unit struct named after the function in `UpperCamelCase`
+/// implementing `JsonRPCMethod`. Arguments are taken from by-position or
+/// by-name params; a `&CallContext<'_>` argument receives the call context
+/// instead. Arguments: `name = "..."` sets the method name (default: the
+/// function name), `schema` documents params and result with the
+/// `schemars` derived schemas.
+#[proc_macro_attribute]
+pub fn rpc_method(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut args = Args::default();
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            args.name = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("schema") {
+            args.schema = true;
+            Ok(())
+        } else {
+            Err(meta.error("expected `name = \"...\"` or `schema`"))
+        }
+    });
+    syn::parse_macro_input!(attr with parser);
+    let function = syn::parse_macro_input!(item as ItemFn);
+    expand(args, function)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct Args {
+    name: Option<LitStr>,
+    schema: bool,
+}
+
+/// One argument of the function
+enum Param<'a> {
+    Context,
+    Value { name: String, ty: &'a Type },
+}
+
+fn expand(args: Args, function: ItemFn) -> syn::Result<TokenStream2> {
+    let sig = &function.sig;
+    if !sig.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            sig.generics.span(),
+            "rpc_method functions cannot be generic",
+        ));
+    }
+
+    let params = sig
+        .inputs
+        .iter()
+        .map(param)
+        .collect::<syn::Result<Vec<_>>>()?;
+    let fn_name = &sig.ident;
+    let vis = &function.vis;
+    let struct_name = format_ident!(
+        "{}",
+        camel_case(&fn_name.to_string()),
+        span = fn_name.span()
+    );
+    let method = args
+        .name
+        .unwrap_or_else(|| LitStr::new(&fn_name.to_string(), fn_name.span()));
+
+    let names: Vec<&str> = params
+        .iter()
+        .filter_map(|param| match param {
+            Param::Value { name, .. } => Some(name.as_str()),
+            Param::Context => None,
+        })
+        .collect();
+    let mut index = 0usize;
+    let extract = params.iter().enumerate().map(|(position, param)| {
+        let arg = format_ident!("__arg{position}");
+        match param {
+            Param::Context => quote! { let #arg = ctx; },
+            Param::Value { name, ty } => {
+                let at = index;
+                index += 1;
+                quote! {
+                    let #arg: #ty = match __params.get(#at, #name) {
+                        Ok(value) => value,
+                        Err(error) => return ::ash_rpc::Response::error(error, id),
+                    };
+                }
+            }
+        }
+    });
+    let call_args = (0..params.len()).map(|position| format_ident!("__arg{position}"));
+    let awaited = sig.asyncness.map(|_| quote!(.await));
+    let (fallible, output) = output(&sig.output);
+    let outcome = if fallible {
+        quote! { #fn_name(#(#call_args),*) #awaited .map_err(::ash_rpc::Error::from) }
+    } else {
+        quote! { Ok::<_, ::ash_rpc::Error>(#fn_name(#(#call_args),*) #awaited) }
+    };
+
+    let spec = spec(&function, &method, &params, output, args.schema);
+
+    Ok(quote! {
+        #function
+
+        #[doc = concat!("The `", #method, "` JSON-RPC method, generated from [`", stringify!(#fn_name), "`]")]
+        #vis struct #struct_name;
+
+        #[::ash_rpc::async_trait]
+        impl ::ash_rpc::JsonRPCMethod for #struct_name {
+            fn method_name(&self) -> &'static str {
+                #method
+            }
+
+            async fn call(
+                &self,
+                params: Option<::ash_rpc::derive::serde_json::Value>,
+                id: Option<::ash_rpc::RequestId>,
+            ) -> ::ash_rpc::Response {
+                let connection = ::ash_rpc::auth::ConnectionContext::default();
+                ::ash_rpc::JsonRPCMethod::call_with_context(
+                    self,
+                    params,
+                    id,
+                    &::ash_rpc::CallContext::new(&connection),
+                )
+                .await
+            }
+
+            async fn call_with_context(
+                &self,
+                params: Option<::ash_rpc::derive::serde_json::Value>,
+                id: Option<::ash_rpc::RequestId>,
+                ctx: &::ash_rpc::CallContext<'_>,
+            ) -> ::ash_rpc::Response {
+                let __params = match ::ash_rpc::derive::Params::new(params, &[#(#names),*]) {
+                    Ok(params) => params,
+                    Err(error) => return ::ash_rpc::Response::error(error, id),
+                };
+                #(#extract)*
+                ::ash_rpc::derive::respond(#method, #outcome, id)
+            }
+
+            fn openapi_components(&self) -> ::ash_rpc::OpenApiMethodSpec {
+                #spec
+            }
+        }
+    })
+}
+
+fn param(arg: &FnArg) -> syn::Result<Param<'_>> {
+    let FnArg::Typed(typed) = arg else {
+        return Err(syn::Error::new(
+            arg.span(),
+            "rpc_method functions cannot take `self`",
+        ));
+    };
+    if let Type::Reference(reference) = typed.ty.as_ref()
+        && last_segment(&reference.elem).is_some_and(|segment| segment.ident == "CallContext")
+    {
+        return Ok(Param::Context);
+    }
+    let Pat::Ident(pat) = typed.pat.as_ref() else {
+        return Err(syn::Error::new(
+            typed.pat.span(),
+            "rpc_method arguments must be plain identifiers",
+        ));
+    };
+    let name = pat.ident.to_string();
+    Ok(Param::Value {
+        name: name.strip_prefix("r#").unwrap_or(&name).to_string(),
+        ty: &typed.ty,
+    })
+}
+
+/// Whether the function returns a `Result`, and its success type
+fn output(output: &ReturnType) -> (bool, Option<&Type>) {
+    let ReturnType::Type(_, ty) = output else {
+        return (false, None);
+    };
+    match last_segment(ty) {
+        Some(segment) if segment.ident == "Result" => {
+            let ok = match &segment.arguments {
+                PathArguments::AngleBracketed(args) => {
+                    args.args.first().and_then(|arg| match arg {
+                        GenericArgument::Type(ty) => Some(ty),
+                        _ => None,
+                    })
+                }
+                _ => None,
+            };
+            (true, ok)
+        }
+        _ => (false, Some(ty)),
+    }
+}
+
+fn last_segment(ty: &Type) -> Option<&syn::PathSegment> {
+    match ty {
+        Type::Path(path) => path.path.segments.last(),
+        _ => None,
+    }
+}
+
+fn spec(
+    function: &ItemFn,
+    method: &LitStr,
+    params: &[Param<'_>],
+    output: Option<&Type>,
+    schema: bool,
+) -> TokenStream2 {
+    let (summary, description) = docs(function);
+    let summary = summary.map(|summary| quote! { .with_summary(#summary) });
+    let description = description.map(|description| quote! { .with_description(#description) });
+
+    let values: Vec<(&str, &Type)> = params
+        .iter()
+        .filter_map(|param| match param {
+            Param::Value { name, ty } => Some((name.as_str(), *ty)),
+            Param::Context => None,
+        })
+        .collect();
+    let parameters = (!values.is_empty()).then(|| {
+        let properties = values.iter().map(|(name, ty)| {
+            let schema = type_schema(ty, schema);
+            quote! { properties.insert(#name.to_string(), #schema); }
+        });
+        let required = values
+            .iter()
+            .filter(|(_, ty)| last_segment(ty).is_none_or(|segment| segment.ident != "Option"))
+            .map(|(name, _)| name);
+        quote! {
+            .with_parameters({
+                let mut properties = ::ash_rpc::derive::serde_json::Map::new();
+                #(#properties)*
+                ::ash_rpc::derive::serde_json::json!({
+                    "type": "object",
+                    "properties": properties,
+                    "required": [#(#required),*],
+                })
+            })
+        }
+    });
+    let result = output
+        .filter(|_| schema)
+        .map(|ty| quote! { .with_result(::ash_rpc::derive::schema::<#ty>()) });
+
+    quote! {
+        ::ash_rpc::OpenApiMethodSpec::new(#method)
+            #summary
+            #description
+            #parameters
+            #result
+    }
+}
+
+fn type_schema(ty: &Type, schema: bool) -> TokenStream2 {
+    if schema {
+        quote! { ::ash_rpc::derive::schema::<#ty>() }
+    } else {
+        quote! { ::ash_rpc::derive::serde_json::json!({}) }
+    }
+}
+
+/// First paragraph of the doc comment as summary, the rest as description
+fn docs(function: &ItemFn) -> (Option<String>, Option<String>) {
+    let lines: Vec<String> = function
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) => match &meta.value {
+                Expr::Lit(expr) => match &expr.lit {
+                    Lit::Str(doc) => Some(doc.value()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        })
+        .flat_map(|doc| {
+            doc.split('\n')
+                .map(|line| line.trim().to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    let text = lines.join("\n");
+    let text = text.trim();
+    if text.is_empty() {
+        return (None, None);
+    }
+    match text.split_once("\n\n") {
+        Some((summary, rest)) => (
+            Some(summary.split_whitespace().collect::<Vec<_>>().join(" ")),
+            Some(rest.trim().to_string()),
+        ),
+        None => (
+            Some(text.split_whitespace().collect::<Vec<_>>().join(" ")),
+            None,
+        ),
+    }
+}
+
+fn camel_case(name: &str) -> String {
+    name.trim_start_matches("r#")
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect()
+}
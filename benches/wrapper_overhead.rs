@@ -0,0 +1,207 @@
+//! Per-request overhead of the processor wrappers.
+//!
+//! Every benchmark sends the same request through a different stack built on
+//! one `MethodRegistry`, so the difference to `baseline` is the cost of the
+//! wrapper alone. No latency is injected anywhere; the numbers are the fast
+//! path of each layer.
+//!
+//! ```text
+//! cargo bench --bench wrapper_overhead --features audit-logging,observability,tower
+//! ```
+//!
+//! Besides the usual criterion output, a summary is written to
+//! `<criterion dir>/wrapper_overhead_report.json`:
+//!
+//! ```json
+//! {"baseline_ns": 310.2, "wrappers": {"audit": {"mean_ns": 1290.4, "overhead_ns": 980.2}}}
+//! ```
+
+use ash_rpc::*;
+use criterion::Criterion;
+use serde_json::json;
+use std::future::Future;
+use std::hint::black_box;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+
+const GROUP: &str = "wrapper_overhead";
+
+struct Echo;
+
+#[async_trait]
+impl JsonRPCMethod for Echo {
+    fn method_name(&self) -> &'static str {
+        "echo"
+    }
+
+    async fn call(&self, params: Option<serde_json::Value>, id: Option<RequestId>) -> Response {
+        rpc_success!(params.unwrap_or_default(), id)
+    }
+}
+
+/// Token bucket that never runs dry, so only the bookkeeping is measured
+struct BucketPolicy {
+    tokens: AtomicU64,
+}
+
+impl auth::AuthPolicy for BucketPolicy {
+    fn can_access(
+        &self,
+        _method: &str,
+        _params: Option<&serde_json::Value>,
+        _ctx: &auth::ConnectionContext,
+    ) -> bool {
+        self.tokens
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |t| {
+                Some(t.checked_sub(1).unwrap_or(u64::MAX))
+            })
+            .is_ok()
+    }
+}
+
+/// Innermost tower service handing the message to a processor
+#[derive(Clone)]
+struct ProcessorService(Arc<dyn MessageProcessor + Send + Sync>);
+
+impl tower::Service<Message> for ProcessorService {
+    type Response = Option<Response>;
+    type Error = std::convert::Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, message: Message) -> Self::Future {
+        let processor = Arc::clone(&self.0);
+        Box::pin(async move { Ok(processor.process_message(message).await) })
+    }
+}
+
+fn registry() -> Arc<MethodRegistry> {
+    Arc::new(MethodRegistry::new(register_methods![Echo]))
+}
+
+fn request() -> Message {
+    Message::Request(
+        RequestBuilder::new("echo")
+            .params(json!({"value": 42}))
+            .id(json!(1))
+            .build(),
+    )
+}
+
+fn processors() -> Vec<(&'static str, Arc<dyn MessageProcessor + Send + Sync>)> {
+    let audit = |inner| -> Arc<dyn MessageProcessor + Send + Sync> {
+        Arc::new(
+            AuditProcessor::builder(inner)
+                .with_backend(Arc::new(NoopAuditBackend))
+                .build(),
+        )
+    };
+    let observable = |inner| -> Arc<dyn MessageProcessor + Send + Sync> {
+        let metrics = obs_prometheus::PrometheusMetrics::new().expect("metrics registry");
+        Arc::new(
+            ObservableProcessor::builder(inner)
+                .with_metrics(Arc::new(metrics))
+                .with_logger(Arc::new(logger::NoopLogger))
+                .build(),
+        )
+    };
+    let rate_limited = || -> Arc<dyn MessageProcessor + Send + Sync> {
+        Arc::new(
+            MethodRegistry::new(register_methods![Echo]).with_auth(BucketPolicy {
+                tokens: AtomicU64::new(u64::MAX),
+            }),
+        )
+    };
+
+    vec![
+        ("baseline", registry()),
+        ("observable", observable(registry())),
+        ("audit", audit(registry())),
+        ("rate_limit", rate_limited()),
+        ("stacked", observable(audit(rate_limited()))),
+    ]
+}
+
+fn bench_wrappers(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().expect("tokio runtime");
+    let mut group = c.benchmark_group(GROUP);
+
+    for (name, processor) in processors() {
+        group.bench_function(name, |b| {
+            b.to_async(&runtime)
+                .iter(|| async { black_box(processor.process_message(request()).await) })
+        });
+    }
+
+    // Each level is boxed, so the chain numbers include one allocation per layer
+    for depth in [1usize, 3] {
+        let mut service = tower::util::BoxCloneService::new(ProcessorService(registry()));
+        for _ in 0..depth {
+            service = tower::util::BoxCloneService::new(JsonRpcMiddleware::new(service));
+        }
+        group.bench_function(format!("middleware_chain_{depth}"), |b| {
+            b.to_async(&runtime).iter(|| {
+                let mut service = service.clone();
+                async move { black_box(tower::Service::call(&mut service, request()).await) }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn criterion_dir() -> PathBuf {
+    if let Some(home) = std::env::var_os("CRITERION_HOME") {
+        return home.into();
+    }
+    let target = std::env::var_os("CARGO_TARGET_DIR").unwrap_or_else(|| "target".into());
+    PathBuf::from(target).join("criterion")
+}
+
+fn mean_ns(dir: &std::path::Path, bench: &str) -> Option<f64> {
+    let path = dir.join(GROUP).join(bench).join("new/estimates.json");
+    let estimates: serde_json::Value = serde_json::from_slice(&std::fs::read(path).ok()?).ok()?;
+    estimates["mean"]["point_estimate"].as_f64()
+}
+
+/// Collect the means criterion just wrote into one JSON document
+fn write_report() {
+    let dir = criterion_dir();
+    let Some(baseline) = mean_ns(&dir, "baseline") else {
+        eprintln!("no baseline estimate found, skipping report");
+        return;
+    };
+
+    let mut wrappers = serde_json::Map::new();
+    let names = ["observable", "audit", "rate_limit", "stacked"];
+    let chains = ["middleware_chain_1", "middleware_chain_3"];
+    for name in names.into_iter().chain(chains) {
+        if let Some(mean) = mean_ns(&dir, name) {
+            wrappers.insert(
+                name.to_string(),
+                json!({ "mean_ns": mean, "overhead_ns": mean - baseline }),
+            );
+        }
+    }
+
+    let report = json!({ "baseline_ns": baseline, "wrappers": wrappers });
+    let path = dir.join("wrapper_overhead_report.json");
+    match serde_json::to_vec_pretty(&report).map(|json| std::fs::write(&path, json)) {
+        Ok(Ok(())) => println!("wrote {}", path.display()),
+        Ok(Err(e)) => eprintln!("failed to write {}: {e}", path.display()),
+        Err(e) => eprintln!("failed to serialize report: {e}"),
+    }
+}
+
+fn main() {
+    let mut criterion = Criterion::default().configure_from_args();
+    bench_wrappers(&mut criterion);
+    criterion.final_summary();
+    write_report();
+}
@@ -38,10 +38,10 @@ impl auth::AuthPolicy for ApiKeyAuth {
         }
 
         // Check for API key in params
-        if let Some(params) = params {
-            if let Some(api_key) = params.get("api_key").and_then(|v| v.as_str()) {
-                return self.valid_keys.contains(&api_key.to_string());
-            }
+        if let Some(params) = params
+            && let Some(api_key) = params.get("api_key").and_then(|v| v.as_str())
+        {
+            return self.valid_keys.contains(&api_key.to_string());
         }
 
         false
@@ -128,12 +128,11 @@ impl auth::AuthPolicy for RbacPolicy {
         };
 
         // Extract user's role from params
-        if let Some(params) = params {
-            if let Some(token) = params.get("user_token").and_then(|v| v.as_str()) {
-                if let Some(user_role) = self.extract_role_from_token(token) {
-                    return required_roles.contains(&user_role);
-                }
-            }
+        if let Some(params) = params
+            && let Some(token) = params.get("user_token").and_then(|v| v.as_str())
+            && let Some(user_role) = self.extract_role_from_token(token)
+        {
+            return required_roles.contains(&user_role);
         }
 
         false
@@ -244,10 +243,10 @@ impl auth::AuthPolicy for RateLimiter {
         _ctx: &auth::ConnectionContext,
     ) -> bool {
         // Extract user ID from params
-        if let Some(params) = params {
-            if let Some(user_id) = params.get("user_id").and_then(|v| v.as_str()) {
-                return self.check_rate(user_id);
-            }
+        if let Some(params) = params
+            && let Some(user_id) = params.get("user_id").and_then(|v| v.as_str())
+        {
+            return self.check_rate(user_id);
         }
         false
     }
@@ -0,0 +1,266 @@
+//! Chat service built from ash-rpc's subsystems
+//!
+//! Demonstrates:
+//! - WebSocket transport with per-room subscriptions through a StreamManager
+//! - JWT auth, the token travelling in each call's `token` param
+//! - Per-user rate limiting of posts
+//! - Audit logging of every call, joins and refused calls included
+//!
+//! Clients join a room with `chat.join`, which opens a stream of the room's
+//! messages, and post with `chat.send`:
+//!
+//! ```json
+//! {"jsonrpc": "2.0", "method": "chat.join", "params": {"room": "general", "token": "..."}, "id": 1, "stream_id": "general"}
+//! {"jsonrpc": "2.0", "method": "chat.send", "params": {"room": "general", "text": "hi", "token": "..."}, "id": 2}
+//! ```
+
+use ash_rpc::audit_logging::{AuditBackend, AuditProcessor, StdoutAuditBackend};
+use ash_rpc::auth::{AuthPolicy, ConnectionContext, JwtAuthPolicy, Principal};
+use ash_rpc::rate_limit::{Limit, RateLimitPolicy};
+use ash_rpc::streaming::{
+    PAUSE_METHOD, RESUME_METHOD, ResultStream, StreamManager, StreamingMethod,
+    StreamingMethodHandler, result_channel,
+};
+use ash_rpc::transports::WebSocketServer;
+use ash_rpc::{
+    CallContext, Error, JsonRPCMethod, MethodRegistry, RequestId, Response, error_codes,
+};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// Subscription method opening a room's stream of messages
+pub const JOIN_METHOD: &str = "chat.join";
+
+/// Method posting a message to a room
+pub const SEND_METHOD: &str = "chat.send";
+
+/// JWT scope required for every chat method
+pub const CHAT_SCOPE: &str = "chat";
+
+/// Messages buffered per member before a slow member misses some
+const ROOM_BACKLOG: usize = 64;
+
+/// Settings of a chat server
+pub struct ChatConfig {
+    secret: Vec<u8>,
+    post_limit: Limit,
+    audit: Arc<dyn AuditBackend>,
+}
+
+impl ChatConfig {
+    /// Accept HS256 tokens signed with `secret`, allowing 5 posts per second
+    /// per user in bursts of 10 and auditing to stdout
+    pub fn new(secret: impl AsRef<[u8]>) -> Self {
+        Self {
+            secret: secret.as_ref().to_vec(),
+            post_limit: Limit::token_bucket(5, 10),
+            audit: Arc::new(StdoutAuditBackend),
+        }
+    }
+
+    /// Limit on `chat.send`, counted per user
+    pub fn post_limit(mut self, limit: Limit) -> Self {
+        self.post_limit = limit;
+        self
+    }
+
+    /// Where audit events are written
+    pub fn audit_backend(mut self, backend: Arc<dyn AuditBackend>) -> Self {
+        self.audit = backend;
+        self
+    }
+}
+
+/// Open rooms and the senders reaching their members
+#[derive(Default)]
+pub struct Rooms {
+    rooms: Mutex<HashMap<String, broadcast::Sender<Value>>>,
+}
+
+impl Rooms {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, broadcast::Sender<Value>>> {
+        self.rooms.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Receive the messages posted to `room` from now on
+    fn join(&self, room: &str) -> broadcast::Receiver<Value> {
+        self.lock()
+            .entry(room.to_string())
+            .or_insert_with(|| broadcast::channel(ROOM_BACKLOG).0)
+            .subscribe()
+    }
+
+    /// Send `message` to the members of `room`, returning how many there are
+    ///
+    /// Rooms nobody is in any more are closed.
+    fn post(&self, room: &str, message: Value) -> usize {
+        let mut rooms = self.lock();
+        let Some(sender) = rooms.get(room) else {
+            return 0;
+        };
+        match sender.send(message) {
+            Ok(members) => members,
+            Err(_) => {
+                rooms.remove(room);
+                0
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct JoinParams {
+    room: String,
+}
+
+#[derive(Deserialize)]
+struct SendParams {
+    room: String,
+    text: String,
+}
+
+fn invalid_params(e: serde_json::Error) -> Error {
+    Error::new(error_codes::INVALID_PARAMS, format!("Invalid params: {e}"))
+}
+
+/// `chat.join`: streams the messages posted to a room until the member
+/// leaves or disconnects
+struct Join {
+    rooms: Arc<Rooms>,
+}
+
+#[async_trait]
+impl StreamingMethod for Join {
+    fn method_name(&self) -> &'static str {
+        JOIN_METHOD
+    }
+
+    async fn open(&self, params: Option<Value>) -> Result<ResultStream, Error> {
+        let JoinParams { room } =
+            serde_json::from_value(params.unwrap_or_default()).map_err(invalid_params)?;
+        let mut messages = self.rooms.join(&room);
+        let (tx, stream) = result_channel(ROOM_BACKLOG);
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    message = messages.recv() => match message {
+                        Ok(message) => {
+                            if tx.send(message).await.is_err() {
+                                break;
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Lagged(missed)) => {
+                            tracing::warn!(room, missed, "member fell behind");
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    _ = tx.closed() => break,
+                }
+            }
+        });
+        Ok(stream)
+    }
+}
+
+/// `chat.send`: posts a message, signed with the caller's identity
+struct Send {
+    rooms: Arc<Rooms>,
+}
+
+impl Send {
+    fn post(
+        &self,
+        params: Option<Value>,
+        id: Option<RequestId>,
+        from: Option<&Principal>,
+    ) -> Response {
+        let SendParams { room, text } = match serde_json::from_value(params.unwrap_or_default()) {
+            Ok(params) => params,
+            Err(e) => return Response::error(invalid_params(e), id),
+        };
+        let from = from.map(|principal| {
+            principal
+                .display_name
+                .clone()
+                .unwrap_or_else(|| principal.id.clone())
+        });
+        let members = self
+            .rooms
+            .post(&room, json!({"room": room, "from": from, "text": text}));
+        Response::success(json!({"delivered": members}), id)
+    }
+}
+
+#[async_trait]
+impl JsonRPCMethod for Send {
+    fn method_name(&self) -> &'static str {
+        SEND_METHOD
+    }
+
+    async fn call(&self, params: Option<Value>, id: Option<RequestId>) -> Response {
+        self.post(params, id, None)
+    }
+
+    async fn call_with_context(
+        &self,
+        params: Option<Value>,
+        id: Option<RequestId>,
+        ctx: &CallContext<'_>,
+    ) -> Response {
+        self.post(params, id, ctx.principal())
+    }
+}
+
+/// JWT auth for the chat methods
+///
+/// Pausing, resuming and leaving only act on the connection's own streams,
+/// which the transport checks, and their requests have no params to carry a
+/// token, so they are let through.
+struct ChatAuth(JwtAuthPolicy);
+
+impl AuthPolicy for ChatAuth {
+    fn can_access(&self, method: &str, params: Option<&Value>, ctx: &ConnectionContext) -> bool {
+        matches!(method, "unsubscribe" | PAUSE_METHOD | RESUME_METHOD)
+            || self.0.can_access(method, params, ctx)
+    }
+
+    fn identify(
+        &self,
+        method: &str,
+        params: Option<&Value>,
+        ctx: &ConnectionContext,
+    ) -> Option<Principal> {
+        self.0.identify(method, params, ctx)
+    }
+}
+
+/// Build a chat server listening on `addr`
+pub async fn server(addr: &str, config: ChatConfig) -> std::io::Result<WebSocketServer> {
+    let rooms = Arc::new(Rooms::default());
+
+    let streams = Arc::new(StreamManager::new());
+    streams
+        .register_handler(StreamingMethodHandler::new(Join {
+            rooms: Arc::clone(&rooms),
+        }))
+        .await;
+
+    let auth = JwtAuthPolicy::hs256(&config.secret).require_scope("chat.*", CHAT_SCOPE);
+    let registry = MethodRegistry::new(vec![Box::new(Send { rooms })])
+        .with_auth(ChatAuth(auth))
+        .with_rate_limit(
+            RateLimitPolicy::new().method_per_principal(SEND_METHOD, config.post_limit),
+        );
+    let processor = AuditProcessor::builder(Arc::new(registry))
+        .with_backend(config.audit)
+        .build();
+
+    WebSocketServer::builder(addr)
+        .processor(processor)
+        .streams(streams)
+        .build()
+}
@@ -0,0 +1,43 @@
+//! Chat service example
+//!
+//! Run with `cargo run -p chat-service`; a token for user `alice` is printed
+//! on startup. Set `CHAT_SECRET` to choose the JWT signing secret.
+
+use chat_service::{CHAT_SCOPE, ChatConfig};
+use jsonwebtoken::{EncodingKey, Header};
+use serde_json::json;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const ADDR: &str = "127.0.0.1:8090";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+
+    let secret = std::env::var("CHAT_SECRET").unwrap_or_else(|_| "chat-demo-secret".to_string());
+    let expires = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() + 3600;
+    let token = jsonwebtoken::encode(
+        &Header::default(),
+        &json!({"sub": "alice", "scope": CHAT_SCOPE, "exp": expires}),
+        &EncodingKey::from_secret(secret.as_bytes()),
+    )?;
+
+    let server = chat_service::server(ADDR, ChatConfig::new(&secret)).await?;
+
+    println!("Chat server listening on ws://{ADDR}");
+    println!("Token for alice (valid for an hour):");
+    println!("  {token}");
+    println!();
+    println!("Join a room:");
+    println!(
+        r#"  {{"jsonrpc": "2.0", "method": "chat.join", "params": {{"room": "general", "token": "{token}"}}, "id": 1, "stream_id": "general"}}"#
+    );
+    println!("Post to it:");
+    println!(
+        r#"  {{"jsonrpc": "2.0", "method": "chat.send", "params": {{"room": "general", "text": "hi", "token": "{token}"}}, "id": 2}}"#
+    );
+
+    server.run().await
+}
@@ -0,0 +1,212 @@
+//! Drives a running chat server with real WebSocket clients.
+
+use ash_rpc::audit_logging::{AuditBackend, AuditEvent, AuditResult};
+use ash_rpc::rate_limit::Limit;
+use ash_rpc::transports::{Subscription, WebSocketClient};
+use ash_rpc::{Message, RequestBuilder, Response, error_codes};
+use chat_service::{CHAT_SCOPE, ChatConfig, JOIN_METHOD, SEND_METHOD};
+use jsonwebtoken::{EncodingKey, Header};
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const SECRET: &str = "end-to-end-secret";
+
+fn token(user: &str, scope: &str, secret: &str) -> String {
+    jsonwebtoken::encode(
+        &Header::default(),
+        &json!({"sub": user, "scope": scope, "exp": 4_102_444_800u64}),
+        &EncodingKey::from_secret(secret.as_bytes()),
+    )
+    .unwrap()
+}
+
+fn alice() -> String {
+    token("alice", CHAT_SCOPE, SECRET)
+}
+
+fn bob() -> String {
+    token("bob", CHAT_SCOPE, SECRET)
+}
+
+#[derive(Default)]
+struct Recorded(Mutex<Vec<AuditEvent>>);
+
+impl AuditBackend for Recorded {
+    fn log_audit(&self, event: &AuditEvent) {
+        self.0.lock().unwrap().push(event.clone());
+    }
+}
+
+impl Recorded {
+    /// Outcomes logged for `method`, in order
+    fn results(&self, method: &str) -> Vec<AuditResult> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|event| event.method.as_deref() == Some(method))
+            .map(|event| event.result)
+            .collect()
+    }
+}
+
+/// Start a chat server and return its URL
+async fn start(config: ChatConfig) -> String {
+    let addr = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().to_string()
+    };
+    let server = chat_service::server(&addr, config).await.unwrap();
+    tokio::spawn(async move { server.run().await.map_err(|e| e.to_string()) });
+    for _ in 0..200 {
+        if tokio::net::TcpStream::connect(&addr).await.is_ok() {
+            return format!("ws://{addr}");
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("chat server on {addr} did not come up");
+}
+
+async fn join(client: &mut WebSocketClient, room: &str, token: &str) -> Subscription {
+    client
+        .subscribe(JOIN_METHOD, Some(json!({"room": room, "token": token})))
+        .await
+        .unwrap()
+}
+
+async fn send(client: &mut WebSocketClient, room: &str, text: &str, token: &str) -> Response {
+    let request = RequestBuilder::new(SEND_METHOD)
+        .params(json!({"room": room, "text": text, "token": token}))
+        .id(json!(1))
+        .build();
+    client
+        .send_message(&Message::Request(request))
+        .await
+        .unwrap();
+    let response = tokio::time::timeout(Duration::from_secs(5), client.recv_message())
+        .await
+        .expect("timed out waiting for response")
+        .unwrap()
+        .unwrap();
+    response.as_response().unwrap().clone()
+}
+
+async fn next_message(subscription: &mut Subscription) -> Value {
+    let event = tokio::time::timeout(Duration::from_secs(5), subscription.next())
+        .await
+        .expect("timed out waiting for a message")
+        .unwrap();
+    event.data().clone()
+}
+
+#[tokio::test]
+async fn members_of_a_room_receive_its_messages() {
+    let url = start(ChatConfig::new(SECRET)).await;
+    let mut alice_client = WebSocketClient::connect(&url).await.unwrap();
+    let mut bob_client = WebSocketClient::connect(&url).await.unwrap();
+
+    let mut alice_general = join(&mut alice_client, "general", &alice()).await;
+    let mut bob_general = join(&mut bob_client, "general", &bob()).await;
+    let mut bob_random = join(&mut bob_client, "random", &bob()).await;
+
+    let response = send(&mut alice_client, "general", "hello", &alice()).await;
+    assert_eq!(response.result, Some(json!({"delivered": 2})));
+    let expected = json!({"room": "general", "from": "alice", "text": "hello"});
+    assert_eq!(next_message(&mut alice_general).await, expected);
+    assert_eq!(next_message(&mut bob_general).await, expected);
+
+    let response = send(&mut bob_client, "random", "anyone?", &bob()).await;
+    assert_eq!(response.result, Some(json!({"delivered": 1})));
+    assert_eq!(
+        next_message(&mut bob_random).await,
+        json!({"room": "random", "from": "bob", "text": "anyone?"})
+    );
+
+    // leaving needs no token and stops the room's messages
+    bob_general.unsubscribe().await.unwrap();
+    send(&mut alice_client, "general", "still here?", &alice()).await;
+    assert_eq!(
+        next_message(&mut alice_general).await["text"],
+        "still here?"
+    );
+
+    alice_client.close().await.unwrap();
+    bob_client.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn calls_without_a_valid_token_are_refused() {
+    let url = start(ChatConfig::new(SECRET)).await;
+    let mut client = WebSocketClient::connect(&url).await.unwrap();
+
+    let forged = token("mallory", CHAT_SCOPE, "another-secret");
+    let unscoped = token("mallory", "profile", SECRET);
+    for params in [
+        json!({"room": "general"}),
+        json!({"room": "general", "token": forged}),
+        json!({"room": "general", "token": unscoped}),
+    ] {
+        assert!(client.subscribe(JOIN_METHOD, Some(params)).await.is_err());
+    }
+
+    let response = send(&mut client, "general", "hi", &forged).await;
+    assert_eq!(response.error.unwrap().code, error_codes::INTERNAL_ERROR);
+
+    client.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn posts_are_rate_limited_per_user() {
+    let config =
+        ChatConfig::new(SECRET).post_limit(Limit::sliding_window(2, Duration::from_secs(60)));
+    let url = start(config).await;
+    let mut client = WebSocketClient::connect(&url).await.unwrap();
+
+    for _ in 0..2 {
+        let response = send(&mut client, "general", "spam", &alice()).await;
+        assert!(response.is_success());
+    }
+    let refused = send(&mut client, "general", "spam", &alice()).await;
+    assert_eq!(refused.error.unwrap().code, error_codes::RETRY_LATER);
+
+    // the same connection, another user
+    let response = send(&mut client, "general", "hi", &bob()).await;
+    assert!(response.is_success());
+
+    client.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn every_call_is_audited() {
+    let audit = Arc::new(Recorded::default());
+    let config = ChatConfig::new(SECRET).audit_backend(audit.clone());
+    let url = start(config).await;
+    let mut client = WebSocketClient::connect(&url).await.unwrap();
+
+    let _general = join(&mut client, "general", &alice()).await;
+    assert!(
+        client
+            .subscribe(JOIN_METHOD, Some(json!({"room": "general"})))
+            .await
+            .is_err()
+    );
+    send(&mut client, "general", "hello", &alice()).await;
+
+    // each call logs its request, then its outcome
+    assert_eq!(
+        audit.results(JOIN_METHOD),
+        [
+            AuditResult::Success,
+            AuditResult::Success,
+            AuditResult::Success,
+            AuditResult::Failure
+        ]
+    );
+    assert_eq!(
+        audit.results(SEND_METHOD),
+        [AuditResult::Success, AuditResult::Success]
+    );
+
+    client.close().await.unwrap();
+}
@@ -32,7 +32,7 @@ impl Sanitizer for ProductionSanitizer {
             }
 
             // Custom server errors also get generic message
-            code if code >= -32099 && code <= -32000 => {
+            code if (-32099..=-32000).contains(&code) => {
                 ErrorBuilder::new(error.code(), "Server error").build()
             }
 
@@ -29,12 +29,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let registry = MethodRegistry::new(vec![Box::new(PingMethod)]);
 
     // Configure security settings
-    let security_config = SecurityConfig {
-        max_connections: 10,                      // Allow max 10 concurrent connections
-        max_request_size: 1024 * 100,             // 100 KB max request size
-        request_timeout: Duration::from_secs(10), // 10 second request timeout
-        idle_timeout: Duration::from_secs(60),    // 60 second idle timeout
-    };
+    let mut security_config = SecurityConfig::default()
+        .with_max_json_depth(32) // Reject deeply nested payloads
+        .with_max_json_nodes(10_000) // Cap values per request
+        .with_max_connection_lifetime(Duration::ZERO) // Never recycle connections
+        .with_max_requests_per_connection(0); // No per-connection request cap
+    security_config.max_connections = 10; // Allow max 10 concurrent connections
+    security_config.max_request_size = 1024 * 100; // 100 KB max request size
+    security_config.request_timeout = Duration::from_secs(10); // 10 second request timeout
+    security_config.idle_timeout = Duration::from_secs(60); // 60 second idle timeout
 
     // Create server with security configuration
     let server = TcpServerBuilder::new("127.0.0.1:8080")
@@ -0,0 +1,27 @@
+#![no_main]
+
+use ash_rpc::transports::security::check_json_limits;
+use libfuzzer_sys::fuzz_target;
+
+const MAX_DEPTH: usize = 16;
+
+fn depth(value: &serde_json::Value) -> usize {
+    match value {
+        serde_json::Value::Array(items) => 1 + items.iter().map(depth).max().unwrap_or(0),
+        serde_json::Value::Object(map) => 1 + map.values().map(depth).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+fuzz_target!(|data: &str| {
+    let accepted = check_json_limits(data, MAX_DEPTH, usize::MAX).is_ok();
+
+    // The scanner must never let through a document nested deeper than the
+    // limit, whatever it thinks of malformed input
+    if !accepted {
+        return;
+    }
+    if let Ok(value) = serde_json::from_str::<serde_json::Value>(data) {
+        assert!(depth(&value) <= MAX_DEPTH, "scanner accepted depth {}", depth(&value));
+    }
+});
@@ -0,0 +1,19 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &str| {
+    let Ok(message) = serde_json::from_str::<ash_rpc::Message>(data) else {
+        return;
+    };
+
+    // The borrowed fast path must agree with the regular deserializer
+    let borrowed = ash_rpc::borrowed::parse_message(data).expect("borrowed parse failed");
+    assert_eq!(
+        serde_json::to_value(&message).unwrap(),
+        serde_json::to_value(&borrowed).unwrap()
+    );
+
+    let encoded = serde_json::to_string(&message).unwrap();
+    serde_json::from_str::<ash_rpc::Message>(&encoded).expect("round trip failed");
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(message) = serde_json::from_slice::<ash_rpc::StreamMessage>(data) {
+        let encoded = serde_json::to_vec(&message).unwrap();
+        let _ = serde_json::from_slice::<ash_rpc::StreamMessage>(&encoded);
+    }
+});
@@ -0,0 +1,26 @@
+#![no_main]
+
+//! Mirrors what the TCP transports do with one line of input: enforce the
+//! JSON complexity limits, then parse either a batch or a single message
+//! through the static-response fast path.
+
+use ash_rpc::borrowed::{Prepared, prepare};
+use ash_rpc::{Message, MethodRegistry, SecurityConfig};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &str| {
+    let config = SecurityConfig::default();
+    if config.check_json_limits(data).is_err() {
+        return;
+    }
+
+    let line = data.trim();
+    if line.starts_with('[') {
+        let _ = serde_json::from_str::<Vec<Message>>(line);
+    } else {
+        let registry = MethodRegistry::empty();
+        if let Ok(Prepared::Message(message)) = prepare(line, &registry) {
+            let _ = serde_json::to_string(&message);
+        }
+    }
+});
@@ -0,0 +1,444 @@
+//! Time-window anomaly detection on the audit stream.
+
+use super::{
+    AuditBackend, AuditEvent, AuditEventType, AuditIntegrity, AuditResult, AuditSeverity,
+    NoIntegrity,
+};
+use crate::transports::listener::pattern_matches;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Detects one kind of anomaly in the events it observes
+///
+/// Rules see every event in the order it is logged and keep whatever window
+/// state they need; they should rely on [`AuditEvent::timestamp`] rather than
+/// the wall clock so replayed logs are analyzed the same way.
+pub trait AnomalyRule: Send + Sync {
+    /// Stable name, used as the `rule` metadata and metric label
+    fn name(&self) -> &str;
+
+    /// Description of the anomaly `event` completes, if any
+    fn observe(&self, event: &AuditEvent) -> Option<String>;
+}
+
+/// Audit backend that runs anomaly rules over the events passing through it
+///
+/// Every event is forwarded to the inner backend first. Each anomaly is then
+/// logged there as a `SecurityViolation` event carrying the `rule` and
+/// `anomaly` metadata and the principal, address, method and correlation id
+/// of the event that triggered it, and counted in [`anomaly_stats`].
+///
+/// ```rust
+/// use ash_rpc::audit_logging::*;
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let analyzer = AuditAnalyzer::new(Arc::new(StdoutAuditBackend))
+///     .rule(DeniedSpikeRule::new(20, Duration::from_secs(60)))
+///     .rule(NewPrincipalRule::new().sensitive("admin.*"))
+///     .rule(OffHoursRule::new(8, 18).method("admin.*"));
+/// ```
+pub struct AuditAnalyzer {
+    inner: Arc<dyn AuditBackend>,
+    integrity: Arc<dyn AuditIntegrity>,
+    rules: Vec<Box<dyn AnomalyRule>>,
+}
+
+impl AuditAnalyzer {
+    pub fn new(inner: Arc<dyn AuditBackend>) -> Self {
+        Self {
+            inner,
+            integrity: Arc::new(NoIntegrity),
+            rules: Vec::new(),
+        }
+    }
+
+    pub fn rule(mut self, rule: impl AnomalyRule + 'static) -> Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    /// Integrity added to the violation events the analyzer logs
+    ///
+    /// Share the instance used by the rest of the pipeline so hash chains and
+    /// sequences stay contiguous.
+    pub fn with_integrity(mut self, integrity: Arc<dyn AuditIntegrity>) -> Self {
+        self.integrity = integrity;
+        self
+    }
+
+    fn violation(&self, rule: &str, description: String, cause: &AuditEvent) -> AuditEvent {
+        let mut event = AuditEvent::builder()
+            .event_type(AuditEventType::SecurityViolation)
+            .result(AuditResult::Violation)
+            .severity(AuditSeverity::Critical)
+            .metadata("violation_type", "anomaly")
+            .metadata("rule", rule)
+            .metadata("anomaly", description)
+            .build();
+        event.correlation_id = cause.correlation_id.clone();
+        event.remote_addr = cause.remote_addr;
+        event.principal = cause.principal.clone();
+        event.method = cause.method.clone();
+        event
+    }
+}
+
+impl AuditBackend for AuditAnalyzer {
+    fn log_audit(&self, event: &AuditEvent) {
+        self.inner.log_audit(event);
+        for rule in &self.rules {
+            let Some(description) = rule.observe(event) else {
+                continue;
+            };
+            tracing::warn!(rule = rule.name(), anomaly = %description, "audit anomaly detected");
+            counter(rule.name()).fetch_add(1, Ordering::Relaxed);
+            let mut violation = self.violation(rule.name(), description, event);
+            self.integrity.add_integrity(&mut violation);
+            self.inner.log_audit(&violation);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+fn counters() -> &'static RwLock<BTreeMap<String, Arc<AtomicU64>>> {
+    static COUNTERS: OnceLock<RwLock<BTreeMap<String, Arc<AtomicU64>>>> = OnceLock::new();
+    COUNTERS.get_or_init(Default::default)
+}
+
+fn counter(rule: &str) -> Arc<AtomicU64> {
+    if let Some(counter) = counters()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(rule)
+    {
+        return Arc::clone(counter);
+    }
+    let mut counters = counters().write().unwrap_or_else(|e| e.into_inner());
+    Arc::clone(counters.entry(rule.to_string()).or_default())
+}
+
+/// Anomalies flagged since process start by rule name, sorted by name
+pub fn anomaly_stats() -> Vec<(String, u64)> {
+    counters()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|(rule, counter)| (rule.clone(), counter.load(Ordering::Relaxed)))
+        .collect()
+}
+
+/// Flags `threshold` `Denied` results within `window`
+///
+/// Once flagged, the window starts over so a sustained spike is reported
+/// about once per `threshold` denials rather than on every one.
+pub struct DeniedSpikeRule {
+    threshold: usize,
+    window: Duration,
+    denials: Mutex<VecDeque<SystemTime>>,
+}
+
+impl DeniedSpikeRule {
+    pub fn new(threshold: usize, window: Duration) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            window,
+            denials: Mutex::new(VecDeque::new()),
+        }
+    }
+}
+
+impl AnomalyRule for DeniedSpikeRule {
+    fn name(&self) -> &str {
+        "denied_spike"
+    }
+
+    fn observe(&self, event: &AuditEvent) -> Option<String> {
+        if event.result != AuditResult::Denied {
+            return None;
+        }
+        let mut denials = self.denials.lock().unwrap_or_else(|e| e.into_inner());
+        denials.push_back(event.timestamp);
+        while denials.front().is_some_and(|oldest| {
+            event
+                .timestamp
+                .duration_since(*oldest)
+                .is_ok_and(|age| age > self.window)
+        }) {
+            denials.pop_front();
+        }
+        if denials.len() < self.threshold {
+            return None;
+        }
+        denials.clear();
+        Some(format!(
+            "{} denied results within {:?}",
+            self.threshold, self.window
+        ))
+    }
+}
+
+/// Flags the first call of a principal to a sensitive method
+///
+/// Principals become known by being [seeded](Self::known), by calling a
+/// sensitive method once (after being flagged) or by showing up at all during
+/// the [learning period](Self::learning_period), which starts with the first
+/// observed event.
+pub struct NewPrincipalRule {
+    sensitive: Vec<String>,
+    learning_period: Duration,
+    state: Mutex<PrincipalState>,
+}
+
+#[derive(Default)]
+struct PrincipalState {
+    started: Option<SystemTime>,
+    known: HashSet<String>,
+}
+
+impl NewPrincipalRule {
+    pub fn new() -> Self {
+        Self {
+            sensitive: Vec::new(),
+            learning_period: Duration::ZERO,
+            state: Mutex::new(PrincipalState::default()),
+        }
+    }
+
+    /// Treat methods matching `pattern` (`"admin.*"` style) as sensitive
+    pub fn sensitive(mut self, pattern: impl Into<String>) -> Self {
+        self.sensitive.push(pattern.into());
+        self
+    }
+
+    pub fn known(self, principal: impl Into<String>) -> Self {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .known
+            .insert(principal.into());
+        self
+    }
+
+    pub fn learning_period(mut self, period: Duration) -> Self {
+        self.learning_period = period;
+        self
+    }
+}
+
+impl Default for NewPrincipalRule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AnomalyRule for NewPrincipalRule {
+    fn name(&self) -> &str {
+        "new_principal"
+    }
+
+    fn observe(&self, event: &AuditEvent) -> Option<String> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let started = *state.started.get_or_insert(event.timestamp);
+        let principal = event.principal.as_ref()?;
+        let learning = event
+            .timestamp
+            .duration_since(started)
+            .map_or(true, |elapsed| elapsed < self.learning_period);
+        if learning {
+            state.known.insert(principal.clone());
+            return None;
+        }
+
+        let method = event.method.as_deref()?;
+        if !self
+            .sensitive
+            .iter()
+            .any(|pattern| pattern_matches(pattern, method))
+            || !state.known.insert(principal.clone())
+        {
+            return None;
+        }
+        Some(format!(
+            "principal '{principal}' called sensitive method '{method}' for the first time"
+        ))
+    }
+}
+
+/// Flags admin actions outside business hours
+///
+/// Business hours run from `start_hour` to `end_hour` (exclusive) in UTC
+/// unless an offset is set; a start after the end spans midnight. Admin
+/// actions are `AdminAction` and `ConfigurationChange` events plus calls of
+/// methods matching [`method`](Self::method) patterns.
+pub struct OffHoursRule {
+    start_hour: u32,
+    end_hour: u32,
+    utc_offset_minutes: i32,
+    methods: Vec<String>,
+}
+
+impl OffHoursRule {
+    pub fn new(start_hour: u32, end_hour: u32) -> Self {
+        Self {
+            start_hour: start_hour % 24,
+            end_hour: end_hour % 24,
+            utc_offset_minutes: 0,
+            methods: Vec::new(),
+        }
+    }
+
+    /// Offset of the business hours' time zone from UTC
+    pub fn utc_offset_minutes(mut self, minutes: i32) -> Self {
+        self.utc_offset_minutes = minutes;
+        self
+    }
+
+    /// Treat calls of methods matching `pattern` as admin actions
+    pub fn method(mut self, pattern: impl Into<String>) -> Self {
+        self.methods.push(pattern.into());
+        self
+    }
+
+    fn local_hour(&self, time: SystemTime) -> u32 {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        let local = secs + i64::from(self.utc_offset_minutes) * 60;
+        local.div_euclid(3600).rem_euclid(24) as u32
+    }
+
+    fn in_hours(&self, hour: u32) -> bool {
+        if self.start_hour <= self.end_hour {
+            (self.start_hour..self.end_hour).contains(&hour)
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        }
+    }
+}
+
+impl AnomalyRule for OffHoursRule {
+    fn name(&self) -> &str {
+        "off_hours_admin"
+    }
+
+    fn observe(&self, event: &AuditEvent) -> Option<String> {
+        let admin = matches!(
+            event.event_type,
+            AuditEventType::AdminAction | AuditEventType::ConfigurationChange
+        ) || event.method.as_deref().is_some_and(|method| {
+            self.methods
+                .iter()
+                .any(|pattern| pattern_matches(pattern, method))
+        });
+        let hour = self.local_hour(event.timestamp);
+        if !admin || self.in_hours(hour) {
+            return None;
+        }
+        Some(format!(
+            "admin action at {hour:02}h, outside {:02}h-{:02}h",
+            self.start_hour, self.end_hour
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Collect(Mutex<Vec<AuditEvent>>);
+
+    impl AuditBackend for Collect {
+        fn log_audit(&self, event: &AuditEvent) {
+            self.0.lock().unwrap().push(event.clone());
+        }
+    }
+
+    fn event(secs: u64, principal: &str, method: &str, result: AuditResult) -> AuditEvent {
+        let mut event = AuditEvent::builder()
+            .event_type(AuditEventType::MethodInvocation)
+            .principal(principal)
+            .method(method)
+            .result(result)
+            .build();
+        event.timestamp = UNIX_EPOCH + Duration::from_secs(secs);
+        event
+    }
+
+    #[test]
+    fn test_analyzer_flags_anomalies() {
+        let collected = Arc::new(Collect::default());
+        let analyzer = AuditAnalyzer::new(collected.clone())
+            .rule(DeniedSpikeRule::new(3, Duration::from_secs(60)))
+            .rule(
+                NewPrincipalRule::new()
+                    .sensitive("admin.*")
+                    .learning_period(Duration::from_secs(3600)),
+            )
+            .rule(OffHoursRule::new(8, 18).method("admin.*"));
+
+        // day 1, 10:00 UTC: alice is learned while calling admin methods in hours
+        let day = 86_400;
+        analyzer.log_audit(&event(
+            day + 10 * 3600,
+            "alice",
+            "admin.reload",
+            AuditResult::Success,
+        ));
+        // two denials a minute apart do not make a spike, three within a minute do
+        for secs in [0, 61, 70, 80] {
+            analyzer.log_audit(&event(
+                day + 11 * 3600 + secs,
+                "mallory",
+                "orders.get",
+                AuditResult::Denied,
+            ));
+        }
+        // mallory calls an admin method at 23:00
+        analyzer.log_audit(&event(
+            day + 23 * 3600,
+            "mallory",
+            "admin.reload",
+            AuditResult::Success,
+        ));
+        analyzer.log_audit(&event(
+            day + 12 * 3600 + day,
+            "alice",
+            "admin.reload",
+            AuditResult::Success,
+        ));
+
+        let events = collected.0.lock().unwrap();
+        let violations: Vec<_> = events
+            .iter()
+            .filter(|e| e.event_type == AuditEventType::SecurityViolation)
+            .map(|e| {
+                (
+                    e.metadata["rule"].as_str().unwrap(),
+                    e.principal.as_deref().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            violations,
+            [
+                ("denied_spike", "mallory"),
+                ("new_principal", "mallory"),
+                ("off_hours_admin", "mallory"),
+            ]
+        );
+        assert_eq!(events.len(), 7 + 3);
+        assert!(
+            anomaly_stats()
+                .iter()
+                .any(|(rule, count)| rule == "denied_spike" && *count >= 1)
+        );
+    }
+}
@@ -0,0 +1,437 @@
+//! Anchoring hash chain heads outside the audit log.
+//!
+//! A hash chain shows that logged events were not edited, removed or
+//! reordered, but not that the newest ones were not cut off, nor that the
+//! whole log was not rewritten by someone able to recompute the hashes. An
+//! [`Anchor`] pins the chain head at a point in time: it is signed with a
+//! key the log writer does not share with the log's storage, optionally
+//! countersigned by an RFC 3161 timestamp authority, and kept in a separate
+//! append-only anchor file. [`AuditVerifier::with_anchors`] then checks
+//! replayed chains against the anchors.
+
+use super::{AuditVerifier, HashChainIntegrity};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, mpsc};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Signed record of a hash chain head
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Anchor {
+    /// Number of events the anchor covers, i.e. the chain's next index
+    pub next_index: u64,
+    /// Hash of the last covered event
+    pub head_hash: String,
+    /// Milliseconds since the Unix epoch
+    pub anchored_at_ms: u64,
+    /// [`AnchorSigner::algorithm`] of the signer
+    pub algorithm: String,
+    /// Hex encoded signature over [`payload`](Self::payload)
+    pub signature: String,
+    /// Hex encoded RFC 3161 response of a timestamp authority, if one was used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp_token: Option<String>,
+}
+
+impl Anchor {
+    /// Bytes covered by the signature
+    pub fn payload(&self) -> Vec<u8> {
+        format!(
+            "{}:{}:{}",
+            self.next_index, self.head_hash, self.anchored_at_ms
+        )
+        .into_bytes()
+    }
+
+    /// SHA-256 of the payload, the digest submitted for timestamping
+    pub fn digest(&self) -> [u8; 32] {
+        Sha256::digest(self.payload()).into()
+    }
+
+    /// Whether the signature is valid and the timestamp token, if any, covers
+    /// this anchor
+    ///
+    /// The token is only checked for carrying the anchor's digest; validating
+    /// the authority's own signature on it is left to the authority's
+    /// tooling, e.g. `openssl ts -verify`.
+    pub fn verify(&self, signer: &dyn AnchorSigner) -> bool {
+        if self.algorithm != signer.algorithm() {
+            return false;
+        }
+        let Some(signature) = unhex(&self.signature) else {
+            return false;
+        };
+        if !signer.verify(&self.payload(), &signature) {
+            return false;
+        }
+        match &self.timestamp_token {
+            Some(token) => unhex(token).is_some_and(|token| {
+                let digest = self.digest();
+                token.windows(digest.len()).any(|window| window == digest)
+            }),
+            None => true,
+        }
+    }
+}
+
+/// Signs and verifies anchors
+pub trait AnchorSigner: Send + Sync {
+    /// Name recorded in [`Anchor::algorithm`]
+    fn algorithm(&self) -> &str;
+
+    fn sign(&self, payload: &[u8]) -> Vec<u8>;
+
+    /// Check `signature`; the default re-signs, which suits symmetric schemes
+    fn verify(&self, payload: &[u8], signature: &[u8]) -> bool {
+        let expected = self.sign(payload);
+        expected.len() == signature.len()
+            && expected
+                .iter()
+                .zip(signature)
+                .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+}
+
+/// HMAC-SHA256 with a shared secret key
+pub struct HmacSha256Signer {
+    key: Vec<u8>,
+}
+
+impl HmacSha256Signer {
+    pub fn new(key: impl Into<Vec<u8>>) -> Self {
+        Self { key: key.into() }
+    }
+}
+
+impl AnchorSigner for HmacSha256Signer {
+    fn algorithm(&self) -> &str {
+        "hmac-sha256"
+    }
+
+    fn sign(&self, payload: &[u8]) -> Vec<u8> {
+        let mut block = [0u8; 64];
+        if self.key.len() > block.len() {
+            block[..32].copy_from_slice(&Sha256::digest(&self.key));
+        } else {
+            block[..self.key.len()].copy_from_slice(&self.key);
+        }
+        let mut inner = Sha256::new();
+        inner.update(block.map(|b| b ^ 0x36));
+        inner.update(payload);
+        let mut outer = Sha256::new();
+        outer.update(block.map(|b| b ^ 0x5c));
+        outer.update(inner.finalize());
+        outer.finalize().to_vec()
+    }
+}
+
+/// Client of an RFC 3161 timestamp authority
+///
+/// Implementations send the DER encoded `TimeStampReq` to the authority,
+/// typically as an HTTP POST with content type
+/// `application/timestamp-query`, and return its DER encoded
+/// `TimeStampResp`.
+pub trait TimestampAuthority: Send + Sync {
+    fn timestamp(
+        &self,
+        request: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>>;
+}
+
+/// DER encoded RFC 3161 `TimeStampReq` for a SHA-256 `digest`, asking for
+/// the authority's certificate to be included
+pub fn timestamp_request(digest: &[u8; 32]) -> Vec<u8> {
+    // SHA-256 AlgorithmIdentifier: OID 2.16.840.1.101.3.4.2.1, NULL parameters
+    const SHA256_ALGORITHM: [u8; 15] = [
+        0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00,
+    ];
+    let mut imprint = vec![0x30, 0x31];
+    imprint.extend_from_slice(&SHA256_ALGORITHM);
+    imprint.extend_from_slice(&[0x04, 0x20]);
+    imprint.extend_from_slice(digest);
+
+    let mut request = vec![0x30, 0x39, 0x02, 0x01, 0x01];
+    request.extend_from_slice(&imprint);
+    // certReq BOOLEAN TRUE
+    request.extend_from_slice(&[0x01, 0x01, 0xff]);
+    request
+}
+
+/// Anchors the head of a [`HashChainIntegrity`]
+///
+/// ```rust,no_run
+/// use ash_rpc::audit_logging::*;
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let chain = Arc::new(HashChainIntegrity::new());
+/// let anchorer = ChainAnchorer::new(Arc::clone(&chain), Arc::new(HmacSha256Signer::new(b"anchor key".to_vec())))
+///     .anchor_file("/var/log/myapp/audit.anchors");
+/// // anchors every five minutes, and once more when dropped
+/// let _schedule = Arc::new(anchorer).every(Duration::from_secs(300));
+/// ```
+pub struct ChainAnchorer {
+    chain: Arc<HashChainIntegrity>,
+    signer: Arc<dyn AnchorSigner>,
+    authority: Option<Arc<dyn TimestampAuthority>>,
+    file: Option<PathBuf>,
+}
+
+impl ChainAnchorer {
+    pub fn new(chain: Arc<HashChainIntegrity>, signer: Arc<dyn AnchorSigner>) -> Self {
+        Self {
+            chain,
+            signer,
+            authority: None,
+            file: None,
+        }
+    }
+
+    /// Have anchors countersigned by `authority`
+    pub fn timestamp_authority(mut self, authority: Arc<dyn TimestampAuthority>) -> Self {
+        self.authority = Some(authority);
+        self
+    }
+
+    /// Append anchors as JSON lines to `path`, read back by [`read_anchors`]
+    pub fn anchor_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Anchor the current chain head
+    ///
+    /// A failing timestamp authority is logged and the anchor kept without a
+    /// token; failing to write the anchor file is an error.
+    pub fn anchor(&self) -> io::Result<Anchor> {
+        let (next_index, head_hash) = self.chain.head();
+        let mut anchor = Anchor {
+            next_index,
+            head_hash,
+            anchored_at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            algorithm: self.signer.algorithm().to_string(),
+            signature: String::new(),
+            timestamp_token: None,
+        };
+        anchor.signature = hex(&self.signer.sign(&anchor.payload()));
+        if let Some(authority) = &self.authority {
+            match authority.timestamp(&timestamp_request(&anchor.digest())) {
+                Ok(token) => anchor.timestamp_token = Some(hex(&token)),
+                Err(e) => tracing::warn!(error = %e, "audit anchor timestamping failed"),
+            }
+        }
+
+        if let Some(path) = &self.file {
+            let mut line = serde_json::to_vec(&anchor)?;
+            line.push(b'\n');
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?;
+            file.write_all(&line)?;
+            file.sync_data()?;
+        }
+        tracing::debug!(next_index, "audit chain anchored");
+        Ok(anchor)
+    }
+
+    /// Anchor every `interval` on a background thread, skipping intervals in
+    /// which the chain did not grow
+    ///
+    /// The chain is anchored one last time when the returned schedule is
+    /// dropped.
+    pub fn every(self: Arc<Self>, interval: Duration) -> AnchorSchedule {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = std::thread::spawn(move || {
+            let mut anchored = None;
+            loop {
+                let last = stopped.recv_timeout(interval) != Err(mpsc::RecvTimeoutError::Timeout);
+                let next_index = self.chain.head().0;
+                if anchored != Some(next_index) {
+                    match self.anchor() {
+                        Ok(_) => anchored = Some(next_index),
+                        Err(e) => tracing::error!(error = %e, "failed to write audit anchor"),
+                    }
+                }
+                if last {
+                    return;
+                }
+            }
+        });
+        AnchorSchedule {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+/// Periodic anchoring started by [`ChainAnchorer::every`]
+pub struct AnchorSchedule {
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for AnchorSchedule {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Anchors of an anchor file, oldest first
+pub fn read_anchors(path: impl AsRef<Path>) -> io::Result<Vec<Anchor>> {
+    let file = std::fs::File::open(path)?;
+    let mut anchors = Vec::new();
+    for line in io::BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        anchors.push(serde_json::from_str(&line)?);
+    }
+    Ok(anchors)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+impl AuditVerifier {
+    /// Check replayed chains against `anchors`
+    ///
+    /// Anchors must carry a valid signature from `signer`, and the chain must
+    /// reach each anchored position with the anchored hash. Anchors past the
+    /// end of the replayed events are reported in
+    /// [`ChainReport::unreached_anchor`](super::ChainReport::unreached_anchor),
+    /// and carried over to [`continuation`](super::ChainReport::continuation)s
+    /// for the following segments.
+    pub fn with_anchors(
+        mut self,
+        anchors: impl IntoIterator<Item = Anchor>,
+        signer: &dyn AnchorSigner,
+    ) -> Self {
+        self.anchors.extend(anchors.into_iter().map(|anchor| {
+            let valid = anchor.verify(signer);
+            if !valid {
+                tracing::warn!(
+                    next_index = anchor.next_index,
+                    "audit anchor failed verification"
+                );
+            }
+            (anchor, valid)
+        }));
+        self.anchors.sort_by_key(|(anchor, _)| anchor.next_index);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit_logging::{
+        AuditEvent, AuditEventType, AuditIntegrity, AuditResult, TamperReason,
+    };
+
+    fn log(chain: &HashChainIntegrity, n: usize) -> Vec<AuditEvent> {
+        (0..n)
+            .map(|_| {
+                let mut event = AuditEvent::builder()
+                    .event_type(AuditEventType::AdminAction)
+                    .result(AuditResult::Success)
+                    .build();
+                chain.add_integrity(&mut event);
+                event
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_hmac_sha256_rfc4231() {
+        let mac = HmacSha256Signer::new(b"Jefe".to_vec()).sign(b"what do ya want for nothing?");
+        assert_eq!(
+            hex(&mac),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_anchors_detect_truncation_and_rewrites() {
+        struct EchoAuthority;
+
+        impl TimestampAuthority for EchoAuthority {
+            fn timestamp(
+                &self,
+                request: &[u8],
+            ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+                Ok(request.to_vec())
+            }
+        }
+
+        let dir = std::env::temp_dir().join(format!("ash-rpc-anchor-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("audit.anchors");
+        let signer = HmacSha256Signer::new(b"anchor key".to_vec());
+        let chain = Arc::new(HashChainIntegrity::new());
+        let anchorer = ChainAnchorer::new(
+            Arc::clone(&chain),
+            Arc::new(HmacSha256Signer::new(b"anchor key".to_vec())),
+        )
+        .timestamp_authority(Arc::new(EchoAuthority))
+        .anchor_file(&path);
+
+        let mut events = log(&chain, 3);
+        anchorer.anchor().unwrap();
+        events.extend(log(&chain, 2));
+        anchorer.anchor().unwrap();
+        let anchors = read_anchors(&path).unwrap();
+        assert_eq!(anchors.len(), 2);
+        assert!(anchors.iter().all(|a| a.timestamp_token.is_some()));
+
+        let verifier = AuditVerifier::new().with_anchors(anchors.clone(), &signer);
+        let report = verifier.verify_events(events.clone());
+        assert!(report.is_intact());
+        assert_eq!(report.unreached_anchor, None);
+
+        // dropping the newest events goes unnoticed by the chain, not the anchors
+        let report = verifier.verify_events(events[..4].to_vec());
+        assert!(report.is_intact());
+        assert_eq!(report.unreached_anchor, Some(5));
+
+        // a rewritten chain does not match the anchored head
+        let forged = log(&HashChainIntegrity::new(), 5);
+        let report = verifier.verify_events(forged);
+        assert_eq!(
+            report.tampered.unwrap().reason,
+            TamperReason::AnchorMismatch { anchored_index: 3 }
+        );
+
+        // nor can anchors be forged without the key
+        let report = AuditVerifier::new()
+            .with_anchors(anchors, &HmacSha256Signer::new(b"guess".to_vec()))
+            .verify_events(events);
+        assert_eq!(
+            report.tampered.unwrap().reason,
+            TamperReason::InvalidAnchor { anchored_index: 3 }
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,232 @@
+//! Append-only JSON lines file backend with rotation.
+
+use super::AuditBackend;
+use crate::audit_logging::{AuditEvent, AuditFormat, NativeJsonFormat};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+struct ActiveFile {
+    file: File,
+    size: u64,
+    opened_at: SystemTime,
+}
+
+/// Appends audit events to a file as JSON lines, or in another
+/// [`format`](Self::format)
+///
+/// The file is only ever opened for appending. When it reaches
+/// [`max_size`](Self::max_size) or has been written for
+/// [`rotate_every`](Self::rotate_every), it is renamed to
+/// `<path>.<unix millis>` and a fresh file is started; rotated files are
+/// never deleted or modified, archiving them is left to the operator.
+pub struct FileAuditBackend {
+    path: PathBuf,
+    max_size: Option<u64>,
+    rotate_every: Option<Duration>,
+    fsync_each_event: bool,
+    format: Arc<dyn AuditFormat>,
+    active: Mutex<ActiveFile>,
+}
+
+impl FileAuditBackend {
+    /// Append to `path`, creating it if needed; no rotation, no fsync per event
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let active = Self::open_active(&path)?;
+        Ok(Self {
+            path,
+            max_size: None,
+            rotate_every: None,
+            fsync_each_event: false,
+            format: Arc::new(NativeJsonFormat),
+            active: Mutex::new(active),
+        })
+    }
+
+    /// Rotate before an event would grow the file beyond `bytes`
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Rotate once the file has been written to for `interval`
+    pub fn rotate_every(mut self, interval: Duration) -> Self {
+        self.rotate_every = Some(interval);
+        self
+    }
+
+    /// Sync every event to disk before `log_audit` returns
+    ///
+    /// Without it, events reach the OS on every write but are only forced to
+    /// disk on [`flush`](AuditBackend::flush) and rotation.
+    pub fn fsync_each_event(mut self, enabled: bool) -> Self {
+        self.fsync_each_event = enabled;
+        self
+    }
+
+    /// Write events as `format` lines instead of the native JSON
+    pub fn format(mut self, format: impl AuditFormat + 'static) -> Self {
+        self.format = Arc::new(format);
+        self
+    }
+
+    /// Path of the file currently written to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Rotate now, returning the path of the rotated file
+    ///
+    /// Returns `None` when the current file is empty and was left in place.
+    pub fn rotate(&self) -> io::Result<Option<PathBuf>> {
+        let mut active = self.active.lock().unwrap_or_else(|e| e.into_inner());
+        self.rotate_locked(&mut active)
+    }
+
+    fn open_active(path: &Path) -> io::Result<ActiveFile> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(ActiveFile {
+            file,
+            size,
+            opened_at: SystemTime::now(),
+        })
+    }
+
+    fn rotate_locked(&self, active: &mut ActiveFile) -> io::Result<Option<PathBuf>> {
+        if active.size == 0 {
+            active.opened_at = SystemTime::now();
+            return Ok(None);
+        }
+        active.file.sync_all()?;
+
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        let mut rotated = PathBuf::from(format!("{}.{millis}", self.path.display()));
+        let mut suffix = 1;
+        while rotated.exists() {
+            rotated = PathBuf::from(format!("{}.{millis}-{suffix}", self.path.display()));
+            suffix += 1;
+        }
+        std::fs::rename(&self.path, &rotated)?;
+        *active = Self::open_active(&self.path)?;
+        tracing::info!(rotated = %rotated.display(), "audit log rotated");
+        Ok(Some(rotated))
+    }
+
+    fn needs_rotation(&self, active: &ActiveFile, incoming: u64) -> bool {
+        let too_large = self
+            .max_size
+            .is_some_and(|max| active.size > 0 && active.size + incoming > max);
+        let too_old = self.rotate_every.is_some_and(|interval| {
+            active
+                .opened_at
+                .elapsed()
+                .is_ok_and(|elapsed| elapsed >= interval)
+        });
+        too_large || too_old
+    }
+
+    fn append(&self, line: &[u8]) -> io::Result<()> {
+        let mut active = self.active.lock().unwrap_or_else(|e| e.into_inner());
+        if self.needs_rotation(&active, line.len() as u64) {
+            self.rotate_locked(&mut active)?;
+        }
+        // one write per event so concurrent appenders never interleave lines
+        active.file.write_all(line)?;
+        active.size += line.len() as u64;
+        if self.fsync_each_event {
+            active.file.sync_data()?;
+        }
+        Ok(())
+    }
+}
+
+impl AuditBackend for FileAuditBackend {
+    fn log_audit(&self, event: &AuditEvent) {
+        let mut line = match self.format.format(event) {
+            Ok(line) => line.into_bytes(),
+            Err(e) => {
+                eprintln!("[AUDIT ERROR] Failed to serialize audit event: {}", e);
+                return;
+            }
+        };
+        line.push(b'\n');
+        if let Err(e) = self.append(&line) {
+            eprintln!(
+                "[AUDIT ERROR] Failed to write audit event to {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+
+    fn flush(&self) {
+        let active = self.active.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = active.file.sync_data();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit_logging::{AuditEventType, AuditResult};
+
+    fn event(method: &str) -> AuditEvent {
+        AuditEvent::builder()
+            .event_type(AuditEventType::MethodInvocation)
+            .method(method)
+            .result(AuditResult::Success)
+            .build()
+    }
+
+    #[test]
+    fn test_appends_jsonl_and_rotates_by_size() {
+        let dir = std::env::temp_dir().join(format!("ash-rpc-audit-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("audit.jsonl");
+        std::fs::write(&path, "{\"earlier\":true}\n").unwrap();
+
+        let backend = FileAuditBackend::open(&path)
+            .unwrap()
+            .max_size(600)
+            .fsync_each_event(true);
+        for i in 0..6 {
+            backend.log_audit(&event(&format!("method_{i}")));
+        }
+        backend.flush();
+
+        let mut files: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        files.sort();
+        assert!(files.len() > 1);
+
+        let mut methods = Vec::new();
+        let mut lines = 0;
+        for file in &files {
+            let content = std::fs::read_to_string(file).unwrap();
+            assert!(content.len() <= 600);
+            for line in content.lines() {
+                lines += 1;
+                let value: serde_json::Value = serde_json::from_str(line).unwrap();
+                if let Some(method) = value["method"].as_str() {
+                    methods.push(method.to_string());
+                }
+            }
+        }
+        // existing content is kept, nothing is lost across rotations
+        assert_eq!(lines, 7);
+        assert_eq!(methods.len(), 6);
+
+        assert!(backend.rotate().unwrap().is_some());
+        assert!(backend.rotate().unwrap().is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
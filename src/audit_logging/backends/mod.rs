@@ -1,7 +1,12 @@
 //! Pluggable audit logging backends for writing events to various destinations.
 
-use super::AuditEvent;
+mod file;
+
+pub use file::FileAuditBackend;
+
+use super::{AuditEvent, AuditFormat};
 use std::io::Write;
+use std::sync::Mutex;
 
 /// Audit log backend trait. Synchronous writes ensure events persist before execution continues.
 pub trait AuditBackend: Send + Sync {
@@ -56,6 +61,55 @@ impl AuditBackend for StderrAuditBackend {
     }
 }
 
+/// Writes audit events as lines in a chosen [`AuditFormat`], e.g. CEF on
+/// stdout for a log shipper
+pub struct WriterAuditBackend {
+    writer: Mutex<Box<dyn Write + Send>>,
+    format: Box<dyn AuditFormat>,
+}
+
+impl WriterAuditBackend {
+    pub fn new(writer: impl Write + Send + 'static, format: impl AuditFormat + 'static) -> Self {
+        Self {
+            writer: Mutex::new(Box::new(writer)),
+            format: Box::new(format),
+        }
+    }
+
+    pub fn stdout(format: impl AuditFormat + 'static) -> Self {
+        Self::new(std::io::stdout(), format)
+    }
+
+    pub fn stderr(format: impl AuditFormat + 'static) -> Self {
+        Self::new(std::io::stderr(), format)
+    }
+}
+
+impl AuditBackend for WriterAuditBackend {
+    fn log_audit(&self, event: &AuditEvent) {
+        let mut line = match self.format.format(event) {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("[AUDIT ERROR] Failed to format audit event: {}", e);
+                return;
+            }
+        };
+        line.push('\n');
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = writer.write_all(line.as_bytes()) {
+            eprintln!("[AUDIT ERROR] Failed to write audit event: {}", e);
+        }
+    }
+
+    fn flush(&self) {
+        let _ = self
+            .writer
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .flush();
+    }
+}
+
 /// Discards all audit events (testing only)
 #[derive(Debug, Clone, Copy, Default)]
 pub struct NoopAuditBackend;
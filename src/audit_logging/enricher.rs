@@ -0,0 +1,43 @@
+//! Hooks adding application-specific fields to every audit event.
+
+use super::AuditEvent;
+use crate::auth::ConnectionContext;
+use std::collections::HashMap;
+
+/// Adds fields to audit events before they are written
+///
+/// [`AuditProcessor`](super::AuditProcessor) runs its enrichers, in the order
+/// they were added, on every event it logs and before integrity metadata is
+/// added, so hash chains cover the enriched fields.
+pub trait AuditEnricher: Send + Sync {
+    fn enrich(&self, event: &mut AuditEvent, ctx: &ConnectionContext);
+}
+
+/// Adds the same metadata fields to every event, e.g. a tenant id or data
+/// classification
+#[derive(Debug, Clone, Default)]
+pub struct StaticMetadataEnricher {
+    fields: HashMap<String, serde_json::Value>,
+}
+
+impl StaticMetadataEnricher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn field(mut self, key: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        self.fields.insert(key.into(), value.into());
+        self
+    }
+}
+
+impl AuditEnricher for StaticMetadataEnricher {
+    fn enrich(&self, event: &mut AuditEvent, _ctx: &ConnectionContext) {
+        for (key, value) in &self.fields {
+            event
+                .metadata
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+    }
+}
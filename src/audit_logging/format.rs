@@ -0,0 +1,483 @@
+//! Output formats for audit events: native JSON, CEF, LEEF and ECS.
+//!
+//! Formats turn an event into a single line. The SIEM formats pick event
+//! fields by source name and write them under a configurable target name:
+//!
+//! | source | value |
+//! |---|---|
+//! | `timestamp` | milliseconds since the epoch (CEF), RFC 3339 (LEEF, ECS) |
+//! | `event_type`, `result`, `severity` | snake case names |
+//! | `severity_level` | 3, 6 or 9 for info, warning and critical |
+//! | `outcome` | `success` or `failure` |
+//! | `correlation_id`, `principal`, `method`, `error`, `params` | as recorded |
+//! | `remote_ip`, `remote_port` | parts of the remote address |
+//! | `metadata.<key>` | the metadata entry `<key>` |
+//!
+//! ```rust
+//! use ash_rpc::audit_logging::CefFormat;
+//!
+//! let cef = CefFormat::new("Acme", "billing-rpc", "1.4.0")
+//!     .field("principal", "duser")
+//!     .field("metadata.tenant", "cs2");
+//! ```
+
+use super::{AuditEvent, AuditResult, AuditSeverity};
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Turns audit events into lines written by a backend
+pub trait AuditFormat: Send + Sync {
+    /// The event as one line, without the trailing newline
+    fn format(&self, event: &AuditEvent) -> serde_json::Result<String>;
+}
+
+/// The crate's own JSON shape, the default of every backend
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NativeJsonFormat;
+
+impl AuditFormat for NativeJsonFormat {
+    fn format(&self, event: &AuditEvent) -> serde_json::Result<String> {
+        serde_json::to_string(event)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum TimeStyle {
+    EpochMillis,
+    Rfc3339,
+}
+
+/// Ordered source to target field mapping
+#[derive(Debug, Clone)]
+struct FieldMap(Vec<(String, String)>);
+
+impl FieldMap {
+    fn new(defaults: &[(&str, &str)]) -> Self {
+        Self(
+            defaults
+                .iter()
+                .map(|(source, target)| (source.to_string(), target.to_string()))
+                .collect(),
+        )
+    }
+
+    fn set(&mut self, source: String, target: String) {
+        match self.0.iter_mut().find(|(s, _)| *s == source) {
+            Some(entry) => entry.1 = target,
+            None => self.0.push((source, target)),
+        }
+    }
+
+    fn remove(&mut self, source: &str) {
+        self.0.retain(|(s, _)| s != source);
+    }
+
+    fn maps_metadata(&self, key: &str) -> bool {
+        self.0
+            .iter()
+            .any(|(source, _)| source.strip_prefix("metadata.") == Some(key))
+    }
+
+    /// Mapped `(source, target, value)` triples present on `event`
+    fn values<'a>(
+        &'a self,
+        event: &'a AuditEvent,
+        time: TimeStyle,
+    ) -> impl Iterator<Item = (&'a str, &'a str, serde_json::Value)> + 'a {
+        self.0.iter().filter_map(move |(source, target)| {
+            source_value(event, source, time).map(|value| (source.as_str(), target.as_str(), value))
+        })
+    }
+}
+
+fn source_value(event: &AuditEvent, source: &str, time: TimeStyle) -> Option<serde_json::Value> {
+    use serde_json::json;
+    let value = match source {
+        "timestamp" => match time {
+            TimeStyle::EpochMillis => json!(epoch_millis(event.timestamp)),
+            TimeStyle::Rfc3339 => json!(rfc3339_millis(event.timestamp)),
+        },
+        "event_type" => serde_json::to_value(event.event_type).ok()?,
+        "result" => serde_json::to_value(event.result).ok()?,
+        "severity" => serde_json::to_value(event.severity).ok()?,
+        "severity_level" => json!(severity_level(event.severity)),
+        "outcome" => json!(match event.result {
+            AuditResult::Success => "success",
+            _ => "failure",
+        }),
+        "correlation_id" => json!(event.correlation_id.as_ref()?),
+        "principal" => json!(event.principal.as_ref()?),
+        "method" => json!(event.method.as_ref()?),
+        "error" => json!(event.error.as_ref()?),
+        "params" => event.params.clone()?,
+        "remote_ip" => json!(event.remote_addr?.ip().to_string()),
+        "remote_port" => json!(event.remote_addr?.port()),
+        _ => event
+            .metadata
+            .get(source.strip_prefix("metadata.")?)?
+            .clone(),
+    };
+    (!value.is_null()).then_some(value)
+}
+
+fn severity_level(severity: AuditSeverity) -> u8 {
+    match severity {
+        AuditSeverity::Info => 3,
+        AuditSeverity::Warning => 6,
+        AuditSeverity::Critical => 9,
+    }
+}
+
+fn text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn epoch_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// `time` as `YYYY-MM-DDTHH:MM:SS.mmmZ`
+fn rfc3339_millis(time: SystemTime) -> String {
+    let millis = epoch_millis(time);
+    let secs = millis / 1000;
+    let (hour, minute, second) = ((secs / 3600) % 24, (secs / 60) % 60, secs % 60);
+
+    // days to civil date, after Howard Hinnant's `civil_from_days`
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}.{:03}Z",
+        millis % 1000
+    )
+}
+
+/// ArcSight Common Event Format
+///
+/// The header carries the event type as signature id, the method (or event
+/// type) as name and the severity level (3, 6 or 9) as severity. Fields mapped to
+/// custom `csN`/`cnN` keys get a matching `csNLabel` naming their source.
+/// Metadata is only written when mapped.
+#[derive(Debug, Clone)]
+pub struct CefFormat {
+    vendor: String,
+    product: String,
+    version: String,
+    fields: FieldMap,
+}
+
+impl CefFormat {
+    pub fn new(
+        vendor: impl Into<String>,
+        product: impl Into<String>,
+        version: impl Into<String>,
+    ) -> Self {
+        Self {
+            vendor: vendor.into(),
+            product: product.into(),
+            version: version.into(),
+            fields: FieldMap::new(&[
+                ("timestamp", "rt"),
+                ("remote_ip", "src"),
+                ("remote_port", "spt"),
+                ("principal", "suser"),
+                ("result", "outcome"),
+                ("correlation_id", "externalId"),
+                ("method", "cs1"),
+                ("error", "msg"),
+            ]),
+        }
+    }
+
+    /// Write `source` as extension key `target`, replacing its default key
+    pub fn field(mut self, source: impl Into<String>, target: impl Into<String>) -> Self {
+        self.fields.set(source.into(), target.into());
+        self
+    }
+
+    /// Leave `source` out of the output
+    pub fn without(mut self, source: &str) -> Self {
+        self.fields.remove(source);
+        self
+    }
+
+    fn escape_header(value: &str) -> String {
+        value.replace('\\', "\\\\").replace('|', "\\|")
+    }
+
+    fn escape_value(value: &str) -> String {
+        value
+            .replace('\\', "\\\\")
+            .replace('=', "\\=")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r")
+    }
+}
+
+impl AuditFormat for CefFormat {
+    fn format(&self, event: &AuditEvent) -> serde_json::Result<String> {
+        let event_type = text(&serde_json::to_value(event.event_type)?);
+        let mut line = format!(
+            "CEF:0|{}|{}|{}|{}|{}|{}|",
+            Self::escape_header(&self.vendor),
+            Self::escape_header(&self.product),
+            Self::escape_header(&self.version),
+            Self::escape_header(&event_type),
+            Self::escape_header(event.method.as_deref().unwrap_or(&event_type)),
+            severity_level(event.severity),
+        );
+        let mut extensions = Vec::new();
+        for (source, target, value) in self.fields.values(event, TimeStyle::EpochMillis) {
+            let custom = target
+                .strip_prefix("cs")
+                .or_else(|| target.strip_prefix("cn"))
+                .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+            if custom {
+                let label = source.strip_prefix("metadata.").unwrap_or(source);
+                extensions.push(format!("{target}Label={}", Self::escape_value(label)));
+            }
+            extensions.push(format!("{target}={}", Self::escape_value(&text(&value))));
+        }
+        line.push_str(&extensions.join(" "));
+        Ok(line)
+    }
+}
+
+/// IBM QRadar Log Event Extended Format, version 1.0
+///
+/// The header carries the event type as event id; attributes are tab
+/// separated. Metadata is only written when mapped.
+#[derive(Debug, Clone)]
+pub struct LeefFormat {
+    vendor: String,
+    product: String,
+    version: String,
+    fields: FieldMap,
+}
+
+impl LeefFormat {
+    pub fn new(
+        vendor: impl Into<String>,
+        product: impl Into<String>,
+        version: impl Into<String>,
+    ) -> Self {
+        Self {
+            vendor: vendor.into(),
+            product: product.into(),
+            version: version.into(),
+            fields: FieldMap::new(&[
+                ("timestamp", "devTime"),
+                ("remote_ip", "src"),
+                ("remote_port", "srcPort"),
+                ("principal", "usrName"),
+                ("severity_level", "sev"),
+                ("event_type", "cat"),
+                ("result", "result"),
+                ("method", "method"),
+                ("correlation_id", "correlationId"),
+                ("error", "reason"),
+            ]),
+        }
+    }
+
+    /// Write `source` as attribute `target`, replacing its default key
+    pub fn field(mut self, source: impl Into<String>, target: impl Into<String>) -> Self {
+        self.fields.set(source.into(), target.into());
+        self
+    }
+
+    /// Leave `source` out of the output
+    pub fn without(mut self, source: &str) -> Self {
+        self.fields.remove(source);
+        self
+    }
+
+    fn escape(value: &str) -> String {
+        value.replace(['\t', '\n', '\r'], " ")
+    }
+}
+
+impl AuditFormat for LeefFormat {
+    fn format(&self, event: &AuditEvent) -> serde_json::Result<String> {
+        let header = |value: &str| Self::escape(value).replace('|', "\\|");
+        let event_type = text(&serde_json::to_value(event.event_type)?);
+        let mut attributes = Vec::new();
+        for (source, target, value) in self.fields.values(event, TimeStyle::Rfc3339) {
+            attributes.push(format!("{target}={}", Self::escape(&text(&value))));
+            if source == "timestamp" {
+                attributes.push("devTimeFormat=yyyy-MM-dd'T'HH:mm:ss.SSSX".to_string());
+            }
+        }
+        Ok(format!(
+            "LEEF:1.0|{}|{}|{}|{}|{}",
+            header(&self.vendor),
+            header(&self.product),
+            header(&self.version),
+            header(&event_type),
+            attributes.join("\t"),
+        ))
+    }
+}
+
+/// Elastic Common Schema JSON
+///
+/// Dotted targets become nested objects. Metadata that is not mapped goes to
+/// `labels`, as strings.
+#[derive(Debug, Clone)]
+pub struct EcsFormat {
+    fields: FieldMap,
+}
+
+/// ECS version written as `ecs.version`
+pub const ECS_VERSION: &str = "8.11.0";
+
+impl EcsFormat {
+    pub fn new() -> Self {
+        Self {
+            fields: FieldMap::new(&[
+                ("timestamp", "@timestamp"),
+                ("event_type", "event.code"),
+                ("method", "event.action"),
+                ("outcome", "event.outcome"),
+                ("severity_level", "event.severity"),
+                ("severity", "log.level"),
+                ("correlation_id", "trace.id"),
+                ("remote_ip", "source.ip"),
+                ("remote_port", "source.port"),
+                ("principal", "user.name"),
+                ("error", "error.message"),
+            ]),
+        }
+    }
+
+    /// Write `source` under the dotted path `target`, replacing its default
+    pub fn field(mut self, source: impl Into<String>, target: impl Into<String>) -> Self {
+        self.fields.set(source.into(), target.into());
+        self
+    }
+
+    /// Leave `source` out of the output
+    pub fn without(mut self, source: &str) -> Self {
+        self.fields.remove(source);
+        self
+    }
+
+    fn insert(
+        root: &mut serde_json::Map<String, serde_json::Value>,
+        path: &str,
+        value: serde_json::Value,
+    ) {
+        let mut node = root;
+        let mut parts = path.split('.').peekable();
+        while let Some(part) = parts.next() {
+            if parts.peek().is_none() {
+                node.insert(part.to_string(), value);
+                return;
+            }
+            let child = node
+                .entry(part.to_string())
+                .or_insert_with(|| serde_json::Value::Object(Default::default()));
+            if !child.is_object() {
+                *child = serde_json::Value::Object(Default::default());
+            }
+            node = child.as_object_mut().expect("replaced by an object above");
+        }
+    }
+}
+
+impl Default for EcsFormat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AuditFormat for EcsFormat {
+    fn format(&self, event: &AuditEvent) -> serde_json::Result<String> {
+        let mut root = serde_json::Map::new();
+        Self::insert(&mut root, "ecs.version", ECS_VERSION.into());
+        Self::insert(&mut root, "event.kind", "event".into());
+        for (_, target, value) in self.fields.values(event, TimeStyle::Rfc3339) {
+            Self::insert(&mut root, target, value);
+        }
+        let labels: BTreeMap<&String, String> = event
+            .metadata
+            .iter()
+            .filter(|(key, _)| !self.fields.maps_metadata(key))
+            .map(|(key, value)| (key, text(value)))
+            .collect();
+        if !labels.is_empty() {
+            root.insert("labels".to_string(), serde_json::to_value(labels)?);
+        }
+        serde_json::to_string(&root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit_logging::AuditEventType;
+    use std::time::Duration;
+
+    fn event() -> AuditEvent {
+        let mut event = AuditEvent::builder()
+            .event_type(AuditEventType::AuthorizationCheck)
+            .principal("alice")
+            .method("admin.reload")
+            .result(AuditResult::Denied)
+            .remote_addr("10.0.0.7:4100".parse().unwrap())
+            .metadata("tenant", "acme")
+            .metadata("rule", "a=b|c")
+            .build();
+        event.timestamp = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        event
+    }
+
+    #[test]
+    fn test_siem_formats() {
+        let cef = CefFormat::new("Acme", "rpc|gw", "1.0")
+            .field("metadata.rule", "cs2")
+            .without("result")
+            .format(&event())
+            .unwrap();
+        assert_eq!(
+            cef,
+            "CEF:0|Acme|rpc\\|gw|1.0|authorization_check|admin.reload|9|rt=1700000000123 \
+             src=10.0.0.7 spt=4100 suser=alice cs1Label=method cs1=admin.reload \
+             cs2Label=rule cs2=a\\=b|c"
+        );
+
+        let leef = LeefFormat::new("Acme", "rpc", "1.0")
+            .format(&event())
+            .unwrap();
+        assert!(leef.starts_with(
+            "LEEF:1.0|Acme|rpc|1.0|authorization_check|devTime=2023-11-14T22:13:20.123Z\t"
+        ));
+        assert!(leef.contains("\tusrName=alice\tsev=9\t"));
+
+        let ecs: serde_json::Value = serde_json::from_str(
+            &EcsFormat::new()
+                .field("metadata.tenant", "organization.id")
+                .format(&event())
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(ecs["@timestamp"], "2023-11-14T22:13:20.123Z");
+        assert_eq!(ecs["event"]["outcome"], "failure");
+        assert_eq!(ecs["source"]["port"], 4100);
+        assert_eq!(ecs["organization"]["id"], "acme");
+        assert_eq!(ecs["labels"], serde_json::json!({"rule": "a=b|c"}));
+    }
+}
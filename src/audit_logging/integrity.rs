@@ -1,6 +1,11 @@
-//! Integrity verification mechanisms using sequence numbers, checksums, or combined checks.
+//! Integrity verification mechanisms using sequence numbers, checksums, hash
+//! chains, or combined checks.
 
 use super::AuditEvent;
+use sha2::{Digest, Sha256};
+use std::io::{self, BufRead};
+use std::path::Path;
+use std::sync::Mutex;
 use std::sync::atomic::{AtomicU64, Ordering};
 
 /// Audit integrity verification trait
@@ -183,6 +188,387 @@ impl AuditIntegrity for CombinedIntegrity {
     }
 }
 
+/// `prev_hash` of the first event of a chain
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Links every event to its predecessor through SHA-256 hashes
+///
+/// Each event records its position in the chain (`chain_index`), the hash of
+/// the event before it (`prev_hash`) and its own hash (`hash`), taken over the
+/// whole event in canonical JSON form. Editing, removing or reordering a
+/// logged event breaks the chain from that point on, which [`AuditVerifier`]
+/// detects; dropping the newest events does not, so anchor the latest hash
+/// elsewhere with a [`ChainAnchorer`](super::ChainAnchorer) if that matters.
+///
+/// When combined with other mechanisms, add this one last so its hash covers
+/// their metadata as well.
+pub struct HashChainIntegrity {
+    head: Mutex<ChainHead>,
+}
+
+struct ChainHead {
+    next_index: u64,
+    last_hash: String,
+}
+
+impl HashChainIntegrity {
+    /// Start a new chain at index 0
+    pub fn new() -> Self {
+        Self::resume(0, GENESIS_HASH)
+    }
+
+    /// Continue a chain, e.g. after a restart, from its last hash
+    pub fn resume(next_index: u64, last_hash: impl Into<String>) -> Self {
+        Self {
+            head: Mutex::new(ChainHead {
+                next_index,
+                last_hash: last_hash.into(),
+            }),
+        }
+    }
+
+    /// Index the next event will get
+    pub fn next_index(&self) -> u64 {
+        self.head
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .next_index
+    }
+
+    /// Hash of the most recent event, [`GENESIS_HASH`] before the first one
+    pub fn last_hash(&self) -> String {
+        self.head
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .last_hash
+            .clone()
+    }
+
+    /// Next index and last hash, read together
+    pub(crate) fn head(&self) -> (u64, String) {
+        let head = self.head.lock().unwrap_or_else(|e| e.into_inner());
+        (head.next_index, head.last_hash.clone())
+    }
+
+    /// SHA-256 of `event` without its `hash` metadata, hex encoded
+    pub fn event_hash(event: &AuditEvent) -> String {
+        let mut event = event.clone();
+        event.metadata.remove("hash");
+        let mut canonical = String::new();
+        match serde_json::to_value(&event) {
+            Ok(value) => write_canonical(&value, &mut canonical),
+            Err(e) => tracing::error!(error = %e, "failed to serialize audit event for hashing"),
+        }
+        Sha256::digest(canonical.as_bytes())
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    fn hash_matches(event: &AuditEvent) -> bool {
+        match event.metadata.get("hash") {
+            Some(serde_json::Value::String(stored)) => *stored == Self::event_hash(event),
+            _ => false,
+        }
+    }
+}
+
+impl Default for HashChainIntegrity {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AuditIntegrity for HashChainIntegrity {
+    fn add_integrity(&self, event: &mut AuditEvent) {
+        let mut head = self.head.lock().unwrap_or_else(|e| e.into_inner());
+        event.add_metadata("chain_index", head.next_index);
+        event.add_metadata("prev_hash", head.last_hash.clone());
+        let hash = Self::event_hash(event);
+        event.add_metadata("hash", hash.clone());
+        head.next_index += 1;
+        head.last_hash = hash;
+    }
+
+    fn verify(&self, event: &AuditEvent) -> bool {
+        Self::hash_matches(event)
+    }
+}
+
+/// Serialize `value` with object keys sorted, so equal events hash equally
+/// whatever order their maps iterate in
+fn write_canonical(value: &serde_json::Value, out: &mut String) {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(value, out);
+            }
+            out.push('}');
+        }
+        serde_json::Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+/// Why [`AuditVerifier`] rejected an entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TamperReason {
+    /// The entry is not an audit event
+    Unreadable(String),
+    /// The entry carries no hash chain metadata
+    MissingChain,
+    /// The entry's contents do not match its hash
+    HashMismatch,
+    /// The entry does not follow the one before it
+    BrokenLink,
+    /// Entries before this one are missing
+    Gap { expected_index: u64 },
+    /// Another entry already took this position
+    Duplicate,
+    /// The anchor for this position is not validly signed
+    InvalidAnchor { anchored_index: u64 },
+    /// The chain reaches this anchored position with a different hash
+    AnchorMismatch { anchored_index: u64 },
+}
+
+/// First entry at which a chain stops verifying
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TamperedEntry {
+    /// 1-based line of the entry in the file, or position in the events; 0
+    /// when the chain is rejected before its first entry
+    pub line: usize,
+    pub chain_index: Option<u64>,
+    pub reason: TamperReason,
+}
+
+/// Outcome of replaying a hash chain
+#[derive(Debug, Clone)]
+pub struct ChainReport {
+    /// Entries verified before the first tampered one
+    pub verified: usize,
+    /// Index the entry after the last verified one should have
+    pub next_index: u64,
+    /// Hash of the last verified entry
+    pub last_hash: String,
+    pub tampered: Option<TamperedEntry>,
+    /// First anchored position past the verified entries; for the last
+    /// segment of a log, entries were removed from its end
+    pub unreached_anchor: Option<u64>,
+    anchors: Vec<(super::Anchor, bool)>,
+}
+
+impl ChainReport {
+    pub fn is_intact(&self) -> bool {
+        self.tampered.is_none()
+    }
+
+    /// Verifier for the segment written after this one, e.g. the next
+    /// rotated file
+    pub fn continuation(&self) -> AuditVerifier {
+        let mut verifier = AuditVerifier::resume(self.next_index, self.last_hash.clone());
+        verifier.anchors = self.anchors.clone();
+        verifier
+    }
+}
+
+/// Replays audit logs written with [`HashChainIntegrity`]
+///
+/// Entries are checked in chain order rather than file order, since
+/// concurrent writers may append them slightly out of order.
+///
+/// ```rust
+/// use ash_rpc::audit_logging::*;
+///
+/// let chain = HashChainIntegrity::new();
+/// let events: Vec<_> = (0..3)
+///     .map(|_| {
+///         let mut event = AuditEvent::builder()
+///             .event_type(AuditEventType::AdminAction)
+///             .result(AuditResult::Success)
+///             .build();
+///         chain.add_integrity(&mut event);
+///         event
+///     })
+///     .collect();
+///
+/// let report = AuditVerifier::new().verify_events(events);
+/// assert!(report.is_intact());
+/// assert_eq!(report.last_hash, chain.last_hash());
+/// ```
+#[derive(Debug, Clone)]
+pub struct AuditVerifier {
+    next_index: u64,
+    prev_hash: String,
+    /// Anchors sorted by position, with the outcome of their signature check
+    pub(super) anchors: Vec<(super::Anchor, bool)>,
+}
+
+impl AuditVerifier {
+    /// Expect the chain to start at index 0
+    pub fn new() -> Self {
+        Self::resume(0, GENESIS_HASH)
+    }
+
+    /// Expect the chain to continue from `prev_hash` at `next_index`
+    pub fn resume(next_index: u64, prev_hash: impl Into<String>) -> Self {
+        Self {
+            next_index,
+            prev_hash: prev_hash.into(),
+            anchors: Vec::new(),
+        }
+    }
+
+    /// Replay a JSON lines file such as the one written by
+    /// [`FileAuditBackend`](super::FileAuditBackend)
+    pub fn verify_file(&self, path: impl AsRef<Path>) -> io::Result<ChainReport> {
+        let file = std::fs::File::open(path)?;
+        let mut entries = Vec::new();
+        for (i, line) in io::BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            entries.push((
+                i + 1,
+                serde_json::from_str(&line).map_err(|e| e.to_string()),
+            ));
+        }
+        Ok(self.replay(entries))
+    }
+
+    /// Replay events in the order they were logged
+    pub fn verify_events(&self, events: impl IntoIterator<Item = AuditEvent>) -> ChainReport {
+        self.replay(
+            events
+                .into_iter()
+                .enumerate()
+                .map(|(i, event)| (i + 1, Ok(event)))
+                .collect(),
+        )
+    }
+
+    fn replay(&self, entries: Vec<(usize, Result<AuditEvent, String>)>) -> ChainReport {
+        let mut report = ChainReport {
+            verified: 0,
+            next_index: self.next_index,
+            last_hash: self.prev_hash.clone(),
+            tampered: None,
+            unreached_anchor: None,
+            anchors: self.anchors.clone(),
+        };
+        let tampered = |line, chain_index, reason| {
+            Some(TamperedEntry {
+                line,
+                chain_index,
+                reason,
+            })
+        };
+
+        // anchors of earlier segments were checked with those
+        let mut anchors = self
+            .anchors
+            .iter()
+            .filter(|(anchor, _)| anchor.next_index >= self.next_index)
+            .peekable();
+        if let Some((anchor, _)) = anchors.clone().find(|(_, valid)| !valid) {
+            let anchored_index = anchor.next_index;
+            report.tampered = tampered(0, None, TamperReason::InvalidAnchor { anchored_index });
+            return report;
+        }
+        let mut anchor_mismatch = |report: &ChainReport| {
+            while let Some((anchor, _)) =
+                anchors.next_if(|(a, _)| a.next_index <= report.next_index)
+            {
+                if anchor.head_hash != report.last_hash {
+                    return Some(TamperReason::AnchorMismatch {
+                        anchored_index: anchor.next_index,
+                    });
+                }
+            }
+            None
+        };
+        if let Some(reason) = anchor_mismatch(&report) {
+            report.tampered = tampered(0, None, reason);
+            return report;
+        }
+
+        let mut chained = Vec::with_capacity(entries.len());
+        for (line, entry) in entries {
+            let event = match entry {
+                Ok(event) => event,
+                Err(e) => {
+                    report.tampered = tampered(line, None, TamperReason::Unreadable(e));
+                    return report;
+                }
+            };
+            match event.metadata.get("chain_index").and_then(|i| i.as_u64()) {
+                Some(index) => chained.push((index, line, event)),
+                None => {
+                    report.tampered = tampered(line, None, TamperReason::MissingChain);
+                    return report;
+                }
+            }
+        }
+        chained.sort_by_key(|(index, line, _)| (*index, *line));
+
+        for (index, line, event) in chained {
+            let reason = if index > report.next_index {
+                Some(TamperReason::Gap {
+                    expected_index: report.next_index,
+                })
+            } else if index < report.next_index {
+                Some(TamperReason::Duplicate)
+            } else if event.metadata.get("prev_hash").and_then(|h| h.as_str())
+                != Some(report.last_hash.as_str())
+            {
+                Some(TamperReason::BrokenLink)
+            } else if !HashChainIntegrity::hash_matches(&event) {
+                Some(TamperReason::HashMismatch)
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                tracing::warn!(line, chain_index = index, ?reason, "audit chain broken");
+                report.tampered = tampered(line, Some(index), reason);
+                return report;
+            }
+            report.verified += 1;
+            report.next_index += 1;
+            report.last_hash = HashChainIntegrity::event_hash(&event);
+            if let Some(reason) = anchor_mismatch(&report) {
+                tracing::warn!(line, chain_index = index, ?reason, "audit chain broken");
+                report.tampered = tampered(line, Some(index), reason);
+                return report;
+            }
+        }
+        report.unreached_anchor = anchors.next().map(|(anchor, _)| anchor.next_index);
+        report
+    }
+}
+
+impl Default for AuditVerifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Wrapper to make Arc<dyn AuditIntegrity> work with Box<dyn AuditIntegrity>
 #[derive(Clone)]
 struct ArcIntegrityWrapper(std::sync::Arc<dyn AuditIntegrity>);
@@ -278,4 +664,52 @@ mod tests {
         assert!(event.metadata.contains_key("checksum"));
         assert!(combined.verify(&event));
     }
+
+    #[test]
+    fn test_hash_chain_verified_from_file() {
+        use crate::audit_logging::{AuditBackend, FileAuditBackend};
+
+        let path =
+            std::env::temp_dir().join(format!("ash-rpc-chain-{}.jsonl", uuid::Uuid::new_v4()));
+        let backend = FileAuditBackend::open(&path).unwrap();
+        let chain = HashChainIntegrity::new();
+        for method in ["login", "transfer", "logout"] {
+            let mut event = AuditEvent::builder()
+                .event_type(AuditEventType::MethodInvocation)
+                .method(method)
+                .result(AuditResult::Success)
+                .build();
+            event.add_metadata("amount", 10);
+            chain.add_integrity(&mut event);
+            assert!(chain.verify(&event));
+            backend.log_audit(&event);
+        }
+
+        let report = AuditVerifier::new().verify_file(&path).unwrap();
+        assert!(report.is_intact());
+        assert_eq!(report.verified, 3);
+        assert_eq!(report.last_hash, chain.last_hash());
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+
+        let edited = content.replacen("transfer", "refund", 1);
+        std::fs::write(&path, edited).unwrap();
+        let report = AuditVerifier::new().verify_file(&path).unwrap();
+        assert_eq!(report.verified, 1);
+        let tampered = report.tampered.unwrap();
+        assert_eq!(
+            (tampered.line, tampered.reason),
+            (2, TamperReason::HashMismatch)
+        );
+
+        std::fs::write(&path, format!("{}\n{}\n", lines[0], lines[2])).unwrap();
+        let tampered = AuditVerifier::new()
+            .verify_file(&path)
+            .unwrap()
+            .tampered
+            .unwrap();
+        assert_eq!(tampered.reason, TamperReason::Gap { expected_index: 1 });
+        std::fs::remove_file(&path).unwrap();
+    }
 }
@@ -5,11 +5,19 @@
 //!
 //! Features: append-only logs, integrity verification, pluggable backends, compliance-ready.
 
+mod analyzer;
+mod anchor;
 mod backends;
+mod enricher;
+mod format;
 mod integrity;
 mod processor;
 
+pub use analyzer::*;
+pub use anchor::*;
 pub use backends::*;
+pub use enricher::*;
+pub use format::*;
 pub use integrity::*;
 pub use processor::*;
 
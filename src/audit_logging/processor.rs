@@ -1,7 +1,12 @@
 //! MessageProcessor wrapper that automatically logs security audit events.
 
-use super::{AuditBackend, AuditEvent, AuditEventType, AuditIntegrity, AuditResult, AuditSeverity};
-use crate::{Message, MessageProcessor, ProcessorCapabilities, Response, auth::ConnectionContext};
+use super::{
+    AuditBackend, AuditEnricher, AuditEvent, AuditEventBuilder, AuditEventType, AuditIntegrity,
+    AuditResult, AuditSeverity,
+};
+use crate::{
+    Message, MessageProcessor, ProcessorCapabilities, Request, Response, auth::ConnectionContext,
+};
 use async_trait::async_trait;
 use std::sync::Arc;
 
@@ -10,6 +15,7 @@ pub struct AuditProcessor {
     inner: Arc<dyn MessageProcessor + Send + Sync>,
     backend: Arc<dyn AuditBackend>,
     integrity: Arc<dyn AuditIntegrity>,
+    enrichers: Vec<Arc<dyn AuditEnricher>>,
     connection_context: Option<Arc<ConnectionContext>>,
 }
 
@@ -20,12 +26,17 @@ impl AuditProcessor {
             processor,
             backend: Arc::new(super::StdoutAuditBackend),
             integrity: Arc::new(super::NoIntegrity),
+            enrichers: Vec::new(),
             connection_context: None,
         }
     }
 
-    /// Log an audit event with integrity metadata
-    fn log_event(&self, mut event: AuditEvent) {
+    /// Log an audit event with enrichments and integrity metadata
+    fn log_event(&self, mut event: AuditEvent, ctx: &ConnectionContext) {
+        for enricher in &self.enrichers {
+            enricher.enrich(&mut event, ctx);
+        }
+
         // Add integrity metadata
         self.integrity.add_integrity(&mut event);
 
@@ -34,7 +45,11 @@ impl AuditProcessor {
     }
 
     /// Create audit event from request message
-    fn create_request_event(&self, message: &Message) -> Option<AuditEvent> {
+    fn create_request_event(
+        &self,
+        message: &Message,
+        ctx: &ConnectionContext,
+    ) -> Option<AuditEvent> {
         match message {
             Message::Request(req) => {
                 let mut event = AuditEvent::builder()
@@ -48,18 +63,11 @@ impl AuditProcessor {
                     event = event.correlation_id(id.to_string());
                 }
 
-                // Add connection context if available
-                if let Some(ref ctx) = self.connection_context {
-                    if let Some(addr) = ctx.remote_addr {
-                        event = event.remote_addr(addr);
-                    }
-
-                    // Try to extract principal from context
-                    if let Some(user_id) = ctx.get::<String>("user_id") {
-                        event = event.principal(user_id);
-                    } else if let Some(api_key) = ctx.get::<String>("api_key") {
-                        event = event.principal(format!("api_key:{}", api_key));
-                    }
+                event = with_peer(event, ctx);
+                if ctx.principal_id().is_some() {
+                    event = with_principal(event, ctx);
+                } else if let Some(api_key) = ctx.get::<String>("api_key") {
+                    event = event.principal(format!("api_key:{}", api_key));
                 }
 
                 // Sanitize and add parameters (avoid logging sensitive data)
@@ -78,14 +86,9 @@ impl AuditProcessor {
                     .severity(AuditSeverity::Info)
                     .metadata("notification", true);
 
-                // Add connection context if available
-                if let Some(ref ctx) = self.connection_context
-                    && let Some(addr) = ctx.remote_addr
-                {
-                    event = event.remote_addr(addr);
-                }
+                event = with_peer(event, ctx);
 
-                Some(event.build())
+                Some(with_principal(event, ctx).build())
             }
             Message::Response(_) => {
                 // We don't audit raw response messages
@@ -95,7 +98,12 @@ impl AuditProcessor {
     }
 
     /// Create audit event from response
-    fn create_response_event(&self, message: &Message, response: Option<&Response>) -> AuditEvent {
+    fn create_response_event(
+        &self,
+        message: &Message,
+        response: Option<&Response>,
+        ctx: &ConnectionContext,
+    ) -> AuditEvent {
         let method = match message {
             Message::Request(req) => Some(req.method.as_str()),
             Message::Notification(notif) => Some(notif.method.as_str()),
@@ -115,16 +123,8 @@ impl AuditProcessor {
             event_builder = event_builder.method(m);
         }
 
-        // Add connection context
-        if let Some(ref ctx) = self.connection_context {
-            if let Some(addr) = ctx.remote_addr {
-                event_builder = event_builder.remote_addr(addr);
-            }
-
-            if let Some(user_id) = ctx.get::<String>("user_id") {
-                event_builder = event_builder.principal(user_id);
-            }
-        }
+        event_builder = with_peer(event_builder, ctx);
+        event_builder = with_principal(event_builder, ctx);
 
         // Determine result based on response
         if let Some(resp) = response {
@@ -153,21 +153,90 @@ impl AuditProcessor {
 #[async_trait]
 impl MessageProcessor for AuditProcessor {
     async fn process_message(&self, message: Message) -> Option<Response> {
+        let ctx = self.connection_context.clone().unwrap_or_default();
+        self.process_message_with_context(message, &ctx).await
+    }
+
+    async fn process_message_with_context(
+        &self,
+        message: Message,
+        ctx: &ConnectionContext,
+    ) -> Option<Response> {
         // Log incoming request
-        if let Some(request_event) = self.create_request_event(&message) {
-            self.log_event(request_event);
+        if let Some(request_event) = self.create_request_event(&message, ctx) {
+            self.log_event(request_event, ctx);
         }
 
         // Process the message
-        let response = self.inner.process_message(message.clone()).await;
+        let response = self
+            .inner
+            .process_message_with_context(message.clone(), ctx)
+            .await;
 
         // Log response
-        let response_event = self.create_response_event(&message, response.as_ref());
-        self.log_event(response_event);
+        let response_event = self.create_response_event(&message, response.as_ref(), ctx);
+        self.log_event(response_event, ctx);
 
         response
     }
 
+    async fn process_batch(&self, messages: Vec<Message>) -> Vec<Response> {
+        let ctx = self.connection_context.clone().unwrap_or_default();
+        self.process_batch_with_context(messages, &ctx).await
+    }
+
+    /// The batch is forwarded whole, so the inner processor's batch policy
+    /// and limits apply; each entry is still audited with its outcome
+    async fn process_batch_with_context(
+        &self,
+        messages: Vec<Message>,
+        ctx: &ConnectionContext,
+    ) -> Vec<Response> {
+        for message in &messages {
+            if let Some(request_event) = self.create_request_event(message, ctx) {
+                self.log_event(request_event, ctx);
+            }
+        }
+
+        let responses = self
+            .inner
+            .process_batch_with_context(messages.clone(), ctx)
+            .await;
+
+        let answers = crate::batch_policy::answers(&messages, &responses);
+        for (message, response) in messages.iter().zip(answers) {
+            let response_event = self.create_response_event(message, response, ctx);
+            self.log_event(response_event, ctx);
+        }
+        responses
+    }
+
+    async fn admit(&self, request: &Request) -> Option<Response> {
+        let ctx = self.connection_context.clone().unwrap_or_default();
+        self.admit_with_context(request, &ctx).await
+    }
+
+    /// Requests a transport answers itself are audited like dispatched ones,
+    /// with the refusal as their outcome when they are not admitted
+    async fn admit_with_context(
+        &self,
+        request: &Request,
+        ctx: &ConnectionContext,
+    ) -> Option<Response> {
+        let message = Message::Request(request.clone());
+        if let Some(request_event) = self.create_request_event(&message, ctx) {
+            self.log_event(request_event, ctx);
+        }
+        let refusal = self.inner.admit_with_context(request, ctx).await;
+        let response_event = self.create_response_event(&message, refusal.as_ref(), ctx);
+        self.log_event(response_event, ctx);
+        refusal
+    }
+
+    fn supports_batching(&self) -> bool {
+        self.inner.supports_batching()
+    }
+
     fn get_capabilities(&self) -> ProcessorCapabilities {
         self.inner.get_capabilities()
     }
@@ -178,6 +247,7 @@ pub struct AuditProcessorBuilder {
     processor: Arc<dyn MessageProcessor + Send + Sync>,
     backend: Arc<dyn AuditBackend>,
     integrity: Arc<dyn AuditIntegrity>,
+    enrichers: Vec<Arc<dyn AuditEnricher>>,
     connection_context: Option<Arc<ConnectionContext>>,
 }
 
@@ -194,6 +264,12 @@ impl AuditProcessorBuilder {
         self
     }
 
+    /// Add an enricher run on every event before it is written
+    pub fn with_enricher(mut self, enricher: Arc<dyn AuditEnricher>) -> Self {
+        self.enrichers.push(enricher);
+        self
+    }
+
     /// Set the connection context for extracting principal and metadata
     pub fn with_connection_context(mut self, context: Arc<ConnectionContext>) -> Self {
         self.connection_context = Some(context);
@@ -206,11 +282,39 @@ impl AuditProcessorBuilder {
             inner: self.processor,
             backend: self.backend,
             integrity: self.integrity,
+            enrichers: self.enrichers,
             connection_context: self.connection_context,
         }
     }
 }
 
+/// Add the remote address of `ctx` and the listener it arrived on
+fn with_peer(mut event: AuditEventBuilder, ctx: &ConnectionContext) -> AuditEventBuilder {
+    if let Some(addr) = ctx.remote_addr {
+        event = event.remote_addr(addr);
+    }
+    if let Some(listener) = ctx.listener() {
+        event = event.metadata("listener", listener.to_string());
+    }
+    event
+}
+
+/// Add the caller of `ctx`, with its tenant and auth method as metadata
+fn with_principal(mut event: AuditEventBuilder, ctx: &ConnectionContext) -> AuditEventBuilder {
+    if let Some(id) = ctx.principal_id() {
+        event = event.principal(id);
+    }
+    if let Some(principal) = ctx.principal() {
+        if let Some(tenant) = &principal.tenant {
+            event = event.metadata("tenant", tenant.as_str());
+        }
+        if let Some(method) = &principal.auth_method {
+            event = event.metadata("auth_method", method.as_str());
+        }
+    }
+    event
+}
+
 /// Log authentication/authorization events
 pub fn log_auth_event(
     backend: &dyn AuditBackend,
@@ -233,15 +337,9 @@ pub fn log_auth_event(
             AuditSeverity::Critical
         });
 
-    if let Some(addr) = ctx.remote_addr {
-        event = event.remote_addr(addr);
-    }
-
-    if let Some(user_id) = ctx.get::<String>("user_id") {
-        event = event.principal(user_id);
-    }
+    event = with_peer(event, ctx);
 
-    let mut evt = event.build();
+    let mut evt = with_principal(event, ctx).build();
     integrity.add_integrity(&mut evt);
     backend.log_audit(&evt);
 }
@@ -273,6 +371,100 @@ pub fn log_security_violation(
     backend.log_audit(&evt);
 }
 
+/// Forwards transport and registry rejections to an audit backend
+///
+/// Security-relevant reasons (auth, limits, method filters) are recorded as
+/// `SecurityViolation`; the rest as `ErrorOccurred`. Register it with
+/// [`crate::rejection::add_observer`].
+pub struct AuditRejectionObserver {
+    backend: Arc<dyn AuditBackend>,
+    integrity: Arc<dyn AuditIntegrity>,
+}
+
+impl AuditRejectionObserver {
+    pub fn new(backend: Arc<dyn AuditBackend>, integrity: Arc<dyn AuditIntegrity>) -> Self {
+        Self { backend, integrity }
+    }
+}
+
+impl crate::rejection::RejectionObserver for AuditRejectionObserver {
+    fn on_rejection(&self, rejection: &crate::rejection::Rejection) {
+        let (event_type, result) = match rejection.reason {
+            crate::rejection::RejectionReason::Unauthorized
+            | crate::rejection::RejectionReason::MethodNotPermitted => {
+                (AuditEventType::AuthorizationCheck, AuditResult::Denied)
+            }
+            crate::rejection::RejectionReason::AuthenticationFailed => {
+                (AuditEventType::AuthenticationAttempt, AuditResult::Failure)
+            }
+            reason if reason.is_security_relevant() => {
+                (AuditEventType::SecurityViolation, AuditResult::Violation)
+            }
+            _ => (AuditEventType::ErrorOccurred, AuditResult::Failure),
+        };
+
+        let mut event = AuditEvent::builder()
+            .event_type(event_type)
+            .result(result)
+            .metadata("rejection", rejection.reason.as_str());
+
+        if let Some(method) = &rejection.method {
+            event = event.method(method);
+        }
+        if let Some(addr) = rejection.remote_addr {
+            event = event.remote_addr(addr);
+        }
+        if let Some(origin) = &rejection.origin {
+            event = event.metadata("listener", origin.as_str());
+        }
+        if let Some(principal) = &rejection.principal {
+            event = event.principal(principal);
+        }
+        if let Some(detail) = &rejection.detail {
+            event = event.error(detail);
+        }
+
+        let mut evt = event.build();
+        self.integrity.add_integrity(&mut evt);
+        self.backend.log_audit(&evt);
+    }
+}
+
+/// Records the result fields masked by a
+/// [`ScopedMasking`](crate::masking::ScopedMasking) as authorization checks
+pub struct AuditMaskingObserver {
+    backend: Arc<dyn AuditBackend>,
+    integrity: Arc<dyn AuditIntegrity>,
+}
+
+impl AuditMaskingObserver {
+    pub fn new(backend: Arc<dyn AuditBackend>, integrity: Arc<dyn AuditIntegrity>) -> Self {
+        Self { backend, integrity }
+    }
+}
+
+impl crate::masking::MaskingObserver for AuditMaskingObserver {
+    fn on_masked(&self, record: &crate::masking::MaskingRecord) {
+        let mut event = AuditEvent::builder()
+            .event_type(AuditEventType::AuthorizationCheck)
+            .result(AuditResult::Success)
+            .method(&record.method)
+            .metadata("masked_fields", record.fields.clone())
+            .metadata("missing_scopes", record.missing_scopes.clone());
+
+        if let Some(addr) = record.remote_addr {
+            event = event.remote_addr(addr);
+        }
+        if let Some(principal) = &record.principal {
+            event = event.principal(principal);
+        }
+
+        let mut evt = event.build();
+        self.integrity.add_integrity(&mut evt);
+        self.backend.log_audit(&evt);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -296,4 +488,200 @@ mod tests {
 
         let _ = audit.process_message(Message::Request(request)).await;
     }
+
+    #[tokio::test]
+    async fn test_audit_processor_forwards_batches() {
+        use crate::batch_policy::BatchPolicy;
+        use crate::{JsonRPCMethod, MethodRegistry, RequestId, error_codes};
+        use std::sync::Mutex;
+
+        struct Capture(Mutex<Vec<AuditEvent>>);
+
+        impl AuditBackend for Capture {
+            fn log_audit(&self, event: &AuditEvent) {
+                self.0.lock().unwrap().push(event.clone());
+            }
+        }
+
+        struct Ping;
+
+        #[async_trait]
+        impl JsonRPCMethod for Ping {
+            fn method_name(&self) -> &'static str {
+                "ping"
+            }
+
+            async fn call(
+                &self,
+                _params: Option<serde_json::Value>,
+                id: Option<RequestId>,
+            ) -> Response {
+                Response::success(serde_json::json!("pong"), id)
+            }
+        }
+
+        let registry = MethodRegistry::new(vec![Box::new(Ping)])
+            .with_batch_policy(BatchPolicy::AbortOnError)
+            .unwrap();
+        let capture = Arc::new(Capture(Mutex::new(Vec::new())));
+        let audit = AuditProcessor::builder(Arc::new(registry))
+            .with_backend(capture.clone())
+            .build();
+
+        let batch = ["ping", "missing", "ping"]
+            .into_iter()
+            .enumerate()
+            .map(|(id, method)| {
+                Message::Request(
+                    RequestBuilder::new(method)
+                        .id(serde_json::json!(id))
+                        .build(),
+                )
+            })
+            .collect();
+        let responses = audit.process_batch(batch).await;
+        let codes: Vec<_> = responses
+            .iter()
+            .map(|r| r.error.as_ref().map(|e| e.code))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                None,
+                Some(error_codes::METHOD_NOT_FOUND),
+                Some(error_codes::DEPENDENT_FAILURE)
+            ]
+        );
+
+        // every entry is audited, the skipped one with its refusal
+        let events = capture.0.lock().unwrap();
+        assert_eq!(events.len(), 6);
+        let outcomes: Vec<_> = events[3..].iter().map(|event| event.result).collect();
+        assert_eq!(
+            outcomes,
+            [
+                AuditResult::Success,
+                AuditResult::Failure,
+                AuditResult::Failure
+            ]
+        );
+        assert_eq!(
+            events[5].metadata["error_code"],
+            error_codes::DEPENDENT_FAILURE
+        );
+    }
+
+    #[test]
+    fn test_audit_rejection_observer() {
+        use crate::rejection::{Rejection, RejectionObserver, RejectionReason};
+        use std::sync::Mutex;
+
+        struct Capture(Mutex<Vec<AuditEvent>>);
+
+        impl AuditBackend for Capture {
+            fn log_audit(&self, event: &AuditEvent) {
+                self.0.lock().unwrap().push(event.clone());
+            }
+        }
+
+        let capture = Arc::new(Capture(Mutex::new(Vec::new())));
+        let observer =
+            AuditRejectionObserver::new(capture.clone(), Arc::new(super::super::NoIntegrity));
+
+        observer.on_rejection(&Rejection::new(RejectionReason::Unauthorized).method("admin"));
+        observer.on_rejection(&Rejection::new(RejectionReason::RequestTooLarge));
+        observer.on_rejection(&Rejection::new(RejectionReason::ParseError));
+
+        let events = capture.0.lock().unwrap();
+        assert_eq!(events[0].event_type, AuditEventType::AuthorizationCheck);
+        assert_eq!(events[0].result, AuditResult::Denied);
+        assert_eq!(events[1].event_type, AuditEventType::SecurityViolation);
+        assert_eq!(events[2].event_type, AuditEventType::ErrorOccurred);
+        assert_eq!(events[2].metadata["rejection"], "parse_error");
+    }
+
+    #[test]
+    fn test_audit_masking_observer() {
+        use crate::masking::{MaskingObserver, MaskingRecord};
+        use std::sync::Mutex;
+
+        struct Capture(Mutex<Vec<AuditEvent>>);
+
+        impl AuditBackend for Capture {
+            fn log_audit(&self, event: &AuditEvent) {
+                self.0.lock().unwrap().push(event.clone());
+            }
+        }
+
+        let capture = Arc::new(Capture(Mutex::new(Vec::new())));
+        let observer =
+            AuditMaskingObserver::new(capture.clone(), Arc::new(super::super::NoIntegrity));
+        observer.on_masked(&MaskingRecord {
+            method: "sales.report".into(),
+            principal: Some("alice".into()),
+            remote_addr: None,
+            fields: vec!["/rows/0/customer".into()],
+            missing_scopes: vec!["analytics:pii".into()],
+        });
+
+        let events = capture.0.lock().unwrap();
+        assert_eq!(events[0].event_type, AuditEventType::AuthorizationCheck);
+        assert_eq!(events[0].principal.as_deref(), Some("alice"));
+        assert_eq!(
+            events[0].metadata["masked_fields"],
+            serde_json::json!(["/rows/0/customer"])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_audit_processor_runs_enrichers() {
+        use super::super::StaticMetadataEnricher;
+        use std::sync::Mutex;
+
+        struct Capture(Mutex<Vec<AuditEvent>>);
+
+        impl AuditBackend for Capture {
+            fn log_audit(&self, event: &AuditEvent) {
+                self.0.lock().unwrap().push(event.clone());
+            }
+        }
+
+        struct TenantEnricher;
+
+        impl AuditEnricher for TenantEnricher {
+            fn enrich(&self, event: &mut AuditEvent, ctx: &ConnectionContext) {
+                if let Some(tenant) = ctx.get::<String>("tenant") {
+                    event.add_metadata("tenant", tenant.as_str());
+                }
+            }
+        }
+
+        let capture = Arc::new(Capture(Mutex::new(Vec::new())));
+        let audit = AuditProcessor::builder(Arc::new(crate::MethodRegistry::empty()))
+            .with_backend(capture.clone())
+            .with_integrity(Arc::new(super::super::HashChainIntegrity::new()))
+            .with_enricher(Arc::new(TenantEnricher))
+            .with_enricher(Arc::new(
+                StaticMetadataEnricher::new().field("classification", "internal"),
+            ))
+            .build();
+
+        let mut ctx = ConnectionContext::new();
+        ctx.insert("tenant".to_string(), "acme".to_string());
+        let request = RequestBuilder::new("orders.list")
+            .id(serde_json::json!(1))
+            .build();
+        audit
+            .process_message_with_context(Message::Request(request), &ctx)
+            .await;
+
+        let events = capture.0.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        for event in events.iter() {
+            assert_eq!(event.metadata["tenant"], "acme");
+            assert_eq!(event.metadata["classification"], "internal");
+            // enrichment happens before the event is hashed
+            assert!(super::super::HashChainIntegrity::new().verify(event));
+        }
+    }
 }
@@ -0,0 +1,166 @@
+//! API key auth policy.
+
+use super::credentials::{self, CredentialSource};
+use super::{AuthPolicy, ConnectionContext, Principal};
+use crate::transports::MethodFilter;
+use std::sync::Arc;
+
+type KeyLookup = dyn Fn(&str, &str) -> bool + Send + Sync;
+
+/// Accepts callers presenting a known API key
+///
+/// The key is read from the first of its [sources](Self::sources) that has
+/// one: the `api_key` param, the `x-api-key` header or the `api_key`
+/// connection metadata by default. Keys are either registered up front,
+/// optionally limited to some methods or tied to a [`Principal`], or
+/// checked by a lookup callback.
+///
+/// ```rust
+/// use ash_rpc::auth::{ApiKeyAuthPolicy, AuthPolicy, ConnectionContext};
+/// use ash_rpc::transports::MethodFilter;
+///
+/// let policy = ApiKeyAuthPolicy::new()
+///     .key("ops-2f9c")
+///     .key_for("reports-81ab", MethodFilter::new().allow("reports.*"));
+///
+/// let params = serde_json::json!({ "api_key": "reports-81ab" });
+/// let ctx = ConnectionContext::new();
+/// assert!(policy.can_access("reports.daily", Some(&params), &ctx));
+/// assert!(!policy.can_access("admin.reload", Some(&params), &ctx));
+/// ```
+pub struct ApiKeyAuthPolicy {
+    sources: Vec<CredentialSource>,
+    keys: Vec<(String, MethodFilter, Option<Principal>)>,
+    lookup: Option<Arc<KeyLookup>>,
+}
+
+impl ApiKeyAuthPolicy {
+    pub fn new() -> Self {
+        Self {
+            sources: vec![
+                CredentialSource::param("api_key"),
+                CredentialSource::header("x-api-key"),
+                CredentialSource::metadata("api_key"),
+            ],
+            keys: Vec::new(),
+            lookup: None,
+        }
+    }
+
+    /// Replace where keys are read from, tried in order
+    pub fn sources(mut self, sources: impl IntoIterator<Item = CredentialSource>) -> Self {
+        self.sources = sources.into_iter().collect();
+        self
+    }
+
+    /// Accept `key` for every method
+    pub fn key(self, key: impl Into<String>) -> Self {
+        self.key_for(key, MethodFilter::new())
+    }
+
+    /// Accept `key` for the methods `filter` permits
+    pub fn key_for(mut self, key: impl Into<String>, filter: MethodFilter) -> Self {
+        self.keys.push((key.into(), filter, None));
+        self
+    }
+
+    /// Accept `key` for every method, identifying its callers as `principal`
+    ///
+    /// The principal's auth method defaults to `api_key`.
+    pub fn key_as(mut self, key: impl Into<String>, mut principal: Principal) -> Self {
+        principal
+            .auth_method
+            .get_or_insert_with(|| "api_key".to_string());
+        self.keys
+            .push((key.into(), MethodFilter::new(), Some(principal)));
+        self
+    }
+
+    fn registered(&self, presented: &str) -> Option<&(String, MethodFilter, Option<Principal>)> {
+        self.keys
+            .iter()
+            .find(|(key, ..)| credentials::constant_time_eq(key.as_bytes(), presented.as_bytes()))
+    }
+
+    /// Decide on keys that were not registered with `(key, method)`
+    pub fn lookup<F>(mut self, lookup: F) -> Self
+    where
+        F: Fn(&str, &str) -> bool + Send + Sync + 'static,
+    {
+        self.lookup = Some(Arc::new(lookup));
+        self
+    }
+}
+
+impl Default for ApiKeyAuthPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AuthPolicy for ApiKeyAuthPolicy {
+    fn can_access(
+        &self,
+        method: &str,
+        params: Option<&serde_json::Value>,
+        ctx: &ConnectionContext,
+    ) -> bool {
+        let Some(presented) = credentials::find(&self.sources, params, ctx) else {
+            tracing::debug!(method, "no api key presented");
+            return false;
+        };
+        match (self.registered(&presented), &self.lookup) {
+            (Some((_, filter, _)), _) => filter.is_permitted(method),
+            (None, Some(lookup)) => lookup(&presented, method),
+            (None, None) => {
+                tracing::debug!(method, "unknown api key");
+                false
+            }
+        }
+    }
+
+    fn identify(
+        &self,
+        _method: &str,
+        params: Option<&serde_json::Value>,
+        ctx: &ConnectionContext,
+    ) -> Option<Principal> {
+        let presented = credentials::find(&self.sources, params, ctx)?;
+        self.registered(&presented)?.2.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_key_sources_and_lookup() {
+        let policy = ApiKeyAuthPolicy::new()
+            .key("static-key")
+            .lookup(|key, method| key == "dynamic-key" && method == "ping");
+
+        let mut ctx = ConnectionContext::new();
+        assert!(!policy.can_access("ping", None, &ctx));
+
+        ctx.insert("api_key".to_string(), "static-key".to_string());
+        assert!(policy.can_access("orders.list", None, &ctx));
+
+        // params are tried before connection metadata
+        let params = serde_json::json!({ "api_key": "dynamic-key" });
+        assert!(policy.can_access("ping", Some(&params), &ctx));
+        assert!(!policy.can_access("orders.list", Some(&params), &ctx));
+
+        let metadata_only = ApiKeyAuthPolicy::new()
+            .key("dynamic-key")
+            .sources([CredentialSource::metadata("api_key")]);
+        assert!(!metadata_only.can_access("ping", Some(&params), &ConnectionContext::new()));
+
+        let identified = ApiKeyAuthPolicy::new().key_as("svc-key", Principal::new("billing-svc"));
+        let params = serde_json::json!({ "api_key": "svc-key" });
+        let principal = identified.identify("ping", Some(&params), &ctx).unwrap();
+        assert_eq!(principal.id, "billing-svc");
+        assert_eq!(principal.auth_method.as_deref(), Some("api_key"));
+        assert!(policy.identify("ping", Some(&params), &ctx).is_none());
+    }
+}
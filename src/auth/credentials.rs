@@ -0,0 +1,81 @@
+//! Where the ready-made auth policies find a caller's credential.
+
+use super::ConnectionContext;
+
+/// Place a credential is read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CredentialSource {
+    /// Member of the request's params object
+    Param(String),
+    /// HTTP header captured by the Axum transport; a `Bearer ` prefix is
+    /// stripped
+    Header(String),
+    /// `String` in the connection metadata, e.g. stored by a handshake
+    /// authenticator
+    Metadata(String),
+}
+
+impl CredentialSource {
+    pub fn param(name: impl Into<String>) -> Self {
+        Self::Param(name.into())
+    }
+
+    pub fn header(name: impl Into<String>) -> Self {
+        Self::Header(name.into())
+    }
+
+    pub fn metadata(key: impl Into<String>) -> Self {
+        Self::Metadata(key.into())
+    }
+
+    /// The credential, if this source holds one
+    pub fn extract(
+        &self,
+        params: Option<&serde_json::Value>,
+        ctx: &ConnectionContext,
+    ) -> Option<String> {
+        match self {
+            Self::Param(name) => params?.get(name)?.as_str().map(str::to_string),
+            Self::Header(name) => header(ctx, name),
+            Self::Metadata(key) => ctx.get::<String>(key).cloned(),
+        }
+    }
+}
+
+/// First credential found in `sources`
+pub(super) fn find(
+    sources: &[CredentialSource],
+    params: Option<&serde_json::Value>,
+    ctx: &ConnectionContext,
+) -> Option<String> {
+    sources
+        .iter()
+        .find_map(|source| source.extract(params, ctx))
+}
+
+#[cfg(feature = "axum")]
+fn header(ctx: &ConnectionContext, name: &str) -> Option<String> {
+    let value = ctx
+        .get::<axum::http::HeaderMap>(super::HTTP_HEADERS_KEY)?
+        .get(name)?
+        .to_str()
+        .ok()?;
+    let value = match value.get(..7) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("bearer ") => &value[7..],
+        _ => value,
+    };
+    Some(value.trim().to_string())
+}
+
+#[cfg(not(feature = "axum"))]
+fn header(_ctx: &ConnectionContext, _name: &str) -> Option<String> {
+    None
+}
+
+/// Compare secrets in time independent of where they differ
+pub(super) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
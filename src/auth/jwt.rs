@@ -0,0 +1,282 @@
+//! JWT bearer token auth policy.
+
+use super::credentials::{self, CredentialSource};
+use super::{AuthPolicy, ConnectionContext, Principal};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use std::sync::Arc;
+
+type ClaimsCheck = dyn Fn(&serde_json::Value, &str) -> bool + Send + Sync;
+
+/// Accepts callers presenting a valid JWT
+///
+/// Tokens are read from the `authorization` header (`Bearer <token>`), the
+/// `token` param or the `jwt` connection metadata by default. Signature and
+/// expiry are always checked; issuer and audience when configured. Claims
+/// then decide per method, through required scopes (`scope`, `scp` or
+/// `scopes` claim) and an optional callback.
+///
+/// The caller is identified by the `sub` claim, with `name`, `roles`,
+/// scopes and the tenant claim filling in the rest of the [`Principal`].
+///
+/// ```rust
+/// use ash_rpc::auth::JwtAuthPolicy;
+///
+/// let policy = JwtAuthPolicy::hs256(b"shared-secret")
+///     .issuer("https://auth.example.com")
+///     .require_scope("admin.*", "admin")
+///     .authorize(|claims, method| {
+///         !method.starts_with("billing.") || claims["tenant"] == "acme"
+///     });
+/// ```
+pub struct JwtAuthPolicy {
+    key: DecodingKey,
+    validation: Validation,
+    sources: Vec<CredentialSource>,
+    scopes: Vec<(String, String)>,
+    authorize: Option<Arc<ClaimsCheck>>,
+    tenant_claim: String,
+}
+
+impl JwtAuthPolicy {
+    /// Validate HS256 tokens signed with `secret`
+    pub fn hs256(secret: impl AsRef<[u8]>) -> Self {
+        Self::with_key(DecodingKey::from_secret(secret.as_ref()), Algorithm::HS256)
+    }
+
+    /// Validate RS256 tokens against a PEM encoded RSA public key
+    pub fn rs256_pem(public_key: &[u8]) -> Result<Self, jsonwebtoken::errors::Error> {
+        Ok(Self::with_key(
+            DecodingKey::from_rsa_pem(public_key)?,
+            Algorithm::RS256,
+        ))
+    }
+
+    fn with_key(key: DecodingKey, algorithm: Algorithm) -> Self {
+        Self {
+            key,
+            validation: Validation::new(algorithm),
+            sources: vec![
+                CredentialSource::header("authorization"),
+                CredentialSource::param("token"),
+                CredentialSource::metadata("jwt"),
+            ],
+            scopes: Vec::new(),
+            authorize: None,
+            tenant_claim: "tenant".to_string(),
+        }
+    }
+
+    /// Require the `iss` claim to be `issuer` (may be repeated)
+    pub fn issuer(mut self, issuer: impl Into<String>) -> Self {
+        let mut issuers: Vec<String> = self.validation.iss.take().into_iter().flatten().collect();
+        issuers.push(issuer.into());
+        self.validation.set_issuer(&issuers);
+        self
+    }
+
+    /// Require the `aud` claim to contain `audience` (may be repeated)
+    pub fn audience(mut self, audience: impl Into<String>) -> Self {
+        let mut audiences: Vec<String> = self.validation.aud.take().into_iter().flatten().collect();
+        audiences.push(audience.into());
+        self.validation.set_audience(&audiences);
+        self
+    }
+
+    /// Clock skew tolerated on `exp` and `nbf`, 60 seconds by default
+    pub fn leeway(mut self, secs: u64) -> Self {
+        self.validation.leeway = secs;
+        self
+    }
+
+    /// Replace where tokens are read from, tried in order
+    pub fn sources(mut self, sources: impl IntoIterator<Item = CredentialSource>) -> Self {
+        self.sources = sources.into_iter().collect();
+        self
+    }
+
+    /// Require `scope` for methods matching `pattern` (`"admin.*"` style)
+    pub fn require_scope(mut self, pattern: impl Into<String>, scope: impl Into<String>) -> Self {
+        self.scopes.push((pattern.into(), scope.into()));
+        self
+    }
+
+    /// Decide on valid tokens with `(claims, method)`
+    pub fn authorize<F>(mut self, check: F) -> Self
+    where
+        F: Fn(&serde_json::Value, &str) -> bool + Send + Sync + 'static,
+    {
+        self.authorize = Some(Arc::new(check));
+        self
+    }
+
+    /// Claim naming the caller's tenant, `tenant` by default
+    pub fn tenant_claim(mut self, claim: impl Into<String>) -> Self {
+        self.tenant_claim = claim.into();
+        self
+    }
+
+    /// Claims of `token` if it is valid
+    pub fn verify(&self, token: &str) -> Result<serde_json::Value, jsonwebtoken::errors::Error> {
+        jsonwebtoken::decode::<serde_json::Value>(token, &self.key, &self.validation)
+            .map(|data| data.claims)
+    }
+
+    fn has_scope(claims: &serde_json::Value, scope: &str) -> bool {
+        Self::scopes(claims).any(|s| s == scope)
+    }
+
+    fn scopes(claims: &serde_json::Value) -> impl Iterator<Item = &str> {
+        let spaced = claims["scope"].as_str();
+        let listed = match spaced {
+            Some(_) => None,
+            None => ["scp", "scopes"]
+                .iter()
+                .find_map(|claim| claims[claim].as_array()),
+        };
+        spaced
+            .into_iter()
+            .flat_map(str::split_whitespace)
+            .chain(listed.into_iter().flatten().filter_map(|s| s.as_str()))
+    }
+
+    /// The caller described by valid `claims`, `None` without a `sub` claim
+    pub fn principal(&self, claims: &serde_json::Value) -> Option<Principal> {
+        let mut principal = Principal::new(claims["sub"].as_str()?)
+            .scopes(Self::scopes(claims))
+            .auth_method("jwt");
+        principal.display_name = claims["name"].as_str().map(str::to_string);
+        principal.roles = claims["roles"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|role| Some(role.as_str()?.to_string()))
+            .collect();
+        principal.tenant = claims[&self.tenant_claim].as_str().map(str::to_string);
+        Some(principal)
+    }
+}
+
+impl AuthPolicy for JwtAuthPolicy {
+    fn can_access(
+        &self,
+        method: &str,
+        params: Option<&serde_json::Value>,
+        ctx: &ConnectionContext,
+    ) -> bool {
+        let Some(token) = credentials::find(&self.sources, params, ctx) else {
+            tracing::debug!(method, "no token presented");
+            return false;
+        };
+        let claims = match self.verify(&token) {
+            Ok(claims) => claims,
+            Err(e) => {
+                tracing::debug!(method, error = %e, "token rejected");
+                return false;
+            }
+        };
+        let scoped = self
+            .scopes
+            .iter()
+            .filter(|(pattern, _)| crate::transports::listener::pattern_matches(pattern, method))
+            .all(|(_, scope)| Self::has_scope(&claims, scope));
+        scoped
+            && self
+                .authorize
+                .as_ref()
+                .is_none_or(|check| check(&claims, method))
+    }
+
+    fn identify(
+        &self,
+        _method: &str,
+        params: Option<&serde_json::Value>,
+        ctx: &ConnectionContext,
+    ) -> Option<Principal> {
+        let token = credentials::find(&self.sources, params, ctx)?;
+        self.principal(&self.verify(&token).ok()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{EncodingKey, Header};
+    use serde_json::json;
+
+    fn token(claims: serde_json::Value, secret: &[u8]) -> String {
+        jsonwebtoken::encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(secret),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_jwt_validation_and_scopes() {
+        let exp = jsonwebtoken::get_current_timestamp() + 600;
+        let policy = JwtAuthPolicy::hs256(b"secret")
+            .issuer("issuer")
+            .require_scope("admin.*", "admin")
+            .authorize(|claims, method| method != "billing.charge" || claims["tenant"] == "acme");
+        let call = |token: String, method: &str| {
+            policy.can_access(
+                method,
+                Some(&json!({ "token": token })),
+                &ConnectionContext::new(),
+            )
+        };
+
+        let user = token(
+            json!({"iss": "issuer", "exp": exp, "scope": "read", "tenant": "acme"}),
+            b"secret",
+        );
+        assert!(call(user.clone(), "orders.list"));
+        assert!(call(user.clone(), "billing.charge"));
+        assert!(!call(user, "admin.reload"));
+
+        let admin = token(
+            json!({"iss": "issuer", "exp": exp, "scp": ["admin"], "tenant": "other"}),
+            b"secret",
+        );
+        assert!(call(admin.clone(), "admin.reload"));
+        assert!(!call(admin, "billing.charge"));
+
+        let forged = token(json!({"iss": "issuer", "exp": exp}), b"other-secret");
+        assert!(!call(forged, "orders.list"));
+        let expired = token(json!({"iss": "issuer", "exp": exp - 3600}), b"secret");
+        assert!(!call(expired, "orders.list"));
+        let foreign = token(json!({"iss": "elsewhere", "exp": exp}), b"secret");
+        assert!(!call(foreign, "orders.list"));
+
+        assert!(JwtAuthPolicy::rs256_pem(b"not a key").is_err());
+    }
+
+    #[test]
+    fn test_jwt_principal() {
+        let exp = jsonwebtoken::get_current_timestamp() + 600;
+        let policy = JwtAuthPolicy::hs256(b"secret").tenant_claim("org");
+        let claims = json!({
+            "sub": "u-7", "exp": exp, "name": "Alice", "roles": ["auditor"],
+            "scope": "read write", "org": "acme",
+        });
+        let params = json!({ "token": token(claims, b"secret") });
+        let principal = policy
+            .identify("ping", Some(&params), &ConnectionContext::new())
+            .unwrap();
+        assert_eq!(
+            principal,
+            Principal::new("u-7")
+                .display_name("Alice")
+                .role("auditor")
+                .scopes(["read", "write"])
+                .auth_method("jwt")
+                .tenant("acme")
+        );
+
+        let anonymous = json!({ "token": token(json!({"exp": exp}), b"secret") });
+        let ctx = ConnectionContext::new();
+        assert!(policy.can_access("ping", Some(&anonymous), &ctx));
+        assert!(policy.identify("ping", Some(&anonymous), &ctx).is_none());
+    }
+}
@@ -0,0 +1,957 @@
+//! Authentication and authorization hooks
+//!
+//! This module provides minimal traits for implementing authentication
+//! and authorization. The library makes NO assumptions about:
+//! - How you authenticate (JWT, API keys, OAuth, certificates, etc.)
+//! - What your user/identity model looks like
+//! - How you authorize (RBAC, ABAC, ACL, custom logic, etc.)
+//!
+//! You implement the trait, we call your `can_access` method.
+//!
+//! # Example
+//! ```
+//! use ash_rpc::auth::{AuthPolicy, ConnectionContext};
+//!
+//! struct MyAuth;
+//!
+//! impl AuthPolicy for MyAuth {
+//!     fn can_access(&self, method: &str, params: Option<&serde_json::Value>, _ctx: &ConnectionContext) -> bool {
+//!         // Your logic here - check API keys, JWT tokens, whatever you need
+//!         let _ = (method, params);
+//!         true
+//!     }
+//! }
+//! ```
+//!
+//! Once a caller is known, describe them with a [`Principal`]: set it on
+//! the connection (e.g. from a handshake) or return it from
+//! [`AuthPolicy::identify`]. Scope and role checks, per-principal rate
+//! limits, audit events and metrics all read it from there.
+//!
+//! With the `auth-providers` feature, [`ApiKeyAuthPolicy`] and
+//! [`JwtAuthPolicy`] cover the common setups without a custom policy.
+
+#[cfg(feature = "auth-providers")]
+mod api_key;
+#[cfg(feature = "auth-providers")]
+mod credentials;
+#[cfg(feature = "auth-providers")]
+mod jwt;
+mod principal;
+
+#[cfg(feature = "auth-providers")]
+pub use api_key::ApiKeyAuthPolicy;
+#[cfg(feature = "auth-providers")]
+pub use credentials::CredentialSource;
+#[cfg(feature = "auth-providers")]
+pub use jwt::JwtAuthPolicy;
+pub use principal::Principal;
+
+use crate::Response;
+use std::any::Any;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Type alias for auth metadata storage
+type AuthMetadata = std::collections::HashMap<String, Arc<dyn Any + Send + Sync>>;
+
+/// Connection context for authentication
+///
+/// This struct holds metadata about a connection that can be used for
+/// authentication and authorization decisions. The library makes NO
+/// assumptions about what data you need - store anything in `metadata`.
+///
+/// # Examples
+/// - TLS client certificates
+/// - IP addresses for whitelisting
+/// - Custom connection-level tokens
+/// - Session identifiers
+#[derive(Default, Clone)]
+pub struct ConnectionContext {
+    /// Remote address of the connection
+    pub remote_addr: Option<SocketAddr>,
+
+    /// Name of the listener the request arrived on
+    ///
+    /// Set by transports configured with a listener name, e.g. "internal"
+    /// or "public", so policies can tell exposures apart.
+    pub origin: Option<String>,
+
+    /// User-defined metadata
+    ///
+    /// Store any auth-related data here:
+    /// - TLS peer certificates
+    /// - Extracted user IDs
+    /// - Session tokens
+    /// - Rate limiting state
+    /// - Whatever you need for your auth logic
+    pub metadata: AuthMetadata,
+}
+
+impl ConnectionContext {
+    /// Create a new empty context
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create context with remote address
+    pub fn with_addr(remote_addr: SocketAddr) -> Self {
+        Self {
+            remote_addr: Some(remote_addr),
+            origin: None,
+            metadata: std::collections::HashMap::new(),
+        }
+    }
+
+    /// Tag the context with the listener it originated from
+    pub fn with_origin(mut self, origin: impl Into<String>) -> Self {
+        self.origin = Some(origin.into());
+        self
+    }
+
+    /// Insert typed metadata
+    pub fn insert<T: Any + Send + Sync>(&mut self, key: String, value: T) {
+        self.metadata.insert(key, Arc::new(value));
+    }
+
+    /// Get typed metadata
+    pub fn get<T: Any + Send + Sync>(&self, key: &str) -> Option<&T> {
+        self.metadata.get(key).and_then(|v| v.downcast_ref::<T>())
+    }
+
+    /// Add to the scopes stored under [`SCOPES_KEY`]
+    pub fn grant_scopes<I, S>(&mut self, scopes: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut granted = self
+            .get::<Vec<String>>(SCOPES_KEY)
+            .cloned()
+            .unwrap_or_default();
+        granted.extend(scopes.into_iter().map(Into::into));
+        self.insert(SCOPES_KEY.to_string(), granted);
+    }
+
+    /// Whether `scope` was granted to the connection or its principal
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.get::<Vec<String>>(SCOPES_KEY)
+            .is_some_and(|granted| granted.iter().any(|s| s == scope))
+            || self.principal().is_some_and(|p| p.has_scope(scope))
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.principal().is_some_and(|p| p.has_role(role))
+    }
+
+    /// Store the caller's identity under [`PRINCIPAL_KEY`]
+    pub fn set_principal(&mut self, principal: Principal) {
+        self.insert(PRINCIPAL_KEY.to_string(), principal);
+    }
+
+    pub fn principal(&self) -> Option<&Principal> {
+        self.get::<Principal>(PRINCIPAL_KEY)
+    }
+
+    /// Id of the principal, falling back to a `String` stored under
+    /// [`USER_ID_KEY`]
+    pub fn principal_id(&self) -> Option<&str> {
+        self.principal()
+            .map(|p| p.id.as_str())
+            .or_else(|| self.get::<String>(USER_ID_KEY).map(String::as_str))
+    }
+
+    /// Subject of the client certificate stored under [`PEER_DN_KEY`]
+    pub fn peer_dn(&self) -> Option<&str> {
+        self.get::<String>(PEER_DN_KEY).map(String::as_str)
+    }
+
+    /// Listener that accepted the connection, stored under [`LISTENER_KEY`]
+    pub fn listener(&self) -> Option<&crate::transports::listener::ListenerId> {
+        self.get(LISTENER_KEY)
+    }
+
+    /// Tag the context with the listener that accepted the connection
+    pub fn with_listener(mut self, listener: crate::transports::listener::ListenerId) -> Self {
+        self.insert(LISTENER_KEY.to_string(), listener);
+        self
+    }
+
+    /// Time by which the current call must be answered, stored under
+    /// [`DEADLINE_KEY`]
+    pub fn deadline(&self) -> Option<Instant> {
+        self.get::<Instant>(DEADLINE_KEY).copied()
+    }
+
+    /// Time left until the [`deadline`](Self::deadline), zero once passed
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline()
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Set the deadline, keeping an earlier one already set
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        let deadline = self
+            .deadline()
+            .map_or(deadline, |current| current.min(deadline));
+        self.insert(DEADLINE_KEY.to_string(), deadline);
+        self
+    }
+
+    /// Correlation id of the current request, stored under
+    /// [`CORRELATION_ID_KEY`]
+    pub fn correlation_id(&self) -> Option<&str> {
+        self.get::<String>(CORRELATION_ID_KEY).map(String::as_str)
+    }
+
+    pub fn with_correlation_id(mut self, correlation_id: impl Into<String>) -> Self {
+        self.insert(CORRELATION_ID_KEY.to_string(), correlation_id.into());
+        self
+    }
+
+    /// Output format of the transport, stored under [`JSON_FORMAT_KEY`];
+    /// the default format when the transport set none
+    pub fn json_format(&self) -> crate::serialization::JsonFormat {
+        self.get(JSON_FORMAT_KEY).copied().unwrap_or_default()
+    }
+
+    pub fn with_json_format(mut self, format: crate::serialization::JsonFormat) -> Self {
+        self.insert(JSON_FORMAT_KEY.to_string(), format);
+        self
+    }
+
+    /// Limits of the processor wrappers a request passed through, stored
+    /// under [`CAPABILITY_LIMITS_KEY`]
+    pub fn capability_limits(&self) -> &[crate::CapabilityLimits] {
+        self.get::<Vec<crate::CapabilityLimits>>(CAPABILITY_LIMITS_KEY)
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Add the limits of a wrapper the request passes through
+    pub fn with_capability_limits(mut self, limits: crate::CapabilityLimits) -> Self {
+        let mut all = self.capability_limits().to_vec();
+        all.push(limits);
+        self.insert(CAPABILITY_LIMITS_KEY.to_string(), all);
+        self
+    }
+
+    /// Values middleware attached to the current request, stored under
+    /// [`EXTENSIONS_KEY`]
+    pub fn extensions(&self) -> Option<&crate::extensions::Extensions> {
+        self.get(EXTENSIONS_KEY)
+    }
+
+    /// Attach `extensions`, on top of those already attached
+    pub fn with_extensions(mut self, extensions: crate::extensions::Extensions) -> Self {
+        let mut merged = self.extensions().cloned().unwrap_or_default();
+        merged.extend(extensions);
+        self.insert(EXTENSIONS_KEY.to_string(), merged);
+        self
+    }
+}
+
+/// Metadata key of the `Vec<String>` of scopes granted to a connection
+pub const SCOPES_KEY: &str = "scopes";
+
+/// Metadata key of the connection's [`Principal`]
+pub const PRINCIPAL_KEY: &str = "principal";
+
+/// Metadata key of a plain `String` user id, read when no [`Principal`] is set
+pub const USER_ID_KEY: &str = "user_id";
+
+/// Metadata key of the `String` subject DN of a TLS client certificate,
+/// set by the TLS transport when the client presented one
+pub const PEER_DN_KEY: &str = "peer_dn";
+
+/// Metadata key of the [`ListenerId`](crate::transports::listener::ListenerId)
+/// of the listener a connection arrived on, set by the socket transports
+pub const LISTENER_KEY: &str = "listener";
+
+/// Metadata key of the `std::time::Instant` deadline of the current call,
+/// set by registries enforcing [timeouts](crate::MethodRegistry)
+pub const DEADLINE_KEY: &str = "deadline";
+
+/// Metadata key of the `String` correlation id of the current request, set
+/// by registries for requests carrying one
+pub const CORRELATION_ID_KEY: &str = "correlation_id";
+
+/// Metadata key of the [`Extensions`](crate::extensions::Extensions) of the
+/// current request, set by registries when middleware attached values
+pub const EXTENSIONS_KEY: &str = "extensions";
+
+/// Metadata key of the `Vec` of [`CapabilityLimits`](crate::CapabilityLimits)
+/// of the processor wrappers a request passed through, so the capabilities
+/// built-in reports what is in effect
+pub const CAPABILITY_LIMITS_KEY: &str = "capability_limits";
+
+/// Metadata key of the [`JsonFormat`](crate::serialization::JsonFormat) a
+/// transport writes responses in, set by transports configured with one
+pub const JSON_FORMAT_KEY: &str = "json_format";
+
+/// Metadata key of the `axum::http::HeaderMap` of an HTTP request, set by
+/// the Axum transport
+pub const HTTP_HEADERS_KEY: &str = "http_headers";
+
+/// Trait for extracting authentication context from connections
+///
+/// Implement this to extract auth data from your transport layer.
+/// The library will call this when a new connection is established.
+///
+/// # What you can extract:
+/// - TLS client certificates for mutual TLS authentication
+/// - IP addresses for whitelisting/geoblocking
+/// - Custom connection-level authentication tokens
+/// - Any connection metadata you need for auth decisions
+///
+/// # Example: TLS Certificate Extraction
+/// ```text
+/// use ash_rpc::auth::{ContextExtractor, ConnectionContext};
+///
+/// struct TlsContextExtractor;
+///
+/// #[async_trait::async_trait]
+/// impl ContextExtractor for TlsContextExtractor {
+///     async fn extract(&self, stream: &tokio_rustls::server::TlsStream<tokio::net::TcpStream>) -> ConnectionContext {
+///         let mut ctx = ConnectionContext::new();
+///         
+///         // Extract TLS peer certificates
+///         if let Some(certs) = stream.get_ref().1.peer_certificates() {
+///             ctx.insert("peer_certs".to_string(), certs.clone());
+///         }
+///         
+///         // Extract client IP
+///         if let Ok(addr) = stream.get_ref().0.peer_addr() {
+///             ctx.remote_addr = Some(addr);
+///         }
+///         
+///         ctx
+///     }
+/// }
+/// ```
+#[async_trait::async_trait]
+pub trait ContextExtractor: Send + Sync {
+    /// Extract connection context for authentication
+    ///
+    /// This is called once when a connection is established.
+    /// The returned context is passed to the auth policy for each request.
+    ///
+    /// # Arguments
+    /// * `remote_addr` - Remote socket address of the connection
+    /// * `metadata` - Optional transport-specific metadata (e.g., TLS session data)
+    ///
+    /// # Returns
+    /// A `ConnectionContext` with whatever data you need for auth
+    async fn extract(
+        &self,
+        remote_addr: Option<SocketAddr>,
+        metadata: Option<Arc<dyn Any + Send + Sync>>,
+    ) -> ConnectionContext;
+}
+
+/// Default context extractor that only captures the remote address
+pub struct DefaultContextExtractor;
+
+#[async_trait::async_trait]
+impl ContextExtractor for DefaultContextExtractor {
+    async fn extract(
+        &self,
+        remote_addr: Option<SocketAddr>,
+        _metadata: Option<Arc<dyn Any + Send + Sync>>,
+    ) -> ConnectionContext {
+        ConnectionContext {
+            remote_addr,
+            origin: None,
+            metadata: std::collections::HashMap::new(),
+        }
+    }
+}
+
+/// Trait for implementing authentication/authorization checks
+///
+/// Implement this to control access to your JSON-RPC methods.
+/// The library will call `can_access` before executing methods.
+///
+/// You decide what "access" means - it could be:
+/// - Checking an API key in request params
+/// - Validating a JWT token
+/// - Verifying client certificates (via ConnectionContext)
+/// - Role-based checks
+/// - Rate limiting
+/// - IP whitelisting (via ConnectionContext)
+/// - Anything else you need
+pub trait AuthPolicy: Send + Sync {
+    /// Check if a request should be allowed to proceed
+    ///
+    /// # Arguments
+    /// * `method` - The JSON-RPC method being called
+    /// * `params` - Optional parameters from the request
+    /// * `ctx` - Connection context (IP, TLS certs, custom metadata)
+    ///
+    /// # Returns
+    /// `true` if the request should proceed, `false` to deny
+    ///
+    /// # Example: Using Connection Context
+    /// ```text
+    /// fn can_access(
+    ///     &self,
+    ///     method: &str,
+    ///     params: Option<&serde_json::Value>,
+    ///     ctx: &ConnectionContext,
+    /// ) -> bool {
+    ///     // Check IP whitelist
+    ///     if let Some(addr) = ctx.remote_addr {
+    ///         if !self.is_ip_allowed(&addr.ip()) {
+    ///             return false;
+    ///         }
+    ///     }
+    ///     
+    ///     // Check TLS client certificate
+    ///     if let Some(certs) = ctx.get::<Vec<Certificate>>("peer_certs") {
+    ///         return self.validate_client_cert(certs);
+    ///     }
+    ///     
+    ///     // Check token in params
+    ///     let token = params
+    ///         .and_then(|p| p.get("auth_token"))
+    ///         .and_then(|t| t.as_str());
+    ///     
+    ///     self.validate_token(token)
+    /// }
+    /// ```
+    fn can_access(
+        &self,
+        method: &str,
+        params: Option<&serde_json::Value>,
+        ctx: &ConnectionContext,
+    ) -> bool;
+
+    /// Optional: The principal behind an accepted call
+    ///
+    /// Called after [`can_access`](Self::can_access) allowed a call on a
+    /// connection without a principal. What is returned is attached to the
+    /// call's context for everything that runs after authorization.
+    fn identify(
+        &self,
+        method: &str,
+        params: Option<&serde_json::Value>,
+        ctx: &ConnectionContext,
+    ) -> Option<Principal> {
+        let _ = (method, params, ctx);
+        None
+    }
+
+    /// Optional: Get the unauthorized error response
+    ///
+    /// Override this if you want custom error messages for denied requests.
+    /// Default returns a generic "Unauthorized" error.
+    fn unauthorized_error(&self, method: &str) -> Response {
+        let _ = method;
+        crate::ResponseBuilder::new()
+            .error(
+                crate::ErrorBuilder::new(crate::error_codes::INTERNAL_ERROR, "Unauthorized")
+                    .build(),
+            )
+            .id(None)
+            .build()
+    }
+}
+
+/// Helper: Always allow all requests (no authentication)
+///
+/// Use this as a placeholder or for development/testing.
+pub struct AllowAll;
+
+impl AuthPolicy for AllowAll {
+    fn can_access(
+        &self,
+        _method: &str,
+        _params: Option<&serde_json::Value>,
+        _ctx: &ConnectionContext,
+    ) -> bool {
+        true
+    }
+}
+
+/// Helper: Deny all requests
+///
+/// Useful for maintenance mode or testing denial paths.
+pub struct DenyAll;
+
+impl AuthPolicy for DenyAll {
+    fn can_access(
+        &self,
+        _method: &str,
+        _params: Option<&serde_json::Value>,
+        _ctx: &ConnectionContext,
+    ) -> bool {
+        false
+    }
+}
+
+/// Per-method scope and role requirements on top of another policy
+///
+/// A call passes when the inner policy accepts it and the caller holds
+/// every scope and role required for the method. Scopes come from
+/// [`ConnectionContext::grant_scopes`] (typically by an authenticator) or
+/// the caller's [`Principal`], roles from the principal only; without one
+/// on the connection, the principal the inner policy
+/// [identifies](AuthPolicy::identify) is used. Methods without requirements
+/// only depend on the inner policy.
+pub struct ScopePolicy<P> {
+    inner: P,
+    required: HashMap<String, Vec<String>>,
+    required_roles: HashMap<String, Vec<String>>,
+}
+
+impl<P: AuthPolicy> ScopePolicy<P> {
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            required: HashMap::new(),
+            required_roles: HashMap::new(),
+        }
+    }
+
+    /// Require `scope` for `method` (may be repeated)
+    pub fn require(mut self, method: impl Into<String>, scope: impl Into<String>) -> Self {
+        self.required
+            .entry(method.into())
+            .or_default()
+            .push(scope.into());
+        self
+    }
+
+    /// Require the principal to have `role` for `method` (may be repeated)
+    pub fn require_role(mut self, method: impl Into<String>, role: impl Into<String>) -> Self {
+        self.required_roles
+            .entry(method.into())
+            .or_default()
+            .push(role.into());
+        self
+    }
+}
+
+impl<P: AuthPolicy> AuthPolicy for ScopePolicy<P> {
+    fn can_access(
+        &self,
+        method: &str,
+        params: Option<&serde_json::Value>,
+        ctx: &ConnectionContext,
+    ) -> bool {
+        if !self.inner.can_access(method, params, ctx) {
+            return false;
+        }
+        let scopes = self.required.get(method);
+        let roles = self.required_roles.get(method);
+        if scopes.is_none() && roles.is_none() {
+            return true;
+        }
+        let identified = match ctx.principal() {
+            Some(_) => None,
+            None => self.inner.identify(method, params, ctx),
+        };
+        let principal = ctx.principal().or(identified.as_ref());
+        scopes.is_none_or(|scopes| {
+            scopes
+                .iter()
+                .all(|scope| ctx.has_scope(scope) || principal.is_some_and(|p| p.has_scope(scope)))
+        }) && roles.is_none_or(|roles| {
+            roles
+                .iter()
+                .all(|role| principal.is_some_and(|p| p.has_role(role)))
+        })
+    }
+
+    fn identify(
+        &self,
+        method: &str,
+        params: Option<&serde_json::Value>,
+        ctx: &ConnectionContext,
+    ) -> Option<Principal> {
+        self.inner.identify(method, params, ctx)
+    }
+
+    fn unauthorized_error(&self, method: &str) -> Response {
+        self.inner.unauthorized_error(method)
+    }
+}
+
+/// Anonymous trial access on top of another policy
+///
+/// Callers the inner policy accepts are unaffected. Anyone else may call the
+/// methods allowed here, but only `max_calls` times and for `duration` after
+/// their first call, counted per client IP. Once the trial is used up the
+/// client must authenticate until `reset_after` has passed since the trial
+/// began. Running out is recorded as a `rate_limited` rejection, so it shows
+/// up in rejection metrics and audit logs.
+///
+/// ```
+/// use ash_rpc::auth::{DenyAll, TrialAccess};
+/// use std::time::Duration;
+///
+/// let policy = TrialAccess::new(DenyAll)
+///     .allow("demo.*")
+///     .max_calls(10)
+///     .duration(Duration::from_secs(60));
+/// # let _ = policy;
+/// ```
+pub struct TrialAccess<P> {
+    inner: P,
+    methods: crate::transports::MethodFilter,
+    max_calls: u32,
+    duration: Duration,
+    reset_after: Duration,
+    trials: Mutex<HashMap<IpAddr, Trial>>,
+}
+
+struct Trial {
+    started: Instant,
+    calls: u32,
+}
+
+impl<P: AuthPolicy> TrialAccess<P> {
+    /// 20 calls over 5 minutes, renewed daily, with no trial methods yet
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            methods: crate::transports::MethodFilter::new(),
+            max_calls: 20,
+            duration: Duration::from_secs(300),
+            reset_after: Duration::from_secs(24 * 60 * 60),
+            trials: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Open methods matching `pattern` (exact or `prefix.*`) to trial users
+    pub fn allow(mut self, pattern: impl Into<String>) -> Self {
+        self.methods = self.methods.allow(pattern);
+        self
+    }
+
+    /// Calls a trial may make
+    pub fn max_calls(mut self, max_calls: u32) -> Self {
+        self.max_calls = max_calls;
+        self
+    }
+
+    /// How long a trial lasts after the first call
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Time after the start of a trial before the client gets a new one
+    pub fn reset_after(mut self, reset_after: Duration) -> Self {
+        self.reset_after = reset_after;
+        self
+    }
+
+    fn admit_trial(&self, method: &str, ctx: &ConnectionContext) -> bool {
+        if self.methods.is_unrestricted() || !self.methods.is_permitted(method) {
+            return false;
+        }
+        let Some(addr) = ctx.remote_addr else {
+            return false;
+        };
+
+        let now = Instant::now();
+        let mut trials = self.trials.lock().unwrap_or_else(|e| e.into_inner());
+        trials.retain(|_, trial| now.duration_since(trial.started) < self.reset_after);
+        let trial = trials.entry(addr.ip()).or_insert_with(|| {
+            tracing::info!(client = %addr.ip(), "anonymous trial started");
+            Trial {
+                started: now,
+                calls: 0,
+            }
+        });
+
+        let expired = now.duration_since(trial.started) >= self.duration;
+        if expired || trial.calls >= self.max_calls {
+            crate::rejection::record(
+                crate::rejection::Rejection::new(crate::rejection::RejectionReason::RateLimited)
+                    .method(method)
+                    .remote_addr(Some(addr))
+                    .origin(ctx.origin.clone())
+                    .detail(if expired {
+                        "anonymous trial expired".to_string()
+                    } else {
+                        format!("anonymous trial limited to {} calls", self.max_calls)
+                    }),
+            );
+            return false;
+        }
+        trial.calls += 1;
+        true
+    }
+}
+
+impl<P: AuthPolicy> AuthPolicy for TrialAccess<P> {
+    fn can_access(
+        &self,
+        method: &str,
+        params: Option<&serde_json::Value>,
+        ctx: &ConnectionContext,
+    ) -> bool {
+        self.inner.can_access(method, params, ctx) || self.admit_trial(method, ctx)
+    }
+
+    fn identify(
+        &self,
+        method: &str,
+        params: Option<&serde_json::Value>,
+        ctx: &ConnectionContext,
+    ) -> Option<Principal> {
+        self.inner.identify(method, params, ctx)
+    }
+
+    fn unauthorized_error(&self, method: &str) -> Response {
+        self.inner.unauthorized_error(method)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allow_all() {
+        let policy = AllowAll;
+        let ctx = ConnectionContext::new();
+        assert!(policy.can_access("any_method", None, &ctx));
+        assert!(policy.can_access(
+            "another_method",
+            Some(&serde_json::json!({"key": "value"})),
+            &ctx
+        ));
+    }
+
+    #[test]
+    fn test_deny_all() {
+        let policy = DenyAll;
+        let ctx = ConnectionContext::new();
+        assert!(!policy.can_access("any_method", None, &ctx));
+        assert!(!policy.can_access(
+            "another_method",
+            Some(&serde_json::json!({"key": "value"})),
+            &ctx
+        ));
+    }
+
+    #[test]
+    fn test_connection_context() {
+        let mut ctx = ConnectionContext::new();
+
+        // Insert and retrieve typed metadata
+        ctx.insert("user_id".to_string(), 42u64);
+        assert_eq!(ctx.get::<u64>("user_id"), Some(&42));
+
+        // Wrong type returns None
+        assert_eq!(ctx.get::<String>("user_id"), None);
+
+        // Non-existent key returns None
+        assert_eq!(ctx.get::<u64>("other"), None);
+    }
+
+    #[test]
+    fn test_connection_context_with_addr() {
+        use std::net::{IpAddr, Ipv4Addr};
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+        let ctx = ConnectionContext::with_addr(addr);
+
+        assert_eq!(ctx.remote_addr, Some(addr));
+        assert_eq!(ctx.metadata.len(), 0);
+    }
+
+    #[test]
+    fn test_connection_context_with_origin() {
+        let ctx = ConnectionContext::new().with_origin("internal");
+        assert_eq!(ctx.origin.as_deref(), Some("internal"));
+        assert!(ConnectionContext::new().origin.is_none());
+    }
+
+    #[test]
+    fn test_connection_context_default() {
+        let ctx = ConnectionContext::default();
+        assert!(ctx.remote_addr.is_none());
+        assert_eq!(ctx.metadata.len(), 0);
+    }
+
+    #[test]
+    fn test_connection_context_multiple_metadata() {
+        let mut ctx = ConnectionContext::new();
+
+        ctx.insert("user_id".to_string(), 123u64);
+        ctx.insert("username".to_string(), String::from("alice"));
+        ctx.insert("is_admin".to_string(), true);
+
+        assert_eq!(ctx.get::<u64>("user_id"), Some(&123));
+        assert_eq!(ctx.get::<String>("username"), Some(&String::from("alice")));
+        assert_eq!(ctx.get::<bool>("is_admin"), Some(&true));
+    }
+
+    #[test]
+    fn test_allow_all_unauthorized_error() {
+        let policy = AllowAll;
+        let response = policy.unauthorized_error("test_method");
+        assert!(response.error.is_some());
+        let error = response.error.unwrap();
+        assert_eq!(error.code, crate::error_codes::INTERNAL_ERROR);
+        assert_eq!(error.message, "Unauthorized");
+    }
+
+    #[test]
+    fn test_deny_all_unauthorized_error() {
+        let policy = DenyAll;
+        let response = policy.unauthorized_error("blocked_method");
+        assert!(response.error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_default_context_extractor() {
+        use std::net::{IpAddr, Ipv4Addr};
+        let extractor = DefaultContextExtractor;
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1)), 9000);
+
+        let ctx = extractor.extract(Some(addr), None).await;
+        assert_eq!(ctx.remote_addr, Some(addr));
+        assert_eq!(ctx.metadata.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_default_context_extractor_no_addr() {
+        let extractor = DefaultContextExtractor;
+        let ctx = extractor.extract(None, None).await;
+        assert!(ctx.remote_addr.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_default_context_extractor_with_metadata() {
+        use std::net::{IpAddr, Ipv4Addr};
+        let extractor = DefaultContextExtractor;
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 3000);
+        let metadata: Arc<dyn Any + Send + Sync> = Arc::new(String::from("test"));
+
+        let ctx = extractor.extract(Some(addr), Some(metadata)).await;
+        assert_eq!(ctx.remote_addr, Some(addr));
+    }
+
+    #[test]
+    fn test_connection_context_clone() {
+        let mut ctx1 = ConnectionContext::new();
+        ctx1.insert("key".to_string(), 100u32);
+
+        let ctx2 = ctx1.clone();
+        assert_eq!(ctx2.get::<u32>("key"), Some(&100));
+    }
+
+    #[test]
+    fn test_trial_access_budget() {
+        use std::net::{IpAddr, Ipv4Addr};
+        let ctx = ConnectionContext::with_addr(SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7)),
+            4000,
+        ));
+        let other = ConnectionContext::with_addr(SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::new(203, 0, 113, 8)),
+            4000,
+        ));
+        let policy = TrialAccess::new(DenyAll).allow("demo.*").max_calls(2);
+
+        assert!(!policy.can_access("admin.reset", None, &ctx));
+        assert!(policy.can_access("demo.echo", None, &ctx));
+        assert!(policy.can_access("demo.echo", None, &ctx));
+        assert!(!policy.can_access("demo.echo", None, &ctx));
+        assert!(policy.can_access("demo.echo", None, &other));
+        assert!(!policy.can_access("demo.echo", None, &ConnectionContext::new()));
+
+        let authenticated = TrialAccess::new(AllowAll).allow("demo.*").max_calls(0);
+        assert!(authenticated.can_access("admin.reset", None, &ctx));
+    }
+
+    #[test]
+    fn test_trial_access_expiry_and_reset() {
+        use std::net::{IpAddr, Ipv4Addr};
+        let ctx = ConnectionContext::with_addr(SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1)),
+            4000,
+        ));
+        let policy = TrialAccess::new(DenyAll)
+            .allow("demo.echo")
+            .duration(Duration::from_millis(20))
+            .reset_after(Duration::from_millis(60));
+
+        assert!(policy.can_access("demo.echo", None, &ctx));
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(!policy.can_access("demo.echo", None, &ctx));
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(policy.can_access("demo.echo", None, &ctx));
+    }
+
+    #[test]
+    fn test_scope_policy() {
+        let policy = ScopePolicy::new(AllowAll)
+            .require("metrics.get", "metrics:read")
+            .require("admin.reset", "admin")
+            .require("admin.reset", "metrics:read");
+
+        let mut ctx = ConnectionContext::new();
+        assert!(policy.can_access("ping", None, &ctx));
+        assert!(!policy.can_access("metrics.get", None, &ctx));
+
+        ctx.grant_scopes(["metrics:read"]);
+        assert!(policy.can_access("metrics.get", None, &ctx));
+        assert!(!policy.can_access("admin.reset", None, &ctx));
+
+        ctx.grant_scopes(["admin"]);
+        assert!(policy.can_access("admin.reset", None, &ctx));
+        assert!(!ScopePolicy::new(DenyAll).can_access("ping", None, &ctx));
+    }
+
+    #[test]
+    fn test_scope_policy_principal() {
+        struct Bearer;
+
+        impl AuthPolicy for Bearer {
+            fn can_access(
+                &self,
+                _: &str,
+                _: Option<&serde_json::Value>,
+                _: &ConnectionContext,
+            ) -> bool {
+                true
+            }
+
+            fn identify(
+                &self,
+                _: &str,
+                params: Option<&serde_json::Value>,
+                _: &ConnectionContext,
+            ) -> Option<Principal> {
+                let token = params?.get("token")?.as_str()?;
+                (token == "ops").then(|| Principal::new("ops-bot").role("operator"))
+            }
+        }
+
+        let policy = ScopePolicy::new(Bearer)
+            .require("invoices.list", "invoices:read")
+            .require_role("admin.reset", "operator");
+
+        let mut ctx = ConnectionContext::new();
+        assert!(!policy.can_access("admin.reset", None, &ctx));
+        let params = serde_json::json!({"token": "ops"});
+        assert!(policy.can_access("admin.reset", Some(&params), &ctx));
+        assert_eq!(
+            policy
+                .identify("admin.reset", Some(&params), &ctx)
+                .map(|p| p.id),
+            Some("ops-bot".to_string())
+        );
+
+        ctx.set_principal(Principal::new("alice").scope("invoices:read"));
+        assert!(policy.can_access("invoices.list", None, &ctx));
+        assert!(!policy.can_access("admin.reset", None, &ctx));
+        assert_eq!(ctx.principal_id(), Some("alice"));
+    }
+}
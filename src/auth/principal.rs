@@ -0,0 +1,103 @@
+//! Authenticated caller identity.
+
+use serde::{Deserialize, Serialize};
+
+/// Who is calling, as established by an auth policy or handshake
+///
+/// Stored on the connection with [`ConnectionContext::set_principal`]
+/// (or produced per call by [`AuthPolicy::identify`]), then read back by
+/// scope and role checks, per-principal rate limits, audit events and
+/// metrics.
+///
+/// [`ConnectionContext::set_principal`]: super::ConnectionContext::set_principal
+/// [`AuthPolicy::identify`]: super::AuthPolicy::identify
+///
+/// ```rust
+/// use ash_rpc::auth::{ConnectionContext, Principal};
+///
+/// let mut ctx = ConnectionContext::new();
+/// ctx.set_principal(
+///     Principal::new("u-1842")
+///         .display_name("Alice")
+///         .role("billing-admin")
+///         .scope("invoices:write")
+///         .auth_method("jwt")
+///         .tenant("acme"),
+/// );
+/// assert_eq!(ctx.principal_id(), Some("u-1842"));
+/// assert!(ctx.has_scope("invoices:write"));
+/// assert!(ctx.has_role("billing-admin"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Principal {
+    /// Stable identifier, e.g. a user id or token subject
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes: Vec<String>,
+    /// How the caller authenticated, e.g. `jwt`, `api_key` or `mtls`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_method: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+}
+
+impl Principal {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            display_name: None,
+            roles: Vec::new(),
+            scopes: Vec::new(),
+            auth_method: None,
+            tenant: None,
+        }
+    }
+
+    pub fn display_name(mut self, name: impl Into<String>) -> Self {
+        self.display_name = Some(name.into());
+        self
+    }
+
+    /// Add a role (may be repeated)
+    pub fn role(mut self, role: impl Into<String>) -> Self {
+        self.roles.push(role.into());
+        self
+    }
+
+    /// Add a scope (may be repeated)
+    pub fn scope(mut self, scope: impl Into<String>) -> Self {
+        self.scopes.push(scope.into());
+        self
+    }
+
+    pub fn scopes<I, S>(mut self, scopes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.scopes.extend(scopes.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn auth_method(mut self, method: impl Into<String>) -> Self {
+        self.auth_method = Some(method.into());
+        self
+    }
+
+    pub fn tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = Some(tenant.into());
+        self
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+}
@@ -0,0 +1,423 @@
+//! Batch execution policies.
+//!
+//! By default every entry of a batch runs no matter how the others fare. A
+//! [`BatchPolicy`] can instead stop at the first failed request, answering
+//! the requests after it with [`DEPENDENT_FAILURE`](error_codes::DEPENDENT_FAILURE),
+//! or run the whole batch as one transaction on a
+//! [`TransactionalContext`](crate::stateful::TransactionalContext).
+//!
+//! Servers set a default policy; a client picks one per batch with the
+//! `ext` member of the batch's first request:
+//!
+//! ```json
+//! [
+//!   {"jsonrpc": "2.0", "method": "debit", "params": [10], "id": 1,
+//!    "ext": {"batch_policy": "abort_on_error"}},
+//!   {"jsonrpc": "2.0", "method": "credit", "params": [10], "id": 2}
+//! ]
+//! ```
+//!
+//! Entries run one after the other unless the processor is given a
+//! [`BatchConcurrency`], which runs up to `limit` entries at once for
+//! batches under [`BatchPolicy::ContinueOnError`]. The other policies need
+//! to see each result before starting the next entry, so their batches
+//! stay sequential.
+
+use crate::{ErrorBuilder, Message, RequestId, Response, error_codes};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Poll;
+
+/// How the entries of a batch depend on each other
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchPolicy {
+    /// Run every entry, failed or not
+    #[default]
+    ContinueOnError,
+    /// Skip the entries after the first failed request
+    AbortOnError,
+    /// Abort on the first failed request and roll back the ones before it;
+    /// only stateful processors with a transactional context support it
+    Transactional,
+}
+
+impl BatchPolicy {
+    /// Policy asked for by the first request of `messages`, `default` if
+    /// it asks for none
+    pub fn requested(messages: &[Message], default: Self) -> Self {
+        messages
+            .iter()
+            .find_map(|message| match message {
+                Message::Request(request) => Some(request),
+                _ => None,
+            })
+            .and_then(|request| request.ext.as_ref()?.batch_policy)
+            .unwrap_or(default)
+    }
+}
+
+/// A default batch policy the processor cannot run
+///
+/// Returned for [`BatchPolicy::Transactional`] by processors without a
+/// transactional context, such as the [`MethodRegistry`](crate::MethodRegistry).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsupportedPolicy(pub BatchPolicy);
+
+impl fmt::Display for UnsupportedPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            BatchPolicy::Transactional => f.write_str(
+                "transactional batches need a StatefulProcessor with a TransactionalContext",
+            ),
+            policy => write!(f, "batch policy {policy:?} is not supported"),
+        }
+    }
+}
+
+impl std::error::Error for UnsupportedPolicy {}
+
+/// Order of the responses of a concurrently processed batch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum BatchOrdering {
+    /// Same order as the requests, which many clients rely on
+    #[default]
+    Request,
+    /// Order in which the entries completed; clients match responses by id
+    Completion,
+}
+
+/// How many entries of one batch may run at once
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BatchConcurrency {
+    limit: usize,
+    ordering: BatchOrdering,
+}
+
+impl BatchConcurrency {
+    /// Run up to `limit` entries at once, answering in request order
+    ///
+    /// # Panics
+    ///
+    /// If `limit` is zero.
+    pub fn new(limit: usize) -> Self {
+        assert!(limit > 0, "batch concurrency limit must be positive");
+        Self {
+            limit,
+            ordering: BatchOrdering::default(),
+        }
+    }
+
+    pub fn ordering(mut self, ordering: BatchOrdering) -> Self {
+        self.ordering = ordering;
+        self
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+}
+
+/// Answer to request `id`, skipped because batch entry `failed_index` failed
+pub fn aborted(id: Option<RequestId>, failed_index: usize) -> Response {
+    let error = ErrorBuilder::new(
+        error_codes::DEPENDENT_FAILURE,
+        "Not executed: an earlier batch entry failed",
+    )
+    .data(serde_json::json!({ "failed_index": failed_index }))
+    .build();
+    Response::error(error, id)
+}
+
+/// Answer to request `id`, executed but rolled back with its transaction
+pub fn rolled_back(id: Option<RequestId>) -> Response {
+    let error = ErrorBuilder::new(
+        error_codes::DEPENDENT_FAILURE,
+        "Rolled back: another batch entry failed",
+    )
+    .build();
+    Response::error(error, id)
+}
+
+/// Answer to a transactional batch sent to a processor without transactions
+pub fn unsupported() -> Response {
+    let error = ErrorBuilder::new(
+        error_codes::INVALID_REQUEST,
+        "Transactional batches are not supported",
+    )
+    .build();
+    Response::error(error, None)
+}
+
+/// Run `messages` in order with `process`, which gets each entry and its
+/// position in the batch
+///
+/// Unless `policy` is [`BatchPolicy::ContinueOnError`], the first error
+/// response stops the batch: later requests are answered with [`aborted`]
+/// and later notifications dropped.
+pub async fn execute<F, Fut>(
+    policy: BatchPolicy,
+    messages: Vec<Message>,
+    mut process: F,
+) -> Vec<Response>
+where
+    F: FnMut(usize, Message) -> Fut,
+    Fut: Future<Output = Option<Response>>,
+{
+    let mut responses = Vec::new();
+    let mut failed = None;
+    for (index, message) in messages.into_iter().enumerate() {
+        if let Some(failed_index) = failed {
+            if let Message::Request(request) = message {
+                responses.push(aborted(request.id, failed_index));
+            }
+            continue;
+        }
+        if let Some(response) = process(index, message).await {
+            if policy != BatchPolicy::ContinueOnError && response.error.is_some() {
+                failed = Some(index);
+            }
+            responses.push(response);
+        }
+    }
+    responses
+}
+
+/// Run `messages` with `process`, up to `concurrency.limit()` at a time
+///
+/// Every entry runs, as under [`BatchPolicy::ContinueOnError`]; responses
+/// come back in the order `concurrency` asks for.
+pub async fn execute_concurrent<F, Fut>(
+    concurrency: BatchConcurrency,
+    messages: Vec<Message>,
+    mut process: F,
+) -> Vec<Response>
+where
+    F: FnMut(usize, Message) -> Fut,
+    Fut: Future<Output = Option<Response>>,
+{
+    let mut pending = messages.into_iter().enumerate();
+    // at most `limit` entries, so polling all of them on every wake-up
+    // stays cheap and keeps the core free of the futures crates
+    let mut running: Vec<(usize, Pin<Box<Fut>>)> = Vec::with_capacity(concurrency.limit);
+    let mut responses = Vec::new();
+    loop {
+        while running.len() < concurrency.limit
+            && let Some((index, message)) = pending.next()
+        {
+            running.push((index, Box::pin(process(index, message))));
+        }
+        if running.is_empty() {
+            break;
+        }
+        let (position, response) = std::future::poll_fn(|cx| {
+            for (position, (_, entry)) in running.iter_mut().enumerate() {
+                if let Poll::Ready(response) = entry.as_mut().poll(cx) {
+                    return Poll::Ready((position, response));
+                }
+            }
+            Poll::Pending
+        })
+        .await;
+        let (index, _) = running.swap_remove(position);
+        responses.extend(response.map(|response| (index, response)));
+    }
+    if concurrency.ordering == BatchOrdering::Request {
+        responses.sort_by_key(|(index, _)| *index);
+    }
+    responses
+        .into_iter()
+        .map(|(_, response)| response)
+        .collect()
+}
+
+/// Ids of the requests of a batch, `None` for its other entries
+pub(crate) fn request_ids(messages: &[Message]) -> Vec<Option<RequestId>> {
+    messages
+        .iter()
+        .map(|message| match message {
+            Message::Request(request) => request.id.clone(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Batch entry each of `responses` answers, given the entries' [`request_ids`]
+///
+/// Responses carrying batch metadata are placed by its index. The others
+/// go to the first request with their id not answered yet, so requests
+/// sharing an id are matched in order.
+pub(crate) fn answered_entries(
+    ids: &[Option<RequestId>],
+    responses: &[Response],
+) -> Vec<Option<usize>> {
+    let mut answered = vec![false; ids.len()];
+    let mut entries: Vec<Option<usize>> = responses
+        .iter()
+        .map(|response| {
+            let index = response.ext.as_ref()?.batch?.index;
+            let id = ids.get(index)?.as_ref();
+            (!answered[index] && id == response.id.as_ref()).then(|| {
+                answered[index] = true;
+                index
+            })
+        })
+        .collect();
+    for (entry, response) in entries.iter_mut().zip(responses) {
+        if entry.is_some() {
+            continue;
+        }
+        let Some(id) = &response.id else {
+            continue;
+        };
+        *entry = (0..ids.len()).find(|&index| !answered[index] && ids[index].as_ref() == Some(id));
+        if let Some(index) = *entry {
+            answered[index] = true;
+        }
+    }
+    entries
+}
+
+/// Response answering each entry of `messages` among `responses`, the
+/// answers to the batch
+#[cfg(any(
+    feature = "audit-logging",
+    feature = "logging",
+    feature = "prometheus",
+    feature = "opentelemetry"
+))]
+pub(crate) fn answers<'a>(
+    messages: &[Message],
+    responses: &'a [Response],
+) -> Vec<Option<&'a Response>> {
+    let mut answers = vec![None; messages.len()];
+    let entries = answered_entries(&request_ids(messages), responses);
+    for (entry, response) in entries.into_iter().zip(responses) {
+        if let Some(entry) = entry {
+            answers[entry] = Some(response);
+        }
+    }
+    answers
+}
+
+/// Put the responses to part of a batch back among the answers to the
+/// entries left out of it
+///
+/// `positions` holds the position in the whole batch of each entry of the
+/// part, `ids` their [`request_ids`], and `others` the answers to the
+/// left-out entries with their positions, in order. Batch indices in
+/// `forwarded` are translated to positions in the whole batch.
+pub(crate) fn merge_answers(
+    positions: &[usize],
+    ids: &[Option<RequestId>],
+    forwarded: Vec<Response>,
+    others: Vec<(usize, Response)>,
+) -> Vec<Response> {
+    let entries = answered_entries(ids, &forwarded);
+    let mut others = others.into_iter().peekable();
+    let mut responses = Vec::with_capacity(forwarded.len() + others.len());
+    for (entry, mut response) in entries.into_iter().zip(forwarded) {
+        if let Some(position) = entry.map(|entry| positions[entry]) {
+            while let Some((_, other)) = others.next_if(|(index, _)| *index < position) {
+                responses.push(other);
+            }
+            // batch metadata counts the entries the inner processor saw
+            if let Some(batch) = response.ext.as_mut().and_then(|ext| ext.batch.as_mut()) {
+                batch.index = position;
+            }
+        }
+        // and so does the failed entry named by an aborted request
+        if let Some(error) = &mut response.error
+            && error.code == error_codes::DEPENDENT_FAILURE
+            && let Some(failed_index) = error
+                .data
+                .as_mut()
+                .and_then(|data| data.get_mut("failed_index"))
+            && let Some(position) = failed_index
+                .as_u64()
+                .and_then(|index| positions.get(usize::try_from(index).ok()?))
+        {
+            *failed_index = (*position).into();
+        }
+        responses.push(response);
+    }
+    responses.extend(others.map(|(_, other)| other));
+    responses
+}
+
+/// Check each entry of a batch in a processor wrapper, then run the
+/// admitted entries as one batch with `forward`
+///
+/// `check` returns `Some` for a refused entry, holding its answer unless
+/// the entry is a notification. Refusals keep their place among the forwarded batch's
+/// responses, and the first request's `ext` travels with the batch. When
+/// the batch asks for a policy other than [`BatchPolicy::ContinueOnError`],
+/// a refused request stops it: the requests after it are answered with
+/// [`aborted`] without being forwarded. Batch indices in the forwarded
+/// batch's responses are translated back to positions in `messages`.
+pub(crate) async fn forward_admitted<C, F, Fut>(
+    messages: Vec<Message>,
+    mut check: C,
+    forward: F,
+) -> Vec<Response>
+where
+    C: FnMut(&Message) -> Option<Option<Response>>,
+    F: FnOnce(Vec<Message>) -> Fut,
+    Fut: Future<Output = Vec<Response>>,
+{
+    let policy = BatchPolicy::requested(&messages, BatchPolicy::ContinueOnError);
+    let ext = messages
+        .iter()
+        .find_map(|message| match message {
+            Message::Request(request) => Some(request.ext.clone()),
+            _ => None,
+        })
+        .flatten();
+    let total = messages.len();
+    let mut admitted = Vec::with_capacity(total);
+    // position in `messages` of each admitted entry
+    let mut positions = Vec::with_capacity(total);
+    let mut refused: Vec<(usize, Response)> = Vec::new();
+    let mut stopped = None;
+    for (index, message) in messages.into_iter().enumerate() {
+        if let Some(failed_index) = stopped {
+            if let Message::Request(request) = message {
+                refused.push((index, aborted(request.id, failed_index)));
+            }
+            continue;
+        }
+        match check(&message) {
+            None => {
+                positions.push(index);
+                admitted.push(message);
+            }
+            Some(Some(refusal)) => {
+                if policy != BatchPolicy::ContinueOnError {
+                    stopped = Some(index);
+                }
+                refused.push((index, refusal));
+            }
+            Some(None) => {}
+        }
+    }
+    if positions.len() == total {
+        return forward(admitted).await;
+    }
+    if let Some(Message::Request(first)) = admitted
+        .iter_mut()
+        .find(|message| matches!(message, Message::Request(_)))
+        && first.ext.is_none()
+    {
+        first.ext = ext;
+    }
+    let ids = request_ids(&admitted);
+    let forwarded = if admitted.is_empty() {
+        Vec::new()
+    } else {
+        forward(admitted).await
+    };
+
+    merge_answers(&positions, &ids, forwarded, refused)
+}
@@ -0,0 +1,204 @@
+//! Borrowed views of JSON-RPC messages.
+//!
+//! Transports receive a full line or frame before dispatching it. The types
+//! here deserialize directly from that buffer: the method name borrows from
+//! the input when it contains no escapes, and `params`/`id` stay as
+//! unparsed [`RawValue`] slices. Transports inspect these views first and
+//! only build an owned [`Message`] once the request is actually dispatched.
+
+use crate::{Message, MessageProcessor, Notification, Request, RequestExtensions};
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use std::borrow::Cow;
+
+/// Request borrowing from the input buffer
+#[derive(Debug, Deserialize)]
+pub struct RequestRef<'a> {
+    #[serde(borrow)]
+    pub jsonrpc: Cow<'a, str>,
+    #[serde(borrow)]
+    pub method: Cow<'a, str>,
+    #[serde(borrow, default)]
+    pub params: Option<&'a RawValue>,
+    #[serde(borrow, default)]
+    pub id: Option<&'a RawValue>,
+    #[serde(borrow, default)]
+    pub correlation_id: Option<Cow<'a, str>>,
+    #[serde(default)]
+    pub ext: Option<RequestExtensions>,
+}
+
+impl<'a> RequestRef<'a> {
+    /// Deserialize a request view from `input`
+    pub fn parse(input: &'a str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(input)
+    }
+
+    /// Method name without copying
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// Raw JSON text of the params, if present
+    pub fn raw_params(&self) -> Option<&'a str> {
+        self.params.map(RawValue::get)
+    }
+
+    /// Build an owned request, parsing params and id
+    pub fn into_owned(self) -> Result<Request, serde_json::Error> {
+        Ok(Request {
+            jsonrpc: self.jsonrpc.into_owned(),
+            method: self.method.into_owned(),
+            params: self.params.map(parse_raw).transpose()?,
+            id: self.id.map(parse_raw).transpose()?,
+            correlation_id: self.correlation_id.map(Cow::into_owned),
+            ext: self.ext,
+        })
+    }
+}
+
+/// Notification borrowing from the input buffer
+#[derive(Debug, Deserialize)]
+pub struct NotificationRef<'a> {
+    #[serde(borrow)]
+    pub jsonrpc: Cow<'a, str>,
+    #[serde(borrow)]
+    pub method: Cow<'a, str>,
+    #[serde(borrow, default)]
+    pub params: Option<&'a RawValue>,
+}
+
+impl<'a> NotificationRef<'a> {
+    pub fn parse(input: &'a str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(input)
+    }
+
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    pub fn into_owned(self) -> Result<Notification, serde_json::Error> {
+        Ok(Notification {
+            jsonrpc: self.jsonrpc.into_owned(),
+            method: self.method.into_owned(),
+            params: self.params.map(parse_raw).transpose()?,
+        })
+    }
+}
+
+fn parse_raw(raw: &RawValue) -> Result<serde_json::Value, serde_json::Error> {
+    serde_json::from_str(raw.get())
+}
+
+/// Parse a transport frame into an owned [`Message`].
+///
+/// Request-shaped input goes through [`RequestRef`]; anything else falls
+/// back to the regular untagged deserializer so the resulting variants are
+/// identical to `serde_json::from_str::<Message>`.
+pub fn parse_message(input: &str) -> Result<Message, serde_json::Error> {
+    match RequestRef::parse(input) {
+        Ok(request) => request.into_owned().map(Message::Request),
+        Err(_) => serde_json::from_str(input),
+    }
+}
+
+/// Outcome of preparing a transport frame for dispatch
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug)]
+pub enum Prepared {
+    /// Response bytes answered from the processor's static response cache
+    Cached(String),
+    /// Owned message to dispatch
+    Message(Message),
+    /// Entries of a batch, parsed one by one when dispatched
+    Batch(Vec<Box<RawValue>>),
+}
+
+/// Parse a frame, answering parameterless requests from the processor's
+/// static responses when available.
+///
+/// Only requests made with [`DEFAULT_VERSION`](crate::versioning::DEFAULT_VERSION)
+/// take the cached path; other versions are dispatched so they are checked
+/// and answered like any other call.
+pub fn prepare(
+    input: &str,
+    processor: &dyn MessageProcessor,
+) -> Result<Prepared, serde_json::Error> {
+    match RequestRef::parse(input) {
+        Ok(request) => {
+            if request.params.is_none()
+                && request.jsonrpc == crate::versioning::DEFAULT_VERSION
+                && let Some(cached) = processor.static_response(request.method(), request.id)
+            {
+                return Ok(Prepared::Cached(cached));
+            }
+            request
+                .into_owned()
+                .map(|request| Prepared::Message(Message::Request(request)))
+        }
+        Err(_) if input.trim_start().starts_with('[') => {
+            serde_json::from_str(input).map(Prepared::Batch)
+        }
+        Err(_) => serde_json::from_str(input).map(Prepared::Message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_request_ref_borrows() {
+        let input = r#"{"jsonrpc":"2.0","method":"add","params":[1, 2],"id":7}"#;
+        let request = RequestRef::parse(input).unwrap();
+
+        assert!(matches!(request.method, Cow::Borrowed("add")));
+        assert_eq!(request.raw_params(), Some("[1, 2]"));
+
+        let owned = request.into_owned().unwrap();
+        assert_eq!(owned.method, "add");
+        assert_eq!(owned.params, Some(json!([1, 2])));
+        assert_eq!(owned.id, Some(json!(7)));
+    }
+
+    #[test]
+    fn test_request_ref_escaped_method() {
+        let input = r#"{"jsonrpc":"2.0","method":"a\"b","id":1}"#;
+        let request = RequestRef::parse(input).unwrap();
+        assert_eq!(request.method(), "a\"b");
+        assert!(matches!(request.method, Cow::Owned(_)));
+    }
+
+    #[test]
+    fn test_notification_ref() {
+        let input = r#"{"jsonrpc":"2.0","method":"log","params":{"level":"info"}}"#;
+        let notification = NotificationRef::parse(input).unwrap().into_owned().unwrap();
+        assert_eq!(notification.method, "log");
+        assert_eq!(notification.params, Some(json!({"level": "info"})));
+    }
+
+    #[test]
+    fn test_parse_message_matches_owned_parser() {
+        let inputs = [
+            r#"{"jsonrpc":"2.0","method":"ping","id":"a","correlation_id":"c-1"}"#,
+            r#"{"jsonrpc":"2.0","method":"notify"}"#,
+            r#"{"jsonrpc":"2.0","result":42,"id":1}"#,
+            r#"{"jsonrpc":"2.0","error":{"code":-32600,"message":"bad"},"id":null}"#,
+        ];
+        for input in inputs {
+            let borrowed = parse_message(input).unwrap();
+            let owned: Message = serde_json::from_str(input).unwrap();
+            assert_eq!(
+                serde_json::to_value(&borrowed).unwrap(),
+                serde_json::to_value(&owned).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_message_invalid() {
+        assert!(parse_message("{not json").is_err());
+        assert!(parse_message(r#"{"method": 5}"#).is_err());
+    }
+}
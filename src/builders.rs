@@ -8,6 +8,7 @@ pub struct RequestBuilder {
     params: Option<serde_json::Value>,
     id: Option<RequestId>,
     correlation_id: Option<String>,
+    ext: Option<RequestExtensions>,
 }
 
 impl RequestBuilder {
@@ -18,6 +19,7 @@ impl RequestBuilder {
             params: None,
             id: None,
             correlation_id: Some(uuid::Uuid::new_v4().to_string()),
+            ext: None,
         }
     }
 
@@ -39,6 +41,12 @@ impl RequestBuilder {
         self
     }
 
+    /// Ask for the batch this request opens to run under `policy`
+    pub fn batch_policy(mut self, policy: crate::batch_policy::BatchPolicy) -> Self {
+        self.ext.get_or_insert_default().batch_policy = Some(policy);
+        self
+    }
+
     /// Build the request
     pub fn build(self) -> Request {
         Request {
@@ -47,6 +55,7 @@ impl RequestBuilder {
             params: self.params,
             id: self.id,
             correlation_id: self.correlation_id,
+            ext: self.ext,
         }
     }
 }
@@ -100,6 +109,7 @@ impl ResponseBuilder {
             error: self.error,
             id: self.id,
             correlation_id: self.correlation_id,
+            ext: None,
         }
     }
 }
@@ -181,6 +191,11 @@ pub struct SecurityConfigBuilder {
     max_request_size: usize,
     request_timeout: std::time::Duration,
     idle_timeout: std::time::Duration,
+    max_json_depth: usize,
+    max_json_nodes: usize,
+    max_connection_lifetime: std::time::Duration,
+    max_requests_per_connection: usize,
+    drain_timeout: std::time::Duration,
 }
 
 #[cfg(any(feature = "tcp", feature = "tcp-stream", feature = "tcp-stream-tls"))]
@@ -192,6 +207,11 @@ impl SecurityConfigBuilder {
             max_request_size: 1024 * 1024, // 1 MB
             request_timeout: std::time::Duration::from_secs(30),
             idle_timeout: std::time::Duration::from_secs(300), // 5 minutes
+            max_json_depth: 64,
+            max_json_nodes: 100_000,
+            max_connection_lifetime: std::time::Duration::ZERO,
+            max_requests_per_connection: 0,
+            drain_timeout: std::time::Duration::from_secs(30),
         }
     }
 
@@ -259,6 +279,72 @@ impl SecurityConfigBuilder {
         self
     }
 
+    /// Set maximum JSON nesting depth
+    ///
+    /// # Arguments
+    /// * `depth` - Maximum depth of nested objects and arrays (1-1024)
+    ///
+    /// # Panics
+    /// Panics if depth is 0 or greater than 1024
+    pub fn max_json_depth(mut self, depth: usize) -> Self {
+        assert!(
+            (1..=1024).contains(&depth),
+            "max_json_depth must be between 1 and 1024"
+        );
+        self.max_json_depth = depth;
+        self
+    }
+
+    /// Set maximum number of JSON values per request
+    ///
+    /// # Arguments
+    /// * `nodes` - Maximum number of values, object keys included (at least 16)
+    ///
+    /// # Panics
+    /// Panics if nodes is less than 16
+    pub fn max_json_nodes(mut self, nodes: usize) -> Self {
+        assert!(nodes >= 16, "max_json_nodes must be at least 16");
+        self.max_json_nodes = nodes;
+        self
+    }
+
+    /// Recycle connections after a maximum lifetime
+    ///
+    /// # Arguments
+    /// * `lifetime` - Lifetime after which the connection is closed between
+    ///   requests (at least 1 second)
+    ///
+    /// # Panics
+    /// Panics if lifetime is less than 1 second
+    pub fn max_connection_lifetime(mut self, lifetime: std::time::Duration) -> Self {
+        assert!(
+            lifetime.as_secs() >= 1,
+            "max_connection_lifetime must be at least 1 second"
+        );
+        self.max_connection_lifetime = lifetime;
+        self
+    }
+
+    /// Recycle connections after a number of requests
+    ///
+    /// # Panics
+    /// Panics if requests is 0
+    pub fn max_requests_per_connection(mut self, requests: usize) -> Self {
+        assert!(
+            requests > 0,
+            "max_requests_per_connection must be greater than 0"
+        );
+        self.max_requests_per_connection = requests;
+        self
+    }
+
+    /// Keep writing owed responses this long after the client stopped
+    /// sending (zero = unlimited)
+    pub fn drain_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
     /// Build the security configuration with validation
     pub fn build(self) -> crate::transports::SecurityConfig {
         tracing::info!(
@@ -266,6 +352,8 @@ impl SecurityConfigBuilder {
             max_request_size = self.max_request_size,
             request_timeout_secs = self.request_timeout.as_secs(),
             idle_timeout_secs = self.idle_timeout.as_secs(),
+            max_json_depth = self.max_json_depth,
+            max_json_nodes = self.max_json_nodes,
             "creating security configuration"
         );
 
@@ -274,6 +362,11 @@ impl SecurityConfigBuilder {
             max_request_size: self.max_request_size,
             request_timeout: self.request_timeout,
             idle_timeout: self.idle_timeout,
+            max_json_depth: self.max_json_depth,
+            max_json_nodes: self.max_json_nodes,
+            max_connection_lifetime: self.max_connection_lifetime,
+            max_requests_per_connection: self.max_requests_per_connection,
+            drain_timeout: self.drain_timeout,
         }
     }
 }
@@ -0,0 +1,381 @@
+//! Built-in method groups.
+//!
+//! The crate ships a number of optional built-in methods (healthcheck,
+//! discovery, admin, diagnostics and cancellation). [`BuiltinMethods`] is a small
+//! bitflag-style set used to switch each group on or off independently, and
+//! [`BuiltinConfig`] additionally allows renaming a group's namespace so the
+//! built-ins never collide with user methods.
+//!
+//! ```rust
+//! use ash_rpc::builtins::{BuiltinConfig, BuiltinMethods};
+//!
+//! let config = BuiltinConfig::new(BuiltinMethods::HEALTHCHECK | BuiltinMethods::DISCOVERY)
+//!     .namespace(BuiltinMethods::DISCOVERY, "meta");
+//!
+//! assert!(config.is_enabled(BuiltinMethods::DISCOVERY));
+//! assert!(!config.is_enabled(BuiltinMethods::ADMIN));
+//! assert_eq!(config.method_name(BuiltinMethods::DISCOVERY, "discover"), "meta.discover");
+//! ```
+
+use crate::traits::JsonRPCMethod;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::{BitAnd, BitOr, BitOrAssign, Not};
+
+/// Set of built-in method groups
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct BuiltinMethods(u8);
+
+impl BuiltinMethods {
+    /// `healthcheck`
+    pub const HEALTHCHECK: Self = Self(1 << 0);
+    /// `rpc.*` introspection methods
+    pub const DISCOVERY: Self = Self(1 << 1);
+    /// `admin.*` operational methods
+    pub const ADMIN: Self = Self(1 << 2);
+    /// `diagnostics.*` methods
+    pub const DIAGNOSTICS: Self = Self(1 << 3);
+    /// `rpc.cancel` and `$/cancelRequest`, see [`crate::cancellation`]
+    pub const CANCELLATION: Self = Self(1 << 4);
+
+    const GROUPS: [(Self, &'static str); 5] = [
+        (Self::HEALTHCHECK, "healthcheck"),
+        (Self::DISCOVERY, "discovery"),
+        (Self::ADMIN, "admin"),
+        (Self::DIAGNOSTICS, "diagnostics"),
+        (Self::CANCELLATION, "cancellation"),
+    ];
+
+    /// No groups enabled
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Every group enabled
+    pub const fn all() -> Self {
+        Self(
+            Self::HEALTHCHECK.0
+                | Self::DISCOVERY.0
+                | Self::ADMIN.0
+                | Self::DIAGNOSTICS.0
+                | Self::CANCELLATION.0,
+        )
+    }
+
+    pub const fn bits(&self) -> u8 {
+        self.0
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns true if all groups in `other` are enabled
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+
+    pub fn remove(&mut self, other: Self) {
+        self.0 &= !other.0;
+    }
+
+    /// Default namespace prefix of a single group
+    pub fn default_namespace(&self) -> &'static str {
+        match *self {
+            Self::DISCOVERY | Self::CANCELLATION => "rpc",
+            Self::ADMIN => "admin",
+            Self::DIAGNOSTICS => "diagnostics",
+            _ => "",
+        }
+    }
+}
+
+impl BitOr for BuiltinMethods {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for BuiltinMethods {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for BuiltinMethods {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl Not for BuiltinMethods {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(!self.0 & Self::all().0)
+    }
+}
+
+impl fmt::Debug for BuiltinMethods {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = Self::GROUPS
+            .iter()
+            .filter(|(group, _)| self.contains(*group))
+            .map(|(_, name)| *name)
+            .collect();
+        write!(f, "BuiltinMethods({})", names.join(" | "))
+    }
+}
+
+/// Which built-in groups a registry exposes and under which namespace
+#[derive(Debug, Clone, Default)]
+pub struct BuiltinConfig {
+    enabled: BuiltinMethods,
+    namespaces: HashMap<BuiltinMethods, String>,
+    service: Option<(String, String)>,
+}
+
+impl BuiltinConfig {
+    pub fn new(enabled: BuiltinMethods) -> Self {
+        Self {
+            enabled,
+            namespaces: HashMap::new(),
+            service: None,
+        }
+    }
+
+    /// Configuration with no built-ins, the registry default
+    pub fn none() -> Self {
+        Self::new(BuiltinMethods::empty())
+    }
+
+    /// Configuration with every built-in group enabled
+    pub fn all() -> Self {
+        Self::new(BuiltinMethods::all())
+    }
+
+    pub fn enable(mut self, groups: BuiltinMethods) -> Self {
+        self.enabled.insert(groups);
+        self
+    }
+
+    pub fn disable(mut self, groups: BuiltinMethods) -> Self {
+        self.enabled.remove(groups);
+        self
+    }
+
+    /// Rename the namespace of a single group; an empty prefix drops it
+    pub fn namespace(mut self, group: BuiltinMethods, prefix: impl Into<String>) -> Self {
+        self.namespaces.insert(group, prefix.into());
+        self
+    }
+
+    /// Title and version `rpc.discover` reports for the service
+    pub fn service_info(mut self, title: impl Into<String>, version: impl Into<String>) -> Self {
+        self.service = Some((title.into(), version.into()));
+        self
+    }
+
+    /// Title and version of the service, `JSON-RPC API` 1.0.0 unless set
+    pub fn service(&self) -> (&str, &str) {
+        self.service
+            .as_ref()
+            .map_or(("JSON-RPC API", "1.0.0"), |(title, version)| {
+                (title.as_str(), version.as_str())
+            })
+    }
+
+    pub fn enabled(&self) -> BuiltinMethods {
+        self.enabled
+    }
+
+    pub fn is_enabled(&self, group: BuiltinMethods) -> bool {
+        !group.is_empty() && self.enabled.contains(group)
+    }
+
+    /// Effective namespace prefix of a group
+    pub fn namespace_of(&self, group: BuiltinMethods) -> &str {
+        self.namespaces
+            .get(&group)
+            .map(String::as_str)
+            .unwrap_or_else(|| group.default_namespace())
+    }
+
+    /// Fully qualified wire name of a built-in method
+    pub fn method_name(&self, group: BuiltinMethods, name: &str) -> String {
+        let prefix = self.namespace_of(group);
+        if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{prefix}.{name}")
+        }
+    }
+}
+
+/// Exposes a method under a different wire name.
+pub struct RenamedMethod {
+    name: String,
+    inner: Box<dyn JsonRPCMethod>,
+}
+
+impl RenamedMethod {
+    pub fn new(name: String, inner: Box<dyn JsonRPCMethod>) -> Self {
+        Self { name, inner }
+    }
+}
+
+#[crate::async_trait]
+impl JsonRPCMethod for RenamedMethod {
+    fn method_name(&self) -> &str {
+        &self.name
+    }
+
+    async fn call(
+        &self,
+        params: Option<serde_json::Value>,
+        id: Option<crate::RequestId>,
+    ) -> crate::Response {
+        self.inner.call(params, id).await
+    }
+
+    async fn call_with_context(
+        &self,
+        params: Option<serde_json::Value>,
+        id: Option<crate::RequestId>,
+        ctx: &crate::CallContext<'_>,
+    ) -> crate::Response {
+        self.inner.call_with_context(params, id, ctx).await
+    }
+
+    fn openapi_components(&self) -> crate::OpenApiMethodSpec {
+        let mut spec = self.inner.openapi_components();
+        spec.method_name = self.name.clone();
+        spec
+    }
+
+    fn security(&self) -> crate::method_security::MethodSecurity {
+        self.inner.security()
+    }
+
+    fn is_builtin(&self) -> bool {
+        self.inner.is_builtin()
+    }
+}
+
+/// Wrap `method` so it answers to its name within `group`'s namespace
+pub fn namespaced(
+    config: &BuiltinConfig,
+    group: BuiltinMethods,
+    method: Box<dyn JsonRPCMethod>,
+) -> Box<dyn JsonRPCMethod> {
+    let name = config.method_name(group, method.method_name());
+    if name == method.method_name() {
+        method
+    } else {
+        Box::new(RenamedMethod::new(name, method))
+    }
+}
+
+/// Instantiate the standalone built-in methods enabled by `config`.
+///
+/// Built-ins that need access to the registry itself are dispatched by the
+/// registry and are not part of this list.
+pub(crate) fn builtin_methods(config: &BuiltinConfig) -> Vec<Box<dyn JsonRPCMethod>> {
+    let mut methods: Vec<Box<dyn JsonRPCMethod>> = Vec::new();
+
+    #[cfg(feature = "healthcheck")]
+    if config.is_enabled(BuiltinMethods::HEALTHCHECK) {
+        methods.push(namespaced(
+            config,
+            BuiltinMethods::HEALTHCHECK,
+            Box::new(crate::healthcheck::HealthcheckMethod::new()),
+        ));
+    }
+
+    if config.is_enabled(BuiltinMethods::DIAGNOSTICS) {
+        methods.push(namespaced(
+            config,
+            BuiltinMethods::DIAGNOSTICS,
+            Box::new(crate::transports::codec_stats::CodecDiagnosticsMethod),
+        ));
+        methods.push(namespaced(
+            config,
+            BuiltinMethods::DIAGNOSTICS,
+            Box::new(crate::resource_usage::ResourceDiagnosticsMethod),
+        ));
+    }
+
+    methods
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flag_operations() {
+        let mut flags = BuiltinMethods::HEALTHCHECK | BuiltinMethods::ADMIN;
+        assert!(flags.contains(BuiltinMethods::HEALTHCHECK));
+        assert!(!flags.contains(BuiltinMethods::DISCOVERY));
+
+        flags.remove(BuiltinMethods::ADMIN);
+        assert_eq!(flags, BuiltinMethods::HEALTHCHECK);
+
+        flags |= BuiltinMethods::DIAGNOSTICS;
+        assert!(flags.contains(BuiltinMethods::DIAGNOSTICS));
+        assert_eq!(!BuiltinMethods::empty(), BuiltinMethods::all());
+    }
+
+    #[test]
+    fn test_debug_lists_groups() {
+        let flags = BuiltinMethods::HEALTHCHECK | BuiltinMethods::DISCOVERY;
+        assert_eq!(
+            format!("{flags:?}"),
+            "BuiltinMethods(healthcheck | discovery)"
+        );
+    }
+
+    #[test]
+    fn test_config_namespaces() {
+        let config = BuiltinConfig::all()
+            .disable(BuiltinMethods::ADMIN)
+            .namespace(BuiltinMethods::HEALTHCHECK, "sys");
+
+        assert!(!config.is_enabled(BuiltinMethods::ADMIN));
+        assert!(config.is_enabled(BuiltinMethods::DISCOVERY));
+        assert_eq!(
+            config.method_name(BuiltinMethods::HEALTHCHECK, "healthcheck"),
+            "sys.healthcheck"
+        );
+        assert_eq!(
+            config.method_name(BuiltinMethods::DISCOVERY, "methods"),
+            "rpc.methods"
+        );
+
+        let renamed = namespaced(
+            &config.namespace(BuiltinMethods::DIAGNOSTICS, "sys"),
+            BuiltinMethods::DIAGNOSTICS,
+            Box::new(crate::resource_usage::ResourceDiagnosticsMethod),
+        );
+        assert!(renamed.method_name().starts_with("sys."));
+        assert_eq!(
+            renamed.openapi_components().method_name,
+            renamed.method_name()
+        );
+    }
+
+    #[test]
+    fn test_default_is_empty() {
+        let config = BuiltinConfig::default();
+        assert!(config.enabled().is_empty());
+        assert!(builtin_methods(&config).is_empty());
+    }
+}
@@ -0,0 +1,267 @@
+//! In-process LRU cache with per-entry expiry
+
+use super::{Cache, CacheError};
+use serde_json::Value;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+type Clock = Arc<dyn Fn() -> SystemTime + Send + Sync>;
+
+struct Entry {
+    value: Value,
+    /// Identifies this version of the entry in `expiry`
+    version: u64,
+    /// Key of the entry in `recency`
+    used: u64,
+}
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<String, Entry>,
+    /// Last use of each entry, oldest first
+    recency: BTreeMap<u64, String>,
+    /// Expiry times; stale items whose entry changed since are skipped
+    expiry: BinaryHeap<Reverse<(u64, u64, String)>>,
+    next_tick: u64,
+}
+
+impl State {
+    fn purge_expired(&mut self, now_ms: u64) {
+        while let Some(Reverse((expires_ms, version, _))) = self.expiry.peek() {
+            if *expires_ms > now_ms {
+                break;
+            }
+            let version = *version;
+            if let Some(Reverse((_, _, key))) = self.expiry.pop()
+                && self
+                    .entries
+                    .get(&key)
+                    .is_some_and(|entry| entry.version == version)
+                && let Some(entry) = self.entries.remove(&key)
+            {
+                self.recency.remove(&entry.used);
+            }
+        }
+    }
+
+    fn touch(&mut self, key: &str) {
+        let tick = self.next_tick;
+        self.next_tick += 1;
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.recency.remove(&entry.used);
+            self.recency.insert(tick, key.to_string());
+            entry.used = tick;
+        }
+    }
+
+    fn store(&mut self, key: &str, value: Value, expires_ms: Option<u64>) {
+        let tick = self.next_tick;
+        self.next_tick += 1;
+        if let Some(old) = self.entries.insert(
+            key.to_string(),
+            Entry {
+                value,
+                version: tick,
+                used: tick,
+            },
+        ) {
+            self.recency.remove(&old.used);
+        }
+        self.recency.insert(tick, key.to_string());
+        if let Some(expires_ms) = expires_ms {
+            self.expiry
+                .push(Reverse((expires_ms, tick, key.to_string())));
+        }
+    }
+}
+
+/// Thread-safe in-memory [`Cache`] bounded to a number of entries
+///
+/// When full, the least recently used entry is evicted, unless
+/// [`reject_when_full`](Self::reject_when_full) is set: then inserts fail
+/// with [`CacheError::Full`] until entries expire. Security-relevant users
+/// such as nonce tracking want the latter, since forgetting a live entry
+/// would let it be reused.
+pub struct MemoryCache {
+    max_entries: usize,
+    evict: bool,
+    clock: Clock,
+    state: Mutex<State>,
+}
+
+impl MemoryCache {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            evict: true,
+            clock: Arc::new(SystemTime::now),
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Fail inserts with [`CacheError::Full`] instead of evicting
+    pub fn reject_when_full(mut self) -> Self {
+        self.evict = false;
+        self
+    }
+
+    /// Time source for expiry, for simulations and tests
+    pub fn with_clock<F>(mut self, clock: F) -> Self
+    where
+        F: Fn() -> SystemTime + Send + Sync + 'static,
+    {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        let now_ms = self.now_ms();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.purge_expired(now_ms);
+        state.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn now_ms(&self) -> u64 {
+        (self.clock)()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0)
+    }
+
+    fn insert_sync(
+        &self,
+        key: &str,
+        value: Value,
+        ttl: Option<Duration>,
+        only_if_absent: bool,
+    ) -> Result<bool, CacheError> {
+        let now_ms = self.now_ms();
+        let expires_ms = ttl.map(|ttl| now_ms.saturating_add(ttl.as_millis() as u64));
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.purge_expired(now_ms);
+
+        let present = state.entries.contains_key(key);
+        if present && only_if_absent {
+            return Ok(false);
+        }
+        if !present && state.entries.len() >= self.max_entries {
+            if !self.evict || self.max_entries == 0 {
+                return Err(CacheError::Full);
+            }
+            if let Some((_, oldest)) = state.recency.pop_first() {
+                state.entries.remove(&oldest);
+            }
+        }
+        state.store(key, value, expires_ms);
+        Ok(true)
+    }
+}
+
+#[async_trait::async_trait]
+impl Cache for MemoryCache {
+    async fn get(&self, key: &str) -> Result<Option<Value>, CacheError> {
+        let now_ms = self.now_ms();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.purge_expired(now_ms);
+        state.touch(key);
+        Ok(state.entries.get(key).map(|entry| entry.value.clone()))
+    }
+
+    async fn insert(
+        &self,
+        key: &str,
+        value: Value,
+        ttl: Option<Duration>,
+    ) -> Result<(), CacheError> {
+        self.insert_sync(key, value, ttl, false).map(|_| ())
+    }
+
+    async fn insert_if_absent(
+        &self,
+        key: &str,
+        value: Value,
+        ttl: Option<Duration>,
+    ) -> Result<bool, CacheError> {
+        self.insert_sync(key, value, ttl, true)
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), CacheError> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(entry) = state.entries.remove(key) {
+            state.recency.remove(&entry.used);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    fn manual_clock() -> (Arc<AtomicU64>, impl Fn() -> SystemTime + Send + Sync) {
+        let secs = Arc::new(AtomicU64::new(1_000));
+        let clock = Arc::clone(&secs);
+        (secs, move || {
+            UNIX_EPOCH + Duration::from_secs(clock.load(Ordering::SeqCst))
+        })
+    }
+
+    #[tokio::test]
+    async fn test_lru_eviction() {
+        let cache = MemoryCache::new(2);
+        cache.insert("a", json!(1), None).await.unwrap();
+        cache.insert("b", json!(2), None).await.unwrap();
+        assert_eq!(cache.get("a").await.unwrap(), Some(json!(1)));
+
+        cache.insert("c", json!(3), None).await.unwrap();
+        assert_eq!(cache.get("b").await.unwrap(), None);
+        assert_eq!(cache.get("a").await.unwrap(), Some(json!(1)));
+        assert_eq!(cache.len(), 2);
+
+        cache.remove("a").await.unwrap();
+        assert_eq!(cache.get("a").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_ttl_and_insert_if_absent() {
+        let (secs, clock) = manual_clock();
+        let cache = MemoryCache::new(10).with_clock(clock);
+        let ttl = Some(Duration::from_secs(30));
+
+        assert!(cache.insert_if_absent("k", json!("v1"), ttl).await.unwrap());
+        assert!(!cache.insert_if_absent("k", json!("v2"), ttl).await.unwrap());
+        // reading does not extend the lifetime
+        secs.store(1_020, Ordering::SeqCst);
+        assert_eq!(cache.get("k").await.unwrap(), Some(json!("v1")));
+
+        secs.store(1_030, Ordering::SeqCst);
+        assert_eq!(cache.get("k").await.unwrap(), None);
+        assert!(cache.insert_if_absent("k", json!("v2"), ttl).await.unwrap());
+        assert!(!cache.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reject_when_full() {
+        let (secs, clock) = manual_clock();
+        let cache = MemoryCache::new(1).reject_when_full().with_clock(clock);
+        let ttl = Some(Duration::from_secs(10));
+
+        cache.insert("a", json!(1), ttl).await.unwrap();
+        assert_eq!(
+            cache.insert("b", json!(2), ttl).await,
+            Err(CacheError::Full)
+        );
+        cache.insert("a", json!(3), ttl).await.unwrap();
+
+        secs.store(1_010, Ordering::SeqCst);
+        cache.insert("b", json!(2), ttl).await.unwrap();
+    }
+}
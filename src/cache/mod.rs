@@ -0,0 +1,82 @@
+//! Bounded, shared key/value caching for server subsystems
+//!
+//! Nonce replay protection, idempotency keys, response caching and session
+//! storage all need the same thing: a concurrent map whose entries expire
+//! and whose size is capped. [`Cache`] is that abstraction; subsystems take
+//! an `Arc<dyn Cache>` so one backend can serve all of them.
+//!
+//! - [`MemoryCache`]: in-process LRU with per-entry TTL
+//! - [`RedisCache`]: shared between server instances (`redis-cache` feature)
+//!
+//! ```rust
+//! use ash_rpc::cache::{Cache, MemoryCache};
+//! use ash_rpc::replay::ReplayGuard;
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//!
+//! let cache: Arc<dyn Cache> = Arc::new(MemoryCache::new(100_000).reject_when_full());
+//! let guard = ReplayGuard::new(Duration::from_secs(300))
+//!     .protect("account.debit")
+//!     .with_cache(Arc::clone(&cache));
+//! # let _ = guard;
+//! ```
+
+mod memory;
+#[cfg(feature = "redis-cache")]
+mod redis;
+
+pub use memory::*;
+#[cfg(feature = "redis-cache")]
+pub use redis::*;
+
+use serde_json::Value;
+use std::fmt;
+use std::time::Duration;
+
+/// Why a cache operation failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheError {
+    /// The cache is at capacity and configured not to evict
+    Full,
+    /// The backend could not be reached or answered unexpectedly
+    Backend(String),
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheError::Full => write!(f, "cache is full"),
+            CacheError::Backend(e) => write!(f, "cache backend error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for CacheError {}
+
+/// A bounded concurrent key/value store with optional expiry
+///
+/// `ttl: None` keeps an entry until it is removed or evicted.
+#[async_trait::async_trait]
+pub trait Cache: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<Value>, CacheError>;
+
+    /// Store `value`, replacing any previous entry
+    async fn insert(
+        &self,
+        key: &str,
+        value: Value,
+        ttl: Option<Duration>,
+    ) -> Result<(), CacheError>;
+
+    /// Store `value` only if `key` is absent; returns whether it was stored
+    ///
+    /// Atomic per key, so concurrent callers agree on a single winner.
+    async fn insert_if_absent(
+        &self,
+        key: &str,
+        value: Value,
+        ttl: Option<Duration>,
+    ) -> Result<bool, CacheError>;
+
+    async fn remove(&self, key: &str) -> Result<(), CacheError>;
+}
@@ -0,0 +1,276 @@
+//! Redis-backed cache shared between server instances
+//!
+//! Speaks the small subset of RESP needed for `GET`, `SET` (with `PX` and
+//! `NX`) and `DEL` over a single connection, reconnecting after errors.
+//! Values are stored as JSON strings.
+
+use super::{Cache, CacheError};
+use serde_json::Value;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+
+/// [`Cache`] stored in Redis
+///
+/// Capacity and eviction are governed by the server's `maxmemory` settings;
+/// [`CacheError::Full`] is never returned.
+pub struct RedisCache {
+    addr: String,
+    password: Option<String>,
+    key_prefix: String,
+    connection: Mutex<Option<BufReader<TcpStream>>>,
+}
+
+enum Reply {
+    Ok,
+    Bulk(Option<Vec<u8>>),
+    Integer,
+}
+
+impl RedisCache {
+    /// Connect lazily to `addr` (`host:port`) on first use
+    pub fn new(addr: impl Into<String>) -> Self {
+        Self {
+            addr: addr.into(),
+            password: None,
+            key_prefix: "ash-rpc:".to_string(),
+            connection: Mutex::new(None),
+        }
+    }
+
+    /// Send `AUTH` after connecting
+    pub fn password(mut self, password: impl Into<String>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Prefix for every key, `ash-rpc:` by default
+    pub fn key_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.key_prefix = prefix.into();
+        self
+    }
+
+    async fn command(&self, args: &[&[u8]]) -> Result<Reply, CacheError> {
+        let mut connection = self.connection.lock().await;
+        if connection.is_none() {
+            let mut stream = BufReader::new(TcpStream::connect(&self.addr).await.map_err(backend)?);
+            if let Some(password) = &self.password {
+                round_trip(&mut stream, &[b"AUTH", password.as_bytes()]).await?;
+            }
+            *connection = Some(stream);
+        }
+        let Some(stream) = connection.as_mut() else {
+            return Err(CacheError::Backend("not connected".to_string()));
+        };
+        let reply = round_trip(stream, args).await;
+        if reply.is_err() {
+            // the stream may hold half a reply; start over next time
+            *connection = None;
+        }
+        reply
+    }
+
+    async fn set(
+        &self,
+        key: &str,
+        value: &Value,
+        ttl: Option<Duration>,
+        only_if_absent: bool,
+    ) -> Result<bool, CacheError> {
+        let key = format!("{}{key}", self.key_prefix);
+        let value = serde_json::to_vec(value).map_err(backend)?;
+        let ttl_ms = ttl.map(|ttl| ttl.as_millis().max(1).to_string());
+        let mut args: Vec<&[u8]> = vec![b"SET", key.as_bytes(), &value];
+        if let Some(ttl_ms) = &ttl_ms {
+            args.extend([b"PX".as_slice(), ttl_ms.as_bytes()]);
+        }
+        if only_if_absent {
+            args.push(b"NX");
+        }
+        match self.command(&args).await? {
+            Reply::Ok => Ok(true),
+            Reply::Bulk(None) => Ok(false),
+            _ => Err(CacheError::Backend("unexpected reply to SET".to_string())),
+        }
+    }
+}
+
+fn backend(e: impl std::fmt::Display) -> CacheError {
+    CacheError::Backend(e.to_string())
+}
+
+async fn round_trip(
+    stream: &mut BufReader<TcpStream>,
+    args: &[&[u8]],
+) -> Result<Reply, CacheError> {
+    let mut request = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        request.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        request.extend_from_slice(arg);
+        request.extend_from_slice(b"\r\n");
+    }
+    stream
+        .get_mut()
+        .write_all(&request)
+        .await
+        .map_err(backend)?;
+    read_reply(stream).await
+}
+
+async fn read_reply(stream: &mut BufReader<TcpStream>) -> Result<Reply, CacheError> {
+    let mut line = String::new();
+    if stream.read_line(&mut line).await.map_err(backend)? == 0 {
+        return Err(CacheError::Backend("connection closed".to_string()));
+    }
+    let line = line.trim_end_matches("\r\n");
+    let (kind, rest) = line.split_at(line.len().min(1));
+    match kind {
+        "+" => Ok(Reply::Ok),
+        "-" => Err(CacheError::Backend(rest.to_string())),
+        ":" => rest.parse::<i64>().map(|_| Reply::Integer).map_err(backend),
+        "$" => {
+            let len: i64 = rest.parse().map_err(backend)?;
+            if len < 0 {
+                return Ok(Reply::Bulk(None));
+            }
+            let mut data = vec![0; len as usize + 2];
+            stream.read_exact(&mut data).await.map_err(backend)?;
+            data.truncate(len as usize);
+            Ok(Reply::Bulk(Some(data)))
+        }
+        _ => Err(CacheError::Backend(format!("unexpected reply {line:?}"))),
+    }
+}
+
+#[async_trait::async_trait]
+impl Cache for RedisCache {
+    async fn get(&self, key: &str) -> Result<Option<Value>, CacheError> {
+        let key = format!("{}{key}", self.key_prefix);
+        match self.command(&[b"GET", key.as_bytes()]).await? {
+            Reply::Bulk(Some(data)) => serde_json::from_slice(&data).map(Some).map_err(backend),
+            Reply::Bulk(None) => Ok(None),
+            _ => Err(CacheError::Backend("unexpected reply to GET".to_string())),
+        }
+    }
+
+    async fn insert(
+        &self,
+        key: &str,
+        value: Value,
+        ttl: Option<Duration>,
+    ) -> Result<(), CacheError> {
+        self.set(key, &value, ttl, false).await.map(|_| ())
+    }
+
+    async fn insert_if_absent(
+        &self,
+        key: &str,
+        value: Value,
+        ttl: Option<Duration>,
+    ) -> Result<bool, CacheError> {
+        self.set(key, &value, ttl, true).await
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), CacheError> {
+        let key = format!("{}{key}", self.key_prefix);
+        match self.command(&[b"DEL", key.as_bytes()]).await? {
+            Reply::Integer => Ok(()),
+            _ => Err(CacheError::Backend("unexpected reply to DEL".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use tokio::net::TcpListener;
+
+    /// Just enough of a Redis server for GET/SET NX/DEL, ignoring expiry
+    async fn fake_redis() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = BufReader::new(stream);
+            let mut data: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+            loop {
+                let mut header = String::new();
+                if stream.read_line(&mut header).await.unwrap_or(0) == 0 {
+                    return;
+                }
+                let count: usize = header.trim()[1..].parse().unwrap();
+                let mut args = Vec::new();
+                for _ in 0..count {
+                    let mut len = String::new();
+                    stream.read_line(&mut len).await.unwrap();
+                    let mut arg = vec![0; len.trim()[1..].parse::<usize>().unwrap() + 2];
+                    stream.read_exact(&mut arg).await.unwrap();
+                    arg.truncate(arg.len() - 2);
+                    args.push(arg);
+                }
+                let reply = match args[0].as_slice() {
+                    b"GET" => match data.get(&args[1]) {
+                        Some(v) => [
+                            format!("${}\r\n", v.len()).into_bytes(),
+                            v.clone(),
+                            b"\r\n".to_vec(),
+                        ]
+                        .concat(),
+                        None => b"$-1\r\n".to_vec(),
+                    },
+                    b"SET" if args.iter().any(|a| a == b"NX") && data.contains_key(&args[1]) => {
+                        b"$-1\r\n".to_vec()
+                    }
+                    b"SET" => {
+                        data.insert(args[1].clone(), args[2].clone());
+                        b"+OK\r\n".to_vec()
+                    }
+                    b"DEL" => {
+                        format!(":{}\r\n", data.remove(&args[1]).map_or(0, |_| 1)).into_bytes()
+                    }
+                    _ => b"-ERR unknown command\r\n".to_vec(),
+                };
+                stream.get_mut().write_all(&reply).await.unwrap();
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_redis_cache_round_trip() {
+        let cache = RedisCache::new(fake_redis().await).key_prefix("t:");
+        let ttl = Some(Duration::from_secs(5));
+
+        assert_eq!(cache.get("k").await.unwrap(), None);
+        assert!(
+            cache
+                .insert_if_absent("k", json!({"a": 1}), ttl)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !cache
+                .insert_if_absent("k", json!({"a": 2}), ttl)
+                .await
+                .unwrap()
+        );
+        assert_eq!(cache.get("k").await.unwrap(), Some(json!({"a": 1})));
+
+        cache.insert("k", json!("replaced"), None).await.unwrap();
+        assert_eq!(cache.get("k").await.unwrap(), Some(json!("replaced")));
+        cache.remove("k").await.unwrap();
+        assert_eq!(cache.get("k").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_redis_unreachable() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        let cache = RedisCache::new(addr);
+        assert!(matches!(cache.get("k").await, Err(CacheError::Backend(_))));
+    }
+}
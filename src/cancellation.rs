@@ -0,0 +1,225 @@
+//! Cancellation of in-flight requests.
+//!
+//! With the [`CANCELLATION`](crate::builtins::BuiltinMethods::CANCELLATION)
+//! built-ins enabled, a registry keeps a [`CancellationToken`] for every
+//! request it is running and serves `rpc.cancel`, also answering to the
+//! LSP-style `$/cancelRequest`. Both take `{"id": <request id>}` and cancel
+//! the running request of that id from the same peer address; `rpc.cancel`
+//! answers whether there was one.
+//!
+//! The cancelled request is answered with
+//! [`REQUEST_CANCELLED`](crate::error_codes::REQUEST_CANCELLED) the next
+//! time its method yields, dropping the method's future. Methods doing
+//! expensive work between awaits read the token from
+//! [`CallContext::cancellation`](crate::CallContext::cancellation) to stop
+//! early. A cancel request can only overtake the request it cancels when
+//! the transport processes a connection's requests concurrently, e.g. with
+//! [pipelining](crate::transports).
+//!
+//! ```rust
+//! use ash_rpc::cancellation::CancellationToken;
+//!
+//! let token = CancellationToken::new();
+//! let handle = token.clone();
+//! assert!(!handle.is_cancelled());
+//! token.cancel();
+//! assert!(handle.is_cancelled());
+//! ```
+
+use crate::RequestId;
+use crate::auth::ConnectionContext;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// Shared flag telling a request's handler to stop
+///
+/// Clones share the flag; cancelling any of them cancels all.
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<Inner>);
+
+#[derive(Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    wakers: Mutex<Vec<Waker>>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel, waking every task waiting in [`cancelled`](Self::cancelled)
+    pub fn cancel(&self) {
+        if !self.0.cancelled.swap(true, Ordering::SeqCst) {
+            let wakers =
+                std::mem::take(&mut *self.0.wakers.lock().unwrap_or_else(|e| e.into_inner()));
+            for waker in wakers {
+                waker.wake();
+            }
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Completes once the token is cancelled
+    pub fn cancelled(&self) -> Cancelled<'_> {
+        Cancelled(self)
+    }
+
+    /// Run `future` to completion, or drop it and return `None` once the
+    /// token is cancelled
+    pub async fn run_until_cancelled<F: Future>(&self, future: F) -> Option<F::Output> {
+        let mut future = std::pin::pin!(future);
+        let mut cancelled = self.cancelled();
+        std::future::poll_fn(|cx| {
+            if Pin::new(&mut cancelled).poll(cx).is_ready() {
+                return Poll::Ready(None);
+            }
+            future.as_mut().poll(cx).map(Some)
+        })
+        .await
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+/// Future returned by [`CancellationToken::cancelled`]
+pub struct Cancelled<'a>(&'a CancellationToken);
+
+impl Future for Cancelled<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0.is_cancelled() {
+            return Poll::Ready(());
+        }
+        let mut wakers = self.0.0.wakers.lock().unwrap_or_else(|e| e.into_inner());
+        // checked again under the lock `cancel` drains the wakers with
+        if self.0.is_cancelled() {
+            return Poll::Ready(());
+        }
+        if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+type Key = (Option<SocketAddr>, String);
+
+/// Tokens of the requests a registry is running, by peer and id
+#[derive(Default)]
+pub(crate) struct InFlight {
+    requests: Mutex<HashMap<Key, CancellationToken>>,
+}
+
+impl InFlight {
+    fn key(ctx: &ConnectionContext, id: &RequestId) -> Key {
+        (ctx.remote_addr, id.to_string())
+    }
+
+    /// Track the request `id`, until the returned guard is dropped
+    pub(crate) fn track(self: &Arc<Self>, ctx: &ConnectionContext, id: &RequestId) -> Tracked {
+        let key = Self::key(ctx, id);
+        let token = CancellationToken::new();
+        self.requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key.clone(), token.clone());
+        Tracked {
+            in_flight: Arc::clone(self),
+            key,
+            token,
+        }
+    }
+
+    /// Cancel the request `id` of the peer of `ctx`; false when none runs
+    pub(crate) fn cancel(&self, ctx: &ConnectionContext, id: &RequestId) -> bool {
+        let requests = self.requests.lock().unwrap_or_else(|e| e.into_inner());
+        match requests.get(&Self::key(ctx, id)) {
+            Some(token) => {
+                tracing::debug!(id = %id, "request cancelled");
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// A tracked request, forgotten when dropped
+pub(crate) struct Tracked {
+    in_flight: Arc<InFlight>,
+    key: Key,
+    pub(crate) token: CancellationToken,
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        let mut requests = self
+            .in_flight
+            .requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        // a later request reusing the id replaced this one's token
+        if requests
+            .get(&self.key)
+            .is_some_and(|token| Arc::ptr_eq(&token.0, &self.token.0))
+        {
+            requests.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_run_until_cancelled() {
+        let token = CancellationToken::new();
+        assert_eq!(token.run_until_cancelled(async { 1 }).await, Some(1));
+
+        let handle = token.clone();
+        let (result, _) = tokio::join!(
+            token.run_until_cancelled(std::future::pending::<()>()),
+            async move {
+                tokio::task::yield_now().await;
+                handle.cancel();
+            }
+        );
+        assert_eq!(result, None);
+        token.cancelled().await;
+    }
+
+    #[test]
+    fn test_in_flight_scoped_by_peer() {
+        let in_flight = Arc::new(InFlight::default());
+        let peer = ConnectionContext::with_addr("127.0.0.1:4000".parse().unwrap());
+        let other = ConnectionContext::with_addr("127.0.0.1:4001".parse().unwrap());
+
+        let tracked = in_flight.track(&peer, &json!(1));
+        let reused = in_flight.track(&peer, &json!(1));
+        drop(tracked);
+        assert!(!in_flight.cancel(&other, &json!(1)));
+        assert!(in_flight.cancel(&peer, &json!(1)));
+        assert!(reused.token.is_cancelled());
+        drop(reused);
+        assert!(!in_flight.cancel(&peer, &json!(1)));
+    }
+}
@@ -0,0 +1,186 @@
+//! Canonical JSON for cache and idempotency keys.
+//!
+//! Two requests carrying the same params must map to the same key, whether
+//! they came from another server instance, a client in another language or
+//! a build with `preserve-order`. [`to_string`] writes a value in one fixed
+//! form following RFC 8785 (JCS): no whitespace, object keys sorted by
+//! their UTF-16 code units, strings escaped minimally and numbers in the
+//! ECMAScript shortest round-trip form, so `1.0`, `1` and `1e0` agree.
+//! Integers beyond the `f64` range of exact values are written with every
+//! digit rather than rounded.
+//!
+//! ```rust
+//! use ash_rpc::canonical;
+//! use serde_json::json;
+//!
+//! let a = json!({"b": [1.0, "x"], "a": 1e21});
+//! let b = json!({"a": 1000000000000000000000.0, "b": [1, "x"]});
+//! assert_eq!(canonical::to_string(&a), r#"{"a":1e+21,"b":[1,"x"]}"#);
+//! assert_eq!(canonical::request_key("sum", Some(&a)), canonical::request_key("sum", Some(&b)));
+//! ```
+
+use serde_json::{Number, Value};
+
+/// `value` in canonical form
+pub fn to_string(value: &Value) -> String {
+    let mut out = String::new();
+    write(value, &mut out);
+    out
+}
+
+/// Append `value` in canonical form to `out`
+pub fn write(value: &Value, out: &mut String) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => write_number(n, out),
+        Value::String(s) => write_str(s, out),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_str(key, out);
+                out.push(':');
+                write(value, out);
+            }
+            out.push('}');
+        }
+    }
+}
+
+/// Key for a call to `method` with `params`: the canonical form of
+/// `[method, params]`, with `null` for absent params
+pub fn request_key(method: &str, params: Option<&Value>) -> String {
+    let mut key = String::from("[");
+    write_str(method, &mut key);
+    key.push(',');
+    write(params.unwrap_or(&Value::Null), &mut key);
+    key.push(']');
+    key
+}
+
+/// 64-bit FNV-1a hash of the canonical form of `value`
+///
+/// Stable across processes, versions and languages, and short enough for
+/// sharding or compact cache keys. It is not collision resistant: where a
+/// collision could hand one caller's result to another, key by
+/// [`request_key`] itself.
+pub fn hash(value: &Value) -> u64 {
+    fnv1a(to_string(value).as_bytes())
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn write_str(s: &str, out: &mut String) {
+    // serde_json escapes exactly what JCS requires: quotes, backslashes and
+    // control characters, with lowercase hex
+    out.push_str(&serde_json::to_string(s).unwrap_or_default());
+}
+
+fn write_number(n: &Number, out: &mut String) {
+    if let Some(i) = n.as_i64() {
+        out.push_str(&i.to_string());
+    } else if let Some(u) = n.as_u64() {
+        out.push_str(&u.to_string());
+    } else if let Some(f) = n.as_f64() {
+        write_float(f, out);
+    }
+}
+
+/// ECMAScript `Number.prototype.toString` for finite `f`
+fn write_float(f: f64, out: &mut String) {
+    if f == 0.0 {
+        out.push('0');
+        return;
+    }
+    if f < 0.0 {
+        out.push('-');
+    }
+    // `{:e}` gives the shortest round-trip digits, e.g. `1.5e-7`
+    let scientific = format!("{:e}", f.abs());
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let digits: String = mantissa.chars().filter(char::is_ascii_digit).collect();
+    let k = digits.len() as i32;
+    // position of the decimal point relative to the digits
+    let n = exponent.parse::<i32>().unwrap_or(0) + 1;
+    if k <= n && n <= 21 {
+        out.push_str(&digits);
+        out.extend(std::iter::repeat_n('0', (n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        out.push_str(&digits[..n as usize]);
+        out.push('.');
+        out.push_str(&digits[n as usize..]);
+    } else if -6 < n && n <= 0 {
+        out.push_str("0.");
+        out.extend(std::iter::repeat_n('0', -n as usize));
+        out.push_str(&digits);
+    } else {
+        out.push_str(&digits[..1]);
+        if k > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        out.push('e');
+        out.push(if n > 0 { '+' } else { '-' });
+        out.push_str(&(n - 1).abs().to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_numbers_follow_ecmascript() {
+        let cases = [
+            (json!(1.0), "1"),
+            (json!(-0.0), "0"),
+            (json!(0.5), "0.5"),
+            (json!(123.456), "123.456"),
+            (json!(1.5e-7), "1.5e-7"),
+            (json!(0.000001), "0.000001"),
+            (json!(1e20), "100000000000000000000"),
+            (json!(1e21), "1e+21"),
+            (json!(-2.5e30), "-2.5e+30"),
+            (json!(u64::MAX), "18446744073709551615"),
+            (json!(i64::MIN), "-9223372036854775808"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(to_string(&value), expected, "{value}");
+        }
+    }
+
+    #[test]
+    fn test_keys_sorted_by_utf16() {
+        // U+FB01 sorts before U+1F600 by code point, after it in UTF-16
+        let value = json!({"\u{1F600}": 1, "\u{FB01}": 2, "b": {"z": null, "a": [true, "\n"]}});
+        assert_eq!(
+            to_string(&value),
+            "{\"b\":{\"a\":[true,\"\\n\"],\"z\":null},\"\u{1F600}\":1,\"\u{FB01}\":2}"
+        );
+        assert_eq!(
+            hash(&json!({"a": 1, "b": 2.0})),
+            hash(&json!({"b": 2, "a": 1}))
+        );
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
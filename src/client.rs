@@ -0,0 +1,1033 @@
+//! Async JSON-RPC client.
+//!
+//! [`RpcClient`] sends requests over any [`ClientTransport`] and matches
+//! responses to calls by id, so any number of calls may be in flight on one
+//! connection. Server-pushed notifications go to callbacks registered with
+//! [`RpcClient::on_notification`].
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "tcp-stream")]
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! use ash_rpc::client::{RpcClient, TcpClientTransport};
+//! use std::time::Duration;
+//!
+//! let transport = TcpClientTransport::connect("127.0.0.1:8080").await?;
+//! let client = RpcClient::new(transport).timeout(Duration::from_secs(5));
+//!
+//! let sum: i64 = client.call("add", [1, 2]).await?;
+//! client.notify("log", "added").await?;
+//!
+//! let mut batch = client.batch();
+//! let first = batch.call("add", [1, 1]);
+//! let second = batch.call("add", [2, 2]);
+//! let results = batch.send().await?;
+//! let (a, b): (i64, i64) = (results.get(first)?, results.get(second)?);
+//! # let _ = (sum, a, b);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A [`ConcurrencyLimit`] caps the calls a client keeps in flight so a burst
+//! of work does not overwhelm a small server. Calls beyond the limit queue,
+//! or fail with [`ClientError::Overloaded`] in fail-fast mode. The adaptive
+//! limit grows by one per window of successful calls and shrinks
+//! multiplicatively when calls time out, the server asks to retry later or
+//! latency climbs well above the lowest seen, settling near the rate the
+//! server sustains.
+
+use crate::{Message, MessageProcessor, Request, RequestId, Response};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{Notify, mpsc, oneshot};
+
+/// Why a client call failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientError {
+    /// The transport failed to send or receive
+    Transport(String),
+    /// The connection closed before the response arrived
+    Closed,
+    /// No response within the call's timeout
+    Timeout,
+    /// The server answered with an error
+    Rpc(crate::Error),
+    /// Params could not be encoded or the result decoded
+    Serialization(String),
+    /// The concurrency limit was reached in fail-fast mode
+    Overloaded,
+    /// The result did not match the schema it was validated against
+    SchemaMismatch(crate::schema::SchemaMismatch),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Transport(e) => write!(f, "transport error: {e}"),
+            ClientError::Closed => write!(f, "connection closed"),
+            ClientError::Timeout => write!(f, "call timed out"),
+            ClientError::Rpc(e) => write!(f, "server error {}: {}", e.code, e.message),
+            ClientError::Serialization(e) => write!(f, "serialization error: {e}"),
+            ClientError::Overloaded => write!(f, "too many calls in flight"),
+            ClientError::SchemaMismatch(e) => write!(f, "unexpected result, {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+/// A connection carrying JSON-RPC frames to and from a server
+///
+/// [`RpcClient`] receives on a background task while other tasks send, so
+/// both methods are called concurrently.
+#[async_trait::async_trait]
+pub trait ClientTransport: Send + Sync + 'static {
+    /// Send one frame: a message or a batch array
+    async fn send(&self, frame: String) -> Result<(), ClientError>;
+
+    /// Next frame from the server, `None` once the connection is closed
+    async fn recv(&self) -> Result<Option<String>, ClientError>;
+}
+
+/// Newline-delimited JSON over TCP, as spoken by `TcpStreamServer`
+#[cfg(feature = "tcp-stream")]
+pub struct TcpClientTransport {
+    reader: tokio::sync::Mutex<tokio::io::BufReader<tokio::net::tcp::OwnedReadHalf>>,
+    writer: tokio::sync::Mutex<tokio::net::tcp::OwnedWriteHalf>,
+    tap: Option<crate::transports::tap::ConnectionTap>,
+}
+
+#[cfg(feature = "tcp-stream")]
+impl TcpClientTransport {
+    pub async fn connect(addr: impl tokio::net::ToSocketAddrs) -> std::io::Result<Self> {
+        let (reader, writer) = tokio::net::TcpStream::connect(addr).await?.into_split();
+        Ok(Self {
+            reader: tokio::sync::Mutex::new(tokio::io::BufReader::new(reader)),
+            writer: tokio::sync::Mutex::new(writer),
+            tap: None,
+        })
+    }
+
+    /// Mirror the connection's frames to `tap`, if sampled
+    pub fn tap(mut self, tap: crate::transports::WireTap) -> Self {
+        let peer = self.writer.get_mut().peer_addr().ok();
+        self.tap = Arc::new(tap).connection(crate::transports::transport_stats::TCP_STREAM, peer);
+        self
+    }
+}
+
+#[cfg(feature = "tcp-stream")]
+#[async_trait::async_trait]
+impl ClientTransport for TcpClientTransport {
+    async fn send(&self, frame: String) -> Result<(), ClientError> {
+        use tokio::io::AsyncWriteExt;
+        if let Some(tap) = &self.tap {
+            tap.capture(crate::transports::TapDirection::Outbound, frame.as_bytes());
+        }
+        let mut writer = self.writer.lock().await;
+        writer
+            .write_all(frame.as_bytes())
+            .await
+            .map_err(transport)?;
+        writer.write_all(b"\n").await.map_err(transport)?;
+        writer.flush().await.map_err(transport)
+    }
+
+    async fn recv(&self) -> Result<Option<String>, ClientError> {
+        use tokio::io::AsyncBufReadExt;
+        let mut reader = self.reader.lock().await;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).await.map_err(transport)? == 0 {
+                return Ok(None);
+            }
+            if let Some(tap) = &self.tap {
+                let frame = line.strip_suffix('\n').unwrap_or(&line);
+                tap.capture(crate::transports::TapDirection::Inbound, frame.as_bytes());
+            }
+            if !line.trim().is_empty() {
+                return Ok(Some(line.trim().to_string()));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tcp-stream")]
+fn transport(e: std::io::Error) -> ClientError {
+    ClientError::Transport(e.to_string())
+}
+
+/// In-process transport calling a [`MessageProcessor`] directly
+///
+/// Useful in tests and for embedding a service without a socket.
+pub struct LocalTransport {
+    processor: Arc<dyn MessageProcessor + Send + Sync>,
+    responses: mpsc::UnboundedSender<String>,
+    incoming: tokio::sync::Mutex<mpsc::UnboundedReceiver<String>>,
+}
+
+impl LocalTransport {
+    pub fn new<P>(processor: P) -> Self
+    where
+        P: MessageProcessor + Send + Sync + 'static,
+    {
+        let (responses, incoming) = mpsc::unbounded_channel();
+        Self {
+            processor: Arc::new(processor),
+            responses,
+            incoming: tokio::sync::Mutex::new(incoming),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ClientTransport for LocalTransport {
+    async fn send(&self, frame: String) -> Result<(), ClientError> {
+        let processor = Arc::clone(&self.processor);
+        let responses = self.responses.clone();
+        let reply = move |json: serde_json::Result<String>| {
+            if let Ok(json) = json {
+                let _ = responses.send(json);
+            }
+        };
+        if let Ok(batch) = serde_json::from_str::<Vec<Message>>(&frame) {
+            tokio::spawn(async move {
+                let results = processor.process_batch(batch).await;
+                if !results.is_empty() {
+                    reply(serde_json::to_string(&results));
+                }
+            });
+        } else {
+            let message: Message = serde_json::from_str(&frame)
+                .map_err(|e| ClientError::Serialization(e.to_string()))?;
+            tokio::spawn(async move {
+                if let Some(response) = processor.process_message(message).await {
+                    reply(serde_json::to_string(&response));
+                }
+            });
+        }
+        Ok(())
+    }
+
+    async fn recv(&self) -> Result<Option<String>, ClientError> {
+        Ok(self.incoming.lock().await.recv().await)
+    }
+}
+
+type NotificationCallback = Arc<dyn Fn(Option<Value>) + Send + Sync>;
+
+struct Shared {
+    /// Calls awaiting a response by id; `None` once the connection closed
+    pending: Mutex<Option<HashMap<String, oneshot::Sender<Response>>>>,
+    notifications: RwLock<HashMap<String, NotificationCallback>>,
+}
+
+impl Shared {
+    fn register(&self, id: &RequestId) -> Result<oneshot::Receiver<Response>, ClientError> {
+        let (tx, rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_mut()
+            .ok_or(ClientError::Closed)?
+            .insert(id.to_string(), tx);
+        Ok(rx)
+    }
+
+    fn forget(&self, id: &RequestId) {
+        if let Some(pending) = self
+            .pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_mut()
+        {
+            pending.remove(&id.to_string());
+        }
+    }
+
+    /// Fail outstanding and future calls with [`ClientError::Closed`]
+    fn close(&self) {
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+    }
+
+    fn deliver(&self, value: Value) {
+        if let Some(method) = value.get("method").and_then(Value::as_str) {
+            if value.get("id").is_some() {
+                tracing::debug!(method, "ignoring server-to-client request");
+                return;
+            }
+            let handler = self
+                .notifications
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .get(method)
+                .cloned();
+            match handler {
+                Some(handler) => handler(value.get("params").cloned()),
+                None => tracing::debug!(method, "unhandled notification"),
+            }
+            return;
+        }
+
+        let Ok(response) = serde_json::from_value::<Response>(value) else {
+            tracing::debug!("ignoring undecodable frame");
+            return;
+        };
+        let key = response.id.as_ref().unwrap_or(&Value::Null).to_string();
+        let waiter = self
+            .pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_mut()
+            .and_then(|pending| pending.remove(&key));
+        match waiter {
+            Some(waiter) => {
+                let _ = waiter.send(response);
+            }
+            None => tracing::debug!(id = %key, "response without a pending call"),
+        }
+    }
+}
+
+/// Cap on the calls a client keeps in flight, see [`RpcClient::concurrency_limit`]
+#[derive(Debug, Clone)]
+pub struct ConcurrencyLimit {
+    min: usize,
+    max: usize,
+    adaptive: bool,
+    fail_fast: bool,
+    tolerance: f64,
+    backoff: f64,
+}
+
+impl ConcurrencyLimit {
+    /// At most `max` calls in flight
+    pub fn fixed(max: usize) -> Self {
+        let max = max.max(1);
+        Self {
+            min: max,
+            max,
+            adaptive: false,
+            fail_fast: false,
+            tolerance: 2.0,
+            backoff: 0.9,
+        }
+    }
+
+    /// A limit between `min` and `max` following the server's capacity
+    ///
+    /// Starts at `min` and adapts with additive increase, multiplicative
+    /// decrease.
+    pub fn adaptive(min: usize, max: usize) -> Self {
+        let min = min.max(1);
+        Self {
+            min,
+            max: max.max(min),
+            adaptive: true,
+            ..Self::fixed(min)
+        }
+    }
+
+    /// Fail calls beyond the limit with [`ClientError::Overloaded`] instead
+    /// of queueing them
+    pub fn fail_fast(mut self) -> Self {
+        self.fail_fast = true;
+        self
+    }
+
+    /// Latency over the lowest seen at which the adaptive limit backs off,
+    /// 2.0 unless changed
+    pub fn tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance.max(1.0);
+        self
+    }
+
+    /// Factor applied to the adaptive limit when backing off, 0.9 unless
+    /// changed
+    pub fn backoff(mut self, backoff: f64) -> Self {
+        self.backoff = backoff.clamp(0.1, 1.0);
+        self
+    }
+}
+
+/// Current state of a client's concurrency limit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConcurrencyStats {
+    pub in_flight: usize,
+    pub limit: usize,
+}
+
+struct LimiterState {
+    in_flight: usize,
+    limit: f64,
+    /// Lowest latency seen, the adaptive limit's no-load baseline
+    baseline: Option<Duration>,
+}
+
+struct Limiter {
+    config: ConcurrencyLimit,
+    state: Mutex<LimiterState>,
+    released: Notify,
+}
+
+impl Limiter {
+    fn new(config: ConcurrencyLimit) -> Self {
+        Self {
+            state: Mutex::new(LimiterState {
+                in_flight: 0,
+                limit: config.min as f64,
+                baseline: None,
+            }),
+            config,
+            released: Notify::new(),
+        }
+    }
+
+    fn stats(&self) -> ConcurrencyStats {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        ConcurrencyStats {
+            in_flight: state.in_flight,
+            limit: state.limit as usize,
+        }
+    }
+
+    async fn acquire(self: &Arc<Self>) -> Result<Permit, ClientError> {
+        loop {
+            let released = self.released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+            {
+                let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+                if state.in_flight < state.limit as usize {
+                    state.in_flight += 1;
+                    return Ok(Permit {
+                        limiter: Arc::clone(self),
+                        started: Instant::now(),
+                    });
+                }
+            }
+            if self.config.fail_fast {
+                return Err(ClientError::Overloaded);
+            }
+            released.await;
+        }
+    }
+
+    /// Adapt the limit to the outcome of one call
+    fn observe(&self, latency: Duration, overloaded: bool) {
+        if !self.config.adaptive {
+            return;
+        }
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let baseline = *state.baseline.get_or_insert(latency);
+        let slow = latency.as_secs_f64() > baseline.as_secs_f64() * self.config.tolerance;
+        let previous = state.limit as usize;
+        if overloaded || slow {
+            state.limit = (state.limit * self.config.backoff).max(self.config.min as f64);
+        } else {
+            state.baseline = Some(baseline.min(latency));
+            state.limit = (state.limit + 1.0 / state.limit).min(self.config.max as f64);
+        }
+        if state.limit as usize != previous {
+            tracing::debug!(
+                limit = state.limit as usize,
+                "client concurrency limit adapted"
+            );
+        }
+        if state.limit as usize > previous {
+            self.released.notify_one();
+        }
+    }
+}
+
+/// A slot under the concurrency limit, released on drop
+struct Permit {
+    limiter: Arc<Limiter>,
+    started: Instant,
+}
+
+impl Permit {
+    /// Feed the outcome of the call into the adaptive limit
+    fn complete<T>(self, result: &Result<T, ClientError>) {
+        let overloaded = match result {
+            Ok(_) => false,
+            Err(ClientError::Timeout | ClientError::Transport(_)) => true,
+            Err(ClientError::Rpc(e)) => e.code == crate::error_codes::RETRY_LATER,
+            Err(_) => return,
+        };
+        self.limiter.observe(self.started.elapsed(), overloaded);
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut state = self.limiter.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.in_flight -= 1;
+        drop(state);
+        self.limiter.released.notify_one();
+    }
+}
+
+/// JSON-RPC client multiplexing calls over one [`ClientTransport`]
+pub struct RpcClient {
+    transport: Arc<dyn ClientTransport>,
+    shared: Arc<Shared>,
+    next_id: AtomicU64,
+    timeout: Option<Duration>,
+    limiter: Option<Arc<Limiter>>,
+    reader: tokio::task::JoinHandle<()>,
+}
+
+impl RpcClient {
+    /// Start receiving on `transport`; must be called within a Tokio runtime
+    pub fn new<T: ClientTransport>(transport: T) -> Self {
+        let transport: Arc<dyn ClientTransport> = Arc::new(transport);
+        let shared = Arc::new(Shared {
+            pending: Mutex::new(Some(HashMap::new())),
+            notifications: RwLock::default(),
+        });
+        let reader = tokio::spawn({
+            let transport = Arc::clone(&transport);
+            let shared = Arc::clone(&shared);
+            async move {
+                while let Ok(Some(frame)) = transport.recv().await {
+                    match serde_json::from_str::<Value>(&frame) {
+                        Ok(Value::Array(batch)) => {
+                            batch.into_iter().for_each(|v| shared.deliver(v))
+                        }
+                        Ok(value) => shared.deliver(value),
+                        Err(e) => tracing::debug!(error = %e, "ignoring malformed frame"),
+                    }
+                }
+                shared.close();
+            }
+        });
+        Self {
+            transport,
+            shared,
+            next_id: AtomicU64::new(1),
+            timeout: Some(Duration::from_secs(30)),
+            limiter: None,
+            reader,
+        }
+    }
+
+    /// Default time to wait for a response, 30 seconds unless changed
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Wait for responses indefinitely
+    pub fn without_timeout(mut self) -> Self {
+        self.timeout = None;
+        self
+    }
+
+    /// Limit the calls and batches in flight at once
+    ///
+    /// Time spent queueing for a slot counts towards a call's timeout.
+    pub fn concurrency_limit(mut self, limit: ConcurrencyLimit) -> Self {
+        self.limiter = Some(Arc::new(Limiter::new(limit)));
+        self
+    }
+
+    /// Calls in flight and the current limit, `None` without a limit
+    pub fn concurrency(&self) -> Option<ConcurrencyStats> {
+        self.limiter.as_ref().map(|limiter| limiter.stats())
+    }
+
+    /// Route notifications for `method` to `callback`
+    ///
+    /// Callbacks run on the receive loop and should return quickly.
+    pub fn on_notification<F>(&self, method: impl Into<String>, callback: F)
+    where
+        F: Fn(Option<Value>) + Send + Sync + 'static,
+    {
+        self.shared
+            .notifications
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(method.into(), Arc::new(callback));
+    }
+
+    /// Call `method` and decode its result as `T`
+    ///
+    /// `params` are omitted when they serialize to `null`, e.g. `()`.
+    pub async fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: impl Serialize,
+    ) -> Result<T, ClientError> {
+        self.call_with_timeout(method, params, self.timeout).await
+    }
+
+    /// [`call`](Self::call) with a timeout for this call only
+    pub async fn call_with_timeout<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: impl Serialize,
+        timeout: Option<Duration>,
+    ) -> Result<T, ClientError> {
+        decode(self.respond(method, params, timeout).await?)
+    }
+
+    /// [`call`](Self::call), validating the result against `schema` before
+    /// decoding it
+    ///
+    /// A result that does not match fails with
+    /// [`ClientError::SchemaMismatch`] listing every offending path, which
+    /// surfaces server drift more clearly than a serde error on the first
+    /// unexpected field.
+    pub async fn call_validated_with<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: impl Serialize,
+        schema: &Value,
+    ) -> Result<T, ClientError> {
+        let response = self.respond(method, params, self.timeout).await?;
+        if let Some(result) = &response.result {
+            crate::schema::validate(result, schema).map_err(ClientError::SchemaMismatch)?;
+        }
+        decode(response)
+    }
+
+    /// [`call_validated_with`](Self::call_validated_with) using the schema
+    /// `schemars` derives for `T`
+    #[cfg(feature = "schemars")]
+    pub async fn call_validated<T>(
+        &self,
+        method: &str,
+        params: impl Serialize,
+    ) -> Result<T, ClientError>
+    where
+        T: DeserializeOwned + schemars::JsonSchema,
+    {
+        let schema = serde_json::to_value(schemars::schema_for!(T))
+            .map_err(|e| ClientError::Serialization(e.to_string()))?;
+        self.call_validated_with(method, params, &schema).await
+    }
+
+    /// The response to a call of `method`
+    async fn respond(
+        &self,
+        method: &str,
+        params: impl Serialize,
+        timeout: Option<Duration>,
+    ) -> Result<Response, ClientError> {
+        let request = self.request(method, params)?;
+        let deadline = timeout.map(|t| tokio::time::Instant::now() + t);
+        let permit = self.acquire(deadline).await?;
+        let result = self.send_request(request, deadline).await;
+        if let Some(permit) = permit {
+            permit.complete(&result);
+        }
+        result
+    }
+
+    async fn send_request(
+        &self,
+        request: Request,
+        deadline: Option<tokio::time::Instant>,
+    ) -> Result<Response, ClientError> {
+        let id = request.id.clone().unwrap_or_default();
+        let waiter = self.shared.register(&id)?;
+        let frame = serde_json::to_string(&request)
+            .map_err(|e| ClientError::Serialization(e.to_string()))?;
+        if let Err(e) = self.transport.send(frame).await {
+            self.shared.forget(&id);
+            return Err(e);
+        }
+        let remaining = deadline.map(|d| d.saturating_duration_since(tokio::time::Instant::now()));
+        self.wait(&id, waiter, remaining).await
+    }
+
+    /// A slot under the concurrency limit, waiting until `deadline` at most
+    async fn acquire(
+        &self,
+        deadline: Option<tokio::time::Instant>,
+    ) -> Result<Option<Permit>, ClientError> {
+        let Some(limiter) = &self.limiter else {
+            return Ok(None);
+        };
+        let permit = match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline, limiter.acquire())
+                .await
+                .map_err(|_| ClientError::Timeout)??,
+            None => limiter.acquire().await?,
+        };
+        Ok(Some(permit))
+    }
+
+    /// Send a notification; no response is expected
+    pub async fn notify(&self, method: &str, params: impl Serialize) -> Result<(), ClientError> {
+        let notification = crate::Notification {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params: encode_params(params)?,
+        };
+        let frame = serde_json::to_string(&notification)
+            .map_err(|e| ClientError::Serialization(e.to_string()))?;
+        self.transport.send(frame).await
+    }
+
+    /// Start a batch of calls and notifications sent as one frame
+    pub fn batch(&self) -> Batch<'_> {
+        Batch {
+            client: self,
+            messages: Vec::new(),
+            ids: Vec::new(),
+        }
+    }
+
+    fn request(&self, method: &str, params: impl Serialize) -> Result<Request, ClientError> {
+        let id = Value::from(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let mut request = Request::new(method).with_id(id);
+        request.params = encode_params(params)?;
+        Ok(request)
+    }
+
+    async fn wait(
+        &self,
+        id: &RequestId,
+        waiter: oneshot::Receiver<Response>,
+        timeout: Option<Duration>,
+    ) -> Result<Response, ClientError> {
+        let result = match timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, waiter).await {
+                Ok(result) => result,
+                Err(_) => {
+                    self.shared.forget(id);
+                    return Err(ClientError::Timeout);
+                }
+            },
+            None => waiter.await,
+        };
+        result.map_err(|_| ClientError::Closed)
+    }
+}
+
+impl Drop for RpcClient {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+fn encode_params(params: impl Serialize) -> Result<Option<Value>, ClientError> {
+    match serde_json::to_value(params) {
+        Ok(Value::Null) => Ok(None),
+        Ok(params) => Ok(Some(params)),
+        Err(e) => Err(ClientError::Serialization(e.to_string())),
+    }
+}
+
+fn decode<T: DeserializeOwned>(response: Response) -> Result<T, ClientError> {
+    if let Some(error) = response.error {
+        return Err(ClientError::Rpc(error));
+    }
+    serde_json::from_value(response.result.unwrap_or_default())
+        .map_err(|e| ClientError::Serialization(e.to_string()))
+}
+
+/// Calls and notifications to send in one batch, see [`RpcClient::batch`]
+pub struct Batch<'a> {
+    client: &'a RpcClient,
+    messages: Vec<Value>,
+    ids: Vec<RequestId>,
+}
+
+impl Batch<'_> {
+    /// Add a call; the returned index selects its result in [`BatchResults`]
+    ///
+    /// Params that fail to serialize are sent as `null`.
+    pub fn call(&mut self, method: &str, params: impl Serialize) -> usize {
+        let id = Value::from(self.client.next_id.fetch_add(1, Ordering::Relaxed));
+        let mut request = serde_json::json!({"jsonrpc": "2.0", "method": method, "id": id});
+        if let Ok(Some(params)) = encode_params(params) {
+            request["params"] = params;
+        }
+        self.messages.push(request);
+        self.ids.push(id);
+        self.ids.len() - 1
+    }
+
+    pub fn notify(&mut self, method: &str, params: impl Serialize) {
+        let mut notification = serde_json::json!({"jsonrpc": "2.0", "method": method});
+        if let Ok(Some(params)) = encode_params(params) {
+            notification["params"] = params;
+        }
+        self.messages.push(notification);
+    }
+
+    /// Send the batch and wait for the response to every call
+    pub async fn send(self) -> Result<BatchResults, ClientError> {
+        if self.messages.is_empty() {
+            return Ok(BatchResults(Vec::new()));
+        }
+        let deadline = self.client.timeout.map(|t| tokio::time::Instant::now() + t);
+        let permit = self.client.acquire(deadline).await?;
+        let result = self.exchange(deadline).await;
+        if let Some(permit) = permit {
+            permit.complete(&result);
+        }
+        result
+    }
+
+    async fn exchange(
+        self,
+        deadline: Option<tokio::time::Instant>,
+    ) -> Result<BatchResults, ClientError> {
+        let shared = &self.client.shared;
+        let waiters = self
+            .ids
+            .iter()
+            .map(|id| shared.register(id))
+            .collect::<Result<Vec<_>, _>>()?;
+        let frame = Value::Array(self.messages).to_string();
+        if let Err(e) = self.client.transport.send(frame).await {
+            self.ids.iter().for_each(|id| shared.forget(id));
+            return Err(e);
+        }
+
+        let mut responses = Vec::with_capacity(waiters.len());
+        for (id, waiter) in self.ids.iter().zip(waiters) {
+            let remaining =
+                deadline.map(|d| d.saturating_duration_since(tokio::time::Instant::now()));
+            match self.client.wait(id, waiter, remaining).await {
+                Ok(response) => responses.push(response),
+                Err(e) => {
+                    self.ids.iter().for_each(|id| shared.forget(id));
+                    return Err(e);
+                }
+            }
+        }
+        Ok(BatchResults(responses))
+    }
+}
+
+/// Responses to a batch, in the order the calls were added
+#[derive(Debug)]
+pub struct BatchResults(Vec<Response>);
+
+impl BatchResults {
+    /// Decode the result of the call at `index`
+    pub fn get<T: DeserializeOwned>(&self, index: usize) -> Result<T, ClientError> {
+        let response = self
+            .0
+            .get(index)
+            .cloned()
+            .ok_or_else(|| ClientError::Serialization(format!("no call at index {index}")))?;
+        decode(response)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn into_responses(self) -> Vec<Response> {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{JsonRPCMethod, MethodRegistry, register_methods, rpc_success};
+
+    struct Add;
+
+    #[async_trait::async_trait]
+    impl JsonRPCMethod for Add {
+        fn method_name(&self) -> &'static str {
+            "add"
+        }
+
+        async fn call(&self, params: Option<Value>, id: Option<RequestId>) -> Response {
+            let terms: Vec<i64> =
+                serde_json::from_value(params.unwrap_or_default()).unwrap_or_default();
+            rpc_success!(terms.iter().sum::<i64>(), id)
+        }
+    }
+
+    struct Slow;
+
+    #[async_trait::async_trait]
+    impl JsonRPCMethod for Slow {
+        fn method_name(&self) -> &'static str {
+            "slow"
+        }
+
+        async fn call(&self, _params: Option<Value>, id: Option<RequestId>) -> Response {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            rpc_success!(true, id)
+        }
+    }
+
+    fn client() -> RpcClient {
+        RpcClient::new(LocalTransport::new(MethodRegistry::new(register_methods![
+            Add, Slow
+        ])))
+    }
+
+    #[tokio::test]
+    async fn test_typed_calls_and_errors() {
+        let client = client();
+        let (a, b) = tokio::join!(
+            client.call::<i64>("add", [1, 2]),
+            client.call::<i64>("add", [3, 4])
+        );
+        assert_eq!((a.unwrap(), b.unwrap()), (3, 7));
+
+        let error = client.call::<i64>("missing", ()).await.unwrap_err();
+        assert!(
+            matches!(error, ClientError::Rpc(e) if e.code == crate::error_codes::METHOD_NOT_FOUND)
+        );
+
+        let error = client.call::<String>("add", [1]).await.unwrap_err();
+        assert!(matches!(error, ClientError::Serialization(_)));
+
+        let error = client
+            .call_with_timeout::<bool>("slow", (), Some(Duration::from_millis(20)))
+            .await
+            .unwrap_err();
+        assert_eq!(error, ClientError::Timeout);
+        assert!(client.notify("add", [1]).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_call_validated_with() {
+        let client = client();
+        let sum: i64 = client
+            .call_validated_with("add", [1, 2], &serde_json::json!({"type": "integer"}))
+            .await
+            .unwrap();
+        assert_eq!(sum, 3);
+
+        let error = client
+            .call_validated_with::<String>("add", [1, 2], &serde_json::json!({"type": "string"}))
+            .await
+            .unwrap_err();
+        let ClientError::SchemaMismatch(mismatch) = error else {
+            panic!("expected a schema mismatch, got {error:?}");
+        };
+        assert_eq!(mismatch.violations[0].path, "");
+    }
+
+    #[tokio::test]
+    async fn test_batch() {
+        let client = client();
+        let mut batch = client.batch();
+        let first = batch.call("add", [1, 1]);
+        batch.notify("add", [0]);
+        let second = batch.call("missing", ());
+        let third = batch.call("add", [2, 3]);
+        let results = batch.send().await.unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results.get::<i64>(first).unwrap(), 2);
+        assert!(matches!(
+            results.get::<i64>(second),
+            Err(ClientError::Rpc(_))
+        ));
+        assert_eq!(results.get::<i64>(third).unwrap(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit_queues_or_fails_fast() {
+        let queueing = client().concurrency_limit(ConcurrencyLimit::fixed(1));
+        let (a, b) = tokio::join!(
+            queueing.call::<bool>("slow", ()),
+            queueing.call::<i64>("add", [1, 2])
+        );
+        assert!(a.unwrap());
+        assert_eq!(b.unwrap(), 3);
+
+        let failing = client().concurrency_limit(ConcurrencyLimit::fixed(1).fail_fast());
+        let (a, b) = tokio::join!(
+            failing.call::<bool>("slow", ()),
+            failing.call::<i64>("add", [1, 2])
+        );
+        assert!(a.unwrap());
+        assert_eq!(b.unwrap_err(), ClientError::Overloaded);
+        assert_eq!(
+            failing.concurrency(),
+            Some(ConcurrencyStats {
+                in_flight: 0,
+                limit: 1
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_limit_grows_and_backs_off() {
+        // in-process latencies are too noisy to back off on
+        let limit = ConcurrencyLimit::adaptive(2, 8)
+            .tolerance(1_000.0)
+            .backoff(0.5);
+        let client = client().concurrency_limit(limit);
+        for _ in 0..10 {
+            client.call::<i64>("add", [1]).await.unwrap();
+        }
+        let grown = client.concurrency().unwrap().limit;
+        assert!(grown > 2);
+
+        let error = client
+            .call_with_timeout::<bool>("slow", (), Some(Duration::from_millis(20)))
+            .await
+            .unwrap_err();
+        assert_eq!(error, ClientError::Timeout);
+        assert!(client.concurrency().unwrap().limit < grown);
+    }
+
+    #[cfg(feature = "tcp-stream")]
+    #[tokio::test]
+    async fn test_tcp_transport_and_notifications() {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let line = lines.next_line().await.unwrap().unwrap();
+            let request: Request = serde_json::from_str(&line).unwrap();
+            let frames = format!(
+                "{}\n{}\n",
+                r#"{"jsonrpc":"2.0","method":"progress","params":{"done":1}}"#,
+                serde_json::to_string(&Response::success(serde_json::json!("ok"), request.id))
+                    .unwrap()
+            );
+            writer.write_all(frames.as_bytes()).await.unwrap();
+        });
+
+        let client = RpcClient::new(TcpClientTransport::connect(addr).await.unwrap());
+        let (progress_tx, mut progress) = mpsc::unbounded_channel();
+        client.on_notification("progress", move |params| {
+            let _ = progress_tx.send(params);
+        });
+
+        assert_eq!(client.call::<String>("work", ()).await.unwrap(), "ok");
+        assert_eq!(
+            progress.recv().await.unwrap(),
+            Some(serde_json::json!({"done": 1}))
+        );
+        assert_eq!(
+            client.call::<String>("work", ()).await.unwrap_err(),
+            ClientError::Closed
+        );
+    }
+}
@@ -0,0 +1,330 @@
+//! Single-flight coalescing of identical concurrent calls.
+//!
+//! When many clients call the same expensive read with the same params at
+//! once, a registry built with
+//! [`with_coalescing`](crate::MethodRegistry::with_coalescing) runs the
+//! method once and hands its result to every caller that arrived while it
+//! was running, each under its own request id. Calls are identical when
+//! their method name and [canonical](crate::canonical) params match, so key
+//! order and number spelling do not matter; calls that arrive after
+//! the result is out start a new flight, nothing is cached.
+//!
+//! Only enable it for methods without side effects whose result does not
+//! depend on the caller: coalesced callers share the result of whichever
+//! call led the flight, including its auth context. Auth policies, rate
+//! limits and replay guards still run for every caller.
+//!
+//! ```rust
+//! use ash_rpc::coalesce::CoalescePolicy;
+//! use ash_rpc::MethodRegistry;
+//!
+//! let registry = MethodRegistry::empty()
+//!     .with_coalescing(CoalescePolicy::new().method("reports.*"));
+//! # let _ = registry;
+//! ```
+//!
+//! [`snapshot`] reports how many calls each method executed and coalesced;
+//! it also feeds the Prometheus `coalesce_*` counters.
+
+use crate::transports::MethodFilter;
+use crate::{RequestId, Response};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::task::{Poll, Waker};
+
+/// Outcome shared by the callers of one flight
+#[derive(Default)]
+struct Flight {
+    state: Mutex<FlightState>,
+}
+
+#[derive(Default)]
+struct FlightState {
+    /// `Some(None)` when the leader was dropped before finishing
+    outcome: Option<Option<Response>>,
+    waiters: Vec<Waker>,
+}
+
+impl Flight {
+    fn finish(&self, outcome: Option<Response>) {
+        let waiters = {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            state.outcome = Some(outcome);
+            std::mem::take(&mut state.waiters)
+        };
+        waiters.into_iter().for_each(Waker::wake);
+    }
+
+    /// The leader's response, `None` if the leader gave up
+    async fn wait(&self) -> Option<Response> {
+        std::future::poll_fn(|cx| {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            match &state.outcome {
+                Some(outcome) => Poll::Ready(outcome.clone()),
+                None => {
+                    state.waiters.push(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+}
+
+/// Ends a flight when the leader finishes or is dropped
+struct Leader<'a> {
+    policy: &'a CoalescePolicy,
+    key: String,
+    flight: Arc<Flight>,
+    outcome: Option<Response>,
+}
+
+impl Drop for Leader<'_> {
+    fn drop(&mut self) {
+        self.policy
+            .flights
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.key);
+        self.flight.finish(self.outcome.take());
+    }
+}
+
+/// Which methods may coalesce identical concurrent calls
+#[derive(Default)]
+pub struct CoalescePolicy {
+    methods: MethodFilter,
+    eligible: bool,
+    flights: Mutex<HashMap<String, Arc<Flight>>>,
+}
+
+impl CoalescePolicy {
+    /// No eligible methods yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Coalesce calls of methods matching `pattern` (exact or `prefix.*`)
+    pub fn method(mut self, pattern: impl Into<String>) -> Self {
+        self.methods = self.methods.allow(pattern);
+        self.eligible = true;
+        self
+    }
+
+    pub fn is_eligible(&self, method: &str) -> bool {
+        self.eligible && self.methods.is_permitted(method)
+    }
+
+    /// Run `call`, or join an identical call already in flight
+    ///
+    /// `call` receives `params` and must answer with `id`; joined callers
+    /// get the shared response under their own `id`.
+    pub async fn run<F, Fut>(
+        &self,
+        method: &str,
+        params: Option<serde_json::Value>,
+        id: Option<RequestId>,
+        call: F,
+    ) -> Response
+    where
+        F: FnOnce(Option<serde_json::Value>, Option<RequestId>) -> Fut,
+        Fut: Future<Output = Response>,
+    {
+        let key = crate::canonical::request_key(method, params.as_ref());
+        let counters = counters(method);
+
+        let (flight, leading) = {
+            let mut flights = self.flights.lock().unwrap_or_else(|e| e.into_inner());
+            match flights.get(&key) {
+                Some(flight) => (Arc::clone(flight), false),
+                None => {
+                    let flight = Arc::new(Flight::default());
+                    flights.insert(key.clone(), Arc::clone(&flight));
+                    (flight, true)
+                }
+            }
+        };
+
+        if !leading {
+            if let Some(mut response) = flight.wait().await {
+                counters.coalesced.fetch_add(1, Ordering::Relaxed);
+                tracing::trace!(method, "call coalesced");
+                response.id = id;
+                return response;
+            }
+            // the leader was cancelled, run the call ourselves
+            counters.executions.fetch_add(1, Ordering::Relaxed);
+            return call(params, id).await;
+        }
+
+        let mut leader = Leader {
+            policy: self,
+            key,
+            flight,
+            outcome: None,
+        };
+        counters.executions.fetch_add(1, Ordering::Relaxed);
+        let response = call(params, id).await;
+        leader.outcome = Some(response.clone());
+        response
+    }
+}
+
+#[derive(Debug, Default)]
+struct MethodCounters {
+    executions: AtomicU64,
+    coalesced: AtomicU64,
+}
+
+/// Coalescing statistics of one method
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoalesceStats {
+    pub method: String,
+    /// Calls that ran the method
+    pub executions: u64,
+    /// Calls answered with the result of another call
+    pub coalesced: u64,
+}
+
+type Methods = RwLock<BTreeMap<String, Arc<MethodCounters>>>;
+
+fn methods() -> &'static Methods {
+    static METHODS: OnceLock<Methods> = OnceLock::new();
+    METHODS.get_or_init(Default::default)
+}
+
+fn counters(method: &str) -> Arc<MethodCounters> {
+    if let Some(counters) = methods()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(method)
+    {
+        return Arc::clone(counters);
+    }
+    let mut methods = methods().write().unwrap_or_else(|e| e.into_inner());
+    Arc::clone(methods.entry(method.to_string()).or_default())
+}
+
+/// Statistics of every coalescing method seen so far, sorted by name
+pub fn snapshot() -> Vec<CoalesceStats> {
+    methods()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|(method, counters)| CoalesceStats {
+            method: method.clone(),
+            executions: counters.executions.load(Ordering::Relaxed),
+            coalesced: counters.coalesced.load(Ordering::Relaxed),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{JsonRPCMethod, MethodRegistry};
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    struct SlowReport(Arc<AtomicUsize>);
+
+    #[async_trait::async_trait]
+    impl JsonRPCMethod for SlowReport {
+        fn method_name(&self) -> &'static str {
+            "test_coalesce.report"
+        }
+
+        async fn call(&self, params: Option<serde_json::Value>, id: Option<RequestId>) -> Response {
+            let run = self.0.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Response::success(serde_json::json!({"params": params, "run": run}), id)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_identical_calls_share_one_execution() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let registry = Arc::new(
+            MethodRegistry::new(crate::register_methods![SlowReport(Arc::clone(&runs))])
+                .with_coalescing(CoalescePolicy::new().method("test_coalesce.*")),
+        );
+
+        let calls: Vec<_> = (0..10)
+            .map(|i| {
+                let registry = Arc::clone(&registry);
+                // two distinct param sets
+                let params = serde_json::json!({"day": i % 2});
+                tokio::spawn(async move {
+                    registry
+                        .call(
+                            "test_coalesce.report",
+                            Some(params),
+                            Some(serde_json::json!(i)),
+                        )
+                        .await
+                })
+            })
+            .collect();
+        for (i, call) in calls.into_iter().enumerate() {
+            let response = call.await.unwrap();
+            assert_eq!(response.id, Some(serde_json::json!(i)));
+            assert_eq!(response.result.unwrap()["params"]["day"], i % 2);
+        }
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+
+        let stats = snapshot()
+            .into_iter()
+            .find(|s| s.method == "test_coalesce.report")
+            .unwrap();
+        assert_eq!((stats.executions, stats.coalesced), (2, 8));
+
+        // nothing is cached once the flight has landed
+        registry
+            .call(
+                "test_coalesce.report",
+                Some(serde_json::json!({"day": 0})),
+                None,
+            )
+            .await;
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_leader_does_not_strand_followers() {
+        let policy = Arc::new(CoalescePolicy::new().method("slow"));
+        let leader = {
+            let policy = Arc::clone(&policy);
+            tokio::spawn(async move {
+                policy
+                    .run("slow", None, None, |_, _| async {
+                        tokio::time::sleep(Duration::from_secs(60)).await;
+                        Response::success(serde_json::json!("leader"), None)
+                    })
+                    .await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let follower = {
+            let policy = Arc::clone(&policy);
+            tokio::spawn(async move {
+                policy
+                    .run("slow", None, Some(serde_json::json!(2)), |_, id| async {
+                        Response::success(serde_json::json!("follower"), id)
+                    })
+                    .await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        leader.abort();
+
+        let response = tokio::time::timeout(Duration::from_secs(5), follower)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.result, Some(serde_json::json!("follower")));
+        assert!(!policy.is_eligible("other"));
+    }
+}
@@ -0,0 +1,219 @@
+//! Wire encodings of JSON-RPC messages.
+//!
+//! A [`Codec`] turns messages into payload bytes and back. [`JsonCodec`] is
+//! the default everywhere; with the `msgpack` and `cbor` features,
+//! [`MsgPackCodec`] and [`CborCodec`] carry the same messages in a binary
+//! encoding, typically around half the size for numeric payloads.
+//!
+//! Transports apply the codec at the frame boundary and keep handing JSON
+//! text to processors, so methods, batches and streams behave the same
+//! under every codec:
+//!
+//! - the TCP streaming and TLS transports take a codec with `.codec(..)`
+//!   on their server and client builders; binary codecs need
+//!   [`Framing::ContentLength`](crate::transports::framing::Framing),
+//!   since their payloads may contain newlines
+//! - the WebSocket server offers its codecs as subprotocols named after
+//!   [`Codec::name`] and sends binary messages to clients that pick one;
+//!   text messages are always read as JSON
+//!
+//! ```rust
+//! use ash_rpc::codec::{Codec, JsonCodec};
+//! use ash_rpc::{Message, RequestBuilder};
+//!
+//! let message = Message::Request(RequestBuilder::new("ping").id(1.into()).build());
+//! let bytes = JsonCodec.encode(&message).unwrap();
+//! assert_eq!(JsonCodec.decode(&bytes).unwrap().method(), Some("ping"));
+//! ```
+
+use crate::Message;
+use serde_json::Value;
+use std::fmt;
+
+/// A payload could not be encoded or decoded
+#[derive(Debug)]
+pub struct CodecError(String);
+
+impl CodecError {
+    pub fn new(message: impl fmt::Display) -> Self {
+        Self(message.to_string())
+    }
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "codec error: {}", self.0)
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+impl From<serde_json::Error> for CodecError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::new(error)
+    }
+}
+
+/// Encoding of message payloads
+///
+/// Implementations convert between payload bytes and JSON values; the
+/// message and JSON text conversions build on those two.
+pub trait Codec: Send + Sync + 'static {
+    /// Codec name, also the WebSocket subprotocol and the
+    /// [`codec_stats`](crate::transports::codec_stats) label
+    fn name(&self) -> &'static str;
+
+    /// Whether payloads are binary rather than UTF-8 text
+    fn is_binary(&self) -> bool {
+        true
+    }
+
+    /// Decode one payload, a single message or a batch
+    fn to_value(&self, bytes: &[u8]) -> Result<Value, CodecError>;
+
+    /// Encode one payload
+    fn to_bytes(&self, value: &Value) -> Result<Vec<u8>, CodecError>;
+
+    fn encode(&self, message: &Message) -> Result<Vec<u8>, CodecError> {
+        self.to_bytes(&serde_json::to_value(message)?)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Message, CodecError> {
+        Ok(serde_json::from_value(self.to_value(bytes)?)?)
+    }
+
+    /// A payload as JSON text, the form transports hand to processors
+    fn decode_json(&self, bytes: &[u8]) -> Result<String, CodecError> {
+        Ok(serde_json::to_string(&self.to_value(bytes)?)?)
+    }
+
+    /// JSON text produced by a processor as a payload
+    fn encode_json(&self, json: &str) -> Result<Vec<u8>, CodecError> {
+        self.to_bytes(&serde_json::from_str(json)?)
+    }
+}
+
+impl fmt::Debug for dyn Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// JSON text, the JSON-RPC default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn is_binary(&self) -> bool {
+        false
+    }
+
+    fn to_value(&self, bytes: &[u8]) -> Result<Value, CodecError> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+
+    fn to_bytes(&self, value: &Value) -> Result<Vec<u8>, CodecError> {
+        Ok(serde_json::to_vec(value)?)
+    }
+
+    fn encode(&self, message: &Message) -> Result<Vec<u8>, CodecError> {
+        Ok(serde_json::to_vec(message)?)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Message, CodecError> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+
+    fn decode_json(&self, bytes: &[u8]) -> Result<String, CodecError> {
+        String::from_utf8(bytes.to_vec()).map_err(CodecError::new)
+    }
+
+    fn encode_json(&self, json: &str) -> Result<Vec<u8>, CodecError> {
+        Ok(json.as_bytes().to_vec())
+    }
+}
+
+/// MessagePack, objects encoded as maps
+#[cfg(feature = "msgpack")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MsgPackCodec;
+
+#[cfg(feature = "msgpack")]
+impl Codec for MsgPackCodec {
+    fn name(&self) -> &'static str {
+        "msgpack"
+    }
+
+    fn to_value(&self, bytes: &[u8]) -> Result<Value, CodecError> {
+        rmp_serde::from_slice(bytes).map_err(CodecError::new)
+    }
+
+    fn to_bytes(&self, value: &Value) -> Result<Vec<u8>, CodecError> {
+        rmp_serde::to_vec_named(value).map_err(CodecError::new)
+    }
+}
+
+/// CBOR (RFC 8949)
+#[cfg(feature = "cbor")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CborCodec;
+
+#[cfg(feature = "cbor")]
+impl Codec for CborCodec {
+    fn name(&self) -> &'static str {
+        "cbor"
+    }
+
+    fn to_value(&self, bytes: &[u8]) -> Result<Value, CodecError> {
+        ciborium::from_reader(bytes).map_err(CodecError::new)
+    }
+
+    fn to_bytes(&self, value: &Value) -> Result<Vec<u8>, CodecError> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(value, &mut bytes).map_err(CodecError::new)?;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn round_trip(codec: &dyn Codec) -> usize {
+        let batch = r#"[{"jsonrpc":"2.0","method":"telemetry.push","params":{"cpu":[0.25,0.5],"host":"edge-7"},"id":41},{"jsonrpc":"2.0","result":null,"id":"x"}]"#;
+        let bytes = codec.encode_json(batch).unwrap();
+        let decoded: Value = serde_json::from_str(&codec.decode_json(&bytes).unwrap()).unwrap();
+        assert_eq!(decoded, serde_json::from_str::<Value>(batch).unwrap());
+
+        let message = Message::Request(
+            crate::RequestBuilder::new("ping")
+                .params(json!([1, -2, 3.5]))
+                .id(json!(7))
+                .build(),
+        );
+        let decoded = codec.decode(&codec.encode(&message).unwrap()).unwrap();
+        assert_eq!(decoded.method(), Some("ping"));
+        assert_eq!(decoded.id(), Some(&json!(7)));
+        bytes.len()
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        round_trip(&JsonCodec);
+        assert!(JsonCodec.decode_json(&[0xff]).is_err());
+    }
+
+    #[cfg(all(feature = "msgpack", feature = "cbor"))]
+    #[test]
+    fn test_binary_codecs_round_trip() {
+        let json = round_trip(&JsonCodec);
+        assert!(round_trip(&MsgPackCodec) < json);
+        assert!(round_trip(&CborCodec) < json);
+        assert!(MsgPackCodec.decode(b"\xc1").is_err());
+    }
+}
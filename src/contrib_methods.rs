@@ -0,0 +1,366 @@
+//! Ready-made methods for new services
+//!
+//! A baseline set of operational methods, each with typed params and
+//! result, and OpenAPI metadata for the generated spec:
+//!
+//! | Method | Params | Result |
+//! |---|---|---|
+//! | `system.info` | none | [`SystemInfo`] |
+//! | `system.time` | none | [`SystemTime`] |
+//! | `echo` | any value | the same value |
+//! | `delay` | [`DelayParams`] | [`DelayResult`] |
+//! | `version` | none | [`VersionInfo`] |
+//! | `metrics.get` | optional `format`, `prefix` | Prometheus text or JSON (`prometheus` feature) |
+//!
+//! ```rust
+//! use ash_rpc::contrib_methods::{self, VersionMethod};
+//! use ash_rpc::MethodRegistry;
+//!
+//! let registry = MethodRegistry::new(contrib_methods::baseline("billing"))
+//!     .add_method(Box::new(VersionMethod::new("billing", "1.4.2")));
+//! assert!(registry.has_method("system.info"));
+//! ```
+
+use crate::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+/// Result of `system.info`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SystemInfo {
+    pub service: String,
+    pub os: String,
+    pub arch: String,
+    pub pid: u32,
+    /// Seconds since the method was created, i.e. roughly since startup
+    pub uptime_secs: u64,
+}
+
+/// `system.info`: host and process details
+pub struct SystemInfoMethod {
+    service: String,
+    started: Instant,
+}
+
+impl SystemInfoMethod {
+    pub fn new(service: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+            started: Instant::now(),
+        }
+    }
+
+    fn info(&self) -> SystemInfo {
+        SystemInfo {
+            service: self.service.clone(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            pid: std::process::id(),
+            uptime_secs: self.started.elapsed().as_secs(),
+        }
+    }
+}
+
+#[crate::async_trait]
+impl JsonRPCMethod for SystemInfoMethod {
+    fn method_name(&self) -> &'static str {
+        "system.info"
+    }
+
+    fn is_builtin(&self) -> bool {
+        true
+    }
+
+    async fn call(&self, _params: Option<serde_json::Value>, id: Option<RequestId>) -> Response {
+        rpc_success!(self.info(), id)
+    }
+
+    fn openapi_components(&self) -> OpenApiMethodSpec {
+        OpenApiMethodSpec::new(self.method_name())
+            .with_summary("Host and process information")
+            .with_tag("system")
+            .with_result(json!({
+                "type": "object",
+                "required": ["service", "os", "arch", "pid", "uptime_secs"],
+                "properties": {
+                    "service": {"type": "string"},
+                    "os": {"type": "string"},
+                    "arch": {"type": "string"},
+                    "pid": {"type": "integer", "minimum": 0},
+                    "uptime_secs": {"type": "integer", "minimum": 0}
+                }
+            }))
+    }
+}
+
+/// Result of `system.time`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SystemTime {
+    pub unix_secs: u64,
+    pub unix_millis: u64,
+}
+
+/// `system.time`: the server clock, for skew checks
+pub struct SystemTimeMethod;
+
+#[crate::async_trait]
+impl JsonRPCMethod for SystemTimeMethod {
+    fn method_name(&self) -> &'static str {
+        "system.time"
+    }
+
+    fn is_builtin(&self) -> bool {
+        true
+    }
+
+    async fn call(&self, _params: Option<serde_json::Value>, id: Option<RequestId>) -> Response {
+        let now = std::time::SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let time = SystemTime {
+            unix_secs: now.as_secs(),
+            unix_millis: now.as_millis() as u64,
+        };
+        rpc_success!(time, id)
+    }
+
+    fn openapi_components(&self) -> OpenApiMethodSpec {
+        OpenApiMethodSpec::new(self.method_name())
+            .with_summary("Current server time")
+            .with_tag("system")
+            .with_result(json!({
+                "type": "object",
+                "required": ["unix_secs", "unix_millis"],
+                "properties": {
+                    "unix_secs": {"type": "integer", "minimum": 0},
+                    "unix_millis": {"type": "integer", "minimum": 0}
+                }
+            }))
+    }
+}
+
+/// `echo`: returns its params unchanged
+pub struct EchoMethod;
+
+#[crate::async_trait]
+impl JsonRPCMethod for EchoMethod {
+    fn method_name(&self) -> &'static str {
+        "echo"
+    }
+
+    async fn call(&self, params: Option<serde_json::Value>, id: Option<RequestId>) -> Response {
+        rpc_success!(params.unwrap_or(serde_json::Value::Null), id)
+    }
+
+    fn openapi_components(&self) -> OpenApiMethodSpec {
+        OpenApiMethodSpec::new(self.method_name())
+            .with_summary("Return the params unchanged")
+            .with_tag("diagnostics")
+            .with_parameters(json!({}))
+            .with_result(json!({}))
+            .with_example(
+                OpenApiExample::new("object")
+                    .with_params(json!({"hello": "world"}))
+                    .with_result(json!({"hello": "world"})),
+            )
+    }
+}
+
+/// Params of `delay`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DelayParams {
+    pub ms: u64,
+}
+
+/// Result of `delay`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DelayResult {
+    pub slept_ms: u64,
+}
+
+/// `delay`: sleeps before answering, capped at a maximum
+pub struct DelayMethod {
+    max: Duration,
+}
+
+impl DelayMethod {
+    /// Accept delays of up to 5 seconds
+    pub fn new() -> Self {
+        Self {
+            max: Duration::from_secs(5),
+        }
+    }
+
+    pub fn max_delay(mut self, max: Duration) -> Self {
+        self.max = max;
+        self
+    }
+}
+
+impl Default for DelayMethod {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[crate::async_trait]
+impl JsonRPCMethod for DelayMethod {
+    fn method_name(&self) -> &'static str {
+        "delay"
+    }
+
+    async fn call(&self, params: Option<serde_json::Value>, id: Option<RequestId>) -> Response {
+        let params = rpc_params!(params, id => DelayParams);
+        let delay = Duration::from_millis(params.ms);
+        if delay > self.max {
+            return rpc_invalid_params!(format!("ms must not exceed {}", self.max.as_millis()), id);
+        }
+        tokio::time::sleep(delay).await;
+        rpc_success!(
+            DelayResult {
+                slept_ms: params.ms
+            },
+            id
+        )
+    }
+
+    fn openapi_components(&self) -> OpenApiMethodSpec {
+        OpenApiMethodSpec::new(self.method_name())
+            .with_summary("Sleep before answering")
+            .with_tag("diagnostics")
+            .with_parameters(json!({
+                "type": "object",
+                "required": ["ms"],
+                "properties": {
+                    "ms": {"type": "integer", "minimum": 0, "maximum": self.max.as_millis() as u64}
+                }
+            }))
+            .with_result(json!({
+                "type": "object",
+                "required": ["slept_ms"],
+                "properties": {"slept_ms": {"type": "integer", "minimum": 0}}
+            }))
+            .with_error(OpenApiError::new(
+                error_codes::INVALID_PARAMS,
+                "ms exceeds the configured maximum",
+            ))
+    }
+}
+
+/// Result of `version`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionInfo {
+    pub name: String,
+    pub version: String,
+    /// Version of ash-rpc the service was built with
+    pub ash_rpc: String,
+}
+
+/// `version`: service name and version
+pub struct VersionMethod {
+    info: VersionInfo,
+}
+
+impl VersionMethod {
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            info: VersionInfo {
+                name: name.into(),
+                version: version.into(),
+                ash_rpc: env!("CARGO_PKG_VERSION").to_string(),
+            },
+        }
+    }
+}
+
+#[crate::async_trait]
+impl JsonRPCMethod for VersionMethod {
+    fn method_name(&self) -> &'static str {
+        "version"
+    }
+
+    async fn call(&self, _params: Option<serde_json::Value>, id: Option<RequestId>) -> Response {
+        rpc_success!(&self.info, id)
+    }
+
+    fn static_result(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(&self.info).ok()
+    }
+
+    fn openapi_components(&self) -> OpenApiMethodSpec {
+        OpenApiMethodSpec::new(self.method_name())
+            .with_summary("Service name and version")
+            .with_tag("system")
+            .with_result(json!({
+                "type": "object",
+                "required": ["name", "version", "ash_rpc"],
+                "properties": {
+                    "name": {"type": "string"},
+                    "version": {"type": "string"},
+                    "ash_rpc": {"type": "string"}
+                }
+            }))
+    }
+}
+
+/// `metrics.get` now lives next to the metrics it exposes
+#[cfg(feature = "prometheus")]
+pub use crate::observability::prometheus::MetricsGetMethod;
+
+/// `system.info`, `system.time`, `echo` and `delay` with default settings.
+///
+/// `version` and `metrics.get` need arguments and are registered separately.
+pub fn baseline(service: impl Into<String>) -> Vec<Box<dyn JsonRPCMethod>> {
+    register_methods![
+        SystemInfoMethod::new(service),
+        SystemTimeMethod,
+        EchoMethod,
+        DelayMethod::new()
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_typed_results() {
+        let response = SystemInfoMethod::new("svc")
+            .call(None, Some(json!(1)))
+            .await;
+        let info: SystemInfo = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert_eq!(info.service, "svc");
+        assert_eq!(info.pid, std::process::id());
+
+        let response = VersionMethod::new("svc", "1.0.0").call(None, None).await;
+        let version: VersionInfo = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert_eq!(version.version, "1.0.0");
+        assert_eq!(version.ash_rpc, env!("CARGO_PKG_VERSION"));
+    }
+
+    #[tokio::test]
+    async fn test_delay_bounds() {
+        let method = DelayMethod::new().max_delay(Duration::from_millis(20));
+
+        let ok = method.call(Some(json!({"ms": 5})), Some(json!(1))).await;
+        assert_eq!(ok.result, Some(json!({"slept_ms": 5})));
+
+        let too_long = method.call(Some(json!({"ms": 500})), Some(json!(2))).await;
+        assert_eq!(too_long.error.unwrap().code, error_codes::INVALID_PARAMS);
+
+        let missing = method.call(None, Some(json!(3))).await;
+        assert!(missing.is_error());
+    }
+
+    #[tokio::test]
+    async fn test_baseline_registry() {
+        let registry = MethodRegistry::new(baseline("svc"));
+        let response = registry
+            .call("echo", Some(json!([1, 2])), Some(json!(1)))
+            .await;
+        assert_eq!(response.result, Some(json!([1, 2])));
+        assert!(registry.has_method("system.time"));
+        assert!(!registry.has_method("version"));
+    }
+}
@@ -0,0 +1,249 @@
+//! Dead letter queue for notifications whose handling failed.
+//!
+//! Notifications have no response to carry an error back to the sender, so
+//! a failed handler would otherwise go unnoticed. A [`DeadLetterQueue`]
+//! retries the handler up to its attempt limit and then records the
+//! notification, the last error and the attempt count in a
+//! [`DeadLetterSink`]. Operators inspect recorded letters and requeue them
+//! once the cause is fixed.
+//!
+//! ```rust
+//! use ash_rpc::dead_letter::{DeadLetterQueue, MemoryDeadLetterSink};
+//! use ash_rpc::builtins::{BuiltinConfig, BuiltinMethods};
+//! use ash_rpc::MethodRegistry;
+//!
+//! let registry = MethodRegistry::empty()
+//!     .with_builtins(BuiltinConfig::new(BuiltinMethods::ADMIN))
+//!     .with_dead_letters(DeadLetterQueue::new(MemoryDeadLetterSink::new(1000)).max_attempts(3));
+//! assert!(registry.has_method("admin.dead_letters"));
+//! ```
+//!
+//! With the `admin` built-ins enabled, a registry serves the letters under
+//! `admin.dead_letters` and requeues one with `admin.requeue_dead_letter`
+//! (`{"id": ...}`).
+
+use crate::{Error, Notification};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A notification that failed every delivery attempt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub id: String,
+    pub notification: Notification,
+    /// Error of the last attempt
+    pub error: Error,
+    /// Attempts so far, requeued attempts included
+    pub attempts: u32,
+    /// Time of the last attempt in milliseconds since the Unix epoch
+    pub failed_at_ms: u64,
+}
+
+/// Storage of dead letters
+pub trait DeadLetterSink: Send + Sync {
+    fn record(&self, letter: DeadLetter);
+
+    /// Recorded letters, oldest first
+    fn list(&self) -> Vec<DeadLetter>;
+
+    /// Remove and return the letter with `id`
+    fn take(&self, id: &str) -> Option<DeadLetter>;
+}
+
+/// In-memory sink keeping the most recent `capacity` letters
+pub struct MemoryDeadLetterSink {
+    capacity: usize,
+    letters: Mutex<VecDeque<DeadLetter>>,
+}
+
+impl MemoryDeadLetterSink {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            letters: Mutex::new(VecDeque::new()),
+        }
+    }
+}
+
+impl DeadLetterSink for MemoryDeadLetterSink {
+    fn record(&self, letter: DeadLetter) {
+        let mut letters = self.letters.lock().unwrap_or_else(|e| e.into_inner());
+        if self.capacity == 0 {
+            return;
+        }
+        if letters.len() >= self.capacity
+            && let Some(dropped) = letters.pop_front()
+        {
+            tracing::warn!(id = %dropped.id, method = %dropped.notification.method, "dead letter sink full, dropping oldest letter");
+        }
+        letters.push_back(letter);
+    }
+
+    fn list(&self) -> Vec<DeadLetter> {
+        let letters = self.letters.lock().unwrap_or_else(|e| e.into_inner());
+        letters.iter().cloned().collect()
+    }
+
+    fn take(&self, id: &str) -> Option<DeadLetter> {
+        let mut letters = self.letters.lock().unwrap_or_else(|e| e.into_inner());
+        let index = letters.iter().position(|letter| letter.id == id)?;
+        letters.remove(index)
+    }
+}
+
+/// Retry policy and sink for failed notifications
+pub struct DeadLetterQueue {
+    sink: Arc<dyn DeadLetterSink>,
+    max_attempts: u32,
+}
+
+impl DeadLetterQueue {
+    /// Record failures in `sink` after a single attempt
+    pub fn new<S: DeadLetterSink + 'static>(sink: S) -> Self {
+        Self {
+            sink: Arc::new(sink),
+            max_attempts: 1,
+        }
+    }
+
+    /// Attempts made before a notification is dead-lettered, at least 1
+    ///
+    /// Retries run back to back, so they only help against failures that
+    /// clear up immediately, such as a lost race; anything slower is better
+    /// requeued later.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    pub fn sink(&self) -> &Arc<dyn DeadLetterSink> {
+        &self.sink
+    }
+
+    /// Run `handle` until it succeeds or the attempts run out, recording the
+    /// notification as a dead letter in the latter case
+    ///
+    /// Returns whether the notification was handled.
+    pub async fn deliver<F, Fut>(&self, notification: Notification, handle: F) -> bool
+    where
+        F: Fn(Notification) -> Fut,
+        Fut: Future<Output = Result<(), Error>>,
+    {
+        self.attempt(notification, 0, handle).await
+    }
+
+    /// Take the letter with `id` out of the sink and deliver it again
+    ///
+    /// Returns `None` when no such letter is recorded; a letter failing again
+    /// is recorded with its attempts carried over.
+    pub async fn requeue<F, Fut>(&self, id: &str, handle: F) -> Option<bool>
+    where
+        F: Fn(Notification) -> Fut,
+        Fut: Future<Output = Result<(), Error>>,
+    {
+        let letter = self.sink.take(id)?;
+        tracing::info!(id, method = %letter.notification.method, "requeueing dead letter");
+        Some(
+            self.attempt(letter.notification, letter.attempts, handle)
+                .await,
+        )
+    }
+
+    async fn attempt<F, Fut>(&self, notification: Notification, previous: u32, handle: F) -> bool
+    where
+        F: Fn(Notification) -> Fut,
+        Fut: Future<Output = Result<(), Error>>,
+    {
+        let mut error = None;
+        for attempt in 1..=self.max_attempts {
+            match handle(notification.clone()).await {
+                Ok(()) => return true,
+                Err(e) => {
+                    tracing::debug!(method = %notification.method, attempt, error = %e, "notification handling failed");
+                    error = Some(e);
+                }
+            }
+        }
+        let Some(error) = error else {
+            return true;
+        };
+
+        let letter = DeadLetter {
+            id: uuid::Uuid::new_v4().to_string(),
+            attempts: previous + self.max_attempts,
+            failed_at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            notification,
+            error,
+        };
+        tracing::warn!(
+            id = %letter.id,
+            method = %letter.notification.method,
+            attempts = letter.attempts,
+            error = %letter.error,
+            "notification dead-lettered"
+        );
+        self.sink.record(letter);
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error_codes;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn test_failed_notification_recorded_and_requeued() {
+        let queue = DeadLetterQueue::new(MemoryDeadLetterSink::new(10)).max_attempts(3);
+        let calls = AtomicU32::new(0);
+        let failing = |_: Notification| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err(Error::new(error_codes::INTERNAL_ERROR, "store offline")) }
+        };
+
+        assert!(
+            !queue
+                .deliver(Notification::new("orders.placed"), failing)
+                .await
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        let letters = queue.sink().list();
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].attempts, 3);
+        assert_eq!(letters[0].error.message, "store offline");
+
+        // failing again carries the attempts over
+        assert_eq!(queue.requeue(&letters[0].id, failing).await, Some(false));
+        let letters = queue.sink().list();
+        assert_eq!(letters[0].attempts, 6);
+
+        let succeeding = |_: Notification| async { Ok(()) };
+        assert_eq!(queue.requeue(&letters[0].id, succeeding).await, Some(true));
+        assert!(queue.sink().list().is_empty());
+        assert_eq!(queue.requeue(&letters[0].id, succeeding).await, None);
+    }
+
+    #[test]
+    fn test_memory_sink_drops_oldest() {
+        let sink = MemoryDeadLetterSink::new(2);
+        for id in ["a", "b", "c"] {
+            sink.record(DeadLetter {
+                id: id.to_string(),
+                notification: Notification::new("n"),
+                error: Error::new(error_codes::INTERNAL_ERROR, "failed"),
+                attempts: 1,
+                failed_at_ms: 0,
+            });
+        }
+        let ids: Vec<_> = sink.list().into_iter().map(|letter| letter.id).collect();
+        assert_eq!(ids, ["b", "c"]);
+        assert!(sink.take("a").is_none());
+    }
+}
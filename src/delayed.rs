@@ -0,0 +1,372 @@
+//! Delayed and scheduled message execution.
+//!
+//! [`DelayedExecution`] holds requests and notifications until their firing
+//! time and then hands them to a processor, like a client would have sent
+//! them at that moment. Responses are discarded; a job answered with
+//! [`RETRY_LATER`](crate::error_codes::RETRY_LATER) is tried again after
+//! [`retry_delay`](DelayedExecution::retry_delay).
+//!
+//! With a [`Cache`] store the queue survives restarts: jobs are written
+//! before `schedule_*` returns and removed only after they ran, so after
+//! [`restore`](DelayedExecution::restore) every job runs at least once. A job
+//! that was running when the process stopped runs again. The queue is stored
+//! as a single entry, so it suits hundreds or thousands of pending jobs, and
+//! a store key must be owned by a single instance.
+//!
+//! ```rust,no_run
+//! use ash_rpc::cache::MemoryCache;
+//! use ash_rpc::delayed::DelayedExecution;
+//! use ash_rpc::{Message, MethodRegistry, Notification};
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//!
+//! # async fn example() -> Result<(), ash_rpc::cache::CacheError> {
+//! let scheduler = Arc::new(
+//!     DelayedExecution::new(Arc::new(MethodRegistry::empty()))
+//!         .with_store(Arc::new(MemoryCache::new(16)), "jobs"),
+//! );
+//! scheduler.restore().await?;
+//! tokio::spawn(Arc::clone(&scheduler).run());
+//!
+//! let reminder = Notification::new("reminders.send").with_params(serde_json::json!({"user": 7}));
+//! scheduler
+//!     .schedule_after(Message::Notification(reminder), Duration::from_secs(3600))
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::cache::{Cache, CacheError};
+use crate::{Message, MessageProcessor, error_codes};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::Notify;
+
+/// A message waiting for its firing time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DelayedJob {
+    pub id: String,
+    pub message: Message,
+    /// Firing time in milliseconds since the Unix epoch
+    pub fire_at_ms: u64,
+    /// Runs answered with `RETRY_LATER` so far
+    #[serde(default)]
+    pub attempts: u32,
+}
+
+fn unix_ms(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+struct Entry {
+    job: DelayedJob,
+    running: bool,
+}
+
+/// Queue of messages executed against a processor at a later time
+pub struct DelayedExecution {
+    processor: Arc<dyn MessageProcessor + Send + Sync>,
+    store: Option<(Arc<dyn Cache>, String)>,
+    retry_delay: Duration,
+    max_attempts: u32,
+    jobs: Mutex<HashMap<String, Entry>>,
+    /// Keeps store writes in snapshot order
+    persisting: tokio::sync::Mutex<()>,
+    changed: Notify,
+}
+
+impl DelayedExecution {
+    /// In-memory queue, retrying `RETRY_LATER` answers 5 times, 30 seconds apart
+    pub fn new(processor: Arc<dyn MessageProcessor + Send + Sync>) -> Self {
+        Self {
+            processor,
+            store: None,
+            retry_delay: Duration::from_secs(30),
+            max_attempts: 5,
+            jobs: Mutex::new(HashMap::new()),
+            persisting: tokio::sync::Mutex::new(()),
+            changed: Notify::new(),
+        }
+    }
+
+    /// Persist the queue under `key` in `store`
+    pub fn with_store(mut self, store: Arc<dyn Cache>, key: impl Into<String>) -> Self {
+        self.store = Some((store, key.into()));
+        self
+    }
+
+    /// Delay before a job answered with `RETRY_LATER` runs again
+    pub fn retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Runs of a job before it is dropped, counting the first
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    fn jobs(&self) -> std::sync::MutexGuard<'_, HashMap<String, Entry>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Load the jobs persisted in the store, returning how many were added
+    pub async fn restore(&self) -> Result<usize, CacheError> {
+        let Some((store, key)) = &self.store else {
+            return Ok(0);
+        };
+        let stored: Vec<DelayedJob> = match store.get(key).await? {
+            Some(value) => serde_json::from_value(value)
+                .map_err(|e| CacheError::Backend(format!("invalid delayed jobs: {e}")))?,
+            None => Vec::new(),
+        };
+        let restored = {
+            let mut jobs = self.jobs();
+            let before = jobs.len();
+            for job in stored {
+                jobs.entry(job.id.clone()).or_insert(Entry {
+                    job,
+                    running: false,
+                });
+            }
+            jobs.len() - before
+        };
+        tracing::info!(restored, "delayed jobs restored");
+        self.changed.notify_one();
+        Ok(restored)
+    }
+
+    async fn persist(&self) -> Result<(), CacheError> {
+        let Some((store, key)) = &self.store else {
+            return Ok(());
+        };
+        let _persisting = self.persisting.lock().await;
+        let jobs: Vec<DelayedJob> = self
+            .jobs()
+            .values()
+            .map(|entry| entry.job.clone())
+            .collect();
+        let jobs = serde_json::to_value(jobs)
+            .map_err(|e| CacheError::Backend(format!("unserializable delayed jobs: {e}")))?;
+        store.insert(key, jobs, None).await
+    }
+
+    /// Run `message` at `at`, returning the job id
+    pub async fn schedule_at(
+        &self,
+        message: Message,
+        at: SystemTime,
+    ) -> Result<String, CacheError> {
+        let id = uuid::Uuid::new_v4().to_string();
+        let job = DelayedJob {
+            id: id.clone(),
+            message,
+            fire_at_ms: unix_ms(at),
+            attempts: 0,
+        };
+        self.jobs().insert(
+            id.clone(),
+            Entry {
+                job,
+                running: false,
+            },
+        );
+        if let Err(e) = self.persist().await {
+            self.jobs().remove(&id);
+            return Err(e);
+        }
+        self.changed.notify_one();
+        Ok(id)
+    }
+
+    /// Run `message` once `delay` has passed, returning the job id
+    pub async fn schedule_after(
+        &self,
+        message: Message,
+        delay: Duration,
+    ) -> Result<String, CacheError> {
+        self.schedule_at(message, SystemTime::now() + delay).await
+    }
+
+    /// Drop a job that has not started; returns whether it was pending
+    pub async fn cancel(&self, id: &str) -> Result<bool, CacheError> {
+        let removed = {
+            let mut jobs = self.jobs();
+            match jobs.get(id) {
+                Some(entry) if !entry.running => jobs.remove(id).is_some(),
+                _ => false,
+            }
+        };
+        if removed {
+            self.persist().await?;
+        }
+        Ok(removed)
+    }
+
+    /// Jobs waiting or running
+    pub fn pending(&self) -> Vec<DelayedJob> {
+        let mut jobs: Vec<DelayedJob> = self.jobs().values().map(|e| e.job.clone()).collect();
+        jobs.sort_by(|a, b| (a.fire_at_ms, &a.id).cmp(&(b.fire_at_ms, &b.id)));
+        jobs
+    }
+
+    /// Fire due jobs; never returns
+    ///
+    /// Due jobs run concurrently, each on its own task.
+    pub async fn run(self: Arc<Self>) {
+        loop {
+            let now = unix_ms(SystemTime::now());
+            let (due, next) = {
+                let mut jobs = self.jobs();
+                let mut due = Vec::new();
+                let mut next: Option<u64> = None;
+                for entry in jobs.values_mut().filter(|entry| !entry.running) {
+                    if entry.job.fire_at_ms <= now {
+                        entry.running = true;
+                        due.push(entry.job.clone());
+                    } else {
+                        next = Some(
+                            next.map_or(entry.job.fire_at_ms, |n| n.min(entry.job.fire_at_ms)),
+                        );
+                    }
+                }
+                (due, next)
+            };
+
+            for job in due {
+                tokio::spawn(Arc::clone(&self).execute(job));
+            }
+
+            let changed = self.changed.notified();
+            match next {
+                Some(next) => {
+                    let wait = Duration::from_millis(next.saturating_sub(now));
+                    let _ = tokio::time::timeout(wait, changed).await;
+                }
+                None => changed.await,
+            }
+        }
+    }
+
+    async fn execute(self: Arc<Self>, job: DelayedJob) {
+        let method = job.message.method().unwrap_or_default().to_string();
+        tracing::debug!(id = %job.id, method = %method, "running delayed job");
+        let response = self.processor.process_message(job.message.clone()).await;
+        let retry = response
+            .as_ref()
+            .and_then(|response| response.error.as_ref())
+            .is_some_and(|error| error.code == error_codes::RETRY_LATER)
+            && job.attempts + 1 < self.max_attempts;
+
+        {
+            let mut jobs = self.jobs();
+            if retry {
+                if let Some(entry) = jobs.get_mut(&job.id) {
+                    entry.running = false;
+                    entry.job.attempts += 1;
+                    entry.job.fire_at_ms = unix_ms(SystemTime::now() + self.retry_delay);
+                }
+                tracing::debug!(id = %job.id, method = %method, "delayed job will be retried");
+            } else {
+                jobs.remove(&job.id);
+            }
+        }
+        if let Err(e) = self.persist().await {
+            tracing::warn!(id = %job.id, error = %e, "failed to persist delayed jobs");
+        }
+        self.changed.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::MemoryCache;
+    use crate::{Notification, Response};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Asks for a retry on the first call of every method, then records it
+    #[derive(Default)]
+    struct Recorder {
+        calls: AtomicUsize,
+        seen: Mutex<Vec<String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl MessageProcessor for Recorder {
+        async fn process_message(&self, message: Message) -> Option<Response> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                return Some(crate::rpc_error!(error_codes::RETRY_LATER, "busy", None));
+            }
+            let method = message.method().unwrap_or_default().to_string();
+            self.seen.lock().unwrap().push(method);
+            None
+        }
+    }
+
+    fn notification(method: &str) -> Message {
+        Message::Notification(Notification::new(method))
+    }
+
+    #[tokio::test]
+    async fn test_jobs_fire_in_order_and_retry() {
+        let recorder = Arc::new(Recorder::default());
+        let scheduler = Arc::new(
+            DelayedExecution::new(recorder.clone()).retry_delay(Duration::from_millis(10)),
+        );
+        tokio::spawn(Arc::clone(&scheduler).run());
+
+        scheduler
+            .schedule_after(notification("second"), Duration::from_millis(60))
+            .await
+            .unwrap();
+        scheduler
+            .schedule_after(notification("first"), Duration::from_millis(20))
+            .await
+            .unwrap();
+        let cancelled = scheduler
+            .schedule_after(notification("never"), Duration::from_millis(30))
+            .await
+            .unwrap();
+        assert!(scheduler.cancel(&cancelled).await.unwrap());
+        assert_eq!(scheduler.pending().len(), 2);
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(*recorder.seen.lock().unwrap(), ["first", "second"]);
+        assert_eq!(recorder.calls.load(Ordering::SeqCst), 3);
+        assert!(scheduler.pending().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_restored_jobs_run_after_restart() {
+        let store: Arc<dyn Cache> = Arc::new(MemoryCache::new(16));
+        let first = DelayedExecution::new(Arc::new(Recorder::default()))
+            .with_store(Arc::clone(&store), "jobs");
+        first
+            .schedule_at(notification("report"), SystemTime::now())
+            .await
+            .unwrap();
+        // the first instance stops before running anything
+        drop(first);
+
+        let recorder = Arc::new(Recorder::default());
+        recorder.calls.store(1, Ordering::SeqCst);
+        let second = Arc::new(
+            DelayedExecution::new(recorder.clone()).with_store(Arc::clone(&store), "jobs"),
+        );
+        assert_eq!(second.restore().await.unwrap(), 1);
+        tokio::spawn(Arc::clone(&second).run());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(*recorder.seen.lock().unwrap(), ["report"]);
+        assert_eq!(
+            store.get("jobs").await.unwrap(),
+            Some(serde_json::json!([]))
+        );
+    }
+}
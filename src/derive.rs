@@ -0,0 +1,119 @@
+//! JSON-RPC methods from plain functions.
+//!
+//! [`rpc_method`] turns a function into a unit struct implementing
+//! [`JsonRPCMethod`](crate::JsonRPCMethod), named after the function in
+//! `UpperCamelCase`:
+//!
+//! - each argument is one param, read from by-position (array) or by-name
+//!   (object) params; `Option` arguments may be left out, and a
+//!   `&CallContext<'_>` argument receives the call context instead
+//! - a `Result<T, E>` return answers with `T` or the error converted with
+//!   `Error::from`, any other return type always succeeds
+//! - the first paragraph of the doc comment becomes the OpenAPI summary,
+//!   the rest the description, and the arguments the params schema
+//!
+//! `#[rpc_method(name = "...")]` overrides the method name. With `schema`,
+//! the params and result schemas are the ones `schemars` derives, which
+//! needs the `schemars` feature and `JsonSchema` on every argument and the
+//! result.
+//!
+//! ```rust
+//! use ash_rpc::derive::rpc_method;
+//! use ash_rpc::*;
+//!
+//! /// Add two numbers.
+//! ///
+//! /// Overflow is reported as an error.
+//! #[rpc_method(name = "math.add")]
+//! async fn add(a: i64, b: Option<i64>) -> Result<i64, Error> {
+//!     a.checked_add(b.unwrap_or(0))
+//!         .ok_or_else(|| Error::new(error_codes::INVALID_PARAMS, "overflow"))
+//! }
+//!
+//! let spec = JsonRPCMethod::openapi_components(&Add);
+//! assert_eq!(spec.summary.as_deref(), Some("Add two numbers."));
+//! assert_eq!(spec.parameters.unwrap()["required"][0], "a");
+//!
+//! let registry = MethodRegistry::new(register_methods![Add]);
+//! assert!(registry.has_method("math.add"));
+//! ```
+
+use crate::{Error, RequestId, Response, error_codes};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+pub use ash_rpc_derive::rpc_method;
+
+#[doc(hidden)]
+pub use serde_json;
+
+/// Params of one call, as the generated code reads them
+#[doc(hidden)]
+pub struct Params(Option<Value>);
+
+impl Params {
+    /// Fails unless `params` is absent, an object, or an array of at most
+    /// `names.len()` values
+    pub fn new(params: Option<Value>, names: &[&str]) -> Result<Self, Error> {
+        match &params {
+            None | Some(Value::Null | Value::Object(_)) => Ok(Self(params)),
+            Some(Value::Array(values)) if values.len() <= names.len() => Ok(Self(params)),
+            Some(Value::Array(values)) => Err(Error::new(
+                error_codes::INVALID_PARAMS,
+                format!(
+                    "Invalid params: expected at most {} params, got {}",
+                    names.len(),
+                    values.len()
+                ),
+            )),
+            Some(_) => Err(Error::new(
+                error_codes::INVALID_PARAMS,
+                "Invalid params: expected an array or an object",
+            )),
+        }
+    }
+
+    /// The param at `index` or called `name`; a missing one reads as `null`
+    pub fn get<T: DeserializeOwned>(&self, index: usize, name: &str) -> Result<T, Error> {
+        let value = match &self.0 {
+            Some(Value::Array(values)) => values.get(index),
+            Some(Value::Object(values)) => values.get(name),
+            _ => None,
+        };
+        serde_json::from_value(value.cloned().unwrap_or(Value::Null)).map_err(|e| {
+            Error::new(
+                error_codes::INVALID_PARAMS,
+                format!("Invalid params: `{name}`: {e}"),
+            )
+        })
+    }
+}
+
+/// Response to a call that produced `output`
+#[doc(hidden)]
+pub fn respond<T: Serialize>(
+    method: &str,
+    output: Result<T, Error>,
+    id: Option<RequestId>,
+) -> Response {
+    match output.map(serde_json::to_value) {
+        Ok(Ok(result)) => Response::success(result, id),
+        Ok(Err(e)) => {
+            tracing::warn!(method = %method, error = %e, "result not serializable");
+            crate::rpc_error!(
+                error_codes::INTERNAL_ERROR,
+                "Result could not be serialized",
+                id
+            )
+        }
+        Err(error) => Response::error(error, id),
+    }
+}
+
+/// The `schemars` schema of `T`
+#[cfg(feature = "schemars")]
+#[doc(hidden)]
+pub fn schema<T: schemars::JsonSchema>() -> Value {
+    schemars::schema_for!(T).to_value()
+}
@@ -0,0 +1,251 @@
+//! Application error codes.
+//!
+//! JSON-RPC reserves -32768 to -32000 for protocol and server errors, so
+//! application errors need codes of their own. An [`ErrorCatalog`] lists
+//! them once, each with a message template, and builds every [`Error`]
+//! with that code from the same template. Typed application errors
+//! implement [`RpcError`] to name their code and data, and convert with
+//! [`ErrorCatalog::render`], typically in an `impl From<MyError> for Error`
+//! so they work with `?` in typed methods and with `rpc_try!(result, id => Error)`.
+//!
+//! ```rust
+//! use ash_rpc::error_catalog::{ErrorCatalog, RpcError};
+//! use ash_rpc::Error;
+//! use std::sync::LazyLock;
+//!
+//! static ERRORS: LazyLock<ErrorCatalog> = LazyLock::new(|| {
+//!     ErrorCatalog::new()
+//!         .register(1001, "Insufficient funds: {available} available, {required} required")
+//!         .register(1002, "Account {account} is frozen")
+//! });
+//!
+//! #[derive(Debug)]
+//! enum PaymentError {
+//!     InsufficientFunds { available: u64, required: u64 },
+//!     Frozen(String),
+//! }
+//!
+//! impl std::fmt::Display for PaymentError {
+//!     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+//!         write!(f, "{self:?}")
+//!     }
+//! }
+//!
+//! impl RpcError for PaymentError {
+//!     fn code(&self) -> i32 {
+//!         match self {
+//!             PaymentError::InsufficientFunds { .. } => 1001,
+//!             PaymentError::Frozen(_) => 1002,
+//!         }
+//!     }
+//!
+//!     fn data(&self) -> Option<serde_json::Value> {
+//!         Some(match self {
+//!             PaymentError::InsufficientFunds { available, required } => {
+//!                 serde_json::json!({ "available": available, "required": required })
+//!             }
+//!             PaymentError::Frozen(account) => serde_json::json!({ "account": account }),
+//!         })
+//!     }
+//! }
+//!
+//! impl From<PaymentError> for Error {
+//!     fn from(error: PaymentError) -> Self {
+//!         ERRORS.render(&error)
+//!     }
+//! }
+//!
+//! let error = Error::from(PaymentError::Frozen("ACC-7".into()));
+//! assert_eq!(error.code(), 1002);
+//! assert_eq!(error.message(), "Account ACC-7 is frozen");
+//! ```
+
+use crate::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Codes JSON-RPC reserves for protocol and server errors
+pub const RESERVED_CODES: std::ops::RangeInclusive<i32> = -32768..=-32000;
+
+/// Application error with a catalog code
+pub trait RpcError: std::fmt::Display {
+    fn code(&self) -> i32;
+
+    /// Values for the message template, sent as the error's `data`
+    fn data(&self) -> Option<serde_json::Value> {
+        None
+    }
+
+    /// The error with its `Display` text as message, for use without a
+    /// catalog
+    fn to_rpc_error(&self) -> Error {
+        let error = Error::new(self.code(), self.to_string());
+        match self.data() {
+            Some(data) => error.with_data(data),
+            None => error,
+        }
+    }
+}
+
+/// One registered error code
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CatalogEntry {
+    pub code: i32,
+    /// Message with `{name}` placeholders filled from the error's data
+    pub template: String,
+}
+
+/// Registered application error codes and their message templates
+#[derive(Debug, Clone, Default)]
+pub struct ErrorCatalog {
+    entries: BTreeMap<i32, CatalogEntry>,
+}
+
+impl ErrorCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `code` with its message template
+    ///
+    /// # Panics
+    ///
+    /// If `code` is in [`RESERVED_CODES`] or already registered.
+    pub fn register(mut self, code: i32, template: impl Into<String>) -> Self {
+        assert!(
+            !RESERVED_CODES.contains(&code),
+            "error code {code} is reserved by JSON-RPC"
+        );
+        let entry = CatalogEntry {
+            code,
+            template: template.into(),
+        };
+        assert!(
+            self.entries.insert(code, entry).is_none(),
+            "error code {code} registered twice"
+        );
+        self
+    }
+
+    pub fn get(&self, code: i32) -> Option<&CatalogEntry> {
+        self.entries.get(&code)
+    }
+
+    /// Registered codes in ascending order
+    pub fn entries(&self) -> impl Iterator<Item = &CatalogEntry> {
+        self.entries.values()
+    }
+
+    /// Error `code` with its template filled from `data`
+    ///
+    /// Codes missing from the catalog become a generic internal error, so
+    /// clients never see codes that were not registered.
+    pub fn error(&self, code: i32, data: Option<serde_json::Value>) -> Error {
+        let Some(entry) = self.entries.get(&code) else {
+            tracing::warn!(code, "error code not in catalog");
+            return Error::new(crate::error_codes::INTERNAL_ERROR, "Internal server error");
+        };
+        let error = Error::new(code, fill(&entry.template, data.as_ref()));
+        match data {
+            Some(data) => error.with_data(data),
+            None => error,
+        }
+    }
+
+    /// `error` as a JSON-RPC error, see [`error`](Self::error)
+    pub fn render(&self, error: &(impl RpcError + ?Sized)) -> Error {
+        self.error(error.code(), error.data())
+    }
+}
+
+/// `template` with each `{name}` replaced by field `name` of `data`;
+/// placeholders without a value are kept
+fn fill(template: &str, data: Option<&serde_json::Value>) -> String {
+    let mut message = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        message.push_str(&rest[..start]);
+        let placeholder = &rest[start..];
+        let Some(end) = placeholder.find('}') else {
+            rest = placeholder;
+            break;
+        };
+        match data.and_then(|data| data.get(&placeholder[1..end])) {
+            Some(serde_json::Value::String(value)) => message.push_str(value),
+            Some(value) => message.push_str(&value.to_string()),
+            None => message.push_str(&placeholder[..=end]),
+        }
+        rest = &placeholder[end + 1..];
+    }
+    message.push_str(rest);
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_templates_filled_from_data() {
+        let catalog = ErrorCatalog::new()
+            .register(4001, "Quota of {limit} calls used by {user}")
+            .register(4002, "Try again {later}");
+
+        let error = catalog.error(4001, Some(json!({"limit": 100, "user": "alice"})));
+        assert_eq!(error.message(), "Quota of 100 calls used by alice");
+        assert_eq!(error.data(), Some(&json!({"limit": 100, "user": "alice"})));
+        assert_eq!(catalog.error(4002, None).message(), "Try again {later}");
+
+        let unknown = catalog.error(4999, None);
+        assert!(unknown.is_internal_error());
+        assert_eq!(
+            catalog.entries().map(|e| e.code).collect::<Vec<_>>(),
+            [4001, 4002]
+        );
+    }
+
+    #[test]
+    fn test_rpc_try_converts_typed_errors() {
+        struct Frozen;
+
+        impl std::fmt::Display for Frozen {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("account frozen by compliance hold #88")
+            }
+        }
+
+        impl RpcError for Frozen {
+            fn code(&self) -> i32 {
+                1002
+            }
+        }
+
+        impl From<Frozen> for Error {
+            fn from(error: Frozen) -> Self {
+                ErrorCatalog::new()
+                    .register(1002, "Account is frozen")
+                    .render(&error)
+            }
+        }
+
+        let id = Some(crate::RequestId::from(1));
+        let response = crate::rpc_try!(Err::<(), _>(Frozen), id.clone() => Error);
+        let error = response.error.unwrap();
+        assert_eq!(
+            (error.code, error.message.as_str()),
+            (1002, "Account is frozen")
+        );
+        assert_eq!(response.id, id);
+        assert_eq!(
+            Frozen.to_rpc_error().message(),
+            "account frozen by compliance hold #88"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "reserved")]
+    fn test_reserved_codes_refused() {
+        let _ = ErrorCatalog::new().register(-32001, "Busy");
+    }
+}
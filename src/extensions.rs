@@ -0,0 +1,145 @@
+//! Typed per-request values.
+//!
+//! [`Extensions`] maps a type to one value of that type, like
+//! `http::Extensions`. A [`Middleware`](crate::interceptor::Middleware)
+//! fills it in
+//! [`before_with_extensions`](crate::interceptor::Middleware::before_with_extensions),
+//! e.g. with the tenant resolved from the caller, and the handler reads it
+//! back from its [`CallContext`](crate::CallContext) without global state.
+//!
+//! ```rust
+//! use ash_rpc::auth::ConnectionContext;
+//! use ash_rpc::extensions::Extensions;
+//! use ash_rpc::interceptor::Middleware;
+//! use ash_rpc::*;
+//!
+//! #[derive(Debug, PartialEq)]
+//! struct TenantId(String);
+//!
+//! struct ResolveTenant;
+//!
+//! #[async_trait]
+//! impl Middleware for ResolveTenant {
+//!     async fn before_with_extensions(
+//!         &self,
+//!         _request: &Request,
+//!         ctx: &ConnectionContext,
+//!         extensions: &mut Extensions,
+//!     ) -> Result<(), Response> {
+//!         let tenant = ctx.principal_id().unwrap_or("public");
+//!         extensions.insert(TenantId(tenant.to_string()));
+//!         Ok(())
+//!     }
+//! }
+//!
+//! struct Whoami;
+//!
+//! #[async_trait]
+//! impl JsonRPCMethod for Whoami {
+//!     fn method_name(&self) -> &'static str {
+//!         "whoami"
+//!     }
+//!
+//!     async fn call(&self, _params: Option<serde_json::Value>, id: Option<RequestId>) -> Response {
+//!         rpc_success!(serde_json::Value::Null, id)
+//!     }
+//!
+//!     async fn call_with_context(
+//!         &self,
+//!         _params: Option<serde_json::Value>,
+//!         id: Option<RequestId>,
+//!         ctx: &CallContext<'_>,
+//!     ) -> Response {
+//!         let tenant = ctx.extensions().and_then(|e| e.get::<TenantId>());
+//!         rpc_success!(tenant.map(|tenant| tenant.0.clone()), id)
+//!     }
+//! }
+//!
+//! let registry = MethodRegistry::new(register_methods![Whoami]).layer(ResolveTenant);
+//! ```
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Map holding at most one value per type
+#[derive(Clone, Default)]
+pub struct Extensions {
+    values: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store `value`, replacing the previous value of its type
+    pub fn insert<T: Any + Send + Sync>(&mut self, value: T) {
+        self.values.insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.values
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref::<T>())
+    }
+
+    pub fn contains<T: Any + Send + Sync>(&self) -> bool {
+        self.values.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Drop the value of type `T`, returning whether there was one
+    pub fn remove<T: Any + Send + Sync>(&mut self) -> bool {
+        self.values.remove(&TypeId::of::<T>()).is_some()
+    }
+
+    /// Take over the values of `other`, replacing values of the same type
+    pub fn extend(&mut self, other: Extensions) {
+        self.values.extend(other.values);
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.values.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct TenantId(&'static str);
+
+    #[test]
+    fn test_one_value_per_type() {
+        let mut extensions = Extensions::new();
+        assert!(extensions.is_empty());
+        extensions.insert(TenantId("acme"));
+        extensions.insert(7u32);
+        extensions.insert(TenantId("initech"));
+        assert_eq!(extensions.len(), 2);
+        assert_eq!(extensions.get::<TenantId>(), Some(&TenantId("initech")));
+        assert_eq!(extensions.get::<u32>(), Some(&7));
+        assert!(extensions.get::<u64>().is_none());
+
+        let mut other = Extensions::new();
+        other.insert(8u32);
+        extensions.extend(other);
+        assert_eq!(extensions.get::<u32>(), Some(&8));
+        assert!(extensions.remove::<TenantId>());
+        assert!(!extensions.contains::<TenantId>());
+    }
+}
@@ -0,0 +1,237 @@
+//! Feature flags for handlers.
+//!
+//! A [`FeatureFlagProvider`] answers whether a named flag is on. Configure
+//! one on the registry with `MethodRegistry::with_feature_flags`; handlers
+//! then branch on flags through
+//! [`CallContext::flag_enabled`](crate::CallContext::flag_enabled), and
+//! [`DenyIfDisabled`] hides entire methods behind a flag.
+//!
+//! [`StaticFlags`] is a fixed set of flags, typically loaded from a JSON file
+//! of the form `{"new_pricing": true, "beta_export": false}`. Wrap it in a
+//! [`Reloadable`] to change flags at runtime.
+//!
+//! ```rust
+//! use ash_rpc::feature_flags::{DenyIfDisabled, StaticFlags};
+//! use ash_rpc::*;
+//! use std::sync::Arc;
+//!
+//! struct Export;
+//!
+//! #[async_trait::async_trait]
+//! impl JsonRPCMethod for Export {
+//!     fn method_name(&self) -> &'static str { "export" }
+//!
+//!     async fn call(&self, _params: Option<serde_json::Value>, id: Option<RequestId>) -> Response {
+//!         rpc_success!("done", id)
+//!     }
+//! }
+//!
+//! let flags = Arc::new(StaticFlags::from_json_str(r#"{"beta_export": false}"#).unwrap());
+//! let registry = MethodRegistry::empty()
+//!     .add_method(Box::new(DenyIfDisabled::new(Export, "beta_export", flags.clone())))
+//!     .with_feature_flags(flags);
+//! # let _ = registry;
+//! ```
+
+use crate::reload::Reloadable;
+use crate::{CallContext, JsonRPCMethod, OpenApiMethodSpec, RequestId, Response};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Source of feature flag values
+pub trait FeatureFlagProvider: Send + Sync {
+    /// Whether `flag` is enabled; unknown flags should be reported as off
+    fn is_enabled(&self, flag: &str) -> bool;
+}
+
+impl<T: FeatureFlagProvider + ?Sized> FeatureFlagProvider for Arc<T> {
+    fn is_enabled(&self, flag: &str) -> bool {
+        (**self).is_enabled(flag)
+    }
+}
+
+impl<T: FeatureFlagProvider> FeatureFlagProvider for Reloadable<T> {
+    fn is_enabled(&self, flag: &str) -> bool {
+        self.load().is_enabled(flag)
+    }
+}
+
+/// Fixed set of flags
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(transparent)]
+pub struct StaticFlags {
+    flags: HashMap<String, bool>,
+}
+
+impl StaticFlags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(mut self, flag: impl Into<String>, enabled: bool) -> Self {
+        self.flags.insert(flag.into(), enabled);
+        self
+    }
+
+    /// Parse a JSON object mapping flag names to booleans
+    pub fn from_json_str(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Load flags from a JSON file
+    pub fn from_json_file(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Self::from_json_str(&text).map_err(std::io::Error::other)
+    }
+}
+
+impl FeatureFlagProvider for StaticFlags {
+    fn is_enabled(&self, flag: &str) -> bool {
+        self.flags.get(flag).copied().unwrap_or(false)
+    }
+}
+
+/// Method wrapper answering `METHOD_NOT_FOUND` while its flag is off
+///
+/// Disabled methods are indistinguishable from unregistered ones, so clients
+/// cannot probe for unreleased functionality.
+pub struct DenyIfDisabled<M> {
+    inner: M,
+    flag: String,
+    flags: Arc<dyn FeatureFlagProvider>,
+}
+
+impl<M: JsonRPCMethod> DenyIfDisabled<M> {
+    pub fn new(inner: M, flag: impl Into<String>, flags: Arc<dyn FeatureFlagProvider>) -> Self {
+        Self {
+            inner,
+            flag: flag.into(),
+            flags,
+        }
+    }
+
+    fn not_found(&self, id: Option<RequestId>) -> Response {
+        tracing::debug!(method = %self.inner.method_name(), flag = %self.flag, "method disabled by feature flag");
+        crate::ResponseBuilder::new()
+            .error(
+                crate::ErrorBuilder::new(crate::error_codes::METHOD_NOT_FOUND, "Method not found")
+                    .build(),
+            )
+            .id(id)
+            .build()
+    }
+}
+
+#[crate::async_trait]
+impl<M: JsonRPCMethod> JsonRPCMethod for DenyIfDisabled<M> {
+    fn method_name(&self) -> &str {
+        self.inner.method_name()
+    }
+
+    async fn call(&self, params: Option<serde_json::Value>, id: Option<RequestId>) -> Response {
+        if !self.flags.is_enabled(&self.flag) {
+            return self.not_found(id);
+        }
+        self.inner.call(params, id).await
+    }
+
+    async fn call_with_context(
+        &self,
+        params: Option<serde_json::Value>,
+        id: Option<RequestId>,
+        ctx: &CallContext<'_>,
+    ) -> Response {
+        if !self.flags.is_enabled(&self.flag) {
+            return self.not_found(id);
+        }
+        self.inner.call_with_context(params, id, ctx).await
+    }
+
+    fn openapi_components(&self) -> OpenApiMethodSpec {
+        self.inner.openapi_components()
+    }
+
+    fn security(&self) -> crate::method_security::MethodSecurity {
+        self.inner.security()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MethodRegistry, rpc_success};
+    use serde_json::json;
+
+    struct Pricing;
+
+    #[crate::async_trait]
+    impl JsonRPCMethod for Pricing {
+        fn method_name(&self) -> &'static str {
+            "price"
+        }
+
+        async fn call(
+            &self,
+            _params: Option<serde_json::Value>,
+            id: Option<RequestId>,
+        ) -> Response {
+            rpc_success!("old", id)
+        }
+
+        async fn call_with_context(
+            &self,
+            _params: Option<serde_json::Value>,
+            id: Option<RequestId>,
+            ctx: &CallContext<'_>,
+        ) -> Response {
+            if ctx.flag_enabled("new_pricing") {
+                rpc_success!("new", id)
+            } else {
+                rpc_success!("old", id)
+            }
+        }
+    }
+
+    #[test]
+    fn test_static_flags_from_json() {
+        let flags = StaticFlags::from_json_str(r#"{"a": true, "b": false}"#).unwrap();
+        assert!(flags.is_enabled("a"));
+        assert!(!flags.is_enabled("b"));
+        assert!(!flags.is_enabled("missing"));
+        assert!(StaticFlags::from_json_str(r#"{"a": "yes"}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_handler_branches_on_flag() {
+        let flags = Reloadable::new(StaticFlags::new());
+        let registry =
+            MethodRegistry::new(vec![Box::new(Pricing)]).with_feature_flags(flags.clone());
+
+        let response = registry.call("price", None, Some(json!(1))).await;
+        assert_eq!(response.result, Some(json!("old")));
+
+        flags.store(StaticFlags::new().set("new_pricing", true));
+        let response = registry.call("price", None, Some(json!(1))).await;
+        assert_eq!(response.result, Some(json!("new")));
+    }
+
+    #[tokio::test]
+    async fn test_deny_if_disabled() {
+        let flags = Reloadable::new(StaticFlags::new());
+        let provider: Arc<dyn FeatureFlagProvider> = Arc::new(flags.clone());
+        let registry = MethodRegistry::new(vec![Box::new(DenyIfDisabled::new(
+            Pricing, "pricing", provider,
+        ))]);
+
+        let response = registry.call("price", None, Some(json!(1))).await;
+        assert_eq!(
+            response.error.unwrap().code,
+            crate::error_codes::METHOD_NOT_FOUND
+        );
+
+        flags.store(StaticFlags::new().set("pricing", true));
+        let response = registry.call("price", None, Some(json!(1))).await;
+        assert!(response.is_success());
+    }
+}
@@ -2,9 +2,35 @@
 //!
 //! Provides a standard health check method that can be used to monitor
 //! service availability and health status.
+//!
+//! [`HealthRegistry`] tracks the health of the dependencies a service relies
+//! on. Attached to a [`MethodRegistry`] through a [`DegradationPolicy`], it
+//! makes methods whose dependencies are down fail fast with
+//! [`error_codes::RETRY_LATER`] instead of running handlers that would time
+//! out, and lets them through again as soon as the dependency recovers:
+//!
+//! ```rust
+//! use ash_rpc::healthcheck::{DegradationPolicy, HealthRegistry};
+//! use ash_rpc::MethodRegistry;
+//! use std::time::Duration;
+//!
+//! let health = HealthRegistry::new();
+//! let registry = MethodRegistry::empty().with_degradation(
+//!     DegradationPolicy::new(health.clone())
+//!         .guard("orders.create", ["postgres", "payments"])
+//!         .retry_after(Duration::from_secs(10)),
+//! );
+//!
+//! // typically called from a background probe
+//! health.set_unhealthy("postgres", "connection refused");
+//! # let _ = registry;
+//! ```
 
 use crate::*;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
 /// Health check response structure
 #[derive(Debug, Serialize, Deserialize)]
@@ -98,6 +124,145 @@ pub fn healthcheck_with_service(service_name: impl Into<String>) -> HealthcheckM
     HealthcheckMethod::with_service_name(service_name)
 }
 
+/// Live health of the dependencies a service relies on
+///
+/// Cheap to clone; all clones share the same state. Dependencies that were
+/// never reported are considered healthy.
+#[derive(Debug, Clone, Default)]
+pub struct HealthRegistry {
+    unhealthy: Arc<RwLock<HashMap<String, String>>>,
+}
+
+impl HealthRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mark `dependency` as working
+    pub fn set_healthy(&self, dependency: &str) {
+        let recovered = self
+            .unhealthy
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(dependency);
+        if recovered.is_some() {
+            tracing::info!(dependency, "dependency recovered");
+        }
+    }
+
+    /// Mark `dependency` as failing
+    pub fn set_unhealthy(&self, dependency: &str, reason: impl Into<String>) {
+        let reason = reason.into();
+        let previous = self
+            .unhealthy
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(dependency.to_string(), reason.clone());
+        if previous.is_none() {
+            tracing::warn!(dependency, reason = %reason, "dependency unhealthy");
+        }
+    }
+
+    /// Record the outcome of a probe
+    pub fn report(&self, dependency: &str, result: Result<(), String>) {
+        match result {
+            Ok(()) => self.set_healthy(dependency),
+            Err(reason) => self.set_unhealthy(dependency, reason),
+        }
+    }
+
+    pub fn is_healthy(&self, dependency: &str) -> bool {
+        !self
+            .unhealthy
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains_key(dependency)
+    }
+
+    /// Failing dependencies with the reason last reported, sorted by name
+    pub fn unhealthy(&self) -> Vec<(String, String)> {
+        let mut unhealthy: Vec<_> = self
+            .unhealthy
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|(name, reason)| (name.clone(), reason.clone()))
+            .collect();
+        unhealthy.sort();
+        unhealthy
+    }
+}
+
+/// Methods that answer `RETRY_LATER` while their dependencies are down
+#[derive(Debug, Clone)]
+pub struct DegradationPolicy {
+    health: HealthRegistry,
+    methods: HashMap<String, Vec<String>>,
+    retry_after: Duration,
+}
+
+impl DegradationPolicy {
+    /// No guarded methods yet, suggesting a retry after 5 seconds
+    pub fn new(health: HealthRegistry) -> Self {
+        Self {
+            health,
+            methods: HashMap::new(),
+            retry_after: Duration::from_secs(5),
+        }
+    }
+
+    /// Fail `method` fast while any of `dependencies` is unhealthy
+    pub fn guard<I, S>(mut self, method: impl Into<String>, dependencies: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.methods
+            .entry(method.into())
+            .or_default()
+            .extend(dependencies.into_iter().map(Into::into));
+        self
+    }
+
+    /// Delay suggested to callers in the error data
+    pub fn retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
+    pub fn is_guarded(&self, method: &str) -> bool {
+        self.methods.contains_key(method)
+    }
+
+    /// The response to send instead of calling `method`, if it is degraded
+    pub fn check(&self, method: &str, id: Option<RequestId>) -> Option<Response> {
+        let unavailable: Vec<&str> = self
+            .methods
+            .get(method)?
+            .iter()
+            .filter(|dependency| !self.health.is_healthy(dependency))
+            .map(String::as_str)
+            .collect();
+        if unavailable.is_empty() {
+            return None;
+        }
+        tracing::debug!(method, unavailable = ?unavailable, "method degraded");
+        Some(
+            ResponseBuilder::new()
+                .error(
+                    ErrorBuilder::new(error_codes::RETRY_LATER, "Service temporarily unavailable")
+                        .data(serde_json::json!({
+                            "retry_after": self.retry_after.as_secs(),
+                            "unavailable": unavailable,
+                        }))
+                        .build(),
+                )
+                .id(id)
+                .build(),
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -237,4 +402,35 @@ mod tests {
         assert_eq!(status.service, "test-service");
         assert_eq!(status.version, Some("1.0.0".to_string()));
     }
+
+    #[tokio::test]
+    async fn test_degradation_follows_health() {
+        let health = HealthRegistry::new();
+        let registry = MethodRegistry::new(register_methods![healthcheck()]).with_degradation(
+            DegradationPolicy::new(health.clone())
+                .guard("healthcheck", ["db"])
+                .retry_after(Duration::from_secs(30)),
+        );
+
+        let response = registry.call("healthcheck", None, Some(1.into())).await;
+        assert!(response.result.is_some());
+
+        health.report("db", Err("connection refused".into()));
+        assert_eq!(
+            health.unhealthy(),
+            vec![("db".to_string(), "connection refused".to_string())]
+        );
+        let response = registry.call("healthcheck", None, Some(2.into())).await;
+        let error = response.error.unwrap();
+        assert_eq!(error.code, error_codes::RETRY_LATER);
+        assert_eq!(
+            error.data,
+            Some(serde_json::json!({"retry_after": 30, "unavailable": ["db"]}))
+        );
+        assert_eq!(response.id, Some(2.into()));
+
+        health.set_healthy("db");
+        let response = registry.call("healthcheck", None, Some(3.into())).await;
+        assert!(response.result.is_some());
+    }
 }
@@ -0,0 +1,314 @@
+//! Request-scoped interceptors for [`MethodRegistry`](crate::MethodRegistry).
+//!
+//! A [`Middleware`] sees every request before the registry dispatches it and
+//! every response before it is returned, without wrapping the whole
+//! [`MessageProcessor`](crate::MessageProcessor). Middleware runs outside the
+//! registry's own checks (auth policy, replay guard, degradation), so it can
+//! implement those concerns itself.
+//!
+//! Layers nest like tower layers: `before` hooks run in registration order
+//! and `after` hooks in reverse, so the first layer registered is the
+//! outermost. A `before` hook returning `Err(response)` short-circuits the
+//! stack: neither the method nor any later layer runs, and only the layers
+//! registered earlier see the response in their `after` hooks.
+//!
+//! ```rust
+//! use ash_rpc::auth::ConnectionContext;
+//! use ash_rpc::interceptor::Middleware;
+//! use ash_rpc::*;
+//!
+//! struct RequireParams;
+//!
+//! #[async_trait]
+//! impl Middleware for RequireParams {
+//!     async fn before(&self, request: &Request, _ctx: &ConnectionContext) -> Result<(), Response> {
+//!         match request.params {
+//!             Some(_) => Ok(()),
+//!             None => Err(Response::error(
+//!                 ErrorBuilder::new(error_codes::INVALID_PARAMS, "params required").build(),
+//!                 request.id.clone(),
+//!             )),
+//!         }
+//!     }
+//! }
+//!
+//! let registry = MethodRegistry::empty().layer(RequireParams);
+//! ```
+
+use crate::auth::ConnectionContext;
+use crate::extensions::Extensions;
+use crate::{Request, Response};
+use std::sync::Arc;
+
+/// Hooks around a single request dispatched by a registry
+///
+/// Notifications are passed as requests without an `id`; their `after`
+/// hooks still run, but the response is discarded.
+#[async_trait::async_trait]
+pub trait Middleware: Send + Sync {
+    /// Inspect the request before dispatch, `Err` answers it immediately
+    async fn before(&self, _request: &Request, _ctx: &ConnectionContext) -> Result<(), Response> {
+        Ok(())
+    }
+
+    /// [`before`](Self::before) with the request's [`Extensions`], where
+    /// values for the handler and later layers can be stored; the default
+    /// calls `before`
+    async fn before_with_extensions(
+        &self,
+        request: &Request,
+        ctx: &ConnectionContext,
+        _extensions: &mut Extensions,
+    ) -> Result<(), Response> {
+        self.before(request, ctx).await
+    }
+
+    /// Inspect or rewrite the outgoing response
+    async fn after(&self, _request: &Request, _ctx: &ConnectionContext, _response: &mut Response) {}
+}
+
+/// Ordered middleware layers of a registry
+#[derive(Clone, Default)]
+pub(crate) struct MiddlewareStack {
+    layers: Vec<Arc<dyn Middleware>>,
+}
+
+impl MiddlewareStack {
+    pub(crate) fn push(&mut self, layer: Arc<dyn Middleware>) {
+        self.layers.push(layer);
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Run `dispatch` inside the stack, passing it the extensions the
+    /// layers attached
+    pub(crate) async fn run<F, Fut>(
+        &self,
+        request: &Request,
+        ctx: &ConnectionContext,
+        dispatch: F,
+    ) -> Response
+    where
+        F: FnOnce(Extensions) -> Fut,
+        Fut: std::future::Future<Output = Response>,
+    {
+        let mut entered = 0;
+        let mut response = None;
+        let mut extensions = Extensions::new();
+        for layer in &self.layers {
+            if let Err(early) = layer
+                .before_with_extensions(request, ctx, &mut extensions)
+                .await
+            {
+                tracing::debug!(method = %request.method, "request short-circuited by middleware");
+                response = Some(early);
+                break;
+            }
+            entered += 1;
+        }
+        let mut response = match response {
+            Some(response) => response,
+            None => dispatch(extensions).await,
+        };
+        for layer in self.layers[..entered].iter().rev() {
+            layer.after(request, ctx, &mut response).await;
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ErrorBuilder, JsonRPCMethod, Message, MessageProcessor, MethodRegistry, RequestId,
+        error_codes,
+    };
+    use std::sync::Mutex;
+
+    struct Ping;
+
+    #[async_trait::async_trait]
+    impl JsonRPCMethod for Ping {
+        fn method_name(&self) -> &'static str {
+            "ping"
+        }
+
+        async fn call(
+            &self,
+            _params: Option<serde_json::Value>,
+            id: Option<RequestId>,
+        ) -> Response {
+            Response::success(serde_json::json!("pong"), id)
+        }
+
+        fn static_result(&self) -> Option<serde_json::Value> {
+            Some(serde_json::json!("pong"))
+        }
+    }
+
+    struct Recorder {
+        name: &'static str,
+        log: Arc<Mutex<Vec<String>>>,
+        reject: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl Middleware for Recorder {
+        async fn before(
+            &self,
+            request: &Request,
+            _ctx: &ConnectionContext,
+        ) -> Result<(), Response> {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("before {}", self.name));
+            if self.reject {
+                return Err(Response::error(
+                    ErrorBuilder::new(error_codes::INVALID_REQUEST, "rejected").build(),
+                    request.id.clone(),
+                ));
+            }
+            Ok(())
+        }
+
+        async fn after(
+            &self,
+            _request: &Request,
+            _ctx: &ConnectionContext,
+            response: &mut Response,
+        ) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("after {}", self.name));
+            response.correlation_id = Some(self.name.to_string());
+        }
+    }
+
+    fn registry(log: &Arc<Mutex<Vec<String>>>, reject_second: bool) -> MethodRegistry {
+        let layer = |name, reject| Recorder {
+            name,
+            log: Arc::clone(log),
+            reject,
+        };
+        MethodRegistry::new(crate::register_methods![Ping])
+            .layer(layer("a", false))
+            .layer(layer("b", reject_second))
+            .layer(layer("c", false))
+    }
+
+    #[tokio::test]
+    async fn test_layers_nest_in_registration_order() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let registry = registry(&log, false);
+        assert!(registry.static_response("ping", None).is_none());
+
+        let request = Request::new("ping").with_id(serde_json::json!(1));
+        let response = registry
+            .process_message(Message::Request(request))
+            .await
+            .unwrap();
+        assert!(response.is_success());
+        assert_eq!(response.correlation_id.as_deref(), Some("a"));
+        assert_eq!(
+            *log.lock().unwrap(),
+            [
+                "before a", "before b", "before c", "after c", "after b", "after a"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_short_circuit_skips_inner_layers() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let registry = registry(&log, true);
+
+        let request = Request::new("ping").with_id(serde_json::json!(7));
+        let response = registry
+            .process_message(Message::Request(request))
+            .await
+            .unwrap();
+        assert_eq!(response.error.unwrap().code, error_codes::INVALID_REQUEST);
+        assert_eq!(response.id, Some(serde_json::json!(7)));
+        assert_eq!(*log.lock().unwrap(), ["before a", "before b", "after a"]);
+    }
+
+    struct TenantId(String);
+
+    struct ResolveTenant;
+
+    #[async_trait::async_trait]
+    impl Middleware for ResolveTenant {
+        async fn before_with_extensions(
+            &self,
+            _request: &Request,
+            ctx: &ConnectionContext,
+            extensions: &mut Extensions,
+        ) -> Result<(), Response> {
+            let tenant = ctx.principal_id().unwrap_or("public");
+            extensions.insert(TenantId(tenant.to_string()));
+            Ok(())
+        }
+    }
+
+    struct Whoami;
+
+    #[async_trait::async_trait]
+    impl JsonRPCMethod for Whoami {
+        fn method_name(&self) -> &'static str {
+            "whoami"
+        }
+
+        async fn call(
+            &self,
+            _params: Option<serde_json::Value>,
+            id: Option<RequestId>,
+        ) -> Response {
+            Response::success(serde_json::Value::Null, id)
+        }
+
+        async fn call_with_context(
+            &self,
+            _params: Option<serde_json::Value>,
+            id: Option<RequestId>,
+            ctx: &crate::RequestContext<'_>,
+        ) -> Response {
+            let tenant = ctx.extensions().and_then(|e| e.get::<TenantId>());
+            Response::success(
+                serde_json::json!({
+                    "tenant": tenant.map(|tenant| tenant.0.clone()),
+                    "correlation_id": ctx.correlation_id(),
+                }),
+                id,
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware_extensions_reach_handler() {
+        let registry = MethodRegistry::new(crate::register_methods![Whoami]);
+        let mut request = Request::new("whoami").with_id(serde_json::json!(1));
+        request.correlation_id = Some("req-1".to_string());
+        let response = registry
+            .process_message(Message::Request(request.clone()))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.result,
+            Some(serde_json::json!({"tenant": null, "correlation_id": "req-1"}))
+        );
+
+        let registry = registry.layer(ResolveTenant);
+        let mut ctx = ConnectionContext::new();
+        ctx.insert(crate::auth::USER_ID_KEY.to_string(), "acme".to_string());
+        let response = registry
+            .process_message_with_context(Message::Request(request), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(response.result.unwrap()["tenant"], "acme");
+    }
+}
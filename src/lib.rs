@@ -39,24 +39,66 @@
 
 // Core module declarations
 pub mod auth;
+pub mod batch_policy;
+pub mod borrowed;
 pub mod builders;
+pub mod builtins;
+pub mod cache;
+pub mod cancellation;
+pub mod canonical;
+pub mod coalesce;
+pub mod codec;
+pub mod dead_letter;
+pub mod error_catalog;
+pub mod extensions;
+pub mod feature_flags;
+pub mod interceptor;
 pub mod logger;
 pub mod macros;
+pub mod masking;
+pub mod method_metadata;
+pub mod method_security;
+pub mod namespaces;
+pub mod numbers;
+pub mod openrpc;
+pub mod params;
+pub mod rate_limit;
 pub mod registry;
+pub mod rejection;
+pub mod reload;
+pub mod replay;
+pub mod resource_usage;
 pub mod sanitization;
+pub mod schema;
+pub mod secrets;
+pub mod selftest;
+pub mod serialization;
+pub mod transform;
+pub mod validation;
+pub mod versioning;
 
 #[cfg(feature = "audit-logging")]
 pub mod audit_logging;
 
+#[cfg(feature = "client")]
+pub mod client;
+
+#[cfg(feature = "delayed-execution")]
+pub mod delayed;
+
 #[cfg(feature = "shutdown")]
 pub mod shutdown;
 
+#[cfg(feature = "timeouts")]
+pub mod timeout;
+
 #[cfg(feature = "streaming")]
 pub mod streaming;
 
 pub mod traits;
 pub mod transports;
 pub mod types;
+pub mod wire;
 
 #[cfg(feature = "stateful")]
 pub mod stateful;
@@ -65,9 +107,18 @@ pub mod stateful;
 #[cfg(feature = "healthcheck")]
 pub mod healthcheck;
 
+#[cfg(feature = "contrib-methods")]
+pub mod contrib_methods;
+
+#[cfg(feature = "testing-methods")]
+pub mod testing_methods;
+
 #[cfg(feature = "tower")]
 pub mod middleware;
 
+#[cfg(feature = "derive")]
+pub mod derive;
+
 #[cfg(any(feature = "logging", feature = "prometheus", feature = "opentelemetry"))]
 pub mod observability;
 
@@ -108,6 +159,8 @@ pub use audit_logging::*;
 
 // Re-export transports
 pub use transports::SecurityConfig;
+pub use transports::{ConfigProblem, ConfigReport, ProblemSeverity};
+pub use transports::{ListenerProcessor, MethodFilter};
 
 #[cfg(feature = "tcp")]
 pub use transports::{TcpServer, TcpServerBuilder};
@@ -117,11 +170,20 @@ pub use transports::{
     TcpStreamClient, TcpStreamClientBuilder, TcpStreamServer, TcpStreamServerBuilder,
 };
 
+#[cfg(all(
+    feature = "streaming",
+    any(feature = "tcp-stream", feature = "websocket")
+))]
+pub use transports::Subscription;
+
 #[cfg(feature = "tcp-stream-tls")]
 pub use transports::{
     TcpStreamTlsClient, TcpStreamTlsServer, TcpStreamTlsServerBuilder, TlsConfig,
 };
 
+#[cfg(feature = "websocket")]
+pub use transports::{WebSocketClient, WebSocketError, WebSocketServer, WebSocketServerBuilder};
+
 #[cfg(feature = "axum")]
 pub use transports::axum;
 
@@ -435,9 +435,27 @@ macro_rules! rpc_params {
 ///     let result = if b != 0.0 { Ok(a / b) } else { Err("Division by zero") };
 ///     rpc_try!(result, id)
 /// });
+///
+/// // errors with `impl From<MyError> for Error`, e.g. rendered by an
+/// // `ErrorCatalog`, are sent as converted
+/// rpc_try!(transfer(params), id => Error)
 /// ```
 #[macro_export]
 macro_rules! rpc_try {
+    ($result:expr, $id:expr => Error) => {
+        match $result {
+            Ok(value) => $crate::rpc_success!(value, $id),
+            Err(error) => {
+                let error: $crate::Error = error.into();
+                tracing::debug!(
+                    error = %error,
+                    request_id = ?$id,
+                    "method returned an application error"
+                );
+                $crate::ResponseBuilder::new().error(error).id($id).build()
+            },
+        }
+    };
     ($result:expr, $id:expr) => {
         match $result {
             Ok(value) => $crate::rpc_success!(value, $id),
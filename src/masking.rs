@@ -0,0 +1,288 @@
+//! Field-level masking of results by caller scope.
+//!
+//! A [`ScopedMasking`] holds rules naming a field of the result, the scope
+//! a caller needs to see it unmasked and the [`Mask`] applied otherwise. It
+//! is a [`ResponseTransformer`], registered for every method with
+//! [`MethodRegistry::with_response_transformer`](crate::MethodRegistry::with_response_transformer)
+//! or for one with
+//! [`with_method_transformer`](crate::MethodRegistry::with_method_transformer),
+//! so the same handler serves full data to privileged callers and masked
+//! data to everyone else.
+//!
+//! Field paths are dot separated object keys or array indices, `*` matching
+//! every entry, e.g. `rows.*.revenue`. Paths not present in a result are
+//! skipped. Every call that had fields masked is reported to the
+//! [`MaskingObserver`]s with the JSON pointers of the masked fields, e.g.
+//! to an audit log.
+//!
+//! ```rust
+//! use ash_rpc::auth::ConnectionContext;
+//! use ash_rpc::masking::{Mask, ScopedMasking};
+//! use ash_rpc::transform::ResponseTransformer;
+//! use serde_json::json;
+//!
+//! let masking = ScopedMasking::new()
+//!     .rule("rows.*.customer", "analytics:pii", Mask::Remove)
+//!     .rule("rows.*.revenue", "analytics:exact", Mask::Round(1000.0));
+//!
+//! let result = json!({"rows": [{"customer": "acme", "revenue": 12345}]});
+//! let masked = masking.transform("sales.report", result, &ConnectionContext::new());
+//! assert_eq!(masked, json!({"rows": [{"revenue": 12000.0}]}));
+//! ```
+
+use crate::auth::ConnectionContext;
+use crate::transform::ResponseTransformer;
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+/// What happens to a field the caller may not see
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mask {
+    /// Leave the field out; array entries are removed
+    Remove,
+    /// Send this value instead
+    Replace(Value),
+    /// Round numbers to a multiple of the step; other values are left alone
+    Round(f64),
+}
+
+impl Mask {
+    /// Mask the entry `key` of `container`, returning whether it changed
+    fn apply(&self, container: &mut Value, key: &str) -> bool {
+        match self {
+            Mask::Remove => match container {
+                Value::Object(map) => map.remove(key).is_some(),
+                Value::Array(items) => match key.parse::<usize>() {
+                    Ok(index) if index < items.len() => {
+                        items.remove(index);
+                        true
+                    }
+                    _ => false,
+                },
+                _ => false,
+            },
+            Mask::Replace(value) => match entry(container, key) {
+                Some(field) => {
+                    *field = value.clone();
+                    true
+                }
+                None => false,
+            },
+            Mask::Round(step) => match entry(container, key) {
+                Some(field) if *step > 0.0 => {
+                    let Some(number) = field.as_f64() else {
+                        return false;
+                    };
+                    *field = serde_json::json!((number / step).round() * step);
+                    true
+                }
+                _ => false,
+            },
+        }
+    }
+}
+
+fn entry<'a>(container: &'a mut Value, key: &str) -> Option<&'a mut Value> {
+    match container {
+        Value::Object(map) => map.get_mut(key),
+        Value::Array(items) => items.get_mut(key.parse::<usize>().ok()?),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    path: Vec<String>,
+    scope: String,
+    mask: Mask,
+}
+
+/// Fields masked in one call's result
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaskingRecord {
+    pub method: String,
+    pub principal: Option<String>,
+    pub remote_addr: Option<SocketAddr>,
+    /// JSON pointers of the masked fields, in the unmasked result
+    pub fields: Vec<String>,
+    /// Scopes the caller lacked
+    pub missing_scopes: Vec<String>,
+}
+
+/// Receives a record of every call that had fields masked
+pub trait MaskingObserver: Send + Sync {
+    fn on_masked(&self, record: &MaskingRecord);
+}
+
+/// Masks result fields of callers without the scope of their rule
+#[derive(Clone, Default)]
+pub struct ScopedMasking {
+    rules: Vec<Rule>,
+    observers: Vec<Arc<dyn MaskingObserver>>,
+}
+
+impl ScopedMasking {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply `mask` to the field at `path` for callers lacking `scope`
+    pub fn rule(mut self, path: &str, scope: impl Into<String>, mask: Mask) -> Self {
+        self.rules.push(Rule {
+            path: path.split('.').map(str::to_string).collect(),
+            scope: scope.into(),
+            mask,
+        });
+        self
+    }
+
+    pub fn observer(mut self, observer: Arc<dyn MaskingObserver>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+}
+
+impl ResponseTransformer for ScopedMasking {
+    fn transform(&self, method: &str, mut result: Value, ctx: &ConnectionContext) -> Value {
+        let mut fields = Vec::new();
+        let mut missing_scopes: Vec<String> = Vec::new();
+        for rule in &self.rules {
+            if ctx.has_scope(&rule.scope) {
+                continue;
+            }
+            let before = fields.len();
+            mask(&mut result, &rule.path, &rule.mask, "", &mut fields);
+            if fields.len() > before && !missing_scopes.contains(&rule.scope) {
+                missing_scopes.push(rule.scope.clone());
+            }
+        }
+        if fields.is_empty() {
+            return result;
+        }
+
+        tracing::debug!(method = %method, fields = fields.len(), "masked result fields");
+        let record = MaskingRecord {
+            method: method.to_string(),
+            principal: ctx.principal_id().map(str::to_string),
+            remote_addr: ctx.remote_addr,
+            fields,
+            missing_scopes,
+        };
+        for observer in &self.observers {
+            observer.on_masked(&record);
+        }
+        result
+    }
+}
+
+/// Mask the entries of `value` matching `path`, collecting their pointers
+fn mask(value: &mut Value, path: &[String], rule: &Mask, at: &str, masked: &mut Vec<String>) {
+    let Some((segment, rest)) = path.split_first() else {
+        return;
+    };
+    let keys: Vec<String> = match value {
+        Value::Object(map) if segment == "*" => map.keys().cloned().collect(),
+        Value::Array(items) if segment == "*" => (0..items.len()).map(|i| i.to_string()).collect(),
+        Value::Object(_) | Value::Array(_) => vec![segment.clone()],
+        _ => return,
+    };
+    // backwards, so removing array entries keeps the indices of the rest
+    for key in keys.iter().rev() {
+        let pointer = format!("{at}/{}", key.replace('~', "~0").replace('/', "~1"));
+        if rest.is_empty() {
+            if rule.apply(value, key) {
+                masked.push(pointer);
+            }
+        } else if let Some(child) = entry(value, key) {
+            mask(child, rest, rule, &pointer, masked);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{JsonRPCMethod, RequestId, Response};
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    struct Capture(Mutex<Vec<MaskingRecord>>);
+
+    impl MaskingObserver for Capture {
+        fn on_masked(&self, record: &MaskingRecord) {
+            self.0.lock().unwrap().push(record.clone());
+        }
+    }
+
+    struct Report;
+
+    #[async_trait::async_trait]
+    impl JsonRPCMethod for Report {
+        fn method_name(&self) -> &'static str {
+            "report"
+        }
+
+        async fn call(&self, _params: Option<Value>, id: Option<RequestId>) -> Response {
+            crate::rpc_success!(
+                json!({
+                    "owner": "alice",
+                    "rows": [
+                        {"customer": "acme", "revenue": 1234},
+                        {"customer": "initech", "revenue": "n/a"}
+                    ]
+                }),
+                id
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn test_masking_by_scope() {
+        let capture = Arc::new(Capture(Mutex::new(Vec::new())));
+        let masking = ScopedMasking::new()
+            .rule("rows.*.customer", "analytics:pii", Mask::Remove)
+            .rule("rows.*.revenue", "analytics:exact", Mask::Round(100.0))
+            .rule("owner", "analytics:pii", Mask::Replace(json!("***")))
+            .rule("missing.field", "analytics:pii", Mask::Remove)
+            .observer(capture.clone());
+        let registry = crate::MethodRegistry::new(crate::register_methods![Report])
+            .with_method_transformer("report", masking);
+
+        let mut ctx = ConnectionContext::with_addr("127.0.0.1:4000".parse().unwrap());
+        ctx.grant_scopes(["analytics:exact"]);
+        let response = registry
+            .call_with_context("report", None, Some(json!(1)), &ctx)
+            .await;
+        assert_eq!(
+            response.result,
+            Some(json!({
+                "owner": "***",
+                "rows": [{"revenue": 1234}, {"revenue": "n/a"}]
+            }))
+        );
+
+        let response = registry.call("report", None, Some(json!(2))).await;
+        assert_eq!(response.result.unwrap()["rows"][0]["revenue"], 1200.0);
+
+        ctx.grant_scopes(["analytics:pii"]);
+        let response = registry
+            .call_with_context("report", None, Some(json!(3)), &ctx)
+            .await;
+        assert_eq!(response.result.unwrap()["owner"], "alice");
+
+        let records = capture.0.lock().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].method, "report");
+        assert_eq!(records[0].remote_addr, ctx.remote_addr);
+        assert_eq!(
+            records[0].fields,
+            ["/rows/1/customer", "/rows/0/customer", "/owner"]
+        );
+        assert_eq!(records[0].missing_scopes, ["analytics:pii"]);
+        assert_eq!(
+            records[1].missing_scopes,
+            ["analytics:pii", "analytics:exact"]
+        );
+    }
+}
@@ -0,0 +1,255 @@
+//! Method documentation loaded at runtime
+//!
+//! Operators can enrich the generated spec without recompiling: summaries,
+//! descriptions, tags, examples and deprecation notices are read from a
+//! [`MetadataSource`] at startup and merged over what the methods declare
+//! in [`JsonRPCMethod::openapi_components`](crate::JsonRPCMethod::openapi_components).
+//! Fields present in the metadata replace the compiled-in ones; tags and
+//! examples are appended.
+//!
+//! ```json
+//! {
+//!   "methods": {
+//!     "transfer": {
+//!       "summary": "Move funds between accounts",
+//!       "tags": ["payments"],
+//!       "deprecated": { "since": "4.2", "replacement": "transfer.v2" }
+//!     }
+//!   }
+//! }
+//! ```
+//!
+//! Every entry must name a registered method, so a typo or a method removed
+//! in a later release fails at startup instead of silently documenting
+//! nothing.
+//!
+//! ```rust
+//! use ash_rpc::method_metadata::MethodMetadata;
+//! use ash_rpc::*;
+//!
+//! struct Transfer;
+//!
+//! #[async_trait]
+//! impl JsonRPCMethod for Transfer {
+//!     fn method_name(&self) -> &'static str { "transfer" }
+//!     async fn call(&self, _: Option<serde_json::Value>, id: Option<RequestId>) -> Response {
+//!         rpc_success!(true, id)
+//!     }
+//! }
+//!
+//! let metadata = MethodMetadata::from_json(r#"{"methods": {"transfer": {"summary": "Move funds"}}}"#)?;
+//! let registry = MethodRegistry::new(register_methods![Transfer]).with_method_metadata(metadata)?;
+//! let spec = registry.generate_openapi_spec("bank", "1.0.0");
+//! assert_eq!(spec.methods["transfer"].summary.as_deref(), Some("Move funds"));
+//! # Ok::<(), ash_rpc::method_metadata::MetadataError>(())
+//! ```
+
+use crate::traits::{OpenApiDeprecation, OpenApiExample, OpenApiMethodSpec};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Why method metadata could not be used
+#[derive(Debug)]
+pub enum MetadataError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+    /// Entries naming methods the registry does not have
+    UnknownMethods(Vec<String>),
+}
+
+impl fmt::Display for MetadataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetadataError::Io(e) => write!(f, "failed to read method metadata: {e}"),
+            MetadataError::Parse(e) => write!(f, "failed to parse method metadata: {e}"),
+            MetadataError::UnknownMethods(names) => {
+                write!(f, "metadata for unknown methods: {}", names.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for MetadataError {}
+
+/// Documentation overrides for one method
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MethodDoc {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<OpenApiExample>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<OpenApiDeprecation>,
+}
+
+impl MethodDoc {
+    /// Merge into a method's compiled-in spec
+    pub fn apply(&self, spec: &mut OpenApiMethodSpec) {
+        if let Some(summary) = &self.summary {
+            spec.summary = Some(summary.clone());
+        }
+        if let Some(description) = &self.description {
+            spec.description = Some(description.clone());
+        }
+        for tag in &self.tags {
+            if !spec.tags.contains(tag) {
+                spec.tags.push(tag.clone());
+            }
+        }
+        spec.examples.extend(self.examples.iter().cloned());
+        if let Some(deprecated) = &self.deprecated {
+            spec.deprecated = Some(deprecated.clone());
+        }
+    }
+}
+
+/// Documentation overrides keyed by method name
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MethodMetadata {
+    #[serde(default)]
+    pub methods: BTreeMap<String, MethodDoc>,
+}
+
+impl MethodMetadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, MetadataError> {
+        serde_json::from_str(json).map_err(MetadataError::Parse)
+    }
+
+    /// Load from any [`MetadataSource`]
+    pub fn load(source: &dyn MetadataSource) -> Result<Self, MetadataError> {
+        source.load()
+    }
+
+    /// Add or replace the entry for `method`
+    pub fn method(mut self, method: impl Into<String>, doc: MethodDoc) -> Self {
+        self.methods.insert(method.into(), doc);
+        self
+    }
+
+    /// Overrides for `method`, if any
+    pub fn get(&self, method: &str) -> Option<&MethodDoc> {
+        self.methods.get(method)
+    }
+
+    /// Check that every entry names one of `registered`
+    pub fn validate<S: AsRef<str>>(&self, registered: &[S]) -> Result<(), MetadataError> {
+        let unknown: Vec<String> = self
+            .methods
+            .keys()
+            .filter(|name| !registered.iter().any(|r| r.as_ref() == name.as_str()))
+            .cloned()
+            .collect();
+        if unknown.is_empty() {
+            Ok(())
+        } else {
+            Err(MetadataError::UnknownMethods(unknown))
+        }
+    }
+}
+
+/// Where method metadata is stored
+pub trait MetadataSource: Send + Sync {
+    fn load(&self) -> Result<MethodMetadata, MetadataError>;
+}
+
+/// A JSON file in the format shown in the [module docs](self)
+#[derive(Debug, Clone)]
+pub struct JsonFileSource {
+    path: PathBuf,
+}
+
+impl JsonFileSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl MetadataSource for JsonFileSource {
+    fn load(&self) -> Result<MethodMetadata, MetadataError> {
+        let contents = std::fs::read_to_string(&self.path).map_err(MetadataError::Io)?;
+        MethodMetadata::from_json(&contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_apply_merges_over_compiled_spec() {
+        let metadata: MethodMetadata = serde_json::from_value(json!({
+            "methods": {
+                "transfer": {
+                    "summary": "Move funds",
+                    "tags": ["payments", "core"],
+                    "examples": [{"name": "basic", "summary": null, "description": null,
+                                  "params": {"amount": 5}, "result": true}],
+                    "deprecated": {"since": "4.2", "replacement": "transfer.v2"}
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut spec = OpenApiMethodSpec::new("transfer")
+            .with_summary("compiled")
+            .with_description("kept")
+            .with_tag("core");
+        metadata.get("transfer").unwrap().apply(&mut spec);
+
+        assert_eq!(spec.summary.as_deref(), Some("Move funds"));
+        assert_eq!(spec.description.as_deref(), Some("kept"));
+        assert_eq!(spec.tags, vec!["core", "payments"]);
+        assert_eq!(spec.examples.len(), 1);
+        let deprecated = spec.deprecated.unwrap();
+        assert_eq!(deprecated.replacement.as_deref(), Some("transfer.v2"));
+    }
+
+    #[test]
+    fn test_validate_and_parse_errors() {
+        let metadata = MethodMetadata::new()
+            .method("known", MethodDoc::default())
+            .method("typo", MethodDoc::default());
+        match metadata.validate(&["known"]) {
+            Err(MetadataError::UnknownMethods(names)) => assert_eq!(names, vec!["typo"]),
+            other => panic!("unexpected {other:?}"),
+        }
+        assert!(metadata.validate(&["known", "typo"]).is_ok());
+
+        let err = MethodMetadata::from_json(r#"{"methods": {"m": {"sumary": "x"}}}"#);
+        assert!(matches!(err, Err(MetadataError::Parse(_))));
+    }
+
+    #[test]
+    fn test_json_file_source() {
+        let path =
+            std::env::temp_dir().join(format!("ash-rpc-metadata-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{"methods": {"ping": {"description": "Liveness"}}}"#,
+        )
+        .unwrap();
+
+        let metadata = MethodMetadata::load(&JsonFileSource::new(&path)).unwrap();
+        assert_eq!(
+            metadata.get("ping").unwrap().description.as_deref(),
+            Some("Liveness")
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        let missing = JsonFileSource::new(&path).load();
+        assert!(matches!(missing, Err(MetadataError::Io(_))));
+    }
+}
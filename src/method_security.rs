@@ -0,0 +1,132 @@
+//! Security requirements declared by methods
+//!
+//! A method states the scopes and roles a caller needs and the rate limit
+//! class it belongs to, either by overriding
+//! [`JsonRPCMethod::security`](crate::JsonRPCMethod::security) or with
+//! [`MethodRegistry::with_method_security`](crate::MethodRegistry::with_method_security).
+//! The registry enforces the declaration on every call, counts the method
+//! against the [`RateLimitPolicy`](crate::rate_limit::RateLimitPolicy)
+//! rules of its class and writes it into the generated OpenAPI and OpenRPC
+//! documents as `x-security`, so the documented requirements are the
+//! enforced ones.
+//!
+//! Requirements are checked after the registry's auth policy accepted and
+//! identified the caller, with the same lookup as
+//! [`ScopePolicy`](crate::auth::ScopePolicy): scopes granted to the
+//! connection or held by its principal, roles of the principal.
+//!
+//! ```rust
+//! use ash_rpc::method_security::MethodSecurity;
+//! use ash_rpc::rate_limit::{Limit, RateLimitPolicy};
+//! use ash_rpc::*;
+//!
+//! struct Refund;
+//!
+//! #[async_trait]
+//! impl JsonRPCMethod for Refund {
+//!     fn method_name(&self) -> &'static str { "payments.refund" }
+//!     async fn call(&self, _: Option<serde_json::Value>, id: Option<RequestId>) -> Response {
+//!         rpc_success!(true, id)
+//!     }
+//!     fn security(&self) -> MethodSecurity {
+//!         MethodSecurity::new().scope("payments:write").role("teller").rate_class("expensive")
+//!     }
+//! }
+//!
+//! let registry = MethodRegistry::new(register_methods![Refund])
+//!     .with_rate_limit(RateLimitPolicy::new().class_per_principal("expensive", Limit::token_bucket(1, 5)));
+//! let spec = registry.generate_openapi_spec("payments", "1.0.0");
+//! assert_eq!(spec.methods["payments.refund"].extensions["x-security"]["scopes"][0], "payments:write");
+//! ```
+
+use crate::auth::ConnectionContext;
+use crate::traits::OpenApiMethodSpec;
+use serde::{Deserialize, Serialize};
+
+/// Extension key of the requirements in generated specs
+pub const SPEC_EXTENSION: &str = "x-security";
+
+/// Scopes, roles and rate limit class of one method
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MethodSecurity {
+    /// Scopes the caller must all hold
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes: Vec<String>,
+    /// Roles the caller's principal must all have
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+    /// Rate limit class the method is counted under
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_class: Option<String>,
+}
+
+impl MethodSecurity {
+    /// No requirements
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require `scope` (may be repeated)
+    pub fn scope(mut self, scope: impl Into<String>) -> Self {
+        self.scopes.push(scope.into());
+        self
+    }
+
+    /// Require the principal to have `role` (may be repeated)
+    pub fn role(mut self, role: impl Into<String>) -> Self {
+        self.roles.push(role.into());
+        self
+    }
+
+    pub fn rate_class(mut self, class: impl Into<String>) -> Self {
+        self.rate_class = Some(class.into());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scopes.is_empty() && self.roles.is_empty() && self.rate_class.is_none()
+    }
+
+    /// Whether the caller on `ctx` meets every scope and role requirement
+    pub fn permits(&self, ctx: &ConnectionContext) -> bool {
+        self.scopes.iter().all(|scope| ctx.has_scope(scope))
+            && self.roles.iter().all(|role| ctx.has_role(role))
+    }
+
+    /// Document the requirements on `spec` under [`SPEC_EXTENSION`]
+    pub fn apply(&self, spec: &mut OpenApiMethodSpec) {
+        if let Ok(value) = serde_json::to_value(self) {
+            spec.extensions.insert(SPEC_EXTENSION.to_string(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Principal;
+    use serde_json::json;
+
+    #[test]
+    fn test_permits_and_documents() {
+        let security = MethodSecurity::new()
+            .scope("ledger:write")
+            .role("auditor")
+            .rate_class("bulk");
+
+        let mut ctx = ConnectionContext::new();
+        ctx.grant_scopes(["ledger:write"]);
+        assert!(!security.permits(&ctx));
+        ctx.set_principal(Principal::new("ann").role("auditor"));
+        assert!(security.permits(&ctx));
+        assert!(MethodSecurity::new().permits(&ConnectionContext::new()));
+
+        let mut spec = OpenApiMethodSpec::new("ledger.post");
+        security.apply(&mut spec);
+        assert_eq!(
+            spec.extensions[SPEC_EXTENSION],
+            json!({"scopes": ["ledger:write"], "roles": ["auditor"], "rateClass": "bulk"})
+        );
+    }
+}
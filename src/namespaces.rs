@@ -0,0 +1,157 @@
+//! Reserved method namespaces.
+//!
+//! Method names starting with `rpc.`, `admin.` or `system.` belong to the
+//! built-ins shipped with ash-rpc, present and future: `rpc.discover`,
+//! `admin.dead_letters`, `system.info` and the like. A registry refuses
+//! user methods under these prefixes, so a later built-in cannot silently
+//! collide with them: [`MethodRegistry::try_new`](crate::MethodRegistry::try_new)
+//! and [`try_add_method`](crate::MethodRegistry::try_add_method) return a
+//! [`RegistrationError`], while the infallible
+//! [`new`](crate::MethodRegistry::new) and
+//! [`add_method`](crate::MethodRegistry::add_method) log an error and leave
+//! the method out. Registries that own these names on purpose opt out with
+//! [`allow_reserved_namespaces`](crate::MethodRegistry::allow_reserved_namespaces),
+//! which also registers the methods refused so far.
+//!
+//! Generated specs state the contract in an `x-reserved-namespaces`
+//! extension.
+//!
+//! ```rust
+//! use ash_rpc::namespaces::RegistrationError;
+//! use ash_rpc::*;
+//!
+//! struct Reset;
+//!
+//! #[async_trait]
+//! impl JsonRPCMethod for Reset {
+//!     fn method_name(&self) -> &'static str {
+//!         "admin.reset"
+//!     }
+//!
+//!     async fn call(&self, _params: Option<serde_json::Value>, id: Option<RequestId>) -> Response {
+//!         rpc_success!(true, id)
+//!     }
+//! }
+//!
+//! let error = MethodRegistry::try_new(register_methods![Reset]).err().unwrap();
+//! assert_eq!(
+//!     error,
+//!     RegistrationError::ReservedNamespace {
+//!         method: "admin.reset".into(),
+//!         prefix: "admin.",
+//!     }
+//! );
+//!
+//! let registry = MethodRegistry::new(register_methods![Reset]);
+//! assert!(!registry.has_method("admin.reset"));
+//!
+//! let registry = registry.allow_reserved_namespaces();
+//! assert!(registry.has_method("admin.reset"));
+//! ```
+
+use serde_json::Value;
+use std::fmt;
+
+/// Method name prefixes reserved for built-ins
+pub const RESERVED_PREFIXES: &[&str] = &["rpc.", "admin.", "system."];
+
+/// The reserved prefix `method` falls under, if any
+pub fn reserved_prefix(method: &str) -> Option<&'static str> {
+    RESERVED_PREFIXES
+        .iter()
+        .copied()
+        .find(|prefix| method.starts_with(prefix))
+}
+
+/// Why a method could not be registered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistrationError {
+    /// A user method named under a reserved prefix
+    ReservedNamespace {
+        method: String,
+        prefix: &'static str,
+    },
+}
+
+impl fmt::Display for RegistrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ReservedNamespace { method, prefix } => write!(
+                f,
+                "method `{method}` uses the reserved namespace `{prefix}`"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RegistrationError {}
+
+/// The `x-reserved-namespaces` spec extension
+pub(crate) fn extension(enforced: bool) -> Value {
+    serde_json::json!({
+        "prefixes": RESERVED_PREFIXES,
+        "enforced": enforced,
+        "description": "Methods under these prefixes are built-ins of the server; \
+                        user methods are not registered under them",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{JsonRPCMethod, MethodRegistry, RequestId, Response};
+
+    struct Method(&'static str, bool);
+
+    #[async_trait::async_trait]
+    impl JsonRPCMethod for Method {
+        fn method_name(&self) -> &'static str {
+            self.0
+        }
+
+        async fn call(&self, _params: Option<Value>, id: Option<RequestId>) -> Response {
+            crate::rpc_success!(true, id)
+        }
+
+        fn is_builtin(&self) -> bool {
+            self.1
+        }
+    }
+
+    #[test]
+    fn test_reserved_namespaces() {
+        assert_eq!(reserved_prefix("rpc.discover"), Some("rpc."));
+        assert_eq!(reserved_prefix("rpcs.list"), None);
+
+        let registry = MethodRegistry::try_new(vec![
+            Box::new(Method("users.get", false)),
+            Box::new(Method("system.info", true)),
+        ])
+        .unwrap();
+        assert!(registry.has_method("system.info"));
+        let error = registry
+            .try_add_method(Box::new(Method("system.reboot", false)))
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "method `system.reboot` uses the reserved namespace `system.`"
+        );
+
+        let registry = MethodRegistry::new(vec![
+            Box::new(Method("users.get", false)),
+            Box::new(Method("rpc.custom", false)),
+        ])
+        .add_method(Box::new(Method("admin.reset", false)));
+        assert_eq!(registry.get_methods(), ["users.get"]);
+        let registry = registry.allow_reserved_namespaces();
+        assert!(registry.has_method("rpc.custom") && registry.has_method("admin.reset"));
+
+        let spec = MethodRegistry::empty()
+            .allow_reserved_namespaces()
+            .generate_openapi_spec("Test", "1.0.0");
+        let extension = &spec.extensions["x-reserved-namespaces"];
+        assert_eq!(extension["prefixes"], serde_json::json!(RESERVED_PREFIXES));
+        assert_eq!(extension["enforced"], false);
+    }
+}
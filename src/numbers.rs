@@ -0,0 +1,298 @@
+//! Big-number safety.
+//!
+//! JSON numbers are commonly decoded as IEEE-754 doubles, so JavaScript
+//! callers silently lose precision on integers beyond ±2^53. This module
+//! provides:
+//!
+//! - [`find_unsafe_integer`] to locate integers outside the safe range,
+//!   used by the registry's strict-numbers mode
+//!   (`MethodRegistry::with_strict_numbers`)
+//! - serde adapters ([`as_string`], [`as_string_opt`]) that put 64-bit
+//!   integers and decimals on the wire as strings while still accepting
+//!   plain numbers on input
+//! - [`DecimalString`], an exact decimal carried as text
+//! - schema fragments describing the string encoding for generated specs
+//!
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Transfer {
+//!     #[serde(with = "ash_rpc::numbers::as_string")]
+//!     account: u64,
+//!     amount: ash_rpc::numbers::DecimalString,
+//! }
+//!
+//! let t: Transfer =
+//!     serde_json::from_str(r#"{"account":"18446744073709551615","amount":"10.25"}"#).unwrap();
+//! assert_eq!(t.account, u64::MAX);
+//! assert_eq!(
+//!     serde_json::to_string(&t).unwrap(),
+//!     r#"{"account":"18446744073709551615","amount":"10.25"}"#
+//! );
+//! ```
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+/// Largest integer a double represents exactly (2^53 - 1)
+pub const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// Whether `number` is an integer that survives a round trip through a double
+pub fn is_safe_integer(number: &serde_json::Number) -> bool {
+    if let Some(n) = number.as_u64() {
+        n <= MAX_SAFE_INTEGER
+    } else if let Some(n) = number.as_i64() {
+        n.unsigned_abs() <= MAX_SAFE_INTEGER
+    } else {
+        // floats are not integers and are checked by the target type
+        true
+    }
+}
+
+/// JSON pointer of the first integer in `value` outside ±(2^53 - 1)
+pub fn find_unsafe_integer(value: &Value) -> Option<String> {
+    match value {
+        Value::Number(n) if !is_safe_integer(n) => Some(String::new()),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .find_map(|(i, item)| find_unsafe_integer(item).map(|path| format!("/{i}{path}"))),
+        Value::Object(map) => map.iter().find_map(|(key, item)| {
+            find_unsafe_integer(item)
+                .map(|path| format!("/{}{path}", key.replace('~', "~0").replace('/', "~1")))
+        }),
+        _ => None,
+    }
+}
+
+/// Serde adapter writing a number as a JSON string.
+///
+/// Deserialization accepts either a string or a plain JSON number, so
+/// clients that never send large values keep working. Works for any type
+/// implementing `Display` and `FromStr`, e.g. `i64`, `u64`, `i128`.
+pub mod as_string {
+    use super::*;
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: fmt::Display,
+        S: Serializer,
+    {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+        D: Deserializer<'de>,
+    {
+        let text = match StringOrNumber::deserialize(deserializer)? {
+            StringOrNumber::String(s) => s,
+            StringOrNumber::Number(n) => n.to_string(),
+        };
+        text.trim().parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// [`as_string`] for optional fields
+pub mod as_string_opt {
+    use super::*;
+
+    pub fn serialize<T, S>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: fmt::Display,
+        S: Serializer,
+    {
+        match value {
+            Some(value) => serializer.collect_str(value),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+        D: Deserializer<'de>,
+    {
+        let text = match Option::<StringOrNumber>::deserialize(deserializer)? {
+            Some(StringOrNumber::String(s)) => s,
+            Some(StringOrNumber::Number(n)) => n.to_string(),
+            None => return Ok(None),
+        };
+        text.trim()
+            .parse()
+            .map(Some)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StringOrNumber {
+    String(String),
+    Number(serde_json::Number),
+}
+
+/// Exact decimal number carried as a JSON string
+///
+/// The text is validated (optional sign, digits, optional fraction) but not
+/// converted, so no precision is lost; parse it with the decimal type of your
+/// choice. Plain JSON numbers are accepted on input.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DecimalString(String);
+
+impl DecimalString {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+/// Error for text that is not a plain decimal number
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidDecimal(String);
+
+impl fmt::Display for InvalidDecimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid decimal number: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidDecimal {}
+
+impl FromStr for DecimalString {
+    type Err = InvalidDecimal;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.strip_prefix('-').unwrap_or(s);
+        let (int, frac) = match digits.split_once('.') {
+            Some((int, frac)) => (int, Some(frac)),
+            None => (digits, None),
+        };
+        let valid = !int.is_empty()
+            && int.bytes().all(|b| b.is_ascii_digit())
+            && frac.is_none_or(|f| !f.is_empty() && f.bytes().all(|b| b.is_ascii_digit()));
+        if valid {
+            Ok(Self(s.to_string()))
+        } else {
+            Err(InvalidDecimal(s.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for DecimalString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for DecimalString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for DecimalString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        as_string::deserialize(deserializer)
+    }
+}
+
+/// Schema of an integer encoded with [`as_string`]; `format` is e.g. `"int64"`
+pub fn string_integer_schema(format: &str) -> Value {
+    serde_json::json!({
+        "type": "string",
+        "format": format,
+        "pattern": "^-?[0-9]+$",
+    })
+}
+
+/// Schema of a [`DecimalString`]
+pub fn decimal_schema() -> Value {
+    serde_json::json!({
+        "type": "string",
+        "format": "decimal",
+        "pattern": "^-?[0-9]+(\\.[0-9]+)?$",
+    })
+}
+
+/// Spec extension describing the strict-numbers wire contract
+pub(crate) fn strict_numbers_extension() -> Value {
+    serde_json::json!({
+        "max_safe_integer": MAX_SAFE_INTEGER,
+        "large_integers": "string",
+        "decimals": "string",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Ids {
+        #[serde(with = "as_string")]
+        id: i64,
+        #[serde(with = "as_string_opt", default)]
+        parent: Option<u64>,
+    }
+
+    #[test]
+    fn test_find_unsafe_integer() {
+        assert_eq!(find_unsafe_integer(&json!(9007199254740991u64)), None);
+        assert_eq!(
+            find_unsafe_integer(&json!(9007199254740992u64)),
+            Some(String::new())
+        );
+        assert_eq!(
+            find_unsafe_integer(&json!({"a": [1, -9007199254740993i64]})),
+            Some("/a/1".to_string())
+        );
+        assert_eq!(find_unsafe_integer(&json!({"x": 1e300})), None);
+    }
+
+    #[test]
+    fn test_as_string_round_trip() {
+        let ids = Ids {
+            id: i64::MIN,
+            parent: Some(u64::MAX),
+        };
+        let json = serde_json::to_value(&ids).unwrap();
+        assert_eq!(
+            json,
+            json!({"id": "-9223372036854775808", "parent": "18446744073709551615"})
+        );
+        assert_eq!(serde_json::from_value::<Ids>(json).unwrap(), ids);
+    }
+
+    #[test]
+    fn test_as_string_accepts_numbers() {
+        let ids: Ids = serde_json::from_value(json!({"id": 42})).unwrap();
+        assert_eq!(ids.id, 42);
+        assert_eq!(ids.parent, None);
+
+        assert!(serde_json::from_value::<Ids>(json!({"id": "4.2"})).is_err());
+        assert!(serde_json::from_value::<Ids>(json!({"id": "99999999999999999999"})).is_err());
+    }
+
+    #[test]
+    fn test_decimal_string() {
+        let value: DecimalString = serde_json::from_value(json!("-12.500")).unwrap();
+        assert_eq!(value.as_str(), "-12.500");
+        let value: DecimalString = serde_json::from_value(json!(3)).unwrap();
+        assert_eq!(value.as_str(), "3");
+
+        for bad in ["", "1.", ".5", "1e5", "--1", "abc"] {
+            assert!(bad.parse::<DecimalString>().is_err(), "{bad}");
+        }
+    }
+}
@@ -2,7 +2,7 @@
 //!
 //! Provides metrics collection, distributed tracing, and unified observability wrapper.
 
-use crate::{Message, MessageProcessor, ProcessorCapabilities, Response};
+use crate::{Message, MessageProcessor, ProcessorCapabilities, Request, Response};
 use async_trait::async_trait;
 use std::sync::Arc;
 
@@ -44,14 +44,16 @@ impl ObservableProcessor {
             logger: None,
         }
     }
-}
 
-#[async_trait]
-impl MessageProcessor for ObservableProcessor {
-    async fn process_message(&self, message: Message) -> Option<Response> {
+    /// Log `message` and start timing and tracing it
+    #[cfg_attr(
+        not(any(feature = "logging", feature = "opentelemetry")),
+        allow(unused_variables)
+    )]
+    fn begin(&self, message: &Message) -> Observation {
         #[cfg(feature = "logging")]
         if let Some(logger) = &self.logger {
-            match &message {
+            match message {
                 Message::Request(req) => {
                     logger.debug(
                         "Processing request",
@@ -67,22 +69,30 @@ impl MessageProcessor for ObservableProcessor {
             }
         }
 
-        #[cfg(feature = "prometheus")]
-        let start = std::time::Instant::now();
-
-        #[cfg(feature = "opentelemetry")]
-        let span_guard = if let Some(tracer) = &self.tracer {
-            tracer.start_span(&message)
-        } else {
-            None
-        };
-
-        let response = self.inner.process_message(message.clone()).await;
+        Observation {
+            #[cfg(feature = "prometheus")]
+            start: std::time::Instant::now(),
+            #[cfg(feature = "opentelemetry")]
+            span: self
+                .tracer
+                .as_ref()
+                .and_then(|tracer| tracer.start_span(message)),
+        }
+    }
 
+    /// Record the outcome of `message`, answered with `response`
+    #[cfg_attr(not(feature = "prometheus"), allow(unused_variables))]
+    fn finish(
+        &self,
+        observation: Observation,
+        message: &Message,
+        response: Option<&Response>,
+        ctx: &crate::auth::ConnectionContext,
+    ) {
         #[cfg(feature = "prometheus")]
         if let Some(metrics) = &self.metrics {
-            let duration = start.elapsed();
-            let method = match &message {
+            let duration = observation.start.elapsed();
+            let method = match message {
                 Message::Request(req) => &req.method,
                 Message::Notification(notif) => &notif.method,
                 Message::Response(_) => "response",
@@ -91,13 +101,14 @@ impl MessageProcessor for ObservableProcessor {
             metrics.record_request(
                 method,
                 duration,
-                response.as_ref().map(|r| r.is_success()).unwrap_or(true),
+                response.map(|r| r.is_success()).unwrap_or(true),
             );
+            metrics.record_principal(ctx.principal());
         }
 
         #[cfg(feature = "opentelemetry")]
-        if let Some(mut guard) = span_guard
-            && let Some(response) = &response
+        if let Some(mut guard) = observation.span
+            && let Some(response) = response
             && !response.is_success()
         {
             guard.record_error();
@@ -105,7 +116,7 @@ impl MessageProcessor for ObservableProcessor {
 
         #[cfg(feature = "logging")]
         if let Some(logger) = &self.logger
-            && let Some(response) = &response
+            && let Some(response) = response
         {
             if response.is_success() {
                 logger.debug("Request succeeded", &[]);
@@ -113,10 +124,77 @@ impl MessageProcessor for ObservableProcessor {
                 logger.warn("Request failed", &[]);
             }
         }
+    }
+}
+
+/// Timer and span of a message being processed
+struct Observation {
+    #[cfg(feature = "prometheus")]
+    start: std::time::Instant,
+    #[cfg(feature = "opentelemetry")]
+    span: Option<tracing::SpanGuard>,
+}
+
+#[async_trait]
+impl MessageProcessor for ObservableProcessor {
+    async fn process_message(&self, message: Message) -> Option<Response> {
+        self.process_message_with_context(message, &crate::auth::ConnectionContext::default())
+            .await
+    }
 
+    async fn process_message_with_context(
+        &self,
+        message: Message,
+        ctx: &crate::auth::ConnectionContext,
+    ) -> Option<Response> {
+        let observation = self.begin(&message);
+        let response = self
+            .inner
+            .process_message_with_context(message.clone(), ctx)
+            .await;
+        self.finish(observation, &message, response.as_ref(), ctx);
         response
     }
 
+    async fn process_batch(&self, messages: Vec<Message>) -> Vec<Response> {
+        self.process_batch_with_context(messages, &crate::auth::ConnectionContext::default())
+            .await
+    }
+
+    /// The batch is forwarded whole, so the inner processor's batch policy
+    /// and limits apply; each entry is still logged, timed and traced
+    async fn process_batch_with_context(
+        &self,
+        messages: Vec<Message>,
+        ctx: &crate::auth::ConnectionContext,
+    ) -> Vec<Response> {
+        let observations: Vec<_> = messages.iter().map(|message| self.begin(message)).collect();
+        let responses = self
+            .inner
+            .process_batch_with_context(messages.clone(), ctx)
+            .await;
+
+        let answers = crate::batch_policy::answers(&messages, &responses);
+        for ((observation, message), response) in
+            observations.into_iter().zip(&messages).zip(answers)
+        {
+            self.finish(observation, message, response, ctx);
+        }
+        responses
+    }
+
+    async fn admit_with_context(
+        &self,
+        request: &Request,
+        ctx: &crate::auth::ConnectionContext,
+    ) -> Option<Response> {
+        self.inner.admit_with_context(request, ctx).await
+    }
+
+    fn supports_batching(&self) -> bool {
+        self.inner.supports_batching()
+    }
+
     fn get_capabilities(&self) -> ProcessorCapabilities {
         self.inner.get_capabilities()
     }
@@ -168,3 +246,79 @@ impl ObservabilityBuilder {
         }
     }
 }
+
+#[cfg(all(test, feature = "logging"))]
+mod tests {
+    use super::*;
+    use crate::batch_policy::BatchPolicy;
+    use crate::logger::LogKv;
+    use crate::{JsonRPCMethod, MethodRegistry, RequestBuilder, RequestId, error_codes};
+    use std::sync::Mutex;
+
+    struct Ping;
+
+    #[async_trait]
+    impl JsonRPCMethod for Ping {
+        fn method_name(&self) -> &'static str {
+            "ping"
+        }
+
+        async fn call(
+            &self,
+            _params: Option<serde_json::Value>,
+            id: Option<RequestId>,
+        ) -> Response {
+            Response::success(serde_json::json!("pong"), id)
+        }
+    }
+
+    #[derive(Default)]
+    struct Warnings(Mutex<usize>);
+
+    impl Logger for Warnings {
+        fn debug(&self, _message: &str, _kvs: &[LogKv]) {}
+        fn info(&self, _message: &str, _kvs: &[LogKv]) {}
+        fn warn(&self, _message: &str, _kvs: &[LogKv]) {
+            *self.0.lock().unwrap() += 1;
+        }
+        fn error(&self, _message: &str, _kvs: &[LogKv]) {}
+    }
+
+    #[tokio::test]
+    async fn test_observable_processor_forwards_batches() {
+        let registry = MethodRegistry::new(vec![Box::new(Ping)])
+            .with_batch_policy(BatchPolicy::AbortOnError)
+            .unwrap();
+        let warnings = Arc::new(Warnings::default());
+        let processor = ObservableProcessor::builder(Arc::new(registry))
+            .with_logger(warnings.clone())
+            .build();
+
+        let batch = ["ping", "missing", "ping"]
+            .into_iter()
+            .enumerate()
+            .map(|(id, method)| {
+                Message::Request(
+                    RequestBuilder::new(method)
+                        .id(serde_json::json!(id))
+                        .build(),
+                )
+            })
+            .collect();
+        let responses = processor.process_batch(batch).await;
+        let codes: Vec<_> = responses
+            .iter()
+            .map(|r| r.error.as_ref().map(|e| e.code))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                None,
+                Some(error_codes::METHOD_NOT_FOUND),
+                Some(error_codes::DEPENDENT_FAILURE)
+            ]
+        );
+        // the failed entry and the one skipped after it
+        assert_eq!(*warnings.0.lock().unwrap(), 2);
+    }
+}
@@ -1,6 +1,14 @@
 //! Prometheus metrics collection for JSON-RPC
 
-use prometheus::{CounterVec, Encoder, HistogramOpts, HistogramVec, IntGauge, Opts, Registry};
+use prometheus::core::{Collector, Desc};
+use prometheus::proto::MetricFamily;
+use prometheus::proto::MetricType;
+use prometheus::{
+    CounterVec, Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -10,6 +18,8 @@ pub struct PrometheusMetrics {
     request_counter: CounterVec,
     request_duration: HistogramVec,
     error_counter: CounterVec,
+    rejection_counter: CounterVec,
+    principal_counter: CounterVec,
     active_connections: IntGauge,
 }
 
@@ -50,6 +60,22 @@ impl PrometheusMetrics {
             &["method"],
         )?;
 
+        let rejection_counter = CounterVec::new(
+            Opts::new(
+                format!("{}_rejections_total", prefix),
+                "Total number of rejected requests and connections",
+            ),
+            &["reason"],
+        )?;
+
+        let principal_counter = CounterVec::new(
+            Opts::new(
+                format!("{}_principal_requests_total", prefix),
+                "Total number of JSON-RPC requests by tenant and auth method",
+            ),
+            &["tenant", "auth_method"],
+        )?;
+
         let active_connections = IntGauge::new(
             format!("{}_active_connections", prefix),
             "Number of active connections",
@@ -58,13 +84,22 @@ impl PrometheusMetrics {
         registry.register(Box::new(request_counter.clone()))?;
         registry.register(Box::new(request_duration.clone()))?;
         registry.register(Box::new(error_counter.clone()))?;
+        registry.register(Box::new(rejection_counter.clone()))?;
+        registry.register(Box::new(principal_counter.clone()))?;
         registry.register(Box::new(active_connections.clone()))?;
+        registry.register(Box::new(CodecCollector::new(prefix)?))?;
+        registry.register(Box::new(TransportCollector::new(prefix)?))?;
+        registry.register(Box::new(CoalesceCollector::new(prefix)?))?;
+        #[cfg(feature = "audit-logging")]
+        registry.register(Box::new(AnomalyCollector::new(prefix)?))?;
 
         Ok(Self {
             registry,
             request_counter,
             request_duration,
             error_counter,
+            rejection_counter,
+            principal_counter,
             active_connections,
         })
     }
@@ -89,6 +124,25 @@ impl PrometheusMetrics {
         }
     }
 
+    /// Record a rejection by reason
+    pub fn record_rejection(&self, reason: crate::rejection::RejectionReason) {
+        self.rejection_counter
+            .with_label_values(&[reason.as_str()])
+            .inc();
+    }
+
+    /// Record a request by the caller's tenant and auth method
+    ///
+    /// Principal ids are left out to keep cardinality bounded; missing
+    /// values are labelled `none`.
+    pub fn record_principal(&self, principal: Option<&crate::auth::Principal>) {
+        let tenant = principal.and_then(|p| p.tenant.as_deref());
+        let auth_method = principal.and_then(|p| p.auth_method.as_deref());
+        self.principal_counter
+            .with_label_values(&[tenant.unwrap_or("none"), auth_method.unwrap_or("none")])
+            .inc();
+    }
+
     /// Increment active connections count
     pub fn connection_opened(&self) {
         self.active_connections.inc();
@@ -114,6 +168,15 @@ impl PrometheusMetrics {
         Ok(String::from_utf8_lossy(&buffer).to_string())
     }
 
+    /// Gather metrics as structured values, sorted by family name
+    pub fn snapshot(&self) -> Vec<MetricFamilySnapshot> {
+        self.registry
+            .gather()
+            .iter()
+            .map(MetricFamilySnapshot::from)
+            .collect()
+    }
+
     /// Normalize method name to prevent cardinality explosion
     /// Keeps known methods as-is, groups unknown methods as "other"
     fn normalize_method<'a>(&self, method: &'a str) -> &'a str {
@@ -144,6 +207,247 @@ impl Default for PrometheusMetrics {
     }
 }
 
+/// Mirrors [`crate::transports::codec_stats`] into counters on every gather
+struct CodecCollector {
+    connections: IntCounterVec,
+    fallbacks: IntCounterVec,
+    frames_decoded: IntCounterVec,
+    decode_errors: IntCounterVec,
+}
+
+impl CodecCollector {
+    fn new(prefix: &str) -> Result<Self, prometheus::Error> {
+        let counter = |name: &str, help: &str| {
+            IntCounterVec::new(
+                Opts::new(format!("{prefix}_codec_{name}_total"), help),
+                &["codec"],
+            )
+        };
+        Ok(Self {
+            connections: counter("connections", "Connections by negotiated codec")?,
+            fallbacks: counter(
+                "fallbacks",
+                "Connections that asked for a codec but used another",
+            )?,
+            frames_decoded: counter("frames_decoded", "Frames decoded successfully by codec")?,
+            decode_errors: counter("decode_errors", "Frames that failed to decode by codec")?,
+        })
+    }
+
+    fn vecs(&self) -> [&IntCounterVec; 4] {
+        [
+            &self.connections,
+            &self.fallbacks,
+            &self.frames_decoded,
+            &self.decode_errors,
+        ]
+    }
+}
+
+impl Collector for CodecCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        self.vecs().into_iter().flat_map(|v| v.desc()).collect()
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        for stats in crate::transports::codec_stats::snapshot() {
+            let values = [
+                stats.connections,
+                stats.fallbacks,
+                stats.frames_decoded,
+                stats.decode_errors,
+            ];
+            for (vec, value) in self.vecs().into_iter().zip(values) {
+                let counter = vec.with_label_values(&[stats.codec.as_str()]);
+                counter.inc_by(value.saturating_sub(counter.get()));
+            }
+        }
+        self.vecs().into_iter().flat_map(|v| v.collect()).collect()
+    }
+}
+
+/// Mirrors [`crate::transports::transport_stats`] into counters and an
+/// active connection gauge on every gather
+struct TransportCollector {
+    accepted: IntCounterVec,
+    rejected: IntCounterVec,
+    bytes_received: IntCounterVec,
+    bytes_sent: IntCounterVec,
+    handshake_failures: IntCounterVec,
+    idle_timeouts: IntCounterVec,
+    active: IntGaugeVec,
+}
+
+impl TransportCollector {
+    fn new(prefix: &str) -> Result<Self, prometheus::Error> {
+        let counter = |name: &str, help: &str| {
+            IntCounterVec::new(
+                Opts::new(format!("{prefix}_transport_{name}_total"), help),
+                &["transport", "listener"],
+            )
+        };
+        Ok(Self {
+            accepted: counter("connections_accepted", "Connections accepted by transport")?,
+            rejected: counter(
+                "connections_rejected",
+                "Connections closed right after accept by transport",
+            )?,
+            bytes_received: counter("bytes_received", "Bytes read from connections")?,
+            bytes_sent: counter("bytes_sent", "Bytes written to connections")?,
+            handshake_failures: counter(
+                "handshake_failures",
+                "Failed TLS handshakes and WebSocket upgrades",
+            )?,
+            idle_timeouts: counter("idle_timeouts", "Connections closed for being idle")?,
+            active: IntGaugeVec::new(
+                Opts::new(
+                    format!("{prefix}_transport_active_connections"),
+                    "Open connections by transport",
+                ),
+                &["transport", "listener"],
+            )?,
+        })
+    }
+
+    fn counters(&self) -> [&IntCounterVec; 6] {
+        [
+            &self.accepted,
+            &self.rejected,
+            &self.bytes_received,
+            &self.bytes_sent,
+            &self.handshake_failures,
+            &self.idle_timeouts,
+        ]
+    }
+}
+
+impl Collector for TransportCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        self.counters()
+            .into_iter()
+            .flat_map(|v| v.desc())
+            .chain(self.active.desc())
+            .collect()
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        for stats in crate::transports::transport_stats::snapshot() {
+            let values = [
+                stats.accepted,
+                stats.rejected,
+                stats.bytes_received,
+                stats.bytes_sent,
+                stats.handshake_failures,
+                stats.idle_timeouts,
+            ];
+            let label = [stats.transport.as_str(), stats.listener.as_str()];
+            for (vec, value) in self.counters().into_iter().zip(values) {
+                let counter = vec.with_label_values(&label);
+                counter.inc_by(value.saturating_sub(counter.get()));
+            }
+            self.active
+                .with_label_values(&label)
+                .set(i64::try_from(stats.active).unwrap_or(i64::MAX));
+        }
+        self.counters()
+            .into_iter()
+            .flat_map(|v| v.collect())
+            .chain(self.active.collect())
+            .collect()
+    }
+}
+
+/// Mirrors [`crate::coalesce::snapshot`] into counters on every gather
+struct CoalesceCollector {
+    executions: IntCounterVec,
+    coalesced: IntCounterVec,
+}
+
+impl CoalesceCollector {
+    fn new(prefix: &str) -> Result<Self, prometheus::Error> {
+        let counter = |name: &str, help: &str| {
+            IntCounterVec::new(
+                Opts::new(format!("{prefix}_coalesce_{name}_total"), help),
+                &["method"],
+            )
+        };
+        Ok(Self {
+            executions: counter("executions", "Coalescing calls that ran their method")?,
+            coalesced: counter(
+                "coalesced",
+                "Calls answered with a concurrent call's result",
+            )?,
+        })
+    }
+}
+
+impl Collector for CoalesceCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        [&self.executions, &self.coalesced]
+            .into_iter()
+            .flat_map(|v| v.desc())
+            .collect()
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        for stats in crate::coalesce::snapshot() {
+            for (vec, value) in [
+                (&self.executions, stats.executions),
+                (&self.coalesced, stats.coalesced),
+            ] {
+                let counter = vec.with_label_values(&[stats.method.as_str()]);
+                counter.inc_by(value.saturating_sub(counter.get()));
+            }
+        }
+        [&self.executions, &self.coalesced]
+            .into_iter()
+            .flat_map(|v| v.collect())
+            .collect()
+    }
+}
+
+/// Mirrors [`crate::audit_logging::anomaly_stats`] into counters on every gather
+#[cfg(feature = "audit-logging")]
+struct AnomalyCollector {
+    anomalies: IntCounterVec,
+}
+
+#[cfg(feature = "audit-logging")]
+impl AnomalyCollector {
+    fn new(prefix: &str) -> Result<Self, prometheus::Error> {
+        Ok(Self {
+            anomalies: IntCounterVec::new(
+                Opts::new(
+                    format!("{prefix}_audit_anomalies_total"),
+                    "Anomalies flagged by audit analyzer rules",
+                ),
+                &["rule"],
+            )?,
+        })
+    }
+}
+
+#[cfg(feature = "audit-logging")]
+impl Collector for AnomalyCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        self.anomalies.desc()
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        for (rule, value) in crate::audit_logging::anomaly_stats() {
+            let counter = self.anomalies.with_label_values(&[rule.as_str()]);
+            counter.inc_by(value.saturating_sub(counter.get()));
+        }
+        self.anomalies.collect()
+    }
+}
+
+impl crate::rejection::RejectionObserver for PrometheusMetrics {
+    fn on_rejection(&self, rejection: &crate::rejection::Rejection) {
+        self.record_rejection(rejection.reason);
+    }
+}
+
 /// Builder for creating Prometheus metrics with custom configuration
 pub struct PrometheusMetricsBuilder {
     prefix: String,
@@ -187,7 +491,215 @@ impl Default for PrometheusMetricsBuilder {
     }
 }
 
+/// One metric family of [`PrometheusMetrics::snapshot`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricFamilySnapshot {
+    pub name: String,
+    pub help: String,
+    /// `counter`, `gauge`, `histogram`, `summary` or `untyped`
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub samples: Vec<MetricSample>,
+}
+
+/// One labelled series of a metric family
+///
+/// Counters and gauges carry `value`; histograms and summaries carry
+/// `count`, `sum` and their `buckets` or `quantiles`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MetricSample {
+    pub labels: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sum: Option<f64>,
+    /// Cumulative `(upper bound, count)` pairs; the `+Inf` bucket equals `count`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub buckets: Vec<(f64, u64)>,
+    /// `(quantile, value)` pairs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub quantiles: Vec<(f64, f64)>,
+}
+
+impl From<&prometheus::proto::MetricFamily> for MetricFamilySnapshot {
+    fn from(family: &prometheus::proto::MetricFamily) -> Self {
+        let kind = family.get_field_type();
+        let samples = family
+            .get_metric()
+            .iter()
+            .map(|metric| {
+                let mut sample = MetricSample {
+                    labels: metric
+                        .get_label()
+                        .iter()
+                        .map(|pair| (pair.name().to_string(), pair.value().to_string()))
+                        .collect(),
+                    ..Default::default()
+                };
+                match kind {
+                    MetricType::COUNTER => sample.value = Some(metric.get_counter().value()),
+                    MetricType::GAUGE => sample.value = Some(metric.get_gauge().value()),
+                    MetricType::UNTYPED => sample.value = Some(metric.untyped.value()),
+                    MetricType::HISTOGRAM => {
+                        let histogram = metric.get_histogram();
+                        sample.count = Some(histogram.sample_count());
+                        sample.sum = Some(histogram.sample_sum());
+                        sample.buckets = histogram
+                            .bucket
+                            .iter()
+                            .filter(|bucket| bucket.upper_bound().is_finite())
+                            .map(|bucket| (bucket.upper_bound(), bucket.cumulative_count()))
+                            .collect();
+                    }
+                    MetricType::SUMMARY => {
+                        let summary = metric.get_summary();
+                        sample.count = Some(summary.sample_count());
+                        sample.sum = Some(summary.sample_sum());
+                        sample.quantiles = summary
+                            .quantile
+                            .iter()
+                            .map(|q| (q.quantile(), q.value()))
+                            .collect();
+                    }
+                }
+                sample
+            })
+            .collect();
+        Self {
+            name: family.name().to_string(),
+            help: family.help().to_string(),
+            kind: format!("{kind:?}").to_lowercase(),
+            samples,
+        }
+    }
+}
+
+/// Scope a caller needs for `metrics.get` under a [`ScopePolicy`](crate::auth::ScopePolicy)
+pub const METRICS_READ_SCOPE: &str = "metrics:read";
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct MetricsGetParams {
+    format: MetricsFormat,
+    prefix: Option<String>,
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum MetricsFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// `metrics.get`: the metrics registry as Prometheus text or JSON
+///
+/// Params are optional: `{"format": "json"}` returns the
+/// [`snapshot`](PrometheusMetrics::snapshot) instead of the text exposition
+/// format, and `{"prefix": "jsonrpc_requests"}` limits the output to
+/// families whose name starts with the prefix. Metrics reveal traffic
+/// patterns, so expose the method behind an auth policy, e.g.
+/// `ScopePolicy::new(inner).require("metrics.get", METRICS_READ_SCOPE)`.
+pub struct MetricsGetMethod {
+    metrics: Arc<PrometheusMetrics>,
+}
+
+impl MetricsGetMethod {
+    pub fn new(metrics: Arc<PrometheusMetrics>) -> Self {
+        Self { metrics }
+    }
+}
+
+#[crate::async_trait]
+impl crate::JsonRPCMethod for MetricsGetMethod {
+    fn method_name(&self) -> &'static str {
+        "metrics.get"
+    }
+
+    async fn call(
+        &self,
+        params: Option<serde_json::Value>,
+        id: Option<crate::RequestId>,
+    ) -> crate::Response {
+        let params: MetricsGetParams = match params.map(serde_json::from_value).transpose() {
+            Ok(params) => params.unwrap_or_default(),
+            Err(e) => {
+                return crate::rpc_error!(crate::error_codes::INVALID_PARAMS, e.to_string(), id);
+            }
+        };
+        let prefix = params.prefix.as_deref().unwrap_or("");
+
+        if params.format == MetricsFormat::Json {
+            let families: Vec<_> = self
+                .metrics
+                .snapshot()
+                .into_iter()
+                .filter(|family| family.name.starts_with(prefix))
+                .collect();
+            return crate::rpc_success!(families, id);
+        }
+
+        let families: Vec<_> = self
+            .metrics
+            .registry
+            .gather()
+            .into_iter()
+            .filter(|family| family.name().starts_with(prefix))
+            .collect();
+        let mut buffer = Vec::new();
+        match prometheus::TextEncoder::new().encode(&families, &mut buffer) {
+            Ok(()) => crate::rpc_success!(String::from_utf8_lossy(&buffer), id),
+            Err(e) => {
+                tracing::error!(error = %e, "failed to gather metrics");
+                crate::rpc_error!(
+                    crate::error_codes::INTERNAL_ERROR,
+                    "Failed to gather metrics",
+                    id
+                )
+            }
+        }
+    }
+
+    fn openapi_components(&self) -> crate::OpenApiMethodSpec {
+        crate::OpenApiMethodSpec::new(self.method_name())
+            .with_summary("Service metrics as Prometheus text or JSON")
+            .with_description(format!(
+                "Requires the `{METRICS_READ_SCOPE}` scope when guarded by a scope policy."
+            ))
+            .with_tag("system")
+            .with_parameters(serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "format": {"type": "string", "enum": ["text", "json"], "default": "text"},
+                    "prefix": {"type": "string"}
+                },
+                "additionalProperties": false
+            }))
+            .with_result(serde_json::json!({
+                "oneOf": [
+                    {"type": "string", "description": "Prometheus text exposition format"},
+                    {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "required": ["name", "help", "type", "samples"],
+                            "properties": {
+                                "name": {"type": "string"},
+                                "help": {"type": "string"},
+                                "type": {"type": "string"},
+                                "samples": {"type": "array", "items": {"type": "object"}}
+                            }
+                        }
+                    }
+                ]
+            }))
+    }
+}
+
 /// RPC method handler that exposes metrics in Prometheus format
+#[deprecated(note = "register `MetricsGetMethod` instead")]
 pub fn get_metrics_method(
     metrics: Arc<PrometheusMetrics>,
 ) -> impl Fn(Option<serde_json::Value>, Option<crate::RequestId>) -> crate::Response {
@@ -236,6 +748,28 @@ mod tests {
         assert!(text.contains("jsonrpc_errors_total"));
     }
 
+    #[test]
+    fn test_record_principal() {
+        let metrics = PrometheusMetrics::new().unwrap();
+        let principal = crate::auth::Principal::new("u-1")
+            .tenant("acme")
+            .auth_method("jwt");
+        metrics.record_principal(Some(&principal));
+        metrics.record_principal(None);
+
+        let text = metrics.gather_text().unwrap();
+        assert!(
+            text.contains(
+                "jsonrpc_principal_requests_total{auth_method=\"jwt\",tenant=\"acme\"} 1"
+            )
+        );
+        assert!(
+            text.contains(
+                "jsonrpc_principal_requests_total{auth_method=\"none\",tenant=\"none\"} 1"
+            )
+        );
+    }
+
     #[test]
     fn test_connection_tracking() {
         let metrics = PrometheusMetrics::new().unwrap();
@@ -251,6 +785,110 @@ mod tests {
         assert_eq!(metrics.active_connections.get(), 1);
     }
 
+    #[test]
+    fn test_record_rejection() {
+        use crate::rejection::{Rejection, RejectionObserver, RejectionReason};
+
+        let metrics = PrometheusMetrics::new().unwrap();
+        metrics.on_rejection(&Rejection::new(RejectionReason::RequestTooLarge));
+        metrics.record_rejection(RejectionReason::Unauthorized);
+
+        let text = metrics.gather_text().unwrap();
+        assert!(text.contains("jsonrpc_rejections_total{reason=\"request_too_large\"} 1"));
+        assert!(text.contains("jsonrpc_rejections_total{reason=\"unauthorized\"} 1"));
+    }
+
+    #[test]
+    fn test_codec_metrics() {
+        let codec = crate::transports::codec_stats::negotiated("prometheus-test-codec");
+        codec.observe(true);
+        codec.observe(false);
+
+        let text = PrometheusMetrics::new().unwrap().gather_text().unwrap();
+        assert!(
+            text.contains("jsonrpc_codec_connections_total{codec=\"prometheus-test-codec\"} 1")
+        );
+        assert!(
+            text.contains("jsonrpc_codec_decode_errors_total{codec=\"prometheus-test-codec\"} 1")
+        );
+    }
+
+    #[test]
+    fn test_transport_metrics() {
+        let transport = crate::transports::transport_stats::transport("prometheus-test-transport");
+        let _active = transport.accepted();
+        transport.received(42);
+        transport.idle_timeout();
+        let internal =
+            crate::transports::listener::ListenerId::new("prometheus-test-transport", None)
+                .name(Some("internal".into()));
+        let _internal = crate::transports::transport_stats::listener(&internal).accepted();
+
+        let text = PrometheusMetrics::new().unwrap().gather_text().unwrap();
+        for line in [
+            "jsonrpc_transport_connections_accepted_total{listener=\"\",transport=\"prometheus-test-transport\"} 1",
+            "jsonrpc_transport_active_connections{listener=\"\",transport=\"prometheus-test-transport\"} 1",
+            "jsonrpc_transport_bytes_received_total{listener=\"\",transport=\"prometheus-test-transport\"} 42",
+            "jsonrpc_transport_idle_timeouts_total{listener=\"\",transport=\"prometheus-test-transport\"} 1",
+            "jsonrpc_transport_connections_accepted_total{listener=\"internal\",transport=\"prometheus-test-transport\"} 1",
+        ] {
+            assert!(text.contains(line), "missing {line}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_metrics_get_formats() {
+        use crate::JsonRPCMethod;
+
+        let metrics = Arc::new(PrometheusMetrics::new().unwrap());
+        metrics.record_request("ping", Duration::from_millis(10), true);
+        let method = MetricsGetMethod::new(Arc::clone(&metrics));
+
+        let text = method.call(None, Some(serde_json::json!(1))).await;
+        assert!(
+            text.result
+                .unwrap()
+                .as_str()
+                .unwrap()
+                .contains("jsonrpc_requests_total")
+        );
+
+        let json = method
+            .call(
+                Some(serde_json::json!({"format": "json", "prefix": "jsonrpc_request"})),
+                Some(serde_json::json!(2)),
+            )
+            .await;
+        let families: Vec<MetricFamilySnapshot> =
+            serde_json::from_value(json.result.unwrap()).unwrap();
+        assert!(
+            families
+                .iter()
+                .all(|f| f.name.starts_with("jsonrpc_request"))
+        );
+        let requests = families
+            .iter()
+            .find(|f| f.name == "jsonrpc_requests_total")
+            .unwrap();
+        assert_eq!(requests.kind, "counter");
+        assert_eq!(requests.samples[0].value, Some(1.0));
+        assert_eq!(requests.samples[0].labels["method"], "ping");
+        let duration = families
+            .iter()
+            .find(|f| f.name == "jsonrpc_request_duration_seconds")
+            .unwrap();
+        assert_eq!(duration.samples[0].count, Some(1));
+        assert!(!duration.samples[0].buckets.is_empty());
+
+        let invalid = method
+            .call(Some(serde_json::json!({"format": "xml"})), None)
+            .await;
+        assert_eq!(
+            invalid.error.unwrap().code,
+            crate::error_codes::INVALID_PARAMS
+        );
+    }
+
     #[test]
     fn test_custom_prefix() {
         let metrics = PrometheusMetrics::with_prefix("custom").unwrap();
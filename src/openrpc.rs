@@ -0,0 +1,379 @@
+//! OpenRPC documents.
+//!
+//! [`document`] turns an [`OpenApiSpec`] into an
+//! [OpenRPC](https://spec.open-rpc.org) service description, the format
+//! JSON-RPC tooling (playgrounds, client generators) reads. Registries with
+//! the [`DISCOVERY`](crate::builtins::BuiltinMethods::DISCOVERY) built-ins
+//! enabled serve it as `rpc.discover`, next to `rpc.methods` listing the
+//! method names and `rpc.capabilities`.
+//!
+//! Params schemas describing an object become one named param per property,
+//! called by name; any other params schema becomes a single `params` param.
+//! Methods are sorted by name.
+//!
+//! With the `html-docs` feature, [`html`] renders the same document as a
+//! single self-contained HTML page.
+//!
+//! ```rust
+//! use ash_rpc::{OpenApiMethodSpec, OpenApiSpec, openrpc};
+//! use serde_json::json;
+//!
+//! let mut spec = OpenApiSpec::new("Calculator", "1.0.0");
+//! spec.add_method(OpenApiMethodSpec::new("add").with_parameters(json!({
+//!     "type": "object",
+//!     "properties": {"a": {"type": "number"}, "b": {"type": "number"}},
+//!     "required": ["a", "b"]
+//! })));
+//!
+//! let doc = openrpc::document(&spec);
+//! assert_eq!(doc["methods"][0]["params"][1]["name"], "b");
+//! assert_eq!(doc["methods"][0]["paramStructure"], "by-name");
+//! ```
+
+use crate::{OpenApiExample, OpenApiMethodSpec, OpenApiSpec};
+use serde_json::{Map, Value, json};
+
+/// OpenRPC specification version of generated documents
+pub const OPENRPC_VERSION: &str = "1.3.2";
+
+/// `spec` as an OpenRPC document
+pub fn document(spec: &OpenApiSpec) -> Value {
+    let mut methods: Vec<&OpenApiMethodSpec> = spec.methods.values().collect();
+    methods.sort_by(|a, b| a.method_name.cmp(&b.method_name));
+
+    let mut info = Map::new();
+    info.insert("title".into(), json!(spec.info.title));
+    info.insert("version".into(), json!(spec.info.version));
+    if let Some(description) = &spec.info.description {
+        info.insert("description".into(), json!(description));
+    }
+
+    let mut doc = Map::new();
+    doc.insert("openrpc".into(), json!(OPENRPC_VERSION));
+    doc.insert("info".into(), Value::Object(info));
+    if !spec.servers.is_empty() {
+        let servers = spec
+            .servers
+            .iter()
+            .map(|server| {
+                let mut entry = Map::new();
+                entry.insert("name".into(), json!(server.url));
+                entry.insert("url".into(), json!(server.url));
+                if let Some(description) = &server.description {
+                    entry.insert("description".into(), json!(description));
+                }
+                Value::Object(entry)
+            })
+            .collect();
+        doc.insert("servers".into(), Value::Array(servers));
+    }
+    doc.insert(
+        "methods".into(),
+        methods.into_iter().map(method).collect::<Vec<_>>().into(),
+    );
+    if !spec.components.schemas.is_empty() {
+        doc.insert(
+            "components".into(),
+            json!({ "schemas": spec.components.schemas }),
+        );
+    }
+    for (name, value) in &spec.extensions {
+        doc.insert(name.clone(), value.clone());
+    }
+    Value::Object(doc)
+}
+
+fn method(spec: &OpenApiMethodSpec) -> Value {
+    let mut method = Map::new();
+    method.insert("name".into(), json!(spec.method_name));
+    if let Some(summary) = &spec.summary {
+        method.insert("summary".into(), json!(summary));
+    }
+    if let Some(description) = &spec.description {
+        method.insert("description".into(), json!(description));
+    }
+    if !spec.tags.is_empty() {
+        let tags: Vec<Value> = spec.tags.iter().map(|tag| json!({ "name": tag })).collect();
+        method.insert("tags".into(), tags.into());
+    }
+
+    let by_name = properties(spec.parameters.as_ref());
+    let params: Vec<Value> = match (by_name, &spec.parameters) {
+        (Some(properties), Some(schema)) => {
+            let required = schema.get("required").and_then(Value::as_array);
+            properties
+                .iter()
+                .map(|(name, schema)| {
+                    let required = required.is_some_and(|names| names.contains(&json!(name)));
+                    json!({ "name": name, "schema": schema, "required": required })
+                })
+                .collect()
+        }
+        (None, Some(schema)) => vec![json!({ "name": "params", "schema": schema })],
+        (_, None) => Vec::new(),
+    };
+    method.insert("params".into(), params.into());
+    if by_name.is_some() {
+        method.insert("paramStructure".into(), json!("by-name"));
+    }
+    method.insert(
+        "result".into(),
+        json!({ "name": "result", "schema": spec.result.clone().unwrap_or_else(|| json!({})) }),
+    );
+
+    if !spec.errors.is_empty() {
+        let errors: Vec<Value> = spec
+            .errors
+            .iter()
+            .map(|error| json!({ "code": error.code, "message": error.message }))
+            .collect();
+        method.insert("errors".into(), errors.into());
+    }
+    if !spec.examples.is_empty() {
+        let examples: Vec<Value> = spec
+            .examples
+            .iter()
+            .map(|example| pairing(example, by_name.is_some()))
+            .collect();
+        method.insert("examples".into(), examples.into());
+    }
+    if spec.deprecated.is_some() {
+        method.insert("deprecated".into(), json!(true));
+    }
+    for (name, value) in &spec.extensions {
+        method.insert(name.clone(), value.clone());
+    }
+    Value::Object(method)
+}
+
+/// Properties of an object params schema
+fn properties(schema: Option<&Value>) -> Option<&Map<String, Value>> {
+    schema?.get("properties")?.as_object()
+}
+
+/// An example as an OpenRPC example pairing
+fn pairing(example: &OpenApiExample, by_name: bool) -> Value {
+    let params: Vec<Value> = match &example.params {
+        Some(Value::Object(values)) if by_name => values
+            .iter()
+            .map(|(name, value)| json!({ "name": name, "value": value }))
+            .collect(),
+        Some(value) => vec![json!({ "name": "params", "value": value })],
+        None => Vec::new(),
+    };
+    let mut pairing = Map::new();
+    pairing.insert("name".into(), json!(example.name));
+    if let Some(summary) = &example.summary {
+        pairing.insert("summary".into(), json!(summary));
+    }
+    if let Some(description) = &example.description {
+        pairing.insert("description".into(), json!(description));
+    }
+    pairing.insert("params".into(), params.into());
+    if let Some(result) = &example.result {
+        pairing.insert(
+            "result".into(),
+            json!({ "name": "result", "value": result }),
+        );
+    }
+    Value::Object(pairing)
+}
+
+/// `spec` as a standalone HTML page listing every method with its params,
+/// result, errors and examples
+#[cfg(feature = "html-docs")]
+pub fn html(spec: &OpenApiSpec) -> String {
+    use std::fmt::Write;
+
+    let doc = document(spec);
+    let title = escape(&spec.info.title);
+    let mut page = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+         <h1>{title} <small>{}</small></h1>\n",
+        escape(&spec.info.version)
+    );
+    if let Some(description) = &spec.info.description {
+        let _ = writeln!(page, "<p>{}</p>", escape(description));
+    }
+    for server in &spec.servers {
+        let _ = writeln!(page, "<p>Server: <code>{}</code></p>", escape(&server.url));
+    }
+
+    let methods = doc["methods"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    page.push_str("<nav><ul>\n");
+    for method in methods {
+        let name = escape(method["name"].as_str().unwrap_or_default());
+        let _ = writeln!(page, "<li><a href=\"#{name}\">{name}</a></li>");
+    }
+    page.push_str("</ul></nav>\n");
+
+    for method in methods {
+        let name = escape(method["name"].as_str().unwrap_or_default());
+        let _ = write!(page, "<section id=\"{name}\">\n<h2>{name}");
+        if method.get("deprecated").is_some() {
+            page.push_str(" <small>deprecated</small>");
+        }
+        page.push_str("</h2>\n");
+        for key in ["summary", "description"] {
+            if let Some(text) = method[key].as_str() {
+                let _ = writeln!(page, "<p>{}</p>", escape(text));
+            }
+        }
+        if let Some(params) = method["params"].as_array().filter(|p| !p.is_empty()) {
+            page.push_str("<h3>Params</h3>\n<table>\n");
+            for param in params {
+                let required = if param["required"] == true {
+                    " <small>required</small>"
+                } else {
+                    ""
+                };
+                let _ = writeln!(
+                    page,
+                    "<tr><td><code>{}</code>{required}</td><td>{}</td></tr>",
+                    escape(param["name"].as_str().unwrap_or_default()),
+                    pretty(&param["schema"])
+                );
+            }
+            page.push_str("</table>\n");
+        }
+        let _ = writeln!(
+            page,
+            "<h3>Result</h3>\n{}",
+            pretty(&method["result"]["schema"])
+        );
+        if let Some(errors) = method["errors"].as_array() {
+            page.push_str("<h3>Errors</h3>\n<ul>\n");
+            for error in errors {
+                let _ = writeln!(
+                    page,
+                    "<li><code>{}</code> {}</li>",
+                    error["code"],
+                    escape(error["message"].as_str().unwrap_or_default())
+                );
+            }
+            page.push_str("</ul>\n");
+        }
+        if let Some(examples) = method.get("examples") {
+            let _ = writeln!(page, "<h3>Examples</h3>\n{}", pretty(examples));
+        }
+        page.push_str("</section>\n");
+    }
+    page.push_str("</body>\n</html>\n");
+    page
+}
+
+#[cfg(feature = "html-docs")]
+const STYLE: &str = "body{font-family:sans-serif;max-width:60em;margin:auto;padding:1em}\
+    section{border-top:1px solid #ccc}td{vertical-align:top;padding:.2em .5em}\
+    pre{background:#f4f4f4;padding:.5em;overflow:auto;margin:0}small{color:#666}";
+
+/// `value` pretty-printed in a `<pre>` block
+#[cfg(feature = "html-docs")]
+fn pretty(value: &Value) -> String {
+    let text = serde_json::to_string_pretty(value).unwrap_or_default();
+    format!("<pre>{}</pre>", escape(&text))
+}
+
+#[cfg(feature = "html-docs")]
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OpenApiError, OpenApiServer};
+
+    #[test]
+    fn test_document_shape() {
+        let mut spec = OpenApiSpec::new("Notes", "2.1.0").with_description("Note storage");
+        spec.add_server(OpenApiServer::new("tcp://127.0.0.1:9000"));
+        spec.add_method(
+            OpenApiMethodSpec::new("notes.get")
+                .with_summary("Fetch a note")
+                .with_tag("notes")
+                .with_parameters(json!({
+                    "type": "object",
+                    "properties": {"id": {"type": "integer"}, "rev": {"type": "integer"}},
+                    "required": ["id"]
+                }))
+                .with_error(OpenApiError::new(-32001, "Not found"))
+                .with_example(
+                    OpenApiExample::new("first")
+                        .with_params(json!({"id": 1}))
+                        .with_result(json!("hello")),
+                ),
+        );
+        spec.add_method(OpenApiMethodSpec::new("echo").with_parameters(json!({"type": "array"})));
+
+        let doc = document(&spec);
+        assert_eq!(doc["openrpc"], OPENRPC_VERSION);
+        assert_eq!(doc["info"]["description"], "Note storage");
+        assert_eq!(doc["servers"][0]["url"], "tcp://127.0.0.1:9000");
+
+        let [echo, get] = doc["methods"].as_array().unwrap().as_slice() else {
+            panic!("expected two methods");
+        };
+        assert_eq!(
+            echo["params"],
+            json!([{"name": "params", "schema": {"type": "array"}}])
+        );
+        assert!(echo.get("paramStructure").is_none());
+        assert_eq!(echo["result"], json!({"name": "result", "schema": {}}));
+
+        assert_eq!(get["tags"], json!([{"name": "notes"}]));
+        assert_eq!(get["paramStructure"], "by-name");
+        assert_eq!(get["params"][0]["name"], "id");
+        assert_eq!(get["params"][0]["required"], true);
+        assert_eq!(get["params"][1]["required"], false);
+        assert_eq!(
+            get["errors"],
+            json!([{"code": -32001, "message": "Not found"}])
+        );
+        assert_eq!(
+            get["examples"][0]["params"],
+            json!([{"name": "id", "value": 1}])
+        );
+        assert_eq!(get["examples"][0]["result"]["value"], "hello");
+    }
+
+    #[cfg(feature = "html-docs")]
+    #[test]
+    fn test_html_page() {
+        let mut spec = OpenApiSpec::new("Notes <beta>", "2.1.0");
+        spec.add_method(
+            OpenApiMethodSpec::new("notes.get")
+                .with_summary("Fetch a note")
+                .with_parameters(json!({
+                    "type": "object",
+                    "properties": {"id": {"type": "integer"}},
+                    "required": ["id"]
+                }))
+                .with_error(OpenApiError::new(-32001, "Not found")),
+        );
+
+        let page = html(&spec);
+        assert!(page.starts_with("<!DOCTYPE html>"));
+        assert!(page.contains("<title>Notes &lt;beta&gt;</title>"));
+        assert!(page.contains("<a href=\"#notes.get\">notes.get</a>"));
+        assert!(page.contains("<p>Fetch a note</p>"));
+        assert!(page.contains("<code>id</code> <small>required</small>"));
+        assert!(page.contains("&quot;integer&quot;"));
+        assert!(page.contains("<li><code>-32001</code> Not found</li>"));
+    }
+}
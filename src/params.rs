@@ -0,0 +1,401 @@
+//! Declared params of typed methods.
+//!
+//! A [`ParamsSpec`] lists a method's named params, which of them are
+//! required, the defaults of optional ones and whether `null` is accepted.
+//! [`TypedJsonRPCMethod`](crate::TypedJsonRPCMethod)s returning one from
+//! [`params_spec`](crate::TypedJsonRPCMethod::params_spec) get their params
+//! checked and completed by it before decoding, and documented by its
+//! [`schema`](ParamsSpec::schema) unless they describe their params
+//! themselves:
+//!
+//! - a missing required param, or a `null` one that is not nullable, is
+//!   answered with `INVALID_PARAMS`
+//! - a missing param with a default decodes as if the default was sent
+//! - by-position params are matched to the params in declaration order
+//!
+//! A missing param without a default stays missing, so serde's own
+//! defaults apply. Decoding it as [`Nullable`] tells a missing param apart
+//! from an explicit `null`, e.g. to leave a field alone versus clearing it.
+//!
+//! ```rust
+//! use ash_rpc::params::{Nullable, ParamSpec, ParamsSpec};
+//! use serde_json::json;
+//!
+//! #[derive(serde::Deserialize)]
+//! struct UpdateParams {
+//!     id: u64,
+//!     limit: u32,
+//!     #[serde(default)]
+//!     note: Nullable<String>,
+//! }
+//!
+//! let spec = ParamsSpec::new()
+//!     .param(ParamSpec::required("id").schema(json!({"type": "integer"})))
+//!     .param(ParamSpec::optional("limit").default(json!(10)))
+//!     .param(ParamSpec::optional("note").nullable());
+//!
+//! let params = spec.apply(Some(json!({"id": 1, "note": null}))).unwrap();
+//! let params: UpdateParams = serde_json::from_value(params).unwrap();
+//! assert_eq!((params.limit, params.note), (10, Nullable::Null));
+//!
+//! assert!(spec.apply(Some(json!({"limit": 5}))).is_err());
+//! ```
+
+use crate::{Error, error_codes};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value, json};
+
+/// One named param
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamSpec {
+    name: String,
+    required: bool,
+    default: Option<Value>,
+    nullable: bool,
+    schema: Option<Value>,
+}
+
+impl ParamSpec {
+    /// A param every call must send
+    pub fn required(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            required: true,
+            default: None,
+            nullable: false,
+            schema: None,
+        }
+    }
+
+    /// A param calls may leave out
+    pub fn optional(name: impl Into<String>) -> Self {
+        Self {
+            required: false,
+            ..Self::required(name)
+        }
+    }
+
+    /// Value used when the param is left out; makes the param optional
+    pub fn default(mut self, value: Value) -> Self {
+        self.required = false;
+        self.default = Some(value);
+        self
+    }
+
+    /// Accept an explicit `null`
+    pub fn nullable(mut self) -> Self {
+        self.nullable = true;
+        self
+    }
+
+    /// JSON Schema of the param's value, `{}` when not set
+    pub fn schema(mut self, schema: Value) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+
+    pub fn is_nullable(&self) -> bool {
+        self.nullable
+    }
+
+    pub fn default_value(&self) -> Option<&Value> {
+        self.default.as_ref()
+    }
+
+    /// Schema of the param as documented: its value schema, widened to
+    /// `null` when nullable, with the default
+    fn documented(&self) -> Value {
+        let mut schema = self.schema.clone().unwrap_or_else(|| json!({}));
+        if self.nullable {
+            schema = match schema.get("type").cloned() {
+                Some(Value::String(ty)) => {
+                    schema["type"] = json!([ty, "null"]);
+                    schema
+                }
+                Some(Value::Array(mut types)) => {
+                    if !types.contains(&json!("null")) {
+                        types.push(json!("null"));
+                    }
+                    schema["type"] = Value::Array(types);
+                    schema
+                }
+                // an empty schema already allows null
+                _ if schema.as_object().is_some_and(Map::is_empty) => schema,
+                _ => json!({ "anyOf": [schema, {"type": "null"}] }),
+            };
+        }
+        if let (Some(default), Value::Object(object)) = (&self.default, &mut schema) {
+            object.insert("default".into(), default.clone());
+        }
+        schema
+    }
+}
+
+/// Named params of a method, in declaration order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParamsSpec {
+    params: Vec<ParamSpec>,
+}
+
+impl ParamsSpec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn param(mut self, param: ParamSpec) -> Self {
+        self.params.push(param);
+        self
+    }
+
+    pub fn params(&self) -> &[ParamSpec] {
+        &self.params
+    }
+
+    /// Check `params` and fill in defaults, returning them as an object
+    ///
+    /// Absent params count as an empty object. Params not declared are
+    /// passed through for the decoder to accept or reject.
+    pub fn apply(&self, params: Option<Value>) -> Result<Value, Error> {
+        let mut values = match params {
+            None | Some(Value::Null) => Map::new(),
+            Some(Value::Object(values)) => values,
+            Some(Value::Array(values)) if values.len() <= self.params.len() => self
+                .params
+                .iter()
+                .map(|param| param.name.clone())
+                .zip(values)
+                .collect(),
+            Some(Value::Array(values)) => {
+                return Err(invalid(format!(
+                    "expected at most {} params, got {}",
+                    self.params.len(),
+                    values.len()
+                )));
+            }
+            Some(_) => return Err(invalid("expected an array or an object".to_string())),
+        };
+
+        for param in &self.params {
+            match values.get(&param.name) {
+                Some(Value::Null) if !param.nullable => {
+                    return Err(invalid(format!("`{}` must not be null", param.name)));
+                }
+                Some(_) => {}
+                None if param.required => {
+                    return Err(invalid(format!("missing `{}`", param.name)));
+                }
+                None => {
+                    if let Some(default) = &param.default {
+                        values.insert(param.name.clone(), default.clone());
+                    }
+                }
+            }
+        }
+        Ok(Value::Object(values))
+    }
+
+    /// JSON Schema of the params object
+    pub fn schema(&self) -> Value {
+        let properties: Map<String, Value> = self
+            .params
+            .iter()
+            .map(|param| (param.name.clone(), param.documented()))
+            .collect();
+        let required: Vec<&str> = self
+            .params
+            .iter()
+            .filter(|param| param.required)
+            .map(|param| param.name.as_str())
+            .collect();
+        json!({ "type": "object", "properties": properties, "required": required })
+    }
+}
+
+fn invalid(message: String) -> Error {
+    Error::new(
+        error_codes::INVALID_PARAMS,
+        format!("Invalid params: {message}"),
+    )
+}
+
+/// A param that may be missing, `null` or set
+///
+/// Decodes `null` as [`Null`](Self::Null) and anything else as
+/// [`Value`](Self::Value); fields need `#[serde(default)]` to decode as
+/// [`Missing`](Self::Missing) when left out. Encodes `Missing` and `Null`
+/// as `null`, so fields that should disappear when missing also need
+/// `#[serde(skip_serializing_if = "Nullable::is_missing")]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Nullable<T> {
+    #[default]
+    Missing,
+    Null,
+    Value(T),
+}
+
+impl<T> Nullable<T> {
+    pub fn is_missing(&self) -> bool {
+        matches!(self, Self::Missing)
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    /// The value, `None` when missing or `null`
+    pub fn value(self) -> Option<T> {
+        match self {
+            Self::Value(value) => Some(value),
+            Self::Missing | Self::Null => None,
+        }
+    }
+
+    pub fn as_ref(&self) -> Nullable<&T> {
+        match self {
+            Self::Missing => Nullable::Missing,
+            Self::Null => Nullable::Null,
+            Self::Value(value) => Nullable::Value(value),
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Nullable<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match Option::<T>::deserialize(deserializer)? {
+            Some(value) => Self::Value(value),
+            None => Self::Null,
+        })
+    }
+}
+
+impl<T: Serialize> Serialize for Nullable<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Value(value) => value.serialize(serializer),
+            Self::Missing | Self::Null => serializer.serialize_none(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CallContext, JsonRPCMethod, TypedJsonRPCMethod};
+
+    fn spec() -> ParamsSpec {
+        ParamsSpec::new()
+            .param(ParamSpec::required("id").schema(json!({"type": "integer"})))
+            .param(
+                ParamSpec::optional("limit")
+                    .schema(json!({"type": "integer"}))
+                    .default(json!(10)),
+            )
+            .param(
+                ParamSpec::optional("note")
+                    .schema(json!({"type": "string"}))
+                    .nullable(),
+            )
+    }
+
+    #[test]
+    fn test_apply_and_schema() {
+        let spec = spec();
+        assert_eq!(
+            spec.apply(Some(json!([1, 5]))).unwrap(),
+            json!({"id": 1, "limit": 5})
+        );
+        assert_eq!(
+            spec.apply(Some(json!({"id": 1, "note": null, "extra": true})))
+                .unwrap(),
+            json!({"id": 1, "limit": 10, "note": null, "extra": true})
+        );
+        for params in [
+            None,
+            Some(json!({"id": null})),
+            Some(json!({"id": 1, "limit": null})),
+            Some(json!([1, 2, 3, 4])),
+            Some(json!("1")),
+        ] {
+            let error = spec.apply(params).unwrap_err();
+            assert_eq!(error.code, error_codes::INVALID_PARAMS);
+        }
+
+        assert_eq!(
+            spec.schema(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "id": {"type": "integer"},
+                    "limit": {"type": "integer", "default": 10},
+                    "note": {"type": ["string", "null"]}
+                },
+                "required": ["id"]
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_typed_method_with_spec() {
+        #[derive(Deserialize)]
+        struct Params {
+            id: u64,
+            limit: u32,
+            #[serde(default)]
+            note: Nullable<String>,
+        }
+
+        struct Update;
+
+        #[async_trait::async_trait]
+        impl TypedJsonRPCMethod for Update {
+            type Params = Params;
+            type Output = Value;
+
+            fn method_name(&self) -> &'static str {
+                "update"
+            }
+
+            fn params_spec(&self) -> Option<ParamsSpec> {
+                Some(spec())
+            }
+
+            async fn call(&self, params: Params) -> Result<Value, Error> {
+                let note = match params.note {
+                    Nullable::Missing => "kept",
+                    Nullable::Null => "cleared",
+                    Nullable::Value(_) => "set",
+                };
+                Ok(json!([params.id, params.limit, note]))
+            }
+        }
+
+        let connection = crate::auth::ConnectionContext::default();
+        let ctx = CallContext::new(&connection);
+        let call = |params| JsonRPCMethod::call_with_context(&Update, params, None, &ctx);
+        assert_eq!(
+            call(Some(json!({"id": 1}))).await.result,
+            Some(json!([1, 10, "kept"]))
+        );
+        assert_eq!(
+            call(Some(json!({"id": 1, "note": null}))).await.result,
+            Some(json!([1, 10, "cleared"]))
+        );
+        assert_eq!(
+            call(Some(json!([2, 3, "x"]))).await.result,
+            Some(json!([2, 3, "set"]))
+        );
+        assert!(call(None).await.error.is_some());
+        assert_eq!(
+            JsonRPCMethod::openapi_components(&Update).parameters,
+            Some(spec().schema())
+        );
+    }
+}
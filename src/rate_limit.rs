@@ -0,0 +1,808 @@
+//! Per-method and per-client rate limiting.
+//!
+//! A [`RateLimitPolicy`] is a list of rules, each pairing a [`Limit`] with
+//! the methods it covers and whether it counts calls per client IP, per
+//! [`Principal`](crate::auth::Principal) or for all callers together. Every
+//! rule matching a call must admit it, and a refused call counts against
+//! none of them. Class rules cover the methods declaring that
+//! [rate class](crate::method_security::MethodSecurity::rate_class), which
+//! only a registry knows about. Attach the policy to a registry with
+//! [`MethodRegistry::with_rate_limit`](crate::MethodRegistry::with_rate_limit),
+//! or put any processor behind it with [`RateLimitedProcessor`].
+//!
+//! Refused calls are answered with [`error_codes::RETRY_LATER`] (see
+//! [`RateLimitPolicy::error_code`]) carrying `retry_after` in seconds, and
+//! reported as a `rate_limited` rejection, which the audit rejection observer
+//! logs as a `SecurityViolation`.
+//!
+//! Each rule remembers at most 10,000 callers. Callers are forgotten once
+//! their state is back to a fresh one, and when the table is full the least
+//! recently seen caller makes room for a new one.
+//!
+//! ```rust
+//! use ash_rpc::rate_limit::{Limit, RateLimitPolicy};
+//! use ash_rpc::MethodRegistry;
+//! use std::time::Duration;
+//!
+//! let policy = RateLimitPolicy::new()
+//!     .per_ip(Limit::token_bucket(20, 40))
+//!     .method("reports.*", Limit::sliding_window(100, Duration::from_secs(60)))
+//!     .method_per_ip("auth.login", Limit::sliding_window(5, Duration::from_secs(60)));
+//!
+//! let registry = MethodRegistry::empty().with_rate_limit(policy);
+//! # let _ = registry;
+//! ```
+
+use crate::auth::ConnectionContext;
+use crate::traits::{CapabilityLimits, MessageProcessor, ProcessorCapabilities};
+use crate::transports::MethodFilter;
+use crate::{ErrorBuilder, Message, Request, RequestId, Response, ResponseBuilder, error_codes};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Client states kept per rule; beyond it the least recently seen is evicted
+const MAX_CALLERS: usize = 10_000;
+
+/// Least recently seen idle callers dropped per call
+const IDLE_SWEEP: usize = 2;
+
+/// How calls are counted against a rule
+#[derive(Debug, Clone, PartialEq)]
+pub enum Limit {
+    /// Sustained `rate_per_sec` with up to `burst` calls at once
+    TokenBucket { rate_per_sec: f64, burst: u32 },
+    /// At most `max_calls` within any `window`
+    SlidingWindow { max_calls: u32, window: Duration },
+}
+
+impl Limit {
+    pub fn token_bucket(rate_per_sec: u32, burst: u32) -> Self {
+        Limit::TokenBucket {
+            rate_per_sec: f64::from(rate_per_sec.max(1)),
+            burst: burst.max(1),
+        }
+    }
+
+    pub fn sliding_window(max_calls: u32, window: Duration) -> Self {
+        Limit::SlidingWindow {
+            max_calls: max_calls.max(1),
+            window,
+        }
+    }
+}
+
+/// Counting state of one rule for one key
+#[derive(Debug)]
+enum State {
+    Bucket { tokens: f64, updated: Instant },
+    Window(VecDeque<Instant>),
+}
+
+impl State {
+    fn new(limit: &Limit, now: Instant) -> Self {
+        match limit {
+            Limit::TokenBucket { burst, .. } => State::Bucket {
+                tokens: f64::from(*burst),
+                updated: now,
+            },
+            Limit::SlidingWindow { .. } => State::Window(VecDeque::new()),
+        }
+    }
+
+    /// Catch up to `now` and check a call could be taken, without taking it
+    fn available(&mut self, limit: &Limit, now: Instant) -> Result<(), Duration> {
+        match (self, limit) {
+            (
+                State::Bucket { tokens, updated },
+                Limit::TokenBucket {
+                    rate_per_sec,
+                    burst,
+                },
+            ) => {
+                let elapsed = now.duration_since(*updated).as_secs_f64();
+                *tokens = (*tokens + elapsed * rate_per_sec).min(f64::from(*burst));
+                *updated = now;
+                if *tokens >= 1.0 {
+                    Ok(())
+                } else {
+                    Err(Duration::from_secs_f64((1.0 - *tokens) / rate_per_sec))
+                }
+            }
+            (State::Window(calls), Limit::SlidingWindow { max_calls, window }) => {
+                while calls
+                    .front()
+                    .is_some_and(|call| now.duration_since(*call) >= *window)
+                {
+                    calls.pop_front();
+                }
+                if calls.len() < *max_calls as usize {
+                    Ok(())
+                } else {
+                    let oldest = calls.front().copied().unwrap_or(now);
+                    Err(window.saturating_sub(now.duration_since(oldest)))
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Take a call [`available`](Self::available) was checked for
+    fn take(&mut self, now: Instant) {
+        match self {
+            State::Bucket { tokens, .. } => *tokens -= 1.0,
+            State::Window(calls) => calls.push_back(now),
+        }
+    }
+
+    /// Whether the state is back to where a new one would start
+    fn is_idle(&self, limit: &Limit, now: Instant) -> bool {
+        match (self, limit) {
+            (
+                State::Bucket { tokens, updated },
+                Limit::TokenBucket {
+                    rate_per_sec,
+                    burst,
+                },
+            ) => {
+                tokens + now.duration_since(*updated).as_secs_f64() * rate_per_sec
+                    >= f64::from(*burst)
+            }
+            (State::Window(calls), Limit::SlidingWindow { window, .. }) => calls
+                .back()
+                .is_none_or(|call| now.duration_since(*call) >= *window),
+            _ => true,
+        }
+    }
+}
+
+/// Who a rule counts calls for
+#[derive(Clone, Copy, PartialEq)]
+enum Per {
+    All,
+    Ip,
+    Principal,
+}
+
+/// State key of a caller under a rule
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Caller {
+    All,
+    Ip(IpAddr),
+    Principal(String),
+}
+
+struct Tracked {
+    state: State,
+    /// Key of the caller in `recency`
+    seen: u64,
+}
+
+/// Caller states of one rule, bounded to [`MAX_CALLERS`]
+///
+/// Every call drops the least recently seen callers that went idle, so the
+/// table shrinks as callers leave without being scanned. A new caller
+/// arriving at a full table evicts the least recently seen one, which starts
+/// over with a fresh state when it comes back.
+#[derive(Default)]
+struct Callers {
+    states: HashMap<Caller, Tracked>,
+    /// Last call of each caller, oldest first
+    recency: BTreeMap<u64, Caller>,
+    next_tick: u64,
+}
+
+impl Callers {
+    fn tick(&mut self) -> u64 {
+        let tick = self.next_tick;
+        self.next_tick += 1;
+        tick
+    }
+
+    fn evict_oldest(&mut self) {
+        if let Some((_, oldest)) = self.recency.pop_first() {
+            self.states.remove(&oldest);
+        }
+    }
+
+    fn sweep(&mut self, limit: &Limit, now: Instant) {
+        for _ in 0..IDLE_SWEEP {
+            let idle = self.recency.first_key_value().is_some_and(|(_, oldest)| {
+                self.states
+                    .get(oldest)
+                    .is_none_or(|tracked| tracked.state.is_idle(limit, now))
+            });
+            if !idle {
+                break;
+            }
+            self.evict_oldest();
+        }
+    }
+
+    /// State of `caller`, marked as just seen
+    fn seen(&mut self, caller: &Caller, limit: &Limit, now: Instant) -> &mut State {
+        self.sweep(limit, now);
+        if !self.states.contains_key(caller) && self.states.len() >= MAX_CALLERS {
+            self.evict_oldest();
+        }
+        let tick = self.tick();
+        let tracked = self
+            .states
+            .entry(caller.clone())
+            .or_insert_with(|| Tracked {
+                state: State::new(limit, now),
+                seen: tick,
+            });
+        self.recency.remove(&tracked.seen);
+        tracked.seen = tick;
+        self.recency.insert(tick, caller.clone());
+        &mut tracked.state
+    }
+
+    fn get_mut(&mut self, caller: &Caller) -> Option<&mut State> {
+        self.states
+            .get_mut(caller)
+            .map(|tracked| &mut tracked.state)
+    }
+}
+
+/// Calls a rule counts
+enum Covers {
+    All,
+    Methods(MethodFilter),
+    Class(String),
+}
+
+struct Rule {
+    covers: Covers,
+    label: String,
+    per: Per,
+    limit: Limit,
+    states: Mutex<Callers>,
+}
+
+impl Rule {
+    fn covers(&self, method: &str, class: Option<&str>) -> bool {
+        match &self.covers {
+            Covers::All => true,
+            Covers::Methods(filter) => filter.is_permitted(method),
+            Covers::Class(name) => class == Some(name.as_str()),
+        }
+    }
+}
+
+/// A call refused by a [`RateLimitPolicy`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitExceeded {
+    /// The refusing rule, e.g. `per-ip` or `method reports.*`
+    pub rule: String,
+    /// When the rule will admit the caller again
+    pub retry_after: Duration,
+}
+
+/// Rate limiting rules checked before dispatch
+pub struct RateLimitPolicy {
+    rules: Vec<Rule>,
+    error_code: i32,
+}
+
+impl Default for RateLimitPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RateLimitPolicy {
+    /// No rules, refusing with `RETRY_LATER`
+    pub fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            error_code: error_codes::RETRY_LATER,
+        }
+    }
+
+    fn rule(self, pattern: Option<String>, per: Per, limit: Limit) -> Self {
+        let label = match (&pattern, per) {
+            (None, Per::Ip) => "per-ip".to_string(),
+            (None, Per::Principal) => "per-principal".to_string(),
+            (None, Per::All) => "global".to_string(),
+            (Some(pattern), Per::Ip) => format!("method-per-ip {pattern}"),
+            (Some(pattern), Per::Principal) => format!("method-per-principal {pattern}"),
+            (Some(pattern), Per::All) => format!("method {pattern}"),
+        };
+        let covers = match pattern {
+            Some(pattern) => Covers::Methods(MethodFilter::new().allow(pattern)),
+            None => Covers::All,
+        };
+        self.push(covers, label, per, limit)
+    }
+
+    fn class_rule(self, class: String, per: Per, limit: Limit) -> Self {
+        let label = match per {
+            Per::Ip => format!("class-per-ip {class}"),
+            Per::Principal => format!("class-per-principal {class}"),
+            Per::All => format!("class {class}"),
+        };
+        self.push(Covers::Class(class), label, per, limit)
+    }
+
+    /// Whether any method or global rule counts calls to `method`
+    pub(crate) fn applies_to(&self, method: &str) -> bool {
+        self.rules.iter().any(|rule| rule.covers(method, None))
+    }
+
+    fn push(mut self, covers: Covers, label: String, per: Per, limit: Limit) -> Self {
+        self.rules.push(Rule {
+            covers,
+            label,
+            per,
+            limit,
+            states: Mutex::new(Callers::default()),
+        });
+        self
+    }
+
+    /// Limit all calls of all callers together
+    pub fn global(self, limit: Limit) -> Self {
+        self.rule(None, Per::All, limit)
+    }
+
+    /// Limit each client IP across all methods
+    pub fn per_ip(self, limit: Limit) -> Self {
+        self.rule(None, Per::Ip, limit)
+    }
+
+    /// Limit each principal across all methods
+    pub fn per_principal(self, limit: Limit) -> Self {
+        self.rule(None, Per::Principal, limit)
+    }
+
+    /// Limit methods matching `pattern` (exact or `prefix.*`) for all callers together
+    pub fn method(self, pattern: impl Into<String>, limit: Limit) -> Self {
+        self.rule(Some(pattern.into()), Per::All, limit)
+    }
+
+    /// Limit methods matching `pattern` for each client IP
+    pub fn method_per_ip(self, pattern: impl Into<String>, limit: Limit) -> Self {
+        self.rule(Some(pattern.into()), Per::Ip, limit)
+    }
+
+    /// Limit methods matching `pattern` for each principal
+    pub fn method_per_principal(self, pattern: impl Into<String>, limit: Limit) -> Self {
+        self.rule(Some(pattern.into()), Per::Principal, limit)
+    }
+
+    /// Limit methods of rate class `class` for all callers together
+    pub fn class(self, class: impl Into<String>, limit: Limit) -> Self {
+        self.class_rule(class.into(), Per::All, limit)
+    }
+
+    /// Limit methods of rate class `class` for each client IP
+    pub fn class_per_ip(self, class: impl Into<String>, limit: Limit) -> Self {
+        self.class_rule(class.into(), Per::Ip, limit)
+    }
+
+    /// Limit methods of rate class `class` for each principal
+    pub fn class_per_principal(self, class: impl Into<String>, limit: Limit) -> Self {
+        self.class_rule(class.into(), Per::Principal, limit)
+    }
+
+    /// Error code of refused calls
+    pub fn error_code(mut self, code: i32) -> Self {
+        self.error_code = code;
+        self
+    }
+
+    /// Count a call of `method`, refusing it if any covering rule is exhausted
+    ///
+    /// Per-IP rules do not apply to callers without a remote address, nor
+    /// per-principal rules to callers without a principal id. Refusals are
+    /// recorded as `rate_limited` rejections.
+    pub fn check(&self, method: &str, ctx: &ConnectionContext) -> Result<(), RateLimitExceeded> {
+        self.check_class(method, None, ctx)
+    }
+
+    /// [`check`](Self::check) a call of `method` declaring rate class `class`
+    pub fn check_class(
+        &self,
+        method: &str,
+        class: Option<&str>,
+        ctx: &ConnectionContext,
+    ) -> Result<(), RateLimitExceeded> {
+        let now = Instant::now();
+        let ip = ctx.remote_addr.map(|addr| addr.ip());
+        let applying: Vec<(&Rule, Caller)> = self
+            .rules
+            .iter()
+            .filter(|rule| rule.covers(method, class))
+            .filter_map(|rule| {
+                let key = match (rule.per, ip, ctx.principal_id()) {
+                    (Per::All, ..) => Caller::All,
+                    (Per::Ip, Some(ip), _) => Caller::Ip(ip),
+                    (Per::Principal, _, Some(id)) => Caller::Principal(id.to_string()),
+                    _ => return None,
+                };
+                Some((rule, key))
+            })
+            .collect();
+
+        // every rule is held until the call is taken or refused, so a call
+        // refused by one rule is not counted by the others
+        let mut held = Vec::with_capacity(applying.len());
+        for (rule, key) in &applying {
+            let mut states = rule.states.lock().unwrap_or_else(|e| e.into_inner());
+            let available = states
+                .seen(key, &rule.limit, now)
+                .available(&rule.limit, now);
+            if let Err(retry_after) = available {
+                crate::rejection::record(
+                    crate::rejection::Rejection::new(
+                        crate::rejection::RejectionReason::RateLimited,
+                    )
+                    .method(method)
+                    .remote_addr(ctx.remote_addr)
+                    .origin(ctx.origin.clone())
+                    .principal(ctx.principal_id())
+                    .detail(format!("{} limit exceeded", rule.label)),
+                );
+                return Err(RateLimitExceeded {
+                    rule: rule.label.clone(),
+                    retry_after,
+                });
+            }
+            held.push(states);
+        }
+        for ((_, key), states) in applying.iter().zip(&mut held) {
+            if let Some(state) = states.get_mut(key) {
+                state.take(now);
+            }
+        }
+        Ok(())
+    }
+
+    /// The error response for a refused call
+    pub fn refusal(&self, exceeded: &RateLimitExceeded, id: Option<RequestId>) -> Response {
+        ResponseBuilder::new()
+            .error(
+                ErrorBuilder::new(self.error_code, "Rate limit exceeded")
+                    .data(serde_json::json!({
+                        "retry_after": exceeded.retry_after.as_secs_f64().ceil() as u64,
+                    }))
+                    .build(),
+            )
+            .id(id)
+            .build()
+    }
+}
+
+/// Processor wrapper refusing calls beyond a [`RateLimitPolicy`]
+pub struct RateLimitedProcessor {
+    inner: Arc<dyn MessageProcessor + Send + Sync>,
+    policy: RateLimitPolicy,
+    limits: CapabilityLimits,
+}
+
+impl RateLimitedProcessor {
+    pub fn new(inner: Arc<dyn MessageProcessor + Send + Sync>, policy: RateLimitPolicy) -> Self {
+        Self {
+            inner,
+            policy,
+            limits: CapabilityLimits::new(),
+        }
+    }
+
+    /// Narrow the capabilities reported for the wrapped processor
+    ///
+    /// Useful to cap batch sizes so a single batch cannot use up a caller's
+    /// budget at once.
+    pub fn capability_limits(mut self, limits: CapabilityLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// The context passed to the inner processor, carrying this wrapper's
+    /// capability limits
+    fn forwarded<'a>(&self, ctx: &'a ConnectionContext) -> Cow<'a, ConnectionContext> {
+        if self.limits.is_unrestricted() {
+            Cow::Borrowed(ctx)
+        } else {
+            Cow::Owned(ctx.clone().with_capability_limits(self.limits.clone()))
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl MessageProcessor for RateLimitedProcessor {
+    async fn process_message(&self, message: Message) -> Option<Response> {
+        self.process_message_with_context(message, &ConnectionContext::default())
+            .await
+    }
+
+    async fn process_message_with_context(
+        &self,
+        message: Message,
+        ctx: &ConnectionContext,
+    ) -> Option<Response> {
+        if let Some(method) = message.method()
+            && let Err(exceeded) = self.policy.check(method, ctx)
+        {
+            return match message {
+                Message::Request(request) => Some(self.policy.refusal(&exceeded, request.id)),
+                _ => None,
+            };
+        }
+        self.inner
+            .process_message_with_context(message, &self.forwarded(ctx))
+            .await
+    }
+
+    async fn process_batch(&self, messages: Vec<Message>) -> Vec<Response> {
+        self.process_batch_with_context(messages, &ConnectionContext::default())
+            .await
+    }
+
+    async fn process_batch_with_context(
+        &self,
+        messages: Vec<Message>,
+        ctx: &ConnectionContext,
+    ) -> Vec<Response> {
+        let forwarded = self.forwarded(ctx);
+        crate::batch_policy::forward_admitted(
+            messages,
+            |message| {
+                let exceeded = self.policy.check(message.method()?, ctx).err()?;
+                Some(match message {
+                    Message::Request(request) => {
+                        Some(self.policy.refusal(&exceeded, request.id.clone()))
+                    }
+                    _ => None,
+                })
+            },
+            |admitted| self.inner.process_batch_with_context(admitted, &forwarded),
+        )
+        .await
+    }
+
+    async fn admit_with_context(
+        &self,
+        request: &Request,
+        ctx: &ConnectionContext,
+    ) -> Option<Response> {
+        if let Err(exceeded) = self.policy.check(&request.method, ctx) {
+            return Some(self.policy.refusal(&exceeded, request.id.clone()));
+        }
+        self.inner
+            .admit_with_context(request, &self.forwarded(ctx))
+            .await
+    }
+
+    fn supports_batching(&self) -> bool {
+        self.inner.supports_batching()
+    }
+
+    fn get_capabilities(&self) -> ProcessorCapabilities {
+        self.inner.get_capabilities().tighten(&self.limits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(port: u16) -> ConnectionContext {
+        ConnectionContext::with_addr(format!("10.0.0.{port}:4000").parse().unwrap())
+    }
+
+    #[test]
+    fn test_sliding_window_per_ip_and_method() {
+        let policy = RateLimitPolicy::new()
+            .method_per_ip("login", Limit::sliding_window(2, Duration::from_secs(60)))
+            .method(
+                "reports.*",
+                Limit::sliding_window(1, Duration::from_secs(60)),
+            );
+
+        assert!(policy.check("login", &client(1)).is_ok());
+        assert!(policy.check("login", &client(1)).is_ok());
+        let exceeded = policy.check("login", &client(1)).unwrap_err();
+        assert_eq!(exceeded.rule, "method-per-ip login");
+        assert!(exceeded.retry_after > Duration::from_secs(59));
+        assert!(policy.check("login", &client(2)).is_ok());
+
+        // shared by all callers
+        assert!(policy.check("reports.daily", &client(1)).is_ok());
+        assert!(policy.check("reports.weekly", &client(2)).is_err());
+        assert!(policy.check("other", &client(1)).is_ok());
+    }
+
+    #[test]
+    fn test_refused_calls_count_against_no_rule() {
+        let policy = RateLimitPolicy::new()
+            .global(Limit::sliding_window(2, Duration::from_secs(60)))
+            .method_per_ip("login", Limit::sliding_window(1, Duration::from_secs(60)));
+
+        assert!(policy.check("login", &client(1)).is_ok());
+        for _ in 0..3 {
+            let exceeded = policy.check("login", &client(1)).unwrap_err();
+            assert_eq!(exceeded.rule, "method-per-ip login");
+        }
+        // the refused logins left the global budget alone
+        assert!(policy.check("ping", &client(2)).is_ok());
+        assert_eq!(policy.check("ping", &client(2)).unwrap_err().rule, "global");
+    }
+
+    #[test]
+    fn test_caller_states_stay_bounded() {
+        let caller = |n: u32| {
+            ConnectionContext::with_addr(std::net::SocketAddr::from((
+                std::net::Ipv4Addr::from(n),
+                4000,
+            )))
+        };
+        let callers =
+            |policy: &RateLimitPolicy| policy.rules[0].states.lock().unwrap().states.len();
+
+        let busy = RateLimitPolicy::new().per_ip(Limit::sliding_window(1, Duration::from_secs(60)));
+        let total = MAX_CALLERS as u32 + 5;
+        for n in 0..total {
+            assert!(busy.check("ping", &caller(n)).is_ok());
+        }
+        assert_eq!(callers(&busy), MAX_CALLERS);
+        // recent callers keep their state, the least recently seen start over
+        assert!(busy.check("ping", &caller(total - 1)).is_err());
+        assert!(busy.check("ping", &caller(0)).is_ok());
+
+        // callers that went idle are dropped as others arrive
+        let idle = RateLimitPolicy::new().per_ip(Limit::sliding_window(1, Duration::ZERO));
+        for n in 0..100 {
+            assert!(idle.check("ping", &caller(n)).is_ok());
+        }
+        assert_eq!(callers(&idle), 1);
+    }
+
+    #[test]
+    fn test_per_principal() {
+        let policy =
+            RateLimitPolicy::new().per_principal(Limit::sliding_window(1, Duration::from_secs(60)));
+        let as_principal = |id: &str| {
+            let mut ctx = client(1);
+            ctx.set_principal(crate::auth::Principal::new(id));
+            ctx
+        };
+
+        assert!(policy.check("ping", &as_principal("alice")).is_ok());
+        let exceeded = policy.check("ping", &as_principal("alice")).unwrap_err();
+        assert_eq!(exceeded.rule, "per-principal");
+        // same address, different caller
+        assert!(policy.check("ping", &as_principal("bob")).is_ok());
+        assert!(policy.check("ping", &client(1)).is_ok());
+    }
+
+    #[test]
+    fn test_token_bucket_refills() {
+        let limit = Limit::token_bucket(1000, 2);
+        let now = Instant::now();
+        let mut state = State::new(&limit, now);
+        let admit = |state: &mut State, now| state.available(&limit, now).map(|()| state.take(now));
+        assert!(admit(&mut state, now).is_ok());
+        assert!(admit(&mut state, now).is_ok());
+        assert!(admit(&mut state, now).is_err());
+        assert!(!state.is_idle(&limit, now));
+        let later = now + Duration::from_millis(5);
+        assert!(admit(&mut state, later).is_ok());
+        assert!(state.is_idle(&limit, later + Duration::from_secs(1)));
+    }
+
+    #[tokio::test]
+    async fn test_registry_and_processor_refuse() {
+        let policy = || {
+            RateLimitPolicy::new()
+                .per_ip(Limit::sliding_window(1, Duration::from_secs(60)))
+                .error_code(-32029)
+        };
+        let registry = crate::MethodRegistry::empty().with_rate_limit(policy());
+        let ctx = client(3);
+        let first = registry
+            .call_with_context("missing", None, Some(serde_json::json!(1)), &ctx)
+            .await;
+        assert_eq!(first.error.unwrap().code, error_codes::METHOD_NOT_FOUND);
+        let second = registry
+            .call_with_context("missing", None, Some(serde_json::json!(2)), &ctx)
+            .await;
+        let error = second.error.unwrap();
+        assert_eq!(error.code, -32029);
+        assert_eq!(error.data.unwrap()["retry_after"], 60);
+
+        let processor =
+            RateLimitedProcessor::new(Arc::new(crate::MethodRegistry::empty()), policy());
+        let request = || Message::Request(Request::new("missing").with_id(serde_json::json!(1)));
+        let first = processor
+            .process_message_with_context(request(), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(first.error.unwrap().code, error_codes::METHOD_NOT_FOUND);
+        let second = processor
+            .process_message_with_context(request(), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(second.error.unwrap().code, -32029);
+    }
+
+    #[tokio::test]
+    async fn test_processor_forwards_batches() {
+        use crate::batch_policy::BatchPolicy;
+
+        let processor = RateLimitedProcessor::new(
+            Arc::new(crate::MethodRegistry::empty().with_batch_metadata(true)),
+            RateLimitPolicy::new().per_ip(Limit::sliding_window(2, Duration::from_secs(60))),
+        );
+        let first = crate::RequestBuilder::new("missing")
+            .id(serde_json::json!(1))
+            .batch_policy(BatchPolicy::AbortOnError)
+            .build();
+        let responses = processor
+            .process_batch_with_context(
+                vec![
+                    Message::Request(first),
+                    Message::Request(Request::new("missing").with_id(serde_json::json!(2))),
+                    Message::Request(Request::new("missing").with_id(serde_json::json!(3))),
+                ],
+                &client(4),
+            )
+            .await;
+        let codes: Vec<_> = responses
+            .iter()
+            .map(|response| response.error.as_ref().unwrap().code)
+            .collect();
+        // the registry's policy stopped the batch, the entry over the limit
+        // was refused before reaching it
+        assert_eq!(
+            codes,
+            [
+                error_codes::METHOD_NOT_FOUND,
+                error_codes::DEPENDENT_FAILURE,
+                error_codes::RETRY_LATER
+            ]
+        );
+        assert_eq!(responses[0].ext.as_ref().unwrap().batch.unwrap().index, 0);
+    }
+
+    #[tokio::test]
+    async fn test_composed_stack_reports_tightest_capabilities() {
+        use crate::builtins::{BuiltinConfig, BuiltinMethods};
+        use crate::transports::ListenerProcessor;
+
+        let registry: Arc<dyn MessageProcessor + Send + Sync> = Arc::new(
+            crate::MethodRegistry::empty()
+                .with_builtins(BuiltinConfig::new(BuiltinMethods::DISCOVERY)),
+        );
+        let limited = Arc::new(
+            RateLimitedProcessor::new(registry, RateLimitPolicy::new())
+                .capability_limits(CapabilityLimits::new().max_batch_size(20)),
+        );
+        let public =
+            ListenerProcessor::new(limited, Some("public".to_string()), MethodFilter::new())
+                .capability_limits(
+                    CapabilityLimits::new()
+                        .max_batch_size(50)
+                        .max_request_size(64 * 1024),
+                );
+
+        let capabilities = public.get_capabilities();
+        assert_eq!(capabilities.max_batch_size, Some(20));
+        assert_eq!(capabilities.max_request_size, Some(64 * 1024));
+        assert_eq!(capabilities.request_timeout_secs, Some(30));
+        assert!(capabilities.supports_batch);
+
+        // the built-in answers with what the whole stack allows
+        let request =
+            Message::Request(Request::new("rpc.capabilities").with_id(serde_json::json!(1)));
+        let response = public.process_message(request).await.unwrap();
+        let reported: ProcessorCapabilities =
+            serde_json::from_value(response.result.unwrap()).unwrap();
+        assert_eq!(reported.max_batch_size, Some(20));
+        assert_eq!(reported.max_request_size, Some(64 * 1024));
+    }
+}
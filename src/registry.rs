@@ -39,12 +39,47 @@
 use crate::builders::*;
 use crate::traits::*;
 use crate::types::*;
+use serde_json::value::RawValue;
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 /// Method registry with optional authentication
 pub struct MethodRegistry {
     methods: Vec<Box<dyn JsonRPCMethod>>,
+    static_results: HashMap<String, Box<RawValue>>,
     auth_policy: Option<Arc<dyn crate::auth::AuthPolicy>>,
+    rate_limit: Option<Arc<crate::rate_limit::RateLimitPolicy>>,
+    coalescing: Option<Arc<crate::coalesce::CoalescePolicy>>,
+    dead_letters: Option<Arc<crate::dead_letter::DeadLetterQueue>>,
+    versions: Option<Arc<crate::versioning::ProtocolVersions>>,
+    builtins: crate::builtins::BuiltinConfig,
+    /// Wire name of the registry-dispatched `rpc.selftest` built-in
+    selftest_method: Option<String>,
+    /// Running requests, tracked while the cancellation built-ins are served
+    in_flight: Option<Arc<crate::cancellation::InFlight>>,
+    strict_numbers: bool,
+    params_validation: Option<Arc<crate::validation::ParamsValidator>>,
+    feature_flags: Option<Arc<dyn crate::feature_flags::FeatureFlagProvider>>,
+    replay_guard: Option<Arc<crate::replay::ReplayGuard>>,
+    batch_metadata: bool,
+    batch_policy: crate::batch_policy::BatchPolicy,
+    batch_concurrency: Option<crate::batch_policy::BatchConcurrency>,
+    method_metadata: Option<Arc<crate::method_metadata::MethodMetadata>>,
+    /// Declared requirements of methods that have any
+    security: HashMap<String, crate::method_security::MethodSecurity>,
+    middleware: crate::interceptor::MiddlewareStack,
+    transformers: crate::transform::Transformers,
+    resource_accounting: bool,
+    /// Whether registrations refuse reserved method names
+    reserved_namespaces: bool,
+    /// User methods refused for a reserved name, registered if reserved
+    /// namespaces are allowed later
+    held_back: Vec<Box<dyn JsonRPCMethod>>,
+    #[cfg(feature = "healthcheck")]
+    degradation: Option<Arc<crate::healthcheck::DegradationPolicy>>,
+    #[cfg(feature = "timeouts")]
+    timeouts: Option<Arc<crate::timeout::TimeoutPolicy>>,
 }
 
 /// Macro to generate method dispatch match arms for registered JsonRPCMethod implementations
@@ -84,19 +119,61 @@ macro_rules! dispatch_call {
 
 impl MethodRegistry {
     /// Create a new method registry with the given method implementations
+    ///
+    /// User methods in a [reserved namespace](crate::namespaces) are refused
+    /// with an error log until
+    /// [`allow_reserved_namespaces`](Self::allow_reserved_namespaces) is
+    /// called; [`try_new`](Self::try_new) reports them instead.
     pub fn new(methods: Vec<Box<dyn JsonRPCMethod>>) -> Self {
         tracing::debug!(method_count = methods.len(), "registry created");
-        Self {
-            methods,
-            auth_policy: None,
+        let mut registry = Self::empty();
+        for method in methods {
+            registry.push_user_method(method);
         }
+        registry
+    }
+
+    /// Create a registry with the given methods, refusing user methods in a
+    /// [reserved namespace](crate::namespaces)
+    pub fn try_new(
+        methods: Vec<Box<dyn JsonRPCMethod>>,
+    ) -> Result<Self, crate::namespaces::RegistrationError> {
+        methods
+            .into_iter()
+            .try_fold(Self::empty(), Self::try_add_method)
     }
 
     /// Create an empty registry
     pub fn empty() -> Self {
         Self {
             methods: Vec::new(),
+            static_results: HashMap::new(),
             auth_policy: None,
+            rate_limit: None,
+            coalescing: None,
+            dead_letters: None,
+            versions: None,
+            builtins: crate::builtins::BuiltinConfig::none(),
+            selftest_method: None,
+            in_flight: None,
+            strict_numbers: false,
+            params_validation: None,
+            feature_flags: None,
+            replay_guard: None,
+            batch_metadata: false,
+            batch_policy: crate::batch_policy::BatchPolicy::default(),
+            batch_concurrency: None,
+            method_metadata: None,
+            security: HashMap::new(),
+            middleware: crate::interceptor::MiddlewareStack::default(),
+            transformers: crate::transform::Transformers::default(),
+            resource_accounting: false,
+            reserved_namespaces: true,
+            held_back: Vec::new(),
+            #[cfg(feature = "healthcheck")]
+            degradation: None,
+            #[cfg(feature = "timeouts")]
+            timeouts: None,
         }
     }
 
@@ -115,13 +192,316 @@ impl MethodRegistry {
         self
     }
 
+    /// Refuse calls beyond `policy`'s limits
+    ///
+    /// Checked after authentication, so denied callers do not use up the
+    /// budget of legitimate ones.
+    pub fn with_rate_limit(mut self, policy: crate::rate_limit::RateLimitPolicy) -> Self {
+        self.rate_limit = Some(Arc::new(policy));
+        self
+    }
+
+    /// Run identical concurrent calls of eligible methods only once
+    ///
+    /// See [`crate::coalesce`] for which methods are safe to coalesce.
+    pub fn with_coalescing(mut self, policy: crate::coalesce::CoalescePolicy) -> Self {
+        self.coalescing = Some(Arc::new(policy));
+        self
+    }
+
+    /// Retry failed notifications and dead-letter them when retries run out
+    ///
+    /// A notification fails when its method answers with an error. With the
+    /// `admin` built-ins enabled, letters are listed by `admin.dead_letters`
+    /// and requeued by `admin.requeue_dead_letter`.
+    pub fn with_dead_letters(mut self, queue: crate::dead_letter::DeadLetterQueue) -> Self {
+        self.dead_letters = Some(Arc::new(queue));
+        self
+    }
+
+    /// Serve additional protocol versions and version-specific methods
+    ///
+    /// See [`crate::versioning`]; with the `discovery` built-ins enabled,
+    /// clients negotiate a version through `rpc.negotiate`.
+    pub fn with_protocol_versions(mut self, versions: crate::versioning::ProtocolVersions) -> Self {
+        tracing::debug!(versions = ?versions.supported(), "protocol versions configured");
+        self.versions = Some(Arc::new(versions));
+        self
+    }
+
+    /// Enable built-in method groups
+    ///
+    /// Standalone built-ins of the enabled groups are registered immediately
+    /// under their configured namespaces. Registries start with no built-ins.
+    ///
+    /// # Example
+    /// ```text
+    /// let registry = MethodRegistry::new(methods)
+    ///     .with_builtins(BuiltinConfig::new(BuiltinMethods::HEALTHCHECK));
+    /// ```
+    pub fn with_builtins(mut self, config: crate::builtins::BuiltinConfig) -> Self {
+        tracing::debug!(builtins = ?config.enabled(), "enabling built-in methods");
+        for method in crate::builtins::builtin_methods(&config) {
+            self.push_method(method);
+        }
+        self.selftest_method = config
+            .is_enabled(crate::builtins::BuiltinMethods::DISCOVERY)
+            .then(|| config.method_name(crate::builtins::BuiltinMethods::DISCOVERY, "selftest"));
+        self.in_flight = config
+            .is_enabled(crate::builtins::BuiltinMethods::CANCELLATION)
+            .then(Default::default);
+        self.builtins = config;
+        self
+    }
+
+    /// Built-in method configuration of this registry
+    pub fn builtins(&self) -> &crate::builtins::BuiltinConfig {
+        &self.builtins
+    }
+
+    /// Reject params containing integers outside ±(2^53 - 1)
+    ///
+    /// Such values cannot be represented exactly by JavaScript callers and
+    /// must be sent as strings instead (see [`crate::numbers::as_string`]).
+    /// Offending requests get `INVALID_PARAMS` naming the offending path, and
+    /// the generated spec documents the contract under `x-json-numbers`.
+    pub fn with_strict_numbers(mut self, strict: bool) -> Self {
+        self.strict_numbers = strict;
+        self
+    }
+
+    /// Expose a feature flag provider to handlers via [`CallContext`]
+    ///
+    /// To hide a whole method behind a flag, wrap it in
+    /// [`DenyIfDisabled`](crate::feature_flags::DenyIfDisabled).
+    pub fn with_feature_flags<F>(mut self, flags: F) -> Self
+    where
+        F: crate::feature_flags::FeatureFlagProvider + 'static,
+    {
+        self.feature_flags = Some(Arc::new(flags));
+        self
+    }
+
+    /// Require a nonce/timestamp envelope for the guard's methods
+    ///
+    /// See [`crate::replay`] for the envelope format.
+    pub fn with_replay_guard(mut self, guard: crate::replay::ReplayGuard) -> Self {
+        self.replay_guard = Some(Arc::new(guard));
+        self
+    }
+
+    /// Attach [`BatchItemMeta`] to every response of a batch
+    ///
+    /// The metadata is sent in the `ext` member and lets clients match
+    /// failures to batch entries even when ids are missing or repeated.
+    pub fn with_batch_metadata(mut self, enabled: bool) -> Self {
+        self.batch_metadata = enabled;
+        self
+    }
+
+    /// Default policy for batches that do not ask for one
+    ///
+    /// [`BatchPolicy::Transactional`](crate::batch_policy::BatchPolicy::Transactional)
+    /// is refused: it needs a stateful transactional context.
+    pub fn with_batch_policy(
+        mut self,
+        policy: crate::batch_policy::BatchPolicy,
+    ) -> Result<Self, crate::batch_policy::UnsupportedPolicy> {
+        if policy == crate::batch_policy::BatchPolicy::Transactional {
+            return Err(crate::batch_policy::UnsupportedPolicy(policy));
+        }
+        self.batch_policy = policy;
+        Ok(self)
+    }
+
+    /// Run entries of a batch concurrently, see [`crate::batch_policy`]
+    pub fn with_batch_concurrency(
+        mut self,
+        concurrency: crate::batch_policy::BatchConcurrency,
+    ) -> Self {
+        self.batch_concurrency = Some(concurrency);
+        self
+    }
+
+    /// Fail guarded methods fast while their dependencies are unhealthy
+    ///
+    /// Checked after authentication, so callers without access learn
+    /// nothing about the service's health.
+    #[cfg(feature = "healthcheck")]
+    pub fn with_degradation(mut self, policy: crate::healthcheck::DegradationPolicy) -> Self {
+        self.degradation = Some(Arc::new(policy));
+        self
+    }
+
+    /// Stop waiting for methods that outlive their timeout or the caller's
+    /// deadline
+    ///
+    /// See [`crate::timeout`]; the default timeout is also advertised as
+    /// `request_timeout_secs` in the capabilities.
+    #[cfg(feature = "timeouts")]
+    pub fn with_timeouts(mut self, policy: crate::timeout::TimeoutPolicy) -> Self {
+        self.timeouts = Some(Arc::new(policy));
+        self
+    }
+
+    /// Wrap request dispatch in a [`Middleware`](crate::interceptor::Middleware)
+    ///
+    /// Layers run in registration order around every request and
+    /// notification processed as a [`MessageProcessor`]; see
+    /// [`crate::interceptor`] for the ordering and short-circuit rules.
+    pub fn layer<M: crate::interceptor::Middleware + 'static>(mut self, middleware: M) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Rewrite the result of every successful call with `transformer`
+    ///
+    /// See [`crate::transform`].
+    pub fn with_response_transformer<T>(mut self, transformer: T) -> Self
+    where
+        T: crate::transform::ResponseTransformer + 'static,
+    {
+        self.transformers.push(None, Arc::new(transformer));
+        self
+    }
+
+    /// Rewrite the result of every successful call of `method` with
+    /// `transformer`
+    pub fn with_method_transformer<T>(mut self, method: impl Into<String>, transformer: T) -> Self
+    where
+        T: crate::transform::ResponseTransformer + 'static,
+    {
+        self.transformers
+            .push(Some(method.into()), Arc::new(transformer));
+        self
+    }
+
+    /// Account poll time and allocations of every method call
+    ///
+    /// See [`crate::resource_usage`]; the figures are served by the
+    /// `diagnostics.resources` built-in.
+    pub fn with_resource_accounting(mut self, enabled: bool) -> Self {
+        self.resource_accounting = enabled;
+        self
+    }
+
+    /// Validate params against per-method JSON Schemas before calling
+    pub fn with_params_validation(mut self, validator: crate::validation::ParamsValidator) -> Self {
+        self.params_validation = Some(Arc::new(validator));
+        self
+    }
+
+    /// Merge externally stored documentation into the generated spec
+    ///
+    /// Call this after all methods, built-ins included, are registered:
+    /// entries naming an unregistered method are rejected.
+    pub fn with_method_metadata(
+        mut self,
+        metadata: crate::method_metadata::MethodMetadata,
+    ) -> Result<Self, crate::method_metadata::MetadataError> {
+        metadata.validate(&self.get_methods())?;
+        tracing::debug!(
+            documented = metadata.methods.len(),
+            "method metadata loaded"
+        );
+        self.method_metadata = Some(Arc::new(metadata));
+        Ok(self)
+    }
+
+    /// Hold callers of `method` to `security`, replacing what the method
+    /// declares itself
+    pub fn with_method_security(
+        mut self,
+        method: impl Into<String>,
+        security: crate::method_security::MethodSecurity,
+    ) -> Self {
+        self.security.insert(method.into(), security);
+        self
+    }
+
+    /// Declared security requirements of `method`, if any
+    pub fn method_security(&self, method: &str) -> Option<&crate::method_security::MethodSecurity> {
+        self.security.get(method)
+    }
+
     /// Add a method implementation to the registry
+    ///
+    /// Refuses user methods in a reserved namespace like [`new`](Self::new).
     pub fn add_method(mut self, method: Box<dyn JsonRPCMethod>) -> Self {
         tracing::trace!("adding method to registry");
-        self.methods.push(method);
+        self.push_user_method(method);
+        self
+    }
+
+    /// Add a method implementation, refusing user methods in a
+    /// [reserved namespace](crate::namespaces) unless
+    /// [allowed](Self::allow_reserved_namespaces)
+    pub fn try_add_method(
+        mut self,
+        method: Box<dyn JsonRPCMethod>,
+    ) -> Result<Self, crate::namespaces::RegistrationError> {
+        if self.reserved_namespaces
+            && !method.is_builtin()
+            && let Some(prefix) = crate::namespaces::reserved_prefix(method.method_name())
+        {
+            return Err(crate::namespaces::RegistrationError::ReservedNamespace {
+                method: method.method_name().to_string(),
+                prefix,
+            });
+        }
+        self.push_method(method);
+        Ok(self)
+    }
+
+    /// Register user methods in reserved namespaces
+    ///
+    /// Methods [`new`](Self::new) or [`add_method`](Self::add_method)
+    /// refused before are registered now.
+    pub fn allow_reserved_namespaces(mut self) -> Self {
+        self.reserved_namespaces = false;
+        for method in std::mem::take(&mut self.held_back) {
+            self.push_method(method);
+        }
         self
     }
 
+    /// Register a user method, refusing reserved names
+    fn push_user_method(&mut self, method: Box<dyn JsonRPCMethod>) {
+        if self.reserved_namespaces
+            && !method.is_builtin()
+            && let Some(prefix) = crate::namespaces::reserved_prefix(method.method_name())
+        {
+            tracing::error!(
+                method = %method.method_name(),
+                prefix,
+                "method in a reserved namespace not registered, see allow_reserved_namespaces"
+            );
+            self.held_back.push(method);
+            return;
+        }
+        self.push_method(method);
+    }
+
+    fn push_method(&mut self, method: Box<dyn JsonRPCMethod>) {
+        if let Some(result) = method.static_result() {
+            match serde_json::value::to_raw_value(&result) {
+                Ok(raw) => {
+                    self.static_results
+                        .insert(method.method_name().to_string(), raw);
+                }
+                Err(e) => {
+                    tracing::warn!(method = %method.method_name(), error = %e, "static result not serializable");
+                }
+            }
+        }
+        let security = method.security();
+        if !security.is_empty() {
+            self.security
+                .insert(method.method_name().to_string(), security);
+        }
+        self.methods.push(method);
+    }
+
     /// Call a registered method asynchronously using compile-time dispatch
     /// Note: This method should typically be replaced by using the dispatch_methods! macro directly
     /// for better compile-time optimization
@@ -150,23 +530,307 @@ impl MethodRegistry {
         id: Option<RequestId>,
         ctx: &crate::auth::ConnectionContext,
     ) -> Response {
+        self.call_with_version(
+            crate::versioning::DEFAULT_VERSION,
+            method_name,
+            params,
+            id,
+            ctx,
+        )
+        .await
+    }
+
+    /// Run the auth policy, declared method security and rate limits for a
+    /// call
+    ///
+    /// Returns the context the call continues with, carrying the principal
+    /// the auth policy identified, or the refusal to answer with.
+    fn admission<'a>(
+        &self,
+        method_name: &str,
+        params: Option<&serde_json::Value>,
+        id: &Option<RequestId>,
+        ctx: &'a crate::auth::ConnectionContext,
+    ) -> Result<Cow<'a, crate::auth::ConnectionContext>, Box<Response>> {
         // Check authentication if policy is set
         if let Some(auth) = &self.auth_policy
-            && !auth.can_access(method_name, params.as_ref(), ctx)
+            && !auth.can_access(method_name, params, ctx)
+        {
+            crate::rejection::record(
+                crate::rejection::Rejection::new(crate::rejection::RejectionReason::Unauthorized)
+                    .method(method_name)
+                    .remote_addr(ctx.remote_addr)
+                    .origin(ctx.origin.clone())
+                    .principal(ctx.principal_id()),
+            );
+            return Err(Box::new(auth.unauthorized_error(method_name)));
+        }
+
+        // Attach the principal the policy identified for everything after authorization
+        let ctx = match &self.auth_policy {
+            Some(auth) if ctx.principal().is_none() => {
+                match auth.identify(method_name, params, ctx) {
+                    Some(principal) => {
+                        let mut with_principal = ctx.clone();
+                        with_principal.set_principal(principal);
+                        Cow::Owned(with_principal)
+                    }
+                    None => Cow::Borrowed(ctx),
+                }
+            }
+            _ => Cow::Borrowed(ctx),
+        };
+
+        let security = self.security.get(method_name);
+        if let Some(security) = security
+            && !security.permits(&ctx)
         {
-            tracing::warn!(
-                method = %method_name,
-                remote_addr = ?ctx.remote_addr,
-                "access denied by auth policy"
+            crate::rejection::record(
+                crate::rejection::Rejection::new(crate::rejection::RejectionReason::Unauthorized)
+                    .method(method_name)
+                    .remote_addr(ctx.remote_addr)
+                    .origin(ctx.origin.clone())
+                    .principal(ctx.principal_id())
+                    .detail("declared scope or role missing"),
             );
-            return auth.unauthorized_error(method_name);
+            return Err(Box::new(match &self.auth_policy {
+                Some(auth) => auth.unauthorized_error(method_name),
+                // the answer of a policy without a custom error
+                None => {
+                    crate::auth::AuthPolicy::unauthorized_error(&crate::auth::DenyAll, method_name)
+                }
+            }));
+        }
+
+        if let Some(policy) = &self.rate_limit {
+            let class = security.and_then(|security| security.rate_class.as_deref());
+            if let Err(exceeded) = policy.check_class(method_name, class, &ctx) {
+                return Err(Box::new(policy.refusal(&exceeded, id.clone())));
+            }
+        }
+
+        Ok(ctx)
+    }
+
+    /// Call a method as requested with protocol `version`
+    ///
+    /// Uses the version-specific implementation of the method when one is
+    /// registered; the version itself is not validated.
+    pub async fn call_with_version(
+        &self,
+        version: &str,
+        method_name: &str,
+        params: Option<serde_json::Value>,
+        id: Option<RequestId>,
+        ctx: &crate::auth::ConnectionContext,
+    ) -> Response {
+        let admitted = match self.admission(method_name, params.as_ref(), &id, ctx) {
+            Ok(ctx) => ctx,
+            Err(refusal) => return *refusal,
+        };
+        let ctx = admitted.as_ref();
+
+        let params = match &self.replay_guard {
+            Some(guard) => match guard.check(method_name, params).await {
+                Ok(params) => params,
+                Err(e) => {
+                    crate::rejection::record(
+                        crate::rejection::Rejection::new(
+                            crate::rejection::RejectionReason::Replayed,
+                        )
+                        .method(method_name)
+                        .remote_addr(ctx.remote_addr)
+                        .origin(ctx.origin.clone())
+                        .principal(ctx.principal_id())
+                        .detail(e.to_string()),
+                    );
+                    return ResponseBuilder::new()
+                        .error(
+                            ErrorBuilder::new(error_codes::INVALID_REQUEST, e.to_string()).build(),
+                        )
+                        .id(id)
+                        .build();
+                }
+            },
+            None => params,
+        };
+
+        if self.strict_numbers
+            && let Some(path) = params
+                .as_ref()
+                .and_then(crate::numbers::find_unsafe_integer)
+        {
+            tracing::debug!(method = %method_name, path = %path, "unsafe integer in params");
+            return ResponseBuilder::new()
+                .error(
+                    ErrorBuilder::new(
+                        error_codes::INVALID_PARAMS,
+                        format!("Integer at '{path}' exceeds the safe range, send it as a string"),
+                    )
+                    .build(),
+                )
+                .id(id)
+                .build();
+        }
+
+        if let Some(validator) = &self.params_validation
+            && let Err(error) = validator.check(method_name, params.as_ref())
+        {
+            tracing::debug!(method = %method_name, error = %error.message(), "params rejected by schema");
+            return ResponseBuilder::new().error(error).id(id).build();
+        }
+
+        #[cfg(feature = "healthcheck")]
+        if let Some(response) = self
+            .degradation
+            .as_ref()
+            .and_then(|policy| policy.check(method_name, id.clone()))
+        {
+            return response;
+        }
+
+        if let Some(response) = self.dead_letter_admin(method_name, params.clone(), &id, ctx) {
+            return response.await;
+        }
+
+        if let Some(versions) = &self.versions
+            && self.negotiate_method().as_deref() == Some(method_name)
+        {
+            return versions.negotiate_response(params.as_ref(), id);
         }
 
+        if self.capabilities_method().as_deref() == Some(method_name) {
+            // as tightened by the wrappers the call passed through
+            let capabilities = ctx
+                .capability_limits()
+                .iter()
+                .fold(self.get_capabilities(), ProcessorCapabilities::tighten);
+            return match serde_json::to_value(capabilities) {
+                Ok(capabilities) => Response::success(capabilities, id),
+                Err(e) => crate::rpc_error!(error_codes::INTERNAL_ERROR, e.to_string(), id),
+            };
+        }
+
+        if let Some([discover, methods]) = self.introspection_methods() {
+            if method_name == discover {
+                let (title, version) = self.builtins.service();
+                let spec = self.generate_openapi_spec(title, version);
+                return Response::success(crate::openrpc::document(&spec), id);
+            }
+            if method_name == methods {
+                let mut names = self.get_methods();
+                names.sort();
+                return crate::rpc_success!(names, id);
+            }
+        }
+
+        if let Some(in_flight) = &self.in_flight
+            && self
+                .cancel_methods()
+                .is_some_and(|names| names.iter().any(|name| name == method_name))
+        {
+            let Some(target) = params.as_ref().and_then(|params| params.get("id")) else {
+                return crate::rpc_error!(
+                    error_codes::INVALID_PARAMS,
+                    "Expected {\"id\": <request id>}",
+                    id
+                );
+            };
+            return crate::rpc_success!(in_flight.cancel(ctx, target), id);
+        }
+
+        if self.selftest_method.as_deref() == Some(method_name) {
+            let report = crate::selftest::run(self).await;
+            return match serde_json::to_value(&report) {
+                Ok(report) => Response::success(report, id),
+                Err(e) => crate::rpc_error!(error_codes::INTERNAL_ERROR, e.to_string(), id),
+            };
+        }
+
+        let versioned = self
+            .versions
+            .as_ref()
+            .and_then(|versions| versions.method_for(version, method_name));
         // Fallback to runtime dispatch if compile-time dispatch is not used
-        for method in &self.methods {
+        for method in versioned
+            .into_iter()
+            .chain(self.methods.iter().map(|method| method.as_ref()))
+        {
             if method.method_name() == method_name {
                 tracing::debug!(method = %method_name, "calling method");
-                return method.call(params, id).await;
+                #[cfg(feature = "timeouts")]
+                let deadlined;
+                #[cfg(feature = "timeouts")]
+                let (ctx, budget) = match self.time_budget(method_name, ctx) {
+                    Some(budget) if budget.is_zero() => {
+                        return crate::timeout::timed_out(method_name, budget, id);
+                    }
+                    Some(budget) => {
+                        deadlined = ctx
+                            .clone()
+                            .with_deadline(std::time::Instant::now() + budget);
+                        (&deadlined, Some(budget))
+                    }
+                    None => (ctx, None),
+                };
+                let mut call_ctx = CallContext::new(ctx);
+                if let Some(flags) = &self.feature_flags {
+                    call_ctx = call_ctx.with_flags(flags.as_ref());
+                }
+                let tracked = self
+                    .in_flight
+                    .as_ref()
+                    .zip(id.as_ref())
+                    .map(|(in_flight, id)| in_flight.track(ctx, id));
+                if let Some(tracked) = &tracked {
+                    call_ctx = call_ctx.with_cancellation(&tracked.token);
+                }
+                let call = |params, id| async {
+                    let call = method.call_with_context(params, id, &call_ctx);
+                    if self.resource_accounting {
+                        crate::resource_usage::measure(method_name, call).await
+                    } else {
+                        call.await
+                    }
+                };
+                let cancelled_id = tracked.as_ref().and(id.clone());
+                #[cfg(feature = "timeouts")]
+                let timeout_id = budget.and(id.clone());
+                let dispatch = async {
+                    match &self.coalescing {
+                        Some(policy) if policy.is_eligible(method_name) => {
+                            policy.run(method_name, params, id, call).await
+                        }
+                        _ => call(params, id).await,
+                    }
+                };
+                #[cfg(feature = "timeouts")]
+                let dispatch = async {
+                    match budget {
+                        Some(budget) => tokio::time::timeout(budget, dispatch)
+                            .await
+                            .unwrap_or_else(|_| {
+                                crate::timeout::timed_out(method_name, budget, timeout_id)
+                            }),
+                        None => dispatch.await,
+                    }
+                };
+                let mut response = match &tracked {
+                    Some(tracked) => tracked
+                        .token
+                        .run_until_cancelled(dispatch)
+                        .await
+                        .unwrap_or_else(|| {
+                            crate::rpc_error!(
+                                error_codes::REQUEST_CANCELLED,
+                                "Request cancelled",
+                                cancelled_id
+                            )
+                        }),
+                    None => dispatch.await,
+                };
+                self.transformers.apply(method_name, &mut response, ctx);
+                return response;
             }
         }
 
@@ -180,6 +844,18 @@ impl MethodRegistry {
     /// Check if a method is registered
     pub fn has_method(&self, method_name: &str) -> bool {
         self.methods.iter().any(|m| m.method_name() == method_name)
+            || self.selftest_method.as_deref() == Some(method_name)
+            || self
+                .dead_letter_methods()
+                .is_some_and(|names| names.iter().any(|name| name == method_name))
+            || self.negotiate_method().as_deref() == Some(method_name)
+            || self.capabilities_method().as_deref() == Some(method_name)
+            || self
+                .introspection_methods()
+                .is_some_and(|names| names.iter().any(|name| name == method_name))
+            || self
+                .cancel_methods()
+                .is_some_and(|names| names.iter().any(|name| name == method_name))
     }
 
     /// Get list of all registered methods
@@ -187,12 +863,24 @@ impl MethodRegistry {
         self.methods
             .iter()
             .map(|m| m.method_name().to_string())
+            .chain(self.selftest_method.clone())
+            .chain(self.dead_letter_methods().into_iter().flatten())
+            .chain(self.negotiate_method())
+            .chain(self.capabilities_method())
+            .chain(self.introspection_methods().into_iter().flatten())
+            .chain(self.cancel_methods().into_iter().flatten())
             .collect()
     }
 
     /// Get the number of registered methods
     pub fn method_count(&self) -> usize {
         self.methods.len()
+            + usize::from(self.selftest_method.is_some())
+            + self.dead_letter_methods().map_or(0, |names| names.len())
+            + usize::from(self.negotiate_method().is_some())
+            + usize::from(self.capabilities_method().is_some())
+            + self.introspection_methods().map_or(0, |names| names.len())
+            + self.cancel_methods().map_or(0, |names| names.len())
     }
 
     /// Generate OpenAPI specification for all registered methods
@@ -201,10 +889,113 @@ impl MethodRegistry {
         let mut spec = OpenApiSpec::new(title, version);
 
         for method in &self.methods {
-            let method_spec = method.openapi_components();
+            let mut method_spec = method.openapi_components();
+            if method_spec.parameters.is_none() {
+                method_spec.parameters = self
+                    .params_validation
+                    .as_ref()
+                    .and_then(|validator| validator.schema(method.method_name()))
+                    .cloned();
+            }
+            if let Some(doc) = self
+                .method_metadata
+                .as_ref()
+                .and_then(|metadata| metadata.get(method.method_name()))
+            {
+                doc.apply(&mut method_spec);
+            }
+            if let Some(security) = self.security.get(method.method_name()) {
+                security.apply(&mut method_spec);
+            }
             spec.add_method(method_spec);
         }
 
+        if let Some(name) = &self.selftest_method {
+            spec.add_method(
+                OpenApiMethodSpec::new(name)
+                    .with_summary("Run the protocol conformance self-test")
+                    .with_tag("builtin"),
+            );
+        }
+
+        if let Some(name) = self.capabilities_method() {
+            spec.add_method(
+                OpenApiMethodSpec::new(name)
+                    .with_summary("Describe batch, size and version limits of this server")
+                    .with_tag("builtin"),
+            );
+        }
+
+        if let Some([discover, methods]) = self.introspection_methods() {
+            spec.add_method(
+                OpenApiMethodSpec::new(discover)
+                    .with_summary("Describe this service as an OpenRPC document")
+                    .with_tag("builtin"),
+            );
+            spec.add_method(
+                OpenApiMethodSpec::new(methods)
+                    .with_summary("Names of the methods this service answers")
+                    .with_tag("builtin"),
+            );
+        }
+
+        if let Some([cancel, alias]) = self.cancel_methods() {
+            let params = serde_json::json!({
+                "type": "object",
+                "properties": {"id": {"description": "Id of the request to cancel"}},
+                "required": ["id"]
+            });
+            spec.add_method(
+                OpenApiMethodSpec::new(cancel)
+                    .with_summary("Cancel a running request of this connection")
+                    .with_parameters(params.clone())
+                    .with_tag("builtin"),
+            );
+            spec.add_method(
+                OpenApiMethodSpec::new(alias)
+                    .with_summary("LSP-style alias of the cancel built-in")
+                    .with_parameters(params)
+                    .with_tag("builtin"),
+            );
+        }
+
+        if let Some(name) = self.negotiate_method() {
+            spec.add_method(
+                OpenApiMethodSpec::new(name)
+                    .with_summary("Pick the protocol version to use")
+                    .with_tag("builtin"),
+            );
+        }
+
+        if let Some([list, requeue]) = self.dead_letter_methods() {
+            spec.add_method(
+                OpenApiMethodSpec::new(list)
+                    .with_summary("Notifications that failed every delivery attempt")
+                    .with_tag("builtin"),
+            );
+            spec.add_method(
+                OpenApiMethodSpec::new(requeue)
+                    .with_summary("Deliver a dead-lettered notification again")
+                    .with_tag("builtin"),
+            );
+        }
+
+        spec.add_extension(
+            "x-reserved-namespaces",
+            crate::namespaces::extension(self.reserved_namespaces),
+        );
+
+        if self.strict_numbers {
+            spec.add_extension("x-json-numbers", crate::numbers::strict_numbers_extension());
+            spec.components.schemas.insert(
+                "Int64String".into(),
+                crate::numbers::string_integer_schema("int64"),
+            );
+            spec.components
+                .schemas
+                .insert("DecimalString".into(), crate::numbers::decimal_schema());
+        }
+
         spec
     }
 
@@ -240,6 +1031,243 @@ impl MethodRegistry {
     }
 }
 
+impl MethodRegistry {
+    /// Wire name of the `rpc.negotiate` built-in, when served
+    fn negotiate_method(&self) -> Option<String> {
+        use crate::builtins::BuiltinMethods;
+
+        (self.versions.is_some() && self.builtins.is_enabled(BuiltinMethods::DISCOVERY)).then(
+            || {
+                self.builtins
+                    .method_name(BuiltinMethods::DISCOVERY, "negotiate")
+            },
+        )
+    }
+
+    /// Wire name of the `rpc.capabilities` built-in, when served
+    ///
+    /// It answers with the registry's own
+    /// [`get_capabilities`](MessageProcessor::get_capabilities); wrappers
+    /// configured with [`CapabilityLimits`] may report and enforce stricter
+    /// limits than this answer.
+    fn capabilities_method(&self) -> Option<String> {
+        use crate::builtins::BuiltinMethods;
+
+        self.builtins
+            .is_enabled(BuiltinMethods::DISCOVERY)
+            .then(|| {
+                self.builtins
+                    .method_name(BuiltinMethods::DISCOVERY, "capabilities")
+            })
+    }
+
+    /// Wire names of the `rpc.discover` and `rpc.methods` built-ins, when
+    /// served
+    fn introspection_methods(&self) -> Option<[String; 2]> {
+        use crate::builtins::BuiltinMethods;
+
+        self.builtins
+            .is_enabled(BuiltinMethods::DISCOVERY)
+            .then(|| {
+                [
+                    self.builtins
+                        .method_name(BuiltinMethods::DISCOVERY, "discover"),
+                    self.builtins
+                        .method_name(BuiltinMethods::DISCOVERY, "methods"),
+                ]
+            })
+    }
+
+    /// Time `method` may run: its timeout, cut short by the caller's deadline
+    #[cfg(feature = "timeouts")]
+    fn time_budget(
+        &self,
+        method: &str,
+        ctx: &crate::auth::ConnectionContext,
+    ) -> Option<std::time::Duration> {
+        let timeout = self
+            .timeouts
+            .as_ref()
+            .and_then(|policy| policy.timeout_for(method));
+        match (timeout, ctx.remaining()) {
+            (Some(timeout), Some(remaining)) => Some(timeout.min(remaining)),
+            (timeout, remaining) => timeout.or(remaining),
+        }
+    }
+
+    /// Wire names of the `rpc.cancel` built-in and its `$/cancelRequest`
+    /// alias, when served
+    fn cancel_methods(&self) -> Option<[String; 2]> {
+        use crate::builtins::BuiltinMethods;
+
+        self.in_flight.is_some().then(|| {
+            [
+                self.builtins
+                    .method_name(BuiltinMethods::CANCELLATION, "cancel"),
+                "$/cancelRequest".to_string(),
+            ]
+        })
+    }
+
+    /// Error answering a message made with an unserved protocol version
+    fn check_version(&self, version: &str, id: &Option<RequestId>) -> Option<Response> {
+        let supported = match &self.versions {
+            Some(versions) => versions.is_supported(version),
+            None => version == crate::versioning::DEFAULT_VERSION,
+        };
+        if supported {
+            return None;
+        }
+        tracing::debug!(version, "unsupported protocol version");
+        Some(match &self.versions {
+            Some(versions) => versions.unsupported(version, id.clone()),
+            None => crate::versioning::ProtocolVersions::new().unsupported(version, id.clone()),
+        })
+    }
+
+    /// Wire names of the dead letter admin built-ins, when served
+    fn dead_letter_methods(&self) -> Option<[String; 2]> {
+        use crate::builtins::BuiltinMethods;
+
+        (self.dead_letters.is_some() && self.builtins.is_enabled(BuiltinMethods::ADMIN)).then(
+            || {
+                [
+                    self.builtins
+                        .method_name(BuiltinMethods::ADMIN, "dead_letters"),
+                    self.builtins
+                        .method_name(BuiltinMethods::ADMIN, "requeue_dead_letter"),
+                ]
+            },
+        )
+    }
+
+    /// Answer the dead letter admin built-ins, `None` for any other method
+    fn dead_letter_admin<'a>(
+        &'a self,
+        method_name: &str,
+        params: Option<serde_json::Value>,
+        id: &Option<RequestId>,
+        ctx: &'a crate::auth::ConnectionContext,
+    ) -> Option<std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send + 'a>>> {
+        let queue = self.dead_letters.as_ref()?;
+        let [list, requeue] = self.dead_letter_methods()?;
+        let id = id.clone();
+        if method_name == list {
+            return Some(Box::pin(async move {
+                crate::rpc_success!(queue.sink().list(), id)
+            }));
+        }
+        if method_name != requeue {
+            return None;
+        }
+        Some(Box::pin(async move {
+            let Some(letter_id) = params
+                .as_ref()
+                .and_then(|params| params.get("id"))
+                .and_then(|letter_id| letter_id.as_str())
+            else {
+                return crate::rpc_error!(
+                    error_codes::INVALID_PARAMS,
+                    "Expected {\"id\": <dead letter id>}",
+                    id
+                );
+            };
+            match queue
+                .requeue(letter_id, |notification| {
+                    self.deliver_notification(notification, ctx)
+                })
+                .await
+            {
+                Some(delivered) => {
+                    crate::rpc_success!(serde_json::json!({ "delivered": delivered }), id)
+                }
+                None => crate::rpc_error!(
+                    error_codes::INVALID_PARAMS,
+                    format!("No dead letter with id '{letter_id}'"),
+                    id
+                ),
+            }
+        }))
+    }
+
+    /// Dispatch a notification once, failing when its method answers with an error
+    fn deliver_notification<'a>(
+        &'a self,
+        notification: Notification,
+        ctx: &'a crate::auth::ConnectionContext,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), crate::Error>> + Send + 'a>>
+    {
+        Box::pin(async move {
+            let response = if self.middleware.is_empty() {
+                self.call_with_version(
+                    &notification.jsonrpc,
+                    &notification.method,
+                    notification.params,
+                    None,
+                    ctx,
+                )
+                .await
+            } else {
+                let request = Request {
+                    jsonrpc: notification.jsonrpc,
+                    method: notification.method,
+                    params: notification.params,
+                    id: None,
+                    correlation_id: None,
+                    ext: None,
+                };
+                self.call_with_middleware(&request, ctx).await
+            };
+            response.error.map_or(Ok(()), Err)
+        })
+    }
+
+    async fn process_notification(
+        &self,
+        notification: Notification,
+        ctx: &crate::auth::ConnectionContext,
+    ) {
+        match &self.dead_letters {
+            Some(queue) => {
+                queue
+                    .deliver(notification, |notification| {
+                        self.deliver_notification(notification, ctx)
+                    })
+                    .await;
+            }
+            None => {
+                let _ = self.deliver_notification(notification, ctx).await;
+            }
+        }
+    }
+
+    async fn call_with_middleware(
+        &self,
+        request: &Request,
+        ctx: &crate::auth::ConnectionContext,
+    ) -> Response {
+        self.middleware
+            .run(request, ctx, |extensions| async move {
+                let extended;
+                let ctx = if extensions.is_empty() {
+                    ctx
+                } else {
+                    extended = ctx.clone().with_extensions(extensions);
+                    &extended
+                };
+                self.call_with_version(
+                    &request.jsonrpc,
+                    &request.method,
+                    request.params.clone(),
+                    request.id.clone(),
+                    ctx,
+                )
+                .await
+            })
+            .await
+    }
+}
+
 impl Default for MethodRegistry {
     fn default() -> Self {
         Self::empty()
@@ -249,34 +1277,91 @@ impl Default for MethodRegistry {
 #[async_trait::async_trait]
 impl MessageProcessor for MethodRegistry {
     async fn process_message(&self, message: Message) -> Option<Response> {
+        self.process_message_with_context(message, &crate::auth::ConnectionContext::default())
+            .await
+    }
+
+    async fn process_message_with_context(
+        &self,
+        message: Message,
+        ctx: &crate::auth::ConnectionContext,
+    ) -> Option<Response> {
         match message {
             Message::Request(request) => {
                 tracing::trace!(method = %request.method, correlation_id = ?request.correlation_id, "processing request");
-                let response = self.call(&request.method, request.params, request.id).await;
+                if let Some(response) = self.check_version(&request.jsonrpc, &request.id) {
+                    return Some(response);
+                }
+                let version = request.jsonrpc.clone();
+                let correlated;
+                let ctx = match &request.correlation_id {
+                    Some(correlation_id) => {
+                        correlated = ctx.clone().with_correlation_id(correlation_id.clone());
+                        &correlated
+                    }
+                    None => ctx,
+                };
+                let mut response = if self.middleware.is_empty() {
+                    self.call_with_version(
+                        &version,
+                        &request.method,
+                        request.params,
+                        request.id,
+                        ctx,
+                    )
+                    .await
+                } else {
+                    self.call_with_middleware(&request, ctx).await
+                };
+                if version != crate::versioning::DEFAULT_VERSION {
+                    response.jsonrpc = version;
+                }
                 Some(response)
             }
             Message::Notification(notification) => {
                 tracing::trace!(method = %notification.method, "processing notification");
-                let _ = self
-                    .call(&notification.method, notification.params, None)
-                    .await;
+                if self.check_version(&notification.jsonrpc, &None).is_some() {
+                    return None;
+                }
+                self.process_notification(notification, ctx).await;
                 None
             }
             Message::Response(_) => None,
         }
     }
 
+    async fn admit_with_context(
+        &self,
+        request: &Request,
+        ctx: &crate::auth::ConnectionContext,
+    ) -> Option<Response> {
+        let mut refusal = self
+            .admission(&request.method, request.params.as_ref(), &request.id, ctx)
+            .err()?;
+        refusal.id = request.id.clone();
+        Some(*refusal)
+    }
+
     async fn process_batch(&self, messages: Vec<Message>) -> Vec<Response> {
+        self.process_batch_with_context(messages, &crate::auth::ConnectionContext::default())
+            .await
+    }
+
+    async fn process_batch_with_context(
+        &self,
+        messages: Vec<Message>,
+        ctx: &crate::auth::ConnectionContext,
+    ) -> Vec<Response> {
         let capabilities = self.get_capabilities();
 
         // Validate batch size
         if let Some(max_size) = capabilities.max_batch_size
             && messages.len() > max_size
         {
-            tracing::warn!(
-                batch_size = messages.len(),
-                max_batch_size = max_size,
-                "batch size limit exceeded"
+            crate::rejection::record(
+                crate::rejection::Rejection::new(crate::rejection::RejectionReason::BatchTooLarge)
+                    .remote_addr(ctx.remote_addr)
+                    .detail(format!("{} entries exceeds {}", messages.len(), max_size)),
             );
             return vec![crate::Response::error(
                 crate::ErrorBuilder::new(
@@ -288,14 +1373,78 @@ impl MessageProcessor for MethodRegistry {
             )];
         }
 
-        tracing::debug!(batch_size = messages.len(), "processing batch");
-        let mut results = Vec::new();
-        for msg in messages {
-            if let Some(response) = self.process_message(msg).await {
-                results.push(response);
+        let policy = crate::batch_policy::BatchPolicy::requested(&messages, self.batch_policy);
+        if policy == crate::batch_policy::BatchPolicy::Transactional {
+            return vec![crate::batch_policy::unsupported()];
+        }
+
+        tracing::debug!(batch_size = messages.len(), ?policy, "processing batch");
+        let process = |index, msg| async move {
+            let started = std::time::Instant::now();
+            let mut response = self.process_message_with_context(msg, ctx).await?;
+            if self.batch_metadata {
+                response.ext.get_or_insert_default().batch = Some(BatchItemMeta {
+                    index,
+                    duration_us: started.elapsed().as_micros() as u64,
+                });
+            }
+            Some(response)
+        };
+        match self.batch_concurrency {
+            Some(concurrency) if policy == crate::batch_policy::BatchPolicy::ContinueOnError => {
+                crate::batch_policy::execute_concurrent(concurrency, messages, process).await
             }
+            _ => crate::batch_policy::execute(policy, messages, process).await,
+        }
+    }
+
+    fn static_response(&self, method: &str, id: Option<&RawValue>) -> Option<String> {
+        // Auth decisions may depend on the caller, never bypass them
+        if self.auth_policy.is_some()
+            || !self.middleware.is_empty()
+            || self.security.contains_key(method)
+            || self.transformers.applies_to(method)
+        {
+            return None;
+        }
+        if self
+            .replay_guard
+            .as_ref()
+            .is_some_and(|guard| guard.is_protected(method))
+        {
+            return None;
+        }
+        if self
+            .rate_limit
+            .as_ref()
+            .is_some_and(|policy| policy.applies_to(method))
+        {
+            return None;
+        }
+        #[cfg(feature = "healthcheck")]
+        if self
+            .degradation
+            .as_ref()
+            .is_some_and(|policy| policy.is_guarded(method))
+        {
+            return None;
+        }
+        // cached results are those of the base method, served as 2.0
+        if self.versions.as_ref().is_some_and(|versions| {
+            !versions.is_supported(crate::versioning::DEFAULT_VERSION)
+                || versions
+                    .method_for(crate::versioning::DEFAULT_VERSION, method)
+                    .is_some()
+        }) {
+            return None;
         }
-        results
+        let result = self.static_results.get(method)?;
+        let id = id.map(RawValue::get).unwrap_or("null");
+        Some(format!(
+            "{{\"jsonrpc\":\"2.0\",\"result\":{},\"id\":{}}}",
+            result.get(),
+            id
+        ))
     }
 
     fn get_capabilities(&self) -> ProcessorCapabilities {
@@ -304,8 +1453,17 @@ impl MessageProcessor for MethodRegistry {
             supports_notifications: true,
             max_batch_size: Some(100),
             max_request_size: Some(1024 * 1024), // 1 MB
+            #[cfg(feature = "timeouts")]
+            request_timeout_secs: self
+                .timeouts
+                .as_ref()
+                .map_or(Some(30), |policy| policy.default_secs()),
+            #[cfg(not(feature = "timeouts"))]
             request_timeout_secs: Some(30),
-            supported_versions: vec!["2.0".to_string()],
+            supported_versions: match &self.versions {
+                Some(versions) => versions.supported().to_vec(),
+                None => vec![crate::versioning::DEFAULT_VERSION.to_string()],
+            },
         }
     }
 }
@@ -317,8 +1475,7 @@ impl Handler for MethodRegistry {
     }
 
     async fn handle_notification(&self, notification: Notification) {
-        let _ = self
-            .call(&notification.method, notification.params, None)
+        self.process_notification(notification, &crate::auth::ConnectionContext::default())
             .await;
     }
 
@@ -611,6 +1768,7 @@ mod tests {
             params: None,
             id: Some(json!(1)),
             correlation_id: None,
+            ext: None,
         };
 
         let response = registry.process_message(Message::Request(request)).await;
@@ -634,6 +1792,51 @@ mod tests {
         assert!(response.is_none());
     }
 
+    #[tokio::test]
+    async fn test_registry_failed_notification_dead_lettered() {
+        use crate::builtins::{BuiltinConfig, BuiltinMethods};
+        use crate::dead_letter::{DeadLetter, DeadLetterQueue, MemoryDeadLetterSink};
+
+        let registry = MethodRegistry::new(vec![Box::new(TestMethod { name: "test" })])
+            .with_builtins(BuiltinConfig::new(BuiltinMethods::ADMIN))
+            .with_dead_letters(DeadLetterQueue::new(MemoryDeadLetterSink::new(10)).max_attempts(2));
+        assert!(registry.has_method("admin.requeue_dead_letter"));
+
+        for method in ["test", "missing"] {
+            let response = registry
+                .process_message(Message::Notification(Notification::new(method)))
+                .await;
+            assert!(response.is_none());
+        }
+
+        let response = registry
+            .call("admin.dead_letters", None, Some(json!(1)))
+            .await;
+        let letters: Vec<DeadLetter> = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].notification.method, "missing");
+        assert_eq!(letters[0].error.code, error_codes::METHOD_NOT_FOUND);
+        assert_eq!(letters[0].attempts, 2);
+
+        let response = registry
+            .call(
+                "admin.requeue_dead_letter",
+                Some(json!({"id": letters[0].id})),
+                Some(json!(2)),
+            )
+            .await;
+        assert_eq!(response.result, Some(json!({"delivered": false})));
+
+        let response = registry
+            .call(
+                "admin.requeue_dead_letter",
+                Some(json!({"id": letters[0].id})),
+                Some(json!(3)),
+            )
+            .await;
+        assert_eq!(response.error.unwrap().code, error_codes::INVALID_PARAMS);
+    }
+
     #[tokio::test]
     async fn test_registry_message_processor_response() {
         let registry = MethodRegistry::new(vec![]);
@@ -644,6 +1847,7 @@ mod tests {
             error: None,
             id: Some(json!(1)),
             correlation_id: None,
+            ext: None,
         };
 
         let response = registry
@@ -663,6 +1867,7 @@ mod tests {
                 params: None,
                 id: Some(json!(1)),
                 correlation_id: None,
+                ext: None,
             }),
             Message::Request(Request {
                 jsonrpc: "2.0".to_string(),
@@ -670,6 +1875,7 @@ mod tests {
                 params: None,
                 id: Some(json!(2)),
                 correlation_id: None,
+                ext: None,
             }),
         ];
 
@@ -677,6 +1883,712 @@ mod tests {
         assert_eq!(responses.len(), 2);
     }
 
+    #[test]
+    fn test_registry_method_metadata() {
+        use crate::method_metadata::{MetadataError, MethodDoc, MethodMetadata};
+
+        let doc = MethodDoc {
+            description: Some("From the metadata file".into()),
+            ..MethodDoc::default()
+        };
+        let registry = MethodRegistry::new(vec![Box::new(TestMethod { name: "test" })])
+            .with_method_metadata(MethodMetadata::new().method("test", doc.clone()))
+            .unwrap();
+        let spec = registry.generate_openapi_spec("api", "1.0.0");
+        assert_eq!(
+            spec.methods["test"].description.as_deref(),
+            Some("From the metadata file")
+        );
+
+        let unknown = MethodRegistry::new(vec![Box::new(TestMethod { name: "test" })])
+            .with_method_metadata(MethodMetadata::new().method("missing", doc));
+        assert!(matches!(unknown, Err(MetadataError::UnknownMethods(_))));
+    }
+
+    #[tokio::test]
+    async fn test_registry_batch_metadata_and_order() {
+        let request = |id: i64| {
+            Message::Request(Request {
+                jsonrpc: "2.0".to_string(),
+                method: "test".to_string(),
+                params: None,
+                id: Some(json!(id)),
+                correlation_id: None,
+                ext: None,
+            })
+        };
+        let messages = vec![
+            request(7),
+            Message::Notification(Notification::new("test")),
+            request(3),
+            request(5),
+        ];
+
+        let plain = MethodRegistry::new(vec![Box::new(TestMethod { name: "test" })]);
+        let responses = plain.process_batch(messages.clone()).await;
+        assert!(responses.iter().all(|r| r.ext.is_none()));
+
+        let registry = MethodRegistry::new(vec![Box::new(TestMethod { name: "test" })])
+            .with_batch_metadata(true);
+        let responses = registry.process_batch(messages).await;
+        let ids: Vec<_> = responses.iter().map(|r| r.id.clone().unwrap()).collect();
+        assert_eq!(ids, vec![json!(7), json!(3), json!(5)]);
+        let indexes: Vec<_> = responses
+            .iter()
+            .map(|r| r.ext.as_ref().unwrap().batch.unwrap().index)
+            .collect();
+        assert_eq!(indexes, vec![0, 2, 3]);
+
+        let wire = serde_json::to_value(&responses[1]).unwrap();
+        assert_eq!(wire["ext"]["batch"]["index"], json!(2));
+        assert!(wire["ext"]["batch"]["duration_us"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn test_registry_params_validation() {
+        let schema = json!({"type": "object", "required": ["name"],
+                            "properties": {"name": {"type": "string"}}});
+        let registry = MethodRegistry::new(vec![
+            Box::new(TestMethod { name: "greet" }),
+            Box::new(TestMethod { name: "free" }),
+        ])
+        .with_params_validation(
+            crate::validation::ParamsValidator::new().method("greet", schema.clone()),
+        );
+
+        let ok = registry
+            .call("greet", Some(json!({"name": "ada"})), None)
+            .await;
+        assert!(ok.result.is_some());
+        assert!(registry.call("free", None, None).await.result.is_some());
+
+        let rejected = registry.call("greet", Some(json!({"name": 7})), None).await;
+        let error = rejected.error.unwrap();
+        assert_eq!(error.code, error_codes::INVALID_PARAMS);
+        assert_eq!(error.data.unwrap()["violations"][0]["path"], json!("/name"));
+        let missing = registry.call("greet", None, None).await;
+        assert!(missing.error.is_some());
+
+        let spec = registry.generate_openapi_spec("test", "1.0");
+        assert_eq!(spec.methods["greet"].parameters, Some(schema));
+        assert!(spec.methods["free"].parameters.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_registry_batch_abort_on_error() {
+        use crate::batch_policy::BatchPolicy;
+
+        let batch = |first: RequestBuilder| {
+            vec![
+                Message::Request(first.id(json!(1)).build()),
+                Message::Request(RequestBuilder::new("missing").id(json!(2)).build()),
+                Message::Request(RequestBuilder::new("test").id(json!(3)).build()),
+            ]
+        };
+        let codes = |responses: Vec<Response>| -> Vec<_> {
+            responses
+                .iter()
+                .map(|r| r.error.as_ref().map(|e| e.code))
+                .collect()
+        };
+        let registry = MethodRegistry::new(vec![Box::new(TestMethod { name: "test" })]);
+
+        let responses = registry
+            .process_batch(batch(RequestBuilder::new("test")))
+            .await;
+        assert_eq!(
+            codes(responses),
+            [None, Some(error_codes::METHOD_NOT_FOUND), None]
+        );
+
+        let requested = RequestBuilder::new("test").batch_policy(BatchPolicy::AbortOnError);
+        let responses = registry.process_batch(batch(requested)).await;
+        assert_eq!(
+            responses[2].error.as_ref().unwrap().data,
+            Some(json!({"failed_index": 1}))
+        );
+        assert_eq!(
+            codes(responses),
+            [
+                None,
+                Some(error_codes::METHOD_NOT_FOUND),
+                Some(error_codes::DEPENDENT_FAILURE)
+            ]
+        );
+
+        let transactional = RequestBuilder::new("test").batch_policy(BatchPolicy::Transactional);
+        let responses = registry.process_batch(batch(transactional)).await;
+        assert_eq!(codes(responses), [Some(error_codes::INVALID_REQUEST)]);
+
+        let refused = MethodRegistry::empty()
+            .with_batch_policy(BatchPolicy::Transactional)
+            .err();
+        assert_eq!(
+            refused,
+            Some(crate::batch_policy::UnsupportedPolicy(
+                BatchPolicy::Transactional
+            ))
+        );
+        assert!(
+            MethodRegistry::empty()
+                .with_batch_policy(BatchPolicy::AbortOnError)
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_registry_concurrent_batch() {
+        use crate::batch_policy::{BatchConcurrency, BatchOrdering};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        #[derive(Default)]
+        struct Sleep {
+            running: AtomicUsize,
+            peak: AtomicUsize,
+        }
+
+        #[async_trait::async_trait]
+        impl JsonRPCMethod for Arc<Sleep> {
+            fn method_name(&self) -> &'static str {
+                "sleep"
+            }
+
+            async fn call(
+                &self,
+                params: Option<serde_json::Value>,
+                id: Option<RequestId>,
+            ) -> Response {
+                let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+                self.peak.fetch_max(running, Ordering::SeqCst);
+                let millis = params.and_then(|p| p.as_u64()).unwrap_or(0);
+                tokio::time::sleep(std::time::Duration::from_millis(millis)).await;
+                self.running.fetch_sub(1, Ordering::SeqCst);
+                crate::rpc_success!(millis, id)
+            }
+        }
+
+        let batch = || -> Vec<Message> {
+            [(1, 120), (2, 0), (3, 60), (4, 0)]
+                .into_iter()
+                .map(|(id, millis)| {
+                    Message::Request(
+                        RequestBuilder::new("sleep")
+                            .params(json!(millis))
+                            .id(json!(id))
+                            .build(),
+                    )
+                })
+                .collect()
+        };
+        let ids = |responses: Vec<Response>| -> Vec<_> {
+            responses.into_iter().map(|r| r.id.unwrap()).collect()
+        };
+
+        let sleep = Arc::new(Sleep::default());
+        let registry = MethodRegistry::new(register_methods![Arc::clone(&sleep)])
+            .with_batch_concurrency(BatchConcurrency::new(3));
+        let responses = registry.process_batch(batch()).await;
+        assert_eq!(ids(responses), [json!(1), json!(2), json!(3), json!(4)]);
+        assert_eq!(sleep.peak.load(Ordering::SeqCst), 3);
+
+        let sleep = Arc::new(Sleep::default());
+        let registry = MethodRegistry::new(register_methods![Arc::clone(&sleep)])
+            .with_batch_concurrency(BatchConcurrency::new(2).ordering(BatchOrdering::Completion));
+        let responses = registry.process_batch(batch()).await;
+        assert_eq!(ids(responses), [json!(2), json!(3), json!(4), json!(1)]);
+        assert_eq!(sleep.peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[cfg(feature = "healthcheck")]
+    #[tokio::test]
+    async fn test_registry_with_builtins() {
+        use crate::builtins::{BuiltinConfig, BuiltinMethods};
+
+        let registry = MethodRegistry::empty().with_builtins(
+            BuiltinConfig::new(BuiltinMethods::HEALTHCHECK)
+                .namespace(BuiltinMethods::HEALTHCHECK, "sys"),
+        );
+        assert!(registry.has_method("sys.healthcheck"));
+        assert!(!registry.has_method("healthcheck"));
+        assert!(registry.builtins().is_enabled(BuiltinMethods::HEALTHCHECK));
+
+        let response = registry.call("sys.healthcheck", None, Some(json!(1))).await;
+        assert!(response.is_success());
+
+        let registry = MethodRegistry::empty().with_builtins(BuiltinConfig::none());
+        assert_eq!(registry.method_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_registry_capabilities_builtin() {
+        use crate::builtins::{BuiltinConfig, BuiltinMethods};
+
+        let registry =
+            MethodRegistry::empty().with_builtins(BuiltinConfig::new(BuiltinMethods::DISCOVERY));
+        assert!(
+            registry
+                .get_methods()
+                .contains(&"rpc.capabilities".to_string())
+        );
+
+        let response = registry
+            .call("rpc.capabilities", None, Some(json!(1)))
+            .await;
+        let capabilities: ProcessorCapabilities =
+            serde_json::from_value(response.result.unwrap()).unwrap();
+        assert_eq!(capabilities.max_batch_size, Some(100));
+        assert_eq!(capabilities.max_request_size, Some(1024 * 1024));
+
+        assert!(!MethodRegistry::empty().has_method("rpc.capabilities"));
+    }
+
+    #[tokio::test]
+    async fn test_declared_security_enforced_and_documented() {
+        use crate::auth::{ConnectionContext, Principal};
+        use crate::method_security::MethodSecurity;
+        use crate::rate_limit::{Limit, RateLimitPolicy};
+
+        let registry = MethodRegistry::new(register_methods![
+            VersionMethod,
+            TestMethod { name: "test" }
+        ])
+        .with_method_security(
+            "version",
+            MethodSecurity::new().scope("meta:read").rate_class("cheap"),
+        )
+        .with_rate_limit(RateLimitPolicy::new().class(
+            "cheap",
+            Limit::sliding_window(1, std::time::Duration::from_secs(60)),
+        ));
+        let anonymous = ConnectionContext::new();
+        let mut reader = ConnectionContext::new();
+        reader.set_principal(Principal::new("ops").scope("meta:read"));
+
+        assert!(registry.static_response("version", None).is_none());
+        let denied = registry
+            .call_with_context("version", None, Some(json!(1)), &anonymous)
+            .await;
+        assert!(denied.is_error());
+        let allowed = registry
+            .call_with_context("version", None, Some(json!(2)), &reader)
+            .await;
+        assert!(allowed.is_success());
+        let limited = registry
+            .call_with_context("version", None, Some(json!(3)), &reader)
+            .await;
+        assert_eq!(limited.error.unwrap().code, error_codes::RETRY_LATER);
+        // methods outside the class are not counted
+        for id in 4..6 {
+            let response = registry
+                .call_with_context("test", None, Some(json!(id)), &anonymous)
+                .await;
+            assert!(response.is_success());
+        }
+
+        let spec = registry.generate_openapi_spec("meta", "1.0.0");
+        assert_eq!(
+            spec.methods["version"].extensions["x-security"],
+            json!({"scopes": ["meta:read"], "rateClass": "cheap"})
+        );
+        assert!(spec.methods["test"].extensions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_registry_introspection_builtins() {
+        use crate::builtins::{BuiltinConfig, BuiltinMethods};
+
+        let registry = MethodRegistry::new(register_methods![VersionMethod]).with_builtins(
+            BuiltinConfig::new(BuiltinMethods::DISCOVERY)
+                .namespace(BuiltinMethods::DISCOVERY, "meta")
+                .service_info("Versions", "3.0.0"),
+        );
+        assert!(registry.has_method("meta.discover"));
+        assert!(!registry.has_method("rpc.discover"));
+
+        let response = registry.call("meta.methods", None, Some(json!(1))).await;
+        let names: Vec<String> = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert_eq!(names.len(), registry.method_count());
+        assert!(names.is_sorted());
+        assert!(names.contains(&"version".to_string()));
+
+        let response = registry.call("meta.discover", None, Some(json!(2))).await;
+        let doc = response.result.unwrap();
+        assert_eq!(doc["openrpc"], crate::openrpc::OPENRPC_VERSION);
+        assert_eq!(
+            doc["info"],
+            json!({"title": "Versions", "version": "3.0.0"})
+        );
+        let described: Vec<&str> = doc["methods"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|method| method["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(described, names);
+    }
+
+    #[tokio::test]
+    async fn test_registry_cancellation_builtins() {
+        use crate::builtins::{BuiltinConfig, BuiltinMethods};
+
+        struct Hang;
+
+        #[async_trait::async_trait]
+        impl JsonRPCMethod for Hang {
+            fn method_name(&self) -> &'static str {
+                "hang"
+            }
+
+            async fn call(
+                &self,
+                _params: Option<serde_json::Value>,
+                _id: Option<RequestId>,
+            ) -> Response {
+                unreachable!("called with context")
+            }
+
+            async fn call_with_context(
+                &self,
+                _params: Option<serde_json::Value>,
+                _id: Option<RequestId>,
+                ctx: &CallContext<'_>,
+            ) -> Response {
+                ctx.cancellation().unwrap().cancelled().await;
+                unreachable!("dropped once cancelled")
+            }
+        }
+
+        let registry = MethodRegistry::new(register_methods![Hang])
+            .with_builtins(BuiltinConfig::new(BuiltinMethods::CANCELLATION));
+        assert!(registry.has_method("rpc.cancel") && registry.has_method("$/cancelRequest"));
+        let ctx = crate::auth::ConnectionContext::with_addr("127.0.0.1:4000".parse().unwrap());
+
+        let (hung, cancel) = tokio::join!(
+            registry.call_with_context("hang", None, Some(json!(7)), &ctx),
+            async {
+                tokio::task::yield_now().await;
+                registry
+                    .call_with_context("rpc.cancel", Some(json!({"id": 7})), Some(json!(8)), &ctx)
+                    .await
+            }
+        );
+        assert_eq!(cancel.result, Some(json!(true)));
+        assert_eq!(hung.id, Some(json!(7)));
+        assert_eq!(hung.error.unwrap().code, error_codes::REQUEST_CANCELLED);
+
+        let (hung, _) = tokio::join!(
+            registry.call_with_context("hang", None, Some(json!(9)), &ctx),
+            async {
+                tokio::task::yield_now().await;
+                let notification = Message::Notification(
+                    Notification::new("$/cancelRequest").with_params(json!({"id": 9})),
+                );
+                registry
+                    .process_message_with_context(notification, &ctx)
+                    .await
+            }
+        );
+        assert_eq!(hung.error.unwrap().code, error_codes::REQUEST_CANCELLED);
+
+        let response = registry
+            .call("rpc.cancel", Some(json!({"id": 7})), Some(json!(10)))
+            .await;
+        assert_eq!(response.result, Some(json!(false)));
+    }
+
+    #[cfg(feature = "timeouts")]
+    #[tokio::test]
+    async fn test_registry_timeouts() {
+        use crate::timeout::TimeoutPolicy;
+        use std::time::{Duration, Instant};
+
+        struct Sleep;
+
+        #[async_trait::async_trait]
+        impl JsonRPCMethod for Sleep {
+            fn method_name(&self) -> &'static str {
+                "sleep"
+            }
+
+            async fn call(
+                &self,
+                _params: Option<serde_json::Value>,
+                _id: Option<RequestId>,
+            ) -> Response {
+                unreachable!("called with context")
+            }
+
+            async fn call_with_context(
+                &self,
+                params: Option<serde_json::Value>,
+                id: Option<RequestId>,
+                ctx: &CallContext<'_>,
+            ) -> Response {
+                let millis = params.and_then(|p| p.as_u64()).unwrap_or(0);
+                tokio::time::sleep(Duration::from_millis(millis)).await;
+                let remaining = ctx
+                    .connection
+                    .remaining()
+                    .map(|left| left.as_millis() as u64);
+                crate::rpc_success!(remaining, id)
+            }
+        }
+
+        let registry = MethodRegistry::new(register_methods![Sleep]).with_timeouts(
+            TimeoutPolicy::new()
+                .default_timeout(Duration::from_millis(1500))
+                .method("sleep", Duration::from_millis(50)),
+        );
+        assert_eq!(registry.get_capabilities().request_timeout_secs, Some(2));
+
+        let response = registry.call("sleep", Some(json!(0)), Some(json!(1))).await;
+        assert!(response.result.unwrap().as_u64().unwrap() <= 50);
+
+        let response = registry
+            .call("sleep", Some(json!(5000)), Some(json!(2)))
+            .await;
+        let error = response.error.unwrap();
+        assert_eq!(error.code, error_codes::REQUEST_TIMEOUT);
+        assert_eq!(error.data, Some(json!({"timeout_ms": 50})));
+        assert_eq!(response.id, Some(json!(2)));
+
+        // a caller's deadline shortens the method's own timeout
+        let ctx = crate::auth::ConnectionContext::new()
+            .with_deadline(Instant::now() + Duration::from_millis(10));
+        let response = registry
+            .call_with_context("sleep", Some(json!(0)), Some(json!(3)), &ctx)
+            .await;
+        assert!(response.result.unwrap().as_u64().unwrap() <= 10);
+        let ctx = crate::auth::ConnectionContext::new().with_deadline(Instant::now());
+        let response = registry
+            .call_with_context("sleep", Some(json!(0)), Some(json!(4)), &ctx)
+            .await;
+        assert_eq!(response.error.unwrap().data, Some(json!({"timeout_ms": 0})));
+    }
+
+    struct VersionMethod;
+
+    #[async_trait::async_trait]
+    impl JsonRPCMethod for VersionMethod {
+        fn method_name(&self) -> &'static str {
+            "version"
+        }
+
+        async fn call(
+            &self,
+            _params: Option<serde_json::Value>,
+            id: Option<RequestId>,
+        ) -> Response {
+            Response::success(json!({"version": "1.2.3"}), id)
+        }
+
+        fn static_result(&self) -> Option<serde_json::Value> {
+            Some(json!({"version": "1.2.3"}))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_static_response_matches_dispatch() {
+        let registry = MethodRegistry::new(register_methods![VersionMethod]);
+        let id = serde_json::value::to_raw_value(&json!(5)).unwrap();
+
+        let cached = registry.static_response("version", Some(&id)).unwrap();
+        let dispatched = registry.call("version", None, Some(json!(5))).await;
+        assert_eq!(cached, serde_json::to_string(&dispatched).unwrap());
+
+        assert!(registry.static_response("unknown", None).is_none());
+
+        let guarded =
+            MethodRegistry::new(register_methods![VersionMethod]).with_auth(TestAuthPolicy {
+                allowed_methods: vec!["version".to_string()],
+            });
+        assert!(guarded.static_response("version", None).is_none());
+    }
+
+    #[test]
+    fn test_prepare_uses_static_response() {
+        use crate::borrowed::{Prepared, prepare};
+
+        let registry = MethodRegistry::new(register_methods![VersionMethod]);
+        let cached = prepare(
+            r#"{"jsonrpc":"2.0","method":"version","id":"x"}"#,
+            &registry,
+        );
+        match cached.unwrap() {
+            Prepared::Cached(json) => assert!(json.ends_with(r#""id":"x"}"#)),
+            other => panic!("expected cached response, got {other:?}"),
+        }
+
+        let with_params = prepare(
+            r#"{"jsonrpc":"2.0","method":"version","params":[],"id":1}"#,
+            &registry,
+        );
+        assert!(matches!(with_params.unwrap(), Prepared::Message(_)));
+    }
+
+    #[tokio::test]
+    async fn test_prepare_dispatches_other_versions() {
+        use crate::borrowed::{Prepared, prepare};
+
+        struct NextVersion;
+
+        #[async_trait::async_trait]
+        impl JsonRPCMethod for NextVersion {
+            fn method_name(&self) -> &'static str {
+                "version"
+            }
+
+            async fn call(
+                &self,
+                _params: Option<serde_json::Value>,
+                id: Option<RequestId>,
+            ) -> Response {
+                Response::success(json!({"version": "2.0.0"}), id)
+            }
+        }
+
+        async fn answer(registry: &MethodRegistry, frame: &str) -> Response {
+            let Prepared::Message(message) = prepare(frame, registry).unwrap() else {
+                panic!("{frame} must be dispatched");
+            };
+            registry.process_message(message).await.unwrap()
+        }
+
+        let registry = MethodRegistry::new(register_methods![VersionMethod]);
+        let unsupported = answer(&registry, r#"{"jsonrpc":"1.0","method":"version","id":1}"#).await;
+        assert!(unsupported.error.is_some());
+
+        let registry = MethodRegistry::new(register_methods![VersionMethod])
+            .with_protocol_versions(
+                crate::versioning::ProtocolVersions::new().method("2.1", Box::new(NextVersion)),
+            );
+        let next = answer(&registry, r#"{"jsonrpc":"2.1","method":"version","id":2}"#).await;
+        assert_eq!(next.jsonrpc, "2.1");
+        assert_eq!(next.result, Some(json!({"version": "2.0.0"})));
+        assert!(matches!(
+            prepare(r#"{"jsonrpc":"2.0","method":"version","id":3}"#, &registry).unwrap(),
+            Prepared::Cached(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_prepare_counts_rate_limited_calls() {
+        use crate::borrowed::{Prepared, prepare};
+        use crate::rate_limit::{Limit, RateLimitPolicy};
+
+        let registry = MethodRegistry::new(register_methods![VersionMethod]).with_rate_limit(
+            RateLimitPolicy::new()
+                .global(Limit::sliding_window(1, std::time::Duration::from_secs(60))),
+        );
+        let mut codes = Vec::new();
+        for id in 1..=3 {
+            let frame = format!(r#"{{"jsonrpc":"2.0","method":"version","id":{id}}}"#);
+            let Prepared::Message(message) = prepare(&frame, &registry).unwrap() else {
+                panic!("rate limited calls must not be answered from the cache");
+            };
+            let response = registry.process_message(message).await.unwrap();
+            codes.push(response.error.map(|error| error.code));
+        }
+        assert_eq!(
+            codes,
+            vec![
+                None,
+                Some(error_codes::RETRY_LATER),
+                Some(error_codes::RETRY_LATER)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay_guard_drops_replayed_notification() {
+        struct Counter(Arc<std::sync::atomic::AtomicUsize>);
+
+        #[async_trait::async_trait]
+        impl JsonRPCMethod for Counter {
+            fn method_name(&self) -> &'static str {
+                "debit"
+            }
+
+            async fn call(
+                &self,
+                params: Option<serde_json::Value>,
+                id: Option<RequestId>,
+            ) -> Response {
+                assert_eq!(params, Some(json!({"amount": 3})));
+                self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                crate::rpc_success!(true, id)
+            }
+        }
+
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let registry = MethodRegistry::new(vec![Box::new(Counter(calls.clone()))])
+            .with_replay_guard(
+                crate::replay::ReplayGuard::new(std::time::Duration::from_secs(60))
+                    .protect("debit"),
+            );
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let notification = Notification::new("debit")
+            .with_params(json!({"nonce": "abc", "timestamp": now, "payload": {"amount": 3}}));
+        for _ in 0..2 {
+            let response = registry
+                .process_message(Message::Notification(notification.clone()))
+                .await;
+            assert!(response.is_none());
+        }
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        let response = registry
+            .call("debit", Some(json!({"amount": 3})), Some(json!(1)))
+            .await;
+        assert_eq!(response.error.unwrap().code, error_codes::INVALID_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_strict_numbers() {
+        let registry = MethodRegistry::new(register_methods![TestMethod { name: "m" }])
+            .with_strict_numbers(true);
+
+        let response = registry
+            .call(
+                "m",
+                Some(json!({"amount": 9007199254740993u64})),
+                Some(json!(1)),
+            )
+            .await;
+        let error = response.error.unwrap();
+        assert_eq!(error.code, error_codes::INVALID_PARAMS);
+        assert!(error.message.contains("/amount"));
+
+        let response = registry
+            .call(
+                "m",
+                Some(json!({"amount": "9007199254740993"})),
+                Some(json!(1)),
+            )
+            .await;
+        assert!(response.is_success());
+
+        let spec = registry.generate_openapi_spec("test", "1.0");
+        assert!(spec.extensions.contains_key("x-json-numbers"));
+        assert!(spec.components.schemas.contains_key("Int64String"));
+
+        let lenient = MethodRegistry::new(register_methods![TestMethod { name: "m" }]);
+        let response = lenient
+            .call("m", Some(json!([u64::MAX])), Some(json!(1)))
+            .await;
+        assert!(response.is_success());
+        assert!(
+            !lenient
+                .generate_openapi_spec("t", "1")
+                .extensions
+                .contains_key("x-json-numbers")
+        );
+    }
+
     #[test]
     fn test_register_methods_macro() {
         let methods = register_methods![TestMethod { name: "m1" }, TestMethod { name: "m2" },];
@@ -0,0 +1,265 @@
+//! Unified rejection reporting
+//!
+//! Requests can be turned away at many points before a handler runs: the
+//! transport enforces connection and size limits, parsing can fail, the
+//! registry consults the auth policy, listeners filter methods. Every such
+//! enforcement point reports a [`Rejection`] carrying a [`RejectionReason`]
+//! through [`record`], which emits a structured log line with a `rejection`
+//! field and forwards the event to all registered [`RejectionObserver`]s.
+//!
+//! Observers are process-wide so that metrics and audit sinks see rejections
+//! from every transport and registry without extra wiring:
+//!
+//! ```rust
+//! use ash_rpc::rejection::{self, Rejection, RejectionObserver, RejectionReason};
+//! use std::sync::Arc;
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//!
+//! #[derive(Default)]
+//! struct Counter(AtomicUsize);
+//!
+//! impl RejectionObserver for Counter {
+//!     fn on_rejection(&self, _rejection: &Rejection) {
+//!         self.0.fetch_add(1, Ordering::Relaxed);
+//!     }
+//! }
+//!
+//! let counter = Arc::new(Counter::default());
+//! rejection::add_observer(counter.clone());
+//! rejection::record(Rejection::new(RejectionReason::ParseError));
+//! assert!(counter.0.load(Ordering::Relaxed) >= 1);
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+
+/// Why a request or connection was turned away
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectionReason {
+    /// Payload was not valid JSON-RPC
+    ParseError,
+    /// Request exceeded `max_request_size`
+    RequestTooLarge,
+    /// Payload exceeded `max_json_depth` or `max_json_nodes`
+    JsonTooComplex,
+    /// Batch exceeded `max_batch_size`
+    BatchTooLarge,
+    /// Server was at `max_connections`
+    ConnectionLimit,
+    /// Rate limit exceeded
+    RateLimited,
+    /// Auth policy denied the call
+    Unauthorized,
+    /// Method is not permitted on the listener
+    MethodNotPermitted,
+    /// Request did not arrive or finish in time
+    Timeout,
+    /// TLS handshake failed
+    TlsHandshake,
+    /// Replay guard refused a nonce or timestamp
+    Replayed,
+    /// Connection would have waited too long behind the accept rate limit
+    AcceptQueueFull,
+    /// Broadcast dropped by an outbound stream rate limit
+    OutboundThrottled,
+    /// Connection handshake refused the credentials
+    AuthenticationFailed,
+}
+
+impl RejectionReason {
+    /// Stable snake_case name used in logs, metric labels and audit metadata
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RejectionReason::ParseError => "parse_error",
+            RejectionReason::RequestTooLarge => "request_too_large",
+            RejectionReason::JsonTooComplex => "json_too_complex",
+            RejectionReason::BatchTooLarge => "batch_too_large",
+            RejectionReason::ConnectionLimit => "connection_limit",
+            RejectionReason::RateLimited => "rate_limited",
+            RejectionReason::Unauthorized => "unauthorized",
+            RejectionReason::MethodNotPermitted => "method_not_permitted",
+            RejectionReason::Timeout => "timeout",
+            RejectionReason::TlsHandshake => "tls_handshake",
+            RejectionReason::Replayed => "replayed",
+            RejectionReason::AcceptQueueFull => "accept_queue_full",
+            RejectionReason::OutboundThrottled => "outbound_throttled",
+            RejectionReason::AuthenticationFailed => "authentication_failed",
+        }
+    }
+
+    /// Whether the rejection stems from a security control rather than a
+    /// malformed or slow client
+    pub fn is_security_relevant(&self) -> bool {
+        matches!(
+            self,
+            RejectionReason::Unauthorized
+                | RejectionReason::AuthenticationFailed
+                | RejectionReason::MethodNotPermitted
+                | RejectionReason::RateLimited
+                | RejectionReason::ConnectionLimit
+                | RejectionReason::RequestTooLarge
+                | RejectionReason::JsonTooComplex
+                | RejectionReason::BatchTooLarge
+                | RejectionReason::Replayed
+        )
+    }
+}
+
+impl fmt::Display for RejectionReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A single rejection event
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rejection {
+    pub reason: RejectionReason,
+    /// Method name, when the request got far enough to have one
+    pub method: Option<String>,
+    pub remote_addr: Option<SocketAddr>,
+    /// Listener name, see `ConnectionContext::origin`
+    pub origin: Option<String>,
+    /// Caller id, see `ConnectionContext::principal_id`
+    pub principal: Option<String>,
+    /// Free-form detail such as the offending size
+    pub detail: Option<String>,
+}
+
+impl Rejection {
+    pub fn new(reason: RejectionReason) -> Self {
+        Self {
+            reason,
+            method: None,
+            remote_addr: None,
+            origin: None,
+            principal: None,
+            detail: None,
+        }
+    }
+
+    pub fn method(mut self, method: impl Into<String>) -> Self {
+        self.method = Some(method.into());
+        self
+    }
+
+    pub fn remote_addr(mut self, addr: Option<SocketAddr>) -> Self {
+        self.remote_addr = addr;
+        self
+    }
+
+    pub fn origin(mut self, origin: Option<String>) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    pub fn principal(mut self, principal: Option<&str>) -> Self {
+        self.principal = principal.map(str::to_string);
+        self
+    }
+
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+/// Receives every recorded rejection
+pub trait RejectionObserver: Send + Sync {
+    fn on_rejection(&self, rejection: &Rejection);
+}
+
+static OBSERVERS: RwLock<Vec<Arc<dyn RejectionObserver>>> = RwLock::new(Vec::new());
+
+/// Register a process-wide rejection observer
+pub fn add_observer(observer: Arc<dyn RejectionObserver>) {
+    if let Ok(mut observers) = OBSERVERS.write() {
+        observers.push(observer);
+    }
+}
+
+/// Remove all registered observers
+pub fn clear_observers() {
+    if let Ok(mut observers) = OBSERVERS.write() {
+        observers.clear();
+    }
+}
+
+/// Log a rejection and notify observers
+pub fn record(rejection: Rejection) {
+    tracing::warn!(
+        rejection = rejection.reason.as_str(),
+        method = ?rejection.method,
+        remote_addr = ?rejection.remote_addr,
+        listener = ?rejection.origin,
+        principal = ?rejection.principal,
+        detail = ?rejection.detail,
+        "request rejected"
+    );
+
+    let observers = match OBSERVERS.read() {
+        Ok(observers) => observers.clone(),
+        Err(_) => return,
+    };
+    for observer in observers {
+        observer.on_rejection(&rejection);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct Collect(Mutex<Vec<Rejection>>);
+
+    impl RejectionObserver for Collect {
+        fn on_rejection(&self, rejection: &Rejection) {
+            self.0.lock().unwrap().push(rejection.clone());
+        }
+    }
+
+    #[test]
+    fn test_reason_names_match_serde() {
+        for reason in [
+            RejectionReason::ParseError,
+            RejectionReason::RequestTooLarge,
+            RejectionReason::MethodNotPermitted,
+            RejectionReason::TlsHandshake,
+            RejectionReason::AcceptQueueFull,
+            RejectionReason::OutboundThrottled,
+            RejectionReason::AuthenticationFailed,
+        ] {
+            let json = serde_json::to_value(reason).unwrap();
+            assert_eq!(json, reason.as_str());
+        }
+    }
+
+    #[test]
+    fn test_record_notifies_observers() {
+        let collect = Arc::new(Collect(Mutex::new(Vec::new())));
+        add_observer(collect.clone());
+
+        record(
+            Rejection::new(RejectionReason::Unauthorized)
+                .method("rejection_test_method")
+                .detail("denied"),
+        );
+
+        let seen = collect.0.lock().unwrap();
+        assert!(
+            seen.iter()
+                .any(|r| r.reason == RejectionReason::Unauthorized
+                    && r.method.as_deref() == Some("rejection_test_method"))
+        );
+    }
+
+    #[test]
+    fn test_security_relevance() {
+        assert!(RejectionReason::Unauthorized.is_security_relevant());
+        assert!(!RejectionReason::ParseError.is_security_relevant());
+    }
+}
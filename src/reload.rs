@@ -0,0 +1,452 @@
+//! Hot configuration reload.
+//!
+//! [`Reloadable`] is a shared handle to a value that can be swapped at
+//! runtime; readers take a cheap snapshot with [`Reloadable::load`]. TCP
+//! servers keep their `SecurityConfig` in such a handle: new connections
+//! pick up the current limits while established connections keep the
+//! settings they were accepted with.
+//!
+//! [`ConfigReloader`] reads a JSON config file and hands each top-level
+//! section to the handler registered for it. A reload is all-or-nothing:
+//! every present section is parsed and validated first, and only when all of
+//! them succeed are the changes applied. Reloads are triggered by calling
+//! [`ConfigReloader::reload`], by `SIGHUP` ([`spawn_sighup_listener`]) or
+//! through the `admin.reload_config` method ([`ConfigReloader::admin_method`]).
+//!
+//! ```json
+//! {
+//!   "security": { "max_connections": 500, "request_timeout_secs": 10 },
+//!   "log_level": "debug",
+//!   "auth": { "allowed_keys": ["..."] }
+//! }
+//! ```
+//!
+//! ```rust
+//! use ash_rpc::reload::{ConfigReloader, Reloadable};
+//! use ash_rpc::SecurityConfig;
+//!
+//! let security = Reloadable::new(SecurityConfig::default());
+//! let allowed_keys = Reloadable::new(Vec::<String>::new());
+//!
+//! let keys = allowed_keys.clone();
+//! let reloader = ConfigReloader::new("/etc/my-service/rpc.json")
+//!     .security(security.clone())
+//!     .section("auth_keys", move |new_keys: Vec<String>| keys.store(new_keys))
+//!     .log_level(|level| println!("log level is now {level}"));
+//! # let _ = reloader;
+//! ```
+
+use crate::transports::SecurityConfig;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Shared, atomically replaceable value
+pub struct Reloadable<T> {
+    inner: Arc<RwLock<Arc<T>>>,
+}
+
+impl<T> Reloadable<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(Arc::new(value))),
+        }
+    }
+
+    /// Snapshot of the current value
+    pub fn load(&self) -> Arc<T> {
+        match self.inner.read() {
+            Ok(guard) => Arc::clone(&guard),
+            Err(poisoned) => Arc::clone(&poisoned.into_inner()),
+        }
+    }
+
+    /// Replace the value; existing snapshots are unaffected
+    pub fn store(&self, value: T) {
+        let value = Arc::new(value);
+        match self.inner.write() {
+            Ok(mut guard) => *guard = value,
+            Err(poisoned) => *poisoned.into_inner() = value,
+        }
+    }
+}
+
+impl<T> Clone for Reloadable<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Reloadable<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Reloadable").field(&self.load()).finish()
+    }
+}
+
+/// Why a reload was rejected; nothing has been applied
+#[derive(Debug)]
+pub enum ReloadError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+    Invalid { section: String, message: String },
+    Secret(crate::secrets::SecretError),
+}
+
+impl fmt::Display for ReloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReloadError::Io(e) => write!(f, "failed to read config: {e}"),
+            ReloadError::Parse(e) => write!(f, "failed to parse config: {e}"),
+            ReloadError::Invalid { section, message } => {
+                write!(f, "invalid '{section}' section: {message}")
+            }
+            ReloadError::Secret(e) => write!(f, "failed to resolve secrets: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ReloadError {}
+
+type Apply = Box<dyn FnOnce() + Send>;
+type SectionHandler = Box<dyn Fn(&serde_json::Value) -> Result<Apply, String> + Send + Sync>;
+
+/// Reloads config file sections into running components
+pub struct ConfigReloader {
+    path: PathBuf,
+    sections: Vec<(String, SectionHandler)>,
+    secrets: Option<crate::secrets::SecretResolver>,
+}
+
+impl ConfigReloader {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            sections: Vec::new(),
+            secrets: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Resolve `${secret:name}` placeholders in every section before it is
+    /// parsed; a missing secret fails the whole reload
+    pub fn secrets(mut self, resolver: crate::secrets::SecretResolver) -> Self {
+        self.secrets = Some(resolver);
+        self
+    }
+
+    /// Deserialize section `name` and pass it to `apply` on reload
+    pub fn section<T, F>(self, name: impl Into<String>, apply: F) -> Self
+    where
+        T: DeserializeOwned + Send + 'static,
+        F: Fn(T) + Send + Sync + 'static,
+    {
+        self.validated_section(name, |_: &T| Ok(()), apply)
+    }
+
+    /// Like [`section`](Self::section), rejecting the whole reload when
+    /// `validate` fails
+    pub fn validated_section<T, V, F>(
+        mut self,
+        name: impl Into<String>,
+        validate: V,
+        apply: F,
+    ) -> Self
+    where
+        T: DeserializeOwned + Send + 'static,
+        V: Fn(&T) -> Result<(), String> + Send + Sync + 'static,
+        F: Fn(T) + Send + Sync + 'static,
+    {
+        let apply = Arc::new(apply);
+        let handler: SectionHandler = Box::new(move |value| {
+            let parsed: T = serde_json::from_value(value.clone()).map_err(|e| e.to_string())?;
+            validate(&parsed)?;
+            let apply = Arc::clone(&apply);
+            Ok(Box::new(move || apply(parsed)))
+        });
+        self.sections.push((name.into(), handler));
+        self
+    }
+
+    /// Reload the `security` section into a transport's security handle.
+    ///
+    /// Fields missing from the section keep their current value.
+    pub fn security(self, handle: Reloadable<SecurityConfig>) -> Self {
+        let current = handle.clone();
+        let build = move |section: &SecuritySection| section.apply_to(&current.load());
+        let check = build.clone();
+        self.validated_section(
+            "security",
+            move |section: &SecuritySection| {
+                let mut report = crate::transports::ConfigReport::new();
+                crate::transports::validation::check_security_config(&check(section), &mut report);
+                report
+                    .into_result()
+                    .map(|_| ())
+                    .map_err(|report| report.to_string())
+            },
+            move |section: SecuritySection| handle.store(build(&section)),
+        )
+    }
+
+    /// Reload the `log_level` section, e.g. into a `tracing_subscriber`
+    /// reload handle
+    pub fn log_level<F>(self, apply: F) -> Self
+    where
+        F: Fn(String) + Send + Sync + 'static,
+    {
+        self.section("log_level", apply)
+    }
+
+    /// Read the config file and apply every known section atomically.
+    ///
+    /// Returns the names of the applied sections. Unknown sections are
+    /// ignored so one file can be shared with other components.
+    pub fn reload(&self) -> Result<Vec<String>, ReloadError> {
+        let text = std::fs::read_to_string(&self.path).map_err(ReloadError::Io)?;
+        self.reload_from_str(&text)
+    }
+
+    /// [`reload`](Self::reload) from in-memory JSON
+    pub fn reload_from_str(&self, text: &str) -> Result<Vec<String>, ReloadError> {
+        let mut config: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(text).map_err(ReloadError::Parse)?;
+        if let Some(resolver) = &self.secrets {
+            for value in config.values_mut() {
+                resolver.resolve_value(value).map_err(ReloadError::Secret)?;
+            }
+        }
+
+        let mut pending = Vec::new();
+        for (name, handler) in &self.sections {
+            if let Some(value) = config.get(name) {
+                let apply = handler(value).map_err(|message| ReloadError::Invalid {
+                    section: name.clone(),
+                    message,
+                })?;
+                pending.push((name.clone(), apply));
+            }
+        }
+
+        let mut applied = Vec::with_capacity(pending.len());
+        for (name, apply) in pending {
+            apply();
+            applied.push(name);
+        }
+        tracing::info!(path = %self.path.display(), sections = ?applied, "configuration reloaded");
+        Ok(applied)
+    }
+
+    /// `admin.reload_config` method bound to this reloader, named according
+    /// to the registry's admin namespace
+    pub fn admin_method(
+        self: &Arc<Self>,
+        builtins: &crate::builtins::BuiltinConfig,
+    ) -> Box<dyn crate::JsonRPCMethod> {
+        crate::builtins::namespaced(
+            builtins,
+            crate::builtins::BuiltinMethods::ADMIN,
+            Box::new(ReloadConfigMethod {
+                reloader: Arc::clone(self),
+            }),
+        )
+    }
+}
+
+/// Partial `SecurityConfig` as written in the config file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SecuritySection {
+    max_connections: Option<usize>,
+    max_request_size: Option<usize>,
+    request_timeout_secs: Option<u64>,
+    idle_timeout_secs: Option<u64>,
+    max_json_depth: Option<usize>,
+    max_json_nodes: Option<usize>,
+    max_connection_lifetime_secs: Option<u64>,
+    max_requests_per_connection: Option<usize>,
+}
+
+impl SecuritySection {
+    fn apply_to(&self, current: &SecurityConfig) -> SecurityConfig {
+        let mut config = current.clone();
+        if let Some(v) = self.max_connections {
+            config.max_connections = v;
+        }
+        if let Some(v) = self.max_request_size {
+            config.max_request_size = v;
+        }
+        if let Some(v) = self.request_timeout_secs {
+            config.request_timeout = Duration::from_secs(v);
+        }
+        if let Some(v) = self.idle_timeout_secs {
+            config.idle_timeout = Duration::from_secs(v);
+        }
+        if let Some(v) = self.max_json_depth {
+            config.max_json_depth = v;
+        }
+        if let Some(v) = self.max_json_nodes {
+            config.max_json_nodes = v;
+        }
+        if let Some(v) = self.max_connection_lifetime_secs {
+            config.max_connection_lifetime = Duration::from_secs(v);
+        }
+        if let Some(v) = self.max_requests_per_connection {
+            config.max_requests_per_connection = v;
+        }
+        config
+    }
+}
+
+/// `reload_config` built-in (admin group)
+pub struct ReloadConfigMethod {
+    reloader: Arc<ConfigReloader>,
+}
+
+#[crate::async_trait]
+impl crate::JsonRPCMethod for ReloadConfigMethod {
+    fn method_name(&self) -> &'static str {
+        "reload_config"
+    }
+
+    fn is_builtin(&self) -> bool {
+        true
+    }
+
+    async fn call(
+        &self,
+        _params: Option<serde_json::Value>,
+        id: Option<crate::RequestId>,
+    ) -> crate::Response {
+        match self.reloader.reload() {
+            Ok(sections) => {
+                crate::Response::success(serde_json::json!({ "reloaded": sections }), id)
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "configuration reload rejected");
+                crate::Response::error(
+                    crate::ErrorBuilder::new(crate::error_codes::INTERNAL_ERROR, e.to_string())
+                        .build(),
+                    id,
+                )
+            }
+        }
+    }
+}
+
+/// Reload on every `SIGHUP` until the runtime shuts down
+#[cfg(all(unix, feature = "signals"))]
+pub fn spawn_sighup_listener(
+    reloader: Arc<ConfigReloader>,
+) -> std::io::Result<tokio::task::JoinHandle<()>> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut hangup = signal(SignalKind::hangup())?;
+    Ok(tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            tracing::info!(path = %reloader.path().display(), "SIGHUP received, reloading configuration");
+            if let Err(e) = reloader.reload() {
+                tracing::error!(error = %e, "configuration reload failed");
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reloadable_snapshots() {
+        let value = Reloadable::new(1);
+        let before = value.load();
+        value.store(2);
+        assert_eq!(*before, 1);
+        assert_eq!(*value.clone().load(), 2);
+    }
+
+    #[test]
+    fn test_reload_applies_sections() {
+        let security = Reloadable::new(SecurityConfig::default());
+        let level = Reloadable::new(String::from("info"));
+        let level_handle = level.clone();
+        let reloader = ConfigReloader::new("unused.json")
+            .security(security.clone())
+            .log_level(move |l| level_handle.store(l));
+
+        let applied = reloader
+            .reload_from_str(r#"{"security":{"max_connections":5},"log_level":"debug","other":1}"#)
+            .unwrap();
+
+        assert_eq!(applied, vec!["security", "log_level"]);
+        assert_eq!(security.load().max_connections, 5);
+        assert_eq!(
+            security.load().max_request_size,
+            SecurityConfig::default().max_request_size
+        );
+        assert_eq!(*level.load(), "debug");
+    }
+
+    #[test]
+    fn test_reload_is_all_or_nothing() {
+        let security = Reloadable::new(SecurityConfig::default());
+        let level = Reloadable::new(String::from("info"));
+        let level_handle = level.clone();
+        let reloader = ConfigReloader::new("unused.json")
+            .log_level(move |l| level_handle.store(l))
+            .security(security.clone());
+
+        let result = reloader
+            .reload_from_str(r#"{"log_level":"trace","security":{"request_timeout_secs":0}}"#);
+
+        assert!(
+            matches!(result, Err(ReloadError::Invalid { ref section, .. }) if section == "security")
+        );
+        assert_eq!(*level.load(), "info");
+        assert_eq!(security.load().request_timeout, Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_reload_resolves_secrets() {
+        let dir = std::env::temp_dir().join(format!("ash-rpc-reload-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("db_url"), "postgres://db\n").unwrap();
+
+        let db_url = Reloadable::new(String::new());
+        let handle = db_url.clone();
+        let reloader = ConfigReloader::new("unused.json")
+            .secrets(
+                crate::secrets::SecretResolver::new()
+                    .with_source(crate::secrets::FileSecrets::new(&dir)),
+            )
+            .section("db_url", move |url: String| handle.store(url));
+
+        reloader
+            .reload_from_str(r#"{"db_url":"${secret:db_url}"}"#)
+            .unwrap();
+        assert_eq!(*db_url.load(), "postgres://db");
+
+        let result = reloader.reload_from_str(r#"{"db_url":"${secret:missing}"}"#);
+        assert!(matches!(result, Err(ReloadError::Secret(_))));
+        assert_eq!(*db_url.load(), "postgres://db");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_admin_method() {
+        let reloader = Arc::new(ConfigReloader::new("/nonexistent/ash-rpc.json"));
+        let method = reloader.admin_method(&crate::builtins::BuiltinConfig::default());
+        assert_eq!(method.method_name(), "admin.reload_config");
+
+        let response = method.call(None, Some(serde_json::json!(1))).await;
+        assert!(response.is_error());
+    }
+}
@@ -0,0 +1,331 @@
+//! Replay protection for mutation methods.
+//!
+//! Notifications carry no id, so a captured notification can be re-sent
+//! without the server noticing. Methods designated on a [`ReplayGuard`]
+//! must instead wrap their params in an envelope:
+//!
+//! ```json
+//! {"jsonrpc": "2.0", "method": "account.debit",
+//!  "params": {"nonce": "5b0c…", "timestamp": 1760000000, "payload": {"amount": 10}}}
+//! ```
+//!
+//! `timestamp` is in Unix seconds and must lie within the guard's window of
+//! the server clock; `nonce` must not have been seen for that method within
+//! the window. Accepted messages reach the handler with `payload` as their
+//! params. Rejected notifications are dropped and reported through
+//! [`crate::rejection`]; requests to guarded methods get `INVALID_REQUEST`.
+//!
+//! Seen nonces live in a [`Cache`]. The default is a private
+//! [`MemoryCache`]; pass a shared one with [`ReplayGuard::with_cache`], e.g.
+//! a `RedisCache`, so several server instances reject each other's replays.
+//!
+//! ```rust
+//! use ash_rpc::replay::ReplayGuard;
+//! use ash_rpc::MethodRegistry;
+//! use std::time::Duration;
+//!
+//! let registry = MethodRegistry::empty().with_replay_guard(
+//!     ReplayGuard::new(Duration::from_secs(300)).protect("account.debit"),
+//! );
+//! # let _ = registry;
+//! ```
+
+use crate::cache::{Cache, CacheError, MemoryCache};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Why a guarded message was refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    /// Params are not a `{nonce, timestamp, payload}` envelope
+    MissingEnvelope,
+    /// Timestamp is outside the accepted window
+    Stale { skew_secs: i64 },
+    /// Nonce was already used within the window
+    Replayed,
+    /// Too many nonces are outstanding to remember another one
+    CacheFull,
+    /// The nonce cache could not be consulted
+    CacheUnavailable(String),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::MissingEnvelope => {
+                write!(f, "params must be a nonce/timestamp/payload envelope")
+            }
+            ReplayError::Stale { skew_secs } => {
+                write!(f, "timestamp is {skew_secs}s away from server time")
+            }
+            ReplayError::Replayed => write!(f, "nonce has already been used"),
+            ReplayError::CacheFull => write!(f, "replay cache is full"),
+            ReplayError::CacheUnavailable(e) => write!(f, "replay cache unavailable: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// Nonce/timestamp check for designated methods
+pub struct ReplayGuard {
+    methods: HashSet<String>,
+    window: Duration,
+    max_entries: usize,
+    cache: Arc<dyn Cache>,
+}
+
+impl fmt::Debug for ReplayGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReplayGuard")
+            .field("methods", &self.methods)
+            .field("window", &self.window)
+            .field("max_entries", &self.max_entries)
+            .finish()
+    }
+}
+
+impl ReplayGuard {
+    /// Accept timestamps up to `window` away from the server clock
+    pub fn new(window: Duration) -> Self {
+        Self {
+            methods: HashSet::new(),
+            window,
+            max_entries: 100_000,
+            cache: Arc::new(MemoryCache::new(100_000).reject_when_full()),
+        }
+    }
+
+    /// Require the envelope for `method`
+    pub fn protect(mut self, method: impl Into<String>) -> Self {
+        self.methods.insert(method.into());
+        self
+    }
+
+    /// Maximum number of remembered nonces.
+    ///
+    /// When the cache is full, new messages are rejected rather than
+    /// forgetting nonces that are still inside the window. Replaces the
+    /// cache with a fresh in-memory one of that size.
+    pub fn max_entries(mut self, max: usize) -> Self {
+        self.max_entries = max;
+        self.cache = Arc::new(MemoryCache::new(max).reject_when_full());
+        self
+    }
+
+    /// Remember nonces in `cache` instead of a private in-memory one
+    ///
+    /// The cache should fail inserts when full rather than evict, or
+    /// replays of evicted nonces will be accepted.
+    pub fn with_cache(mut self, cache: Arc<dyn Cache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    pub fn is_protected(&self, method: &str) -> bool {
+        self.methods.contains(method)
+    }
+
+    /// Validate the envelope of a guarded method and return its payload.
+    ///
+    /// Params of unguarded methods are returned unchanged.
+    pub async fn check(
+        &self,
+        method: &str,
+        params: Option<Value>,
+    ) -> Result<Option<Value>, ReplayError> {
+        if !self.is_protected(method) {
+            return Ok(params);
+        }
+        self.check_at(method, params, unix_now()).await
+    }
+
+    async fn check_at(
+        &self,
+        method: &str,
+        params: Option<Value>,
+        now: u64,
+    ) -> Result<Option<Value>, ReplayError> {
+        let Some(Value::Object(mut envelope)) = params else {
+            return Err(ReplayError::MissingEnvelope);
+        };
+        let nonce = match envelope.get("nonce") {
+            Some(Value::String(nonce)) if !nonce.is_empty() && nonce.len() <= 128 => nonce.clone(),
+            _ => return Err(ReplayError::MissingEnvelope),
+        };
+        let timestamp = envelope
+            .get("timestamp")
+            .and_then(Value::as_u64)
+            .ok_or(ReplayError::MissingEnvelope)?;
+
+        let window = self.window.as_secs();
+        // client supplied, so anywhere in u64; beyond i64 it is skew all the same
+        let skew = i128::from(timestamp) - i128::from(now);
+        if skew.unsigned_abs() > u128::from(window) {
+            let skew_secs = skew.clamp(i64::MIN.into(), i64::MAX.into()) as i64;
+            return Err(ReplayError::Stale { skew_secs });
+        }
+
+        // Once `timestamp + window` has passed the message is stale anyway,
+        // so the nonce does not need to be remembered any longer
+        let ttl = Duration::from_secs(
+            timestamp
+                .saturating_add(window)
+                .saturating_add(1)
+                .saturating_sub(now),
+        );
+        let key = format!("replay:{}:{method}:{nonce}", method.len());
+        match self
+            .cache
+            .insert_if_absent(&key, Value::Bool(true), Some(ttl))
+            .await
+        {
+            Ok(true) => {}
+            Ok(false) => return Err(ReplayError::Replayed),
+            Err(CacheError::Full) => return Err(ReplayError::CacheFull),
+            Err(CacheError::Backend(e)) => return Err(ReplayError::CacheUnavailable(e)),
+        }
+
+        Ok(envelope.remove("payload"))
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    /// Guard whose nonce cache follows the simulated clock of `check_at`
+    struct Sim {
+        guard: ReplayGuard,
+        now: Arc<AtomicU64>,
+    }
+
+    impl Sim {
+        fn max_entries(self, max: usize) -> Self {
+            let now = Arc::clone(&self.now);
+            let clock = Arc::clone(&now);
+            let cache = MemoryCache::new(max)
+                .reject_when_full()
+                .with_clock(move || UNIX_EPOCH + Duration::from_secs(clock.load(Ordering::SeqCst)));
+            Self {
+                guard: self.guard.with_cache(Arc::new(cache)),
+                now,
+            }
+        }
+
+        async fn check(
+            &self,
+            method: &str,
+            params: Option<Value>,
+        ) -> Result<Option<Value>, ReplayError> {
+            self.guard.check(method, params).await
+        }
+
+        async fn check_at(
+            &self,
+            method: &str,
+            params: Option<Value>,
+            now: u64,
+        ) -> Result<Option<Value>, ReplayError> {
+            self.now.store(now, Ordering::SeqCst);
+            self.guard.check_at(method, params, now).await
+        }
+    }
+
+    fn guard() -> Sim {
+        Sim {
+            guard: ReplayGuard::new(Duration::from_secs(60)).protect("debit"),
+            now: Arc::new(AtomicU64::new(0)),
+        }
+        .max_entries(100)
+    }
+
+    fn envelope(nonce: &str, timestamp: u64) -> Option<Value> {
+        Some(json!({"nonce": nonce, "timestamp": timestamp, "payload": {"amount": 5}}))
+    }
+
+    #[tokio::test]
+    async fn test_unprotected_passthrough() {
+        let params = Some(json!({"a": 1}));
+        assert_eq!(guard().check("credit", params.clone()).await, Ok(params));
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_nonce_rejected() {
+        let guard = guard();
+        let payload = guard
+            .check_at("debit", envelope("n1", 1000), 1000)
+            .await
+            .unwrap();
+        assert_eq!(payload, Some(json!({"amount": 5})));
+
+        assert_eq!(
+            guard.check_at("debit", envelope("n1", 1000), 1010).await,
+            Err(ReplayError::Replayed)
+        );
+        assert!(
+            guard
+                .check_at("debit", envelope("n2", 1000), 1010)
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_window_enforced() {
+        let guard = guard();
+        assert_eq!(
+            guard.check_at("debit", envelope("n1", 1000), 1061).await,
+            Err(ReplayError::Stale { skew_secs: -61 })
+        );
+        assert!(matches!(
+            guard.check_at("debit", envelope("n1", 1100), 1000).await,
+            Err(ReplayError::Stale { .. })
+        ));
+        assert_eq!(
+            guard
+                .check_at("debit", envelope("n1", u64::MAX), 1000)
+                .await,
+            Err(ReplayError::Stale {
+                skew_secs: i64::MAX
+            })
+        );
+        assert_eq!(
+            guard
+                .check_at("debit", Some(json!({"amount": 5})), 1000)
+                .await,
+            Err(ReplayError::MissingEnvelope)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_nonces_evicted() {
+        let guard = guard().max_entries(1);
+        guard
+            .check_at("debit", envelope("n1", 1000), 1000)
+            .await
+            .unwrap();
+        assert_eq!(
+            guard.check_at("debit", envelope("n2", 1000), 1000).await,
+            Err(ReplayError::CacheFull)
+        );
+        // n1 can no longer pass the window check, so its slot is reused
+        guard
+            .check_at("debit", envelope("n2", 1061), 1061)
+            .await
+            .unwrap();
+    }
+}
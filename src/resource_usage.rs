@@ -0,0 +1,303 @@
+//! Per-method resource usage accounting.
+//!
+//! Registries built with
+//! [`with_resource_accounting`](crate::MethodRegistry::with_resource_accounting)
+//! measure how long each method's future spends being polled, which
+//! approximates the CPU time a call costs independently of how long it waits
+//! on I/O. With the `alloc-accounting` feature and [`CountingAllocator`]
+//! installed as the global allocator, the bytes allocated while polling are
+//! counted as well.
+//!
+//! Figures are aggregated per method name across the process and meant for
+//! capacity planning, not exact billing: work a method hands to other tasks
+//! or threads is not attributed to it. [`snapshot`] backs the
+//! `diagnostics.resources` built-in.
+//!
+//! ```rust,ignore
+//! use ash_rpc::resource_usage::CountingAllocator;
+//!
+//! #[global_allocator]
+//! static ALLOC: CountingAllocator = CountingAllocator::system();
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Instant;
+
+/// Live counters of one method
+#[derive(Debug, Default)]
+struct MethodCounters {
+    calls: AtomicU64,
+    polls: AtomicU64,
+    poll_time_us: AtomicU64,
+    max_call_poll_time_us: AtomicU64,
+    allocated_bytes: AtomicU64,
+}
+
+/// Point-in-time resource usage of one method
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MethodResourceStats {
+    pub method: String,
+    pub calls: u64,
+    pub polls: u64,
+    /// Time spent polling the method's futures, summed over all calls
+    pub poll_time_us: u64,
+    /// `poll_time_us` over `calls`, 0 before the first call
+    pub mean_poll_time_us: u64,
+    /// Largest poll time of a single call
+    pub max_call_poll_time_us: u64,
+    /// Bytes allocated while polling, `None` without a [`CountingAllocator`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allocated_bytes: Option<u64>,
+}
+
+type Methods = RwLock<BTreeMap<String, Arc<MethodCounters>>>;
+
+fn methods() -> &'static Methods {
+    static METHODS: OnceLock<Methods> = OnceLock::new();
+    METHODS.get_or_init(Default::default)
+}
+
+fn counters(method: &str) -> Arc<MethodCounters> {
+    if let Some(counters) = methods()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(method)
+    {
+        return Arc::clone(counters);
+    }
+    let mut methods = methods().write().unwrap_or_else(|e| e.into_inner());
+    Arc::clone(methods.entry(method.to_string()).or_default())
+}
+
+/// Drive `fut` to completion, charging its poll time and allocations to `method`
+pub async fn measure<F: Future>(method: &str, fut: F) -> F::Output {
+    let counters = counters(method);
+    let mut fut = std::pin::pin!(fut);
+    let mut polls = 0u64;
+    let mut poll_time = std::time::Duration::ZERO;
+    let mut allocated = 0u64;
+
+    let output = std::future::poll_fn(|cx| {
+        let allocated_before = thread_allocated();
+        let start = Instant::now();
+        let poll = fut.as_mut().poll(cx);
+        poll_time += start.elapsed();
+        allocated += thread_allocated().wrapping_sub(allocated_before);
+        polls += 1;
+        poll
+    })
+    .await;
+
+    let poll_time_us = poll_time.as_micros() as u64;
+    counters.calls.fetch_add(1, Ordering::Relaxed);
+    counters.polls.fetch_add(polls, Ordering::Relaxed);
+    counters
+        .poll_time_us
+        .fetch_add(poll_time_us, Ordering::Relaxed);
+    counters
+        .max_call_poll_time_us
+        .fetch_max(poll_time_us, Ordering::Relaxed);
+    counters
+        .allocated_bytes
+        .fetch_add(allocated, Ordering::Relaxed);
+    output
+}
+
+/// Resource usage of every method measured so far, sorted by name
+pub fn snapshot() -> Vec<MethodResourceStats> {
+    let counting = allocations_counted();
+    methods()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|(method, counters)| {
+            let calls = counters.calls.load(Ordering::Relaxed);
+            let poll_time_us = counters.poll_time_us.load(Ordering::Relaxed);
+            MethodResourceStats {
+                method: method.clone(),
+                calls,
+                polls: counters.polls.load(Ordering::Relaxed),
+                poll_time_us,
+                mean_poll_time_us: poll_time_us.checked_div(calls).unwrap_or(0),
+                max_call_poll_time_us: counters.max_call_poll_time_us.load(Ordering::Relaxed),
+                allocated_bytes: counting.then(|| counters.allocated_bytes.load(Ordering::Relaxed)),
+            }
+        })
+        .collect()
+}
+
+#[cfg(feature = "alloc-accounting")]
+mod counting {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    thread_local! {
+        static ALLOCATED: Cell<u64> = const { Cell::new(0) };
+    }
+
+    pub(super) static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+    pub(super) fn thread_allocated() -> u64 {
+        ALLOCATED.try_with(Cell::get).unwrap_or(0)
+    }
+
+    fn count(bytes: usize) {
+        let _ = ALLOCATED.try_with(|allocated| allocated.set(allocated.get() + bytes as u64));
+    }
+
+    /// Global allocator wrapper counting the bytes each thread allocates
+    pub struct CountingAllocator<A = System> {
+        inner: A,
+    }
+
+    impl CountingAllocator<System> {
+        pub const fn system() -> Self {
+            Self { inner: System }
+        }
+    }
+
+    impl<A> CountingAllocator<A> {
+        pub const fn new(inner: A) -> Self {
+            Self { inner }
+        }
+    }
+
+    unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            INSTALLED.store(true, Ordering::Relaxed);
+            count(layout.size());
+            // SAFETY: forwarded unchanged from the caller
+            unsafe { self.inner.alloc(layout) }
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            INSTALLED.store(true, Ordering::Relaxed);
+            count(layout.size());
+            // SAFETY: forwarded unchanged from the caller
+            unsafe { self.inner.alloc_zeroed(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            // SAFETY: forwarded unchanged from the caller
+            unsafe { self.inner.dealloc(ptr, layout) }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            count(new_size.saturating_sub(layout.size()));
+            // SAFETY: forwarded unchanged from the caller
+            unsafe { self.inner.realloc(ptr, layout, new_size) }
+        }
+    }
+}
+
+#[cfg(feature = "alloc-accounting")]
+pub use counting::CountingAllocator;
+
+#[cfg(feature = "alloc-accounting")]
+fn thread_allocated() -> u64 {
+    counting::thread_allocated()
+}
+
+#[cfg(not(feature = "alloc-accounting"))]
+fn thread_allocated() -> u64 {
+    0
+}
+
+/// Whether a [`CountingAllocator`] is counting this process's allocations
+#[cfg(feature = "alloc-accounting")]
+fn allocations_counted() -> bool {
+    counting::INSTALLED.load(Ordering::Relaxed)
+}
+
+#[cfg(not(feature = "alloc-accounting"))]
+fn allocations_counted() -> bool {
+    false
+}
+
+/// `diagnostics.resources`: the current [`snapshot`]
+pub struct ResourceDiagnosticsMethod;
+
+#[async_trait::async_trait]
+impl crate::JsonRPCMethod for ResourceDiagnosticsMethod {
+    fn method_name(&self) -> &'static str {
+        "resources"
+    }
+
+    async fn call(
+        &self,
+        _params: Option<serde_json::Value>,
+        id: Option<crate::RequestId>,
+    ) -> crate::Response {
+        crate::rpc_success!(snapshot(), id)
+    }
+
+    fn openapi_components(&self) -> crate::OpenApiMethodSpec {
+        crate::OpenApiMethodSpec::new(self.method_name())
+            .with_summary("Poll time and allocations per method")
+            .with_tag("diagnostics")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{JsonRPCMethod, MethodRegistry, RequestId, Response};
+
+    struct Spin;
+
+    #[async_trait::async_trait]
+    impl JsonRPCMethod for Spin {
+        fn method_name(&self) -> &'static str {
+            "test_resources_spin"
+        }
+
+        async fn call(
+            &self,
+            _params: Option<serde_json::Value>,
+            id: Option<RequestId>,
+        ) -> Response {
+            let start = Instant::now();
+            while start.elapsed() < std::time::Duration::from_millis(2) {
+                std::hint::spin_loop();
+            }
+            // waiting is not charged as poll time
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            Response::success(serde_json::json!(true), id)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_poll_time_charged_per_method() {
+        use crate::builtins::{BuiltinConfig, BuiltinMethods};
+
+        let registry = MethodRegistry::new(crate::register_methods![Spin])
+            .with_builtins(BuiltinConfig::new(BuiltinMethods::DIAGNOSTICS))
+            .with_resource_accounting(true);
+        for id in 0..2 {
+            let response = registry
+                .call("test_resources_spin", None, Some(serde_json::json!(id)))
+                .await;
+            assert!(response.is_success());
+        }
+
+        let response = registry
+            .call("diagnostics.resources", None, Some(serde_json::json!(3)))
+            .await;
+        let stats: Vec<MethodResourceStats> =
+            serde_json::from_value(response.result.unwrap()).unwrap();
+        let spin = stats
+            .iter()
+            .find(|s| s.method == "test_resources_spin")
+            .unwrap();
+        assert_eq!(spin.calls, 2);
+        assert!(spin.polls >= 4);
+        assert!(spin.mean_poll_time_us >= 2_000);
+        assert!(spin.max_call_poll_time_us < 20_000);
+        assert!(spin.allocated_bytes.is_none());
+    }
+}
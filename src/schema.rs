@@ -0,0 +1,350 @@
+//! Minimal JSON Schema validation.
+//!
+//! Supports the subset of JSON Schema emitted by the OpenAPI helpers in this
+//! crate and by `schemars`: `type` (single or list), `enum`, `const`,
+//! `properties`, `required`, `additionalProperties`, `items`, `minItems`,
+//! `maxItems`, `minLength`, `maxLength`, `minimum`, `maximum`, `allOf`,
+//! `anyOf`, `oneOf` and local `$ref`s into `$defs`/`definitions`. Unknown
+//! keywords such as `format` or `description` are ignored.
+//!
+//! ```rust
+//! use ash_rpc::schema::validate;
+//! use serde_json::json;
+//!
+//! let schema = json!({"type": "object", "required": ["id"],
+//!                     "properties": {"id": {"type": "integer"}}});
+//! assert!(validate(&json!({"id": 1}), &schema).is_ok());
+//!
+//! let mismatch = validate(&json!({"id": "1"}), &schema).unwrap_err();
+//! assert_eq!(mismatch.violations[0].path, "/id");
+//! ```
+
+use serde_json::Value;
+use std::fmt;
+
+/// A single failed constraint
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SchemaViolation {
+    /// JSON pointer to the offending value, `""` for the root
+    pub path: String,
+    pub message: String,
+}
+
+/// A value did not match its schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaMismatch {
+    pub violations: Vec<SchemaViolation>,
+}
+
+impl fmt::Display for SchemaMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "schema mismatch")?;
+        for (i, v) in self.violations.iter().enumerate() {
+            let sep = if i == 0 { ": " } else { "; " };
+            let path = if v.path.is_empty() { "/" } else { &v.path };
+            write!(f, "{sep}{path} {}", v.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SchemaMismatch {}
+
+/// Validate `instance` against `schema`, collecting every violation
+pub fn validate(instance: &Value, schema: &Value) -> Result<(), SchemaMismatch> {
+    let mut violations = Vec::new();
+    Validator { root: schema }.check(instance, schema, &mut String::new(), &mut violations);
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(SchemaMismatch { violations })
+    }
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+}
+
+impl<'a> Validator<'a> {
+    fn resolve(&self, reference: &str) -> Option<&'a Value> {
+        let pointer = reference.strip_prefix('#')?;
+        self.root.pointer(pointer)
+    }
+
+    fn check(
+        &self,
+        value: &Value,
+        schema: &Value,
+        path: &mut String,
+        out: &mut Vec<SchemaViolation>,
+    ) {
+        let schema = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => {
+                push(out, path, "is not allowed");
+                return;
+            }
+            Value::Object(schema) => schema,
+            _ => return,
+        };
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            match self.resolve(reference) {
+                Some(target) => self.check(value, target, path, out),
+                None => push(out, path, format!("has unresolvable $ref '{reference}'")),
+            }
+        }
+
+        if let Some(expected) = schema.get("type")
+            && !type_matches(value, expected)
+        {
+            push(
+                out,
+                path,
+                format!("expected type {expected}, got {}", type_name(value)),
+            );
+            return;
+        }
+
+        if let Some(options) = schema.get("enum").and_then(Value::as_array)
+            && !options.contains(value)
+        {
+            push(
+                out,
+                path,
+                format!("must be one of {}", Value::Array(options.clone())),
+            );
+        }
+        if let Some(expected) = schema.get("const")
+            && expected != value
+        {
+            push(out, path, format!("must equal {expected}"));
+        }
+
+        if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+            for sub in all {
+                self.check(value, sub, path, out);
+            }
+        }
+        if let Some(any) = schema.get("anyOf").and_then(Value::as_array)
+            && !any.iter().any(|sub| self.matches(value, sub))
+        {
+            push(out, path, "matches none of the anyOf schemas");
+        }
+        if let Some(one) = schema.get("oneOf").and_then(Value::as_array) {
+            let count = one.iter().filter(|sub| self.matches(value, sub)).count();
+            if count != 1 {
+                push(
+                    out,
+                    path,
+                    format!("matches {count} oneOf schemas, expected exactly 1"),
+                );
+            }
+        }
+
+        match value {
+            Value::Object(map) => {
+                let properties = schema.get("properties").and_then(Value::as_object);
+                if let Some(required) = schema.get("required").and_then(Value::as_array) {
+                    for name in required.iter().filter_map(Value::as_str) {
+                        if !map.contains_key(name) {
+                            push(out, path, format!("is missing required property '{name}'"));
+                        }
+                    }
+                }
+                let additional = schema.get("additionalProperties");
+                for (key, item) in map {
+                    let len = path.len();
+                    path.push('/');
+                    path.push_str(&key.replace('~', "~0").replace('/', "~1"));
+                    match properties.and_then(|p| p.get(key)) {
+                        Some(sub) => self.check(item, sub, path, out),
+                        None => {
+                            if let Some(sub) = additional {
+                                if sub == &Value::Bool(false) {
+                                    push(out, path, "is not an allowed property");
+                                } else {
+                                    self.check(item, sub, path, out);
+                                }
+                            }
+                        }
+                    }
+                    path.truncate(len);
+                }
+            }
+            Value::Array(items) => {
+                check_len(
+                    schema,
+                    "minItems",
+                    "maxItems",
+                    items.len(),
+                    "items",
+                    path,
+                    out,
+                );
+                if let Some(sub) = schema.get("items") {
+                    for (i, item) in items.iter().enumerate() {
+                        let len = path.len();
+                        path.push('/');
+                        path.push_str(&i.to_string());
+                        self.check(item, sub, path, out);
+                        path.truncate(len);
+                    }
+                }
+            }
+            Value::String(s) => {
+                let chars = s.chars().count();
+                check_len(
+                    schema,
+                    "minLength",
+                    "maxLength",
+                    chars,
+                    "characters",
+                    path,
+                    out,
+                );
+            }
+            Value::Number(n) => {
+                let n = n.as_f64().unwrap_or(f64::NAN);
+                if let Some(min) = schema.get("minimum").and_then(Value::as_f64)
+                    && n < min
+                {
+                    push(out, path, format!("must be >= {min}"));
+                }
+                if let Some(max) = schema.get("maximum").and_then(Value::as_f64)
+                    && n > max
+                {
+                    push(out, path, format!("must be <= {max}"));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn matches(&self, value: &Value, schema: &Value) -> bool {
+        let mut scratch = Vec::new();
+        self.check(value, schema, &mut String::new(), &mut scratch);
+        scratch.is_empty()
+    }
+}
+
+fn push(out: &mut Vec<SchemaViolation>, path: &str, message: impl Into<String>) {
+    out.push(SchemaViolation {
+        path: path.to_string(),
+        message: message.into(),
+    });
+}
+
+fn check_len(
+    schema: &serde_json::Map<String, Value>,
+    min_key: &str,
+    max_key: &str,
+    len: usize,
+    unit: &str,
+    path: &str,
+    out: &mut Vec<SchemaViolation>,
+) {
+    if let Some(min) = schema.get(min_key).and_then(Value::as_u64)
+        && (len as u64) < min
+    {
+        push(out, path, format!("must have at least {min} {unit}"));
+    }
+    if let Some(max) = schema.get(max_key).and_then(Value::as_u64)
+        && (len as u64) > max
+    {
+        push(out, path, format!("must have at most {max} {unit}"));
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn type_matches(value: &Value, expected: &Value) -> bool {
+    let matches_one = |name: &str| match (name, value) {
+        ("number", Value::Number(_)) => true,
+        ("integer", Value::Number(n)) => {
+            n.is_i64() || n.is_u64() || n.as_f64().is_some_and(|f| f.fract() == 0.0)
+        }
+        (name, value) => name == type_name(value),
+    };
+    match expected {
+        Value::String(name) => matches_one(name),
+        Value::Array(names) => names.iter().filter_map(Value::as_str).any(matches_one),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn user_schema() -> Value {
+        json!({
+            "type": "object",
+            "required": ["name", "tags"],
+            "additionalProperties": false,
+            "properties": {
+                "name": {"type": "string", "minLength": 1},
+                "age": {"type": ["integer", "null"], "minimum": 0},
+                "tags": {"type": "array", "items": {"$ref": "#/$defs/Tag"}},
+            },
+            "$defs": {"Tag": {"type": "string", "enum": ["admin", "user"]}}
+        })
+    }
+
+    #[test]
+    fn test_valid_instance() {
+        let user = json!({"name": "ada", "age": null, "tags": ["admin"]});
+        assert!(validate(&user, &user_schema()).is_ok());
+    }
+
+    #[test]
+    fn test_collects_violations_with_paths() {
+        let user = json!({"name": "", "age": -1, "tags": ["root", 3], "extra": true});
+        let mismatch = validate(&user, &user_schema()).unwrap_err();
+        let mut paths: Vec<_> = mismatch
+            .violations
+            .iter()
+            .map(|v| v.path.as_str())
+            .collect();
+        paths.sort();
+        assert_eq!(paths, ["/age", "/extra", "/name", "/tags/0", "/tags/1"]);
+        assert!(mismatch.to_string().contains("/tags/1 expected type"));
+    }
+
+    #[test]
+    fn test_missing_required_and_combinators() {
+        let mismatch = validate(&json!({}), &user_schema()).unwrap_err();
+        assert_eq!(mismatch.violations.len(), 2);
+
+        let schema = json!({"oneOf": [{"type": "integer"}, {"type": "number"}]});
+        assert!(validate(&json!(1.5), &schema).is_ok());
+        assert!(validate(&json!(1), &schema).is_err());
+        assert!(validate(&json!("x"), &json!({"anyOf": [{"type": "null"}]})).is_err());
+    }
+
+    #[cfg(feature = "schemars")]
+    #[test]
+    fn test_schemars_generated_schema() {
+        #[allow(dead_code)]
+        #[derive(schemars::JsonSchema)]
+        struct Point {
+            x: i32,
+            label: Option<String>,
+        }
+
+        let schema = serde_json::to_value(schemars::schema_for!(Point)).unwrap();
+        assert!(validate(&json!({"x": 1, "label": null}), &schema).is_ok());
+        assert!(validate(&json!({"x": "1"}), &schema).is_err());
+    }
+}
@@ -0,0 +1,426 @@
+//! Secret resolution for configuration values.
+//!
+//! Config files reference database URLs, API keys and TLS material that
+//! should not be stored in plaintext. Instead, a value can contain
+//! `${secret:name}` placeholders which a [`SecretResolver`] replaces at
+//! startup (or on reload, see `ConfigReloader::secrets`) by asking its
+//! sources in order:
+//!
+//! - [`EnvSecrets`]: environment variables, e.g. `APP_SECRET_DB_URL`
+//! - [`FileSecrets`]: one file per secret, e.g. `/run/secrets/db_url`
+//! - [`ExecSecrets`]: the stdout of a helper command
+//! - `VaultSecrets` (`vault` feature): HashiCorp Vault KV through the
+//!   `vault` CLI
+//!
+//! Resolved values are never logged. PEM material resolved this way can be
+//! handed to `TlsConfig::from_pem_bytes`.
+//!
+//! ```rust
+//! use ash_rpc::secrets::{EnvSecrets, SecretResolver};
+//!
+//! unsafe { std::env::set_var("DOCS_SECRET_DB_PASSWORD", "hunter2") };
+//!
+//! let resolver = SecretResolver::new().with_source(EnvSecrets::new("DOCS_SECRET_"));
+//! let url = resolver
+//!     .resolve_str("postgres://app:${secret:db_password}@db/app")
+//!     .unwrap();
+//! assert_eq!(url, "postgres://app:hunter2@db/app");
+//! ```
+
+use std::fmt;
+use std::path::PathBuf;
+use std::process::Command;
+
+const PLACEHOLDER_START: &str = "${secret:";
+
+/// Why a secret could not be resolved
+#[derive(Debug)]
+pub enum SecretError {
+    /// No source knows the secret
+    NotFound(String),
+    /// The secret name contains characters outside `[A-Za-z0-9_.-]`
+    InvalidName(String),
+    /// A `${secret:` placeholder without closing brace
+    Unterminated,
+    /// A source failed while looking the secret up
+    Source { name: String, message: String },
+}
+
+impl fmt::Display for SecretError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecretError::NotFound(name) => write!(f, "secret '{name}' not found"),
+            SecretError::InvalidName(name) => write!(f, "invalid secret name '{name}'"),
+            SecretError::Unterminated => write!(f, "unterminated secret placeholder"),
+            SecretError::Source { name, message } => {
+                write!(f, "failed to resolve secret '{name}': {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SecretError {}
+
+/// A place secrets can be looked up in
+pub trait SecretSource: Send + Sync {
+    /// Short label used in diagnostics
+    fn kind(&self) -> &'static str;
+
+    /// Value of secret `name`, or `None` if this source does not have it
+    fn get(&self, name: &str) -> Result<Option<String>, SecretError>;
+}
+
+/// Secrets from environment variables named `prefix` + upper-cased name
+#[derive(Debug, Clone)]
+pub struct EnvSecrets {
+    prefix: String,
+}
+
+impl EnvSecrets {
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+        }
+    }
+}
+
+impl SecretSource for EnvSecrets {
+    fn kind(&self) -> &'static str {
+        "env"
+    }
+
+    fn get(&self, name: &str) -> Result<Option<String>, SecretError> {
+        let var = format!(
+            "{}{}",
+            self.prefix,
+            name.to_uppercase().replace(['.', '-'], "_")
+        );
+        Ok(std::env::var(var).ok())
+    }
+}
+
+/// Secrets stored one per file in a directory, as mounted by Docker and
+/// Kubernetes; a single trailing newline is stripped
+#[derive(Debug, Clone)]
+pub struct FileSecrets {
+    dir: PathBuf,
+}
+
+impl FileSecrets {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+impl SecretSource for FileSecrets {
+    fn kind(&self) -> &'static str {
+        "file"
+    }
+
+    fn get(&self, name: &str) -> Result<Option<String>, SecretError> {
+        match std::fs::read_to_string(self.dir.join(name)) {
+            Ok(mut value) => {
+                if value.ends_with('\n') {
+                    value.pop();
+                    if value.ends_with('\r') {
+                        value.pop();
+                    }
+                }
+                Ok(Some(value))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(SecretError::Source {
+                name: name.to_string(),
+                message: e.to_string(),
+            }),
+        }
+    }
+}
+
+/// Secrets printed by a helper command.
+///
+/// The command is run without a shell as `program args... name`. Exit code
+/// 0 yields the trimmed stdout, exit code 2 means "not found" and anything
+/// else is an error.
+#[derive(Debug, Clone)]
+pub struct ExecSecrets {
+    program: String,
+    args: Vec<String>,
+}
+
+impl ExecSecrets {
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            args: Vec::new(),
+        }
+    }
+
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    fn run(&self, name: &str, trailing: &[&str]) -> Result<Option<String>, SecretError> {
+        let output = Command::new(&self.program)
+            .args(&self.args)
+            .args(trailing)
+            .output()
+            .map_err(|e| SecretError::Source {
+                name: name.to_string(),
+                message: format!("failed to run '{}': {e}", self.program),
+            })?;
+
+        match output.status.code() {
+            Some(0) => Ok(Some(
+                String::from_utf8_lossy(&output.stdout)
+                    .trim_end()
+                    .to_string(),
+            )),
+            Some(2) => Ok(None),
+            status => Err(SecretError::Source {
+                name: name.to_string(),
+                message: format!("'{}' exited with {status:?}", self.program),
+            }),
+        }
+    }
+}
+
+impl SecretSource for ExecSecrets {
+    fn kind(&self) -> &'static str {
+        "exec"
+    }
+
+    fn get(&self, name: &str) -> Result<Option<String>, SecretError> {
+        self.run(name, &[name])
+    }
+}
+
+/// Secrets from a HashiCorp Vault KV engine, read with the `vault` CLI.
+///
+/// Secret `name` is the `value` field (see [`field`](Self::field)) of
+/// `<path_prefix>/<name>` in the `mount` engine. Authentication follows the CLI (`VAULT_ADDR`,
+/// `VAULT_TOKEN`, ...).
+#[cfg(feature = "vault")]
+#[derive(Debug, Clone)]
+pub struct VaultSecrets {
+    cli: ExecSecrets,
+    path_prefix: String,
+}
+
+#[cfg(feature = "vault")]
+impl VaultSecrets {
+    pub fn new(mount: impl Into<String>, path_prefix: impl Into<String>) -> Self {
+        Self {
+            cli: ExecSecrets::new("vault")
+                .arg("kv")
+                .arg("get")
+                .arg(format!("-mount={}", mount.into()))
+                .arg("-field=value"),
+            path_prefix: path_prefix.into(),
+        }
+    }
+
+    /// Use a different `vault` executable
+    pub fn program(mut self, program: impl Into<String>) -> Self {
+        self.cli.program = program.into();
+        self
+    }
+
+    /// Read field `field` instead of `value`
+    pub fn field(mut self, field: &str) -> Self {
+        if let Some(arg) = self.cli.args.iter_mut().find(|a| a.starts_with("-field=")) {
+            *arg = format!("-field={field}");
+        }
+        self
+    }
+}
+
+#[cfg(feature = "vault")]
+impl SecretSource for VaultSecrets {
+    fn kind(&self) -> &'static str {
+        "vault"
+    }
+
+    fn get(&self, name: &str) -> Result<Option<String>, SecretError> {
+        let path = format!("{}/{name}", self.path_prefix.trim_end_matches('/'));
+        self.cli.run(name, &[&path])
+    }
+}
+
+/// Resolves `${secret:name}` placeholders against a chain of sources
+#[derive(Default)]
+pub struct SecretResolver {
+    sources: Vec<Box<dyn SecretSource>>,
+}
+
+impl fmt::Debug for SecretResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kinds: Vec<_> = self.sources.iter().map(|s| s.kind()).collect();
+        f.debug_struct("SecretResolver")
+            .field("sources", &kinds)
+            .finish()
+    }
+}
+
+impl SecretResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a source; earlier sources take precedence
+    pub fn with_source<S: SecretSource + 'static>(mut self, source: S) -> Self {
+        self.sources.push(Box::new(source));
+        self
+    }
+
+    /// Look up a single secret by name
+    pub fn resolve(&self, name: &str) -> Result<String, SecretError> {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+            || name.starts_with('.')
+        {
+            return Err(SecretError::InvalidName(name.to_string()));
+        }
+
+        for source in &self.sources {
+            if let Some(value) = source.get(name)? {
+                tracing::debug!(secret = %name, source = source.kind(), "resolved secret");
+                return Ok(value);
+            }
+        }
+        Err(SecretError::NotFound(name.to_string()))
+    }
+
+    /// Replace every `${secret:name}` in `input`
+    pub fn resolve_str(&self, input: &str) -> Result<String, SecretError> {
+        let mut output = String::with_capacity(input.len());
+        let mut rest = input;
+        while let Some(start) = rest.find(PLACEHOLDER_START) {
+            output.push_str(&rest[..start]);
+            let after = &rest[start + PLACEHOLDER_START.len()..];
+            let end = after.find('}').ok_or(SecretError::Unterminated)?;
+            output.push_str(&self.resolve(&after[..end])?);
+            rest = &after[end + 1..];
+        }
+        output.push_str(rest);
+        Ok(output)
+    }
+
+    /// Resolve placeholders in every string of a JSON document in place
+    pub fn resolve_value(&self, value: &mut serde_json::Value) -> Result<(), SecretError> {
+        match value {
+            serde_json::Value::String(s) if s.contains(PLACEHOLDER_START) => {
+                *s = self.resolve_str(s)?;
+            }
+            serde_json::Value::Array(items) => {
+                for item in items {
+                    self.resolve_value(item)?;
+                }
+            }
+            serde_json::Value::Object(map) => {
+                for item in map.values_mut() {
+                    self.resolve_value(item)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    struct MapSecrets(HashMap<&'static str, &'static str>);
+
+    impl SecretSource for MapSecrets {
+        fn kind(&self) -> &'static str {
+            "map"
+        }
+
+        fn get(&self, name: &str) -> Result<Option<String>, SecretError> {
+            Ok(self.0.get(name).map(|v| v.to_string()))
+        }
+    }
+
+    fn resolver() -> SecretResolver {
+        SecretResolver::new()
+            .with_source(MapSecrets(HashMap::from([("db_url", "postgres://x")])))
+            .with_source(MapSecrets(HashMap::from([
+                ("db_url", "shadowed"),
+                ("api_key", "k1"),
+            ])))
+    }
+
+    #[test]
+    fn test_resolve_str() {
+        let resolver = resolver();
+        assert_eq!(
+            resolver
+                .resolve_str("${secret:db_url}?key=${secret:api_key}")
+                .unwrap(),
+            "postgres://x?key=k1"
+        );
+        assert_eq!(resolver.resolve_str("plain").unwrap(), "plain");
+        assert!(matches!(
+            resolver.resolve_str("${secret:missing}"),
+            Err(SecretError::NotFound(_))
+        ));
+        assert!(matches!(
+            resolver.resolve_str("${secret:db_url"),
+            Err(SecretError::Unterminated)
+        ));
+        assert!(matches!(
+            resolver.resolve("../etc/passwd"),
+            Err(SecretError::InvalidName(_))
+        ));
+    }
+
+    #[test]
+    fn test_resolve_value() {
+        let mut config = json!({
+            "db": { "url": "${secret:db_url}" },
+            "keys": ["${secret:api_key}", "static"],
+            "port": 5432
+        });
+        resolver().resolve_value(&mut config).unwrap();
+        assert_eq!(config["db"]["url"], "postgres://x");
+        assert_eq!(config["keys"], json!(["k1", "static"]));
+        assert_eq!(config["port"], 5432);
+    }
+
+    #[test]
+    fn test_file_secrets() {
+        let dir = std::env::temp_dir().join(format!("ash-rpc-secrets-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("token"), "abc\n").unwrap();
+
+        let source = FileSecrets::new(&dir);
+        assert_eq!(source.get("token").unwrap().as_deref(), Some("abc"));
+        assert_eq!(source.get("absent").unwrap(), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exec_secrets() {
+        let source = ExecSecrets::new("echo").arg("value-of");
+        assert_eq!(
+            source.get("db_url").unwrap().as_deref(),
+            Some("value-of db_url")
+        );
+
+        let missing = ExecSecrets::new("sh").arg("-c").arg("exit 2").arg("sh");
+        assert_eq!(missing.get("db_url").unwrap(), None);
+
+        let failing = ExecSecrets::new("false");
+        assert!(failing.get("db_url").is_err());
+    }
+}
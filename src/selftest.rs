@@ -0,0 +1,257 @@
+//! Protocol conformance self-test.
+//!
+//! [`run`] drives a processor through a short JSON-RPC 2.0 conformance suite
+//! (error codes, id echo, notification and batch handling, batch size limit)
+//! using only method names nothing is registered under, so it has no side
+//! effects on the service. Registries with the
+//! [`DISCOVERY`](crate::builtins::BuiltinMethods::DISCOVERY) built-ins
+//! enabled expose it as `rpc.selftest`, which lets operators check a live
+//! deployment after a configuration change.
+//!
+//! Auth policies, middleware and method filters see the suite's requests like
+//! any others; a check failing because of them shows what a client calling an
+//! unknown method would get.
+
+use crate::{Message, MessageProcessor, Notification, Request, Response, error_codes};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+/// Outcome of a single conformance check
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SelfTestCheck {
+    pub name: String,
+    pub passed: bool,
+    /// What went wrong, or why the check was skipped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Outcome of [`run`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SelfTestReport {
+    pub passed: bool,
+    pub checks: Vec<SelfTestCheck>,
+}
+
+impl SelfTestReport {
+    /// Checks that did not pass
+    pub fn failures(&self) -> impl Iterator<Item = &SelfTestCheck> {
+        self.checks.iter().filter(|check| !check.passed)
+    }
+}
+
+/// Run the conformance suite against `processor`
+pub async fn run<P: MessageProcessor + ?Sized>(processor: &P) -> SelfTestReport {
+    let missing = format!("rpc.selftest.missing-{}", uuid::Uuid::new_v4());
+    let mut checks = Vec::new();
+
+    for (name, id) in [
+        ("method_not_found", json!(1)),
+        ("string_id_echoed", json!("selftest")),
+        ("fractional_id_echoed", json!(1.5)),
+    ] {
+        let response = processor
+            .process_message(request(&missing, id.clone()))
+            .await;
+        checks.push(check(
+            name,
+            expect_error(response, error_codes::METHOD_NOT_FOUND, &id),
+        ));
+    }
+
+    let response = processor.process_message(notification(&missing)).await;
+    checks.push(check(
+        "notification_unanswered",
+        match response {
+            None => Ok(()),
+            Some(response) => Err(format!("answered with {}", describe(&response))),
+        },
+    ));
+
+    let response = processor
+        .process_message(Message::Response(Response::success(
+            json!(null),
+            Some(json!(1)),
+        )))
+        .await;
+    checks.push(check(
+        "response_ignored",
+        match response {
+            None => Ok(()),
+            Some(response) => Err(format!("answered with {}", describe(&response))),
+        },
+    ));
+
+    if processor.supports_batching() {
+        let batch = vec![
+            request(&missing, json!("a")),
+            notification(&missing),
+            request(&missing, json!("b")),
+        ];
+        let responses = processor.process_batch(batch).await;
+        checks.push(check("batch", expect_batch(responses)));
+
+        checks.push(match processor.get_capabilities().max_batch_size {
+            Some(max) => {
+                let oversized = (0..=max).map(|_| notification(&missing)).collect();
+                let responses = processor.process_batch(oversized).await;
+                check("batch_size_limit", expect_rejected_batch(responses, max))
+            }
+            None => skipped("batch_size_limit", "no batch size limit configured"),
+        });
+    } else {
+        checks.push(skipped("batch", "batching not supported"));
+    }
+
+    let report = SelfTestReport {
+        passed: checks.iter().all(|check| check.passed),
+        checks,
+    };
+    tracing::info!(
+        passed = report.passed,
+        failures = report.failures().count(),
+        "protocol self-test finished"
+    );
+    report
+}
+
+fn request(method: &str, id: Value) -> Message {
+    Message::Request(Request::new(method).with_id(id))
+}
+
+fn notification(method: &str) -> Message {
+    Message::Notification(Notification::new(method))
+}
+
+fn check(name: &str, outcome: Result<(), String>) -> SelfTestCheck {
+    SelfTestCheck {
+        name: name.to_string(),
+        passed: outcome.is_ok(),
+        detail: outcome.err(),
+    }
+}
+
+fn skipped(name: &str, reason: &str) -> SelfTestCheck {
+    SelfTestCheck {
+        name: name.to_string(),
+        passed: true,
+        detail: Some(format!("skipped: {reason}")),
+    }
+}
+
+fn describe(response: &Response) -> String {
+    serde_json::to_string(response).unwrap_or_else(|_| "an unserializable response".into())
+}
+
+fn expect_error(response: Option<Response>, code: i32, id: &Value) -> Result<(), String> {
+    let response = response.ok_or("no response")?;
+    well_formed(&response)?;
+    match &response.error {
+        Some(error) if error.code == code => {}
+        Some(error) => return Err(format!("expected error {code}, got {}", error.code)),
+        None => return Err(format!("expected error {code}, got a result")),
+    }
+    if response.id.as_ref() != Some(id) {
+        return Err(format!("expected id {id}, got {}", describe(&response)));
+    }
+    Ok(())
+}
+
+fn well_formed(response: &Response) -> Result<(), String> {
+    if response.jsonrpc != "2.0" {
+        return Err(format!("jsonrpc is {:?}", response.jsonrpc));
+    }
+    if response.result.is_some() == response.error.is_some() {
+        return Err("response must carry exactly one of result and error".into());
+    }
+    Ok(())
+}
+
+fn expect_batch(responses: Vec<Response>) -> Result<(), String> {
+    let ids: Vec<Option<Value>> = responses.iter().map(|r| r.id.clone()).collect();
+    if ids != [Some(json!("a")), Some(json!("b"))] {
+        return Err(format!(
+            "expected responses for ids \"a\" and \"b\" only, got {}",
+            serde_json::to_string(&ids).unwrap_or_default()
+        ));
+    }
+    responses.iter().try_for_each(well_formed)
+}
+
+fn expect_rejected_batch(responses: Vec<Response>, max: usize) -> Result<(), String> {
+    match responses.as_slice() {
+        [response] => {
+            well_formed(response)?;
+            match &response.error {
+                Some(error) if error.code == error_codes::INVALID_REQUEST => Ok(()),
+                _ => Err(format!(
+                    "expected INVALID_REQUEST, got {}",
+                    describe(response)
+                )),
+            }
+        }
+        _ => Err(format!(
+            "batch of {} entries was not rejected as a whole",
+            max + 1
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MethodRegistry;
+
+    /// Answers everything, ignoring ids
+    struct Sloppy;
+
+    #[async_trait::async_trait]
+    impl MessageProcessor for Sloppy {
+        async fn process_message(&self, _message: Message) -> Option<Response> {
+            Some(Response::success(json!(true), None))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_registry_conforms() {
+        use crate::builtins::{BuiltinConfig, BuiltinMethods};
+
+        let registry =
+            MethodRegistry::empty().with_builtins(BuiltinConfig::new(BuiltinMethods::DISCOVERY));
+        assert!(registry.has_method("rpc.selftest"));
+
+        let response = registry
+            .process_message(request("rpc.selftest", json!(1)))
+            .await
+            .unwrap();
+        let report: SelfTestReport = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert!(report.passed, "{report:?}");
+        let names: Vec<&str> = report.checks.iter().map(|c| c.name.as_str()).collect();
+        assert!(names.contains(&"batch_size_limit"));
+
+        assert!(!MethodRegistry::empty().has_method("rpc.selftest"));
+    }
+
+    #[tokio::test]
+    async fn test_reports_failures() {
+        let report = run(&Sloppy).await;
+        assert!(!report.passed);
+        let failed: Vec<&str> = report.failures().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            failed,
+            [
+                "method_not_found",
+                "string_id_echoed",
+                "fractional_id_echoed",
+                "notification_unanswered",
+                "response_ignored",
+                "batch",
+                "batch_size_limit"
+            ]
+        );
+        assert_eq!(
+            report.checks[0].detail.as_deref(),
+            Some("expected error -32601, got a result")
+        );
+    }
+}
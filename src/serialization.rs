@@ -0,0 +1,460 @@
+//! Configurable JSON output.
+//!
+//! [`JsonFormat`] controls how responses are rendered: compact or pretty
+//! output, and what happens to non-finite floats. `serde_json` silently
+//! writes `NaN` and `±Infinity` as `null`; with [`NonFiniteFloats::Error`]
+//! such values are reported instead so they never reach a client as a
+//! misleading `null`.
+//!
+//! Object key order is a compile-time choice in `serde_json`: enable the
+//! `preserve-order` feature to keep keys in insertion order (useful for
+//! audit trails and golden files) instead of sorted order.
+//!
+//! A response holds `serde_json::Value`s, which cannot represent non-finite
+//! floats, so the float policy applies where results are converted to a
+//! `Value`: typed methods convert theirs with the format of the transport
+//! they are called through, and other handlers can use
+//! [`JsonFormat::success`] with
+//! [`CallContext::json_format`](crate::CallContext::json_format).
+//!
+//! Transports accept a format through their builders' `json_format` and
+//! hand it to handlers on the [`ConnectionContext`](crate::auth::ConnectionContext).
+//! Only the HTTP transport pretty-prints; the TCP, TLS and WebSocket
+//! transports always write compact frames, since a pretty-printed response
+//! would span several lines.
+
+use serde::Serialize;
+use serde::ser;
+use std::fmt;
+
+/// Handling of `NaN` and `±Infinity`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonFiniteFloats {
+    /// Write `null`, matching `serde_json`
+    #[default]
+    Null,
+    /// Fail serialization
+    Error,
+}
+
+/// JSON output options
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct JsonFormat {
+    pub pretty: bool,
+    pub non_finite: NonFiniteFloats,
+}
+
+impl JsonFormat {
+    /// Compact output, non-finite floats become `null`
+    pub fn compact() -> Self {
+        Self::default()
+    }
+
+    /// Pretty-printed output
+    pub fn pretty() -> Self {
+        Self {
+            pretty: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn non_finite(mut self, policy: NonFiniteFloats) -> Self {
+        self.non_finite = policy;
+        self
+    }
+
+    /// Whether object keys keep insertion order (`preserve-order` feature)
+    pub const fn preserves_key_order() -> bool {
+        cfg!(feature = "preserve-order")
+    }
+
+    /// Serialize `value` to a string
+    pub fn to_string<T: Serialize + ?Sized>(&self, value: &T) -> Result<String, serde_json::Error> {
+        self.check(value)?;
+        if self.pretty {
+            serde_json::to_string_pretty(value)
+        } else {
+            serde_json::to_string(value)
+        }
+    }
+
+    /// Serialize `value` to bytes
+    pub fn to_vec<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, serde_json::Error> {
+        self.check(value)?;
+        if self.pretty {
+            serde_json::to_vec_pretty(value)
+        } else {
+            serde_json::to_vec(value)
+        }
+    }
+
+    /// Convert `value` to a `serde_json::Value`.
+    ///
+    /// Handlers should use this rather than `serde_json::to_value` when the
+    /// result may contain floats, since `Value` cannot represent non-finite
+    /// numbers and the policy has to be applied before conversion.
+    pub fn to_value<T: Serialize + ?Sized>(
+        &self,
+        value: &T,
+    ) -> Result<serde_json::Value, serde_json::Error> {
+        self.check(value)?;
+        serde_json::to_value(value)
+    }
+
+    /// Build a success response from a typed result, or an
+    /// `INTERNAL_ERROR` response if it cannot be serialized
+    pub fn success<T: Serialize + ?Sized>(
+        &self,
+        result: &T,
+        id: Option<crate::RequestId>,
+    ) -> crate::Response {
+        match self.to_value(result) {
+            Ok(value) => crate::Response::success(value, id),
+            Err(e) => {
+                tracing::error!(error = %e, "result serialization failed");
+                crate::Response::error(
+                    crate::ErrorBuilder::new(
+                        crate::error_codes::INTERNAL_ERROR,
+                        "Result could not be serialized",
+                    )
+                    .build(),
+                    id,
+                )
+            }
+        }
+    }
+
+    fn check<T: Serialize + ?Sized>(&self, value: &T) -> Result<(), serde_json::Error> {
+        match self.non_finite {
+            NonFiniteFloats::Null => Ok(()),
+            NonFiniteFloats::Error => value
+                .serialize(FiniteCheck)
+                .map_err(|e| <serde_json::Error as ser::Error>::custom(e.0)),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct NonFinite(String);
+
+impl fmt::Display for NonFinite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for NonFinite {}
+
+impl ser::Error for NonFinite {
+    fn custom<M: fmt::Display>(msg: M) -> Self {
+        NonFinite(msg.to_string())
+    }
+}
+
+/// Serializer that only walks a value looking for non-finite floats
+struct FiniteCheck;
+
+impl FiniteCheck {
+    fn float(v: f64) -> Result<(), NonFinite> {
+        if v.is_finite() {
+            Ok(())
+        } else {
+            Err(NonFinite(format!(
+                "non-finite float {v} cannot be serialized"
+            )))
+        }
+    }
+}
+
+macro_rules! accept {
+    ($($name:ident($ty:ty)),* $(,)?) => {
+        $(fn $name(self, _v: $ty) -> Result<(), NonFinite> { Ok(()) })*
+    };
+}
+
+impl ser::Serializer for FiniteCheck {
+    type Ok = ();
+    type Error = NonFinite;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    accept!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+        serialize_unit_struct(&'static str),
+    );
+
+    fn serialize_f32(self, v: f32) -> Result<(), NonFinite> {
+        Self::float(f64::from(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), NonFinite> {
+        Self::float(v)
+    }
+
+    fn serialize_none(self) -> Result<(), NonFinite> {
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), NonFinite> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), NonFinite> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+    ) -> Result<(), NonFinite> {
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), NonFinite> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<(), NonFinite> {
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self, NonFinite> {
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, NonFinite> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, NonFinite> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, NonFinite> {
+        Ok(self)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self, NonFinite> {
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, NonFinite> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, NonFinite> {
+        Ok(self)
+    }
+}
+
+impl ser::SerializeSeq for FiniteCheck {
+    type Ok = ();
+    type Error = NonFinite;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), NonFinite> {
+        value.serialize(FiniteCheck)
+    }
+
+    fn end(self) -> Result<(), NonFinite> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for FiniteCheck {
+    type Ok = ();
+    type Error = NonFinite;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), NonFinite> {
+        value.serialize(FiniteCheck)
+    }
+
+    fn end(self) -> Result<(), NonFinite> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for FiniteCheck {
+    type Ok = ();
+    type Error = NonFinite;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), NonFinite> {
+        value.serialize(FiniteCheck)
+    }
+
+    fn end(self) -> Result<(), NonFinite> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for FiniteCheck {
+    type Ok = ();
+    type Error = NonFinite;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), NonFinite> {
+        value.serialize(FiniteCheck)
+    }
+
+    fn end(self) -> Result<(), NonFinite> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for FiniteCheck {
+    type Ok = ();
+    type Error = NonFinite;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, _key: &T) -> Result<(), NonFinite> {
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), NonFinite> {
+        value.serialize(FiniteCheck)
+    }
+
+    fn end(self) -> Result<(), NonFinite> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for FiniteCheck {
+    type Ok = ();
+    type Error = NonFinite;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), NonFinite> {
+        value.serialize(FiniteCheck)
+    }
+
+    fn end(self) -> Result<(), NonFinite> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for FiniteCheck {
+    type Ok = ();
+    type Error = NonFinite;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), NonFinite> {
+        value.serialize(FiniteCheck)
+    }
+
+    fn end(self) -> Result<(), NonFinite> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[derive(Serialize)]
+    struct Reading {
+        sensor: &'static str,
+        values: Vec<f64>,
+        tags: HashMap<&'static str, f32>,
+    }
+
+    #[test]
+    fn test_compact_and_pretty() {
+        let value = json!({"a": 1});
+        assert_eq!(
+            JsonFormat::compact().to_string(&value).unwrap(),
+            r#"{"a":1}"#
+        );
+        assert!(
+            JsonFormat::pretty()
+                .to_string(&value)
+                .unwrap()
+                .contains('\n')
+        );
+    }
+
+    #[test]
+    fn test_non_finite_null_by_default() {
+        let reading = Reading {
+            sensor: "t1",
+            values: vec![1.0, f64::NAN],
+            tags: HashMap::new(),
+        };
+        let json = JsonFormat::default().to_string(&reading).unwrap();
+        assert!(json.contains("[1.0,null]"));
+    }
+
+    #[test]
+    fn test_non_finite_error() {
+        let format = JsonFormat::compact().non_finite(NonFiniteFloats::Error);
+
+        let mut tags = HashMap::new();
+        tags.insert("offset", f32::INFINITY);
+        let reading = Reading {
+            sensor: "t1",
+            values: vec![1.0],
+            tags,
+        };
+        assert!(format.to_string(&reading).is_err());
+        assert!(format.to_value(&Some(f64::NEG_INFINITY)).is_err());
+        assert!(format.to_value(&vec![0.5_f64]).is_ok());
+    }
+
+    #[test]
+    fn test_success_response_on_non_finite() {
+        let format = JsonFormat::compact().non_finite(NonFiniteFloats::Error);
+        let response = format.success(&f64::NAN, Some(json!(1)));
+        assert_eq!(
+            response.error.unwrap().code,
+            crate::error_codes::INTERNAL_ERROR
+        );
+        assert!(format.success(&2.5, None).is_success());
+    }
+}
@@ -6,8 +6,10 @@
 //! shared application state through a service context.
 //!
 
+use crate::batch_policy::{self, BatchPolicy};
 use crate::{
-    ErrorBuilder, Message, MessageProcessor, Request, Response, ResponseBuilder, error_codes,
+    ErrorBuilder, Message, MessageProcessor, OpenApiServer, OpenApiSpec, Request, Response,
+    ResponseBuilder, error_codes,
 };
 use std::sync::Arc;
 
@@ -16,6 +18,50 @@ pub trait ServiceContext: Send + Sync + 'static {
     type Error: std::error::Error + Send + Sync + 'static;
 }
 
+/// Context whose changes can be grouped into transactions
+///
+/// Lets a [`StatefulProcessor`] built with
+/// [`transactional`](StatefulProcessorBuilder::transactional) run batches
+/// under [`BatchPolicy::Transactional`]: `begin` before the first entry,
+/// `commit` when every request succeeded and `rollback` after the first
+/// failure. Keeping concurrent calls out of an open transaction is up to
+/// the context.
+#[async_trait::async_trait]
+pub trait TransactionalContext: ServiceContext {
+    async fn begin(&self) -> Result<(), Self::Error>;
+
+    async fn commit(&self) -> Result<(), Self::Error>;
+
+    async fn rollback(&self) -> Result<(), Self::Error>;
+}
+
+/// Transaction hooks of a context known to be transactional
+#[async_trait::async_trait]
+trait Transactions<C: ServiceContext>: Send + Sync {
+    async fn begin(&self, context: &C) -> Result<(), C::Error>;
+
+    async fn commit(&self, context: &C) -> Result<(), C::Error>;
+
+    async fn rollback(&self, context: &C) -> Result<(), C::Error>;
+}
+
+struct ContextTransactions;
+
+#[async_trait::async_trait]
+impl<C: TransactionalContext> Transactions<C> for ContextTransactions {
+    async fn begin(&self, context: &C) -> Result<(), C::Error> {
+        context.begin().await
+    }
+
+    async fn commit(&self, context: &C) -> Result<(), C::Error> {
+        context.commit().await
+    }
+
+    async fn rollback(&self, context: &C) -> Result<(), C::Error> {
+        context.rollback().await
+    }
+}
+
 /// Async trait for stateful JSON-RPC method implementations with context
 #[async_trait::async_trait]
 pub trait StatefulJsonRPCMethod<C: ServiceContext>: Send + Sync {
@@ -52,6 +98,17 @@ pub trait StatefulHandler<C: ServiceContext>: Send + Sync {
         let _ = notification;
         Ok(())
     }
+
+    /// Check if a method is supported
+    fn supports_method(&self, method: &str) -> bool {
+        let _ = method;
+        true
+    }
+
+    /// Get list of supported methods
+    fn get_supported_methods(&self) -> Vec<String> {
+        vec![]
+    }
 }
 
 /// Registry for organizing stateful JSON-RPC methods
@@ -77,6 +134,69 @@ impl<C: ServiceContext> StatefulMethodRegistry<C> {
         self
     }
 
+    /// Add a boxed method implementation to the registry
+    pub fn add_method(mut self, method: Box<dyn StatefulJsonRPCMethod<C>>) -> Self {
+        tracing::trace!("adding stateful method to registry");
+        self.methods.push(method);
+        self
+    }
+
+    /// Check if a method is registered
+    pub fn has_method(&self, method_name: &str) -> bool {
+        self.methods.iter().any(|m| m.method_name() == method_name)
+    }
+
+    /// Get list of all registered methods
+    pub fn get_methods(&self) -> Vec<String> {
+        self.methods
+            .iter()
+            .map(|m| m.method_name().to_string())
+            .collect()
+    }
+
+    /// Get the number of registered methods
+    pub fn method_count(&self) -> usize {
+        self.methods.len()
+    }
+
+    /// Generate OpenAPI specification for all registered methods
+    pub fn generate_openapi_spec(&self, title: &str, version: &str) -> OpenApiSpec {
+        tracing::debug!(method_count = self.methods.len(), "generating openapi spec");
+        let mut spec = OpenApiSpec::new(title, version);
+        for method in &self.methods {
+            spec.add_method(method.openapi_components());
+        }
+        spec
+    }
+
+    /// Generate OpenAPI specification with custom info and servers
+    pub fn generate_openapi_spec_with_info(
+        &self,
+        title: &str,
+        version: &str,
+        description: Option<&str>,
+        servers: Vec<OpenApiServer>,
+    ) -> OpenApiSpec {
+        let mut spec = self.generate_openapi_spec(title, version);
+        if let Some(desc) = description {
+            spec.info.description = Some(desc.to_string());
+        }
+        for server in servers {
+            spec.add_server(server);
+        }
+        spec
+    }
+
+    /// Export OpenAPI spec as JSON string
+    pub fn export_openapi_json(
+        &self,
+        title: &str,
+        version: &str,
+    ) -> Result<String, serde_json::Error> {
+        let spec = self.generate_openapi_spec(title, version);
+        serde_json::to_string_pretty(&spec)
+    }
+
     /// Call a registered method with context
     pub async fn call(
         &self,
@@ -125,12 +245,23 @@ impl<C: ServiceContext> StatefulHandler<C> for StatefulMethodRegistry<C> {
             .await?;
         Ok(())
     }
+
+    fn supports_method(&self, method: &str) -> bool {
+        self.has_method(method)
+    }
+
+    fn get_supported_methods(&self) -> Vec<String> {
+        self.get_methods()
+    }
 }
 
 /// Stateful message processor that wraps a context and handler
 pub struct StatefulProcessor<C: ServiceContext> {
     context: Arc<C>,
     handler: Arc<dyn StatefulHandler<C>>,
+    dead_letters: Option<Arc<crate::dead_letter::DeadLetterQueue>>,
+    batch_policy: BatchPolicy,
+    transactions: Option<Arc<dyn Transactions<C>>>,
 }
 
 impl<C: ServiceContext> StatefulProcessor<C> {
@@ -142,6 +273,9 @@ impl<C: ServiceContext> StatefulProcessor<C> {
         Self {
             context: Arc::new(context),
             handler: Arc::new(handler),
+            dead_letters: None,
+            batch_policy: BatchPolicy::default(),
+            transactions: None,
         }
     }
 
@@ -149,6 +283,75 @@ impl<C: ServiceContext> StatefulProcessor<C> {
     pub fn builder(context: C) -> StatefulProcessorBuilder<C> {
         StatefulProcessorBuilder::new(context)
     }
+
+    /// Deliver a dead-lettered notification again
+    ///
+    /// Returns `None` when no dead letter queue is configured or it holds
+    /// no letter with `id`.
+    pub async fn requeue_dead_letter(&self, id: &str) -> Option<bool> {
+        let queue = self.dead_letters.as_ref()?;
+        queue
+            .requeue(id, |notification| self.deliver_notification(notification))
+            .await
+    }
+
+    async fn deliver_notification(
+        &self,
+        notification: crate::Notification,
+    ) -> Result<(), crate::Error> {
+        self.handler
+            .handle_notification(&self.context, notification)
+            .await
+            .map_err(|e| crate::Error::new(crate::error_codes::INTERNAL_ERROR, e.to_string()))
+    }
+
+    /// Run `messages` as one transaction, rolling back on the first failure
+    async fn process_transaction(&self, messages: Vec<Message>) -> Vec<Response> {
+        let Some(transactions) = &self.transactions else {
+            return vec![batch_policy::unsupported()];
+        };
+        let ids: Vec<_> = messages
+            .iter()
+            .filter_map(|message| match message {
+                Message::Request(request) => Some(request.id.clone()),
+                _ => None,
+            })
+            .collect();
+        if let Err(error) = transactions.begin(&self.context).await {
+            let error = crate::Error::from_error_logged(&error as &dyn std::error::Error);
+            return ids
+                .into_iter()
+                .map(|id| Response::error(error.clone(), id))
+                .collect();
+        }
+
+        let mut responses =
+            batch_policy::execute(BatchPolicy::AbortOnError, messages, |_, message| {
+                self.process_message(message)
+            })
+            .await;
+        let failed = responses.iter().any(|response| response.error.is_some());
+        let finished = if failed {
+            transactions.rollback(&self.context).await
+        } else {
+            transactions.commit(&self.context).await
+        };
+        match finished {
+            Ok(()) if failed => {
+                for response in responses.iter_mut().filter(|r| r.error.is_none()) {
+                    *response = batch_policy::rolled_back(response.id.take());
+                }
+            }
+            Ok(()) => {}
+            Err(error) => {
+                let error = crate::Error::from_error_logged(&error as &dyn std::error::Error);
+                for response in responses.iter_mut().filter(|r| r.error.is_none()) {
+                    *response = Response::error(error.clone(), response.id.take());
+                }
+            }
+        }
+        responses
+    }
 }
 
 #[async_trait::async_trait]
@@ -186,21 +389,52 @@ impl<C: ServiceContext> MessageProcessor for StatefulProcessor<C> {
                 }
             }
             Message::Notification(notification) => {
-                let _ = self
-                    .handler
-                    .handle_notification(&self.context, notification)
-                    .await;
+                match &self.dead_letters {
+                    Some(queue) => {
+                        queue
+                            .deliver(notification, |notification| {
+                                self.deliver_notification(notification)
+                            })
+                            .await;
+                    }
+                    None => {
+                        if let Err(e) = self.deliver_notification(notification).await {
+                            tracing::debug!(error = %e, "stateful notification handler error");
+                        }
+                    }
+                }
                 None
             }
             Message::Response(_) => None,
         }
     }
+
+    async fn process_batch(&self, messages: Vec<Message>) -> Vec<Response> {
+        match BatchPolicy::requested(&messages, self.batch_policy) {
+            BatchPolicy::Transactional => self.process_transaction(messages).await,
+            policy => {
+                batch_policy::execute(policy, messages, |_, message| self.process_message(message))
+                    .await
+            }
+        }
+    }
+
+    async fn process_batch_with_context(
+        &self,
+        messages: Vec<Message>,
+        _ctx: &crate::auth::ConnectionContext,
+    ) -> Vec<Response> {
+        self.process_batch(messages).await
+    }
 }
 
 /// Builder for creating stateful processors
 pub struct StatefulProcessorBuilder<C: ServiceContext> {
     context: C,
     handler: Option<Arc<dyn StatefulHandler<C>>>,
+    dead_letters: Option<crate::dead_letter::DeadLetterQueue>,
+    batch_policy: BatchPolicy,
+    transactions: Option<Arc<dyn Transactions<C>>>,
 }
 
 impl<C: ServiceContext> StatefulProcessorBuilder<C> {
@@ -209,6 +443,9 @@ impl<C: ServiceContext> StatefulProcessorBuilder<C> {
         Self {
             context,
             handler: None,
+            dead_letters: None,
+            batch_policy: BatchPolicy::default(),
+            transactions: None,
         }
     }
 
@@ -227,16 +464,46 @@ impl<C: ServiceContext> StatefulProcessorBuilder<C> {
         self
     }
 
+    /// Retry failed notifications and dead-letter them when retries run out
+    pub fn dead_letters(mut self, queue: crate::dead_letter::DeadLetterQueue) -> Self {
+        self.dead_letters = Some(queue);
+        self
+    }
+
+    /// Default policy for batches that do not ask for one
+    pub fn batch_policy(mut self, policy: BatchPolicy) -> Self {
+        self.batch_policy = policy;
+        self
+    }
+
     /// Build the stateful processor
+    ///
+    /// Fails without a handler, or with a transactional batch policy on a
+    /// builder that is not [`transactional`](Self::transactional).
     pub fn build(self) -> Result<StatefulProcessor<C>, Box<dyn std::error::Error>> {
         let handler = self.handler.ok_or("Handler not set")?;
+        if self.batch_policy == BatchPolicy::Transactional && self.transactions.is_none() {
+            return Err("Transactional batch policy needs a transactional context".into());
+        }
         Ok(StatefulProcessor {
             context: Arc::new(self.context),
             handler,
+            dead_letters: self.dead_letters.map(Arc::new),
+            batch_policy: self.batch_policy,
+            transactions: self.transactions,
         })
     }
 }
 
+impl<C: TransactionalContext> StatefulProcessorBuilder<C> {
+    /// Allow [`BatchPolicy::Transactional`] batches, run with the context's
+    /// transaction hooks
+    pub fn transactional(mut self) -> Self {
+        self.transactions = Some(Arc::new(ContextTransactions));
+        self
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -257,6 +524,8 @@ mod tests {
 
     struct TestContext {
         counter: AtomicU32,
+        /// Counter value when the open transaction began
+        saved: AtomicU32,
     }
 
     impl ServiceContext for TestContext {
@@ -267,6 +536,7 @@ mod tests {
         fn new() -> Self {
             Self {
                 counter: AtomicU32::new(0),
+                saved: AtomicU32::new(0),
             }
         }
 
@@ -279,6 +549,24 @@ mod tests {
         }
     }
 
+    #[async_trait::async_trait]
+    impl TransactionalContext for TestContext {
+        async fn begin(&self) -> Result<(), TestError> {
+            self.saved.store(self.get_count(), Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn commit(&self) -> Result<(), TestError> {
+            Ok(())
+        }
+
+        async fn rollback(&self) -> Result<(), TestError> {
+            self.counter
+                .store(self.saved.load(Ordering::SeqCst), Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
     // Test method implementation
     struct IncrementMethod;
 
@@ -488,6 +776,56 @@ mod tests {
         assert!(response.is_some());
     }
 
+    #[tokio::test]
+    async fn test_stateful_processor_transactional_batch() {
+        let registry = || {
+            StatefulMethodRegistry::new()
+                .register(IncrementMethod)
+                .register(FailingMethod)
+        };
+        let not_transactional = StatefulProcessor::builder(TestContext::new())
+            .registry(registry())
+            .batch_policy(BatchPolicy::Transactional)
+            .build();
+        assert!(not_transactional.is_err());
+
+        let processor = StatefulProcessor::builder(TestContext::new())
+            .registry(registry())
+            .batch_policy(BatchPolicy::Transactional)
+            .transactional()
+            .build()
+            .unwrap();
+        let request = |method: &str, id: i64| {
+            Message::Request(
+                RequestBuilder::new(method)
+                    .id(serde_json::json!(id))
+                    .build(),
+            )
+        };
+
+        let responses = processor
+            .process_batch(vec![
+                request("increment", 1),
+                request("increment", 2),
+                request("fail", 3),
+                request("increment", 4),
+            ])
+            .await;
+        let codes: Vec<_> = responses
+            .iter()
+            .map(|r| r.error.as_ref().unwrap().code)
+            .collect();
+        assert_eq!(codes[..2], [error_codes::DEPENDENT_FAILURE; 2]);
+        assert_eq!(codes[3], error_codes::DEPENDENT_FAILURE);
+        assert_eq!(processor.context.get_count(), 0);
+
+        let responses = processor
+            .process_batch(vec![request("increment", 1), request("increment", 2)])
+            .await;
+        assert!(responses.iter().all(|r| r.result.is_some()));
+        assert_eq!(processor.context.get_count(), 2);
+    }
+
     #[tokio::test]
     async fn test_stateful_processor_builder_no_handler() {
         let context = TestContext::new();
@@ -502,6 +840,34 @@ mod tests {
         assert_eq!(spec.method_name, "increment");
     }
 
+    #[test]
+    fn test_stateful_registry_introspection_and_openapi() {
+        let registry = StatefulMethodRegistry::new()
+            .register(IncrementMethod)
+            .add_method(Box::new(FailingMethod));
+        assert!(registry.has_method("increment"));
+        assert!(registry.supports_method("fail"));
+        assert!(!registry.has_method("missing"));
+        assert_eq!(registry.get_methods(), ["increment", "fail"]);
+        assert_eq!(registry.method_count(), 2);
+
+        let spec = registry.generate_openapi_spec_with_info(
+            "Stateful API",
+            "1.0.0",
+            Some("Counter service"),
+            vec![OpenApiServer::new("http://localhost:8080")],
+        );
+        assert_eq!(spec.methods.len(), 2);
+        assert!(spec.methods.contains_key("increment"));
+        assert_eq!(spec.info.description.as_deref(), Some("Counter service"));
+        assert!(
+            registry
+                .export_openapi_json("Stateful API", "1.0.0")
+                .unwrap()
+                .contains("\"title\": \"Stateful API\"")
+        );
+    }
+
     #[test]
     fn test_stateful_registry_default() {
         let registry = StatefulMethodRegistry::<TestContext>::default();
@@ -2,16 +2,53 @@
 //!
 //! This module provides functionality for long-lived subscriptions and streaming responses,
 //! allowing servers to push events to clients over time.
+//!
+//! Methods that produce a bounded sequence of results, such as a log tail,
+//! implement [`StreamingMethod`] and return a [`ResultStream`]. The same
+//! method can be served in two ways depending on what the transport can do:
+//! [`StreamingMethodHandler`] turns each item into a [`StreamEvent`] for
+//! transports that push events, and [`CollectedStreamMethod`] gathers the
+//! items into one array result for plain request/response transports.
+//!
+//! [`StreamManager::broadcast_to_method`] can be rate limited globally and
+//! per method with [`OutboundRateLimit`], so a broadcast in a tight loop
+//! cannot flood every subscriber. Broadcasts over the limit are dropped or
+//! make the caller wait, depending on the [`ThrottlePolicy`]; drops are
+//! reported as [`RejectionReason::OutboundThrottled`] and show up in the
+//! Prometheus `rejections_total` counter.
+//!
+//! Subscribers can pause a stream with [`PAUSE_METHOD`] and pick it up
+//! again with [`RESUME_METHOD`]. The subscription stays open while paused;
+//! [`StreamManager`] holds its events back, up to
+//! [`with_pause_buffer`](StreamManager::with_pause_buffer) of them, and
+//! delivers them in order on resume.
+//!
+//! [`RejectionReason::OutboundThrottled`]: crate::rejection::RejectionReason::OutboundThrottled
 
 use crate::types::*;
+use futures_core::Stream;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::pin::Pin;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
 use tokio::sync::{RwLock, mpsc};
+use tokio::time::Instant;
 
 /// Unique identifier for a stream/subscription
 pub type StreamId = String;
 
+/// Method of a [`StreamControlRequest`] holding a stream's events back
+pub const PAUSE_METHOD: &str = "rpc.stream.pause";
+
+/// Method of a [`StreamControlRequest`] delivering a paused stream's events
+pub const RESUME_METHOD: &str = "rpc.stream.resume";
+
+/// Events held back per paused stream unless configured otherwise
+pub const DEFAULT_PAUSE_BUFFER: usize = 1024;
+
 /// Stream request for creating a new subscription
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StreamRequest {
@@ -108,6 +145,31 @@ impl StreamResponse {
         }
     }
 
+    /// Confirm that a stream was paused
+    pub fn paused(stream_id: StreamId, id: RequestId) -> Self {
+        Self {
+            result: Some(serde_json::json!({
+                "stream_id": stream_id.clone(),
+                "status": "paused"
+            })),
+            stream_status: Some(StreamStatus::Paused),
+            ..Self::success(stream_id, id)
+        }
+    }
+
+    /// Confirm that a stream was resumed, `dropped` events having been
+    /// lost to the pause buffer cap
+    pub fn resumed(stream_id: StreamId, id: RequestId, dropped: u64) -> Self {
+        Self {
+            result: Some(serde_json::json!({
+                "stream_id": stream_id.clone(),
+                "status": "active",
+                "dropped": dropped
+            })),
+            ..Self::success(stream_id, id)
+        }
+    }
+
     /// Create a stream closed response
     pub fn closed(stream_id: StreamId, id: RequestId) -> Self {
         Self {
@@ -143,6 +205,9 @@ pub struct StreamEvent {
     pub params: serde_json::Value,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sequence: Option<u64>,
+    /// Set on the last event of a stream that ended on its own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<StreamStatus>,
 }
 
 impl StreamEvent {
@@ -154,6 +219,15 @@ impl StreamEvent {
             stream_id,
             params: data,
             sequence: None,
+            status: None,
+        }
+    }
+
+    /// Final event of a finite stream, carrying no data
+    pub fn closed(stream_id: StreamId, method: impl Into<String>) -> Self {
+        Self {
+            status: Some(StreamStatus::Closed),
+            ..Self::new(stream_id, method, serde_json::Value::Null)
         }
     }
 
@@ -205,6 +279,35 @@ impl UnsubscribeRequest {
     }
 }
 
+/// Request to pause or resume a stream, see [`PAUSE_METHOD`] and
+/// [`RESUME_METHOD`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamControlRequest {
+    pub jsonrpc: String,
+    pub method: String,
+    pub stream_id: StreamId,
+    pub id: RequestId,
+}
+
+impl StreamControlRequest {
+    pub fn pause(stream_id: StreamId, id: RequestId) -> Self {
+        Self::new(PAUSE_METHOD, stream_id, id)
+    }
+
+    pub fn resume(stream_id: StreamId, id: RequestId) -> Self {
+        Self::new(RESUME_METHOD, stream_id, id)
+    }
+
+    fn new(method: &str, stream_id: StreamId, id: RequestId) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            stream_id,
+            id,
+        }
+    }
+}
+
 /// Message types for streaming communication
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -290,6 +393,170 @@ pub trait StreamHandler: Send + Sync {
 
     /// Check if a stream is active
     async fn is_active(&self, stream_id: &str) -> bool;
+
+    /// Documentation of the subscribe call, params schema included
+    fn openapi_components(&self) -> crate::OpenApiMethodSpec {
+        crate::OpenApiMethodSpec::new(self.subscription_method())
+    }
+
+    /// Schema of the `params` carried by this subscription's events
+    fn event_schema(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+/// What happens to broadcasts over an [`OutboundRateLimit`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThrottlePolicy {
+    /// Discard the broadcast
+    Drop,
+    /// Make the caller wait until the broadcast fits the rate, discarding it
+    /// if that would take longer than `max_delay`
+    Queue { max_delay: Duration },
+}
+
+/// Token bucket limiting how often events are broadcast
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboundRateLimit {
+    /// Sustained broadcasts per second
+    pub rate_per_sec: f64,
+    /// Broadcasts allowed back to back after an idle period
+    pub burst: u32,
+    pub policy: ThrottlePolicy,
+}
+
+impl OutboundRateLimit {
+    /// `rate_per_sec` with a burst of one second worth of broadcasts,
+    /// dropping the excess
+    pub fn new(rate_per_sec: u32) -> Self {
+        Self {
+            rate_per_sec: f64::from(rate_per_sec.max(1)),
+            burst: rate_per_sec.max(1),
+            policy: ThrottlePolicy::Drop,
+        }
+    }
+
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+
+    pub fn policy(mut self, policy: ThrottlePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+static BROADCASTS_DELAYED: AtomicU64 = AtomicU64::new(0);
+static BROADCASTS_DROPPED: AtomicU64 = AtomicU64::new(0);
+
+/// Broadcasts held back by an outbound rate limit
+pub fn throttled_delayed_total() -> u64 {
+    BROADCASTS_DELAYED.load(Ordering::Relaxed)
+}
+
+/// Broadcasts dropped by an outbound rate limit
+pub fn throttled_dropped_total() -> u64 {
+    BROADCASTS_DROPPED.load(Ordering::Relaxed)
+}
+
+/// GCRA state of one [`OutboundRateLimit`]
+#[derive(Debug)]
+struct Bucket {
+    limit: OutboundRateLimit,
+    /// Theoretical arrival time of the next conforming broadcast
+    tat: Option<Instant>,
+}
+
+impl Bucket {
+    fn new(limit: OutboundRateLimit) -> Self {
+        Self { limit, tat: None }
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_nanos((1e9 / self.limit.rate_per_sec) as u64)
+    }
+
+    /// Wait needed before the next broadcast conforms
+    fn delay(&self, now: Instant) -> Duration {
+        let tolerance = self.interval() * self.limit.burst.saturating_sub(1);
+        let tat = self.tat.map_or(now, |tat| tat.max(now));
+        tat.checked_sub(tolerance)
+            .map_or(Duration::ZERO, |start| start.saturating_duration_since(now))
+    }
+
+    fn max_delay(&self) -> Duration {
+        match self.limit.policy {
+            ThrottlePolicy::Drop => Duration::ZERO,
+            ThrottlePolicy::Queue { max_delay } => max_delay,
+        }
+    }
+
+    fn commit(&mut self, now: Instant) {
+        let tat = self.tat.map_or(now, |tat| tat.max(now));
+        self.tat = Some(tat + self.interval());
+    }
+}
+
+#[derive(Debug, Default)]
+struct Throttle {
+    global: Option<Bucket>,
+    methods: HashMap<String, Bucket>,
+}
+
+impl Throttle {
+    /// Delay before broadcasting on `method`, or `None` to drop it
+    fn admit(&mut self, method: &str, now: Instant) -> Option<Duration> {
+        let Throttle { global, methods } = self;
+        let mut buckets: Vec<(&str, &mut Bucket)> = global
+            .as_mut()
+            .map(|bucket| ("global", bucket))
+            .into_iter()
+            .chain(methods.get_mut(method).map(|bucket| (method, bucket)))
+            .collect();
+
+        let mut delay = Duration::ZERO;
+        for (scope, bucket) in &buckets {
+            let wait = bucket.delay(now);
+            if wait > bucket.max_delay() {
+                BROADCASTS_DROPPED.fetch_add(1, Ordering::Relaxed);
+                tracing::warn!(method, scope, wait = ?wait, "outbound rate limit dropped broadcast");
+                crate::rejection::record(
+                    crate::rejection::Rejection::new(
+                        crate::rejection::RejectionReason::OutboundThrottled,
+                    )
+                    .method(method)
+                    .detail(format!("{scope} limit, would wait {wait:?}")),
+                );
+                return None;
+            }
+            delay = delay.max(wait);
+        }
+        for (_, bucket) in &mut buckets {
+            bucket.commit(now);
+        }
+        if !delay.is_zero() {
+            BROADCASTS_DELAYED.fetch_add(1, Ordering::Relaxed);
+            tracing::debug!(method, delay = ?delay, "outbound rate limit delayed broadcast");
+        }
+        Some(delay)
+    }
+}
+
+/// Events held back for paused streams
+#[derive(Debug, Default)]
+struct Paused {
+    buffers: HashMap<StreamId, PauseBuffer>,
+    /// Events of resumed streams, delivered before anything new
+    ready: VecDeque<StreamEvent>,
+    /// Wakes `next_event` when `ready` fills
+    waker: Option<Waker>,
+}
+
+#[derive(Debug, Default)]
+struct PauseBuffer {
+    events: VecDeque<StreamEvent>,
+    dropped: u64,
 }
 
 /// Manages multiple stream subscriptions
@@ -298,6 +565,9 @@ pub struct StreamManager {
     active_streams: Arc<RwLock<HashMap<StreamId, StreamInfo>>>,
     event_sender: mpsc::UnboundedSender<StreamEvent>,
     event_receiver: Arc<RwLock<mpsc::UnboundedReceiver<StreamEvent>>>,
+    throttle: std::sync::Mutex<Throttle>,
+    paused: std::sync::Mutex<Paused>,
+    pause_buffer: usize,
 }
 
 /// Information about an active stream
@@ -320,9 +590,45 @@ impl StreamManager {
             active_streams: Arc::new(RwLock::new(HashMap::new())),
             event_sender: tx,
             event_receiver: Arc::new(RwLock::new(rx)),
+            throttle: std::sync::Mutex::new(Throttle::default()),
+            paused: std::sync::Mutex::new(Paused::default()),
+            pause_buffer: DEFAULT_PAUSE_BUFFER,
         }
     }
 
+    /// Events held back per paused stream; later ones are dropped, except
+    /// the stream's closing event
+    pub fn with_pause_buffer(mut self, events: usize) -> Self {
+        self.pause_buffer = events;
+        self
+    }
+
+    fn paused(&self) -> std::sync::MutexGuard<'_, Paused> {
+        self.paused.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Limit broadcasts across all methods
+    pub fn with_outbound_limit(mut self, limit: OutboundRateLimit) -> Self {
+        self.throttle_mut().global = Some(Bucket::new(limit));
+        self
+    }
+
+    /// Limit broadcasts to subscribers of `method`
+    pub fn with_method_outbound_limit(
+        mut self,
+        method: impl Into<String>,
+        limit: OutboundRateLimit,
+    ) -> Self {
+        self.throttle_mut()
+            .methods
+            .insert(method.into(), Bucket::new(limit));
+        self
+    }
+
+    fn throttle_mut(&mut self) -> &mut Throttle {
+        self.throttle.get_mut().unwrap_or_else(|e| e.into_inner())
+    }
+
     /// Register a stream handler
     pub async fn register_handler<H>(&self, handler: H)
     where
@@ -337,6 +643,11 @@ impl StreamManager {
         tracing::debug!(method = %method, "stream handler registered");
     }
 
+    /// Whether a handler serves subscriptions to `method`
+    pub async fn has_handler(&self, method: &str) -> bool {
+        self.handlers.read().await.contains_key(method)
+    }
+
     /// Subscribe to a stream
     pub async fn subscribe(&self, request: StreamRequest) -> Result<StreamResponse, crate::Error> {
         let stream_id = request.stream_id();
@@ -354,10 +665,11 @@ impl StreamManager {
         let handler = Arc::clone(handler);
         drop(handlers);
 
-        // Call the handler to subscribe
-        let response = handler
+        // Call the handler to subscribe, answering under the request's id
+        let mut response = handler
             .subscribe(request.params.clone(), stream_id.clone())
             .await?;
+        response.id = request.id.clone();
 
         // Store stream info
         let stream_info = StreamInfo {
@@ -415,15 +727,89 @@ impl StreamManager {
         let mut streams = self.active_streams.write().await;
         streams.remove(stream_id);
         drop(streams);
+        self.paused().buffers.remove(stream_id);
 
         tracing::info!(stream_id = %stream_id, method = %method, "stream unsubscribed");
         Ok(())
     }
 
+    /// Hold back the events of `stream_id` until it is resumed
+    pub async fn pause(&self, stream_id: &str) -> Result<(), crate::Error> {
+        let mut streams = self.active_streams.write().await;
+        let info = streams
+            .get_mut(stream_id)
+            .ok_or_else(|| stream_not_found(stream_id))?;
+        info.status = StreamStatus::Paused;
+        self.paused()
+            .buffers
+            .entry(stream_id.to_string())
+            .or_default();
+        tracing::debug!(stream_id, "stream paused");
+        Ok(())
+    }
+
+    /// Deliver the events held back for `stream_id` and the ones after
+    ///
+    /// Returns the number of events dropped while paused because the
+    /// buffer was full.
+    pub async fn resume(&self, stream_id: &str) -> Result<u64, crate::Error> {
+        let mut streams = self.active_streams.write().await;
+        let info = streams
+            .get_mut(stream_id)
+            .ok_or_else(|| stream_not_found(stream_id))?;
+        info.status = StreamStatus::Active;
+        let mut paused = self.paused();
+        let Some(buffer) = paused.buffers.remove(stream_id) else {
+            return Ok(0);
+        };
+        paused.ready.extend(buffer.events);
+        if let Some(waker) = paused.waker.take() {
+            waker.wake();
+        }
+        tracing::debug!(stream_id, dropped = buffer.dropped, "stream resumed");
+        Ok(buffer.dropped)
+    }
+
     /// Get next event from any active stream
+    ///
+    /// Events of paused streams are held back and returned after the
+    /// stream is resumed.
     pub async fn next_event(&self) -> Option<StreamEvent> {
         let mut receiver = self.event_receiver.write().await;
-        receiver.recv().await
+        std::future::poll_fn(|cx| {
+            loop {
+                let mut paused = self.paused();
+                if let Some(event) = paused.ready.pop_front() {
+                    return Poll::Ready(Some(event));
+                }
+                paused.waker = Some(cx.waker().clone());
+                drop(paused);
+
+                let event = match receiver.poll_recv(cx) {
+                    Poll::Ready(Some(event)) => event,
+                    other => return other,
+                };
+                if let Some(event) = self.hold_back(event) {
+                    return Poll::Ready(Some(event));
+                }
+            }
+        })
+        .await
+    }
+
+    /// Buffer `event` if its stream is paused, otherwise hand it back
+    fn hold_back(&self, event: StreamEvent) -> Option<StreamEvent> {
+        let mut paused = self.paused();
+        let Some(buffer) = paused.buffers.get_mut(&event.stream_id) else {
+            return Some(event);
+        };
+        if buffer.events.len() < self.pause_buffer || event.status.is_some() {
+            buffer.events.push_back(event);
+        } else {
+            buffer.dropped += 1;
+            tracing::warn!(stream_id = %event.stream_id, "pause buffer full, event dropped");
+        }
+        None
     }
 
     /// Get all active stream IDs
@@ -432,6 +818,37 @@ impl StreamManager {
         streams.keys().cloned().collect()
     }
 
+    /// Document every registered subscription in `spec`
+    ///
+    /// Subscription methods are added like regular methods, with an
+    /// `params` schema and the methods that cancel, pause and resume them.
+    /// `params` schema and the method that cancels them.
+    pub async fn document(&self, spec: &mut crate::OpenApiSpec) {
+        let handlers = self.handlers.read().await;
+        for (method, handler) in handlers.iter() {
+            let mut method_spec = handler.openapi_components();
+            if method_spec.result.is_none() {
+                method_spec.result = Some(serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "stream_id": {"type": "string"},
+                        "status": {"type": "string"}
+                    }
+                }));
+            }
+            let subscription = serde_json::json!({
+                "event": {
+                    "method": method,
+                    "params": handler.event_schema().unwrap_or_else(|| serde_json::json!({})),
+                },
+                "unsubscribe": "unsubscribe",
+                "pause": PAUSE_METHOD,
+                "resume": RESUME_METHOD,
+            });
+            spec.add_method(method_spec.with_extension("x-subscription", subscription));
+        }
+    }
+
     /// Get stream info
     pub async fn get_stream_info(&self, stream_id: &str) -> Option<StreamInfo> {
         let streams = self.active_streams.read().await;
@@ -484,35 +901,284 @@ impl StreamManager {
     }
 
     /// Broadcast event to all subscribers of a method
-    pub async fn broadcast_to_method(&self, method: &str, data: serde_json::Value) {
-        let streams = self.active_streams.read().await;
-        let matching_streams: Vec<_> = streams
-            .values()
-            .filter(|info| info.method == method && info.status == StreamStatus::Active)
-            .collect();
+    ///
+    /// Subject to the configured outbound rate limits; returns whether the
+    /// broadcast was sent.
+    pub async fn broadcast_to_method(&self, method: &str, data: serde_json::Value) -> bool {
+        let admitted = self
+            .throttle
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .admit(method, Instant::now());
+        match admitted {
+            None => return false,
+            Some(delay) if !delay.is_zero() => tokio::time::sleep(delay).await,
+            Some(_) => {}
+        }
+
+        let mut streams = self.active_streams.write().await;
+        let matching_streams = streams.values_mut().filter(|info| {
+            info.method == method
+                && matches!(info.status, StreamStatus::Active | StreamStatus::Paused)
+        });
 
         for stream_info in matching_streams {
-            let sequence = self.increment_sequence(&stream_info.stream_id).await;
-            let event = StreamEvent::new(stream_info.stream_id.clone(), method, data.clone());
-            let event = if let Some(seq) = sequence {
-                event.with_sequence(seq)
-            } else {
-                event
-            };
+            stream_info.sequence += 1;
+            let event = StreamEvent::new(stream_info.stream_id.clone(), method, data.clone())
+                .with_sequence(stream_info.sequence);
 
             if self.event_sender.send(event).is_err() {
                 tracing::error!(stream_id = %stream_info.stream_id, "failed to send event");
             }
         }
+        true
     }
 }
 
+fn stream_not_found(stream_id: &str) -> crate::Error {
+    crate::ErrorBuilder::new(
+        crate::error_codes::INVALID_PARAMS,
+        format!("Stream not found: {}", stream_id),
+    )
+    .build()
+}
+
 impl Default for StreamManager {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// Results of a [`StreamingMethod`], in order
+pub type ResultStream = Pin<Box<dyn Stream<Item = serde_json::Value> + Send>>;
+
+/// Wrap any stream of JSON values as a [`ResultStream`]
+pub fn result_stream<S>(stream: S) -> ResultStream
+where
+    S: Stream<Item = serde_json::Value> + Send + 'static,
+{
+    Box::pin(stream)
+}
+
+/// A [`ResultStream`] fed through a bounded channel
+///
+/// The stream ends once every sender has been dropped.
+pub fn result_channel(buffer: usize) -> (mpsc::Sender<serde_json::Value>, ResultStream) {
+    let (tx, rx) = mpsc::channel(buffer);
+    (tx, Box::pin(ChannelStream(rx)))
+}
+
+struct ChannelStream(mpsc::Receiver<serde_json::Value>);
+
+impl Stream for ChannelStream {
+    type Item = serde_json::Value;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.poll_recv(cx)
+    }
+}
+
+async fn next_item(stream: &mut ResultStream) -> Option<serde_json::Value> {
+    std::future::poll_fn(|cx| stream.as_mut().poll_next(cx)).await
+}
+
+/// A method whose result is a sequence of values produced over time
+#[async_trait::async_trait]
+pub trait StreamingMethod: Send + Sync {
+    fn method_name(&self) -> &'static str;
+
+    /// Validate `params` and start producing results
+    async fn open(&self, params: Option<serde_json::Value>) -> Result<ResultStream, crate::Error>;
+
+    /// Documentation of the method, params schema included
+    fn openapi_components(&self) -> crate::OpenApiMethodSpec {
+        crate::OpenApiMethodSpec::new(self.method_name())
+    }
+
+    /// Schema of a single produced item
+    fn item_schema(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+#[async_trait::async_trait]
+impl<M: StreamingMethod + ?Sized> StreamingMethod for Arc<M> {
+    fn method_name(&self) -> &'static str {
+        (**self).method_name()
+    }
+
+    async fn open(&self, params: Option<serde_json::Value>) -> Result<ResultStream, crate::Error> {
+        (**self).open(params).await
+    }
+
+    fn openapi_components(&self) -> crate::OpenApiMethodSpec {
+        (**self).openapi_components()
+    }
+
+    fn item_schema(&self) -> Option<serde_json::Value> {
+        (**self).item_schema()
+    }
+}
+
+/// Serves a [`StreamingMethod`] as a subscription
+///
+/// Every item is sent as a [`StreamEvent`] named after the method, and a
+/// [`StreamEvent::closed`] event follows the last one. Errors from
+/// [`StreamingMethod::open`] are returned from the subscribe call itself.
+pub struct StreamingMethodHandler<M> {
+    method: M,
+    pending: std::sync::Mutex<HashMap<StreamId, ResultStream>>,
+    active: Arc<RwLock<HashSet<StreamId>>>,
+}
+
+impl<M: StreamingMethod> StreamingMethodHandler<M> {
+    pub fn new(method: M) -> Self {
+        Self {
+            method,
+            pending: std::sync::Mutex::new(HashMap::new()),
+            active: Arc::new(RwLock::new(HashSet::new())),
+        }
+    }
+
+    fn take_pending(&self, stream_id: &str) -> Option<ResultStream> {
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(stream_id)
+    }
+}
+
+#[async_trait::async_trait]
+impl<M: StreamingMethod> StreamHandler for StreamingMethodHandler<M> {
+    fn subscription_method(&self) -> &'static str {
+        self.method.method_name()
+    }
+
+    async fn subscribe(
+        &self,
+        params: Option<serde_json::Value>,
+        stream_id: StreamId,
+    ) -> Result<StreamResponse, crate::Error> {
+        let stream = self.method.open(params).await?;
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(stream_id.clone(), stream);
+        self.active.write().await.insert(stream_id.clone());
+        Ok(StreamResponse::success(stream_id, serde_json::Value::Null))
+    }
+
+    async fn unsubscribe(&self, stream_id: &str) -> Result<(), crate::Error> {
+        self.take_pending(stream_id);
+        self.active.write().await.remove(stream_id);
+        Ok(())
+    }
+
+    async fn start_stream(
+        &self,
+        stream_id: StreamId,
+        _params: Option<serde_json::Value>,
+        sender: mpsc::UnboundedSender<StreamEvent>,
+    ) -> Result<(), crate::Error> {
+        let Some(mut stream) = self.take_pending(&stream_id) else {
+            return Ok(());
+        };
+        let method = self.method.method_name();
+        let mut sequence = 0;
+        while let Some(item) = next_item(&mut stream).await {
+            if !self.active.read().await.contains(&stream_id) {
+                tracing::debug!(stream_id = %stream_id, "streaming method cancelled");
+                return Ok(());
+            }
+            sequence += 1;
+            let event = StreamEvent::new(stream_id.clone(), method, item).with_sequence(sequence);
+            if sender.send(event).is_err() {
+                break;
+            }
+        }
+        if self.active.write().await.remove(&stream_id) {
+            let _ = sender.send(StreamEvent::closed(stream_id, method));
+        }
+        Ok(())
+    }
+
+    async fn is_active(&self, stream_id: &str) -> bool {
+        self.active.read().await.contains(stream_id)
+    }
+
+    fn openapi_components(&self) -> crate::OpenApiMethodSpec {
+        self.method.openapi_components()
+    }
+
+    fn event_schema(&self) -> Option<serde_json::Value> {
+        self.method.item_schema()
+    }
+}
+
+/// Serves a [`StreamingMethod`] as a regular method returning an array
+///
+/// For transports without server push. Streams longer than the item limit
+/// fail with `INVALID_REQUEST` rather than building an unbounded response.
+pub struct CollectedStreamMethod<M> {
+    method: M,
+    max_items: usize,
+}
+
+impl<M: StreamingMethod> CollectedStreamMethod<M> {
+    /// Collect up to 10 000 items
+    pub fn new(method: M) -> Self {
+        Self {
+            method,
+            max_items: 10_000,
+        }
+    }
+
+    pub fn max_items(mut self, max_items: usize) -> Self {
+        self.max_items = max_items;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl<M: StreamingMethod> crate::JsonRPCMethod for CollectedStreamMethod<M> {
+    fn method_name(&self) -> &'static str {
+        self.method.method_name()
+    }
+
+    async fn call(&self, params: Option<serde_json::Value>, id: Option<RequestId>) -> Response {
+        let mut stream = match self.method.open(params).await {
+            Ok(stream) => stream,
+            Err(error) => return Response::error(error, id),
+        };
+        let mut items = Vec::new();
+        while let Some(item) = next_item(&mut stream).await {
+            if items.len() == self.max_items {
+                return Response::error(
+                    crate::ErrorBuilder::new(
+                        crate::error_codes::INVALID_REQUEST,
+                        format!(
+                            "Stream exceeds {} items, subscribe to it instead",
+                            self.max_items
+                        ),
+                    )
+                    .build(),
+                    id,
+                );
+            }
+            items.push(item);
+        }
+        Response::success(serde_json::Value::Array(items), id)
+    }
+
+    fn openapi_components(&self) -> crate::OpenApiMethodSpec {
+        let spec = self.method.openapi_components();
+        match self.method.item_schema() {
+            Some(item) => spec.with_result(serde_json::json!({"type": "array", "items": item})),
+            None => spec,
+        }
+    }
+}
+
 /// Builder for creating stream requests
 pub struct StreamRequestBuilder {
     method: String,
@@ -972,4 +1638,216 @@ mod tests {
         assert_eq!(json["stream_id"], "stream-123");
         assert_eq!(json["sequence"], 42);
     }
+
+    /// Emits the integers in `[from, to)`
+    struct Range;
+
+    #[async_trait::async_trait]
+    impl StreamingMethod for Range {
+        fn method_name(&self) -> &'static str {
+            "range"
+        }
+
+        async fn open(
+            &self,
+            params: Option<serde_json::Value>,
+        ) -> Result<ResultStream, crate::Error> {
+            let params = params.unwrap_or_default();
+            let (Some(from), Some(to)) = (params["from"].as_u64(), params["to"].as_u64()) else {
+                return Err(crate::ErrorBuilder::new(
+                    crate::error_codes::INVALID_PARAMS,
+                    "from and to are required",
+                )
+                .build());
+            };
+            let (tx, stream) = result_channel(4);
+            tokio::spawn(async move {
+                for n in from..to {
+                    if tx.send(json!(n)).await.is_err() {
+                        break;
+                    }
+                }
+            });
+            Ok(stream)
+        }
+
+        fn openapi_components(&self) -> crate::OpenApiMethodSpec {
+            crate::OpenApiMethodSpec::new("range").with_parameters(json!({
+                "type": "object",
+                "required": ["from", "to"]
+            }))
+        }
+
+        fn item_schema(&self) -> Option<serde_json::Value> {
+            Some(json!({"type": "integer"}))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscriptions_documented() {
+        let manager = StreamManager::new();
+        manager
+            .register_handler(StreamingMethodHandler::new(Range))
+            .await;
+        let mut spec = crate::OpenApiSpec::new("streams", "1.0.0");
+        manager.document(&mut spec).await;
+
+        let range = serde_json::to_value(&spec.methods["range"]).unwrap();
+        assert_eq!(range["parameters"]["required"], json!(["from", "to"]));
+        assert_eq!(
+            range["x-subscription"],
+            json!({
+                "event": {"method": "range", "params": {"type": "integer"}},
+                "unsubscribe": "unsubscribe",
+                "pause": "rpc.stream.pause",
+                "resume": "rpc.stream.resume"
+            })
+        );
+
+        let collected =
+            crate::JsonRPCMethod::openapi_components(&CollectedStreamMethod::new(Range));
+        assert_eq!(
+            collected.result,
+            Some(json!({"type": "array", "items": {"type": "integer"}}))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_streaming_method_as_subscription() {
+        let manager = StreamManager::new();
+        manager
+            .register_handler(StreamingMethodHandler::new(Range))
+            .await;
+
+        let request =
+            StreamRequest::new("range", json!(1)).with_params(json!({"from": 3, "to": 6}));
+        manager.subscribe(request).await.unwrap();
+
+        let mut items = Vec::new();
+        loop {
+            let event = manager.next_event().await.unwrap();
+            if event.status == Some(StreamStatus::Closed) {
+                break;
+            }
+            items.push((event.sequence.unwrap(), event.params));
+        }
+        assert_eq!(items, vec![(1, json!(3)), (2, json!(4)), (3, json!(5))]);
+
+        let invalid = StreamRequest::new("range", json!(2));
+        assert!(manager.subscribe(invalid).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_streaming_method_collected() {
+        use crate::JsonRPCMethod;
+
+        let method = CollectedStreamMethod::new(Arc::new(Range)).max_items(5);
+        let response = method
+            .call(Some(json!({"from": 0, "to": 3})), Some(json!(1)))
+            .await;
+        assert_eq!(response.result, Some(json!([0, 1, 2])));
+
+        let too_long = method
+            .call(Some(json!({"from": 0, "to": 100})), Some(json!(2)))
+            .await;
+        assert_eq!(
+            too_long.error.unwrap().code,
+            crate::error_codes::INVALID_REQUEST
+        );
+
+        let closed = serde_json::to_value(StreamEvent::closed("s".into(), "range")).unwrap();
+        assert_eq!(closed["status"], "closed");
+    }
+
+    #[test]
+    fn test_throttle_global_and_method_limits() {
+        let mut throttle = Throttle {
+            global: Some(Bucket::new(OutboundRateLimit::new(10).burst(3))),
+            methods: HashMap::from([(
+                "prices".to_string(),
+                Bucket::new(
+                    OutboundRateLimit::new(1)
+                        .burst(1)
+                        .policy(ThrottlePolicy::Queue {
+                            max_delay: Duration::from_millis(1500),
+                        }),
+                ),
+            )]),
+        };
+        let now = Instant::now();
+
+        assert_eq!(throttle.admit("prices", now), Some(Duration::ZERO));
+        assert_eq!(throttle.admit("prices", now), Some(Duration::from_secs(1)));
+
+        let dropped = throttled_dropped_total();
+        assert_eq!(throttle.admit("prices", now), None);
+        assert!(throttled_dropped_total() > dropped);
+
+        // the global burst of 3 is used up by the two admitted broadcasts
+        assert_eq!(throttle.admit("other", now), Some(Duration::ZERO));
+        assert_eq!(throttle.admit("other", now), None);
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_rate_limited() {
+        let manager = StreamManager::new().with_method_outbound_limit(
+            "range",
+            OutboundRateLimit::new(20)
+                .burst(1)
+                .policy(ThrottlePolicy::Queue {
+                    max_delay: Duration::from_secs(1),
+                }),
+        );
+        manager
+            .register_handler(StreamingMethodHandler::new(Range))
+            .await;
+        let request =
+            StreamRequest::new("range", json!(1)).with_params(json!({"from": 0, "to": 0}));
+        manager.subscribe(request).await.unwrap();
+
+        let started = Instant::now();
+        assert!(manager.broadcast_to_method("range", json!("a")).await);
+        assert!(manager.broadcast_to_method("range", json!("b")).await);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+
+        let mut sequences = Vec::new();
+        while sequences.len() < 2 {
+            let event = manager.next_event().await.unwrap();
+            if event.status.is_none() {
+                sequences.push(event.sequence.unwrap());
+            }
+        }
+        assert_eq!(sequences, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_pause_buffers_until_resume() {
+        let manager = StreamManager::new().with_pause_buffer(2);
+        manager
+            .register_handler(StreamingMethodHandler::new(Range))
+            .await;
+        let request = StreamRequest::new("range", json!(1))
+            .with_stream_id("s-1")
+            .with_params(json!({"from": 0, "to": 0}));
+        manager.subscribe(request).await.unwrap();
+        let closed = manager.next_event().await.unwrap();
+        assert_eq!(closed.status, Some(StreamStatus::Closed));
+
+        manager.pause("s-1").await.unwrap();
+        assert_eq!(
+            manager.get_stream_info("s-1").await.unwrap().status,
+            StreamStatus::Paused
+        );
+        for data in ["a", "b", "c"] {
+            assert!(manager.broadcast_to_method("range", json!(data)).await);
+        }
+        let held = tokio::time::timeout(Duration::from_millis(50), manager.next_event()).await;
+        assert!(held.is_err());
+
+        assert_eq!(manager.resume("s-1").await.unwrap(), 1);
+        let first = manager.next_event().await.unwrap();
+        let second = manager.next_event().await.unwrap();
+        assert_eq!((first.params, second.params), (json!("a"), json!("b")));
+        assert!(manager.pause("missing").await.is_err());
+    }
 }
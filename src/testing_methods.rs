@@ -0,0 +1,292 @@
+//! Fault-injection methods for client integration tests
+//!
+//! `test.delay` answers after an exact delay and `test.flaky` fails with a
+//! given probability, which is enough to exercise client timeouts, retries
+//! and circuit breakers against a real server:
+//!
+//! ```json
+//! {"jsonrpc": "2.0", "method": "test.delay", "params": {"ms": 250}, "id": 1}
+//! {"jsonrpc": "2.0", "method": "test.flaky", "params": {"error_rate": 0.3}, "id": 2}
+//! ```
+//!
+//! Both also accept a single positional param (`[250]`, `[0.3]`).
+//!
+//! These methods must not end up in production by accident, so
+//! [`TestingMethods::methods`] returns nothing in release builds unless
+//! [`TestingMethods::allow_in_release`] was set, and delays are capped by
+//! [`TestingMethods::max_delay`]. Failures come from a seeded generator, so
+//! a fixed [`seed`](TestingMethods::seed) yields the same sequence of
+//! outcomes on every run.
+//!
+//! ```rust
+//! use ash_rpc::testing_methods::TestingMethods;
+//! use ash_rpc::MethodRegistry;
+//!
+//! let registry = MethodRegistry::new(TestingMethods::new().seed(7).methods());
+//! # let _ = registry;
+//! ```
+
+use crate::*;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Error code of failures injected by `test.flaky`
+pub const INJECTED_FAILURE: i32 = -32050;
+
+/// Configuration for the `test.*` methods
+#[derive(Debug, Clone)]
+pub struct TestingMethods {
+    max_delay: Duration,
+    seed: u64,
+    allow_in_release: bool,
+}
+
+impl TestingMethods {
+    /// Delays up to 10 seconds, debug builds only
+    pub fn new() -> Self {
+        Self {
+            max_delay: Duration::from_secs(10),
+            seed: 0x9e37_79b9_7f4a_7c15,
+            allow_in_release: false,
+        }
+    }
+
+    /// Longest delay `test.delay` accepts
+    pub fn max_delay(mut self, max: Duration) -> Self {
+        self.max_delay = max;
+        self
+    }
+
+    /// Seed for the failure sequence of `test.flaky`
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Register the methods in release builds too
+    pub fn allow_in_release(mut self, allow: bool) -> Self {
+        self.allow_in_release = allow;
+        self
+    }
+
+    /// Whether [`methods`](Self::methods) will return anything
+    pub fn is_enabled(&self) -> bool {
+        cfg!(debug_assertions) || self.allow_in_release
+    }
+
+    /// The `test.*` methods, or none when disabled for this build
+    pub fn methods(self) -> Vec<Box<dyn JsonRPCMethod>> {
+        if !self.is_enabled() {
+            tracing::warn!("testing methods are disabled in release builds");
+            return Vec::new();
+        }
+        register_methods![
+            TestDelayMethod {
+                max: self.max_delay
+            },
+            TestFlakyMethod {
+                state: Mutex::new(self.seed | 1),
+            }
+        ]
+    }
+}
+
+impl Default for TestingMethods {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Deserialize)]
+struct DelayParams {
+    ms: u64,
+}
+
+#[derive(Deserialize)]
+struct FlakyParams {
+    error_rate: f64,
+}
+
+/// Named (`{"ms": 5}`) or single positional (`[5]`) params
+fn parse<T: serde::de::DeserializeOwned>(
+    params: Option<serde_json::Value>,
+    field: &str,
+) -> Option<T> {
+    let params = match params? {
+        serde_json::Value::Array(mut values) if values.len() == 1 => {
+            json!({ field: values.pop() })
+        }
+        other => other,
+    };
+    serde_json::from_value(params).ok()
+}
+
+/// `test.delay`: answers after exactly `ms` milliseconds
+pub struct TestDelayMethod {
+    max: Duration,
+}
+
+#[crate::async_trait]
+impl JsonRPCMethod for TestDelayMethod {
+    fn method_name(&self) -> &'static str {
+        "test.delay"
+    }
+
+    async fn call(&self, params: Option<serde_json::Value>, id: Option<RequestId>) -> Response {
+        let Some(DelayParams { ms }) = parse(params, "ms") else {
+            return rpc_invalid_params!("expected {\"ms\": <milliseconds>}", id);
+        };
+        let delay = Duration::from_millis(ms);
+        if delay > self.max {
+            return rpc_invalid_params!(format!("ms must not exceed {}", self.max.as_millis()), id);
+        }
+        tokio::time::sleep(delay).await;
+        rpc_success!(json!({ "slept_ms": ms }), id)
+    }
+
+    fn openapi_components(&self) -> OpenApiMethodSpec {
+        OpenApiMethodSpec::new(self.method_name())
+            .with_summary("Answer after a fixed delay (testing only)")
+            .with_tag("testing")
+            .with_parameters(json!({
+                "type": "object",
+                "required": ["ms"],
+                "properties": {
+                    "ms": {"type": "integer", "minimum": 0, "maximum": self.max.as_millis() as u64}
+                }
+            }))
+    }
+}
+
+/// `test.flaky`: fails with probability `error_rate`
+pub struct TestFlakyMethod {
+    state: Mutex<u64>,
+}
+
+impl TestFlakyMethod {
+    /// Next value of a xorshift64 sequence, scaled to `[0, 1)`
+    fn next_unit(&self) -> f64 {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        (*state >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[crate::async_trait]
+impl JsonRPCMethod for TestFlakyMethod {
+    fn method_name(&self) -> &'static str {
+        "test.flaky"
+    }
+
+    async fn call(&self, params: Option<serde_json::Value>, id: Option<RequestId>) -> Response {
+        let error_rate = match parse::<FlakyParams>(params, "error_rate") {
+            Some(FlakyParams { error_rate }) if (0.0..=1.0).contains(&error_rate) => error_rate,
+            _ => {
+                return rpc_invalid_params!("expected {\"error_rate\": <0.0..=1.0>}", id);
+            }
+        };
+        if self.next_unit() < error_rate {
+            rpc_error!(INJECTED_FAILURE, "Injected failure", id)
+        } else {
+            rpc_success!(json!({ "ok": true }), id)
+        }
+    }
+
+    fn openapi_components(&self) -> OpenApiMethodSpec {
+        OpenApiMethodSpec::new(self.method_name())
+            .with_summary("Fail with the given probability (testing only)")
+            .with_tag("testing")
+            .with_parameters(json!({
+                "type": "object",
+                "required": ["error_rate"],
+                "properties": {
+                    "error_rate": {"type": "number", "minimum": 0.0, "maximum": 1.0}
+                }
+            }))
+            .with_error(OpenApiError::new(INJECTED_FAILURE, "Injected failure"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn outcomes(seed: u64) -> Vec<bool> {
+        let registry = MethodRegistry::new(TestingMethods::new().seed(seed).methods());
+        let mut outcomes = Vec::new();
+        for i in 0..50 {
+            let response = registry
+                .call("test.flaky", Some(json!([0.5])), Some(json!(i)))
+                .await;
+            outcomes.push(response.is_error());
+        }
+        outcomes
+    }
+
+    #[tokio::test]
+    async fn test_flaky_is_deterministic() {
+        let first = outcomes(42).await;
+        assert_eq!(first, outcomes(42).await);
+        assert!(first.iter().any(|failed| *failed));
+        assert!(first.iter().any(|failed| !*failed));
+    }
+
+    #[tokio::test]
+    async fn test_flaky_extremes_and_validation() {
+        let registry = MethodRegistry::new(TestingMethods::new().methods());
+        let never = registry
+            .call(
+                "test.flaky",
+                Some(json!({"error_rate": 0.0})),
+                Some(json!(1)),
+            )
+            .await;
+        assert_eq!(never.result, Some(json!({"ok": true})));
+
+        let always = registry
+            .call(
+                "test.flaky",
+                Some(json!({"error_rate": 1.0})),
+                Some(json!(2)),
+            )
+            .await;
+        assert_eq!(always.error.unwrap().code, INJECTED_FAILURE);
+
+        let invalid = registry
+            .call(
+                "test.flaky",
+                Some(json!({"error_rate": 2.0})),
+                Some(json!(3)),
+            )
+            .await;
+        assert_eq!(invalid.error.unwrap().code, error_codes::INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn test_delay_capped() {
+        let registry = MethodRegistry::new(
+            TestingMethods::new()
+                .max_delay(Duration::from_millis(50))
+                .methods(),
+        );
+        let ok = registry
+            .call("test.delay", Some(json!([10])), Some(json!(1)))
+            .await;
+        assert_eq!(ok.result, Some(json!({"slept_ms": 10})));
+
+        let too_long = registry
+            .call("test.delay", Some(json!({"ms": 60_000})), Some(json!(2)))
+            .await;
+        assert_eq!(too_long.error.unwrap().code, error_codes::INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_release_gate() {
+        assert_eq!(TestingMethods::new().is_enabled(), cfg!(debug_assertions));
+        assert!(TestingMethods::new().allow_in_release(true).is_enabled());
+    }
+}
@@ -0,0 +1,80 @@
+//! Per-method timeouts and deadline propagation.
+//!
+//! A registry configured with [`MethodRegistry::with_timeouts`](crate::MethodRegistry::with_timeouts)
+//! stops waiting for a method once its timeout elapses, dropping the
+//! method's future and answering with
+//! [`REQUEST_TIMEOUT`](crate::error_codes::REQUEST_TIMEOUT), whose `data`
+//! holds the `timeout_ms` that applied.
+//!
+//! The deadline of a timed call is stored on the
+//! [`ConnectionContext`](crate::auth::ConnectionContext) the method sees, so
+//! nested calls made with that context inherit the remaining time: a call
+//! never gets longer than its own timeout or what is left of its caller's
+//! deadline, whichever ends first. Contexts arriving with a deadline that
+//! has already passed are answered without calling the method.
+//!
+//! ```rust
+//! use ash_rpc::timeout::TimeoutPolicy;
+//! use std::time::Duration;
+//!
+//! let policy = TimeoutPolicy::new()
+//!     .default_timeout(Duration::from_secs(5))
+//!     .method("reports.build", Duration::from_secs(60));
+//!
+//! assert_eq!(policy.timeout_for("reports.build"), Some(Duration::from_secs(60)));
+//! assert_eq!(policy.timeout_for("ping"), Some(Duration::from_secs(5)));
+//! ```
+
+use crate::{ErrorBuilder, RequestId, Response, ResponseBuilder, error_codes};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// How long methods may run
+#[derive(Debug, Clone, Default)]
+pub struct TimeoutPolicy {
+    default: Option<Duration>,
+    methods: HashMap<String, Duration>,
+}
+
+impl TimeoutPolicy {
+    /// Policy without timeouts
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Timeout of methods without their own
+    pub fn default_timeout(mut self, timeout: Duration) -> Self {
+        self.default = Some(timeout);
+        self
+    }
+
+    /// Timeout of `method`, overriding the default
+    pub fn method(mut self, method: impl Into<String>, timeout: Duration) -> Self {
+        self.methods.insert(method.into(), timeout);
+        self
+    }
+
+    pub fn timeout_for(&self, method: &str) -> Option<Duration> {
+        self.methods.get(method).copied().or(self.default)
+    }
+
+    /// The default timeout, rounded up to whole seconds, as advertised in
+    /// [`ProcessorCapabilities::request_timeout_secs`](crate::ProcessorCapabilities::request_timeout_secs)
+    pub(crate) fn default_secs(&self) -> Option<u64> {
+        self.default
+            .map(|timeout| timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0))
+    }
+}
+
+/// Answer to a call of `method` that ran out of `timeout`
+pub(crate) fn timed_out(method: &str, timeout: Duration, id: Option<RequestId>) -> Response {
+    tracing::warn!(method = %method, timeout_ms = timeout.as_millis() as u64, "method timed out");
+    ResponseBuilder::new()
+        .error(
+            ErrorBuilder::new(error_codes::REQUEST_TIMEOUT, "Request timed out")
+                .data(serde_json::json!({ "timeout_ms": timeout.as_millis() as u64 }))
+                .build(),
+        )
+        .id(id)
+        .build()
+}
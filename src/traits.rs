@@ -8,15 +8,286 @@ use std::collections::HashMap;
 #[async_trait::async_trait]
 pub trait JsonRPCMethod: Send + Sync {
     /// Get the method name that this implementation handles
-    fn method_name(&self) -> &'static str;
+    ///
+    /// Most methods return a `&'static str` literal; wrappers can return a
+    /// name they own.
+    fn method_name(&self) -> &str;
 
     /// Execute the JSON-RPC method asynchronously
     async fn call(&self, params: Option<serde_json::Value>, id: Option<RequestId>) -> Response;
 
+    /// Execute the method with access to the call context
+    ///
+    /// The registry always dispatches through this method. Override it to
+    /// read connection details or feature flags; the default forwards to
+    /// [`call`](Self::call).
+    async fn call_with_context(
+        &self,
+        params: Option<serde_json::Value>,
+        id: Option<RequestId>,
+        ctx: &CallContext<'_>,
+    ) -> Response {
+        let _ = ctx;
+        self.call(params, id).await
+    }
+
     /// Get OpenAPI components for this method
     fn openapi_components(&self) -> OpenApiMethodSpec {
         OpenApiMethodSpec::new(self.method_name())
     }
+
+    /// Constant result for calls without params
+    ///
+    /// Methods whose result never changes (ping, version) can return it here.
+    /// The registry serializes it once and transports answer parameterless
+    /// requests from those bytes without dispatching.
+    fn static_result(&self) -> Option<serde_json::Value> {
+        None
+    }
+
+    /// Scopes, roles and rate limit class callers of this method are held to
+    ///
+    /// The registry enforces and documents them, see
+    /// [`crate::method_security`].
+    fn security(&self) -> crate::method_security::MethodSecurity {
+        crate::method_security::MethodSecurity::default()
+    }
+
+    /// Whether this is a built-in allowed under a
+    /// [reserved namespace](crate::namespaces)
+    fn is_builtin(&self) -> bool {
+        false
+    }
+}
+
+/// Information about the current call, passed to
+/// [`JsonRPCMethod::call_with_context`]
+#[derive(Clone, Copy)]
+pub struct CallContext<'a> {
+    /// Connection the request arrived on
+    pub connection: &'a crate::auth::ConnectionContext,
+    flags: Option<&'a dyn crate::feature_flags::FeatureFlagProvider>,
+    cancellation: Option<&'a crate::cancellation::CancellationToken>,
+}
+
+impl<'a> CallContext<'a> {
+    pub fn new(connection: &'a crate::auth::ConnectionContext) -> Self {
+        Self {
+            connection,
+            flags: None,
+            cancellation: None,
+        }
+    }
+
+    /// Caller identity, see [`ConnectionContext::principal`](crate::auth::ConnectionContext::principal)
+    pub fn principal(&self) -> Option<&'a crate::auth::Principal> {
+        self.connection.principal()
+    }
+
+    pub fn with_flags(mut self, flags: &'a dyn crate::feature_flags::FeatureFlagProvider) -> Self {
+        self.flags = Some(flags);
+        self
+    }
+
+    /// Feature flag provider configured on the registry, if any
+    pub fn flags(&self) -> Option<&'a dyn crate::feature_flags::FeatureFlagProvider> {
+        self.flags
+    }
+
+    /// Whether `flag` is enabled; false when no provider is configured
+    pub fn flag_enabled(&self, flag: &str) -> bool {
+        self.flags.is_some_and(|flags| flags.is_enabled(flag))
+    }
+
+    pub fn with_cancellation(mut self, token: &'a crate::cancellation::CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Token cancelled when the client cancels this request; only set by
+    /// registries serving the cancellation built-ins, for requests with an id
+    pub fn cancellation(&self) -> Option<&'a crate::cancellation::CancellationToken> {
+        self.cancellation
+    }
+
+    /// Whether the client cancelled this request
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_some_and(|token| token.is_cancelled())
+    }
+
+    /// Correlation id the request carried
+    pub fn correlation_id(&self) -> Option<&'a str> {
+        self.connection.correlation_id()
+    }
+
+    pub fn remote_addr(&self) -> Option<std::net::SocketAddr> {
+        self.connection.remote_addr
+    }
+
+    /// Time by which the call must be answered, when the registry enforces
+    /// timeouts
+    pub fn deadline(&self) -> Option<std::time::Instant> {
+        self.connection.deadline()
+    }
+
+    /// Output format of the transport; results converted with its
+    /// [`to_value`](crate::serialization::JsonFormat::to_value) follow its
+    /// non-finite float policy
+    pub fn json_format(&self) -> crate::serialization::JsonFormat {
+        self.connection.json_format()
+    }
+
+    /// Values middleware attached to the request
+    pub fn extensions(&self) -> Option<&'a crate::extensions::Extensions> {
+        self.connection.extensions()
+    }
+}
+
+/// Per-request view handlers get in
+/// [`JsonRPCMethod::call_with_context`]
+pub type RequestContext<'a> = CallContext<'a>;
+
+/// JSON-RPC method with typed params and result
+///
+/// Every implementation is also a [`JsonRPCMethod`]: params are decoded into
+/// [`Params`](Self::Params) before the call, with `INVALID_PARAMS` returned
+/// when they don't fit, and the result is encoded from
+/// [`Output`](Self::Output). Missing params decode from `null`, so methods
+/// without params can use `()` and optional ones `Option<T>`. Methods
+/// declaring a [`ParamsSpec`](crate::params::ParamsSpec) have their params
+/// checked and completed with defaults first.
+///
+/// ```rust
+/// use ash_rpc::*;
+///
+/// #[derive(serde::Deserialize)]
+/// struct AddParams {
+///     a: i64,
+///     b: i64,
+/// }
+///
+/// struct Add;
+///
+/// #[async_trait]
+/// impl TypedJsonRPCMethod for Add {
+///     type Params = AddParams;
+///     type Output = i64;
+///
+///     fn method_name(&self) -> &'static str {
+///         "add"
+///     }
+///
+///     async fn call(&self, params: AddParams) -> Result<i64, Error> {
+///         Ok(params.a + params.b)
+///     }
+/// }
+///
+/// let registry = MethodRegistry::new(register_methods![Add]);
+/// ```
+#[async_trait::async_trait]
+pub trait TypedJsonRPCMethod: Send + Sync {
+    type Params: serde::de::DeserializeOwned + Send;
+    type Output: Serialize + Send;
+
+    /// Get the method name that this implementation handles
+    fn method_name(&self) -> &'static str;
+
+    /// Execute the method with decoded params
+    async fn call(&self, params: Self::Params) -> Result<Self::Output, Error>;
+
+    /// Execute the method with access to the call context
+    ///
+    /// The default forwards to [`call`](Self::call).
+    async fn call_with_context(
+        &self,
+        params: Self::Params,
+        ctx: &CallContext<'_>,
+    ) -> Result<Self::Output, Error> {
+        let _ = ctx;
+        self.call(params).await
+    }
+
+    /// Declared params, see [`crate::params`]; built on every call
+    fn params_spec(&self) -> Option<crate::params::ParamsSpec> {
+        None
+    }
+
+    /// Get OpenAPI components for this method
+    ///
+    /// Params left undocumented are described by the
+    /// [`params_spec`](Self::params_spec), if any.
+    fn openapi_components(&self) -> OpenApiMethodSpec {
+        OpenApiMethodSpec::new(TypedJsonRPCMethod::method_name(self))
+    }
+
+    /// See [`JsonRPCMethod::security`]
+    fn security(&self) -> crate::method_security::MethodSecurity {
+        crate::method_security::MethodSecurity::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: TypedJsonRPCMethod> JsonRPCMethod for T {
+    fn method_name(&self) -> &'static str {
+        TypedJsonRPCMethod::method_name(self)
+    }
+
+    async fn call(&self, params: Option<serde_json::Value>, id: Option<RequestId>) -> Response {
+        let connection = crate::auth::ConnectionContext::default();
+        JsonRPCMethod::call_with_context(self, params, id, &CallContext::new(&connection)).await
+    }
+
+    async fn call_with_context(
+        &self,
+        params: Option<serde_json::Value>,
+        id: Option<RequestId>,
+        ctx: &CallContext<'_>,
+    ) -> Response {
+        let params = match TypedJsonRPCMethod::params_spec(self) {
+            Some(spec) => match spec.apply(params) {
+                Ok(params) => params,
+                Err(error) => return Response::error(error, id),
+            },
+            None => params.unwrap_or(serde_json::Value::Null),
+        };
+        let params = match serde_json::from_value(params) {
+            Ok(params) => params,
+            Err(e) => {
+                tracing::debug!(method = %TypedJsonRPCMethod::method_name(self), error = %e, "params do not match");
+                return crate::rpc_error!(
+                    crate::error_codes::INVALID_PARAMS,
+                    format!("Invalid params: {e}"),
+                    id
+                );
+            }
+        };
+        match TypedJsonRPCMethod::call_with_context(self, params, ctx).await {
+            Ok(output) => match ctx.json_format().to_value(&output) {
+                Ok(result) => Response::success(result, id),
+                Err(e) => {
+                    tracing::warn!(method = %TypedJsonRPCMethod::method_name(self), error = %e, "result not serializable");
+                    crate::rpc_error!(
+                        crate::error_codes::INTERNAL_ERROR,
+                        "Result could not be serialized",
+                        id
+                    )
+                }
+            },
+            Err(error) => Response::error(error, id),
+        }
+    }
+
+    fn openapi_components(&self) -> OpenApiMethodSpec {
+        let spec = TypedJsonRPCMethod::openapi_components(self);
+        match TypedJsonRPCMethod::params_spec(self) {
+            Some(params) if spec.parameters.is_none() => spec.with_parameters(params.schema()),
+            _ => spec,
+        }
+    }
+
+    fn security(&self) -> crate::method_security::MethodSecurity {
+        TypedJsonRPCMethod::security(self)
+    }
 }
 
 /// Trait for handling JSON-RPC requests and notifications
@@ -46,7 +317,24 @@ pub trait MessageProcessor: Send + Sync {
     /// Process a single JSON-RPC message
     async fn process_message(&self, message: Message) -> Option<Response>;
 
+    /// Process a single message on behalf of a known connection
+    ///
+    /// Transports call this when they can describe the caller (listener
+    /// origin, remote address, ...). The default ignores the context.
+    async fn process_message_with_context(
+        &self,
+        message: Message,
+        ctx: &crate::auth::ConnectionContext,
+    ) -> Option<Response> {
+        let _ = ctx;
+        self.process_message(message).await
+    }
+
     /// Process a batch of JSON-RPC messages
+    ///
+    /// Responses come back in the order of their requests, with
+    /// notifications left out. Implementations that process entries
+    /// concurrently must restore that order before returning.
     async fn process_batch(&self, messages: Vec<Message>) -> Vec<Response> {
         let mut results = Vec::new();
         for msg in messages {
@@ -57,12 +345,71 @@ pub trait MessageProcessor: Send + Sync {
         results
     }
 
+    /// Process a batch on behalf of a known connection
+    ///
+    /// The default processes the entries one by one with
+    /// [`process_message_with_context`](Self::process_message_with_context).
+    async fn process_batch_with_context(
+        &self,
+        messages: Vec<Message>,
+        ctx: &crate::auth::ConnectionContext,
+    ) -> Vec<Response> {
+        let mut results = Vec::new();
+        for msg in messages {
+            if let Some(response) = self.process_message_with_context(msg, ctx).await {
+                results.push(response);
+            }
+        }
+        results
+    }
+
+    /// Check whether `request` may run, without running it
+    ///
+    /// Transports ask this before answering a request themselves, e.g. a
+    /// stream subscription, so listener filters, auth policies, declared
+    /// method security and rate limits apply to it as well. Returns the
+    /// refusal to send instead; the default admits everything.
+    async fn admit(&self, request: &Request) -> Option<Response> {
+        self.admit_with_context(request, &crate::auth::ConnectionContext::default())
+            .await
+    }
+
+    /// Check a request on behalf of a known connection
+    ///
+    /// Wrappers apply their own checks and then ask their inner processor.
+    async fn admit_with_context(
+        &self,
+        request: &Request,
+        ctx: &crate::auth::ConnectionContext,
+    ) -> Option<Response> {
+        let _ = (request, ctx);
+        None
+    }
+
+    /// Pre-serialized response for a parameterless `2.0` request
+    ///
+    /// Transports call this with the raw request id before building an owned
+    /// message; returning `Some` skips dispatch and serialization entirely.
+    fn static_response(
+        &self,
+        method: &str,
+        id: Option<&serde_json::value::RawValue>,
+    ) -> Option<String> {
+        let _ = (method, id);
+        None
+    }
+
     /// Check if batch processing is supported
     fn supports_batching(&self) -> bool {
         true
     }
 
     /// Get processor capabilities
+    ///
+    /// Wrappers report their inner processor's capabilities, narrowed by
+    /// their own [`CapabilityLimits`] through
+    /// [`ProcessorCapabilities::tighten`], so the outermost processor
+    /// describes the whole stack. A wrapper never loosens what it wraps.
     fn get_capabilities(&self) -> ProcessorCapabilities {
         ProcessorCapabilities::default()
     }
@@ -92,7 +439,7 @@ pub trait StreamingMessageProcessor: MessageProcessor {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessorCapabilities {
     pub supports_batch: bool,
     pub supports_notifications: bool,
@@ -115,6 +462,94 @@ impl Default for ProcessorCapabilities {
     }
 }
 
+impl ProcessorCapabilities {
+    /// Apply `limits` where they are stricter than these capabilities
+    pub fn tighten(mut self, limits: &CapabilityLimits) -> Self {
+        fn stricter<T: Ord>(current: Option<T>, limit: Option<T>) -> Option<T> {
+            match (current, limit) {
+                (Some(current), Some(limit)) => Some(current.min(limit)),
+                (current, None) => current,
+                (None, limit) => limit,
+            }
+        }
+
+        self.supports_batch &= limits.batches;
+        self.supports_notifications &= limits.notifications;
+        self.max_batch_size = stricter(self.max_batch_size, limits.max_batch_size);
+        self.max_request_size = stricter(self.max_request_size, limits.max_request_size);
+        self.request_timeout_secs =
+            stricter(self.request_timeout_secs, limits.request_timeout_secs);
+        self
+    }
+}
+
+/// Limits a processor wrapper imposes on top of the processor it wraps
+///
+/// Unset limits leave the inner capabilities as they are. Wrappers also pass
+/// them on with
+/// [`ConnectionContext::with_capability_limits`](crate::auth::ConnectionContext::with_capability_limits),
+/// so a registry's capabilities built-in answers with them applied.
+#[derive(Debug, Clone)]
+pub struct CapabilityLimits {
+    batches: bool,
+    notifications: bool,
+    max_batch_size: Option<usize>,
+    max_request_size: Option<usize>,
+    request_timeout_secs: Option<u64>,
+}
+
+impl CapabilityLimits {
+    pub fn new() -> Self {
+        Self {
+            batches: true,
+            notifications: true,
+            max_batch_size: None,
+            max_request_size: None,
+            request_timeout_secs: None,
+        }
+    }
+
+    pub fn max_batch_size(mut self, size: usize) -> Self {
+        self.max_batch_size = Some(size);
+        self
+    }
+
+    pub fn max_request_size(mut self, bytes: usize) -> Self {
+        self.max_request_size = Some(bytes);
+        self
+    }
+
+    pub fn request_timeout_secs(mut self, secs: u64) -> Self {
+        self.request_timeout_secs = Some(secs);
+        self
+    }
+
+    pub fn disable_batches(mut self) -> Self {
+        self.batches = false;
+        self
+    }
+
+    pub fn disable_notifications(mut self) -> Self {
+        self.notifications = false;
+        self
+    }
+
+    /// True if no limit is set
+    pub fn is_unrestricted(&self) -> bool {
+        self.batches
+            && self.notifications
+            && self.max_batch_size.is_none()
+            && self.max_request_size.is_none()
+            && self.request_timeout_secs.is_none()
+    }
+}
+
+impl Default for CapabilityLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Builder for ProcessorCapabilities with validation
 pub struct ProcessorCapabilitiesBuilder {
     supports_batch: bool,
@@ -248,6 +683,11 @@ pub struct OpenApiMethodSpec {
     pub errors: Vec<OpenApiError>,
     pub tags: Vec<String>,
     pub examples: Vec<OpenApiExample>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<OpenApiDeprecation>,
+    /// Vendor extensions (`x-*` keys) written alongside the method
+    #[serde(flatten, default, skip_serializing_if = "HashMap::is_empty")]
+    pub extensions: HashMap<String, serde_json::Value>,
 }
 
 impl OpenApiMethodSpec {
@@ -262,6 +702,8 @@ impl OpenApiMethodSpec {
             errors: Vec::new(),
             tags: Vec::new(),
             examples: Vec::new(),
+            deprecated: None,
+            extensions: HashMap::new(),
         }
     }
 
@@ -306,6 +748,31 @@ impl OpenApiMethodSpec {
         self.examples.push(example);
         self
     }
+
+    /// Mark the method as deprecated
+    pub fn with_deprecated(mut self, deprecated: OpenApiDeprecation) -> Self {
+        self.deprecated = Some(deprecated);
+        self
+    }
+
+    /// Add a vendor extension; `name` should start with `x-`
+    pub fn with_extension(mut self, name: impl Into<String>, value: serde_json::Value) -> Self {
+        self.extensions.insert(name.into(), value);
+        self
+    }
+}
+
+/// Deprecation notice for a method
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenApiDeprecation {
+    /// Version the method was deprecated in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+    /// Method to call instead
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
 }
 
 /// OpenAPI error specification
@@ -388,6 +855,9 @@ pub struct OpenApiSpec {
     pub servers: Vec<OpenApiServer>,
     pub methods: HashMap<String, OpenApiMethodSpec>,
     pub components: OpenApiComponents,
+    /// Vendor extensions (`x-*` keys) written at the top level
+    #[serde(flatten, default, skip_serializing_if = "HashMap::is_empty")]
+    pub extensions: HashMap<String, serde_json::Value>,
 }
 
 impl OpenApiSpec {
@@ -403,6 +873,7 @@ impl OpenApiSpec {
             servers: Vec::new(),
             methods: HashMap::new(),
             components: OpenApiComponents::default(),
+            extensions: HashMap::new(),
         }
     }
 
@@ -428,6 +899,11 @@ impl OpenApiSpec {
         self.info.description = Some(description.into());
         self
     }
+
+    /// Add a vendor extension; `name` should start with `x-`
+    pub fn add_extension(&mut self, name: impl Into<String>, value: serde_json::Value) {
+        self.extensions.insert(name.into(), value);
+    }
 }
 
 /// OpenAPI info section
@@ -668,6 +1144,57 @@ mod tests {
         }
     }
 
+    #[derive(Deserialize)]
+    struct DivideParams {
+        a: i64,
+        b: i64,
+    }
+
+    struct Divide;
+
+    #[async_trait::async_trait]
+    impl TypedJsonRPCMethod for Divide {
+        type Params = DivideParams;
+        type Output = i64;
+
+        fn method_name(&self) -> &'static str {
+            "divide"
+        }
+
+        async fn call(&self, params: DivideParams) -> Result<i64, Error> {
+            if params.b == 0 {
+                return Err(Error::new(
+                    crate::error_codes::INVALID_PARAMS,
+                    "division by zero",
+                ));
+            }
+            Ok(params.a / params.b)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_typed_method_adapter() {
+        let method: Box<dyn JsonRPCMethod> = Box::new(Divide);
+        assert_eq!(method.method_name(), "divide");
+
+        let response = method
+            .call(Some(json!({"a": 7, "b": 2})), Some(json!(1)))
+            .await;
+        assert_eq!(response.result, Some(json!(3)));
+
+        let response = method
+            .call(Some(json!({"a": 7, "b": 0})), Some(json!(2)))
+            .await;
+        assert_eq!(response.error.unwrap().message, "division by zero");
+
+        for params in [None, Some(json!({"a": "7", "b": 2}))] {
+            let response = method.call(params, Some(json!(3))).await;
+            let error = response.error.unwrap();
+            assert_eq!(error.code, crate::error_codes::INVALID_PARAMS);
+            assert_eq!(response.id, Some(json!(3)));
+        }
+    }
+
     #[tokio::test]
     async fn test_jsonrpc_method_trait() {
         let method = TestMethod;
@@ -718,6 +1245,7 @@ mod tests {
             params: None,
             id: Some(json!(1)),
             correlation_id: None,
+            ext: None,
         };
 
         let response = handler.handle_request(request).await;
@@ -762,6 +1290,7 @@ mod tests {
             params: None,
             id: Some(json!(1)),
             correlation_id: None,
+            ext: None,
         });
 
         let response = processor.process_message(request).await;
@@ -778,6 +1307,7 @@ mod tests {
                 params: None,
                 id: Some(json!(1)),
                 correlation_id: None,
+                ext: None,
             }),
             Message::Request(Request {
                 jsonrpc: "2.0".to_string(),
@@ -785,6 +1315,7 @@ mod tests {
                 params: None,
                 id: Some(json!(2)),
                 correlation_id: None,
+                ext: None,
             }),
         ];
 
@@ -806,6 +1337,26 @@ mod tests {
         assert!(caps.supports_notifications);
     }
 
+    #[test]
+    fn test_processor_capabilities_tighten() {
+        let caps = ProcessorCapabilitiesBuilder::new()
+            .max_batch_size(None)
+            .build()
+            .tighten(
+                &CapabilityLimits::new()
+                    .max_batch_size(10)
+                    .max_request_size(usize::MAX)
+                    .disable_notifications(),
+            );
+        assert_eq!(caps.max_batch_size, Some(10));
+        assert_eq!(caps.max_request_size, Some(1024 * 1024));
+        assert!(caps.supports_batch);
+        assert!(!caps.supports_notifications);
+
+        let caps = caps.tighten(&CapabilityLimits::new().max_batch_size(50));
+        assert_eq!(caps.max_batch_size, Some(10));
+    }
+
     // ProcessorCapabilities additional tests
     #[test]
     fn test_processor_capabilities_builder_disabled_batch() {
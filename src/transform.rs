@@ -0,0 +1,162 @@
+//! Rewriting of method results.
+//!
+//! A [`ResponseTransformer`] registered on a
+//! [`MethodRegistry`](crate::MethodRegistry) sees the result of every
+//! successful call, or of one method's calls, and returns the result to
+//! send instead: redact fields the caller has no scope for, add links,
+//! rename internal values. Error responses are left alone. Transformers run
+//! in registration order, each on the previous one's output.
+//!
+//! Closures taking the method name, the result and the caller's context
+//! are transformers too:
+//!
+//! ```rust
+//! use ash_rpc::auth::ConnectionContext;
+//! use ash_rpc::*;
+//! use serde_json::Value;
+//!
+//! let registry = MethodRegistry::empty()
+//!     .with_response_transformer(|_method: &str, mut result: Value, ctx: &ConnectionContext| {
+//!         if !ctx.has_scope("pii:read")
+//!             && let Some(user) = result.as_object_mut()
+//!         {
+//!             user.remove("email");
+//!         }
+//!         result
+//!     })
+//!     .with_method_transformer("users.get", |_method: &str, mut result: Value, _ctx: &ConnectionContext| {
+//!         result["links"] = serde_json::json!({"self": format!("/users/{}", result["id"])});
+//!         result
+//!     });
+//! ```
+
+use crate::Response;
+use crate::auth::ConnectionContext;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Rewrites the result of a successful call
+pub trait ResponseTransformer: Send + Sync {
+    fn transform(&self, method: &str, result: Value, ctx: &ConnectionContext) -> Value;
+}
+
+impl<F> ResponseTransformer for F
+where
+    F: Fn(&str, Value, &ConnectionContext) -> Value + Send + Sync,
+{
+    fn transform(&self, method: &str, result: Value, ctx: &ConnectionContext) -> Value {
+        self(method, result, ctx)
+    }
+}
+
+/// Transformers of a registry, each for every method or just one
+#[derive(Clone, Default)]
+pub(crate) struct Transformers {
+    transformers: Vec<(Option<String>, Arc<dyn ResponseTransformer>)>,
+}
+
+impl Transformers {
+    pub(crate) fn push(
+        &mut self,
+        method: Option<String>,
+        transformer: Arc<dyn ResponseTransformer>,
+    ) {
+        self.transformers.push((method, transformer));
+    }
+
+    /// Whether any transformer applies to `method`
+    pub(crate) fn applies_to(&self, method: &str) -> bool {
+        self.transformers
+            .iter()
+            .any(|(only, _)| only.as_deref().is_none_or(|only| only == method))
+    }
+
+    /// Run the transformers of `method` over the result of `response`
+    pub(crate) fn apply(&self, method: &str, response: &mut Response, ctx: &ConnectionContext) {
+        let Some(mut result) = response.result.take() else {
+            return;
+        };
+        for (only, transformer) in &self.transformers {
+            if only.as_deref().is_none_or(|only| only == method) {
+                result = transformer.transform(method, result, ctx);
+            }
+        }
+        response.result = Some(result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{JsonRPCMethod, MessageProcessor, MethodRegistry, RequestId};
+    use serde_json::json;
+
+    struct User;
+
+    #[async_trait::async_trait]
+    impl JsonRPCMethod for User {
+        fn method_name(&self) -> &'static str {
+            "user"
+        }
+
+        async fn call(&self, _params: Option<Value>, id: Option<RequestId>) -> Response {
+            crate::rpc_success!(json!({"id": 7, "email": "a@example.com"}), id)
+        }
+    }
+
+    struct Version;
+
+    #[async_trait::async_trait]
+    impl JsonRPCMethod for Version {
+        fn method_name(&self) -> &'static str {
+            "version"
+        }
+
+        async fn call(&self, _params: Option<Value>, id: Option<RequestId>) -> Response {
+            crate::rpc_success!("1.0", id)
+        }
+
+        fn static_result(&self) -> Option<Value> {
+            Some(json!("1.0"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transformers_in_order() {
+        let registry = MethodRegistry::new(crate::register_methods![User, Version])
+            .with_response_transformer(|_: &str, mut result: Value, ctx: &ConnectionContext| {
+                if !ctx.has_scope("pii:read")
+                    && let Some(object) = result.as_object_mut()
+                {
+                    object.remove("email");
+                }
+                result
+            })
+            .with_method_transformer(
+                "user",
+                |method: &str, mut result: Value, _: &ConnectionContext| {
+                    result["links"] = json!({"self": format!("/{method}/{}", result["id"])});
+                    result
+                },
+            );
+
+        let response = registry.call("user", None, Some(json!(1))).await;
+        assert_eq!(
+            response.result,
+            Some(json!({"id": 7, "links": {"self": "/user/7"}}))
+        );
+
+        let mut ctx = ConnectionContext::new();
+        ctx.grant_scopes(["pii:read"]);
+        let response = registry
+            .call_with_context("user", None, Some(json!(2)), &ctx)
+            .await;
+        assert_eq!(response.result.unwrap()["email"], "a@example.com");
+
+        assert!(registry.static_response("version", None).is_none());
+        let response = registry.call("version", None, Some(json!(3))).await;
+        assert_eq!(response.result, Some(json!("1.0")));
+        let response = registry.call("missing", None, Some(json!(4))).await;
+        assert!(response.result.is_none() && response.error.is_some());
+    }
+}
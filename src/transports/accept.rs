@@ -0,0 +1,208 @@
+//! Listener backlog and accept pacing
+//!
+//! After a deploy every client reconnects at once. The kernel backlog set
+//! with the servers' `backlog()` option decides how many of those
+//! connections can wait for `accept()`; an [`AcceptRateLimit`] then spreads
+//! the accepted ones out so TLS handshakes and handler startup do not all
+//! hit at the same moment.
+//!
+//! Pacing is a GCRA token bucket: up to `burst` connections start right
+//! away, later ones are delayed to `rate_per_sec`. A connection that would
+//! have to wait longer than `max_delay` overflows the accept queue. It is
+//! closed and reported as [`RejectionReason::AcceptQueueFull`], so the
+//! overflow shows up in every rejection observer, including the Prometheus
+//! `rejections_total` counter.
+//!
+//! [`RejectionReason::AcceptQueueFull`]: crate::rejection::RejectionReason::AcceptQueueFull
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpSocket};
+use tokio::time::Instant;
+
+/// How fast a server starts handling new connections
+#[derive(Debug, Clone, PartialEq)]
+pub struct AcceptRateLimit {
+    /// Sustained connections per second
+    pub rate_per_sec: f64,
+    /// Connections started without delay after an idle period
+    pub burst: u32,
+    /// Longest a connection may be held back before it is dropped
+    pub max_delay: Duration,
+}
+
+impl AcceptRateLimit {
+    /// `rate_per_sec` with a burst of one second worth of connections
+    pub fn new(rate_per_sec: u32) -> Self {
+        Self {
+            rate_per_sec: f64::from(rate_per_sec.max(1)),
+            burst: rate_per_sec.max(1),
+            max_delay: Duration::from_secs(1),
+        }
+    }
+
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+}
+
+static ACCEPTS_DELAYED: AtomicU64 = AtomicU64::new(0);
+static ACCEPT_OVERFLOWS: AtomicU64 = AtomicU64::new(0);
+
+/// Connections whose handling was delayed by an accept rate limit
+pub fn delayed_total() -> u64 {
+    ACCEPTS_DELAYED.load(Ordering::Relaxed)
+}
+
+/// Connections dropped because the accept queue was full
+pub fn overflow_total() -> u64 {
+    ACCEPT_OVERFLOWS.load(Ordering::Relaxed)
+}
+
+/// Per-listener pacing state
+#[derive(Debug)]
+pub struct AcceptPacer {
+    limit: Option<AcceptRateLimit>,
+    /// Theoretical arrival time of the next conforming connection
+    tat: Option<Instant>,
+}
+
+impl AcceptPacer {
+    pub fn new(limit: Option<AcceptRateLimit>) -> Self {
+        Self { limit, tat: None }
+    }
+
+    /// Delay before handling a just-accepted connection.
+    ///
+    /// `None` means the connection overflowed the queue; the rejection has
+    /// already been recorded and the caller should drop the stream.
+    pub fn admit(&mut self, remote_addr: SocketAddr) -> Option<Duration> {
+        self.admit_at(Instant::now(), remote_addr)
+    }
+
+    fn admit_at(&mut self, now: Instant, remote_addr: SocketAddr) -> Option<Duration> {
+        let Some(limit) = &self.limit else {
+            return Some(Duration::ZERO);
+        };
+        let interval = Duration::from_nanos((1e9 / limit.rate_per_sec) as u64);
+        let tolerance = interval * limit.burst.saturating_sub(1);
+
+        let tat = self.tat.map_or(now, |tat| tat.max(now));
+        let start = tat.checked_sub(tolerance).unwrap_or(now).max(now);
+        let delay = start - now;
+        if delay > limit.max_delay {
+            ACCEPT_OVERFLOWS.fetch_add(1, Ordering::Relaxed);
+            crate::rejection::record(
+                crate::rejection::Rejection::new(
+                    crate::rejection::RejectionReason::AcceptQueueFull,
+                )
+                .remote_addr(Some(remote_addr))
+                .detail(format!("would wait {delay:?}, limit {:?}", limit.max_delay)),
+            );
+            return None;
+        }
+
+        self.tat = Some(tat + interval);
+        if !delay.is_zero() {
+            ACCEPTS_DELAYED.fetch_add(1, Ordering::Relaxed);
+            tracing::debug!(remote_addr = %remote_addr, delay = ?delay, "pacing accepted connection");
+        }
+        Some(delay)
+    }
+}
+
+/// Bind `addr`, using an explicit listen backlog when one is given
+pub async fn bind(addr: &str, backlog: Option<u32>) -> io::Result<TcpListener> {
+    let Some(backlog) = backlog else {
+        return TcpListener::bind(addr).await;
+    };
+
+    let mut last_error = None;
+    for addr in tokio::net::lookup_host(addr).await? {
+        match bind_with_backlog(addr, backlog) {
+            Ok(listener) => return Ok(listener),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "could not resolve to any address",
+        )
+    }))
+}
+
+fn bind_with_backlog(addr: SocketAddr, backlog: u32) -> io::Result<TcpListener> {
+    let socket = if addr.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+    // matches what `TcpListener::bind` does
+    #[cfg(not(windows))]
+    socket.set_reuseaddr(true)?;
+    socket.bind(addr)?;
+    socket.listen(backlog)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer() -> SocketAddr {
+        "127.0.0.1:9".parse().unwrap()
+    }
+
+    #[test]
+    fn test_no_limit_admits_immediately() {
+        let mut pacer = AcceptPacer::new(None);
+        for _ in 0..1000 {
+            assert_eq!(pacer.admit(peer()), Some(Duration::ZERO));
+        }
+    }
+
+    #[test]
+    fn test_burst_then_paced_then_overflow() {
+        let limit = AcceptRateLimit::new(10)
+            .burst(2)
+            .max_delay(Duration::from_millis(250));
+        let mut pacer = AcceptPacer::new(Some(limit));
+        let now = Instant::now();
+
+        assert_eq!(pacer.admit_at(now, peer()), Some(Duration::ZERO));
+        assert_eq!(pacer.admit_at(now, peer()), Some(Duration::ZERO));
+        assert_eq!(
+            pacer.admit_at(now, peer()),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(
+            pacer.admit_at(now, peer()),
+            Some(Duration::from_millis(200))
+        );
+
+        let overflows = overflow_total();
+        assert_eq!(pacer.admit_at(now, peer()), None);
+        assert!(overflow_total() > overflows);
+
+        // once the queue has drained, connections start immediately again
+        let later = now + Duration::from_secs(1);
+        assert_eq!(pacer.admit_at(later, peer()), Some(Duration::ZERO));
+    }
+
+    #[tokio::test]
+    async fn test_bind_with_backlog() {
+        let listener = bind("127.0.0.1:0", Some(16)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = tokio::net::TcpStream::connect(addr);
+        let (accepted, _) = tokio::join!(listener.accept(), client);
+        assert!(accepted.is_ok());
+    }
+}
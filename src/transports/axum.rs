@@ -7,14 +7,76 @@
 //! - Router-based setup for embedding in existing Axum applications
 //! - Batch request support
 //! - Error handling with proper HTTP status codes
+//! - Configurable response formatting via [`JsonFormat`]
+//!
+//! Every request is processed with a [`ConnectionContext`] holding its
+//! headers under [`HTTP_HEADERS_KEY`](crate::auth::HTTP_HEADERS_KEY) and, when
+//! the router is served with
+//! `into_make_service_with_connect_info::<SocketAddr>()`, the peer address.
+//!
+//! [`AxumRpcRoutes`] serves several processors on different paths of one
+//! router, e.g. a public and an admin registry with their own method
+//! filters, sharing a processor wrapper for observability and, being one
+//! router, the server's graceful shutdown:
+//!
+//! ```rust,no_run
+//! # fn example(public: ash_rpc::MethodRegistry, admin: ash_rpc::MethodRegistry, spec: ash_rpc::OpenApiSpec) -> std::io::Result<()> {
+//! use ash_rpc::transports::{AxumRpcLayer, AxumRpcRoutes};
+//!
+//! let routes = AxumRpcRoutes::builder()
+//!     .endpoint(
+//!         AxumRpcLayer::builder()
+//!             .path("/rpc/public")
+//!             .name("public")
+//!             .processor(public)
+//!             .openapi(spec),
+//!     )
+//!     .endpoint(AxumRpcLayer::builder().path("/rpc/admin").name("admin").processor(admin))
+//!     .openapi_suffix("/openapi.json")
+//!     .openrpc_suffix("/openrpc.json")
+//!     .build()?;
+//! let app = routes.into_router();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Endpoints built with [`stream_batches`](AxumRpcBuilder::stream_batches)
+//! also take batches on their path. Batches of at least the given size are
+//! answered with a chunked body written entry by entry as responses
+//! complete, so a batch of thousands of entries never sits in memory as a
+//! whole: a JSON array, or one response per line when the client sends
+//! `Accept: application/x-ndjson`. Streamed entries are processed one at a
+//! time with `process_message_with_context`, so the processor's batch
+//! policy does not apply to them; smaller batches go through
+//! `process_batch_with_context` as usual.
 
-use crate::{ErrorBuilder, Message, MessageProcessor, Response, ResponseBuilder, error_codes};
-use axum::{Router, extract::State, http::StatusCode, response::Json, routing::post};
+use crate::auth::ConnectionContext;
+use crate::serialization::JsonFormat;
+use crate::{
+    ErrorBuilder, Message, MessageProcessor, OpenApiServer, OpenApiSpec, Response, ResponseBuilder,
+    error_codes,
+};
+use axum::{
+    Router,
+    extract::{ConnectInfo, State},
+    http::{Extensions, HeaderMap, StatusCode, header},
+    response::{IntoResponse, Json},
+    routing::{get, post},
+};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{Context, Poll};
 
 pub struct AxumRpcBuilder {
     processor: Option<Arc<dyn MessageProcessor + Send + Sync>>,
     path: String,
+    name: Option<String>,
+    method_filter: super::listener::MethodFilter,
+    json_format: JsonFormat,
+    openapi: Option<OpenApiSpec>,
+    stream_batches: Option<usize>,
 }
 
 impl AxumRpcBuilder {
@@ -22,6 +84,11 @@ impl AxumRpcBuilder {
         Self {
             processor: None,
             path: "/rpc".to_string(),
+            name: None,
+            method_filter: super::listener::MethodFilter::default(),
+            json_format: JsonFormat::default(),
+            openapi: None,
+            stream_batches: None,
         }
     }
 
@@ -38,14 +105,61 @@ impl AxumRpcBuilder {
         self
     }
 
+    /// Name this listener; the name is exposed as `ConnectionContext::origin`
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Restrict which methods may be called through this listener
+    pub fn method_filter(mut self, filter: super::listener::MethodFilter) -> Self {
+        self.method_filter = filter;
+        self
+    }
+
+    /// Output format for responses, compact by default
+    pub fn json_format(mut self, format: JsonFormat) -> Self {
+        self.json_format = format;
+        self
+    }
+
+    /// Document this endpoint with `spec`
+    ///
+    /// Methods the method filter denies are left out, and the endpoint's
+    /// path is listed as server unless `spec` names its own.
+    pub fn openapi(mut self, spec: OpenApiSpec) -> Self {
+        self.openapi = Some(spec);
+        self
+    }
+
+    /// Accept batches, streaming the responses of those with at least
+    /// `min_entries` entries; see the [module docs](self)
+    pub fn stream_batches(mut self, min_entries: usize) -> Self {
+        self.stream_batches = Some(min_entries);
+        self
+    }
+
     pub fn build(self) -> Result<AxumRpcLayer, std::io::Error> {
         let processor = self.processor.ok_or_else(|| {
             std::io::Error::new(std::io::ErrorKind::InvalidInput, "Processor not set")
         })?;
+        let openapi = self.openapi.map(|mut spec| {
+            spec.methods
+                .retain(|method, _| self.method_filter.is_permitted(method));
+            if spec.servers.is_empty() {
+                spec.add_server(OpenApiServer::new(&self.path));
+            }
+            spec
+        });
+        let processor =
+            super::listener::ListenerProcessor::wrap(processor, self.name, self.method_filter);
 
         Ok(AxumRpcLayer {
             processor,
             path: self.path,
+            json_format: self.json_format,
+            openapi,
+            stream_batches: self.stream_batches,
         })
     }
 }
@@ -53,6 +167,9 @@ impl AxumRpcBuilder {
 pub struct AxumRpcLayer {
     processor: Arc<dyn MessageProcessor + Send + Sync>,
     path: String,
+    json_format: JsonFormat,
+    openapi: Option<OpenApiSpec>,
+    stream_batches: Option<usize>,
 }
 
 impl AxumRpcLayer {
@@ -60,10 +177,182 @@ impl AxumRpcLayer {
         AxumRpcBuilder::new()
     }
 
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The endpoint's spec, narrowed to the methods it serves
+    pub fn openapi(&self) -> Option<&OpenApiSpec> {
+        self.openapi.as_ref()
+    }
+
     pub fn into_router(self) -> Router {
+        if let Some(min_entries) = self.stream_batches {
+            return Router::new()
+                .route(&self.path, post(handle_rpc_streaming))
+                .with_state(StreamingEndpoint {
+                    processor: self.processor,
+                    format: self.json_format,
+                    min_entries,
+                });
+        }
+        if self.json_format == JsonFormat::default() {
+            return Router::new()
+                .route(&self.path, post(handle_rpc))
+                .with_state(self.processor);
+        }
         Router::new()
-            .route(&self.path, post(handle_rpc))
-            .with_state(self.processor)
+            .route(&self.path, post(handle_rpc_formatted))
+            .with_state((self.processor, self.json_format))
+    }
+}
+
+type ProcessorWrapper = Arc<
+    dyn Fn(Arc<dyn MessageProcessor + Send + Sync>) -> Arc<dyn MessageProcessor + Send + Sync>
+        + Send
+        + Sync,
+>;
+
+/// Builder of [`AxumRpcRoutes`]
+#[derive(Default)]
+pub struct AxumRpcRoutesBuilder {
+    endpoints: Vec<AxumRpcBuilder>,
+    wrapper: Option<ProcessorWrapper>,
+    openapi_suffix: Option<String>,
+    openrpc_suffix: Option<String>,
+    #[cfg(feature = "html-docs")]
+    docs_suffix: Option<String>,
+}
+
+impl AxumRpcRoutesBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve the endpoint `builder` describes
+    pub fn endpoint(mut self, builder: AxumRpcBuilder) -> Self {
+        self.endpoints.push(builder);
+        self
+    }
+
+    /// Wrap the processor of every endpoint, e.g. in one
+    /// `ObservableProcessor` setup shared by all of them
+    ///
+    /// The wrapper sits outside the endpoint's name and method filter.
+    pub fn wrap<F>(mut self, wrapper: F) -> Self
+    where
+        F: Fn(Arc<dyn MessageProcessor + Send + Sync>) -> Arc<dyn MessageProcessor + Send + Sync>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.wrapper = Some(Arc::new(wrapper));
+        self
+    }
+
+    /// Serve each documented endpoint's spec with `GET` at its path
+    /// followed by `suffix`, e.g. `/openapi.json`
+    pub fn openapi_suffix(mut self, suffix: impl Into<String>) -> Self {
+        self.openapi_suffix = Some(suffix.into());
+        self
+    }
+
+    /// Serve each documented endpoint's spec as an OpenRPC document with
+    /// `GET` at its path followed by `suffix`, e.g. `/openrpc.json`
+    pub fn openrpc_suffix(mut self, suffix: impl Into<String>) -> Self {
+        self.openrpc_suffix = Some(suffix.into());
+        self
+    }
+
+    /// Serve an HTML page documenting each documented endpoint with `GET`
+    /// at its path followed by `suffix`, e.g. `/docs`
+    #[cfg(feature = "html-docs")]
+    pub fn docs_suffix(mut self, suffix: impl Into<String>) -> Self {
+        self.docs_suffix = Some(suffix.into());
+        self
+    }
+
+    /// Fails when an endpoint has no processor or two share a path
+    pub fn build(self) -> Result<AxumRpcRoutes, std::io::Error> {
+        let mut endpoints = BTreeMap::new();
+        for builder in self.endpoints {
+            let mut layer = builder.build()?;
+            if endpoints.contains_key(&layer.path) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("Duplicate endpoint path {}", layer.path),
+                ));
+            }
+            if let Some(wrapper) = &self.wrapper {
+                layer.processor = wrapper(layer.processor);
+            }
+            endpoints.insert(layer.path.clone(), layer);
+        }
+        Ok(AxumRpcRoutes {
+            endpoints,
+            openapi_suffix: self.openapi_suffix,
+            openrpc_suffix: self.openrpc_suffix,
+            #[cfg(feature = "html-docs")]
+            docs_suffix: self.docs_suffix,
+        })
+    }
+}
+
+/// Several JSON-RPC endpoints, each with its own processor, served by one
+/// router
+pub struct AxumRpcRoutes {
+    endpoints: BTreeMap<String, AxumRpcLayer>,
+    openapi_suffix: Option<String>,
+    openrpc_suffix: Option<String>,
+    #[cfg(feature = "html-docs")]
+    docs_suffix: Option<String>,
+}
+
+impl AxumRpcRoutes {
+    pub fn builder() -> AxumRpcRoutesBuilder {
+        AxumRpcRoutesBuilder::new()
+    }
+
+    /// Endpoints by path
+    pub fn endpoints(&self) -> impl Iterator<Item = &AxumRpcLayer> {
+        self.endpoints.values()
+    }
+
+    /// Spec of the endpoint at `path`
+    pub fn openapi(&self, path: &str) -> Option<&OpenApiSpec> {
+        self.endpoints.get(path)?.openapi()
+    }
+
+    pub fn into_router(self) -> Router {
+        let mut router = Router::new();
+        for (path, mut layer) in self.endpoints {
+            if let Some(spec) = layer.openapi.take() {
+                if let Some(suffix) = &self.openrpc_suffix {
+                    let document = Arc::new(crate::openrpc::document(&spec));
+                    router = router.route(
+                        &format!("{path}{suffix}"),
+                        get(move || async move { Json(serde_json::Value::clone(&document)) }),
+                    );
+                }
+                #[cfg(feature = "html-docs")]
+                if let Some(suffix) = &self.docs_suffix {
+                    let page = Arc::new(crate::openrpc::html(&spec));
+                    router = router.route(
+                        &format!("{path}{suffix}"),
+                        get(move || async move { axum::response::Html(String::clone(&page)) }),
+                    );
+                }
+                if let Some(suffix) = &self.openapi_suffix {
+                    let spec = Arc::new(spec);
+                    router = router.route(
+                        &format!("{path}{suffix}"),
+                        get(move || async move { Json(OpenApiSpec::clone(&spec)) }),
+                    );
+                }
+            }
+            router = router.merge(layer.into_router());
+        }
+        router
     }
 }
 
@@ -76,11 +365,24 @@ where
         .with_state(Arc::new(processor))
 }
 
+/// Context of an HTTP request
+fn request_context(headers: HeaderMap, extensions: &Extensions) -> ConnectionContext {
+    let mut ctx = match extensions.get::<ConnectInfo<SocketAddr>>() {
+        Some(ConnectInfo(addr)) => ConnectionContext::with_addr(*addr),
+        None => ConnectionContext::new(),
+    };
+    ctx.insert(crate::auth::HTTP_HEADERS_KEY.to_string(), headers);
+    ctx
+}
+
 async fn handle_rpc(
     State(processor): State<Arc<dyn MessageProcessor + Send + Sync>>,
+    headers: HeaderMap,
+    extensions: Extensions,
     Json(message): Json<Message>,
 ) -> Result<Json<Response>, (StatusCode, Json<Response>)> {
-    match processor.process_message(message).await {
+    let ctx = request_context(headers, &extensions);
+    match processor.process_message_with_context(message, &ctx).await {
         Some(response) => Ok(Json(response)),
         None => {
             let error_response = ResponseBuilder::new()
@@ -99,19 +401,218 @@ async fn handle_rpc(
     }
 }
 
-pub async fn handle_rpc_batch(
-    State(processor): State<Arc<dyn MessageProcessor + Send + Sync>>,
-    Json(messages): Json<Vec<Message>>,
-) -> Json<Vec<Response>> {
-    let mut responses = Vec::new();
+async fn handle_rpc_formatted(
+    State((processor, format)): State<(Arc<dyn MessageProcessor + Send + Sync>, JsonFormat)>,
+    headers: HeaderMap,
+    extensions: Extensions,
+    Json(message): Json<Message>,
+) -> axum::response::Response {
+    let ctx = request_context(headers, &extensions).with_json_format(format);
+    respond_formatted(processor.as_ref(), message, &ctx, &format).await
+}
 
-    for message in messages {
-        if let Some(response) = processor.process_message(message).await {
-            responses.push(response);
+async fn respond_formatted(
+    processor: &(dyn MessageProcessor + Send + Sync),
+    message: Message,
+    ctx: &ConnectionContext,
+    format: &JsonFormat,
+) -> axum::response::Response {
+    let response = processor
+        .process_message_with_context(message, ctx)
+        .await
+        .unwrap_or_else(|| {
+            ResponseBuilder::new()
+                .error(
+                    ErrorBuilder::new(
+                        error_codes::INVALID_REQUEST,
+                        "No response generated for request",
+                    )
+                    .build(),
+                )
+                .id(None)
+                .build()
+        });
+    (
+        [(header::CONTENT_TYPE, "application/json")],
+        render(format, &response),
+    )
+        .into_response()
+}
+
+/// `response` in `format`, or an internal error if it does not serialize
+fn render(format: &JsonFormat, response: &Response) -> String {
+    format.to_string(response).unwrap_or_else(|e| {
+        tracing::error!(error = %e, "response serialization failed");
+        let fallback = ResponseBuilder::new()
+            .error(
+                ErrorBuilder::new(
+                    error_codes::INTERNAL_ERROR,
+                    "Response could not be serialized",
+                )
+                .build(),
+            )
+            .id(response.id.clone())
+            .build();
+        serde_json::to_string(&fallback).unwrap_or_default()
+    })
+}
+
+/// State of an endpoint that streams batch responses
+#[derive(Clone)]
+struct StreamingEndpoint {
+    processor: Arc<dyn MessageProcessor + Send + Sync>,
+    format: JsonFormat,
+    min_entries: usize,
+}
+
+const NDJSON: &str = "application/x-ndjson";
+
+async fn handle_rpc_streaming(
+    State(endpoint): State<StreamingEndpoint>,
+    headers: HeaderMap,
+    extensions: Extensions,
+    body: axum::body::Bytes,
+) -> axum::response::Response {
+    let ndjson = headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains(NDJSON));
+    let ctx = request_context(headers, &extensions).with_json_format(endpoint.format);
+    let content_type = if ndjson { NDJSON } else { "application/json" };
+    let json = |response: &Response| {
+        (
+            [(header::CONTENT_TYPE, "application/json")],
+            render(&endpoint.format, response),
+        )
+            .into_response()
+    };
+
+    let parsed = std::str::from_utf8(&body).map_err(|e| e.to_string());
+    let batch = parsed
+        .as_ref()
+        .is_ok_and(|text| text.trim_start().starts_with('['));
+    if !batch {
+        return match parsed
+            .and_then(|text| crate::borrowed::parse_message(text).map_err(|e| e.to_string()))
+        {
+            Ok(message) => {
+                respond_formatted(endpoint.processor.as_ref(), message, &ctx, &endpoint.format)
+                    .await
+            }
+            Err(e) => json(&parse_error(e)),
+        };
+    }
+    let entries: Vec<Box<serde_json::value::RawValue>> = match serde_json::from_slice(&body) {
+        Ok(entries) => entries,
+        Err(e) => return json(&parse_error(e.to_string())),
+    };
+    if let Some(error) =
+        super::batch::refusal(endpoint.processor.as_ref(), entries.len(), ctx.remote_addr)
+    {
+        return json(&error);
+    }
+
+    if entries.len() < endpoint.min_entries {
+        let responses = super::batch::process(&entries, |messages| {
+            endpoint
+                .processor
+                .process_batch_with_context(messages, &ctx)
+        })
+        .await;
+        if responses.is_empty() {
+            return StatusCode::NO_CONTENT.into_response();
+        }
+        let body = if ndjson {
+            responses
+                .iter()
+                .map(|response| render(&endpoint.format, response) + "\n")
+                .collect()
+        } else {
+            endpoint
+                .format
+                .to_string(&responses)
+                .unwrap_or_else(|e| render(&endpoint.format, &serialization_error(e)))
+        };
+        return ([(header::CONTENT_TYPE, content_type)], body).into_response();
+    }
+
+    let (tx, rx) = tokio::sync::mpsc::channel(16);
+    tokio::spawn(async move {
+        let mut written = 0usize;
+        for entry in entries {
+            let response = match crate::borrowed::parse_message(entry.get()) {
+                Ok(message) => {
+                    endpoint
+                        .processor
+                        .process_message_with_context(message, &ctx)
+                        .await
+                }
+                Err(e) => Some(super::batch::invalid_entry(e)),
+            };
+            let Some(response) = response else {
+                continue;
+            };
+            let json = render(&endpoint.format, &response);
+            let chunk = match (ndjson, written) {
+                (true, _) => json + "\n",
+                (false, 0) => format!("[{json}"),
+                (false, _) => format!(",{json}"),
+            };
+            written += 1;
+            if tx.send(axum::body::Bytes::from(chunk)).await.is_err() {
+                // the client went away
+                return;
+            }
+        }
+        if !ndjson && written > 0 {
+            let _ = tx.send(axum::body::Bytes::from_static(b"]")).await;
         }
+    });
+    (
+        [(header::CONTENT_TYPE, content_type)],
+        axum::body::Body::from_stream(Chunks(rx)),
+    )
+        .into_response()
+}
+
+fn parse_error(message: String) -> Response {
+    Response::error(
+        ErrorBuilder::new(error_codes::PARSE_ERROR, format!("Parse error: {message}")).build(),
+        None,
+    )
+}
+
+fn serialization_error(e: serde_json::Error) -> Response {
+    tracing::error!(error = %e, "response serialization failed");
+    Response::error(
+        ErrorBuilder::new(
+            error_codes::INTERNAL_ERROR,
+            "Response could not be serialized",
+        )
+        .build(),
+        None,
+    )
+}
+
+/// Body chunks of a streamed batch
+struct Chunks(tokio::sync::mpsc::Receiver<axum::body::Bytes>);
+
+impl futures_core::Stream for Chunks {
+    type Item = Result<axum::body::Bytes, std::convert::Infallible>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.poll_recv(cx).map(|chunk| chunk.map(Ok))
     }
+}
 
-    Json(responses)
+pub async fn handle_rpc_batch(
+    State(processor): State<Arc<dyn MessageProcessor + Send + Sync>>,
+    headers: HeaderMap,
+    extensions: Extensions,
+    Json(messages): Json<Vec<Message>>,
+) -> Json<Vec<Response>> {
+    let ctx = request_context(headers, &extensions);
+    Json(processor.process_batch_with_context(messages, &ctx).await)
 }
 
 impl Default for AxumRpcBuilder {
@@ -230,7 +731,13 @@ mod tests {
             .build();
         let message = Message::Request(request);
 
-        let result = handle_rpc(State(processor), Json(message)).await;
+        let result = handle_rpc(
+            State(processor),
+            HeaderMap::new(),
+            Extensions::new(),
+            Json(message),
+        )
+        .await;
         assert!(result.is_ok());
 
         let Json(response) = result.unwrap();
@@ -247,14 +754,46 @@ mod tests {
             params: None,
             id: None,
             correlation_id: None,
+            ext: None,
         };
         let message = Message::Request(notification);
 
-        let result = handle_rpc(State(processor), Json(message)).await;
+        let result = handle_rpc(
+            State(processor),
+            HeaderMap::new(),
+            Extensions::new(),
+            Json(message),
+        )
+        .await;
         // Notifications are handled by returning a response with id: None
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_handle_rpc_formatted_pretty() {
+        let processor: Arc<dyn MessageProcessor + Send + Sync> = Arc::new(MockProcessor);
+        let request = RequestBuilder::new("test_method")
+            .id(serde_json::Value::Number(1.into()))
+            .build();
+
+        let response = handle_rpc_formatted(
+            State((processor, JsonFormat::pretty())),
+            HeaderMap::new(),
+            Extensions::new(),
+            Json(Message::Request(request)),
+        )
+        .await;
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains('\n'));
+        let parsed: Response = serde_json::from_str(&body).unwrap();
+        assert!(parsed.is_success());
+    }
+
     #[tokio::test]
     async fn test_handle_rpc_batch() {
         let processor = Arc::new(MockProcessor);
@@ -267,7 +806,13 @@ mod tests {
 
         let messages = vec![Message::Request(request1), Message::Request(request2)];
 
-        let Json(responses) = handle_rpc_batch(State(processor), Json(messages)).await;
+        let Json(responses) = handle_rpc_batch(
+            State(processor),
+            HeaderMap::new(),
+            Extensions::new(),
+            Json(messages),
+        )
+        .await;
         assert_eq!(responses.len(), 2);
     }
 
@@ -276,7 +821,13 @@ mod tests {
         let processor = Arc::new(MockProcessor);
         let messages: Vec<Message> = vec![];
 
-        let Json(responses) = handle_rpc_batch(State(processor), Json(messages)).await;
+        let Json(responses) = handle_rpc_batch(
+            State(processor),
+            HeaderMap::new(),
+            Extensions::new(),
+            Json(messages),
+        )
+        .await;
         assert_eq!(responses.len(), 0);
     }
 
@@ -319,12 +870,191 @@ mod tests {
             params: None,
             id: None,
             correlation_id: None,
+            ext: None,
         };
 
         let messages = vec![Message::Request(request), Message::Request(notification)];
 
-        let Json(responses) = handle_rpc_batch(State(processor), Json(messages)).await;
+        let Json(responses) = handle_rpc_batch(
+            State(processor),
+            HeaderMap::new(),
+            Extensions::new(),
+            Json(messages),
+        )
+        .await;
         // Should have at least 1 response (from the request)
         assert!(!responses.is_empty());
     }
+
+    #[test]
+    fn test_request_context_carries_peer_and_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-tenant", "acme".parse().unwrap());
+        let mut extensions = Extensions::new();
+        let addr: SocketAddr = "192.0.2.10:5000".parse().unwrap();
+        extensions.insert(ConnectInfo(addr));
+
+        let ctx = request_context(headers, &extensions);
+        assert_eq!(ctx.remote_addr, Some(addr));
+        let headers = ctx.get::<HeaderMap>(crate::auth::HTTP_HEADERS_KEY).unwrap();
+        assert_eq!(headers["x-tenant"], "acme");
+
+        let ctx = request_context(HeaderMap::new(), &Extensions::new());
+        assert!(ctx.remote_addr.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_routes_serve_endpoints_and_specs() {
+        use super::super::listener::MethodFilter;
+        use axum::body::Body;
+        use axum::http::Request;
+        use http_body_util::BodyExt;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tower::ServiceExt;
+
+        async fn call(router: &Router, request: Request<Body>) -> (StatusCode, serde_json::Value) {
+            let response = router.clone().oneshot(request).await.unwrap();
+            let status = response.status();
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            (status, serde_json::from_slice(&body).unwrap_or_default())
+        }
+        fn rpc(path: &str, method: &str) -> Request<Body> {
+            Request::post(path)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(format!(
+                    r#"{{"jsonrpc":"2.0","method":"{method}","id":1}}"#
+                )))
+                .unwrap()
+        }
+
+        let mut spec = crate::OpenApiSpec::new("api", "1.0");
+        spec.add_method(crate::OpenApiMethodSpec::new("ping"));
+        spec.add_method(crate::OpenApiMethodSpec::new("admin.reset"));
+        let wrapped = Arc::new(AtomicUsize::new(0));
+        let count = Arc::clone(&wrapped);
+        let routes = AxumRpcRoutes::builder()
+            .endpoint(
+                AxumRpcLayer::builder()
+                    .path("/rpc/public")
+                    .processor(MockProcessor)
+                    .method_filter(MethodFilter::new().allow("ping"))
+                    .openapi(spec.clone()),
+            )
+            .endpoint(
+                AxumRpcLayer::builder()
+                    .path("/rpc/admin")
+                    .processor(MockProcessor)
+                    .openapi(spec),
+            )
+            .wrap(move |processor| {
+                count.fetch_add(1, Ordering::Relaxed);
+                processor
+            })
+            .openapi_suffix("/openapi.json")
+            .openrpc_suffix("/openrpc.json")
+            .build()
+            .unwrap();
+        assert_eq!(wrapped.load(Ordering::Relaxed), 2);
+        let public = routes.openapi("/rpc/public").unwrap();
+        assert_eq!(public.methods.len(), 1);
+        assert_eq!(public.servers[0].url, "/rpc/public");
+        assert_eq!(routes.openapi("/rpc/admin").unwrap().methods.len(), 2);
+
+        let router = routes.into_router();
+        let (_, body) = call(&router, rpc("/rpc/public", "ping")).await;
+        assert_eq!(body["result"]["result"], "success");
+        let (_, body) = call(&router, rpc("/rpc/public", "admin.reset")).await;
+        assert!(body["error"].is_object());
+        let (_, body) = call(&router, rpc("/rpc/admin", "admin.reset")).await;
+        assert_eq!(body["result"]["result"], "success");
+        let spec_request = |path: &str| Request::get(path).body(Body::empty()).unwrap();
+        let (status, body) = call(&router, spec_request("/rpc/admin/openapi.json")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["methods"]["admin.reset"].is_object());
+        let (_, body) = call(&router, spec_request("/rpc/public/openapi.json")).await;
+        assert!(body["methods"]["admin.reset"].is_null());
+        let (status, body) = call(&router, spec_request("/rpc/public/openrpc.json")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["openrpc"], crate::openrpc::OPENRPC_VERSION);
+        assert_eq!(
+            body["methods"],
+            serde_json::json!([{"name": "ping", "params": [], "result": {"name": "result", "schema": {}}}])
+        );
+
+        let duplicate = AxumRpcRoutes::builder()
+            .endpoint(AxumRpcLayer::builder().processor(MockProcessor))
+            .endpoint(AxumRpcLayer::builder().processor(MockProcessor))
+            .build();
+        assert_eq!(
+            duplicate.err().unwrap().kind(),
+            std::io::ErrorKind::InvalidInput
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_batches() {
+        use axum::body::Body;
+        use axum::http::Request;
+        use http_body_util::BodyExt;
+        use tower::ServiceExt;
+
+        async fn post(router: &Router, body: &str, accept: &str) -> (String, String) {
+            let request = Request::post("/rpc")
+                .header(header::ACCEPT, accept)
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            let content_type = response.headers()[header::CONTENT_TYPE]
+                .to_str()
+                .unwrap()
+                .to_string();
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            (content_type, String::from_utf8(body.to_vec()).unwrap())
+        }
+
+        let router = AxumRpcLayer::builder()
+            .processor(MockProcessor)
+            .stream_batches(3)
+            .build()
+            .unwrap()
+            .into_router();
+        let batch = concat!(
+            r#"[{"jsonrpc":"2.0","method":"a","id":1},"#,
+            r#"{"foo":1},"#,
+            r#"{"jsonrpc":"2.0","method":"b","id":2}]"#
+        );
+
+        let (content_type, body) = post(&router, batch, "application/json").await;
+        assert_eq!(content_type, "application/json");
+        let responses: Vec<Response> = serde_json::from_str(&body).unwrap();
+        let ids: Vec<_> = responses.iter().map(|r| r.id.clone()).collect();
+        assert_eq!(
+            ids,
+            [Some(serde_json::json!(1)), None, Some(serde_json::json!(2))]
+        );
+
+        let (content_type, body) = post(&router, batch, NDJSON).await;
+        assert_eq!(content_type, NDJSON);
+        assert_eq!(body.lines().count(), 3);
+        assert!(
+            body.lines()
+                .all(|line| serde_json::from_str::<Response>(line).is_ok())
+        );
+
+        // below the threshold and single requests are answered whole
+        let small = r#"[{"jsonrpc":"2.0","method":"a","id":1}]"#;
+        let (_, body) = post(&router, small, "application/json").await;
+        assert_eq!(
+            serde_json::from_str::<Vec<Response>>(&body).unwrap().len(),
+            1
+        );
+        let (_, body) = post(&router, r#"{"jsonrpc":"2.0","method":"a","id":7}"#, "*/*").await;
+        assert_eq!(
+            serde_json::from_str::<Response>(&body).unwrap().id,
+            Some(serde_json::json!(7))
+        );
+        let (_, body) = post(&router, "[]", "*/*").await;
+        let refused: Response = serde_json::from_str(&body).unwrap();
+        assert_eq!(refused.error.unwrap().code, error_codes::INVALID_REQUEST);
+    }
 }
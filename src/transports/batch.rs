@@ -0,0 +1,169 @@
+//! Batch dispatch shared by the stream and HTTP transports
+//!
+//! A frame holding a JSON array is a batch. Its entries are parsed one by
+//! one so a malformed entry only fails itself, and the whole batch is
+//! refused with a single error when it is empty, exceeds the processor's
+//! `max_batch_size` or the processor does not batch at all.
+
+use crate::{ErrorBuilder, MessageProcessor, Response, error_codes};
+use std::future::Future;
+use std::net::SocketAddr;
+
+/// The error answering a whole batch of `len` entries, if it is refused
+pub(crate) fn refusal(
+    processor: &dyn MessageProcessor,
+    len: usize,
+    remote_addr: Option<SocketAddr>,
+) -> Option<Response> {
+    let message = if len == 0 {
+        "Invalid Request: empty batch".to_string()
+    } else if !processor.supports_batching() {
+        "Batch requests are not supported".to_string()
+    } else {
+        match processor.get_capabilities().max_batch_size {
+            Some(max) if len > max => {
+                crate::rejection::record(
+                    crate::rejection::Rejection::new(
+                        crate::rejection::RejectionReason::BatchTooLarge,
+                    )
+                    .remote_addr(remote_addr)
+                    .detail(format!("{len} entries exceeds {max}")),
+                );
+                format!("Batch size {len} exceeds maximum {max}")
+            }
+            _ => return None,
+        }
+    };
+    Some(Response::error(
+        ErrorBuilder::new(error_codes::INVALID_REQUEST, message).build(),
+        None,
+    ))
+}
+
+/// The error answering a batch entry that is not a valid message
+pub(crate) fn invalid_entry(error: serde_json::Error) -> Response {
+    Response::error(
+        ErrorBuilder::new(
+            error_codes::INVALID_REQUEST,
+            format!("Invalid Request: {error}"),
+        )
+        .build(),
+        None,
+    )
+}
+
+/// Parse the entries of a batch and run the valid ones as one batch with
+/// `run`
+///
+/// Invalid entries are answered at their position among the responses.
+pub(crate) async fn process<F, Fut>(
+    entries: &[Box<serde_json::value::RawValue>],
+    run: F,
+) -> Vec<Response>
+where
+    F: FnOnce(Vec<crate::Message>) -> Fut,
+    Fut: Future<Output = Vec<Response>>,
+{
+    let mut messages = Vec::with_capacity(entries.len());
+    let mut positions = Vec::with_capacity(entries.len());
+    let mut invalid = Vec::new();
+    for (index, entry) in entries.iter().enumerate() {
+        match crate::borrowed::parse_message(entry.get()) {
+            Ok(message) => {
+                messages.push(message);
+                positions.push(index);
+            }
+            Err(e) => invalid.push((index, invalid_entry(e))),
+        }
+    }
+
+    let ids = crate::batch_policy::request_ids(&messages);
+    let processed = if messages.is_empty() {
+        Vec::new()
+    } else {
+        run(messages).await
+    };
+    if invalid.is_empty() {
+        return processed;
+    }
+    crate::batch_policy::merge_answers(&positions, &ids, processed, invalid)
+}
+
+/// Process a batch, returning the rendered response array, or a single
+/// error object when the batch is refused
+///
+/// Returns `None` when nothing needs answering, i.e. the batch held only
+/// notifications.
+#[cfg(any(
+    feature = "tcp",
+    feature = "tcp-stream",
+    feature = "tcp-stream-tls",
+    feature = "websocket"
+))]
+pub(crate) async fn respond(
+    processor: &dyn MessageProcessor,
+    entries: Vec<Box<serde_json::value::RawValue>>,
+    remote_addr: Option<SocketAddr>,
+) -> Result<Option<String>, serde_json::Error> {
+    if let Some(error) = refusal(processor, entries.len(), remote_addr) {
+        return serde_json::to_string(&error).map(Some);
+    }
+
+    let responses = process(&entries, |messages| processor.process_batch(messages)).await;
+    if responses.is_empty() {
+        return Ok(None);
+    }
+    serde_json::to_string(&responses).map(Some)
+}
+
+#[cfg(all(
+    test,
+    any(
+        feature = "tcp",
+        feature = "tcp-stream",
+        feature = "tcp-stream-tls",
+        feature = "websocket"
+    )
+))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use serde_json::value::RawValue;
+
+    fn entries(input: &str) -> Vec<Box<RawValue>> {
+        serde_json::from_str(input).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_batch_responses() {
+        let registry = crate::MethodRegistry::empty();
+        let batch = entries(r#"[{"jsonrpc":"2.0","method":"a","id":1},{"foo":1}]"#);
+        let json = respond(&registry, batch, None).await.unwrap().unwrap();
+        let responses: Vec<Response> = serde_json::from_str(&json).unwrap();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0].id, Some(json!(1)));
+        assert_eq!(
+            responses[1].error.as_ref().unwrap().code,
+            error_codes::INVALID_REQUEST
+        );
+
+        let batch = entries(
+            r#"[{"foo":1},{"jsonrpc":"2.0","method":"a","id":1},{"bar":2},{"jsonrpc":"2.0","method":"b","id":2}]"#,
+        );
+        let json = respond(&registry, batch, None).await.unwrap().unwrap();
+        let responses: Vec<Response> = serde_json::from_str(&json).unwrap();
+        let ids: Vec<_> = responses.iter().map(|r| r.id.clone()).collect();
+        assert_eq!(ids, [None, Some(json!(1)), None, Some(json!(2))]);
+
+        let empty = respond(&registry, Vec::new(), None).await.unwrap().unwrap();
+        let error: Response = serde_json::from_str(&empty).unwrap();
+        assert_eq!(error.error.unwrap().code, error_codes::INVALID_REQUEST);
+
+        let max = registry.get_capabilities().max_batch_size.unwrap();
+        let oversized = (0..=max)
+            .map(|_| RawValue::from_string(r#"{"jsonrpc":"2.0","method":"n"}"#.into()).unwrap())
+            .collect();
+        let refused = respond(&registry, oversized, None).await.unwrap().unwrap();
+        assert!(refused.starts_with('{'));
+    }
+}
@@ -0,0 +1,196 @@
+//! Per-codec connection and decode statistics
+//!
+//! Each connection reports the codec it ended up using with [`negotiated`],
+//! or [`fallback`] when the codec the peer asked for was not available, and
+//! then counts every frame it decodes on the returned [`CodecCounters`].
+//! The counters are process-wide so a codec rollout can be followed across
+//! all listeners: [`snapshot`] backs the `diagnostics.codecs` built-in and
+//! the Prometheus `codec_*` metrics.
+//!
+//! The TCP transports report [`JSON_LINES`], or [`CONTENT_LENGTH`] when
+//! configured for [`Framing::ContentLength`](super::framing::Framing); the
+//! WebSocket transport reports [`WEBSOCKET`]. Connections using a binary
+//! [`Codec`](crate::codec::Codec) report the codec's name instead, and a
+//! WebSocket client asking for a subprotocol the server does not offer is
+//! counted as a fallback.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// Newline-delimited JSON, the framing of the TCP transports
+pub const JSON_LINES: &str = "json-lines";
+
+/// JSON messages behind `Content-Length` headers, as in LSP
+pub const CONTENT_LENGTH: &str = "content-length";
+
+/// One JSON text message per WebSocket message
+pub const WEBSOCKET: &str = "websocket";
+
+/// Live counters of one codec
+#[derive(Debug, Default)]
+pub struct CodecCounters {
+    connections: AtomicU64,
+    fallbacks: AtomicU64,
+    frames_decoded: AtomicU64,
+    decode_errors: AtomicU64,
+}
+
+impl CodecCounters {
+    /// Count one decode attempt
+    pub fn observe(&self, decoded: bool) {
+        if decoded {
+            self.frames_decoded.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.decode_errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Point-in-time statistics of one codec
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CodecStats {
+    pub codec: String,
+    /// Connections that used this codec
+    pub connections: u64,
+    /// Connections that asked for this codec but used another one
+    pub fallbacks: u64,
+    pub frames_decoded: u64,
+    pub decode_errors: u64,
+    /// `decode_errors` over all decode attempts, 0 when nothing was decoded
+    pub decode_error_rate: f64,
+}
+
+type Codecs = RwLock<BTreeMap<String, Arc<CodecCounters>>>;
+
+fn codecs() -> &'static Codecs {
+    static CODECS: OnceLock<Codecs> = OnceLock::new();
+    CODECS.get_or_init(Default::default)
+}
+
+fn counters(codec: &str) -> Arc<CodecCounters> {
+    if let Some(counters) = codecs()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(codec)
+    {
+        return Arc::clone(counters);
+    }
+    let mut codecs = codecs().write().unwrap_or_else(|e| e.into_inner());
+    Arc::clone(codecs.entry(codec.to_string()).or_default())
+}
+
+/// Record a connection using `codec`
+pub fn negotiated(codec: &str) -> Arc<CodecCounters> {
+    let counters = counters(codec);
+    counters.connections.fetch_add(1, Ordering::Relaxed);
+    counters
+}
+
+/// Record a connection that asked for `requested` but uses `used`
+pub fn fallback(requested: &str, used: &str) -> Arc<CodecCounters> {
+    tracing::debug!(requested, used, "codec fallback");
+    counters(requested)
+        .fallbacks
+        .fetch_add(1, Ordering::Relaxed);
+    negotiated(used)
+}
+
+/// Statistics of every codec seen so far, sorted by name
+pub fn snapshot() -> Vec<CodecStats> {
+    codecs()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|(codec, counters)| {
+            let frames_decoded = counters.frames_decoded.load(Ordering::Relaxed);
+            let decode_errors = counters.decode_errors.load(Ordering::Relaxed);
+            let attempts = frames_decoded + decode_errors;
+            CodecStats {
+                codec: codec.clone(),
+                connections: counters.connections.load(Ordering::Relaxed),
+                fallbacks: counters.fallbacks.load(Ordering::Relaxed),
+                frames_decoded,
+                decode_errors,
+                decode_error_rate: if attempts == 0 {
+                    0.0
+                } else {
+                    decode_errors as f64 / attempts as f64
+                },
+            }
+        })
+        .collect()
+}
+
+/// `diagnostics.codecs`: the current [`snapshot`]
+pub struct CodecDiagnosticsMethod;
+
+#[async_trait::async_trait]
+impl crate::JsonRPCMethod for CodecDiagnosticsMethod {
+    fn method_name(&self) -> &'static str {
+        "codecs"
+    }
+
+    async fn call(
+        &self,
+        _params: Option<serde_json::Value>,
+        id: Option<crate::RequestId>,
+    ) -> crate::Response {
+        crate::rpc_success!(snapshot(), id)
+    }
+
+    fn openapi_components(&self) -> crate::OpenApiMethodSpec {
+        crate::OpenApiMethodSpec::new(self.method_name())
+            .with_summary("Connection and decode statistics per codec")
+            .with_tag("diagnostics")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(codec: &str) -> CodecStats {
+        snapshot().into_iter().find(|s| s.codec == codec).unwrap()
+    }
+
+    #[test]
+    fn test_negotiation_and_decode_counts() {
+        let counters = negotiated("test-codec-a");
+        counters.observe(true);
+        counters.observe(true);
+        counters.observe(true);
+        counters.observe(false);
+
+        let a = stats("test-codec-a");
+        assert_eq!(a.connections, 1);
+        assert_eq!(a.frames_decoded, 3);
+        assert_eq!(a.decode_errors, 1);
+        assert_eq!(a.decode_error_rate, 0.25);
+    }
+
+    #[test]
+    fn test_fallback_counted_on_requested_codec() {
+        fallback("test-codec-b", "test-codec-c");
+
+        let b = stats("test-codec-b");
+        assert_eq!((b.connections, b.fallbacks), (0, 1));
+        assert_eq!(b.decode_error_rate, 0.0);
+        assert_eq!(stats("test-codec-c").connections, 1);
+    }
+
+    #[tokio::test]
+    async fn test_diagnostics_builtin() {
+        use crate::builtins::{BuiltinConfig, BuiltinMethods};
+
+        negotiated("test-codec-d");
+        let registry = crate::MethodRegistry::empty()
+            .with_builtins(BuiltinConfig::new(BuiltinMethods::DIAGNOSTICS));
+        let response = registry
+            .call("diagnostics.codecs", None, Some(serde_json::json!(1)))
+            .await;
+        let stats: Vec<CodecStats> = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert!(stats.iter().any(|s| s.codec == "test-codec-d"));
+    }
+}
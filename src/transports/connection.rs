@@ -0,0 +1,155 @@
+//! Per-connection processor carrying the caller's context.
+//!
+//! Transports build a [`ConnectionContext`] for every accepted connection
+//! (remote address, TLS client certificate subject, ...) and wrap their
+//! processor in a [`ConnectionProcessor`], so auth policies and methods see
+//! who is calling on every message of that connection.
+
+use crate::auth::ConnectionContext;
+use crate::{Message, MessageProcessor, ProcessorCapabilities, Request, Response};
+use std::sync::Arc;
+
+/// Processor wrapper passing a fixed connection context to its inner processor
+pub struct ConnectionProcessor {
+    inner: Arc<dyn MessageProcessor + Send + Sync>,
+    context: ConnectionContext,
+}
+
+impl ConnectionProcessor {
+    pub fn new(inner: Arc<dyn MessageProcessor + Send + Sync>, context: ConnectionContext) -> Self {
+        Self { inner, context }
+    }
+
+    pub fn context(&self) -> &ConnectionContext {
+        &self.context
+    }
+}
+
+/// Bind `processor` to a new stream connection, through the handshake gate
+/// when one is configured
+#[cfg(any(feature = "tcp-stream", feature = "tcp-stream-tls"))]
+pub(crate) fn bind(
+    processor: Arc<dyn MessageProcessor + Send + Sync>,
+    handshake: Option<super::handshake::AuthHandshake>,
+    context: ConnectionContext,
+) -> (
+    Option<Arc<super::handshake::HandshakeProcessor>>,
+    Arc<dyn MessageProcessor + Send + Sync>,
+) {
+    match super::handshake::HandshakeProcessor::for_connection(&processor, handshake, &context) {
+        Some(gate) => (Some(Arc::clone(&gate)), gate),
+        None => (None, Arc::new(ConnectionProcessor::new(processor, context))),
+    }
+}
+
+#[async_trait::async_trait]
+impl MessageProcessor for ConnectionProcessor {
+    async fn process_message(&self, message: Message) -> Option<Response> {
+        self.inner
+            .process_message_with_context(message, &self.context)
+            .await
+    }
+
+    /// An explicitly passed context replaces the connection's own
+    async fn process_message_with_context(
+        &self,
+        message: Message,
+        ctx: &ConnectionContext,
+    ) -> Option<Response> {
+        self.inner.process_message_with_context(message, ctx).await
+    }
+
+    async fn admit(&self, request: &Request) -> Option<Response> {
+        self.inner.admit_with_context(request, &self.context).await
+    }
+
+    async fn admit_with_context(
+        &self,
+        request: &Request,
+        ctx: &ConnectionContext,
+    ) -> Option<Response> {
+        self.inner.admit_with_context(request, ctx).await
+    }
+
+    async fn process_batch(&self, messages: Vec<Message>) -> Vec<Response> {
+        self.inner
+            .process_batch_with_context(messages, &self.context)
+            .await
+    }
+
+    async fn process_batch_with_context(
+        &self,
+        messages: Vec<Message>,
+        ctx: &ConnectionContext,
+    ) -> Vec<Response> {
+        self.inner.process_batch_with_context(messages, ctx).await
+    }
+
+    fn static_response(
+        &self,
+        method: &str,
+        id: Option<&serde_json::value::RawValue>,
+    ) -> Option<String> {
+        self.inner.static_response(method, id)
+    }
+
+    fn supports_batching(&self) -> bool {
+        self.inner.supports_batching()
+    }
+
+    fn get_capabilities(&self) -> ProcessorCapabilities {
+        self.inner.get_capabilities()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::AuthPolicy;
+    use crate::{MethodRegistry, Request};
+
+    struct InternalOnly;
+
+    impl AuthPolicy for InternalOnly {
+        fn can_access(
+            &self,
+            _method: &str,
+            _params: Option<&serde_json::Value>,
+            ctx: &ConnectionContext,
+        ) -> bool {
+            ctx.remote_addr.is_some_and(|addr| addr.ip().is_loopback())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_context_reaches_auth_policy() {
+        let registry: Arc<dyn MessageProcessor + Send + Sync> =
+            Arc::new(MethodRegistry::empty().with_auth(InternalOnly));
+        let request = || Message::Request(Request::new("missing").with_id(serde_json::json!(1)));
+
+        let local = ConnectionProcessor::new(
+            Arc::clone(&registry),
+            ConnectionContext::with_addr("127.0.0.1:4000".parse().unwrap()),
+        );
+        let response = local.process_message(request()).await.unwrap();
+        assert_eq!(
+            response.error.unwrap().code,
+            crate::error_codes::METHOD_NOT_FOUND
+        );
+        let responses = local.process_batch(vec![request()]).await;
+        assert_eq!(
+            responses[0].error.as_ref().unwrap().code,
+            crate::error_codes::METHOD_NOT_FOUND
+        );
+
+        let remote = ConnectionProcessor::new(
+            registry,
+            ConnectionContext::with_addr("203.0.113.7:4000".parse().unwrap()),
+        );
+        let response = remote.process_message(request()).await.unwrap();
+        assert_ne!(
+            response.error.unwrap().code,
+            crate::error_codes::METHOD_NOT_FOUND
+        );
+    }
+}
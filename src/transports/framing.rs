@@ -0,0 +1,343 @@
+//! Message framing of the persistent TCP transports.
+//!
+//! By default each message travels as one line of JSON. With
+//! [`Framing::ContentLength`] every message is preceded by headers the way
+//! the Language Server and Debug Adapter protocols frame them, so payloads
+//! may contain newlines and LSP-style clients can connect directly:
+//!
+//! ```text
+//! Content-Length: 46\r\n
+//! \r\n
+//! {"jsonrpc":"2.0","method":"initialize","id":1}
+//! ```
+//!
+//! Headers other than `Content-Length`, such as `Content-Type`, are
+//! ignored. Responses use the same framing as the connection's requests.
+//!
+//! `Content-Length` framing also carries the binary [`Codec`]s; a payload
+//! that fails to decode is answered with a parse error and the connection
+//! continues with the next frame.
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "tcp-stream")]
+//! # fn example(registry: ash_rpc::MethodRegistry) {
+//! use ash_rpc::transports::{Framing, TcpStreamServer};
+//!
+//! let server = TcpStreamServer::builder("127.0.0.1:8080")
+//!     .processor(registry)
+//!     .framing(Framing::ContentLength)
+//!     .build();
+//! # }
+//! ```
+
+use crate::codec::{Codec, CodecError, JsonCodec};
+use std::borrow::Cow;
+use std::io;
+use std::sync::Arc;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// How messages are delimited on a connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Framing {
+    /// One message per line
+    #[default]
+    LineDelimited,
+    /// `Content-Length` headers, a blank line, then exactly that many bytes
+    ContentLength,
+}
+
+impl Framing {
+    /// Codec the connection reports to [`codec_stats`](super::codec_stats)
+    pub fn codec(&self) -> &'static str {
+        match self {
+            Framing::LineDelimited => super::codec_stats::JSON_LINES,
+            Framing::ContentLength => super::codec_stats::CONTENT_LENGTH,
+        }
+    }
+
+    /// Read the next message into `frame`, replacing its contents
+    ///
+    /// Returns the bytes consumed, 0 once the peer closed the connection.
+    /// A `Content-Length` above `max_size` (0 for no limit) fails with
+    /// [`io::ErrorKind::InvalidData`] before the payload is read, as does a
+    /// header block without a valid length.
+    pub async fn read_frame<R>(
+        &self,
+        reader: &mut R,
+        frame: &mut String,
+        max_size: usize,
+    ) -> io::Result<usize>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        frame.clear();
+        match self {
+            Framing::LineDelimited => reader.read_line(frame).await,
+            Framing::ContentLength => {
+                let mut payload = Vec::new();
+                let read = read_content_length(reader, &mut payload, max_size).await?;
+                frame.push_str(std::str::from_utf8(&payload).map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("payload is not UTF-8: {e}"),
+                    )
+                })?);
+                Ok(read)
+            }
+        }
+    }
+
+    /// Write `payload` as one message, without flushing
+    pub async fn write_frame<W>(&self, writer: &mut W, payload: &str) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        self.write_bytes(writer, payload.as_bytes()).await
+    }
+
+    async fn write_bytes<W>(&self, writer: &mut W, payload: &[u8]) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        match self {
+            Framing::LineDelimited => {
+                writer.write_all(payload).await?;
+                writer.write_all(b"\n").await
+            }
+            Framing::ContentLength => {
+                let header = format!("Content-Length: {}\r\n\r\n", payload.len());
+                writer.write_all(header.as_bytes()).await?;
+                writer.write_all(payload).await
+            }
+        }
+    }
+}
+
+/// Framing and codec of a connection
+#[derive(Debug, Clone)]
+pub(crate) struct Wire {
+    framing: Framing,
+    codec: Arc<dyn Codec>,
+    tap: Option<super::tap::ConnectionTap>,
+}
+
+impl Default for Wire {
+    fn default() -> Self {
+        Self {
+            framing: Framing::default(),
+            codec: Arc::new(JsonCodec),
+            tap: None,
+        }
+    }
+}
+
+impl Wire {
+    /// Fails for a binary codec without `Content-Length` framing
+    pub(crate) fn new(framing: Framing, codec: Arc<dyn Codec>) -> io::Result<Self> {
+        if codec.is_binary() && framing != Framing::ContentLength {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("the {} codec requires Content-Length framing", codec.name()),
+            ));
+        }
+        Ok(Self {
+            framing,
+            codec,
+            tap: None,
+        })
+    }
+
+    /// Mirror the payloads of this connection's frames to `tap`
+    pub(crate) fn tapped(mut self, tap: Option<super::tap::ConnectionTap>) -> Self {
+        self.tap = tap;
+        self
+    }
+
+    /// Codec the connection reports to [`codec_stats`](super::codec_stats)
+    pub(crate) fn codec_name(&self) -> &'static str {
+        if self.codec.is_binary() {
+            self.codec.name()
+        } else {
+            self.framing.codec()
+        }
+    }
+
+    /// [`Framing::read_frame`], decoding the payload to JSON text
+    pub(crate) async fn read_frame<R>(
+        &self,
+        reader: &mut R,
+        frame: &mut String,
+        max_size: usize,
+    ) -> io::Result<usize>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        use super::tap::TapDirection::Inbound;
+        if !self.codec.is_binary() {
+            let read = self.framing.read_frame(reader, frame, max_size).await?;
+            if let Some(tap) = &self.tap
+                && read > 0
+            {
+                tap.capture(
+                    Inbound,
+                    frame.strip_suffix('\n').unwrap_or(frame).as_bytes(),
+                );
+            }
+            return Ok(read);
+        }
+        frame.clear();
+        let mut payload = Vec::new();
+        let read = read_content_length(reader, &mut payload, max_size).await?;
+        if read > 0 {
+            if let Some(tap) = &self.tap {
+                tap.capture(Inbound, &payload);
+            }
+            frame.push_str(&self.codec.decode_json(&payload).map_err(io::Error::other)?);
+        }
+        Ok(read)
+    }
+
+    /// [`Framing::write_frame`], encoding the JSON text `payload`
+    pub(crate) async fn write_frame<W>(&self, writer: &mut W, payload: &str) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let payload = if self.codec.is_binary() {
+            Cow::Owned(self.codec.encode_json(payload).map_err(io::Error::other)?)
+        } else {
+            Cow::Borrowed(payload.as_bytes())
+        };
+        if let Some(tap) = &self.tap {
+            tap.capture(super::tap::TapDirection::Outbound, &payload);
+        }
+        self.framing.write_bytes(writer, &payload).await
+    }
+}
+
+/// Parse error response for a frame read that failed only because its
+/// payload did not decode; the frame itself was consumed
+pub(crate) fn decode_failure(error: &io::Error) -> Option<String> {
+    let error = error.get_ref()?.downcast_ref::<CodecError>()?;
+    let response = crate::Response::error(
+        crate::ErrorBuilder::new(crate::error_codes::PARSE_ERROR, error.to_string()).build(),
+        None,
+    );
+    serde_json::to_string(&response).ok()
+}
+
+async fn read_content_length<R>(
+    reader: &mut R,
+    payload: &mut Vec<u8>,
+    max_size: usize,
+) -> io::Result<usize>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+    let mut header = String::new();
+    let mut consumed = 0;
+    let mut headers = false;
+    let mut length = None;
+    loop {
+        header.clear();
+        let read = reader.read_line(&mut header).await?;
+        if read == 0 {
+            if !headers {
+                return Ok(0);
+            }
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        consumed += read;
+        let line = header.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            // blank lines between messages are tolerated
+            if !headers {
+                continue;
+            }
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(invalid(format!("malformed header line: {line}")));
+        };
+        headers = true;
+        if name.trim().eq_ignore_ascii_case("content-length") {
+            let value = value.trim();
+            length = Some(
+                value
+                    .parse::<usize>()
+                    .map_err(|_| invalid(format!("invalid Content-Length: {value}")))?,
+            );
+        }
+    }
+
+    let Some(length) = length else {
+        return Err(invalid("missing Content-Length header".to_string()));
+    };
+    if max_size > 0 && length > max_size {
+        return Err(invalid(format!(
+            "Content-Length {length} exceeds maximum {max_size}"
+        )));
+    }
+    payload.resize(length, 0);
+    reader.read_exact(payload).await?;
+    Ok(consumed + length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::BufReader;
+
+    #[tokio::test]
+    async fn test_content_length_round_trip() {
+        let (client, server) = tokio::io::duplex(1024);
+        let (_, mut client) = tokio::io::split(client);
+        let payload = "{\"jsonrpc\":\"2.0\",\n\"method\":\"initialize\",\"id\":1}";
+        Framing::ContentLength
+            .write_frame(&mut client, payload)
+            .await
+            .unwrap();
+        client
+            .write_all(b"Content-Type: application/vscode-jsonrpc; charset=utf-8\r\ncontent-length: 2\r\n\r\n{}")
+            .await
+            .unwrap();
+        drop(client);
+
+        let mut reader = BufReader::new(server);
+        let mut frame = String::new();
+        let read = Framing::ContentLength
+            .read_frame(&mut reader, &mut frame, 0)
+            .await
+            .unwrap();
+        assert_eq!(frame, payload);
+        let header = format!("Content-Length: {}\r\n\r\n", payload.len());
+        assert_eq!(read, header.len() + payload.len());
+        Framing::ContentLength
+            .read_frame(&mut reader, &mut frame, 0)
+            .await
+            .unwrap();
+        assert_eq!(frame, "{}");
+        let end = Framing::ContentLength
+            .read_frame(&mut reader, &mut frame, 0)
+            .await
+            .unwrap();
+        assert_eq!(end, 0);
+    }
+
+    #[tokio::test]
+    async fn test_content_length_limits() {
+        let mut oversized = BufReader::new(&b"Content-Length: 100\r\n\r\n"[..]);
+        let error = Framing::ContentLength
+            .read_frame(&mut oversized, &mut String::new(), 64)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let mut missing = BufReader::new(&b"Content-Type: json\r\n\r\n{}"[..]);
+        let error = Framing::ContentLength
+            .read_frame(&mut missing, &mut String::new(), 0)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,476 @@
+//! Zero-downtime restarts by passing listening sockets to a new process.
+//!
+//! The running process serves a small control socket. A newly started
+//! process connects to it, receives duplicates of the listening sockets
+//! (`SCM_RIGHTS` over a unix socket) and starts accepting on them. Once it
+//! reports ready, the old process stops accepting and drains the
+//! connections it still has; the shared accept queue means no connection
+//! attempt is refused in between. If the new process dies before reporting
+//! ready, the old one carries on and waits for the next successor.
+//!
+//! Passing sockets needs the `listener-handoff` feature on a unix target.
+//! Without it [`inherit`](ListenerHandoff::inherit) never finds a
+//! predecessor and [`serve`](ListenerHandoff::serve) fails as unsupported.
+//!
+//! ```rust,no_run
+//! # #[cfg(all(feature = "tcp-stream", feature = "listener-handoff"))]
+//! # async fn example(registry: ash_rpc::MethodRegistry) -> Result<(), Box<dyn std::error::Error>> {
+//! use ash_rpc::transports::{ListenerHandoff, TcpStreamServer};
+//! use std::sync::Arc;
+//!
+//! let handoff = Arc::new(ListenerHandoff::new("/run/my-service/handoff.sock"));
+//! // take over the listeners of a running predecessor, if there is one
+//! handoff.inherit().await?;
+//!
+//! let server = TcpStreamServer::builder("0.0.0.0:8080")
+//!     .processor(registry)
+//!     .handoff(Arc::clone(&handoff), "rpc")
+//!     .build()?;
+//! let server = tokio::spawn(async move { server.run().await.map_err(|e| e.to_string()) });
+//!
+//! // let the predecessor drain, then wait for our own successor
+//! handoff.ready().await?;
+//! handoff.serve().await?;
+//! // `run` returns once the remaining connections have drained
+//! server.await??;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Protocol, one connection per handoff: the successor sends `take\n`, the
+//! predecessor answers with a JSON array of listener names carrying one
+//! descriptor per name, and the successor sends `ready\n` once it accepts.
+
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+
+#[cfg(all(unix, feature = "listener-handoff"))]
+const TAKE: &str = "take";
+#[cfg(all(unix, feature = "listener-handoff"))]
+const READY: &str = "ready";
+
+/// Passes a process's listening sockets to its successor
+pub struct ListenerHandoff {
+    #[cfg_attr(not(all(unix, feature = "listener-handoff")), allow(dead_code))]
+    path: PathBuf,
+    drain_timeout: Duration,
+    /// Received from the predecessor, not yet claimed by a server
+    inherited: Mutex<HashMap<String, std::net::TcpListener>>,
+    /// Our listeners, offered to the successor
+    published: Mutex<Vec<(String, std::net::TcpListener)>>,
+    #[cfg(all(unix, feature = "listener-handoff"))]
+    predecessor: Mutex<Option<std::os::unix::net::UnixStream>>,
+    handed_off: watch::Sender<bool>,
+}
+
+impl ListenerHandoff {
+    /// Coordinate through the unix socket at `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            drain_timeout: Duration::from_secs(30),
+            inherited: Mutex::new(HashMap::new()),
+            published: Mutex::new(Vec::new()),
+            #[cfg(all(unix, feature = "listener-handoff"))]
+            predecessor: Mutex::new(None),
+            handed_off: watch::channel(false).0,
+        }
+    }
+
+    /// How long servers wait for open connections after handing off,
+    /// 30 seconds by default
+    pub fn drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// Take the listeners of a running predecessor
+    ///
+    /// Returns `false` when no predecessor is listening on the control
+    /// socket, as on a cold start; servers then bind their own listeners.
+    pub async fn inherit(&self) -> io::Result<bool> {
+        #[cfg(all(unix, feature = "listener-handoff"))]
+        {
+            let path = self.path.clone();
+            let taken = tokio::task::spawn_blocking(move || unix::take(&path))
+                .await
+                .map_err(io::Error::other)??;
+            let Some((stream, listeners)) = taken else {
+                return Ok(false);
+            };
+            tracing::info!(
+                listeners = listeners.len(),
+                "inherited listeners from predecessor"
+            );
+            lock(&self.inherited).extend(listeners);
+            *lock(&self.predecessor) = Some(stream);
+            Ok(true)
+        }
+        #[cfg(not(all(unix, feature = "listener-handoff")))]
+        Ok(false)
+    }
+
+    /// The listener called `name`: inherited when available, otherwise bound
+    /// at `addr`. Either way it is offered to the next successor.
+    pub async fn listener(
+        &self,
+        name: &str,
+        addr: &str,
+        backlog: Option<u32>,
+    ) -> io::Result<TcpListener> {
+        let inherited = lock(&self.inherited).remove(name);
+        let listener = match inherited {
+            Some(listener) => {
+                listener.set_nonblocking(true)?;
+                TcpListener::from_std(listener)?
+            }
+            None => super::accept::bind(addr, backlog).await?,
+        };
+        let published = socket2::SockRef::from(&listener).try_clone()?;
+        lock(&self.published).push((name.to_string(), published.into()));
+        Ok(listener)
+    }
+
+    /// Tell the predecessor that this process accepts connections, so it
+    /// stops accepting and drains
+    pub async fn ready(&self) -> io::Result<()> {
+        #[cfg(all(unix, feature = "listener-handoff"))]
+        if let Some(mut stream) = lock(&self.predecessor).take() {
+            use std::io::Write;
+            stream.write_all(format!("{READY}\n").as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Serve the control socket until a successor has taken over
+    ///
+    /// Afterwards servers using this handoff stop accepting and their `run`
+    /// returns once open connections have closed or the drain timeout
+    /// passed.
+    pub async fn serve(&self) -> io::Result<()> {
+        #[cfg(all(unix, feature = "listener-handoff"))]
+        {
+            let _ = std::fs::remove_file(&self.path);
+            let control = tokio::net::UnixListener::bind(&self.path)?;
+            loop {
+                let stream = control.accept().await?.0.into_std()?;
+                stream.set_nonblocking(false)?;
+                let listeners = lock(&self.published)
+                    .iter()
+                    .map(|(name, listener)| Ok((name.clone(), listener.try_clone()?)))
+                    .collect::<io::Result<Vec<_>>>()?;
+                let handed = tokio::task::spawn_blocking(move || unix::give(stream, listeners))
+                    .await
+                    .map_err(io::Error::other)?;
+                match handed {
+                    Ok(()) => break,
+                    Err(e) => tracing::warn!(error = %e, "listener handoff aborted"),
+                }
+            }
+            // the path now belongs to the successor's control socket
+            tracing::info!("listeners handed off; draining");
+            self.handed_off.send_replace(true);
+            Ok(())
+        }
+        #[cfg(not(all(unix, feature = "listener-handoff")))]
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "listener handoff needs unix sockets",
+        ))
+    }
+
+    pub fn is_handed_off(&self) -> bool {
+        *self.handed_off.borrow()
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A server's named listener within a [`ListenerHandoff`]
+#[derive(Clone)]
+pub(crate) struct HandoffSlot {
+    pub(crate) handoff: Arc<ListenerHandoff>,
+    pub(crate) name: String,
+}
+
+/// Listener for a server, bound normally when it takes no part in handoff
+pub(crate) async fn listen(
+    slot: Option<&HandoffSlot>,
+    addr: &str,
+    backlog: Option<u32>,
+) -> io::Result<(TcpListener, Option<watch::Receiver<bool>>)> {
+    match slot {
+        Some(slot) => Ok((
+            slot.handoff.listener(&slot.name, addr, backlog).await?,
+            Some(slot.handoff.handed_off.subscribe()),
+        )),
+        None => Ok((super::accept::bind(addr, backlog).await?, None)),
+    }
+}
+
+/// Next connection, or `None` once the listener has been handed off
+pub(crate) async fn accept(
+    listener: &TcpListener,
+    handed_off: Option<&mut watch::Receiver<bool>>,
+) -> Option<io::Result<(TcpStream, SocketAddr)>> {
+    let Some(handed_off) = handed_off else {
+        return Some(listener.accept().await);
+    };
+    let mut stop = std::pin::pin!(handed_off.wait_for(|handed| *handed));
+    let mut accept = std::pin::pin!(listener.accept());
+    std::future::poll_fn(|cx| {
+        if stop.as_mut().poll(cx).is_ready() {
+            return std::task::Poll::Ready(None);
+        }
+        accept.as_mut().poll(cx).map(Some)
+    })
+    .await
+}
+
+/// Wait for a handed-off server's connections to finish
+pub(crate) async fn drain(slot: Option<&HandoffSlot>, active_connections: &AtomicUsize) {
+    let timeout = slot.map_or(Duration::ZERO, |slot| slot.handoff.drain_timeout);
+    let deadline = tokio::time::Instant::now() + timeout;
+    while active_connections.load(Ordering::Relaxed) > 0 {
+        if tokio::time::Instant::now() >= deadline {
+            tracing::warn!(
+                remaining = active_connections.load(Ordering::Relaxed),
+                "drain timeout after handoff"
+            );
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+#[cfg(all(unix, feature = "listener-handoff"))]
+mod unix {
+    use super::{READY, TAKE};
+    use std::io::{self, BufRead, BufReader, Write};
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+    use std::os::unix::net::UnixStream;
+    use std::path::Path;
+
+    const MAX_LISTENERS: usize = 64;
+
+    pub(super) type Listeners = Vec<(String, std::net::TcpListener)>;
+
+    /// Successor side: ask for listeners, `None` when nobody is serving
+    pub(super) fn take(path: &Path) -> io::Result<Option<(UnixStream, Listeners)>> {
+        let mut stream = match UnixStream::connect(path) {
+            Ok(stream) => stream,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused
+                ) =>
+            {
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        };
+        stream.write_all(format!("{TAKE}\n").as_bytes())?;
+
+        let mut payload = vec![0; 16 * 1024];
+        let (len, fds) = recv_with_fds(&stream, &mut payload)?;
+        let names: Vec<String> = serde_json::from_slice(&payload[..len])
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if names.len() != fds.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} names for {} descriptors", names.len(), fds.len()),
+            ));
+        }
+        let listeners = names
+            .into_iter()
+            .zip(fds)
+            .map(|(name, fd)| (name, std::net::TcpListener::from(fd)))
+            .collect();
+        Ok(Some((stream, listeners)))
+    }
+
+    /// Predecessor side: hand `listeners` over and wait for `ready`
+    pub(super) fn give(stream: UnixStream, listeners: Listeners) -> io::Result<()> {
+        let mut reader = BufReader::new(&stream);
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        if line.trim() != TAKE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unexpected handoff request {:?}", line.trim()),
+            ));
+        }
+
+        let names: Vec<&str> = listeners.iter().map(|(name, _)| name.as_str()).collect();
+        let fds: Vec<RawFd> = listeners.iter().map(|(_, l)| l.as_raw_fd()).collect();
+        let payload = serde_json::to_vec(&names).map_err(io::Error::other)?;
+        send_with_fds(&stream, &payload, &fds)?;
+
+        line.clear();
+        reader.read_line(&mut line)?;
+        if line.trim() == READY {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "successor went away before it was ready",
+            ))
+        }
+    }
+
+    fn control_buffer(fds: usize) -> Vec<u64> {
+        // SAFETY: CMSG_SPACE only computes a size
+        let space = unsafe { libc::CMSG_SPACE(std::mem::size_of::<RawFd>() as u32 * fds as u32) };
+        // u64 elements keep the buffer aligned for `cmsghdr`
+        vec![0; (space as usize).div_ceil(8)]
+    }
+
+    fn send_with_fds(stream: &UnixStream, payload: &[u8], fds: &[RawFd]) -> io::Result<()> {
+        if fds.len() > MAX_LISTENERS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "too many listeners",
+            ));
+        }
+        let mut iov = libc::iovec {
+            iov_base: payload.as_ptr() as *mut libc::c_void,
+            iov_len: payload.len(),
+        };
+        let mut control = control_buffer(fds.len());
+        let fd_bytes = std::mem::size_of_val(fds);
+
+        // SAFETY: msghdr is plain data; every pointer set below refers to a
+        // live buffer of the stated length for the duration of sendmsg.
+        let sent = unsafe {
+            let mut msg: libc::msghdr = std::mem::zeroed();
+            msg.msg_iov = &mut iov;
+            msg.msg_iovlen = 1;
+            if !fds.is_empty() {
+                msg.msg_control = control.as_mut_ptr().cast();
+                msg.msg_controllen = std::mem::size_of_val(control.as_slice()) as _;
+                let cmsg = libc::CMSG_FIRSTHDR(&msg);
+                (*cmsg).cmsg_level = libc::SOL_SOCKET;
+                (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+                (*cmsg).cmsg_len = libc::CMSG_LEN(fd_bytes as u32) as _;
+                std::ptr::copy_nonoverlapping(
+                    fds.as_ptr().cast::<u8>(),
+                    libc::CMSG_DATA(cmsg),
+                    fd_bytes,
+                );
+            }
+            libc::sendmsg(stream.as_raw_fd(), &msg, 0)
+        };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if sent as usize != payload.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "short handoff write",
+            ));
+        }
+        Ok(())
+    }
+
+    fn recv_with_fds(stream: &UnixStream, payload: &mut [u8]) -> io::Result<(usize, Vec<OwnedFd>)> {
+        let mut iov = libc::iovec {
+            iov_base: payload.as_mut_ptr().cast(),
+            iov_len: payload.len(),
+        };
+        let mut control = control_buffer(MAX_LISTENERS);
+        let mut fds = Vec::new();
+
+        // SAFETY: as in send_with_fds; the kernel writes at most
+        // msg_controllen bytes of control data, which is walked with the
+        // CMSG_* helpers and only read within each header's length.
+        let (received, truncated) = unsafe {
+            let mut msg: libc::msghdr = std::mem::zeroed();
+            msg.msg_iov = &mut iov;
+            msg.msg_iovlen = 1;
+            msg.msg_control = control.as_mut_ptr().cast();
+            msg.msg_controllen = std::mem::size_of_val(control.as_slice()) as _;
+            let received = libc::recvmsg(stream.as_raw_fd(), &mut msg, 0);
+            if received < 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+            while !cmsg.is_null() {
+                if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                    let data = libc::CMSG_DATA(cmsg);
+                    let len = (*cmsg).cmsg_len as usize - libc::CMSG_LEN(0) as usize;
+                    for i in 0..len / std::mem::size_of::<RawFd>() {
+                        let fd = std::ptr::read_unaligned(data.cast::<RawFd>().add(i));
+                        libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
+                        fds.push(OwnedFd::from_raw_fd(fd));
+                    }
+                }
+                cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+            }
+            (received as usize, msg.msg_flags & libc::MSG_CTRUNC != 0)
+        };
+        if truncated {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "handoff descriptors truncated",
+            ));
+        }
+        Ok((received, fds))
+    }
+}
+
+#[cfg(all(test, unix, feature = "listener-handoff"))]
+mod tests {
+    use super::*;
+
+    fn socket_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ash-rpc-{name}-{}.sock", std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn test_cold_start_inherits_nothing() {
+        let handoff = ListenerHandoff::new(socket_path("cold"));
+        assert!(!handoff.inherit().await.unwrap());
+        assert!(handoff.listener("rpc", "127.0.0.1:0", None).await.is_ok());
+        assert!(handoff.ready().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_listener_handed_to_successor() {
+        let path = socket_path("handoff");
+        let old = Arc::new(ListenerHandoff::new(&path));
+        let old_listener = old.listener("rpc", "127.0.0.1:0", None).await.unwrap();
+        let addr = old_listener.local_addr().unwrap();
+        let mut stop = old.handed_off.subscribe();
+        let serving = tokio::spawn({
+            let old = Arc::clone(&old);
+            async move { old.serve().await }
+        });
+        while !path.exists() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        let new = ListenerHandoff::new(&path);
+        assert!(new.inherit().await.unwrap());
+        let new_listener = new.listener("rpc", "unused:0", None).await.unwrap();
+        assert_eq!(new_listener.local_addr().unwrap(), addr);
+        assert!(!old.is_handed_off());
+
+        new.ready().await.unwrap();
+        serving.await.unwrap().unwrap();
+        assert!(old.is_handed_off());
+        assert!(accept(&old_listener, Some(&mut stop)).await.is_none());
+
+        let _client = TcpStream::connect(addr).await.unwrap();
+        assert!(accept(&new_listener, None).await.unwrap().is_ok());
+    }
+}
@@ -0,0 +1,460 @@
+//! First-message authentication for persistent connections
+//!
+//! Raw TCP has no headers to carry credentials, so a connection configured
+//! with an [`AuthHandshake`] must first call `rpc.authenticate`:
+//!
+//! ```json
+//! {"jsonrpc": "2.0", "method": "rpc.authenticate", "params": {"token": "..."}, "id": 0}
+//! ```
+//!
+//! Until that succeeds every other request is answered with
+//! [`AUTHENTICATION_REQUIRED`] and never reaches the processor. Each failed
+//! attempt is recorded as an `authentication_failed` rejection, which an
+//! `AuditRejectionObserver` audits when registered; after
+//! [`AuthHandshake::max_attempts`] failures the transport closes the
+//! connection.
+//!
+//! The context the authenticator fills in is passed along with every later
+//! request, so an [`AuthPolicy`](crate::auth::AuthPolicy) can see who
+//! authenticated the connection.
+
+use crate::auth::ConnectionContext;
+use crate::{Message, MessageProcessor, ProcessorCapabilities, Request, RequestId, Response};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, OnceLock};
+
+/// Method name of the handshake request
+pub const AUTHENTICATE_METHOD: &str = "rpc.authenticate";
+
+/// Error code for calls on a connection that is not authenticated
+pub const AUTHENTICATION_REQUIRED: i32 = -32002;
+
+/// Checks the credentials sent with `rpc.authenticate`
+#[async_trait::async_trait]
+pub trait ConnectionAuthenticator: Send + Sync {
+    /// Accept or refuse the connection's credentials
+    ///
+    /// On success, store whatever later authorization needs (user id,
+    /// roles, ...) in `ctx`. The error text is only logged and audited,
+    /// never sent to the client.
+    async fn authenticate(
+        &self,
+        params: Option<&serde_json::Value>,
+        ctx: &mut ConnectionContext,
+    ) -> Result<(), String>;
+}
+
+/// Handshake configuration shared by all connections of a listener
+#[derive(Clone)]
+pub struct AuthHandshake {
+    authenticator: Arc<dyn ConnectionAuthenticator>,
+    max_attempts: u32,
+}
+
+impl AuthHandshake {
+    /// Require `authenticator` to accept the connection, allowing 3 attempts
+    pub fn new<A>(authenticator: A) -> Self
+    where
+        A: ConnectionAuthenticator + 'static,
+    {
+        Self {
+            authenticator: Arc::new(authenticator),
+            max_attempts: 3,
+        }
+    }
+
+    /// Failed attempts after which the connection is closed
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+}
+
+/// Per-connection processor enforcing an [`AuthHandshake`]
+pub struct HandshakeProcessor {
+    inner: Arc<dyn MessageProcessor + Send + Sync>,
+    handshake: AuthHandshake,
+    connection: ConnectionContext,
+    authenticated: OnceLock<ConnectionContext>,
+    failures: AtomicU32,
+}
+
+impl HandshakeProcessor {
+    pub fn new(
+        inner: Arc<dyn MessageProcessor + Send + Sync>,
+        handshake: AuthHandshake,
+        connection: ConnectionContext,
+    ) -> Self {
+        Self {
+            inner,
+            handshake,
+            connection,
+            authenticated: OnceLock::new(),
+            failures: AtomicU32::new(0),
+        }
+    }
+
+    /// Gate for a new connection, or `None` when no handshake is configured
+    ///
+    /// The authenticator starts from `connection`, the context the transport
+    /// built for the connection.
+    pub fn for_connection(
+        inner: &Arc<dyn MessageProcessor + Send + Sync>,
+        handshake: Option<AuthHandshake>,
+        connection: &ConnectionContext,
+    ) -> Option<Arc<Self>> {
+        handshake
+            .map(|handshake| Arc::new(Self::new(Arc::clone(inner), handshake, connection.clone())))
+    }
+
+    pub fn is_authenticated(&self) -> bool {
+        self.authenticated.get().is_some()
+    }
+
+    /// True once the connection used up its attempts
+    pub fn should_close(&self) -> bool {
+        !self.is_authenticated()
+            && self.failures.load(Ordering::Relaxed) >= self.handshake.max_attempts
+    }
+
+    async fn authenticate(
+        &self,
+        params: Option<&serde_json::Value>,
+        id: Option<RequestId>,
+    ) -> Response {
+        let mut ctx = self.connection.clone();
+        match self
+            .handshake
+            .authenticator
+            .authenticate(params, &mut ctx)
+            .await
+        {
+            Ok(()) => {
+                tracing::info!(remote_addr = ?self.connection.remote_addr, "connection authenticated");
+                let _ = self.authenticated.set(ctx);
+                crate::rpc_success!(serde_json::json!({ "authenticated": true }), id)
+            }
+            Err(reason) => {
+                let failures = self.failures.fetch_add(1, Ordering::Relaxed) + 1;
+                crate::rejection::record(
+                    crate::rejection::Rejection::new(
+                        crate::rejection::RejectionReason::AuthenticationFailed,
+                    )
+                    .method(AUTHENTICATE_METHOD)
+                    .remote_addr(self.connection.remote_addr)
+                    .detail(format!(
+                        "attempt {failures} of {}: {reason}",
+                        self.handshake.max_attempts
+                    )),
+                );
+                crate::rpc_error!(AUTHENTICATION_REQUIRED, "Authentication failed", id)
+            }
+        }
+    }
+
+    fn refuse(&self, method: &str) {
+        crate::rejection::record(
+            crate::rejection::Rejection::new(crate::rejection::RejectionReason::Unauthorized)
+                .method(method)
+                .remote_addr(self.connection.remote_addr)
+                .detail("connection not authenticated"),
+        );
+    }
+}
+
+#[async_trait::async_trait]
+impl MessageProcessor for HandshakeProcessor {
+    async fn process_message(&self, message: Message) -> Option<Response> {
+        if let Some(ctx) = self.authenticated.get() {
+            if let Message::Request(request) = &message
+                && request.method() == AUTHENTICATE_METHOD
+            {
+                return Some(crate::rpc_error!(
+                    crate::error_codes::INVALID_REQUEST,
+                    "Connection already authenticated",
+                    request.id.clone()
+                ));
+            }
+            return self.inner.process_message_with_context(message, ctx).await;
+        }
+
+        match message {
+            Message::Request(request) if request.method() == AUTHENTICATE_METHOD => Some(
+                self.authenticate(request.params(), request.id.clone())
+                    .await,
+            ),
+            Message::Request(request) => {
+                self.refuse(request.method());
+                Some(crate::rpc_error!(
+                    AUTHENTICATION_REQUIRED,
+                    "Authentication required",
+                    request.id
+                ))
+            }
+            Message::Notification(notification) => {
+                self.refuse(notification.method());
+                None
+            }
+            Message::Response(_) => None,
+        }
+    }
+
+    async fn process_batch(&self, messages: Vec<Message>) -> Vec<Response> {
+        let Some(ctx) = self.authenticated.get() else {
+            // the handshake is a single request, batches wait for its outcome
+            return messages
+                .into_iter()
+                .filter_map(|message| {
+                    let method = message.method()?.to_string();
+                    self.refuse(&method);
+                    let id = message.id()?.clone();
+                    Some(crate::rpc_error!(
+                        AUTHENTICATION_REQUIRED,
+                        "Authentication required",
+                        Some(id)
+                    ))
+                })
+                .collect();
+        };
+        // handshakes stay in the batch so policy and metadata see every
+        // entry; their answers are replaced afterwards
+        let handshakes: Vec<RequestId> = messages
+            .iter()
+            .filter(|message| message.method() == Some(AUTHENTICATE_METHOD))
+            .filter_map(|message| message.id().cloned())
+            .collect();
+        let mut responses = self.inner.process_batch_with_context(messages, ctx).await;
+        for response in &mut responses {
+            if response
+                .id
+                .as_ref()
+                .is_some_and(|id| handshakes.contains(id))
+            {
+                response.result = None;
+                response.error = Some(
+                    crate::ErrorBuilder::new(
+                        crate::error_codes::INVALID_REQUEST,
+                        "Connection already authenticated",
+                    )
+                    .build(),
+                );
+            }
+        }
+        responses
+    }
+
+    async fn process_batch_with_context(
+        &self,
+        messages: Vec<Message>,
+        _ctx: &ConnectionContext,
+    ) -> Vec<Response> {
+        self.process_batch(messages).await
+    }
+
+    async fn admit(&self, request: &Request) -> Option<Response> {
+        let Some(ctx) = self.authenticated.get() else {
+            self.refuse(request.method());
+            return Some(crate::rpc_error!(
+                AUTHENTICATION_REQUIRED,
+                "Authentication required",
+                request.id.clone()
+            ));
+        };
+        self.inner.admit_with_context(request, ctx).await
+    }
+
+    async fn admit_with_context(
+        &self,
+        request: &Request,
+        _ctx: &ConnectionContext,
+    ) -> Option<Response> {
+        self.admit(request).await
+    }
+
+    fn static_response(
+        &self,
+        method: &str,
+        id: Option<&serde_json::value::RawValue>,
+    ) -> Option<String> {
+        if self.is_authenticated() {
+            self.inner.static_response(method, id)
+        } else {
+            None
+        }
+    }
+
+    fn get_capabilities(&self) -> ProcessorCapabilities {
+        self.inner.get_capabilities()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{JsonRPCMethod, MethodRegistry, RequestBuilder, register_methods};
+    use serde_json::json;
+
+    struct Ping;
+
+    #[async_trait::async_trait]
+    impl JsonRPCMethod for Ping {
+        fn method_name(&self) -> &'static str {
+            "ping"
+        }
+
+        async fn call(
+            &self,
+            _params: Option<serde_json::Value>,
+            id: Option<RequestId>,
+        ) -> Response {
+            crate::rpc_success!("pong", id)
+        }
+    }
+
+    struct Token(&'static str);
+
+    #[async_trait::async_trait]
+    impl ConnectionAuthenticator for Token {
+        async fn authenticate(
+            &self,
+            params: Option<&serde_json::Value>,
+            ctx: &mut ConnectionContext,
+        ) -> Result<(), String> {
+            match params.and_then(|p| p.get("token")).and_then(|t| t.as_str()) {
+                Some(token) if token == self.0 => {
+                    ctx.insert("user".to_string(), "alice".to_string());
+                    Ok(())
+                }
+                _ => Err("bad token".to_string()),
+            }
+        }
+    }
+
+    fn request(method: &str, params: Option<serde_json::Value>, id: i64) -> Message {
+        let mut request = RequestBuilder::new(method).id(json!(id));
+        if let Some(params) = params {
+            request = request.params(params);
+        }
+        Message::Request(request.build())
+    }
+
+    fn processor(max_attempts: u32) -> HandshakeProcessor {
+        let registry = MethodRegistry::new(register_methods![Ping]);
+        HandshakeProcessor::new(
+            Arc::new(registry),
+            AuthHandshake::new(Token("secret")).max_attempts(max_attempts),
+            ConnectionContext::new(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_requests_blocked_until_authenticated() {
+        let processor = processor(3);
+
+        let blocked = processor
+            .process_message(request("ping", None, 1))
+            .await
+            .unwrap();
+        assert_eq!(blocked.error.unwrap().code, AUTHENTICATION_REQUIRED);
+        assert!(processor.static_response("ping", None).is_none());
+
+        let ok = processor
+            .process_message(request(
+                AUTHENTICATE_METHOD,
+                Some(json!({"token": "secret"})),
+                2,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(ok.result, Some(json!({"authenticated": true})));
+        assert_eq!(
+            processor
+                .authenticated
+                .get()
+                .unwrap()
+                .get::<String>("user")
+                .map(String::as_str),
+            Some("alice")
+        );
+
+        let pong = processor
+            .process_message(request("ping", None, 3))
+            .await
+            .unwrap();
+        assert!(pong.result.is_some());
+
+        let again = processor
+            .process_message(request(
+                AUTHENTICATE_METHOD,
+                Some(json!({"token": "secret"})),
+                4,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            again.error.unwrap().code,
+            crate::error_codes::INVALID_REQUEST
+        );
+    }
+
+    #[tokio::test]
+    async fn test_batches_after_authentication_use_inner_processor() {
+        let registry = MethodRegistry::new(register_methods![Ping]).with_batch_metadata(true);
+        let processor = HandshakeProcessor::new(
+            Arc::new(registry),
+            AuthHandshake::new(Token("secret")),
+            ConnectionContext::new(),
+        );
+        let batch = || {
+            vec![
+                request(AUTHENTICATE_METHOD, Some(json!({"token": "secret"})), 1),
+                request("ping", None, 2),
+            ]
+        };
+
+        let refused = processor.process_batch(batch()).await;
+        assert_eq!(refused.len(), 2);
+        assert!(
+            refused
+                .iter()
+                .all(|response| response.error.as_ref().unwrap().code == AUTHENTICATION_REQUIRED)
+        );
+        assert!(!processor.is_authenticated());
+
+        processor
+            .process_message(request(
+                AUTHENTICATE_METHOD,
+                Some(json!({"token": "secret"})),
+                3,
+            ))
+            .await
+            .unwrap();
+        let responses = processor.process_batch(batch()).await;
+        assert_eq!(responses.len(), 2);
+        assert_eq!(
+            responses[0].error.as_ref().unwrap().code,
+            crate::error_codes::INVALID_REQUEST
+        );
+        assert_eq!(responses[1].result, Some(json!("pong")));
+        assert_eq!(responses[1].ext.as_ref().unwrap().batch.unwrap().index, 1);
+    }
+
+    #[tokio::test]
+    async fn test_attempts_limited() {
+        let processor = processor(2);
+        for id in 0..2 {
+            assert!(!processor.should_close());
+            let failed = processor
+                .process_message(request(
+                    AUTHENTICATE_METHOD,
+                    Some(json!({"token": "wrong"})),
+                    id,
+                ))
+                .await
+                .unwrap();
+            assert_eq!(failed.error.unwrap().code, AUTHENTICATION_REQUIRED);
+        }
+        assert!(processor.should_close());
+        assert!(!processor.is_authenticated());
+    }
+}
@@ -0,0 +1,130 @@
+//! Server hello for persistent connections
+//!
+//! A stream server configured with a [`ServerHello`] writes it as the first
+//! frame of every connection, before reading anything:
+//!
+//! ```json
+//! {"jsonrpc": "2.0", "method": "rpc.hello", "params": {
+//!     "name": "billing", "version": "1.4.0",
+//!     "capabilities": {"supports_batch": true, ...},
+//!     "auth": {"mode": "handshake", "method": "rpc.authenticate"}}}
+//! ```
+//!
+//! Clients learn the server's limits and whether they must authenticate
+//! before sending their first request. The capabilities are the processor's
+//! unless the hello sets its own, and the auth mode follows the server's
+//! [`AuthHandshake`](super::AuthHandshake). Servers without a hello send
+//! nothing unsolicited, keeping the connection pure JSON-RPC for clients
+//! that would trip over the notification.
+
+use crate::{MessageProcessor, NotificationBuilder, ProcessorCapabilities};
+use serde::{Deserialize, Serialize};
+
+/// Method name of the hello notification
+pub const HELLO_METHOD: &str = "rpc.hello";
+
+/// What a server tells a client when it connects
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerHello {
+    pub name: String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<ProcessorCapabilities>,
+    #[serde(default)]
+    pub auth: AuthMode,
+}
+
+impl ServerHello {
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            capabilities: None,
+            auth: AuthMode::None,
+        }
+    }
+
+    /// Advertise `capabilities` instead of the processor's
+    pub fn capabilities(mut self, capabilities: ProcessorCapabilities) -> Self {
+        self.capabilities = Some(capabilities);
+        self
+    }
+}
+
+/// How a client gets to call methods on the connection
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum AuthMode {
+    /// Calls are accepted right away
+    #[default]
+    None,
+    /// `method` must succeed first
+    Handshake { method: String },
+}
+
+/// What a new stream connection starts with: the hello it sends and the
+/// handshake it requires
+#[derive(Clone, Default)]
+pub(crate) struct Opening {
+    pub(crate) handshake: Option<super::handshake::AuthHandshake>,
+    pub(crate) hello: Option<ServerHello>,
+}
+
+impl Opening {
+    /// The encoded hello frame, if the server sends one
+    pub(crate) fn hello_frame(&self, processor: &dyn MessageProcessor) -> Option<String> {
+        let mut hello = self.hello.clone()?;
+        if hello.capabilities.is_none() {
+            hello.capabilities = Some(processor.get_capabilities());
+        }
+        if self.handshake.is_some() {
+            hello.auth = AuthMode::Handshake {
+                method: super::handshake::AUTHENTICATE_METHOD.to_string(),
+            };
+        }
+        let params = serde_json::to_value(hello).ok()?;
+        serde_json::to_string(
+            &NotificationBuilder::new(HELLO_METHOD)
+                .params(params)
+                .build(),
+        )
+        .ok()
+    }
+}
+
+/// The hello in `frame`, if it is one
+#[cfg(feature = "tcp-stream")]
+pub(crate) fn parse(frame: &str) -> Option<ServerHello> {
+    let message = serde_json::from_str::<crate::Message>(frame).ok()?;
+    if message.method() != Some(HELLO_METHOD) || message.id().is_some() {
+        return None;
+    }
+    let params = match message {
+        crate::Message::Request(request) => request.params,
+        crate::Message::Notification(notification) => notification.params,
+        crate::Message::Response(_) => None,
+    };
+    serde_json::from_value(params?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hello_frame() {
+        let registry = crate::MethodRegistry::empty();
+        assert!(Opening::default().hello_frame(&registry).is_none());
+
+        let opening = Opening {
+            handshake: None,
+            hello: Some(ServerHello::new("billing", "1.4.0")),
+        };
+        let frame: serde_json::Value =
+            serde_json::from_str(&opening.hello_frame(&registry).unwrap()).unwrap();
+        assert_eq!(frame["method"], HELLO_METHOD);
+        assert_eq!(frame["params"]["name"], "billing");
+        assert_eq!(frame["params"]["auth"], serde_json::json!({"mode": "none"}));
+        assert_eq!(frame["params"]["capabilities"]["supports_batch"], true);
+    }
+}
@@ -0,0 +1,186 @@
+//! Connection recycling
+//!
+//! Long-lived connections pin clients to one server instance and keep old
+//! configuration alive. With [`SecurityConfig::with_max_connection_lifetime`]
+//! or [`SecurityConfig::with_max_requests_per_connection`] set, a connection is closed
+//! once its budget is used up. The check runs between requests, so the
+//! response to the last request is always written first, followed by a
+//! [`CONNECTION_CLOSING_METHOD`] notification telling the client to
+//! reconnect.
+//!
+//! However a streaming connection ends, including a client half-closing its
+//! write side, responses still queued or in flight keep being written for
+//! up to [`SecurityConfig::with_drain_timeout`] before the connection is closed.
+
+use super::security::SecurityConfig;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::time::Instant;
+
+/// Method of the notification sent before a recycled connection is closed
+pub const CONNECTION_CLOSING_METHOD: &str = "rpc.connection_closing";
+
+/// Why a connection was recycled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecycleReason {
+    MaxLifetime,
+    MaxRequests,
+}
+
+impl RecycleReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecycleReason::MaxLifetime => "max_lifetime",
+            RecycleReason::MaxRequests => "max_requests",
+        }
+    }
+}
+
+static RECYCLED_LIFETIME: AtomicU64 = AtomicU64::new(0);
+static RECYCLED_REQUESTS: AtomicU64 = AtomicU64::new(0);
+
+/// Connections recycled for `reason` since process start
+pub fn recycled_total(reason: RecycleReason) -> u64 {
+    match reason {
+        RecycleReason::MaxLifetime => RECYCLED_LIFETIME.load(Ordering::Relaxed),
+        RecycleReason::MaxRequests => RECYCLED_REQUESTS.load(Ordering::Relaxed),
+    }
+}
+
+/// The `rpc.connection_closing` notification for `reason`
+pub fn closing_notification(reason: RecycleReason) -> crate::Notification {
+    crate::Notification::new(CONNECTION_CLOSING_METHOD)
+        .with_params(serde_json::json!({ "reason": reason.as_str() }))
+}
+
+/// Remaining lifetime and request budget of one connection
+#[derive(Debug)]
+pub struct ConnectionBudget {
+    deadline: Option<Instant>,
+    remaining_requests: Option<usize>,
+}
+
+impl ConnectionBudget {
+    pub fn new(config: &SecurityConfig) -> Self {
+        Self {
+            deadline: (!config.max_connection_lifetime.is_zero())
+                .then(|| Instant::now() + config.max_connection_lifetime),
+            remaining_requests: (config.max_requests_per_connection > 0)
+                .then_some(config.max_requests_per_connection),
+        }
+    }
+
+    /// Count a handled request
+    pub fn on_request(&mut self) {
+        if let Some(remaining) = &mut self.remaining_requests {
+            *remaining = remaining.saturating_sub(1);
+        }
+    }
+
+    /// Reason to close the connection now, if the budget is used up
+    pub fn exhausted(&self) -> Option<RecycleReason> {
+        if self.remaining_requests == Some(0) {
+            Some(RecycleReason::MaxRequests)
+        } else if self.deadline.is_some_and(|d| Instant::now() >= d) {
+            Some(RecycleReason::MaxLifetime)
+        } else {
+            None
+        }
+    }
+
+    /// Run `fut` until it completes or the lifetime ends; `None` on expiry
+    pub async fn guard<F: Future>(&self, fut: F) -> Option<F::Output> {
+        match self.deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline, fut).await.ok(),
+            None => Some(fut.await),
+        }
+    }
+
+    /// Log and count a recycled connection, returning the notification line
+    pub fn recycle(
+        &self,
+        reason: RecycleReason,
+        remote_addr: Option<std::net::SocketAddr>,
+    ) -> Option<String> {
+        match reason {
+            RecycleReason::MaxLifetime => RECYCLED_LIFETIME.fetch_add(1, Ordering::Relaxed),
+            RecycleReason::MaxRequests => RECYCLED_REQUESTS.fetch_add(1, Ordering::Relaxed),
+        };
+        tracing::info!(
+            reason = reason.as_str(),
+            remote_addr = ?remote_addr,
+            "recycling connection"
+        );
+        serde_json::to_string(&closing_notification(reason)).ok()
+    }
+}
+
+/// Wait for a connection's writer to finish the responses still owed,
+/// aborting it after [`SecurityConfig::with_drain_timeout`]
+#[cfg(any(feature = "tcp-stream", feature = "tcp-stream-tls"))]
+pub(crate) async fn drain(
+    writer: tokio::task::JoinHandle<()>,
+    config: &SecurityConfig,
+    remote_addr: Option<std::net::SocketAddr>,
+) {
+    if config.drain_timeout.is_zero() {
+        let _ = writer.await;
+        return;
+    }
+    let abort = writer.abort_handle();
+    if tokio::time::timeout(config.drain_timeout, writer)
+        .await
+        .is_err()
+    {
+        tracing::debug!(
+            remote_addr = ?remote_addr,
+            timeout = ?config.drain_timeout,
+            "drain timed out, dropping unwritten responses"
+        );
+        abort.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_request_budget() {
+        let config = SecurityConfig::default().with_max_requests_per_connection(2);
+        let mut budget = ConnectionBudget::new(&config);
+        assert_eq!(budget.exhausted(), None);
+        budget.on_request();
+        budget.on_request();
+        assert_eq!(budget.exhausted(), Some(RecycleReason::MaxRequests));
+    }
+
+    #[tokio::test]
+    async fn test_lifetime_budget() {
+        let config =
+            SecurityConfig::default().with_max_connection_lifetime(Duration::from_millis(20));
+        let budget = ConnectionBudget::new(&config);
+        assert_eq!(budget.exhausted(), None);
+
+        let pending = budget.guard(std::future::pending::<()>()).await;
+        assert!(pending.is_none());
+        assert_eq!(budget.exhausted(), Some(RecycleReason::MaxLifetime));
+    }
+
+    #[test]
+    fn test_unlimited_by_default() {
+        let mut budget = ConnectionBudget::new(&SecurityConfig::default());
+        for _ in 0..1000 {
+            budget.on_request();
+        }
+        assert_eq!(budget.exhausted(), None);
+    }
+
+    #[test]
+    fn test_closing_notification() {
+        let json = serde_json::to_value(closing_notification(RecycleReason::MaxLifetime)).unwrap();
+        assert_eq!(json["method"], CONNECTION_CLOSING_METHOD);
+        assert_eq!(json["params"]["reason"], "max_lifetime");
+    }
+}
@@ -0,0 +1,513 @@
+//! Per-listener origin tagging and method restrictions
+//!
+//! The same processor is often exposed on several listeners, e.g. an
+//! internal TCP port and a public HTTP endpoint. A [`MethodFilter`] decides
+//! which methods a listener may dispatch, and [`ListenerProcessor`] applies
+//! it before the inner processor runs while tagging the
+//! [`ConnectionContext`](crate::auth::ConnectionContext) with the listener
+//! name.
+//!
+//! Transport builders expose this through `name(..)` and `method_filter(..)`.
+//!
+//! A running server also identifies itself with a [`ListenerId`]: its name,
+//! protocol and bound address. Every connection's context carries it
+//! ([`ConnectionContext::listener`]), transport counters and their
+//! Prometheus series are labelled with the name, and connection tracing
+//! spans and audit events name the listener, so traffic of a process
+//! serving several listeners can be told apart.
+
+use crate::auth::ConnectionContext;
+use crate::{
+    CapabilityLimits, Message, MessageProcessor, ProcessorCapabilities, Request, Response,
+};
+use std::borrow::Cow;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+/// Which listener of a process accepted a connection
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ListenerId {
+    /// Name given with the server builder's `name(..)`
+    pub name: Option<String>,
+    /// Transport name, as in [`transport_stats`](super::transport_stats)
+    pub protocol: &'static str,
+    /// Bound address
+    pub addr: Option<SocketAddr>,
+}
+
+impl ListenerId {
+    pub fn new(protocol: &'static str, addr: Option<SocketAddr>) -> Self {
+        Self {
+            name: None,
+            protocol,
+            addr,
+        }
+    }
+
+    pub fn name(mut self, name: Option<String>) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// Value of the `listener` metrics label: the name, empty if unnamed
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or("")
+    }
+}
+
+/// The name if set, otherwise `protocol://addr`
+impl fmt::Display for ListenerId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.name, self.addr) {
+            (Some(name), _) => f.write_str(name),
+            (None, Some(addr)) => write!(f, "{}://{addr}", self.protocol),
+            (None, None) => f.write_str(self.protocol),
+        }
+    }
+}
+
+/// Allow/deny rules for method names
+///
+/// Patterns match a method exactly, or by prefix when they end in `*`
+/// (`"admin.*"`). Deny rules win over allow rules; an empty allow list
+/// permits everything not denied.
+#[derive(Debug, Clone, Default)]
+pub struct MethodFilter {
+    allow: Vec<String>,
+    deny: Vec<String>,
+}
+
+impl MethodFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only permit methods matching `pattern` (may be repeated)
+    pub fn allow(mut self, pattern: impl Into<String>) -> Self {
+        self.allow.push(pattern.into());
+        self
+    }
+
+    /// Reject methods matching `pattern` (may be repeated)
+    pub fn deny(mut self, pattern: impl Into<String>) -> Self {
+        self.deny.push(pattern.into());
+        self
+    }
+
+    /// True if no rules are configured
+    pub fn is_unrestricted(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Check whether `method` may be dispatched
+    pub fn is_permitted(&self, method: &str) -> bool {
+        if self.deny.iter().any(|p| pattern_matches(p, method)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|p| pattern_matches(p, method))
+    }
+}
+
+pub(crate) fn pattern_matches(pattern: &str, method: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => method.starts_with(prefix),
+        None => pattern == method,
+    }
+}
+
+/// Processor wrapper applying a listener's origin and method filter
+pub struct ListenerProcessor {
+    inner: Arc<dyn MessageProcessor + Send + Sync>,
+    origin: Option<String>,
+    filter: MethodFilter,
+    limits: CapabilityLimits,
+}
+
+impl ListenerProcessor {
+    pub fn new(
+        inner: Arc<dyn MessageProcessor + Send + Sync>,
+        origin: Option<String>,
+        filter: MethodFilter,
+    ) -> Self {
+        Self {
+            inner,
+            origin,
+            filter,
+            limits: CapabilityLimits::new(),
+        }
+    }
+
+    /// Narrow the capabilities this listener reports, e.g. smaller batches
+    /// on a public listener than on an internal one
+    pub fn capability_limits(mut self, limits: CapabilityLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn origin(&self) -> Option<&str> {
+        self.origin.as_deref()
+    }
+
+    fn refuse(&self, method: &str, ctx: &ConnectionContext) {
+        crate::rejection::record(
+            crate::rejection::Rejection::new(crate::rejection::RejectionReason::MethodNotPermitted)
+                .method(method)
+                .remote_addr(ctx.remote_addr)
+                .origin(self.origin.clone())
+                .principal(ctx.principal_id()),
+        );
+    }
+
+    /// The context passed to the inner processor: tagged with the listener
+    /// name and carrying this listener's capability limits
+    fn forwarded<'a>(&self, ctx: &'a ConnectionContext) -> Cow<'a, ConnectionContext> {
+        let tag = self.origin.as_ref().filter(|_| ctx.origin.is_none());
+        if tag.is_none() && self.limits.is_unrestricted() {
+            return Cow::Borrowed(ctx);
+        }
+        let mut ctx = ctx.clone();
+        if let Some(origin) = tag {
+            ctx = ctx.with_origin(origin.clone());
+        }
+        if !self.limits.is_unrestricted() {
+            ctx = ctx.with_capability_limits(self.limits.clone());
+        }
+        Cow::Owned(ctx)
+    }
+
+    /// Wrap `processor` only when a name or filter is configured
+    pub fn wrap(
+        processor: Arc<dyn MessageProcessor + Send + Sync>,
+        origin: Option<String>,
+        filter: MethodFilter,
+    ) -> Arc<dyn MessageProcessor + Send + Sync> {
+        if origin.is_none() && filter.is_unrestricted() {
+            processor
+        } else {
+            Arc::new(Self::new(processor, origin, filter))
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl MessageProcessor for ListenerProcessor {
+    async fn process_message(&self, message: Message) -> Option<Response> {
+        self.process_message_with_context(message, &ConnectionContext::default())
+            .await
+    }
+
+    async fn process_message_with_context(
+        &self,
+        message: Message,
+        ctx: &ConnectionContext,
+    ) -> Option<Response> {
+        if let Some(method) = message.method()
+            && !self.filter.is_permitted(method)
+        {
+            self.refuse(method, ctx);
+            return match message {
+                Message::Request(request) => Some(not_found(request.id, request.correlation_id)),
+                _ => None,
+            };
+        }
+
+        self.inner
+            .process_message_with_context(message, &self.forwarded(ctx))
+            .await
+    }
+
+    async fn process_batch(&self, messages: Vec<Message>) -> Vec<Response> {
+        self.process_batch_with_context(messages, &ConnectionContext::default())
+            .await
+    }
+
+    async fn process_batch_with_context(
+        &self,
+        messages: Vec<Message>,
+        ctx: &ConnectionContext,
+    ) -> Vec<Response> {
+        let ctx = &*self.forwarded(ctx);
+        crate::batch_policy::forward_admitted(
+            messages,
+            |message| match message.method() {
+                Some(method) if !self.filter.is_permitted(method) => {
+                    self.refuse(method, ctx);
+                    Some(match message {
+                        Message::Request(request) => Some(not_found(
+                            request.id.clone(),
+                            request.correlation_id.clone(),
+                        )),
+                        _ => None,
+                    })
+                }
+                _ => None,
+            },
+            |admitted| self.inner.process_batch_with_context(admitted, ctx),
+        )
+        .await
+    }
+
+    async fn admit_with_context(
+        &self,
+        request: &Request,
+        ctx: &ConnectionContext,
+    ) -> Option<Response> {
+        if !self.filter.is_permitted(&request.method) {
+            self.refuse(&request.method, ctx);
+            return Some(not_found(
+                request.id.clone(),
+                request.correlation_id.clone(),
+            ));
+        }
+        self.inner
+            .admit_with_context(request, &self.forwarded(ctx))
+            .await
+    }
+
+    fn static_response(
+        &self,
+        method: &str,
+        id: Option<&serde_json::value::RawValue>,
+    ) -> Option<String> {
+        if !self.filter.is_permitted(method) {
+            return None;
+        }
+        self.inner.static_response(method, id)
+    }
+
+    fn supports_batching(&self) -> bool {
+        self.inner.supports_batching()
+    }
+
+    fn get_capabilities(&self) -> ProcessorCapabilities {
+        self.inner.get_capabilities().tighten(&self.limits)
+    }
+}
+
+/// Filtered methods look like methods that do not exist
+fn not_found(id: Option<crate::RequestId>, correlation_id: Option<String>) -> Response {
+    crate::ResponseBuilder::new()
+        .error(
+            crate::ErrorBuilder::new(crate::error_codes::METHOD_NOT_FOUND, "Method not found")
+                .build(),
+        )
+        .id(id)
+        .correlation_id(correlation_id)
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ResponseBuilder;
+
+    struct OriginEcho;
+
+    #[async_trait::async_trait]
+    impl MessageProcessor for OriginEcho {
+        async fn process_message(&self, message: Message) -> Option<Response> {
+            self.process_message_with_context(message, &ConnectionContext::default())
+                .await
+        }
+
+        async fn process_message_with_context(
+            &self,
+            message: Message,
+            ctx: &ConnectionContext,
+        ) -> Option<Response> {
+            match message {
+                Message::Request(req) => Some(
+                    ResponseBuilder::new()
+                        .success(serde_json::json!(ctx.origin))
+                        .id(req.id)
+                        .build(),
+                ),
+                _ => None,
+            }
+        }
+    }
+
+    fn request(method: &str) -> Message {
+        Message::Request(Request::new(method).with_id(serde_json::json!(1)))
+    }
+
+    #[test]
+    fn test_method_filter_rules() {
+        let filter = MethodFilter::new().deny("admin.*");
+        assert!(filter.is_permitted("ping"));
+        assert!(!filter.is_permitted("admin.reload"));
+
+        let filter = MethodFilter::new().allow("public.*").allow("ping");
+        assert!(filter.is_permitted("ping"));
+        assert!(filter.is_permitted("public.list"));
+        assert!(!filter.is_permitted("internal.sync"));
+
+        let filter = MethodFilter::new().allow("*").deny("debug");
+        assert!(!filter.is_permitted("debug"));
+        assert!(MethodFilter::new().is_unrestricted());
+    }
+
+    #[tokio::test]
+    async fn test_listener_processor_tags_origin() {
+        let processor = ListenerProcessor::new(
+            Arc::new(OriginEcho),
+            Some("internal".to_string()),
+            MethodFilter::new(),
+        );
+        let response = processor.process_message(request("ping")).await.unwrap();
+        assert_eq!(response.result, Some(serde_json::json!("internal")));
+    }
+
+    #[tokio::test]
+    async fn test_listener_processor_denies_method() {
+        let processor = ListenerProcessor::new(
+            Arc::new(OriginEcho),
+            Some("public".to_string()),
+            MethodFilter::new().deny("admin.*"),
+        );
+        let response = processor
+            .process_message(request("admin.shutdown"))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.error.unwrap().code,
+            crate::error_codes::METHOD_NOT_FOUND
+        );
+        assert_eq!(response.id, Some(serde_json::json!(1)));
+    }
+
+    #[tokio::test]
+    async fn test_batches_keep_inner_policy_and_metadata() {
+        use crate::batch_policy::BatchPolicy;
+        use crate::{MethodRegistry, RequestBuilder};
+
+        let processor = ListenerProcessor::new(
+            Arc::new(MethodRegistry::empty().with_batch_metadata(true)),
+            Some("public".to_string()),
+            MethodFilter::new().deny("admin.*"),
+        );
+        let entry = |method: &str, id: u64| {
+            Message::Request(
+                RequestBuilder::new(method)
+                    .id(serde_json::json!(id))
+                    .build(),
+            )
+        };
+        let codes = |responses: &[Response]| -> Vec<_> {
+            responses
+                .iter()
+                .map(|response| (response.id.clone(), response.error.as_ref().unwrap().code))
+                .collect()
+        };
+
+        let first = RequestBuilder::new("missing")
+            .id(serde_json::json!(1))
+            .batch_policy(BatchPolicy::AbortOnError)
+            .build();
+        let responses = processor
+            .process_batch(vec![Message::Request(first), entry("missing", 2)])
+            .await;
+        assert_eq!(
+            codes(&responses),
+            [
+                (
+                    Some(serde_json::json!(1)),
+                    crate::error_codes::METHOD_NOT_FOUND
+                ),
+                (
+                    Some(serde_json::json!(2)),
+                    crate::error_codes::DEPENDENT_FAILURE
+                ),
+            ]
+        );
+        assert_eq!(responses[0].ext.as_ref().unwrap().batch.unwrap().index, 0);
+
+        // refused entries keep their place
+        let responses = processor
+            .process_batch(vec![
+                entry("missing", 1),
+                entry("admin.reload", 2),
+                entry("missing", 3),
+            ])
+            .await;
+        let ids: Vec<_> = responses.iter().map(|r| r.id.clone().unwrap()).collect();
+        assert_eq!(ids, [1, 2, 3].map(|id| serde_json::json!(id)));
+        assert_eq!(responses[1].ext, None);
+        assert_eq!(responses[2].ext.as_ref().unwrap().batch.unwrap().index, 2);
+
+        // and stop a batch that aborts on errors
+        let first = RequestBuilder::new("admin.reload")
+            .id(serde_json::json!(1))
+            .batch_policy(BatchPolicy::AbortOnError)
+            .build();
+        let responses = processor
+            .process_batch(vec![Message::Request(first), entry("missing", 2)])
+            .await;
+        assert_eq!(
+            codes(&responses),
+            [
+                (
+                    Some(serde_json::json!(1)),
+                    crate::error_codes::METHOD_NOT_FOUND
+                ),
+                (
+                    Some(serde_json::json!(2)),
+                    crate::error_codes::DEPENDENT_FAILURE
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_batch_responses_placed_by_position() {
+        use crate::batch_policy::BatchPolicy;
+        use crate::{MethodRegistry, NotificationBuilder, RequestBuilder};
+
+        let processor = ListenerProcessor::new(
+            Arc::new(MethodRegistry::empty().with_batch_metadata(true)),
+            Some("public".to_string()),
+            MethodFilter::new().deny("admin.*"),
+        );
+        let entry = |method: &str| {
+            Message::Request(RequestBuilder::new(method).id(serde_json::json!(1)).build())
+        };
+
+        // every entry shares an id
+        let responses = processor
+            .process_batch(vec![
+                entry("missing"),
+                entry("admin.reload"),
+                entry("missing"),
+            ])
+            .await;
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0].ext.as_ref().unwrap().batch.unwrap().index, 0);
+        assert_eq!(responses[1].ext, None);
+        assert_eq!(responses[2].ext.as_ref().unwrap().batch.unwrap().index, 2);
+
+        // a dropped notification shifts what the inner processor sees
+        let first = RequestBuilder::new("missing")
+            .id(serde_json::json!(1))
+            .batch_policy(BatchPolicy::AbortOnError)
+            .build();
+        let responses = processor
+            .process_batch(vec![
+                Message::Notification(NotificationBuilder::new("admin.reload").build()),
+                Message::Request(first),
+                entry("missing"),
+            ])
+            .await;
+        assert_eq!(responses[0].ext.as_ref().unwrap().batch.unwrap().index, 1);
+        let aborted = responses[1].error.as_ref().unwrap();
+        assert_eq!(aborted.code, crate::error_codes::DEPENDENT_FAILURE);
+        assert_eq!(aborted.data, Some(serde_json::json!({"failed_index": 1})));
+    }
+
+    #[test]
+    fn test_wrap_is_noop_without_config() {
+        let inner: Arc<dyn MessageProcessor + Send + Sync> = Arc::new(OriginEcho);
+        let wrapped = ListenerProcessor::wrap(Arc::clone(&inner), None, MethodFilter::new());
+        assert!(Arc::ptr_eq(&inner, &wrapped));
+    }
+}
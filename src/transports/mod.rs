@@ -4,25 +4,153 @@
 //! - **TCP**: Simple one-request-per-connection transport
 //! - **TCP Stream**: Persistent connections with multiple requests
 //! - **TCP TLS**: Encrypted streaming transport with TLS/rustls
+//! - **WebSocket**: Persistent connections that can also deliver stream events
 //! - **Axum**: HTTP transport via Axum web framework
 //! - **Tower**: Middleware integration for composable services
 
+pub mod codec_stats;
+pub mod connection;
+pub mod listener;
 pub mod security;
+pub mod transport_stats;
+pub mod validation;
+
+#[cfg(any(
+    feature = "tcp",
+    feature = "tcp-stream",
+    feature = "tcp-stream-tls",
+    feature = "websocket"
+))]
+pub mod supervisor;
+
+#[cfg(any(
+    feature = "tcp",
+    feature = "tcp-stream",
+    feature = "tcp-stream-tls",
+    feature = "websocket"
+))]
+pub mod lifetime;
+
+#[cfg(any(
+    feature = "tcp",
+    feature = "tcp-stream",
+    feature = "tcp-stream-tls",
+    feature = "websocket",
+    feature = "axum"
+))]
+pub(crate) mod batch;
+
+#[cfg(any(feature = "tcp", feature = "tcp-stream", feature = "tcp-stream-tls"))]
+pub mod accept;
+
+#[cfg(any(
+    feature = "tcp",
+    feature = "tcp-stream",
+    feature = "tcp-stream-tls",
+    feature = "websocket"
+))]
+pub mod socket;
+
+#[cfg(any(feature = "tcp", feature = "tcp-stream", feature = "tcp-stream-tls"))]
+pub mod handoff;
+
+#[cfg(any(feature = "tcp-stream", feature = "tcp-stream-tls"))]
+pub mod framing;
+
+#[cfg(any(
+    feature = "tcp-stream",
+    feature = "tcp-stream-tls",
+    feature = "websocket"
+))]
+pub mod tap;
+
+#[cfg(any(feature = "tcp-stream", feature = "tcp-stream-tls"))]
+pub mod handshake;
+
+#[cfg(any(feature = "tcp-stream", feature = "tcp-stream-tls"))]
+pub mod hello;
+
+#[cfg(any(feature = "tcp-stream", feature = "tcp-stream-tls"))]
+pub mod pipeline;
+
+#[cfg(all(
+    feature = "streaming",
+    any(feature = "tcp-stream", feature = "websocket")
+))]
+pub(crate) mod stream_router;
+
+#[cfg(all(
+    feature = "streaming",
+    any(feature = "tcp-stream", feature = "websocket")
+))]
+pub(crate) mod subscription;
 
 #[cfg(feature = "tcp")]
 pub mod tcp;
 
+#[cfg(feature = "tcp-stream")]
+pub mod reconnect;
+
 #[cfg(feature = "tcp-stream")]
 pub mod tcp_stream;
 
 #[cfg(feature = "tcp-stream-tls")]
 pub mod tcp_tls;
 
+#[cfg(feature = "websocket")]
+pub mod websocket;
+
 #[cfg(feature = "axum")]
 pub mod axum;
 
 // Re-export security config for all transports
-pub use security::SecurityConfig;
+pub use connection::ConnectionProcessor;
+pub use listener::{ListenerProcessor, MethodFilter};
+pub use security::{JsonLimitError, SecurityConfig};
+pub use validation::{
+    CHECK_CONFIG_FLAG, ConfigProblem, ConfigReport, ProblemSeverity, check_config_requested,
+};
+
+#[cfg(any(
+    feature = "tcp",
+    feature = "tcp-stream",
+    feature = "tcp-stream-tls",
+    feature = "websocket"
+))]
+pub use supervisor::{AcceptSupervisor, SupervisionPolicy};
+
+#[cfg(any(
+    feature = "tcp",
+    feature = "tcp-stream",
+    feature = "tcp-stream-tls",
+    feature = "websocket"
+))]
+pub use socket::{Keepalive, SocketOptions};
+
+#[cfg(any(feature = "tcp", feature = "tcp-stream", feature = "tcp-stream-tls"))]
+pub use accept::AcceptRateLimit;
+
+#[cfg(any(feature = "tcp", feature = "tcp-stream", feature = "tcp-stream-tls"))]
+pub use handoff::ListenerHandoff;
+
+#[cfg(any(feature = "tcp-stream", feature = "tcp-stream-tls"))]
+pub use framing::Framing;
+
+#[cfg(any(
+    feature = "tcp-stream",
+    feature = "tcp-stream-tls",
+    feature = "websocket"
+))]
+pub use tap::{TapDirection, TapFrame, TapSink, WireTap};
+
+#[cfg(any(feature = "tcp-stream", feature = "tcp-stream-tls"))]
+pub use handshake::{AuthHandshake, ConnectionAuthenticator};
+
+#[cfg(any(feature = "tcp-stream", feature = "tcp-stream-tls"))]
+pub use hello::{AuthMode, ServerHello};
+
+#[cfg(any(feature = "tcp-stream", feature = "tcp-stream-tls"))]
+pub use pipeline::{Pipelining, ResponseOrdering};
 
 // Re-export TCP transport
 #[cfg(feature = "tcp")]
@@ -34,10 +162,26 @@ pub use tcp_stream::{
     TcpStreamClient, TcpStreamClientBuilder, TcpStreamServer, TcpStreamServerBuilder,
 };
 
+#[cfg(feature = "tcp-stream")]
+pub use reconnect::{ConnectionState, ReconnectPolicy};
+
+#[cfg(all(
+    feature = "streaming",
+    any(feature = "tcp-stream", feature = "websocket")
+))]
+pub use subscription::Subscription;
+
 // Re-export TLS transport
 #[cfg(feature = "tcp-stream-tls")]
 pub use tcp_tls::{TcpStreamTlsClient, TcpStreamTlsServer, TcpStreamTlsServerBuilder, TlsConfig};
 
+// Re-export WebSocket transport
+#[cfg(feature = "websocket")]
+pub use websocket::{
+    WebSocketClient, WebSocketClientBuilder, WebSocketError, WebSocketServer,
+    WebSocketServerBuilder,
+};
+
 // Re-export Axum transport
 #[cfg(feature = "axum")]
 pub use axum::*;
@@ -0,0 +1,238 @@
+//! Concurrent request processing on a single streaming connection.
+//!
+//! By default a connection handles one request at a time, so responses are
+//! written in request order. [`Pipelining`] lets several requests from the
+//! same connection run at once; with [`ResponseOrdering::Ordered`] their
+//! responses are still written in arrival order, a slow request holding back
+//! those behind it, while [`ResponseOrdering::Unordered`] writes each
+//! response as soon as it is ready and clients match them up by `id`.
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "tcp-stream")]
+//! # fn example(registry: ash_rpc::MethodRegistry) {
+//! use ash_rpc::transports::{Pipelining, TcpStreamServer};
+//!
+//! let server = TcpStreamServer::builder("127.0.0.1:8080")
+//!     .processor(registry)
+//!     .pipelining(Pipelining::new(16))
+//!     .build();
+//! # }
+//! ```
+//!
+//! While an [`authentication`](super::handshake) handshake is pending,
+//! requests are processed one at a time so nothing overtakes
+//! `rpc.authenticate`.
+
+use crate::{Message, MessageProcessor, Response};
+use std::sync::Arc;
+use tokio::sync::{Semaphore, mpsc, oneshot};
+
+/// Order in which a connection's responses are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResponseOrdering {
+    /// Same order as the requests arrived
+    #[default]
+    Ordered,
+    /// As soon as each response is ready
+    Unordered,
+}
+
+/// How many requests of one connection may be processed concurrently
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pipelining {
+    max_in_flight: usize,
+    ordering: ResponseOrdering,
+}
+
+impl Default for Pipelining {
+    /// One request at a time
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl Pipelining {
+    /// Allow up to `max_in_flight` requests at once, responses in order
+    pub fn new(max_in_flight: usize) -> Self {
+        Self {
+            max_in_flight: max_in_flight.max(1),
+            ordering: ResponseOrdering::Ordered,
+        }
+    }
+
+    pub fn ordering(mut self, ordering: ResponseOrdering) -> Self {
+        self.ordering = ordering;
+        self
+    }
+
+    /// Shorthand for `ordering(ResponseOrdering::Unordered)`
+    pub fn unordered(self) -> Self {
+        self.ordering(ResponseOrdering::Unordered)
+    }
+
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight
+    }
+
+    pub fn response_ordering(&self) -> ResponseOrdering {
+        self.ordering
+    }
+}
+
+/// The connection's writer has gone away
+pub(crate) struct Closed;
+
+type Slot = oneshot::Receiver<Option<String>>;
+
+/// Per-connection dispatcher sitting between the reader loop and the writer
+pub(crate) struct ResponsePipeline {
+    out: mpsc::Sender<String>,
+    permits: Arc<Semaphore>,
+    concurrent: bool,
+    /// Responses in arrival order, present in ordered concurrent mode
+    slots: Option<mpsc::Sender<Slot>>,
+}
+
+impl ResponsePipeline {
+    pub(crate) fn new(out: mpsc::Sender<String>, pipelining: Pipelining) -> Self {
+        let concurrent = pipelining.max_in_flight > 1;
+        let slots = (concurrent && pipelining.ordering == ResponseOrdering::Ordered).then(|| {
+            let (slots, mut pending) = mpsc::channel::<Slot>(pipelining.max_in_flight);
+            let out = out.clone();
+            tokio::spawn(async move {
+                while let Some(slot) = pending.recv().await {
+                    if let Ok(Some(json)) = slot.await
+                        && out.send(json).await.is_err()
+                    {
+                        break;
+                    }
+                }
+            });
+            slots
+        });
+        Self {
+            out,
+            permits: Arc::new(Semaphore::new(pipelining.max_in_flight)),
+            concurrent,
+            slots,
+        }
+    }
+
+    /// Queue an already rendered response behind any earlier ones
+    pub(crate) async fn send(&self, json: String) -> Result<(), Closed> {
+        match &self.slots {
+            Some(slots) => {
+                let (ready, slot) = oneshot::channel();
+                let _ = ready.send(Some(json));
+                slots.send(slot).await.map_err(|_| Closed)
+            }
+            None => self.out.send(json).await.map_err(|_| Closed),
+        }
+    }
+
+    /// Process `message`, in the background unless `inline` or pipelining is off
+    ///
+    /// Waits for a free slot when `max_in_flight` requests are running, which
+    /// stops the connection from being read any further.
+    pub(crate) async fn process(
+        &self,
+        processor: &Arc<dyn MessageProcessor + Send + Sync>,
+        message: Message,
+        inline: bool,
+    ) -> Result<(), Closed> {
+        if !self.concurrent || inline {
+            return match render(processor.process_message(message).await) {
+                Some(json) => self.send(json).await,
+                None => Ok(()),
+            };
+        }
+
+        let permit = Arc::clone(&self.permits)
+            .acquire_owned()
+            .await
+            .map_err(|_| Closed)?;
+        let processor = Arc::clone(processor);
+        match &self.slots {
+            Some(slots) => {
+                let (ready, slot) = oneshot::channel();
+                slots.send(slot).await.map_err(|_| Closed)?;
+                tokio::spawn(async move {
+                    let _ = ready.send(render(processor.process_message(message).await));
+                    drop(permit);
+                });
+            }
+            None => {
+                let out = self.out.clone();
+                tokio::spawn(async move {
+                    if let Some(json) = render(processor.process_message(message).await) {
+                        let _ = out.send(json).await;
+                    }
+                    drop(permit);
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn render(response: Option<Response>) -> Option<String> {
+    response.and_then(|response| serde_json::to_string(&response).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Responds after `params` milliseconds
+    struct Sleepy;
+
+    #[async_trait::async_trait]
+    impl MessageProcessor for Sleepy {
+        async fn process_message(&self, message: Message) -> Option<Response> {
+            let request = message.into_request()?;
+            let ms = request.params().and_then(|p| p.as_u64()).unwrap_or(0);
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+            Some(Response::success(serde_json::json!(ms), request.id))
+        }
+    }
+
+    async fn run(pipelining: Pipelining, delays: &[u64]) -> Vec<u64> {
+        let (out, mut written) = mpsc::channel(16);
+        let pipeline = ResponsePipeline::new(out, pipelining);
+        let processor: Arc<dyn MessageProcessor + Send + Sync> = Arc::new(Sleepy);
+        for (id, ms) in delays.iter().enumerate() {
+            let request = crate::Request::new("sleep")
+                .with_params(serde_json::json!(ms))
+                .with_id(serde_json::json!(id));
+            let _ = pipeline
+                .process(&processor, Message::Request(request), false)
+                .await;
+        }
+        drop(pipeline);
+
+        let mut ids = Vec::new();
+        while let Some(json) = written.recv().await {
+            let response: Response = serde_json::from_str(&json).unwrap();
+            ids.push(response.id.and_then(|id| id.as_u64()).unwrap());
+        }
+        ids
+    }
+
+    #[tokio::test]
+    async fn test_response_ordering() {
+        let started = std::time::Instant::now();
+        assert_eq!(run(Pipelining::new(4), &[120, 10, 60]).await, vec![0, 1, 2]);
+        // the three requests overlapped
+        assert!(started.elapsed() < Duration::from_millis(180));
+
+        assert_eq!(
+            run(Pipelining::new(4).unordered(), &[120, 10, 60]).await,
+            vec![1, 2, 0]
+        );
+        assert_eq!(
+            run(Pipelining::default().unordered(), &[60, 10]).await,
+            vec![0, 1]
+        );
+    }
+}
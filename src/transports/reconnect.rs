@@ -0,0 +1,105 @@
+//! Client reconnection
+//!
+//! A [`TcpStreamClient`](super::TcpStreamClient) built with a
+//! [`ReconnectPolicy`] reconnects when its connection goes away, waiting an
+//! exponentially growing delay between attempts. Calls waiting for a
+//! response when the connection is lost fail; later calls go out on the new
+//! connection. The client's [`ConnectionState`] is published on a watch
+//! channel.
+
+use std::time::Duration;
+
+/// How a client reconnects after losing its connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    /// Delay before the first attempt
+    pub initial_backoff: Duration,
+    /// Upper bound for the exponential backoff
+    pub max_backoff: Duration,
+    /// Attempts before the client gives up (0 = never)
+    pub max_attempts: u32,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(30),
+            max_attempts: 10,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts;
+        self
+    }
+
+    /// Retry forever
+    pub fn unlimited(self) -> Self {
+        self.max_attempts(0)
+    }
+
+    /// Delay before attempt number `attempt`, counting from 1
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let doublings = attempt.saturating_sub(1).min(31);
+        self.initial_backoff
+            .saturating_mul(1 << doublings)
+            .min(self.max_backoff)
+    }
+
+    pub(crate) fn gives_up_after(&self, attempt: u32) -> bool {
+        self.max_attempts != 0 && attempt >= self.max_attempts
+    }
+}
+
+/// State of a client's connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connected,
+    /// Lost, waiting for or making reconnection attempt `attempt`
+    Reconnecting {
+        attempt: u32,
+    },
+    /// Lost for good: closed without a reconnect policy, or out of attempts
+    Disconnected,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let policy = ReconnectPolicy::new()
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_secs(1))
+            .max_attempts(5);
+        let delays: Vec<u64> = (1..=6)
+            .map(|attempt| policy.backoff(attempt).as_millis() as u64)
+            .collect();
+        assert_eq!(delays, [100, 200, 400, 800, 1000, 1000]);
+        assert!(!policy.gives_up_after(4));
+        assert!(policy.gives_up_after(5));
+        assert!(!policy.unlimited().gives_up_after(u32::MAX));
+        assert_eq!(
+            ReconnectPolicy::new().backoff(u32::MAX),
+            Duration::from_secs(30)
+        );
+    }
+}
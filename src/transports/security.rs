@@ -1,5 +1,6 @@
 //! Security configuration
 
+use std::fmt;
 use std::time::Duration;
 
 /// Security configuration
@@ -13,6 +14,17 @@ pub struct SecurityConfig {
     pub request_timeout: Duration,
     /// Connection idle timeout
     pub idle_timeout: Duration,
+    /// Maximum nesting depth of objects and arrays (0 = unlimited)
+    pub(crate) max_json_depth: usize,
+    /// Maximum number of JSON values, including object keys (0 = unlimited)
+    pub(crate) max_json_nodes: usize,
+    /// Close connections after this long, between requests (zero = unlimited)
+    pub(crate) max_connection_lifetime: Duration,
+    /// Close connections after this many requests (0 = unlimited)
+    pub(crate) max_requests_per_connection: usize,
+    /// How long responses still queued or in flight are written once the
+    /// client stopped sending, e.g. after half-closing (zero = unlimited)
+    pub(crate) drain_timeout: Duration,
 }
 
 impl Default for SecurityConfig {
@@ -22,10 +34,139 @@ impl Default for SecurityConfig {
             max_request_size: 1024 * 1024, // 1 MB
             request_timeout: Duration::from_secs(30),
             idle_timeout: Duration::from_secs(300), // 5 minutes
+            max_json_depth: 64,
+            max_json_nodes: 100_000,
+            max_connection_lifetime: Duration::ZERO,
+            max_requests_per_connection: 0,
+            drain_timeout: Duration::from_secs(30),
         }
     }
 }
 
+impl SecurityConfig {
+    /// Limit the nesting depth of objects and arrays (0 = unlimited)
+    pub fn with_max_json_depth(mut self, depth: usize) -> Self {
+        self.max_json_depth = depth;
+        self
+    }
+
+    /// Limit the number of JSON values, object keys included (0 = unlimited)
+    pub fn with_max_json_nodes(mut self, nodes: usize) -> Self {
+        self.max_json_nodes = nodes;
+        self
+    }
+
+    /// Close connections after this long, between requests (zero = unlimited)
+    pub fn with_max_connection_lifetime(mut self, lifetime: Duration) -> Self {
+        self.max_connection_lifetime = lifetime;
+        self
+    }
+
+    /// Close connections after this many requests (0 = unlimited)
+    pub fn with_max_requests_per_connection(mut self, requests: usize) -> Self {
+        self.max_requests_per_connection = requests;
+        self
+    }
+
+    /// Keep writing owed responses this long after the client stopped
+    /// sending (zero = unlimited)
+    pub fn with_drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// Check a frame against `max_json_depth` and `max_json_nodes`
+    pub fn check_json_limits(&self, input: &str) -> Result<(), JsonLimitError> {
+        check_json_limits(input, self.max_json_depth, self.max_json_nodes)
+    }
+}
+
+/// A JSON structure limit that was exceeded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonLimitError {
+    TooDeep { limit: usize },
+    TooManyNodes { limit: usize },
+}
+
+impl fmt::Display for JsonLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonLimitError::TooDeep { limit } => {
+                write!(f, "JSON nesting exceeds depth limit of {limit}")
+            }
+            JsonLimitError::TooManyNodes { limit } => {
+                write!(f, "JSON exceeds limit of {limit} values")
+            }
+        }
+    }
+}
+
+impl std::error::Error for JsonLimitError {}
+
+/// Scan `input` for excessive nesting or value count without parsing it.
+///
+/// This is a lexical pass only: malformed input that stays within the
+/// limits is left for the parser to reject. Limits of 0 disable the
+/// respective check.
+pub fn check_json_limits(
+    input: &str,
+    max_depth: usize,
+    max_nodes: usize,
+) -> Result<(), JsonLimitError> {
+    let bytes = input.as_bytes();
+    let mut depth = 0usize;
+    let mut nodes = 0usize;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let starts_value = match bytes[i] {
+            b'{' | b'[' => {
+                depth += 1;
+                if max_depth > 0 && depth > max_depth {
+                    return Err(JsonLimitError::TooDeep { limit: max_depth });
+                }
+                i += 1;
+                true
+            }
+            b'}' | b']' => {
+                depth = depth.saturating_sub(1);
+                i += 1;
+                false
+            }
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                i += 1;
+                true
+            }
+            b'-' | b'0'..=b'9' | b't' | b'f' | b'n' => {
+                while i < bytes.len()
+                    && !matches!(bytes[i], b',' | b':' | b'}' | b']' | b'"')
+                    && !bytes[i].is_ascii_whitespace()
+                {
+                    i += 1;
+                }
+                true
+            }
+            _ => {
+                i += 1;
+                false
+            }
+        };
+
+        if starts_value {
+            nodes += 1;
+            if max_nodes > 0 && nodes > max_nodes {
+                return Err(JsonLimitError::TooManyNodes { limit: max_nodes });
+            }
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -36,5 +177,61 @@ mod tests {
         assert_eq!(config.max_request_size, 1024 * 1024);
         assert_eq!(config.request_timeout, Duration::from_secs(30));
         assert_eq!(config.idle_timeout, Duration::from_secs(300));
+        assert_eq!(config.max_json_depth, 64);
+        assert_eq!(config.max_json_nodes, 100_000);
+        assert!(config.max_connection_lifetime.is_zero());
+        assert_eq!(config.max_requests_per_connection, 0);
+        assert_eq!(config.drain_timeout, Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_json_limit_builders() {
+        let config = SecurityConfig::default()
+            .with_max_json_depth(8)
+            .with_max_json_nodes(0);
+        assert_eq!(config.max_json_depth, 8);
+        assert_eq!(config.max_json_nodes, 0);
+        assert_eq!(config.max_connections, 1000);
+    }
+
+    #[test]
+    fn test_connection_budget_builders() {
+        let config = SecurityConfig::default()
+            .with_max_connection_lifetime(Duration::from_secs(600))
+            .with_max_requests_per_connection(1000);
+        assert_eq!(config.max_connection_lifetime, Duration::from_secs(600));
+        assert_eq!(config.max_requests_per_connection, 1000);
+    }
+
+    #[test]
+    fn test_drain_timeout_builder() {
+        let config = SecurityConfig::default().with_drain_timeout(Duration::ZERO);
+        assert!(config.drain_timeout.is_zero());
+    }
+
+    #[test]
+    fn test_json_depth_limit() {
+        let nested = format!("{}{}", "[".repeat(10), "]".repeat(10));
+        assert!(check_json_limits(&nested, 10, 0).is_ok());
+        assert_eq!(
+            check_json_limits(&nested, 9, 0),
+            Err(JsonLimitError::TooDeep { limit: 9 })
+        );
+
+        // brackets inside strings do not count
+        let quoted = r#"{"params":"[[[[[[\"]]]"}"#;
+        assert!(check_json_limits(quoted, 1, 0).is_ok());
+    }
+
+    #[test]
+    fn test_json_node_limit() {
+        let input = r#"{"jsonrpc":"2.0","method":"sum","params":[1,2.5,-3,true,null],"id":1}"#;
+        // 1 object + 4 keys + 3 values + 1 array + 5 elements
+        assert!(check_json_limits(input, 0, 14).is_ok());
+        assert_eq!(
+            check_json_limits(input, 0, 13),
+            Err(JsonLimitError::TooManyNodes { limit: 13 })
+        );
+        assert!(check_json_limits(input, 0, 0).is_ok());
     }
 }
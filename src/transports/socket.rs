@@ -0,0 +1,169 @@
+//! Socket-level TCP tuning
+//!
+//! Servers apply their [`SocketOptions`] to every accepted connection,
+//! clients to the connection they open. Options left unset keep the
+//! operating system default, so an empty `SocketOptions` changes nothing.
+//!
+//! JSON-RPC frames are usually small enough that Nagle's algorithm holds
+//! them back waiting for more data; [`SocketOptions::nodelay`] turns it off.
+
+use std::io;
+use std::time::Duration;
+use tokio::net::TcpStream;
+
+/// TCP keepalive probing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Keepalive {
+    /// Idle time before the first probe
+    pub time: Duration,
+    /// Time between unanswered probes; not supported on every platform
+    pub interval: Option<Duration>,
+}
+
+/// Options set on each TCP socket
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SocketOptions {
+    /// `TCP_NODELAY`
+    pub nodelay: Option<bool>,
+    /// `SO_KEEPALIVE` with its timers
+    pub keepalive: Option<Keepalive>,
+    /// `SO_RCVBUF` in bytes
+    pub recv_buffer_size: Option<usize>,
+    /// `SO_SNDBUF` in bytes
+    pub send_buffer_size: Option<usize>,
+    /// `SO_LINGER`; `Duration::ZERO` resets the connection on close
+    pub linger: Option<Duration>,
+}
+
+impl SocketOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = Some(nodelay);
+        self
+    }
+
+    /// Enable keepalive, sending the first probe after `time` of idleness
+    pub fn keepalive(mut self, time: Duration) -> Self {
+        let interval = self.keepalive.and_then(|k| k.interval);
+        self.keepalive = Some(Keepalive { time, interval });
+        self
+    }
+
+    /// Interval between keepalive probes.
+    ///
+    /// Enables keepalive if it is not already, using `interval` as the idle
+    /// time as well.
+    pub fn keepalive_interval(mut self, interval: Duration) -> Self {
+        let time = self.keepalive.map_or(interval, |k| k.time);
+        self.keepalive = Some(Keepalive {
+            time,
+            interval: Some(interval),
+        });
+        self
+    }
+
+    pub fn recv_buffer_size(mut self, size: usize) -> Self {
+        self.recv_buffer_size = Some(size);
+        self
+    }
+
+    pub fn send_buffer_size(mut self, size: usize) -> Self {
+        self.send_buffer_size = Some(size);
+        self
+    }
+
+    pub fn linger(mut self, linger: Duration) -> Self {
+        self.linger = Some(linger);
+        self
+    }
+
+    /// Set the configured options on `stream`
+    pub fn apply(&self, stream: &TcpStream) -> io::Result<()> {
+        let socket = socket2::SockRef::from(stream);
+        if let Some(nodelay) = self.nodelay {
+            socket.set_tcp_nodelay(nodelay)?;
+        }
+        if let Some(keepalive) = self.keepalive {
+            let params = socket2::TcpKeepalive::new().with_time(keepalive.time);
+            #[cfg(any(
+                target_os = "linux",
+                target_os = "android",
+                target_os = "macos",
+                target_os = "ios",
+                target_os = "freebsd",
+                target_os = "netbsd",
+                windows
+            ))]
+            let params = match keepalive.interval {
+                Some(interval) => params.with_interval(interval),
+                None => params,
+            };
+            socket.set_tcp_keepalive(&params)?;
+        }
+        if let Some(size) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(size)?;
+        }
+        if let Some(size) = self.send_buffer_size {
+            socket.set_send_buffer_size(size)?;
+        }
+        if let Some(linger) = self.linger {
+            socket.set_linger(Some(linger))?;
+        }
+        Ok(())
+    }
+
+    /// Apply to an accepted connection; failures are logged, not fatal
+    pub(crate) fn apply_accepted(&self, stream: &TcpStream, remote_addr: std::net::SocketAddr) {
+        if let Err(e) = self.apply(stream) {
+            tracing::warn!(remote_addr = %remote_addr, error = %e, "failed to set socket options");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_apply_sets_options() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+
+        SocketOptions::new()
+            .nodelay(true)
+            .keepalive(Duration::from_secs(30))
+            .keepalive_interval(Duration::from_secs(5))
+            .recv_buffer_size(64 * 1024)
+            .linger(Duration::from_secs(1))
+            .apply(&stream)
+            .unwrap();
+
+        let socket = socket2::SockRef::from(&stream);
+        assert!(socket.tcp_nodelay().unwrap());
+        assert!(socket.keepalive().unwrap());
+        assert_eq!(socket.linger().unwrap(), Some(Duration::from_secs(1)));
+        // the kernel may round the buffer size up
+        assert!(socket.recv_buffer_size().unwrap() >= 64 * 1024);
+    }
+
+    #[test]
+    fn test_keepalive_builders_compose() {
+        let options = SocketOptions::new()
+            .keepalive_interval(Duration::from_secs(5))
+            .keepalive(Duration::from_secs(60));
+        assert_eq!(
+            options.keepalive,
+            Some(Keepalive {
+                time: Duration::from_secs(60),
+                interval: Some(Duration::from_secs(5)),
+            })
+        );
+        assert_eq!(SocketOptions::new(), SocketOptions::default());
+    }
+}
@@ -0,0 +1,270 @@
+//! Subscription routing shared by the stream-capable transports.
+//!
+//! A [`StreamRouter`] takes over a [`StreamManager`]'s event queue, answers
+//! subscribe, unsubscribe, pause and resume requests arriving on a
+//! connection and pushes
+//! each [`StreamEvent`](crate::streaming::StreamEvent) to the connection
+//! that opened the stream. Transports hand it the sender of a connection's
+//! outgoing queue; `T` is whatever that queue carries, built from the
+//! event's JSON.
+
+use crate::streaming::{StreamId, StreamManager};
+use crate::{MessageProcessor, Request, RequestId};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::mpsc;
+
+/// Routes a [`StreamManager`]'s events to the connections that subscribed
+pub(crate) struct StreamRouter<T> {
+    manager: Arc<StreamManager>,
+    routes: Mutex<HashMap<StreamId, mpsc::Sender<T>>>,
+}
+
+impl<T: From<String> + Send + 'static> StreamRouter<T> {
+    pub(crate) fn start(manager: Arc<StreamManager>) -> Arc<Self> {
+        let router = Arc::new(Self {
+            manager,
+            routes: Mutex::new(HashMap::new()),
+        });
+        let pump = Arc::clone(&router);
+        tokio::spawn(async move {
+            while let Some(event) = pump.manager.next_event().await {
+                pump.deliver(event);
+            }
+        });
+        router
+    }
+
+    fn routes(&self) -> MutexGuard<'_, HashMap<StreamId, mpsc::Sender<T>>> {
+        self.routes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn deliver(&self, event: crate::streaming::StreamEvent) {
+        let last = event.status == Some(crate::streaming::StreamStatus::Closed);
+        let mut routes = self.routes();
+        let Some(route) = routes.get(&event.stream_id) else {
+            tracing::debug!(stream_id = %event.stream_id, "event for unrouted stream dropped");
+            return;
+        };
+        let Ok(json) = serde_json::to_string(&event) else {
+            return;
+        };
+        match route.try_send(T::from(json)) {
+            Ok(()) => {}
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                tracing::warn!(stream_id = %event.stream_id, "subscriber too slow, event dropped");
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => {
+                routes.remove(&event.stream_id);
+                return;
+            }
+        }
+        if last {
+            routes.remove(&event.stream_id);
+        }
+    }
+
+    /// Answer `text` if it is a subscribe, unsubscribe, pause or resume
+    /// request
+    ///
+    /// `processor` must admit the request first, so listener filters, auth
+    /// and rate limits cover streams too. Streams opened here are added to
+    /// `owned` and their events sent to `out`.
+    async fn handle(
+        &self,
+        text: &str,
+        processor: &dyn MessageProcessor,
+        out: &mpsc::Sender<T>,
+        owned: &mut Vec<StreamId>,
+    ) -> Option<String> {
+        use crate::streaming::{
+            PAUSE_METHOD, RESUME_METHOD, StreamControlRequest, StreamRequest, StreamResponse,
+            UnsubscribeRequest,
+        };
+
+        #[derive(serde::Deserialize)]
+        struct Peek {
+            method: Option<String>,
+            stream_id: Option<String>,
+        }
+
+        let peek: Peek = serde_json::from_str(text).ok()?;
+        let method = peek.method?;
+        if method == "unsubscribe" && peek.stream_id.is_some() {
+            let request: UnsubscribeRequest = serde_json::from_str(text).ok()?;
+            if let Some(refusal) =
+                refusal(processor, &method, &request.stream_id, &request.id).await
+            {
+                return Some(refusal);
+            }
+            let response = match owned.iter().position(|id| *id == request.stream_id) {
+                Some(index) => {
+                    owned.swap_remove(index);
+                    self.routes().remove(&request.stream_id);
+                    match self.manager.unsubscribe(&request.stream_id).await {
+                        Ok(()) => StreamResponse::closed(request.stream_id, request.id),
+                        Err(e) => StreamResponse::error(e, request.id, request.stream_id),
+                    }
+                }
+                None => StreamResponse::error(
+                    not_found(&request.stream_id),
+                    request.id,
+                    request.stream_id,
+                ),
+            };
+            return serde_json::to_string(&response).ok();
+        }
+
+        if (method == PAUSE_METHOD || method == RESUME_METHOD) && peek.stream_id.is_some() {
+            let request: StreamControlRequest = serde_json::from_str(text).ok()?;
+            if let Some(refusal) =
+                refusal(processor, &method, &request.stream_id, &request.id).await
+            {
+                return Some(refusal);
+            }
+            let (stream_id, id) = (request.stream_id, request.id);
+            let response = if !owned.contains(&stream_id) {
+                StreamResponse::error(not_found(&stream_id), id, stream_id)
+            } else if method == PAUSE_METHOD {
+                match self.manager.pause(&stream_id).await {
+                    Ok(()) => StreamResponse::paused(stream_id, id),
+                    Err(e) => StreamResponse::error(e, id, stream_id),
+                }
+            } else {
+                match self.manager.resume(&stream_id).await {
+                    Ok(dropped) => StreamResponse::resumed(stream_id, id, dropped),
+                    Err(e) => StreamResponse::error(e, id, stream_id),
+                }
+            };
+            return serde_json::to_string(&response).ok();
+        }
+
+        if !self.manager.has_handler(&method).await {
+            return None;
+        }
+        let mut request: StreamRequest = serde_json::from_str(text).ok()?;
+        let subscription = admitted(&method, request.params.clone(), &request.id);
+        if let Some(refusal) = processor.admit(&subscription).await {
+            return serde_json::to_string(&refusal).ok();
+        }
+        let stream_id = request.stream_id();
+        let id = request.id.clone();
+        {
+            let mut routes = self.routes();
+            if routes.contains_key(&stream_id) {
+                let error = crate::ErrorBuilder::new(
+                    crate::error_codes::INVALID_PARAMS,
+                    format!("Stream id already in use: {stream_id}"),
+                )
+                .build();
+                return serde_json::to_string(&StreamResponse::error(error, id, stream_id)).ok();
+            }
+            // route first so the stream's first events are not lost
+            routes.insert(stream_id.clone(), out.clone());
+        }
+        request.stream_id = Some(stream_id.clone());
+        let response = match self.manager.subscribe(request).await {
+            Ok(response) => {
+                owned.push(stream_id);
+                response
+            }
+            Err(e) => {
+                self.routes().remove(&stream_id);
+                StreamResponse::error(e, id, stream_id)
+            }
+        };
+        serde_json::to_string(&response).ok()
+    }
+
+    /// Unsubscribe the streams of a closed connection
+    async fn release(&self, owned: Vec<StreamId>) {
+        for stream_id in owned {
+            self.routes().remove(&stream_id);
+            let _ = self.manager.unsubscribe(&stream_id).await;
+        }
+    }
+}
+
+/// The serialized refusal if `processor` does not admit a request on an open
+/// stream
+async fn refusal(
+    processor: &dyn MessageProcessor,
+    method: &str,
+    stream_id: &str,
+    id: &RequestId,
+) -> Option<String> {
+    let request = admitted(
+        method,
+        Some(serde_json::json!({ "stream_id": stream_id })),
+        id,
+    );
+    let refusal = processor.admit(&request).await?;
+    serde_json::to_string(&refusal).ok()
+}
+
+/// The request a processor is asked to admit for a stream request
+fn admitted(method: &str, params: Option<serde_json::Value>, id: &RequestId) -> Request {
+    Request {
+        jsonrpc: crate::versioning::DEFAULT_VERSION.to_string(),
+        method: method.to_string(),
+        params,
+        id: Some(id.clone()),
+        correlation_id: None,
+        ext: None,
+    }
+}
+
+/// The streams one connection opened
+///
+/// Dropping it unsubscribes them too, so a connection that ends on an error
+/// does not leave its streams running.
+pub(crate) struct OwnedStreams<T: From<String> + Send + 'static> {
+    router: Arc<StreamRouter<T>>,
+    streams: Vec<StreamId>,
+}
+
+impl<T: From<String> + Send + 'static> OwnedStreams<T> {
+    pub(crate) fn new(router: Arc<StreamRouter<T>>) -> Self {
+        Self {
+            router,
+            streams: Vec::new(),
+        }
+    }
+
+    /// Answer `text` through the router, see [`StreamRouter::handle`]
+    pub(crate) async fn handle(
+        &mut self,
+        text: &str,
+        processor: &dyn MessageProcessor,
+        out: &mpsc::Sender<T>,
+    ) -> Option<String> {
+        self.router
+            .handle(text, processor, out, &mut self.streams)
+            .await
+    }
+
+    /// Unsubscribe the streams once the connection closed
+    pub(crate) async fn release(mut self) {
+        let streams = std::mem::take(&mut self.streams);
+        self.router.release(streams).await;
+    }
+}
+
+impl<T: From<String> + Send + 'static> Drop for OwnedStreams<T> {
+    fn drop(&mut self) {
+        if self.streams.is_empty() {
+            return;
+        }
+        let router = Arc::clone(&self.router);
+        let streams = std::mem::take(&mut self.streams);
+        tokio::spawn(async move { router.release(streams).await });
+    }
+}
+
+fn not_found(stream_id: &str) -> crate::Error {
+    crate::ErrorBuilder::new(
+        crate::error_codes::INVALID_PARAMS,
+        format!("Stream not found: {stream_id}"),
+    )
+    .build()
+}
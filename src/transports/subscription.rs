@@ -0,0 +1,275 @@
+//! Client side of stream subscriptions.
+//!
+//! Stream-capable clients keep a `SubscriptionRoutes` next to their read
+//! loop. Events for a stream the client opened are pulled off the
+//! connection there and handed to that stream's [`Subscription`], so they
+//! never show up among the client's regular messages.
+
+use crate::streaming::{
+    StreamControlRequest, StreamEvent, StreamId, StreamStatus, UnsubscribeRequest,
+};
+use crate::types::RequestId;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+use tokio::sync::{mpsc, oneshot};
+
+/// Writes one message to the connection, `false` once it is gone
+pub(crate) type SendText =
+    Arc<dyn Fn(String) -> Pin<Box<dyn Future<Output = bool> + Send>> + Send + Sync>;
+
+type Outcome = Result<(), crate::Error>;
+
+#[derive(Default)]
+struct Routes {
+    events: HashMap<StreamId, mpsc::UnboundedSender<StreamEvent>>,
+    /// Unsubscribe, pause or resume requests in flight: request id and
+    /// waiting caller
+    pending: HashMap<StreamId, (RequestId, oneshot::Sender<Outcome>)>,
+}
+
+/// The subscriptions a client has open, keyed by stream id
+#[derive(Default)]
+pub(crate) struct SubscriptionRoutes(Mutex<Routes>);
+
+impl SubscriptionRoutes {
+    fn lock(&self) -> MutexGuard<'_, Routes> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Start collecting the events of `stream_id`
+    pub(crate) fn route(&self, stream_id: &str) -> mpsc::UnboundedReceiver<StreamEvent> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.lock().events.insert(stream_id.to_string(), tx);
+        rx
+    }
+
+    pub(crate) fn remove(&self, stream_id: &str) {
+        self.lock().events.remove(stream_id);
+    }
+
+    /// Take `text` off the connection if it belongs to an open subscription
+    pub(crate) fn dispatch(&self, text: &str) -> bool {
+        #[derive(serde::Deserialize)]
+        struct Peek {
+            method: Option<String>,
+            stream_id: Option<String>,
+            id: Option<RequestId>,
+            error: Option<crate::Error>,
+        }
+
+        let mut routes = self.lock();
+        if routes.events.is_empty() && routes.pending.is_empty() {
+            return false;
+        }
+        let Ok(peek) = serde_json::from_str::<Peek>(text) else {
+            return false;
+        };
+        let Some(stream_id) = peek.stream_id else {
+            return false;
+        };
+        match (peek.method, peek.id) {
+            (Some(_), None) => {
+                let Some(route) = routes.events.get(&stream_id) else {
+                    // sent before the server saw the unsubscribe, nobody wants it
+                    return routes.pending.contains_key(&stream_id);
+                };
+                let Ok(event) = serde_json::from_str::<StreamEvent>(text) else {
+                    return false;
+                };
+                let last = matches!(
+                    event.status,
+                    Some(StreamStatus::Closed | StreamStatus::Error)
+                );
+                if route.send(event).is_err() || last {
+                    routes.events.remove(&stream_id);
+                }
+                true
+            }
+            (None, Some(id)) => match routes.pending.get(&stream_id) {
+                Some((expected, _)) if *expected == id => {
+                    if let Some((_, waiter)) = routes.pending.remove(&stream_id) {
+                        let _ = waiter.send(peek.error.map_or(Ok(()), Err));
+                    }
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// End every subscription, the connection is gone
+    pub(crate) fn close_all(&self) {
+        let mut routes = self.lock();
+        routes.events.clear();
+        routes.pending.clear();
+    }
+
+    /// Wait for the answer to the `action` request on `stream_id`
+    fn expect(&self, stream_id: &str, action: &str) -> (RequestId, oneshot::Receiver<Outcome>) {
+        let id = RequestId::from(format!("{action}:{stream_id}"));
+        let (tx, rx) = oneshot::channel();
+        self.lock()
+            .pending
+            .insert(stream_id.to_string(), (id.clone(), tx));
+        (id, rx)
+    }
+
+    fn closing(&self, stream_id: &str) -> (RequestId, oneshot::Receiver<Outcome>) {
+        self.remove(stream_id);
+        self.expect(stream_id, "unsubscribe")
+    }
+}
+
+/// The server's answer to the subscribe request `id`, `None` if `text` is
+/// something else
+///
+/// Servers without a stream for the method answer with a plain response,
+/// which fails the subscription.
+pub(crate) fn subscribe_outcome(
+    text: &str,
+    id: &RequestId,
+) -> Option<Result<(), Box<dyn std::error::Error>>> {
+    #[derive(serde::Deserialize)]
+    struct Reply {
+        method: Option<String>,
+        id: Option<RequestId>,
+        stream_id: Option<StreamId>,
+        error: Option<crate::Error>,
+    }
+
+    let reply: Reply = serde_json::from_str(text).ok()?;
+    if reply.method.is_some() || reply.id.as_ref() != Some(id) {
+        return None;
+    }
+    Some(match (reply.error, reply.stream_id) {
+        (Some(error), _) => Err(error.into()),
+        (None, Some(_)) => Ok(()),
+        (None, None) => Err("server did not open a stream".into()),
+    })
+}
+
+/// Events of one stream opened by a client
+///
+/// Yields each [`StreamEvent`] of the stream, the closing one included, and
+/// ends once the server closes the stream or the connection goes away.
+/// Dropping a subscription that is still open unsubscribes in the
+/// background; [`unsubscribe`](Self::unsubscribe) waits for the server to
+/// confirm instead.
+pub struct Subscription {
+    stream_id: StreamId,
+    events: mpsc::UnboundedReceiver<StreamEvent>,
+    routes: Arc<SubscriptionRoutes>,
+    send: SendText,
+    open: bool,
+}
+
+impl Subscription {
+    pub(crate) fn new(
+        stream_id: StreamId,
+        events: mpsc::UnboundedReceiver<StreamEvent>,
+        routes: Arc<SubscriptionRoutes>,
+        send: SendText,
+    ) -> Self {
+        Self {
+            stream_id,
+            events,
+            routes,
+            send,
+            open: true,
+        }
+    }
+
+    pub fn stream_id(&self) -> &str {
+        &self.stream_id
+    }
+
+    /// Next event, `None` once the stream ended
+    pub async fn next(&mut self) -> Option<StreamEvent> {
+        std::future::poll_fn(|cx| futures_core::Stream::poll_next(Pin::new(&mut *self), cx)).await
+    }
+
+    /// Have the server hold the stream's events back until
+    /// [`resume`](Self::resume), keeping the subscription open
+    pub async fn pause(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let (id, confirmed) = self.routes.expect(&self.stream_id, "pause");
+        let request = StreamControlRequest::pause(self.stream_id.clone(), id);
+        self.request(serde_json::to_string(&request)?, confirmed)
+            .await
+    }
+
+    /// Have the server deliver the events held back since
+    /// [`pause`](Self::pause), then the ones after
+    pub async fn resume(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let (id, confirmed) = self.routes.expect(&self.stream_id, "resume");
+        let request = StreamControlRequest::resume(self.stream_id.clone(), id);
+        self.request(serde_json::to_string(&request)?, confirmed)
+            .await
+    }
+
+    /// Close the stream and wait for the server to confirm
+    pub async fn unsubscribe(mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.open = false;
+        let (id, confirmed) = self.routes.closing(&self.stream_id);
+        let request = UnsubscribeRequest::new(self.stream_id.clone(), id);
+        self.request(serde_json::to_string(&request)?, confirmed)
+            .await
+    }
+
+    async fn request(
+        &self,
+        text: String,
+        confirmed: oneshot::Receiver<Outcome>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if !(self.send)(text).await {
+            self.routes.lock().pending.remove(&self.stream_id);
+            return Err("connection closed".into());
+        }
+        match confirmed.await {
+            Ok(outcome) => Ok(outcome?),
+            Err(_) => Err("connection closed before confirmation".into()),
+        }
+    }
+}
+
+impl futures_core::Stream for Subscription {
+    type Item = StreamEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<StreamEvent>> {
+        let polled = self.events.poll_recv(cx);
+        if let Poll::Ready(None) = polled {
+            self.open = false;
+        }
+        polled
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if !self.open {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            self.routes.remove(&self.stream_id);
+            return;
+        };
+        // the confirmation is still taken off the connection, nobody waits for it
+        let (id, _) = self.routes.closing(&self.stream_id);
+        let request = UnsubscribeRequest::new(self.stream_id.clone(), id);
+        if let Ok(text) = serde_json::to_string(&request) {
+            runtime.spawn((self.send)(text));
+        }
+    }
+}
+
+impl std::fmt::Debug for Subscription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Subscription")
+            .field("stream_id", &self.stream_id)
+            .field("open", &self.open)
+            .finish()
+    }
+}
@@ -0,0 +1,187 @@
+//! Accept-loop supervision
+//!
+//! `accept()` can fail for reasons that have nothing to do with the listener
+//! itself, most commonly file descriptor exhaustion (`EMFILE`/`ENFILE`). The
+//! servers hand such errors to an [`AcceptSupervisor`], which logs them,
+//! backs off exponentially and keeps the loop alive until
+//! [`SupervisionPolicy::max_consecutive_failures`] is reached.
+
+use std::io;
+use std::time::Duration;
+
+/// How a server reacts to failing `accept()` calls
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SupervisionPolicy {
+    /// Delay after the first failure
+    pub initial_backoff: Duration,
+    /// Upper bound for the exponential backoff
+    pub max_backoff: Duration,
+    /// Consecutive failures before the server gives up (0 = never)
+    pub max_consecutive_failures: usize,
+}
+
+impl Default for SupervisionPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(5),
+            max_consecutive_failures: 100,
+        }
+    }
+}
+
+impl SupervisionPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    pub fn max_consecutive_failures(mut self, failures: usize) -> Self {
+        self.max_consecutive_failures = failures;
+        self
+    }
+
+    /// Retry forever
+    pub fn unlimited(self) -> Self {
+        self.max_consecutive_failures(0)
+    }
+}
+
+/// Tracks consecutive accept failures for one listener
+#[derive(Debug)]
+pub struct AcceptSupervisor {
+    policy: SupervisionPolicy,
+    failures: usize,
+    backoff: Duration,
+}
+
+impl AcceptSupervisor {
+    pub fn new(policy: SupervisionPolicy) -> Self {
+        let backoff = policy.initial_backoff;
+        Self {
+            policy,
+            failures: 0,
+            backoff,
+        }
+    }
+
+    /// Number of failures since the last successful accept
+    pub fn consecutive_failures(&self) -> usize {
+        self.failures
+    }
+
+    /// Record a successful accept, resetting the backoff
+    pub fn on_success(&mut self) {
+        if self.failures > 0 {
+            tracing::info!(failures = self.failures, "accept loop recovered");
+        }
+        self.failures = 0;
+        self.backoff = self.policy.initial_backoff;
+    }
+
+    /// Record a failed accept.
+    ///
+    /// Errors caused by a single peer aborting its handshake are retried
+    /// immediately. Anything else sleeps for the current backoff; once the
+    /// failure threshold is reached the error is returned and the server
+    /// should stop.
+    pub async fn on_error(&mut self, error: io::Error) -> Result<(), io::Error> {
+        if is_per_connection(&error) {
+            tracing::debug!(error = %error, "peer aborted before accept completed");
+            return Ok(());
+        }
+
+        self.failures += 1;
+        if self.policy.max_consecutive_failures > 0
+            && self.failures >= self.policy.max_consecutive_failures
+        {
+            tracing::error!(
+                error = %error,
+                failures = self.failures,
+                "accept loop giving up"
+            );
+            return Err(error);
+        }
+
+        tracing::warn!(
+            error = %error,
+            failures = self.failures,
+            backoff_ms = self.backoff.as_millis() as u64,
+            "failed to accept connection, backing off"
+        );
+        tokio::time::sleep(self.backoff).await;
+        self.backoff = (self.backoff * 2).min(self.policy.max_backoff);
+        Ok(())
+    }
+}
+
+fn is_per_connection(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::Interrupted
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emfile() -> io::Error {
+        io::Error::other("too many open files")
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_gives_up_after_threshold() {
+        let policy = SupervisionPolicy::new()
+            .initial_backoff(Duration::from_millis(1))
+            .max_backoff(Duration::from_millis(2))
+            .max_consecutive_failures(3);
+        let mut supervisor = AcceptSupervisor::new(policy);
+
+        assert!(supervisor.on_error(emfile()).await.is_ok());
+        assert!(supervisor.on_error(emfile()).await.is_ok());
+        assert!(supervisor.on_error(emfile()).await.is_err());
+
+        let policy = SupervisionPolicy::new()
+            .initial_backoff(Duration::from_millis(1))
+            .max_backoff(Duration::from_millis(1))
+            .unlimited();
+        let mut supervisor = AcceptSupervisor::new(policy);
+        for _ in 0..5 {
+            assert!(supervisor.on_error(emfile()).await.is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_resets_on_success() {
+        let policy = SupervisionPolicy::new()
+            .initial_backoff(Duration::from_millis(1))
+            .max_consecutive_failures(2);
+        let mut supervisor = AcceptSupervisor::new(policy);
+
+        supervisor.on_error(emfile()).await.unwrap();
+        assert_eq!(supervisor.consecutive_failures(), 1);
+        supervisor.on_success();
+        assert_eq!(supervisor.consecutive_failures(), 0);
+        supervisor.on_error(emfile()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_per_connection_errors_not_counted() {
+        let mut supervisor = AcceptSupervisor::new(SupervisionPolicy::new());
+        let aborted = io::Error::from(io::ErrorKind::ConnectionAborted);
+        supervisor.on_error(aborted).await.unwrap();
+        assert_eq!(supervisor.consecutive_failures(), 0);
+    }
+}
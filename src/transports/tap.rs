@@ -0,0 +1,211 @@
+//! Wire tap: a copy of the raw frames of a connection
+//!
+//! A [`WireTap`] hands every frame a connection reads or writes to a
+//! [`TapSink`], exactly as it travelled: after TLS decryption, before
+//! parsing, in the connection's framing and codec. That makes interop
+//! problems with a peer visible without packet capture, which cannot see
+//! into encrypted traffic anyway.
+//!
+//! Taps are attached with `.tap(..)` on the TCP streaming, TLS and WebSocket
+//! server builders and on [`TcpStreamClientBuilder`], the WebSocket client
+//! builder and the `client` module's TCP transport. Sampling picks whole
+//! connections, so a sampled conversation is always complete; frames longer
+//! than [`max_frame_bytes`](WireTap::max_frame_bytes) are cut, with
+//! [`TapFrame::len`] keeping the original size.
+//!
+//! [`TcpStreamClientBuilder`]: super::TcpStreamClientBuilder
+//!
+//! ```rust
+//! use ash_rpc::transports::{TapDirection, TapFrame, WireTap};
+//!
+//! let tap = WireTap::new(|frame: &TapFrame<'_>| {
+//!     let arrow = match frame.direction {
+//!         TapDirection::Inbound => "<-",
+//!         TapDirection::Outbound => "->",
+//!     };
+//!     eprintln!("{arrow} {:?} {}", frame.remote_addr, frame.text());
+//! })
+//! .sample_rate(0.1)
+//! .max_frame_bytes(1024);
+//! ```
+//!
+//! Sinks run on the connection's task: hand frames off to a channel rather
+//! than blocking on slow I/O.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Which way a frame travelled, seen from the tapped side
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapDirection {
+    /// Read from the peer
+    Inbound,
+    /// Written to the peer
+    Outbound,
+}
+
+/// One captured frame
+#[derive(Debug)]
+pub struct TapFrame<'a> {
+    /// Transport name, as in [`transport_stats`](super::transport_stats)
+    pub transport: &'static str,
+    pub remote_addr: Option<SocketAddr>,
+    pub direction: TapDirection,
+    /// The frame, at most `max_frame_bytes` of it
+    pub bytes: &'a [u8],
+    /// Size of the whole frame
+    pub len: usize,
+}
+
+impl TapFrame<'_> {
+    /// Whether [`bytes`](Self::bytes) is only the start of the frame
+    pub fn is_truncated(&self) -> bool {
+        self.bytes.len() < self.len
+    }
+
+    /// The frame as text, invalid UTF-8 replaced
+    pub fn text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self.bytes)
+    }
+}
+
+/// Receiver of captured frames
+pub trait TapSink: Send + Sync + 'static {
+    fn capture(&self, frame: &TapFrame<'_>);
+}
+
+impl<F> TapSink for F
+where
+    F: Fn(&TapFrame<'_>) + Send + Sync + 'static,
+{
+    fn capture(&self, frame: &TapFrame<'_>) {
+        self(frame)
+    }
+}
+
+/// Mirrors the frames of sampled connections to a [`TapSink`]
+pub struct WireTap {
+    sink: Box<dyn TapSink>,
+    sample_rate: f64,
+    max_frame_bytes: usize,
+    connections: AtomicU64,
+}
+
+impl fmt::Debug for WireTap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WireTap")
+            .field("sample_rate", &self.sample_rate)
+            .field("max_frame_bytes", &self.max_frame_bytes)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WireTap {
+    /// Tap every connection, keeping up to 4 KiB of each frame
+    pub fn new(sink: impl TapSink) -> Self {
+        Self {
+            sink: Box::new(sink),
+            sample_rate: 1.0,
+            max_frame_bytes: 4096,
+            connections: AtomicU64::new(0),
+        }
+    }
+
+    /// Fraction of connections to tap, clamped to `0.0..=1.0`
+    ///
+    /// Connections are picked evenly rather than at random: at 0.25 every
+    /// fourth one is tapped.
+    pub fn sample_rate(mut self, rate: f64) -> Self {
+        self.sample_rate = if rate.is_nan() {
+            0.0
+        } else {
+            rate.clamp(0.0, 1.0)
+        };
+        self
+    }
+
+    /// Bytes kept of each frame, 0 for whole frames
+    pub fn max_frame_bytes(mut self, max: usize) -> Self {
+        self.max_frame_bytes = max;
+        self
+    }
+
+    /// The tap of a new connection, `None` if it is not sampled
+    pub(crate) fn connection(
+        self: &Arc<Self>,
+        transport: &'static str,
+        remote_addr: Option<SocketAddr>,
+    ) -> Option<ConnectionTap> {
+        let n = self.connections.fetch_add(1, Ordering::Relaxed) as f64;
+        // sampled when the running count of taps to take steps up
+        let sampled = ((n + 1.0) * self.sample_rate).floor() > (n * self.sample_rate).floor();
+        sampled.then(|| ConnectionTap {
+            tap: Arc::clone(self),
+            transport,
+            remote_addr,
+        })
+    }
+}
+
+/// The tap of one sampled connection
+#[derive(Debug, Clone)]
+pub(crate) struct ConnectionTap {
+    tap: Arc<WireTap>,
+    transport: &'static str,
+    remote_addr: Option<SocketAddr>,
+}
+
+impl ConnectionTap {
+    pub(crate) fn capture(&self, direction: TapDirection, frame: &[u8]) {
+        let max = self.tap.max_frame_bytes;
+        let kept = if max > 0 && frame.len() > max {
+            &frame[..max]
+        } else {
+            frame
+        };
+        self.tap.sink.capture(&TapFrame {
+            transport: self.transport,
+            remote_addr: self.remote_addr,
+            direction,
+            bytes: kept,
+            len: frame.len(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_sampling_and_truncation() {
+        let frames = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&frames);
+        let tap = Arc::new(
+            WireTap::new(move |frame: &TapFrame<'_>| {
+                seen.lock()
+                    .unwrap()
+                    .push((frame.direction, frame.text().into_owned(), frame.len));
+            })
+            .sample_rate(0.25)
+            .max_frame_bytes(4),
+        );
+
+        let sampled: Vec<_> = (0..8).map(|_| tap.connection("test", None)).collect();
+        assert_eq!(sampled.iter().filter(|tap| tap.is_some()).count(), 2);
+        let connection = sampled.into_iter().flatten().next().unwrap();
+        connection.capture(TapDirection::Inbound, b"{\"id\":1}");
+        connection.capture(TapDirection::Outbound, b"{}");
+        assert_eq!(
+            *frames.lock().unwrap(),
+            vec![
+                (TapDirection::Inbound, "{\"id".to_string(), 8),
+                (TapDirection::Outbound, "{}".to_string(), 2),
+            ]
+        );
+    }
+}
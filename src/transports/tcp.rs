@@ -3,11 +3,11 @@
 //! Simple TCP server for one-request-per-connection pattern.
 
 use super::security::SecurityConfig;
-use crate::{Message, MessageProcessor};
+use crate::MessageProcessor;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::net::{TcpListener, TcpStream};
+use tokio::net::TcpStream;
 use tokio::runtime::Runtime;
 use tokio::time::timeout;
 
@@ -19,6 +19,14 @@ pub struct TcpServerBuilder {
     addr: String,
     processor: Option<Arc<dyn MessageProcessor + Send + Sync>>,
     security_config: SecurityConfig,
+    supervision: super::supervisor::SupervisionPolicy,
+    socket_options: super::socket::SocketOptions,
+    backlog: Option<u32>,
+    accept_rate: Option<super::accept::AcceptRateLimit>,
+    name: Option<String>,
+    method_filter: super::listener::MethodFilter,
+    json_format: crate::serialization::JsonFormat,
+    handoff: Option<super::handoff::HandoffSlot>,
 }
 
 impl TcpServerBuilder {
@@ -27,6 +35,14 @@ impl TcpServerBuilder {
             addr: addr.into(),
             processor: None,
             security_config: SecurityConfig::default(),
+            supervision: super::supervisor::SupervisionPolicy::default(),
+            socket_options: super::socket::SocketOptions::default(),
+            backlog: None,
+            accept_rate: None,
+            name: None,
+            method_filter: super::listener::MethodFilter::default(),
+            json_format: crate::serialization::JsonFormat::default(),
+            handoff: None,
         }
     }
 
@@ -58,15 +74,97 @@ impl TcpServerBuilder {
         self
     }
 
+    /// How to recover from failing `accept()` calls
+    pub fn supervision(mut self, policy: super::supervisor::SupervisionPolicy) -> Self {
+        self.supervision = policy;
+        self
+    }
+
+    /// TCP options applied to every accepted connection
+    pub fn socket_options(mut self, options: super::socket::SocketOptions) -> Self {
+        self.socket_options = options;
+        self
+    }
+
+    /// Listen backlog passed to `listen(2)` instead of the OS default
+    pub fn backlog(mut self, backlog: u32) -> Self {
+        self.backlog = Some(backlog);
+        self
+    }
+
+    /// Pace how quickly accepted connections are handled
+    pub fn accept_rate(mut self, limit: super::accept::AcceptRateLimit) -> Self {
+        self.accept_rate = Some(limit);
+        self
+    }
+
+    /// Name this listener; the name is exposed as `ConnectionContext::origin`
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Restrict which methods may be called through this listener
+    pub fn method_filter(mut self, filter: super::listener::MethodFilter) -> Self {
+        self.method_filter = filter;
+        self
+    }
+
+    /// Output format handlers convert results with; frames are always
+    /// written compact
+    pub fn json_format(mut self, format: crate::serialization::JsonFormat) -> Self {
+        self.json_format = format;
+        self
+    }
+
+    /// Take part in `handoff` as the listener called `name`, so a restarted
+    /// process can take over the listening socket
+    pub fn handoff(
+        mut self,
+        handoff: Arc<super::handoff::ListenerHandoff>,
+        name: impl Into<String>,
+    ) -> Self {
+        self.handoff = Some(super::handoff::HandoffSlot {
+            handoff,
+            name: name.into(),
+        });
+        self
+    }
+
+    /// Validate the configuration without binding a listener for real.
+    ///
+    /// Checks that a processor is set, the address can be bound and the
+    /// security limits are sane.
+    pub fn validate(&self) -> super::validation::ConfigReport {
+        super::validation::check_server(
+            self.processor.is_some(),
+            &self.addr,
+            &self.security_config,
+            |_| {},
+        )
+    }
+
     pub fn build(self) -> Result<TcpServer, std::io::Error> {
         let processor = self.processor.ok_or_else(|| {
             std::io::Error::new(std::io::ErrorKind::InvalidInput, "Processor not set")
         })?;
+        let processor = super::listener::ListenerProcessor::wrap(
+            processor,
+            self.name.clone(),
+            self.method_filter,
+        );
 
         Ok(TcpServer {
             addr: self.addr,
+            name: self.name,
+            json_format: self.json_format,
             processor,
-            security_config: self.security_config,
+            security_config: crate::reload::Reloadable::new(self.security_config),
+            supervision: self.supervision,
+            socket_options: self.socket_options,
+            backlog: self.backlog,
+            handoff: self.handoff,
+            accept_rate: self.accept_rate,
             active_connections: Arc::new(AtomicUsize::new(0)),
         })
     }
@@ -74,8 +172,15 @@ impl TcpServerBuilder {
 
 pub struct TcpServer {
     addr: String,
+    name: Option<String>,
+    json_format: crate::serialization::JsonFormat,
     processor: Arc<dyn MessageProcessor + Send + Sync>,
-    security_config: SecurityConfig,
+    security_config: crate::reload::Reloadable<SecurityConfig>,
+    supervision: super::supervisor::SupervisionPolicy,
+    socket_options: super::socket::SocketOptions,
+    backlog: Option<u32>,
+    handoff: Option<super::handoff::HandoffSlot>,
+    accept_rate: Option<super::accept::AcceptRateLimit>,
     active_connections: Arc<AtomicUsize>,
 }
 
@@ -84,57 +189,110 @@ impl TcpServer {
         TcpServerBuilder::new(addr)
     }
 
+    /// Handle to the live security configuration.
+    ///
+    /// Storing a new config affects connections accepted afterwards; see
+    /// [`ConfigReloader::security`](crate::reload::ConfigReloader::security).
+    pub fn security_handle(&self) -> crate::reload::Reloadable<SecurityConfig> {
+        self.security_config.clone()
+    }
+
     pub fn run(&self) -> Result<(), std::io::Error> {
         let rt = Runtime::new()?;
         rt.block_on(self.run_async())
     }
 
     async fn run_async(&self) -> Result<(), std::io::Error> {
-        let listener = TcpListener::bind(&self.addr).await?;
+        let (listener, mut handed_off) =
+            super::handoff::listen(self.handoff.as_ref(), &self.addr, self.backlog).await?;
+        let initial = self.security_config.load();
+        let id = super::listener::ListenerId::new(
+            super::transport_stats::TCP,
+            listener.local_addr().ok(),
+        )
+        .name(self.name.clone());
         tracing::info!(
             addr = %self.addr,
+            listener = %id,
             protocol = "tcp",
-            max_connections = self.security_config.max_connections,
-            max_request_size = self.security_config.max_request_size,
+            max_connections = initial.max_connections,
+            max_request_size = initial.max_request_size,
             "server listening"
         );
 
+        let mut supervisor = super::supervisor::AcceptSupervisor::new(self.supervision.clone());
+        let mut pacer = super::accept::AcceptPacer::new(self.accept_rate.clone());
+        let transport = super::transport_stats::listener(&id);
         loop {
-            match listener.accept().await {
+            let Some(accepted) = super::handoff::accept(&listener, handed_off.as_mut()).await
+            else {
+                super::handoff::drain(self.handoff.as_ref(), &self.active_connections).await;
+                return Ok(());
+            };
+            match accepted {
                 Ok((stream, addr)) => {
+                    supervisor.on_success();
                     let current_connections = self.active_connections.load(Ordering::Relaxed);
+                    let security_config = self.security_config.load();
 
                     // Check connection limit
-                    if self.security_config.max_connections > 0
-                        && current_connections >= self.security_config.max_connections
+                    if security_config.max_connections > 0
+                        && current_connections >= security_config.max_connections
                     {
-                        tracing::warn!(
-                            remote_addr = %addr,
-                            active_connections = current_connections,
-                            max_connections = self.security_config.max_connections,
-                            "connection limit reached, rejecting connection"
+                        crate::rejection::record(
+                            crate::rejection::Rejection::new(
+                                crate::rejection::RejectionReason::ConnectionLimit,
+                            )
+                            .remote_addr(Some(addr))
+                            .detail(format!(
+                                "{current_connections} of {} connections in use",
+                                security_config.max_connections
+                            )),
                         );
+                        transport.rejected();
                         drop(stream);
                         continue;
                     }
 
+                    let Some(delay) = pacer.admit(addr) else {
+                        transport.rejected();
+                        drop(stream);
+                        continue;
+                    };
+
+                    self.socket_options.apply_accepted(&stream, addr);
                     self.active_connections.fetch_add(1, Ordering::Relaxed);
                     let processor = Arc::clone(&self.processor);
-                    let security_config = self.security_config.clone();
+                    let security_config = SecurityConfig::clone(&security_config);
                     let active_connections = Arc::clone(&self.active_connections);
-
-                    tokio::spawn(async move {
-                        let result = handle_client(stream, processor, security_config).await;
+                    let active = transport.accepted();
+                    let id = id.clone();
+                    let json_format = self.json_format;
+                    let span =
+                        tracing::info_span!("connection", listener = %id, remote_addr = %addr);
+
+                    let connection = async move {
+                        if !delay.is_zero() {
+                            tokio::time::sleep(delay).await;
+                        }
+                        let result = handle_client(
+                            stream,
+                            processor,
+                            security_config,
+                            Some(id),
+                            json_format,
+                        )
+                        .await;
                         active_connections.fetch_sub(1, Ordering::Relaxed);
+                        drop(active);
 
                         if let Err(e) = result {
                             tracing::error!(remote_addr = %addr, error = %e, "client handler failed");
                         }
-                    });
-                }
-                Err(e) => {
-                    tracing::error!(error = %e, "failed to accept connection");
+                    };
+                    tokio::spawn(tracing::Instrument::instrument(connection, span));
                 }
+                Err(e) => supervisor.on_error(e).await?,
             }
         }
     }
@@ -144,30 +302,82 @@ async fn handle_client(
     stream: TcpStream,
     processor: Arc<dyn MessageProcessor + Send + Sync>,
     security_config: SecurityConfig,
+    listener: Option<super::listener::ListenerId>,
+    json_format: crate::serialization::JsonFormat,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let (reader, mut writer) = stream.into_split();
-    let mut reader = BufReader::new(reader);
+    use super::transport_stats::{self, Metered};
+
+    let transport = listener.as_ref().map_or_else(
+        || transport_stats::transport(transport_stats::TCP),
+        transport_stats::listener,
+    );
+    let remote_addr = stream.peer_addr().ok();
+    let mut connection = remote_addr
+        .map(crate::auth::ConnectionContext::with_addr)
+        .unwrap_or_default();
+    if let Some(listener) = listener {
+        connection = connection.with_listener(listener);
+    }
+    if json_format != crate::serialization::JsonFormat::default() {
+        connection = connection.with_json_format(json_format);
+    }
+    let processor: Arc<dyn MessageProcessor + Send + Sync> = Arc::new(
+        super::connection::ConnectionProcessor::new(processor, connection),
+    );
+    let (reader, writer) = stream.into_split();
+    let mut reader = BufReader::new(Metered::new(reader, Arc::clone(&transport)));
+    let mut writer = Metered::new(writer, Arc::clone(&transport));
     let mut line = String::new();
+    let mut budget = super::lifetime::ConnectionBudget::new(&security_config);
+    let codec = super::codec_stats::negotiated(super::codec_stats::JSON_LINES);
 
     loop {
+        if let Some(reason) = budget.exhausted() {
+            if let Some(notification) = budget.recycle(reason, remote_addr) {
+                writer.write_all(notification.as_bytes()).await?;
+                writer.write_all(b"\n").await?;
+                writer.flush().await?;
+            }
+            break;
+        }
+
         line.clear();
 
         // Apply request timeout
-        let bytes_read =
-            match timeout(security_config.request_timeout, reader.read_line(&mut line)).await {
-                Ok(result) => result?,
-                Err(_) => {
-                    tracing::warn!("request timeout exceeded");
-                    return Err("request timeout".into());
-                }
-            };
+        let read = budget
+            .guard(timeout(
+                security_config.request_timeout,
+                reader.read_line(&mut line),
+            ))
+            .await;
+        let Some(read) = read else {
+            // lifetime ended while waiting for the next request
+            continue;
+        };
+        let bytes_read = match read {
+            Ok(result) => result?,
+            Err(_) => {
+                crate::rejection::record(
+                    crate::rejection::Rejection::new(crate::rejection::RejectionReason::Timeout)
+                        .remote_addr(remote_addr),
+                );
+                transport.idle_timeout();
+                return Err("request timeout".into());
+            }
+        };
 
         // Check max request size
         if security_config.max_request_size > 0 && line.len() > security_config.max_request_size {
-            tracing::warn!(
-                request_size = line.len(),
-                max_size = security_config.max_request_size,
-                "request size limit exceeded"
+            crate::rejection::record(
+                crate::rejection::Rejection::new(
+                    crate::rejection::RejectionReason::RequestTooLarge,
+                )
+                .remote_addr(remote_addr)
+                .detail(format!(
+                    "{} bytes exceeds {}",
+                    line.len(),
+                    security_config.max_request_size
+                )),
             );
             let error_response = crate::Response::error(
                 crate::ErrorBuilder::new(
@@ -193,8 +403,43 @@ async fn handle_client(
             continue;
         }
 
-        match serde_json::from_str::<Message>(line) {
-            Ok(message) => {
+        if let Err(e) = security_config.check_json_limits(line) {
+            crate::rejection::record(
+                crate::rejection::Rejection::new(crate::rejection::RejectionReason::JsonTooComplex)
+                    .remote_addr(remote_addr)
+                    .detail(e.to_string()),
+            );
+            let error_response = crate::Response::error(
+                crate::ErrorBuilder::new(crate::error_codes::INVALID_REQUEST, e.to_string())
+                    .build(),
+                None,
+            );
+            let response_json = serde_json::to_string(&error_response)?;
+            writer.write_all(response_json.as_bytes()).await?;
+            writer.write_all(b"\n").await?;
+            writer.flush().await?;
+            continue;
+        }
+
+        budget.on_request();
+        let prepared = crate::borrowed::prepare(line, processor.as_ref());
+        codec.observe(prepared.is_ok());
+        match prepared {
+            Ok(crate::borrowed::Prepared::Cached(response_json)) => {
+                writer.write_all(response_json.as_bytes()).await?;
+                writer.write_all(b"\n").await?;
+                writer.flush().await?;
+            }
+            Ok(crate::borrowed::Prepared::Batch(entries)) => {
+                if let Some(response_json) =
+                    super::batch::respond(processor.as_ref(), entries, remote_addr).await?
+                {
+                    writer.write_all(response_json.as_bytes()).await?;
+                    writer.write_all(b"\n").await?;
+                    writer.flush().await?;
+                }
+            }
+            Ok(crate::borrowed::Prepared::Message(message)) => {
                 let response_opt = processor.process_message(message).await;
                 if let Some(response) = response_opt {
                     let response_json = serde_json::to_string(&response)?;
@@ -204,6 +449,11 @@ async fn handle_client(
                 }
             }
             Err(e) => {
+                crate::rejection::record(
+                    crate::rejection::Rejection::new(crate::rejection::RejectionReason::ParseError)
+                        .remote_addr(remote_addr)
+                        .detail(e.to_string()),
+                );
                 let error_response = crate::ResponseBuilder::new()
                     .error(
                         crate::ErrorBuilder::new(
@@ -229,9 +479,10 @@ async fn handle_client(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{Request, Response, error_codes};
+    use crate::{Message, Request, Response, error_codes};
     use std::time::Duration;
     use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
     use tokio::net::TcpStream;
 
     // Mock processor for testing
@@ -294,6 +545,7 @@ mod tests {
             max_request_size: 2048,
             request_timeout: Duration::from_secs(10),
             idle_timeout: Duration::from_secs(60),
+            ..Default::default()
         };
         let builder = TcpServerBuilder::new("127.0.0.1:8080").security_config(config.clone());
         assert_eq!(builder.security_config.max_connections, 50);
@@ -384,7 +636,7 @@ mod tests {
             let (stream, _) = listener.accept().await.unwrap();
             let processor = Arc::new(MockProcessor);
             let config = SecurityConfig::default();
-            let _ = handle_client(stream, processor, config).await;
+            let _ = handle_client(stream, processor, config, None, Default::default()).await;
         });
 
         // Give server time to start
@@ -408,6 +660,51 @@ mod tests {
         assert_eq!(resp.result.unwrap(), serde_json::json!({"msg": "hello"}));
     }
 
+    struct Ratio;
+
+    #[async_trait::async_trait]
+    impl crate::TypedJsonRPCMethod for Ratio {
+        type Params = ();
+        type Output = f64;
+
+        fn method_name(&self) -> &'static str {
+            "ratio"
+        }
+
+        async fn call(&self, _params: ()) -> Result<f64, crate::Error> {
+            Ok(f64::NAN)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tcp_server_non_finite_policy() {
+        use crate::serialization::{JsonFormat, NonFiniteFloats};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let format = JsonFormat::compact().non_finite(NonFiniteFloats::Error);
+            let (stream, _) = listener.accept().await.unwrap();
+            let processor = Arc::new(crate::MethodRegistry::new(vec![Box::new(Ratio)]));
+            let config = SecurityConfig::default();
+            let _ = handle_client(stream, processor, config, None, format).await;
+        });
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let request = Request::new("ratio").with_id(serde_json::json!(1));
+        let request_json = serde_json::to_string(&Message::Request(request)).unwrap();
+        client.write_all(request_json.as_bytes()).await.unwrap();
+        client.write_all(b"\n").await.unwrap();
+
+        let mut response = String::new();
+        BufReader::new(client)
+            .read_line(&mut response)
+            .await
+            .unwrap();
+        let resp: Response = serde_json::from_str(&response).unwrap();
+        assert_eq!(resp.error.unwrap().code, error_codes::INTERNAL_ERROR);
+    }
+
     #[tokio::test]
     async fn test_tcp_server_error_response() {
         let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
@@ -417,7 +714,7 @@ mod tests {
             let (stream, _) = listener.accept().await.unwrap();
             let processor = Arc::new(MockProcessor);
             let config = SecurityConfig::default();
-            let _ = handle_client(stream, processor, config).await;
+            let _ = handle_client(stream, processor, config, None, Default::default()).await;
         });
 
         tokio::time::sleep(Duration::from_millis(10)).await;
@@ -449,7 +746,7 @@ mod tests {
             let (stream, _) = listener.accept().await.unwrap();
             let processor = Arc::new(MockProcessor);
             let config = SecurityConfig::default();
-            let _ = handle_client(stream, processor, config).await;
+            let _ = handle_client(stream, processor, config, None, Default::default()).await;
         });
 
         tokio::time::sleep(Duration::from_millis(10)).await;
@@ -478,7 +775,7 @@ mod tests {
             let (stream, _) = listener.accept().await.unwrap();
             let processor = Arc::new(MockProcessor);
             let config = SecurityConfig::default();
-            let _ = handle_client(stream, processor, config).await;
+            let _ = handle_client(stream, processor, config, None, Default::default()).await;
         });
 
         tokio::time::sleep(Duration::from_millis(10)).await;
@@ -503,7 +800,7 @@ mod tests {
             let (stream, _) = listener.accept().await.unwrap();
             let processor = Arc::new(MockProcessor);
             let config = SecurityConfig::default();
-            let _ = handle_client(stream, processor, config).await;
+            let _ = handle_client(stream, processor, config, None, Default::default()).await;
         });
 
         tokio::time::sleep(Duration::from_millis(10)).await;
@@ -534,6 +831,7 @@ mod tests {
             max_request_size: 50, // Very small limit
             request_timeout: Duration::from_secs(5),
             idle_timeout: Duration::from_secs(60),
+            ..Default::default()
         };
 
         let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
@@ -542,7 +840,7 @@ mod tests {
         tokio::spawn(async move {
             let (stream, _) = listener.accept().await.unwrap();
             let processor = Arc::new(MockProcessor);
-            let _ = handle_client(stream, processor, config).await;
+            let _ = handle_client(stream, processor, config, None, Default::default()).await;
         });
 
         tokio::time::sleep(Duration::from_millis(10)).await;
@@ -567,6 +865,80 @@ mod tests {
         assert!(error.message.contains("size limit exceeded"));
     }
 
+    #[tokio::test]
+    async fn test_tcp_server_json_depth_limit() {
+        let config = SecurityConfig::default().with_max_json_depth(4);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let processor = Arc::new(MockProcessor);
+            let _ = handle_client(stream, processor, config, None, Default::default()).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let client = TcpStream::connect(addr).await.unwrap();
+        let mut reader = BufReader::new(client);
+        let nested = r#"{"jsonrpc":"2.0","method":"echo","params":[[[[1]]]],"id":1}"#;
+        reader.write_all(nested.as_bytes()).await.unwrap();
+        reader.write_all(b"\n").await.unwrap();
+
+        let mut response = String::new();
+        reader.read_line(&mut response).await.unwrap();
+        let resp: Response = serde_json::from_str(&response).unwrap();
+        assert_eq!(resp.error.unwrap().code, error_codes::INVALID_REQUEST);
+
+        // the connection stays usable for well-formed requests
+        let flat = r#"{"jsonrpc":"2.0","method":"echo","params":[1],"id":2}"#;
+        reader.write_all(flat.as_bytes()).await.unwrap();
+        reader.write_all(b"\n").await.unwrap();
+
+        response.clear();
+        reader.read_line(&mut response).await.unwrap();
+        let resp: Response = serde_json::from_str(&response).unwrap();
+        assert!(resp.error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tcp_connection_recycled_after_max_requests() {
+        let config = SecurityConfig::default().with_max_requests_per_connection(1);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let processor = Arc::new(MockProcessor);
+            let _ = handle_client(stream, processor, config, None, Default::default()).await;
+        });
+
+        let client = TcpStream::connect(addr).await.unwrap();
+        let mut reader = BufReader::new(client);
+        let request = r#"{"jsonrpc":"2.0","method":"echo","params":[1],"id":1}"#;
+        reader.write_all(request.as_bytes()).await.unwrap();
+        reader.write_all(b"\n").await.unwrap();
+
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        let resp: Response = serde_json::from_str(&line).unwrap();
+        assert_eq!(resp.result, Some(serde_json::json!([1])));
+
+        line.clear();
+        reader.read_line(&mut line).await.unwrap();
+        let notification: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            notification["method"],
+            super::super::lifetime::CONNECTION_CLOSING_METHOD
+        );
+        assert_eq!(notification["params"]["reason"], "max_requests");
+
+        line.clear();
+        assert_eq!(reader.read_line(&mut line).await.unwrap(), 0);
+    }
+
     #[tokio::test]
     async fn test_tcp_server_request_timeout() {
         let config = SecurityConfig {
@@ -574,6 +946,7 @@ mod tests {
             max_request_size: 1024 * 1024,
             request_timeout: Duration::from_millis(100), // Very short timeout
             idle_timeout: Duration::from_secs(60),
+            ..Default::default()
         };
 
         let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
@@ -582,7 +955,7 @@ mod tests {
         tokio::spawn(async move {
             let (stream, _) = listener.accept().await.unwrap();
             let processor = Arc::new(MockProcessor);
-            let _ = handle_client(stream, processor, config).await;
+            let _ = handle_client(stream, processor, config, None, Default::default()).await;
         });
 
         tokio::time::sleep(Duration::from_millis(10)).await;
@@ -607,7 +980,7 @@ mod tests {
             let (stream, _) = listener.accept().await.unwrap();
             let processor = Arc::new(MockProcessor);
             let config = SecurityConfig::default();
-            let _ = handle_client(stream, processor, config).await;
+            let _ = handle_client(stream, processor, config, None, Default::default()).await;
         });
 
         tokio::time::sleep(Duration::from_millis(10)).await;
@@ -638,7 +1011,7 @@ mod tests {
             let (stream, _) = listener.accept().await.unwrap();
             let processor = Arc::new(MockProcessor);
             let config = SecurityConfig::default();
-            let _ = handle_client(stream, processor, config).await;
+            let _ = handle_client(stream, processor, config, None, Default::default()).await;
         });
 
         tokio::time::sleep(Duration::from_millis(10)).await;
@@ -692,6 +1065,7 @@ mod tests {
             max_request_size: 0, // Zero means no limit
             request_timeout: Duration::from_secs(5),
             idle_timeout: Duration::from_secs(60),
+            ..Default::default()
         };
 
         let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
@@ -700,7 +1074,7 @@ mod tests {
         tokio::spawn(async move {
             let (stream, _) = listener.accept().await.unwrap();
             let processor = Arc::new(MockProcessor);
-            let _ = handle_client(stream, processor, config).await;
+            let _ = handle_client(stream, processor, config, None, Default::default()).await;
         });
 
         tokio::time::sleep(Duration::from_millis(10)).await;
@@ -1,19 +1,46 @@
 //! TCP streaming transport implementation for JSON-RPC servers.
 //!
 //! Streaming TCP server for persistent connections with multiple requests per connection.
+//!
+//! With the `streaming` feature, a server given a
+//! [`StreamManager`](crate::streaming::StreamManager) through
+//! [`TcpStreamServerBuilder::streams`] also accepts subscription requests
+//! for the manager's methods and writes their
+//! [`StreamEvent`](crate::streaming::StreamEvent)s as lines to the
+//! connection that subscribed. Streams still open when a connection goes
+//! away are unsubscribed, and a connection can only unsubscribe its own
+//! streams. A manager must be served by a single server, which takes over
+//! its event queue.
 
 use super::security::SecurityConfig;
 use crate::{Message, MessageProcessor};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::net::{TcpListener, TcpStream};
+use std::time::Duration;
+use tokio::io::{AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
 use tokio::sync::mpsc;
 
 pub struct TcpStreamServerBuilder {
     addr: String,
     processor: Option<Arc<dyn MessageProcessor + Send + Sync>>,
     security_config: SecurityConfig,
+    supervision: super::supervisor::SupervisionPolicy,
+    socket_options: super::socket::SocketOptions,
+    backlog: Option<u32>,
+    accept_rate: Option<super::accept::AcceptRateLimit>,
+    name: Option<String>,
+    method_filter: super::listener::MethodFilter,
+    json_format: crate::serialization::JsonFormat,
+    handoff: Option<super::handoff::HandoffSlot>,
+    handshake: Option<super::handshake::AuthHandshake>,
+    hello: Option<super::hello::ServerHello>,
+    pipelining: super::pipeline::Pipelining,
+    framing: super::framing::Framing,
+    codec: Arc<dyn crate::codec::Codec>,
+    tap: Option<Arc<super::tap::WireTap>>,
+    #[cfg(feature = "streaming")]
+    streams: Option<Arc<crate::streaming::StreamManager>>,
 }
 
 impl TcpStreamServerBuilder {
@@ -22,6 +49,22 @@ impl TcpStreamServerBuilder {
             addr: addr.into(),
             processor: None,
             security_config: SecurityConfig::default(),
+            supervision: super::supervisor::SupervisionPolicy::default(),
+            socket_options: super::socket::SocketOptions::default(),
+            backlog: None,
+            accept_rate: None,
+            name: None,
+            method_filter: super::listener::MethodFilter::default(),
+            json_format: crate::serialization::JsonFormat::default(),
+            handoff: None,
+            handshake: None,
+            hello: None,
+            pipelining: super::pipeline::Pipelining::default(),
+            framing: super::framing::Framing::default(),
+            codec: Arc::new(crate::codec::JsonCodec),
+            tap: None,
+            #[cfg(feature = "streaming")]
+            streams: None,
         }
     }
 
@@ -53,15 +96,150 @@ impl TcpStreamServerBuilder {
         self
     }
 
+    /// How to recover from failing `accept()` calls
+    pub fn supervision(mut self, policy: super::supervisor::SupervisionPolicy) -> Self {
+        self.supervision = policy;
+        self
+    }
+
+    /// TCP options applied to every accepted connection
+    pub fn socket_options(mut self, options: super::socket::SocketOptions) -> Self {
+        self.socket_options = options;
+        self
+    }
+
+    /// Listen backlog passed to `listen(2)` instead of the OS default
+    pub fn backlog(mut self, backlog: u32) -> Self {
+        self.backlog = Some(backlog);
+        self
+    }
+
+    /// Pace how quickly accepted connections are handled
+    pub fn accept_rate(mut self, limit: super::accept::AcceptRateLimit) -> Self {
+        self.accept_rate = Some(limit);
+        self
+    }
+
+    /// Name this listener; the name is exposed as `ConnectionContext::origin`
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Restrict which methods may be called through this listener
+    pub fn method_filter(mut self, filter: super::listener::MethodFilter) -> Self {
+        self.method_filter = filter;
+        self
+    }
+
+    /// Output format handlers convert results with; frames are always
+    /// written compact
+    pub fn json_format(mut self, format: crate::serialization::JsonFormat) -> Self {
+        self.json_format = format;
+        self
+    }
+
+    /// Take part in `handoff` as the listener called `name`, so a restarted
+    /// process can take over the listening socket
+    pub fn handoff(
+        mut self,
+        handoff: Arc<super::handoff::ListenerHandoff>,
+        name: impl Into<String>,
+    ) -> Self {
+        self.handoff = Some(super::handoff::HandoffSlot {
+            handoff,
+            name: name.into(),
+        });
+        self
+    }
+
+    /// Require `rpc.authenticate` before any other call on a connection
+    pub fn authentication(mut self, handshake: super::handshake::AuthHandshake) -> Self {
+        self.handshake = Some(handshake);
+        self
+    }
+
+    /// Greet every connection with `hello` before reading from it
+    pub fn hello(mut self, hello: super::hello::ServerHello) -> Self {
+        self.hello = Some(hello);
+        self
+    }
+
+    /// Process several requests of a connection concurrently
+    pub fn pipelining(mut self, pipelining: super::pipeline::Pipelining) -> Self {
+        self.pipelining = pipelining;
+        self
+    }
+
+    /// How messages are delimited, newlines by default
+    pub fn framing(mut self, framing: super::framing::Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Encoding of message payloads, JSON by default
+    ///
+    /// Binary codecs need [`Framing::ContentLength`](super::framing::Framing).
+    pub fn codec(mut self, codec: impl crate::codec::Codec) -> Self {
+        self.codec = Arc::new(codec);
+        self
+    }
+
+    /// Mirror the frames of sampled connections to `tap`
+    pub fn tap(mut self, tap: super::tap::WireTap) -> Self {
+        self.tap = Some(Arc::new(tap));
+        self
+    }
+
+    /// Serve subscriptions of `manager` and push its events to subscribers
+    #[cfg(feature = "streaming")]
+    pub fn streams(mut self, manager: Arc<crate::streaming::StreamManager>) -> Self {
+        self.streams = Some(manager);
+        self
+    }
+
+    /// Validate the configuration without binding a listener for real.
+    ///
+    /// Checks that a processor is set, the address can be bound and the
+    /// security limits are sane.
+    pub fn validate(&self) -> super::validation::ConfigReport {
+        super::validation::check_server(
+            self.processor.is_some(),
+            &self.addr,
+            &self.security_config,
+            |report| super::validation::check_wire(self.framing, &self.codec, report),
+        )
+    }
+
     pub fn build(self) -> Result<TcpStreamServer, std::io::Error> {
         let processor = self.processor.ok_or_else(|| {
             std::io::Error::new(std::io::ErrorKind::InvalidInput, "Processor not set")
         })?;
+        let processor = super::listener::ListenerProcessor::wrap(
+            processor,
+            self.name.clone(),
+            self.method_filter,
+        );
+        let wire = super::framing::Wire::new(self.framing, self.codec)?;
 
         Ok(TcpStreamServer {
             addr: self.addr,
+            name: self.name,
+            json_format: self.json_format,
             processor,
-            security_config: self.security_config,
+            security_config: crate::reload::Reloadable::new(self.security_config),
+            supervision: self.supervision,
+            socket_options: self.socket_options,
+            backlog: self.backlog,
+            handoff: self.handoff,
+            accept_rate: self.accept_rate,
+            handshake: self.handshake,
+            hello: self.hello,
+            pipelining: self.pipelining,
+            wire,
+            tap: self.tap,
+            #[cfg(feature = "streaming")]
+            streams: self.streams,
             active_connections: Arc::new(AtomicUsize::new(0)),
         })
     }
@@ -69,89 +247,260 @@ impl TcpStreamServerBuilder {
 
 pub struct TcpStreamServer {
     addr: String,
+    name: Option<String>,
+    json_format: crate::serialization::JsonFormat,
     processor: Arc<dyn MessageProcessor + Send + Sync>,
-    security_config: SecurityConfig,
+    security_config: crate::reload::Reloadable<SecurityConfig>,
+    supervision: super::supervisor::SupervisionPolicy,
+    socket_options: super::socket::SocketOptions,
+    backlog: Option<u32>,
+    handoff: Option<super::handoff::HandoffSlot>,
+    accept_rate: Option<super::accept::AcceptRateLimit>,
+    handshake: Option<super::handshake::AuthHandshake>,
+    hello: Option<super::hello::ServerHello>,
+    pipelining: super::pipeline::Pipelining,
+    wire: super::framing::Wire,
+    tap: Option<Arc<super::tap::WireTap>>,
+    #[cfg(feature = "streaming")]
+    streams: Option<Arc<crate::streaming::StreamManager>>,
     active_connections: Arc<AtomicUsize>,
 }
 
+/// An accepted connection and the context its requests run under
+struct Accepted {
+    stream: TcpStream,
+    connection: crate::auth::ConnectionContext,
+}
+
+impl From<TcpStream> for Accepted {
+    fn from(stream: TcpStream) -> Self {
+        let connection = stream
+            .peer_addr()
+            .map(crate::auth::ConnectionContext::with_addr)
+            .unwrap_or_default();
+        Self { stream, connection }
+    }
+}
+
+/// Subscription routing of a running server
+#[derive(Clone, Default)]
+struct Streams {
+    #[cfg(feature = "streaming")]
+    router: Option<Arc<super::stream_router::StreamRouter<String>>>,
+}
+
 impl TcpStreamServer {
     pub fn builder(addr: impl Into<String>) -> TcpStreamServerBuilder {
         TcpStreamServerBuilder::new(addr)
     }
 
+    /// Handle to the live security configuration.
+    ///
+    /// Storing a new config affects connections accepted afterwards; see
+    /// [`ConfigReloader::security`](crate::reload::ConfigReloader::security).
+    pub fn security_handle(&self) -> crate::reload::Reloadable<SecurityConfig> {
+        self.security_config.clone()
+    }
+
     pub async fn run(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let listener = TcpListener::bind(&self.addr).await?;
+        let (listener, mut handed_off) =
+            super::handoff::listen(self.handoff.as_ref(), &self.addr, self.backlog).await?;
+        let initial = self.security_config.load();
+        let id = super::listener::ListenerId::new(
+            super::transport_stats::TCP_STREAM,
+            listener.local_addr().ok(),
+        )
+        .name(self.name.clone());
         tracing::info!(
             addr = %self.addr,
+            listener = %id,
             protocol = "tcp-stream",
-            max_connections = self.security_config.max_connections,
-            max_request_size = self.security_config.max_request_size,
+            max_connections = initial.max_connections,
+            max_request_size = initial.max_request_size,
             "server listening"
         );
 
+        let streams = Streams {
+            #[cfg(feature = "streaming")]
+            router: self
+                .streams
+                .clone()
+                .map(super::stream_router::StreamRouter::start),
+        };
+
+        let mut supervisor = super::supervisor::AcceptSupervisor::new(self.supervision.clone());
+        let mut pacer = super::accept::AcceptPacer::new(self.accept_rate.clone());
+        let transport = super::transport_stats::listener(&id);
         loop {
-            let (stream, addr) = listener.accept().await?;
+            let Some(accepted) = super::handoff::accept(&listener, handed_off.as_mut()).await
+            else {
+                super::handoff::drain(self.handoff.as_ref(), &self.active_connections).await;
+                return Ok(());
+            };
+            let (stream, addr) = match accepted {
+                Ok(accepted) => {
+                    supervisor.on_success();
+                    accepted
+                }
+                Err(e) => {
+                    supervisor.on_error(e).await?;
+                    continue;
+                }
+            };
 
             let current_connections = self.active_connections.load(Ordering::Relaxed);
+            let security_config = self.security_config.load();
 
             // Check connection limit
-            if self.security_config.max_connections > 0
-                && current_connections >= self.security_config.max_connections
+            if security_config.max_connections > 0
+                && current_connections >= security_config.max_connections
             {
-                tracing::warn!(
-                    remote_addr = %addr,
-                    active_connections = current_connections,
-                    max_connections = self.security_config.max_connections,
-                    "connection limit reached, rejecting connection"
+                crate::rejection::record(
+                    crate::rejection::Rejection::new(
+                        crate::rejection::RejectionReason::ConnectionLimit,
+                    )
+                    .remote_addr(Some(addr))
+                    .detail(format!(
+                        "{current_connections} of {} connections in use",
+                        security_config.max_connections
+                    )),
                 );
+                transport.rejected();
                 drop(stream);
                 continue;
             }
 
+            let Some(delay) = pacer.admit(addr) else {
+                transport.rejected();
+                drop(stream);
+                continue;
+            };
+
+            self.socket_options.apply_accepted(&stream, addr);
             self.active_connections.fetch_add(1, Ordering::Relaxed);
             tracing::debug!(remote_addr = %addr, active_connections = current_connections + 1, "new connection");
 
             let processor = Arc::clone(&self.processor);
-            let security_config = self.security_config.clone();
+            let opening = super::hello::Opening {
+                handshake: self.handshake.clone(),
+                hello: self.hello.clone(),
+            };
+            let pipelining = self.pipelining;
+            let tap = self
+                .tap
+                .as_ref()
+                .and_then(|tap| tap.connection(super::transport_stats::TCP_STREAM, Some(addr)));
+            let wire = self.wire.clone().tapped(tap);
+            let streams = streams.clone();
+            let security_config = SecurityConfig::clone(&security_config);
             let active_connections = Arc::clone(&self.active_connections);
+            let active = transport.accepted();
+            let mut accepted = Accepted::from(stream);
+            accepted.connection = accepted.connection.with_listener(id.clone());
+            if self.json_format != crate::serialization::JsonFormat::default() {
+                accepted.connection = accepted.connection.with_json_format(self.json_format);
+            }
+            let span = tracing::info_span!("connection", listener = %id, remote_addr = %addr);
 
-            tokio::spawn(async move {
-                let result = handle_stream_client(stream, processor, security_config).await;
+            let connection = async move {
+                if !delay.is_zero() {
+                    tokio::time::sleep(delay).await;
+                }
+                let result = handle_stream_client(
+                    accepted,
+                    processor,
+                    security_config,
+                    opening,
+                    pipelining,
+                    wire,
+                    streams,
+                )
+                .await;
                 active_connections.fetch_sub(1, Ordering::Relaxed);
+                drop(active);
 
                 if let Err(e) = result {
                     tracing::error!(remote_addr = %addr, error = %e, "client handler failed");
                 }
-            });
+            };
+            tokio::spawn(tracing::Instrument::instrument(connection, span));
         }
     }
 }
 
 async fn handle_stream_client(
-    stream: TcpStream,
+    accepted: Accepted,
     processor: Arc<dyn MessageProcessor + Send + Sync>,
-    _security_config: SecurityConfig,
+    security_config: SecurityConfig,
+    opening: super::hello::Opening,
+    pipelining: super::pipeline::Pipelining,
+    wire: super::framing::Wire,
+    #[cfg_attr(not(feature = "streaming"), allow(unused_variables))] streams: Streams,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let Accepted { stream, connection } = accepted;
+    let remote_addr = connection.remote_addr;
+    let transport = connection.listener().map_or_else(
+        || super::transport_stats::transport(super::transport_stats::TCP_STREAM),
+        super::transport_stats::listener,
+    );
+    let hello = opening.hello_frame(&*processor);
+    let (gate, processor) = super::connection::bind(processor, opening.handshake, connection);
     let (reader, writer) = stream.into_split();
-    let mut reader = BufReader::new(reader);
+    let mut reader = BufReader::new(super::transport_stats::Metered::new(
+        reader,
+        Arc::clone(&transport),
+    ));
+    let writer = super::transport_stats::Metered::new(writer, transport);
     let (tx, mut rx) = mpsc::channel::<String>(100);
 
-    tokio::spawn(async move {
+    let codec = super::codec_stats::negotiated(wire.codec_name());
+    let reads = wire.clone();
+    let writer_task = tokio::spawn(async move {
         let mut writer = writer;
         while let Some(response) = rx.recv().await {
-            if writer.write_all(response.as_bytes()).await.is_err()
-                || writer.write_all(b"\n").await.is_err()
+            if wire.write_frame(&mut writer, &response).await.is_err()
                 || writer.flush().await.is_err()
             {
                 break;
             }
         }
+        // a FIN, or close_notify under TLS, once everything is written
+        let _ = writer.shutdown().await;
     });
 
+    if let Some(hello) = hello {
+        tx.send(hello).await?;
+    }
+    #[cfg(feature = "streaming")]
+    let (events, mut owned) = (
+        tx.clone(),
+        streams.router.map(super::stream_router::OwnedStreams::new),
+    );
+    let pipeline = super::pipeline::ResponsePipeline::new(tx, pipelining);
     let mut line = String::new();
+    let mut budget = super::lifetime::ConnectionBudget::new(&security_config);
     loop {
-        line.clear();
-        let bytes_read = reader.read_line(&mut line).await?;
+        if let Some(reason) = budget.exhausted() {
+            if let Some(notification) = budget.recycle(reason, remote_addr) {
+                let _ = pipeline.send(notification).await;
+            }
+            break;
+        }
+
+        let frame = reads.read_frame(&mut reader, &mut line, security_config.max_request_size);
+        let Some(read) = budget.guard(frame).await else {
+            continue;
+        };
+        if let Err(e) = &read
+            && let Some(reply) = super::framing::decode_failure(e)
+        {
+            codec.observe(false);
+            if pipeline.send(reply).await.is_err() {
+                break;
+            }
+            continue;
+        }
+        let bytes_read = read?;
 
         if bytes_read == 0 {
             break;
@@ -162,17 +511,69 @@ async fn handle_stream_client(
             continue;
         }
 
-        match serde_json::from_str::<Message>(line_content) {
-            Ok(message) => {
-                if let Some(response) = processor.process_message(message).await
-                    && let Ok(response_json) = serde_json::to_string(&response)
-                    && tx.send(response_json).await.is_err()
+        if let Err(e) = security_config.check_json_limits(line_content) {
+            crate::rejection::record(
+                crate::rejection::Rejection::new(crate::rejection::RejectionReason::JsonTooComplex)
+                    .remote_addr(remote_addr)
+                    .detail(e.to_string()),
+            );
+            let error_response = crate::Response::error(
+                crate::ErrorBuilder::new(crate::error_codes::INVALID_REQUEST, e.to_string())
+                    .build(),
+                None,
+            );
+            let response_json = serde_json::to_string(&error_response)?;
+            if pipeline.send(response_json).await.is_err() {
+                break;
+            }
+            continue;
+        }
+
+        budget.on_request();
+        #[cfg(feature = "streaming")]
+        if let Some(owned) = &mut owned
+            && gate.as_ref().is_none_or(|gate| gate.is_authenticated())
+            && let Some(reply) = owned
+                .handle(line_content, processor.as_ref(), &events)
+                .await
+        {
+            if pipeline.send(reply).await.is_err() {
+                break;
+            }
+            continue;
+        }
+
+        let prepared = crate::borrowed::prepare(line_content, processor.as_ref());
+        codec.observe(prepared.is_ok());
+        match prepared {
+            Ok(crate::borrowed::Prepared::Cached(response_json)) => {
+                if pipeline.send(response_json).await.is_err() {
+                    break;
+                }
+            }
+            Ok(crate::borrowed::Prepared::Batch(entries)) => {
+                if let Some(response_json) =
+                    super::batch::respond(processor.as_ref(), entries, remote_addr).await?
+                    && pipeline.send(response_json).await.is_err()
                 {
                     break;
                 }
             }
+            Ok(crate::borrowed::Prepared::Message(message)) => {
+                let inline = gate.as_ref().is_some_and(|gate| !gate.is_authenticated());
+                if pipeline.process(&processor, message, inline).await.is_err() {
+                    break;
+                }
+                if gate.as_ref().is_some_and(|gate| gate.should_close()) {
+                    break;
+                }
+            }
             Err(e) => {
-                tracing::debug!(error = %e, "json-rpc parse failed");
+                crate::rejection::record(
+                    crate::rejection::Rejection::new(crate::rejection::RejectionReason::ParseError)
+                        .remote_addr(remote_addr)
+                        .detail(e.to_string()),
+                );
                 let error_response = crate::ResponseBuilder::new()
                     .error(
                         crate::ErrorBuilder::new(
@@ -185,95 +586,601 @@ async fn handle_stream_client(
                     .build();
 
                 let response_json = serde_json::to_string(&error_response)?;
-                if tx.send(response_json).await.is_err() {
+                if pipeline.send(response_json).await.is_err() {
                     break;
                 }
             }
         }
     }
 
+    #[cfg(feature = "streaming")]
+    {
+        if let Some(owned) = owned {
+            owned.release().await;
+        }
+        drop(events);
+    }
+    // let in-flight and queued responses and the closing notification reach
+    // the client
+    drop(pipeline);
+    super::lifetime::drain(writer_task, &security_config, remote_addr).await;
     Ok(())
 }
 
 pub struct TcpStreamClientBuilder {
     addr: String,
+    socket_options: super::socket::SocketOptions,
+    framing: super::framing::Framing,
+    codec: Arc<dyn crate::codec::Codec>,
+    tap: Option<super::tap::WireTap>,
+    keepalive_ping: Option<(String, Duration)>,
+    reconnect: Option<super::reconnect::ReconnectPolicy>,
 }
 
 impl TcpStreamClientBuilder {
     pub fn new(addr: impl Into<String>) -> Self {
-        Self { addr: addr.into() }
+        Self {
+            addr: addr.into(),
+            socket_options: super::socket::SocketOptions::default(),
+            framing: super::framing::Framing::default(),
+            codec: Arc::new(crate::codec::JsonCodec),
+            tap: None,
+            keepalive_ping: None,
+            reconnect: None,
+        }
+    }
+
+    /// TCP options for the client connection
+    pub fn socket_options(mut self, options: super::socket::SocketOptions) -> Self {
+        self.socket_options = options;
+        self
+    }
+
+    /// Enable TCP keepalive, probing after `time` of idleness
+    pub fn tcp_keepalive(mut self, time: Duration) -> Self {
+        self.socket_options = self.socket_options.keepalive(time);
+        self
+    }
+
+    /// Send a `method` notification whenever nothing was written for
+    /// `interval`
+    ///
+    /// Writing to a connection the server dropped fails, so the ping finds
+    /// dead connections while the client is idle. The server should accept
+    /// the notification without answering, e.g. by ignoring the method.
+    pub fn keepalive_ping(mut self, method: impl Into<String>, interval: Duration) -> Self {
+        self.keepalive_ping = Some((method.into(), interval));
+        self
+    }
+
+    /// Reconnect after losing the connection
+    pub fn reconnect(mut self, policy: super::reconnect::ReconnectPolicy) -> Self {
+        self.reconnect = Some(policy);
+        self
+    }
+
+    /// How messages are delimited, must match the server
+    pub fn framing(mut self, framing: super::framing::Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Encoding of message payloads, must match the server
+    pub fn codec(mut self, codec: impl crate::codec::Codec) -> Self {
+        self.codec = Arc::new(codec);
+        self
+    }
+
+    /// Mirror the connection's frames to `tap`, if sampled
+    pub fn tap(mut self, tap: super::tap::WireTap) -> Self {
+        self.tap = Some(tap);
+        self
     }
 
     pub async fn connect(self) -> Result<TcpStreamClient, Box<dyn std::error::Error>> {
+        let connector = Connector {
+            addr: self.addr,
+            socket_options: self.socket_options,
+            wire: super::framing::Wire::new(self.framing, self.codec)?,
+            tap: self.tap.map(Arc::new),
+        };
+        let (stream, wire) = connector.connect().await?;
+        let ping = match self.keepalive_ping {
+            Some((method, interval)) => Some((
+                serde_json::to_string(&crate::NotificationBuilder::new(method).build())?,
+                interval,
+            )),
+            None => None,
+        };
+        Ok(TcpStreamClient::new(
+            Driver {
+                connector,
+                ping,
+                reconnect: self.reconnect,
+                handlers: Arc::default(),
+                #[cfg(feature = "streaming")]
+                routes: Arc::default(),
+                losses: Arc::default(),
+                hello: Arc::new(tokio::sync::watch::channel(None).0),
+            },
+            stream,
+            wire,
+        ))
+    }
+}
+
+/// Opens the client's connections
+struct Connector {
+    addr: String,
+    socket_options: super::socket::SocketOptions,
+    wire: super::framing::Wire,
+    tap: Option<Arc<super::tap::WireTap>>,
+}
+
+impl Connector {
+    async fn connect(&self) -> std::io::Result<(TcpStream, super::framing::Wire)> {
         let stream = TcpStream::connect(&self.addr).await?;
-        Ok(TcpStreamClient::new(stream))
+        self.socket_options.apply(&stream)?;
+        let tap = self.tap.as_ref().and_then(|tap| {
+            tap.connection(super::transport_stats::TCP_STREAM, stream.peer_addr().ok())
+        });
+        Ok((stream, self.wire.clone().tapped(tap)))
     }
 }
 
-pub struct TcpStreamClient {
-    tx: mpsc::Sender<String>,
-    rx: mpsc::Receiver<String>,
+/// What the client's connection hands to the client
+enum Inbound {
+    Frame(String),
+    /// The connection was lost, the number of losses so far; the client
+    /// reconnects
+    Lost(u64),
 }
 
-impl TcpStreamClient {
-    fn new(stream: TcpStream) -> Self {
-        let (reader, writer) = stream.into_split();
-        let mut reader = BufReader::new(reader);
-        let (write_tx, mut write_rx) = mpsc::channel::<String>(100);
-        let (read_tx, read_rx) = mpsc::channel::<String>(100);
+/// Moves the client's messages over its connection, and reconnects
+struct Driver {
+    connector: Connector,
+    /// Encoded keepalive notification, and the idle time before sending it
+    ping: Option<(String, Duration)>,
+    reconnect: Option<super::reconnect::ReconnectPolicy>,
+    handlers: Arc<NotificationHandlers>,
+    #[cfg(feature = "streaming")]
+    routes: Arc<super::subscription::SubscriptionRoutes>,
+    losses: Arc<std::sync::atomic::AtomicU64>,
+    hello: Arc<tokio::sync::watch::Sender<Option<super::hello::ServerHello>>>,
+}
 
-        tokio::spawn(async move {
-            let mut writer = writer;
-            while let Some(message) = write_rx.recv().await {
-                if writer.write_all(message.as_bytes()).await.is_err() {
-                    break;
+impl Driver {
+    async fn run(
+        self,
+        mut stream: TcpStream,
+        mut wire: super::framing::Wire,
+        mut outbound: mpsc::Receiver<String>,
+        inbound: mpsc::Sender<Inbound>,
+        state: tokio::sync::watch::Sender<super::reconnect::ConnectionState>,
+    ) {
+        use super::reconnect::ConnectionState;
+
+        loop {
+            let client_alive = self.serve(stream, wire, &mut outbound, &inbound).await;
+            // streams die with the connection that opened them
+            #[cfg(feature = "streaming")]
+            self.routes.close_all();
+            let Some(policy) = self.reconnect.as_ref().filter(|_| client_alive) else {
+                break;
+            };
+            self.hello.send_replace(None);
+            let losses = self.losses.fetch_add(1, Ordering::SeqCst) + 1;
+            tracing::warn!(addr = %self.connector.addr, "connection lost, reconnecting");
+            if inbound.send(Inbound::Lost(losses)).await.is_err() {
+                break;
+            }
+
+            let mut attempt = 0;
+            let connection = loop {
+                attempt += 1;
+                state.send_replace(ConnectionState::Reconnecting { attempt });
+                tokio::time::sleep(policy.backoff(attempt)).await;
+                if inbound.is_closed() {
+                    break None;
                 }
-                if writer.write_all(b"\n").await.is_err() {
-                    break;
+                match self.connector.connect().await {
+                    Ok(connection) => break Some(connection),
+                    Err(e) if policy.gives_up_after(attempt) => {
+                        tracing::error!(addr = %self.connector.addr, error = %e, attempts = attempt, "giving up reconnecting");
+                        break None;
+                    }
+                    Err(e) => {
+                        tracing::debug!(addr = %self.connector.addr, error = %e, attempt, "reconnect failed");
+                    }
                 }
-                if writer.flush().await.is_err() {
-                    break;
+            };
+            let Some(connection) = connection else {
+                break;
+            };
+            tracing::info!(addr = %self.connector.addr, attempts = attempt, "reconnected");
+            (stream, wire) = connection;
+            state.send_replace(ConnectionState::Connected);
+        }
+        state.send_replace(ConnectionState::Disconnected);
+    }
+
+    /// Serve one connection until it is lost; false once the client is gone
+    async fn serve(
+        &self,
+        stream: TcpStream,
+        wire: super::framing::Wire,
+        outbound: &mut mpsc::Receiver<String>,
+        inbound: &mpsc::Sender<Inbound>,
+    ) -> bool {
+        let (reader, mut writer) = stream.into_split();
+        let mut reading = tokio::spawn(read_loop(
+            BufReader::new(reader),
+            wire.clone(),
+            Arc::clone(&self.handlers),
+            #[cfg(feature = "streaming")]
+            Arc::clone(&self.routes),
+            inbound.clone(),
+            Arc::clone(&self.hello),
+        ));
+        let mut ping = self.ping.as_ref().map(|(frame, interval)| {
+            let mut ticks =
+                tokio::time::interval_at(tokio::time::Instant::now() + *interval, *interval);
+            ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            (frame, ticks)
+        });
+
+        let client_alive = loop {
+            let next = std::future::poll_fn(|cx| {
+                if std::pin::Pin::new(&mut reading).poll(cx).is_ready() {
+                    return std::task::Poll::Ready(None);
+                }
+                if let Some((frame, ticks)) = &mut ping
+                    && ticks.poll_tick(cx).is_ready()
+                {
+                    return std::task::Poll::Ready(Some(Some((*frame).clone())));
                 }
+                outbound.poll_recv(cx).map(Some)
+            })
+            .await;
+            let message = match next {
+                Some(Some(message)) => message,
+                // the client and its subscriptions are gone
+                Some(None) => break false,
+                // read side closed, by the server or by the client going away
+                None => break !inbound.is_closed(),
+            };
+            if wire.write_frame(&mut writer, &message).await.is_err()
+                || writer.flush().await.is_err()
+            {
+                break true;
             }
-        });
+            if let Some((_, ticks)) = &mut ping {
+                ticks.reset();
+            }
+        };
+        reading.abort();
+        client_alive
+    }
+}
 
-        tokio::spawn(async move {
-            let mut line = String::new();
-            loop {
-                line.clear();
-                match reader.read_line(&mut line).await {
-                    Ok(0) => break,
-                    Ok(_) => {
-                        let line_content = line.trim();
-                        if !line_content.is_empty()
-                            && read_tx.send(line_content.to_string()).await.is_err()
-                        {
-                            break;
-                        }
-                    }
-                    Err(_) => break,
+async fn read_loop(
+    mut reader: BufReader<tokio::net::tcp::OwnedReadHalf>,
+    wire: super::framing::Wire,
+    handlers: Arc<NotificationHandlers>,
+    #[cfg(feature = "streaming")] routes: Arc<super::subscription::SubscriptionRoutes>,
+    inbound: mpsc::Sender<Inbound>,
+    hello: Arc<tokio::sync::watch::Sender<Option<super::hello::ServerHello>>>,
+) {
+    let mut line = String::new();
+    let mut first = true;
+    loop {
+        match wire.read_frame(&mut reader, &mut line, 0).await {
+            Ok(0) => break,
+            Ok(_) => {
+                let line_content = line.trim();
+                // servers greet before anything else
+                if std::mem::take(&mut first)
+                    && let Some(greeting) = super::hello::parse(line_content)
+                {
+                    hello.send_replace(Some(greeting));
+                    continue;
+                }
+                if line_content.is_empty() || handlers.dispatch(line_content) {
+                    continue;
+                }
+                #[cfg(feature = "streaming")]
+                if routes.dispatch(line_content) {
+                    continue;
+                }
+                if inbound
+                    .send(Inbound::Frame(line_content.to_string()))
+                    .await
+                    .is_err()
+                {
+                    break;
                 }
             }
-        });
+            Err(_) => break,
+        }
+    }
+}
+
+type NotificationCallback = Arc<dyn Fn(Option<serde_json::Value>) + Send + Sync>;
+
+/// Callbacks for server-pushed notifications, keyed by method
+#[derive(Default)]
+struct NotificationHandlers(
+    std::sync::RwLock<std::collections::HashMap<String, NotificationCallback>>,
+);
+
+impl NotificationHandlers {
+    /// Run the handler for `line` if it is a notification with one registered
+    fn dispatch(&self, line: &str) -> bool {
+        let handlers = self.0.read().unwrap_or_else(|e| e.into_inner());
+        if handlers.is_empty() {
+            return false;
+        }
+        let Ok(message) = serde_json::from_str::<Message>(line) else {
+            return false;
+        };
+        let handler = match (message.method(), message.id()) {
+            (Some(method), None) => handlers.get(method).cloned(),
+            _ => None,
+        };
+        drop(handlers);
+        let Some(handler) = handler else {
+            return false;
+        };
+        let params = match message {
+            Message::Request(request) => request.params,
+            Message::Notification(notification) => notification.params,
+            Message::Response(_) => None,
+        };
+        handler(params);
+        true
+    }
+}
+
+pub struct TcpStreamClient {
+    tx: mpsc::Sender<String>,
+    rx: mpsc::Receiver<Inbound>,
+    notifications: Arc<NotificationHandlers>,
+    #[cfg(feature = "streaming")]
+    subscriptions: Arc<super::subscription::SubscriptionRoutes>,
+    state: tokio::sync::watch::Receiver<super::reconnect::ConnectionState>,
+    losses: Arc<std::sync::atomic::AtomicU64>,
+    hello: tokio::sync::watch::Receiver<Option<super::hello::ServerHello>>,
+    next_id: u64,
+}
+
+impl TcpStreamClient {
+    fn new(driver: Driver, stream: TcpStream, wire: super::framing::Wire) -> Self {
+        let (write_tx, write_rx) = mpsc::channel::<String>(100);
+        let (read_tx, read_rx) = mpsc::channel::<Inbound>(100);
+        let (state_tx, state) =
+            tokio::sync::watch::channel(super::reconnect::ConnectionState::Connected);
+        let notifications = Arc::clone(&driver.handlers);
+        #[cfg(feature = "streaming")]
+        let subscriptions = Arc::clone(&driver.routes);
+        let losses = Arc::clone(&driver.losses);
+        let hello = driver.hello.subscribe();
+        tokio::spawn(driver.run(stream, wire, write_rx, read_tx, state_tx));
 
         Self {
             tx: write_tx,
             rx: read_rx,
+            notifications,
+            #[cfg(feature = "streaming")]
+            subscriptions,
+            state,
+            losses,
+            hello,
+            next_id: 1,
         }
     }
 
+    /// Watch the hello of the server, `None` until one arrives on the
+    /// current connection
+    pub fn server_hello(&self) -> tokio::sync::watch::Receiver<Option<super::hello::ServerHello>> {
+        self.hello.clone()
+    }
+
+    /// Watch the state of the connection
+    pub fn connection_state(
+        &self,
+    ) -> tokio::sync::watch::Receiver<super::reconnect::ConnectionState> {
+        self.state.clone()
+    }
+
+    /// Route notifications for `method` to `callback` instead of
+    /// [`recv_message`](Self::recv_message)
+    ///
+    /// Callbacks run on the connection's read loop, also while a
+    /// [`call`](Self::call) is waiting, so they should return quickly and
+    /// hand longer work to a task. Registering again replaces the callback.
+    pub fn on_notification<F>(&self, method: impl Into<String>, callback: F)
+    where
+        F: Fn(Option<serde_json::Value>) + Send + Sync + 'static,
+    {
+        self.notifications
+            .0
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(method.into(), Arc::new(callback));
+    }
+
+    /// Stop routing notifications for `method` to a callback
+    pub fn remove_notification_handler(&self, method: &str) {
+        self.notifications
+            .0
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(method);
+    }
+
     pub async fn send_message(&self, message: &Message) -> Result<(), Box<dyn std::error::Error>> {
         let json = serde_json::to_string(message)?;
         self.tx.send(json).await.map_err(|e| e.into())
     }
 
+    /// Next message from the server, `None` once the connection is closed
+    ///
+    /// Fails when the connection was lost and the client is reconnecting.
     pub async fn recv_message(&mut self) -> Result<Option<Message>, Box<dyn std::error::Error>> {
-        if let Some(response) = self.rx.recv().await {
-            let message: Message = serde_json::from_str(&response)?;
-            Ok(Some(message))
-        } else {
-            Ok(None)
+        self.recv_since(0).await
+    }
+
+    /// Next message, failing on connection losses beyond the first `losses`
+    async fn recv_since(
+        &mut self,
+        losses: u64,
+    ) -> Result<Option<Message>, Box<dyn std::error::Error>> {
+        loop {
+            match self.rx.recv().await {
+                Some(Inbound::Frame(frame)) => return Ok(Some(serde_json::from_str(&frame)?)),
+                Some(Inbound::Lost(lost)) if lost > losses => {
+                    return Err("connection lost".into());
+                }
+                // lost before the caller sent anything
+                Some(Inbound::Lost(_)) => continue,
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Call `method` and wait for its result
+    ///
+    /// Messages other than the matching response are discarded while
+    /// waiting; notifications with a registered callback still reach it.
+    pub async fn call(
+        &mut self,
+        method: &str,
+        params: Option<serde_json::Value>,
+    ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        self.call_raw(method, params).await
+    }
+
+    /// Send a request and wait for its result, skipping unrelated messages
+    async fn call_raw(
+        &mut self,
+        method: &str,
+        params: Option<serde_json::Value>,
+    ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        let id = serde_json::json!(self.next_id);
+        self.next_id += 1;
+
+        let mut request = crate::RequestBuilder::new(method).id(id.clone());
+        if let Some(params) = params {
+            request = request.params(params);
         }
+        let losses = self.losses.load(Ordering::SeqCst);
+        self.send_message(&Message::Request(request.build()))
+            .await?;
+
+        loop {
+            match self.recv_since(losses).await? {
+                Some(Message::Response(response)) if response.id.as_ref() == Some(&id) => {
+                    if let Some(error) = response.error {
+                        return Err(error.into());
+                    }
+                    return Ok(response.result.unwrap_or(serde_json::Value::Null));
+                }
+                Some(_) => continue,
+                None => return Err("connection closed before response".into()),
+            }
+        }
+    }
+
+    /// Open a stream of `method` on the server
+    ///
+    /// The stream's events are taken off the connection and yielded by the
+    /// returned [`Subscription`](super::subscription::Subscription) instead
+    /// of [`recv_message`](Self::recv_message). Other messages arriving
+    /// before the server confirms are discarded, as in [`call`](Self::call).
+    #[cfg(feature = "streaming")]
+    pub async fn subscribe(
+        &mut self,
+        method: &str,
+        params: Option<serde_json::Value>,
+    ) -> Result<super::subscription::Subscription, Box<dyn std::error::Error>> {
+        let id = serde_json::json!(self.next_id);
+        self.next_id += 1;
+
+        let mut request = crate::streaming::StreamRequest::new(method, id.clone());
+        if let Some(params) = params {
+            request = request.with_params(params);
+        }
+        let stream_id = request.stream_id();
+        let request = request.with_stream_id(stream_id.clone());
+        // route first, events may overtake the confirmation
+        let events = self.subscriptions.route(&stream_id);
+        let losses = self.losses.load(Ordering::SeqCst);
+        let outcome = match self.tx.send(serde_json::to_string(&request)?).await {
+            Ok(()) => loop {
+                match self.rx.recv().await {
+                    Some(Inbound::Frame(line)) => {
+                        if let Some(outcome) = super::subscription::subscribe_outcome(&line, &id) {
+                            break outcome;
+                        }
+                    }
+                    Some(Inbound::Lost(lost)) if lost > losses => {
+                        break Err("connection lost before response".into());
+                    }
+                    Some(Inbound::Lost(_)) => {}
+                    None => break Err("connection closed before response".into()),
+                }
+            },
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = outcome {
+            self.subscriptions.remove(&stream_id);
+            return Err(e);
+        }
+
+        let tx = self.tx.clone();
+        let send: super::subscription::SendText = Arc::new(move |text| {
+            let tx = tx.clone();
+            Box::pin(async move { tx.send(text).await.is_ok() })
+        });
+        Ok(super::subscription::Subscription::new(
+            stream_id,
+            events,
+            Arc::clone(&self.subscriptions),
+            send,
+        ))
+    }
+
+    /// Call `method` and check the raw result against `schema` before
+    /// deserializing it.
+    ///
+    /// A result that does not match fails with a
+    /// [`SchemaMismatch`](crate::schema::SchemaMismatch) listing every
+    /// offending path, which surfaces server drift more clearly than a serde
+    /// error on the first unexpected field.
+    pub async fn call_validated_with<T: serde::de::DeserializeOwned>(
+        &mut self,
+        method: &str,
+        params: Option<serde_json::Value>,
+        schema: &serde_json::Value,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        let result = self.call_raw(method, params).await?;
+        crate::schema::validate(&result, schema)?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// [`call_validated_with`](Self::call_validated_with) using the schema
+    /// `schemars` derives for `T`
+    #[cfg(feature = "schemars")]
+    pub async fn call_validated<T>(
+        &mut self,
+        method: &str,
+        params: Option<serde_json::Value>,
+    ) -> Result<T, Box<dyn std::error::Error>>
+    where
+        T: serde::de::DeserializeOwned + schemars::JsonSchema,
+    {
+        let schema = serde_json::to_value(schemars::schema_for!(T))?;
+        self.call_validated_with(method, params, &schema).await
     }
 }
 
@@ -281,6 +1188,8 @@ impl TcpStreamClient {
 mod tests {
     use super::*;
     use crate::{Message, RequestBuilder, Response, ResponseBuilder};
+    use tokio::io::AsyncBufReadExt;
+    use tokio::net::TcpListener;
 
     // Mock message processor for testing
     struct MockProcessor;
@@ -324,6 +1233,7 @@ mod tests {
             max_request_size: 1024,
             request_timeout: std::time::Duration::from_secs(30),
             idle_timeout: std::time::Duration::from_secs(60),
+            ..Default::default()
         };
         let builder =
             TcpStreamServerBuilder::new("127.0.0.1:8080").security_config(security_config.clone());
@@ -362,6 +1272,29 @@ mod tests {
         assert_eq!(server.addr, "127.0.0.1:8080");
     }
 
+    #[test]
+    fn test_tcp_stream_server_builder_listener_options() {
+        let builder = TcpStreamServerBuilder::new("127.0.0.1:8080")
+            .processor(MockProcessor)
+            .name("internal")
+            .method_filter(crate::transports::MethodFilter::new().deny("admin.*"));
+        assert_eq!(builder.name.as_deref(), Some("internal"));
+        assert!(!builder.method_filter.is_permitted("admin.reload"));
+        assert!(builder.build().is_ok());
+    }
+
+    #[test]
+    fn test_tcp_stream_server_builder_validate() {
+        let report = TcpStreamServerBuilder::new("127.0.0.1:0")
+            .processor(MockProcessor)
+            .validate();
+        assert!(report.is_ok(), "{report}");
+
+        let report = TcpStreamServerBuilder::new("127.0.0.1:0").validate();
+        assert!(!report.is_ok());
+        assert_eq!(report.errors().next().unwrap().component, "processor");
+    }
+
     #[test]
     fn test_tcp_stream_server_builder_build_no_processor() {
         let builder = TcpStreamServerBuilder::new("127.0.0.1:8080");
@@ -382,10 +1315,10 @@ mod tests {
             .request_timeout(std::time::Duration::from_secs(20));
 
         let server = builder.build().unwrap();
-        assert_eq!(server.security_config.max_connections, 100);
-        assert_eq!(server.security_config.max_request_size, 4096);
+        assert_eq!(server.security_config.load().max_connections, 100);
+        assert_eq!(server.security_config.load().max_request_size, 4096);
         assert_eq!(
-            server.security_config.request_timeout,
+            server.security_config.load().request_timeout,
             std::time::Duration::from_secs(20)
         );
     }
@@ -461,6 +1394,7 @@ mod tests {
             max_request_size: 1024,
             request_timeout: std::time::Duration::from_secs(30),
             idle_timeout: std::time::Duration::from_secs(60),
+            ..Default::default()
         };
         let config2 = config1.clone();
 
@@ -487,4 +1421,953 @@ mod tests {
             _ => panic!("Expected Request"),
         }
     }
+
+    #[tokio::test]
+    async fn test_content_length_framing() {
+        use super::super::framing::{Framing, Wire};
+        use crate::codec::JsonCodec;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let _ = handle_stream_client(
+                        stream.into(),
+                        Arc::new(MockProcessor),
+                        SecurityConfig::default(),
+                        super::super::hello::Opening::default(),
+                        super::super::pipeline::Pipelining::default(),
+                        Wire::new(Framing::ContentLength, Arc::new(JsonCodec)).unwrap(),
+                        Streams::default(),
+                    )
+                    .await;
+                });
+            }
+        });
+
+        // an LSP-style peer, payload spread over several lines
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let request = "{\n  \"jsonrpc\": \"2.0\",\n  \"method\": \"initialize\",\n  \"id\": 1\n}";
+        Framing::ContentLength
+            .write_frame(&mut writer, request)
+            .await
+            .unwrap();
+        let mut reader = BufReader::new(reader);
+        let mut frame = String::new();
+        Framing::ContentLength
+            .read_frame(&mut reader, &mut frame, 0)
+            .await
+            .unwrap();
+        let response: Response = serde_json::from_str(&frame).unwrap();
+        assert_eq!(response.id, Some(serde_json::json!(1)));
+
+        let mut client = TcpStreamClientBuilder::new(addr.to_string())
+            .framing(Framing::ContentLength)
+            .connect()
+            .await
+            .unwrap();
+        let result = client.call("ping", None).await.unwrap();
+        assert_eq!(result, serde_json::json!({"result": "success"}));
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[tokio::test]
+    async fn test_msgpack_codec() {
+        use super::super::framing::{Framing, Wire};
+        use crate::codec::{Codec, MsgPackCodec};
+
+        assert!(
+            TcpStreamServer::builder("127.0.0.1:0")
+                .processor(MockProcessor)
+                .codec(MsgPackCodec)
+                .build()
+                .is_err()
+        );
+
+        let wire = Wire::new(Framing::ContentLength, Arc::new(MsgPackCodec)).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server_wire = wire.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let wire = server_wire.clone();
+                tokio::spawn(async move {
+                    let _ = handle_stream_client(
+                        stream.into(),
+                        Arc::new(MockProcessor),
+                        SecurityConfig::default(),
+                        super::super::hello::Opening::default(),
+                        super::super::pipeline::Pipelining::default(),
+                        wire,
+                        Streams::default(),
+                    )
+                    .await;
+                });
+            }
+        });
+
+        // a payload that is not MessagePack is answered, the connection stays
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        let mut frame = String::new();
+        writer
+            .write_all(b"Content-Length: 1\r\n\r\n\xc1")
+            .await
+            .unwrap();
+        wire.read_frame(&mut reader, &mut frame, 0).await.unwrap();
+        let response: Response = serde_json::from_str(&frame).unwrap();
+        assert_eq!(
+            response.error.unwrap().code,
+            crate::error_codes::PARSE_ERROR
+        );
+        let request = Message::Request(RequestBuilder::new("ping").id(7.into()).build());
+        let payload = MsgPackCodec.encode(&request).unwrap();
+        writer
+            .write_all(format!("Content-Length: {}\r\n\r\n", payload.len()).as_bytes())
+            .await
+            .unwrap();
+        writer.write_all(&payload).await.unwrap();
+        wire.read_frame(&mut reader, &mut frame, 0).await.unwrap();
+        let response: Response = serde_json::from_str(&frame).unwrap();
+        assert_eq!(response.id, Some(serde_json::json!(7)));
+
+        let mut client = TcpStreamClientBuilder::new(addr.to_string())
+            .framing(Framing::ContentLength)
+            .codec(MsgPackCodec)
+            .connect()
+            .await
+            .unwrap();
+        let result = client.call("ping", None).await.unwrap();
+        assert_eq!(result, serde_json::json!({"result": "success"}));
+    }
+
+    #[tokio::test]
+    async fn test_wire_tap_mirrors_frames() {
+        use super::super::tap::{TapDirection, TapFrame, WireTap};
+        use std::sync::Mutex;
+
+        type Captured = Arc<Mutex<Vec<(TapDirection, String, usize)>>>;
+        fn recorder(frames: &Captured) -> WireTap {
+            let frames = Arc::clone(frames);
+            WireTap::new(move |frame: &TapFrame<'_>| {
+                frames.lock().unwrap().push((
+                    frame.direction,
+                    frame.text().into_owned(),
+                    frame.len,
+                ));
+            })
+            .max_frame_bytes(16)
+        }
+
+        let server_frames = Captured::default();
+        let tap = Arc::new(recorder(&server_frames));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, peer) = listener.accept().await.unwrap();
+            let wire = super::super::framing::Wire::default()
+                .tapped(tap.connection(super::super::transport_stats::TCP_STREAM, Some(peer)));
+            let _ = handle_stream_client(
+                stream.into(),
+                Arc::new(MockProcessor),
+                SecurityConfig::default(),
+                super::super::hello::Opening::default(),
+                super::super::pipeline::Pipelining::default(),
+                wire,
+                Streams::default(),
+            )
+            .await;
+        });
+
+        let client_frames = Captured::default();
+        let mut client = TcpStreamClientBuilder::new(addr.to_string())
+            .tap(recorder(&client_frames))
+            .connect()
+            .await
+            .unwrap();
+        client.call("ping", None).await.unwrap();
+
+        // the same two frames, cut to 16 bytes, seen from both ends
+        let client_frames = client_frames.lock().unwrap().clone();
+        let server_frames = server_frames.lock().unwrap().clone();
+        assert_eq!(client_frames.len(), 2);
+        assert_eq!(client_frames[0].0, TapDirection::Outbound);
+        assert_eq!(client_frames[0].1, r#"{"jsonrpc":"2.0""#);
+        assert!(client_frames[0].2 > 16);
+        assert_eq!(client_frames[1].0, TapDirection::Inbound);
+        assert_eq!(
+            server_frames,
+            vec![
+                (
+                    TapDirection::Inbound,
+                    client_frames[0].1.clone(),
+                    client_frames[0].2
+                ),
+                (
+                    TapDirection::Outbound,
+                    client_frames[1].1.clone(),
+                    client_frames[1].2
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_client_call_validated_with() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let _ = handle_stream_client(
+                stream.into(),
+                Arc::new(MockProcessor),
+                SecurityConfig::default(),
+                super::super::hello::Opening::default(),
+                super::super::pipeline::Pipelining::default(),
+                super::super::framing::Wire::default(),
+                Streams::default(),
+            )
+            .await;
+        });
+
+        let mut client = TcpStreamClientBuilder::new(addr.to_string())
+            .connect()
+            .await
+            .unwrap();
+
+        let schema = serde_json::json!({
+            "type": "object",
+            "required": ["result"],
+            "properties": {"result": {"type": "string"}}
+        });
+        let value: serde_json::Value = client
+            .call_validated_with("anything", None, &schema)
+            .await
+            .unwrap();
+        assert_eq!(value["result"], "success");
+
+        let drifted = serde_json::json!({
+            "type": "object",
+            "required": ["result", "count"],
+            "properties": {"result": {"type": "integer"}}
+        });
+        let error = client
+            .call_validated_with::<serde_json::Value>("anything", None, &drifted)
+            .await
+            .unwrap_err();
+        let mismatch = error
+            .downcast_ref::<crate::schema::SchemaMismatch>()
+            .unwrap();
+        assert_eq!(mismatch.violations.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_client_notification_callbacks() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut line = String::new();
+            BufReader::new(reader).read_line(&mut line).await.unwrap();
+            let replies = concat!(
+                r#"{"jsonrpc":"2.0","method":"tick","params":[1]}"#,
+                "\n",
+                r#"{"jsonrpc":"2.0","result":"subscribed","id":1}"#,
+                "\n",
+                r#"{"jsonrpc":"2.0","method":"other"}"#,
+                "\n",
+            );
+            writer.write_all(replies.as_bytes()).await.unwrap();
+        });
+
+        let mut client = TcpStreamClientBuilder::new(addr.to_string())
+            .connect()
+            .await
+            .unwrap();
+        let (ticks_tx, mut ticks) = mpsc::unbounded_channel();
+        client.on_notification("tick", move |params| {
+            let _ = ticks_tx.send(params);
+        });
+
+        let result = client.call("subscribe", None).await.unwrap();
+        assert_eq!(result, "subscribed");
+        assert_eq!(ticks.recv().await.unwrap(), Some(serde_json::json!([1])));
+
+        let unhandled = client.recv_message().await.unwrap().unwrap();
+        assert_eq!(unhandled.method(), Some("other"));
+    }
+
+    #[tokio::test]
+    async fn test_client_keepalive_and_reconnect() {
+        use super::super::reconnect::{ConnectionState, ReconnectPolicy};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (pings_tx, mut pings) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            // the first connection answers one call, sees a ping and drops
+            for connection in 0..2 {
+                let (stream, _) = listener.accept().await.unwrap();
+                let (reader, mut writer) = stream.into_split();
+                let mut lines = BufReader::new(reader).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let message: Message = serde_json::from_str(&line).unwrap();
+                    if message.method() == Some("$/ping") {
+                        let _ = pings_tx.send(connection);
+                        if connection == 0 {
+                            break;
+                        }
+                        continue;
+                    }
+                    let reply = format!(
+                        "{{\"jsonrpc\":\"2.0\",\"result\":{connection},\"id\":{}}}\n",
+                        message.id().unwrap()
+                    );
+                    writer.write_all(reply.as_bytes()).await.unwrap();
+                }
+            }
+        });
+
+        let mut client = TcpStreamClientBuilder::new(addr.to_string())
+            .keepalive_ping("$/ping", std::time::Duration::from_millis(20))
+            .reconnect(ReconnectPolicy::new().initial_backoff(std::time::Duration::from_millis(10)))
+            .connect()
+            .await
+            .unwrap();
+        let mut state = client.connection_state();
+        assert_eq!(client.call("first", None).await.unwrap(), 0);
+        assert_eq!(pings.recv().await, Some(0));
+
+        state
+            .wait_for(|state| matches!(state, ConnectionState::Reconnecting { .. }))
+            .await
+            .unwrap();
+        state
+            .wait_for(|state| *state == ConnectionState::Connected)
+            .await
+            .unwrap();
+        assert_eq!(client.call("second", None).await.unwrap(), 1);
+        assert_eq!(pings.recv().await, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_server_hello_reaches_client() {
+        use super::super::hello::{AuthMode, ServerHello};
+
+        struct AcceptAll;
+
+        #[async_trait::async_trait]
+        impl super::super::handshake::ConnectionAuthenticator for AcceptAll {
+            async fn authenticate(
+                &self,
+                _params: Option<&serde_json::Value>,
+                _ctx: &mut crate::auth::ConnectionContext,
+            ) -> Result<(), String> {
+                Ok(())
+            }
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let _ = handle_stream_client(
+                stream.into(),
+                Arc::new(MockProcessor),
+                SecurityConfig::default(),
+                super::super::hello::Opening {
+                    handshake: Some(super::super::handshake::AuthHandshake::new(AcceptAll)),
+                    hello: Some(ServerHello::new("billing", "1.4.0")),
+                },
+                super::super::pipeline::Pipelining::default(),
+                super::super::framing::Wire::default(),
+                Streams::default(),
+            )
+            .await;
+        });
+
+        let mut client = TcpStreamClientBuilder::new(addr.to_string())
+            .connect()
+            .await
+            .unwrap();
+        let hello = client
+            .server_hello()
+            .wait_for(Option::is_some)
+            .await
+            .unwrap()
+            .clone()
+            .unwrap();
+        assert_eq!(
+            (hello.name.as_str(), hello.version.as_str()),
+            ("billing", "1.4.0")
+        );
+        assert_eq!(
+            hello.auth,
+            AuthMode::Handshake {
+                method: "rpc.authenticate".into()
+            }
+        );
+        assert_eq!(hello.capabilities.unwrap().max_batch_size, Some(100));
+        assert!(client.call("rpc.authenticate", None).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_handshake_closes_after_failed_attempts() {
+        use super::super::handshake::{AUTHENTICATION_REQUIRED, AuthHandshake};
+
+        struct RejectAll;
+
+        #[async_trait::async_trait]
+        impl super::super::handshake::ConnectionAuthenticator for RejectAll {
+            async fn authenticate(
+                &self,
+                _params: Option<&serde_json::Value>,
+                _ctx: &mut crate::auth::ConnectionContext,
+            ) -> Result<(), String> {
+                Err("no".to_string())
+            }
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let _ = handle_stream_client(
+                stream.into(),
+                Arc::new(MockProcessor),
+                SecurityConfig::default(),
+                super::super::hello::Opening {
+                    handshake: Some(AuthHandshake::new(RejectAll).max_attempts(1)),
+                    ..Default::default()
+                },
+                super::super::pipeline::Pipelining::default(),
+                super::super::framing::Wire::default(),
+                Streams::default(),
+            )
+            .await;
+        });
+
+        let mut client = TcpStreamClientBuilder::new(addr.to_string())
+            .connect()
+            .await
+            .unwrap();
+        let error = client.call_raw("anything", None).await.unwrap_err();
+        let error = error.downcast_ref::<crate::Error>().unwrap();
+        assert_eq!(error.code, AUTHENTICATION_REQUIRED);
+
+        assert!(client.call_raw("rpc.authenticate", None).await.is_err());
+        assert!(client.recv_message().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_batch_line_answered_with_array() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let _ = handle_stream_client(
+                stream.into(),
+                Arc::new(MockProcessor),
+                SecurityConfig::default(),
+                super::super::hello::Opening::default(),
+                super::super::pipeline::Pipelining::default(),
+                super::super::framing::Wire::default(),
+                Streams::default(),
+            )
+            .await;
+        });
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        let batch = concat!(
+            r#"[{"jsonrpc":"2.0","method":"a","id":1},"#,
+            r#"{"jsonrpc":"2.0","method":"b","id":2}]"#,
+            "\n",
+            "[]\n",
+        );
+        writer.write_all(batch.as_bytes()).await.unwrap();
+
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        let responses: Vec<crate::Response> = serde_json::from_str(&line).unwrap();
+        let ids: Vec<_> = responses.iter().map(|r| r.id.clone()).collect();
+        assert_eq!(
+            ids,
+            [Some(serde_json::json!(1)), Some(serde_json::json!(2))]
+        );
+
+        line.clear();
+        reader.read_line(&mut line).await.unwrap();
+        let refused: crate::Response = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            refused.error.unwrap().code,
+            crate::error_codes::INVALID_REQUEST
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connection_carries_listener() {
+        use super::super::listener::ListenerId;
+
+        struct Whoami;
+
+        #[async_trait::async_trait]
+        impl MessageProcessor for Whoami {
+            async fn process_message(&self, _: Message) -> Option<Response> {
+                None
+            }
+
+            async fn process_message_with_context(
+                &self,
+                message: Message,
+                ctx: &crate::auth::ConnectionContext,
+            ) -> Option<Response> {
+                let listener = ctx.listener().map(ToString::to_string);
+                Some(Response::success(
+                    serde_json::json!(listener),
+                    message.id().cloned(),
+                ))
+            }
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let id = ListenerId::new(super::super::transport_stats::TCP_STREAM, Some(addr))
+            .name(Some("listener-test-admin".into()));
+        let served = id.clone();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut accepted = Accepted::from(stream);
+            accepted.connection = accepted.connection.with_listener(served);
+            let _ = handle_stream_client(
+                accepted,
+                Arc::new(Whoami),
+                SecurityConfig::default(),
+                super::super::hello::Opening::default(),
+                super::super::pipeline::Pipelining::default(),
+                super::super::framing::Wire::default(),
+                Streams::default(),
+            )
+            .await;
+        });
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        let request = "{\"jsonrpc\":\"2.0\",\"method\":\"whoami\",\"id\":1}\n";
+        writer.write_all(request.as_bytes()).await.unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        let response: Response = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            response.result,
+            Some(serde_json::json!("listener-test-admin"))
+        );
+
+        let stats = super::super::transport_stats::snapshot()
+            .into_iter()
+            .find(|s| s.listener == "listener-test-admin")
+            .unwrap();
+        assert_eq!(stats.transport, super::super::transport_stats::TCP_STREAM);
+        assert_eq!(stats.bytes_received, request.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn test_half_close_drains_owed_responses() {
+        use std::time::Duration;
+
+        /// Responds after `params` milliseconds
+        struct Sleepy;
+
+        #[async_trait::async_trait]
+        impl MessageProcessor for Sleepy {
+            async fn process_message(&self, message: Message) -> Option<Response> {
+                let request = message.into_request()?;
+                let ms = request.params().and_then(|p| p.as_u64()).unwrap_or(0);
+                tokio::time::sleep(Duration::from_millis(ms)).await;
+                Some(Response::success(serde_json::json!(ms), request.id))
+            }
+        }
+
+        async fn half_close(drain_timeout: Duration, delay_ms: u64) -> Vec<String> {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                let _ = handle_stream_client(
+                    stream.into(),
+                    Arc::new(Sleepy),
+                    SecurityConfig::default().with_drain_timeout(drain_timeout),
+                    super::super::hello::Opening::default(),
+                    super::super::pipeline::Pipelining::new(4),
+                    super::super::framing::Wire::default(),
+                    Streams::default(),
+                )
+                .await;
+            });
+
+            let stream = TcpStream::connect(addr).await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            for id in 1..=2 {
+                let request = format!(
+                    "{{\"jsonrpc\":\"2.0\",\"method\":\"sleep\",\"params\":{delay_ms},\"id\":{id}}}\n"
+                );
+                writer.write_all(request.as_bytes()).await.unwrap();
+            }
+            writer.shutdown().await.unwrap();
+
+            let mut lines = BufReader::new(reader).lines();
+            let mut received = Vec::new();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                received.push(line);
+            }
+            received
+        }
+
+        let drained = half_close(Duration::from_secs(5), 50).await;
+        assert_eq!(drained.len(), 2);
+        assert!(drained.iter().all(|line| line.contains("\"result\":50")));
+
+        let cut_off = half_close(Duration::from_millis(20), 2_000).await;
+        assert!(cut_off.is_empty());
+    }
+
+    #[cfg(feature = "streaming")]
+    #[tokio::test]
+    async fn test_subscription_events_reach_subscriber_only() {
+        use crate::streaming::*;
+        use serde_json::json;
+
+        struct Ticks;
+
+        #[async_trait::async_trait]
+        impl StreamingMethod for Ticks {
+            fn method_name(&self) -> &'static str {
+                "ticks"
+            }
+
+            async fn open(
+                &self,
+                params: Option<serde_json::Value>,
+            ) -> Result<ResultStream, crate::Error> {
+                let to = params.and_then(|p| p.as_u64()).unwrap_or(0);
+                let (tx, stream) = result_channel(4);
+                tokio::spawn(async move {
+                    for n in 0..to {
+                        if tx.send(json!(n)).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+                Ok(stream)
+            }
+        }
+
+        let manager = Arc::new(StreamManager::new());
+        manager
+            .register_handler(StreamingMethodHandler::new(Ticks))
+            .await;
+        let router = super::super::stream_router::StreamRouter::start(manager);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let streams = Streams {
+                    router: Some(Arc::clone(&router)),
+                };
+                tokio::spawn(async move {
+                    let _ = handle_stream_client(
+                        stream.into(),
+                        Arc::new(MockProcessor),
+                        SecurityConfig::default(),
+                        super::super::hello::Opening::default(),
+                        super::super::pipeline::Pipelining::default(),
+                        super::super::framing::Wire::default(),
+                        streams,
+                    )
+                    .await;
+                });
+            }
+        });
+
+        let connect = || async {
+            let (reader, writer) = TcpStream::connect(addr).await.unwrap().into_split();
+            (BufReader::new(reader).lines(), writer)
+        };
+        let (mut lines, mut writer) = connect().await;
+        let (mut other_lines, mut other_writer) = connect().await;
+
+        let subscribe = StreamRequest::new("ticks", json!(1))
+            .with_params(json!(3))
+            .with_stream_id("t-1");
+        let line = serde_json::to_string(&subscribe).unwrap() + "\n";
+        writer.write_all(line.as_bytes()).await.unwrap();
+        let mut received = Vec::new();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            match serde_json::from_str::<StreamMessage>(&line).unwrap() {
+                StreamMessage::StreamResponse(response) => assert!(response.error.is_none()),
+                StreamMessage::StreamEvent(event) if event.status.is_some() => break,
+                StreamMessage::StreamEvent(event) => received.push(event.params),
+                other => panic!("unexpected {other:?}"),
+            }
+        }
+        assert_eq!(received, [json!(0), json!(1), json!(2)]);
+
+        // other connections cannot cancel the stream, and still get plain calls answered
+        let unsubscribe = UnsubscribeRequest::new("t-1".into(), json!(2));
+        let line = serde_json::to_string(&unsubscribe).unwrap() + "\n";
+        other_writer.write_all(line.as_bytes()).await.unwrap();
+        let response: StreamResponse =
+            serde_json::from_str(&other_lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(
+            response.error.unwrap().code,
+            crate::error_codes::INVALID_PARAMS
+        );
+        other_writer
+            .write_all(b"{\"jsonrpc\":\"2.0\",\"method\":\"ping\",\"id\":3}\n")
+            .await
+            .unwrap();
+        let response: Response =
+            serde_json::from_str(&other_lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(response.id, Some(json!(3)));
+    }
+
+    #[cfg(feature = "streaming")]
+    #[tokio::test]
+    async fn test_subscriptions_pass_filter_and_auth() {
+        use crate::streaming::*;
+        use serde_json::json;
+
+        struct Idle;
+
+        #[async_trait::async_trait]
+        impl StreamingMethod for Idle {
+            fn method_name(&self) -> &'static str {
+                "ticks"
+            }
+
+            async fn open(
+                &self,
+                _params: Option<serde_json::Value>,
+            ) -> Result<ResultStream, crate::Error> {
+                let (_tx, stream) = result_channel(1);
+                Ok(stream)
+            }
+        }
+
+        let manager = Arc::new(StreamManager::new());
+        manager
+            .register_handler(StreamingMethodHandler::new(Idle))
+            .await;
+        let router = super::super::stream_router::StreamRouter::start(Arc::clone(&manager));
+        let filtered: Arc<dyn MessageProcessor + Send + Sync> =
+            Arc::new(super::super::listener::ListenerProcessor::new(
+                Arc::new(MockProcessor),
+                None,
+                super::super::listener::MethodFilter::new().deny("ticks"),
+            ));
+        let denied: Arc<dyn MessageProcessor + Send + Sync> =
+            Arc::new(crate::MethodRegistry::empty().with_auth(crate::auth::DenyAll));
+
+        for (processor, code) in [
+            (filtered, crate::error_codes::METHOD_NOT_FOUND),
+            (denied, crate::error_codes::INTERNAL_ERROR),
+        ] {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let streams = Streams {
+                router: Some(Arc::clone(&router)),
+            };
+            tokio::spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                let _ = handle_stream_client(
+                    stream.into(),
+                    processor,
+                    SecurityConfig::default(),
+                    super::super::hello::Opening::default(),
+                    super::super::pipeline::Pipelining::default(),
+                    super::super::framing::Wire::default(),
+                    streams,
+                )
+                .await;
+            });
+
+            let (reader, mut writer) = TcpStream::connect(addr).await.unwrap().into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let subscribe = StreamRequest::new("ticks", json!(1)).with_stream_id("t-1");
+            let line = serde_json::to_string(&subscribe).unwrap() + "\n";
+            writer.write_all(line.as_bytes()).await.unwrap();
+            let response: Response =
+                serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+            assert_eq!(response.error.unwrap().code, code);
+            assert_eq!(response.id, Some(json!(1)));
+            assert_eq!(manager.active_count().await, 0);
+        }
+    }
+
+    #[cfg(feature = "streaming")]
+    #[tokio::test]
+    async fn test_reset_connection_releases_streams() {
+        use crate::streaming::*;
+        use serde_json::json;
+
+        struct Idle;
+
+        #[async_trait::async_trait]
+        impl StreamingMethod for Idle {
+            fn method_name(&self) -> &'static str {
+                "idle"
+            }
+
+            async fn open(
+                &self,
+                _params: Option<serde_json::Value>,
+            ) -> Result<ResultStream, crate::Error> {
+                let (tx, stream) = result_channel(1);
+                tokio::spawn(async move { tx.closed().await });
+                Ok(stream)
+            }
+        }
+
+        let manager = Arc::new(StreamManager::new());
+        manager
+            .register_handler(StreamingMethodHandler::new(Idle))
+            .await;
+        let router = super::super::stream_router::StreamRouter::start(Arc::clone(&manager));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let _ = handle_stream_client(
+                stream.into(),
+                Arc::new(MockProcessor),
+                SecurityConfig::default(),
+                super::super::hello::Opening::default(),
+                super::super::pipeline::Pipelining::default(),
+                super::super::framing::Wire::default(),
+                Streams {
+                    router: Some(router),
+                },
+            )
+            .await;
+        });
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        let subscribe = StreamRequest::new("idle", json!(1)).with_stream_id("i-1");
+        let line = serde_json::to_string(&subscribe).unwrap() + "\n";
+        writer.write_all(line.as_bytes()).await.unwrap();
+        lines.next_line().await.unwrap().unwrap();
+        assert_eq!(manager.active_count().await, 1);
+
+        // close with a RST, the server's next read fails
+        let stream = lines.into_inner().into_inner().reunite(writer).unwrap();
+        socket2::SockRef::from(&stream)
+            .set_linger(Some(Duration::ZERO))
+            .unwrap();
+        drop(stream);
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while manager.active_count().await > 0 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[cfg(feature = "streaming")]
+    #[tokio::test]
+    async fn test_client_subscribe_and_unsubscribe() {
+        use crate::streaming::*;
+        use serde_json::json;
+
+        struct Forever;
+
+        #[async_trait::async_trait]
+        impl StreamingMethod for Forever {
+            fn method_name(&self) -> &'static str {
+                "forever"
+            }
+
+            async fn open(
+                &self,
+                _params: Option<serde_json::Value>,
+            ) -> Result<ResultStream, crate::Error> {
+                let (tx, stream) = result_channel(4);
+                tokio::spawn(async move {
+                    for n in 0.. {
+                        if tx.send(json!(n)).await.is_err() {
+                            break;
+                        }
+                        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+                    }
+                });
+                Ok(stream)
+            }
+        }
+
+        let manager = Arc::new(StreamManager::new());
+        manager
+            .register_handler(StreamingMethodHandler::new(Forever))
+            .await;
+        let router = super::super::stream_router::StreamRouter::start(manager);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let streams = Streams {
+                router: Some(router),
+            };
+            let _ = handle_stream_client(
+                stream.into(),
+                Arc::new(MockProcessor),
+                SecurityConfig::default(),
+                super::super::hello::Opening::default(),
+                super::super::pipeline::Pipelining::default(),
+                super::super::framing::Wire::default(),
+                streams,
+            )
+            .await;
+        });
+
+        let mut client = TcpStreamClientBuilder::new(addr.to_string())
+            .connect()
+            .await
+            .unwrap();
+        assert!(client.subscribe("missing", None).await.is_err());
+
+        let mut subscription = client.subscribe("forever", None).await.unwrap();
+        let first = subscription.next().await.unwrap();
+        let second = subscription.next().await.unwrap();
+        assert_eq!(first.stream_id(), subscription.stream_id());
+        assert_eq!((first.params, second.params), (json!(0), json!(1)));
+        subscription.pause().await.unwrap();
+        subscription.resume().await.unwrap();
+        let next = subscription.next().await.unwrap();
+        assert_eq!(next.sequence, Some(second.sequence.unwrap() + 1));
+        subscription.unsubscribe().await.unwrap();
+
+        // neither events nor the confirmation leak into regular calls
+        assert_eq!(
+            client.call("ping", None).await.unwrap(),
+            json!({"result": "success"})
+        );
+        client
+            .send_message(&Message::Request(
+                crate::RequestBuilder::new("ping").id(json!("last")).build(),
+            ))
+            .await
+            .unwrap();
+        let response = client.recv_message().await.unwrap().unwrap();
+        assert_eq!(response.id(), Some(&json!("last")));
+    }
 }
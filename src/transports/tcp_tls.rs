@@ -3,12 +3,12 @@
 //! Provides secure TCP streaming with TLS encryption using rustls.
 
 use super::security::SecurityConfig;
-use crate::{Message, MessageProcessor};
+use crate::MessageProcessor;
 use std::path::Path;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader as TokioBufReader};
-use tokio::net::{TcpListener, TcpStream};
+use tokio::net::TcpStream;
 use tokio::time::timeout;
 use tokio_rustls::TlsAcceptor;
 use tokio_rustls::rustls::ServerConfig;
@@ -74,6 +74,20 @@ pub struct TcpStreamTlsServerBuilder {
     processor: Option<Arc<dyn MessageProcessor + Send + Sync>>,
     tls_config: Option<TlsConfig>,
     security_config: SecurityConfig,
+    supervision: super::supervisor::SupervisionPolicy,
+    socket_options: super::socket::SocketOptions,
+    backlog: Option<u32>,
+    accept_rate: Option<super::accept::AcceptRateLimit>,
+    name: Option<String>,
+    method_filter: super::listener::MethodFilter,
+    json_format: crate::serialization::JsonFormat,
+    handoff: Option<super::handoff::HandoffSlot>,
+    handshake: Option<super::handshake::AuthHandshake>,
+    hello: Option<super::hello::ServerHello>,
+    pipelining: super::pipeline::Pipelining,
+    framing: super::framing::Framing,
+    codec: Arc<dyn crate::codec::Codec>,
+    tap: Option<Arc<super::tap::WireTap>>,
 }
 
 impl TcpStreamTlsServerBuilder {
@@ -83,6 +97,20 @@ impl TcpStreamTlsServerBuilder {
             processor: None,
             tls_config: None,
             security_config: SecurityConfig::default(),
+            supervision: super::supervisor::SupervisionPolicy::default(),
+            socket_options: super::socket::SocketOptions::default(),
+            backlog: None,
+            accept_rate: None,
+            name: None,
+            method_filter: super::listener::MethodFilter::default(),
+            json_format: crate::serialization::JsonFormat::default(),
+            handoff: None,
+            handshake: None,
+            hello: None,
+            pipelining: super::pipeline::Pipelining::default(),
+            framing: super::framing::Framing::default(),
+            codec: Arc::new(crate::codec::JsonCodec),
+            tap: None,
         }
     }
 
@@ -119,20 +147,151 @@ impl TcpStreamTlsServerBuilder {
         self
     }
 
+    /// How to recover from failing `accept()` calls
+    pub fn supervision(mut self, policy: super::supervisor::SupervisionPolicy) -> Self {
+        self.supervision = policy;
+        self
+    }
+
+    /// TCP options applied to every accepted connection
+    pub fn socket_options(mut self, options: super::socket::SocketOptions) -> Self {
+        self.socket_options = options;
+        self
+    }
+
+    /// Listen backlog passed to `listen(2)` instead of the OS default
+    pub fn backlog(mut self, backlog: u32) -> Self {
+        self.backlog = Some(backlog);
+        self
+    }
+
+    /// Pace how quickly accepted connections are handled
+    pub fn accept_rate(mut self, limit: super::accept::AcceptRateLimit) -> Self {
+        self.accept_rate = Some(limit);
+        self
+    }
+
+    /// Name this listener; the name is exposed as `ConnectionContext::origin`
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Restrict which methods may be called through this listener
+    pub fn method_filter(mut self, filter: super::listener::MethodFilter) -> Self {
+        self.method_filter = filter;
+        self
+    }
+
+    /// Output format handlers convert results with; frames are always
+    /// written compact
+    pub fn json_format(mut self, format: crate::serialization::JsonFormat) -> Self {
+        self.json_format = format;
+        self
+    }
+
+    /// Take part in `handoff` as the listener called `name`, so a restarted
+    /// process can take over the listening socket
+    pub fn handoff(
+        mut self,
+        handoff: Arc<super::handoff::ListenerHandoff>,
+        name: impl Into<String>,
+    ) -> Self {
+        self.handoff = Some(super::handoff::HandoffSlot {
+            handoff,
+            name: name.into(),
+        });
+        self
+    }
+
+    /// Require `rpc.authenticate` before any other call on a connection
+    pub fn authentication(mut self, handshake: super::handshake::AuthHandshake) -> Self {
+        self.handshake = Some(handshake);
+        self
+    }
+
+    /// Greet every connection with `hello` before reading from it
+    pub fn hello(mut self, hello: super::hello::ServerHello) -> Self {
+        self.hello = Some(hello);
+        self
+    }
+
+    /// Process several requests of a connection concurrently
+    pub fn pipelining(mut self, pipelining: super::pipeline::Pipelining) -> Self {
+        self.pipelining = pipelining;
+        self
+    }
+
+    /// How messages are delimited, newlines by default
+    pub fn framing(mut self, framing: super::framing::Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Encoding of message payloads, JSON by default
+    ///
+    /// Binary codecs need [`Framing::ContentLength`](super::framing::Framing).
+    pub fn codec(mut self, codec: impl crate::codec::Codec) -> Self {
+        self.codec = Arc::new(codec);
+        self
+    }
+
+    /// Mirror the decrypted frames of sampled connections to `tap`
+    pub fn tap(mut self, tap: super::tap::WireTap) -> Self {
+        self.tap = Some(Arc::new(tap));
+        self
+    }
+
+    /// Validate the configuration without binding a listener for real.
+    ///
+    /// In addition to the plain TCP checks this verifies TLS material was
+    /// supplied; certificates are parsed when the `TlsConfig` is created.
+    pub fn validate(&self) -> super::validation::ConfigReport {
+        super::validation::check_server(
+            self.processor.is_some(),
+            &self.addr,
+            &self.security_config,
+            |report| {
+                if self.tls_config.is_none() {
+                    report.error("tls", "TLS config not set");
+                }
+                super::validation::check_wire(self.framing, &self.codec, report);
+            },
+        )
+    }
+
     pub fn build(self) -> Result<TcpStreamTlsServer, std::io::Error> {
         let processor = self.processor.ok_or_else(|| {
             std::io::Error::new(std::io::ErrorKind::InvalidInput, "Processor not set")
         })?;
+        let processor = super::listener::ListenerProcessor::wrap(
+            processor,
+            self.name.clone(),
+            self.method_filter,
+        );
 
         let tls_config = self.tls_config.ok_or_else(|| {
             std::io::Error::new(std::io::ErrorKind::InvalidInput, "TLS config not set")
         })?;
+        let wire = super::framing::Wire::new(self.framing, self.codec)?;
 
         Ok(TcpStreamTlsServer {
             addr: self.addr,
+            name: self.name,
+            json_format: self.json_format,
             processor,
             tls_config,
-            security_config: self.security_config,
+            security_config: crate::reload::Reloadable::new(self.security_config),
+            supervision: self.supervision,
+            socket_options: self.socket_options,
+            backlog: self.backlog,
+            handoff: self.handoff,
+            accept_rate: self.accept_rate,
+            handshake: self.handshake,
+            hello: self.hello,
+            pipelining: self.pipelining,
+            wire,
+            tap: self.tap,
             active_connections: Arc::new(AtomicUsize::new(0)),
         })
     }
@@ -140,9 +299,21 @@ impl TcpStreamTlsServerBuilder {
 
 pub struct TcpStreamTlsServer {
     addr: String,
+    name: Option<String>,
+    json_format: crate::serialization::JsonFormat,
     processor: Arc<dyn MessageProcessor + Send + Sync>,
     tls_config: TlsConfig,
-    security_config: SecurityConfig,
+    security_config: crate::reload::Reloadable<SecurityConfig>,
+    supervision: super::supervisor::SupervisionPolicy,
+    socket_options: super::socket::SocketOptions,
+    backlog: Option<u32>,
+    handoff: Option<super::handoff::HandoffSlot>,
+    accept_rate: Option<super::accept::AcceptRateLimit>,
+    handshake: Option<super::handshake::AuthHandshake>,
+    hello: Option<super::hello::ServerHello>,
+    pipelining: super::pipeline::Pipelining,
+    wire: super::framing::Wire,
+    tap: Option<Arc<super::tap::WireTap>>,
     active_connections: Arc<AtomicUsize>,
 }
 
@@ -151,60 +322,180 @@ impl TcpStreamTlsServer {
         TcpStreamTlsServerBuilder::new(addr)
     }
 
+    /// Handle to the live security configuration.
+    ///
+    /// Storing a new config affects connections accepted afterwards; see
+    /// [`ConfigReloader::security`](crate::reload::ConfigReloader::security).
+    pub fn security_handle(&self) -> crate::reload::Reloadable<SecurityConfig> {
+        self.security_config.clone()
+    }
+
     pub async fn run(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let listener = TcpListener::bind(&self.addr).await?;
+        let (listener, mut handed_off) =
+            super::handoff::listen(self.handoff.as_ref(), &self.addr, self.backlog).await?;
+        let initial = self.security_config.load();
+        let id = super::listener::ListenerId::new(
+            super::transport_stats::TLS,
+            listener.local_addr().ok(),
+        )
+        .name(self.name.clone());
         tracing::info!(
             addr = %self.addr,
+            listener = %id,
             protocol = "tls",
-            max_connections = self.security_config.max_connections,
-            max_request_size = self.security_config.max_request_size,
+            max_connections = initial.max_connections,
+            max_request_size = initial.max_request_size,
             "server listening"
         );
 
+        let mut supervisor = super::supervisor::AcceptSupervisor::new(self.supervision.clone());
+        let mut pacer = super::accept::AcceptPacer::new(self.accept_rate.clone());
+        let transport = super::transport_stats::listener(&id);
         loop {
-            let (stream, addr) = listener.accept().await?;
+            let Some(accepted) = super::handoff::accept(&listener, handed_off.as_mut()).await
+            else {
+                super::handoff::drain(self.handoff.as_ref(), &self.active_connections).await;
+                return Ok(());
+            };
+            let (stream, addr) = match accepted {
+                Ok(accepted) => {
+                    supervisor.on_success();
+                    accepted
+                }
+                Err(e) => {
+                    supervisor.on_error(e).await?;
+                    continue;
+                }
+            };
 
             let current_connections = self.active_connections.load(Ordering::Relaxed);
+            let security_config = self.security_config.load();
 
             // Check connection limit
-            if self.security_config.max_connections > 0
-                && current_connections >= self.security_config.max_connections
+            if security_config.max_connections > 0
+                && current_connections >= security_config.max_connections
             {
-                tracing::warn!(
-                    remote_addr = %addr,
-                    active_connections = current_connections,
-                    max_connections = self.security_config.max_connections,
-                    "connection limit reached, rejecting connection"
+                crate::rejection::record(
+                    crate::rejection::Rejection::new(
+                        crate::rejection::RejectionReason::ConnectionLimit,
+                    )
+                    .remote_addr(Some(addr))
+                    .detail(format!(
+                        "{current_connections} of {} connections in use",
+                        security_config.max_connections
+                    )),
                 );
+                transport.rejected();
                 drop(stream);
                 continue;
             }
 
+            let Some(delay) = pacer.admit(addr) else {
+                transport.rejected();
+                drop(stream);
+                continue;
+            };
+
+            self.socket_options.apply_accepted(&stream, addr);
             self.active_connections.fetch_add(1, Ordering::Relaxed);
             tracing::debug!(remote_addr = %addr, protocol = "tls", active_connections = current_connections + 1, "new connection");
 
             let processor = Arc::clone(&self.processor);
+            let opening = super::hello::Opening {
+                handshake: self.handshake.clone(),
+                hello: self.hello.clone(),
+            };
+            let pipelining = self.pipelining;
+            let json_format = self.json_format;
+            let tap = self
+                .tap
+                .as_ref()
+                .and_then(|tap| tap.connection(super::transport_stats::TLS, Some(addr)));
+            let wire = self.wire.clone().tapped(tap);
             let acceptor = self.tls_config.acceptor.clone();
-            let security_config = self.security_config.clone();
+            let security_config = SecurityConfig::clone(&security_config);
             let active_connections = Arc::clone(&self.active_connections);
-
-            tokio::spawn(async move {
+            let transport = Arc::clone(&transport);
+            let active = transport.accepted();
+            let id = id.clone();
+            let span = tracing::info_span!("connection", listener = %id, remote_addr = %addr);
+
+            let connection = async move {
+                if !delay.is_zero() {
+                    tokio::time::sleep(delay).await;
+                }
+                let stream = super::transport_stats::Metered::new(stream, Arc::clone(&transport));
                 let result = match acceptor.accept(stream).await {
                     Ok(tls_stream) => {
-                        handle_tls_client(tls_stream, processor, security_config).await
+                        let mut connection =
+                            connection_context(&tls_stream, addr).with_listener(id);
+                        if json_format != crate::serialization::JsonFormat::default() {
+                            connection = connection.with_json_format(json_format);
+                        }
+                        handle_tls_client(
+                            tls_stream,
+                            processor,
+                            security_config,
+                            opening,
+                            pipelining,
+                            wire,
+                            connection,
+                        )
+                        .await
                     }
                     Err(e) => {
-                        tracing::warn!(remote_addr = %addr, error = %e, "tls handshake failed");
+                        transport.handshake_failed();
+                        crate::rejection::record(
+                            crate::rejection::Rejection::new(
+                                crate::rejection::RejectionReason::TlsHandshake,
+                            )
+                            .remote_addr(Some(addr))
+                            .detail(e.to_string()),
+                        );
                         Err(e.into())
                     }
                 };
 
                 active_connections.fetch_sub(1, Ordering::Relaxed);
+                drop(active);
 
                 if let Err(e) = result {
                     tracing::error!(remote_addr = %addr, error = %e, "tls client handler failed");
                 }
-            });
+            };
+            tokio::spawn(tracing::Instrument::instrument(connection, span));
+        }
+    }
+}
+
+/// Context of an accepted connection, with the subject of the client
+/// certificate under [`PEER_DN_KEY`](crate::auth::PEER_DN_KEY) when the client
+/// presented one
+fn connection_context<S>(
+    stream: &tokio_rustls::server::TlsStream<S>,
+    addr: std::net::SocketAddr,
+) -> crate::auth::ConnectionContext {
+    let mut context = crate::auth::ConnectionContext::with_addr(addr);
+    let subject = stream
+        .get_ref()
+        .1
+        .peer_certificates()
+        .and_then(|chain| chain.first())
+        .and_then(|cert| peer_dn(cert.as_ref()));
+    if let Some(subject) = subject {
+        tracing::debug!(remote_addr = %addr, peer_dn = %subject, "client certificate presented");
+        context.insert(crate::auth::PEER_DN_KEY.to_string(), subject);
+    }
+    context
+}
+
+/// Subject DN of a DER encoded certificate, e.g. `CN=billing, O=Example`
+fn peer_dn(der: &[u8]) -> Option<String> {
+    match x509_parser::parse_x509_certificate(der) {
+        Ok((_, cert)) => Some(cert.subject().to_string()),
+        Err(e) => {
+            tracing::warn!(error = %e, "unparseable client certificate");
+            None
         }
     }
 }
@@ -213,41 +504,74 @@ async fn handle_tls_client<S>(
     stream: S,
     processor: Arc<dyn MessageProcessor + Send + Sync>,
     security_config: SecurityConfig,
+    opening: super::hello::Opening,
+    pipelining: super::pipeline::Pipelining,
+    wire: super::framing::Wire,
+    connection: crate::auth::ConnectionContext,
 ) -> Result<(), Box<dyn std::error::Error>>
 where
     S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
 {
+    let remote_addr = connection.remote_addr;
+    let transport = connection.listener().map_or_else(
+        || super::transport_stats::transport(super::transport_stats::TLS),
+        super::transport_stats::listener,
+    );
+    let hello = opening.hello_frame(&*processor);
+    let (gate, processor) = super::connection::bind(processor, opening.handshake, connection);
     let (reader, writer) = tokio::io::split(stream);
     let mut reader = TokioBufReader::new(reader);
     let (tx, mut rx) = tokio::sync::mpsc::channel::<String>(100);
+    let codec = super::codec_stats::negotiated(wire.codec_name());
+    let reads = wire.clone();
 
     // Writer task
-    tokio::spawn(async move {
+    let writer_task = tokio::spawn(async move {
         let mut writer = writer;
         while let Some(response) = rx.recv().await {
-            if writer.write_all(response.as_bytes()).await.is_err()
-                || writer.write_all(b"\n").await.is_err()
+            if wire.write_frame(&mut writer, &response).await.is_err()
                 || writer.flush().await.is_err()
             {
                 break;
             }
         }
+        // a FIN, or close_notify under TLS, once everything is written
+        let _ = writer.shutdown().await;
     });
 
     // Reader/processor loop
+    if let Some(hello) = hello {
+        tx.send(hello).await?;
+    }
+    let pipeline = super::pipeline::ResponsePipeline::new(tx, pipelining);
     let mut line = String::new();
+    let mut budget = super::lifetime::ConnectionBudget::new(&security_config);
     loop {
-        line.clear();
+        if let Some(reason) = budget.exhausted() {
+            if let Some(notification) = budget.recycle(reason, None) {
+                let _ = pipeline.send(notification).await;
+            }
+            break;
+        }
 
         // Apply idle timeout
-        let read_result =
-            match timeout(security_config.idle_timeout, reader.read_line(&mut line)).await {
-                Ok(result) => result,
-                Err(_) => {
-                    tracing::debug!("connection idle timeout");
-                    break;
-                }
-            };
+        let Some(read) = budget
+            .guard(timeout(
+                security_config.idle_timeout,
+                reads.read_frame(&mut reader, &mut line, security_config.max_request_size),
+            ))
+            .await
+        else {
+            continue;
+        };
+        let read_result = match read {
+            Ok(result) => result,
+            Err(_) => {
+                tracing::debug!("connection idle timeout");
+                transport.idle_timeout();
+                break;
+            }
+        };
 
         match read_result {
             Ok(0) => break,
@@ -256,10 +580,15 @@ where
                 if security_config.max_request_size > 0
                     && line.len() > security_config.max_request_size
                 {
-                    tracing::warn!(
-                        request_size = line.len(),
-                        max_size = security_config.max_request_size,
-                        "request size limit exceeded"
+                    crate::rejection::record(
+                        crate::rejection::Rejection::new(
+                            crate::rejection::RejectionReason::RequestTooLarge,
+                        )
+                        .detail(format!(
+                            "{} bytes exceeds {}",
+                            line.len(),
+                            security_config.max_request_size
+                        )),
                     );
                     let error_response = crate::Response::error(
                         crate::ErrorBuilder::new(
@@ -270,24 +599,68 @@ where
                         None,
                     );
                     if let Ok(json) = serde_json::to_string(&error_response) {
-                        let _ = tx.send(json).await;
+                        let _ = pipeline.send(json).await;
                     }
                     break;
                 }
 
-                let message_result: Result<Message, _> = serde_json::from_str(line.trim());
+                if let Err(e) = security_config.check_json_limits(line.trim()) {
+                    crate::rejection::record(
+                        crate::rejection::Rejection::new(
+                            crate::rejection::RejectionReason::JsonTooComplex,
+                        )
+                        .detail(e.to_string()),
+                    );
+                    let error_response = crate::Response::error(
+                        crate::ErrorBuilder::new(
+                            crate::error_codes::INVALID_REQUEST,
+                            e.to_string(),
+                        )
+                        .build(),
+                        None,
+                    );
+                    if let Ok(error_json) = serde_json::to_string(&error_response)
+                        && pipeline.send(error_json).await.is_err()
+                    {
+                        break;
+                    }
+                    continue;
+                }
+
+                budget.on_request();
+                let message_result = crate::borrowed::prepare(line.trim(), processor.as_ref());
+                codec.observe(message_result.is_ok());
 
                 match message_result {
-                    Ok(message) => {
-                        if let Some(response) = processor.process_message(message).await
-                            && let Ok(response_json) = serde_json::to_string(&response)
-                            && tx.send(response_json).await.is_err()
+                    Ok(crate::borrowed::Prepared::Cached(response_json)) => {
+                        if pipeline.send(response_json).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(crate::borrowed::Prepared::Batch(entries)) => {
+                        if let Ok(Some(response_json)) =
+                            super::batch::respond(processor.as_ref(), entries, remote_addr).await
+                            && pipeline.send(response_json).await.is_err()
                         {
                             break;
                         }
                     }
+                    Ok(crate::borrowed::Prepared::Message(message)) => {
+                        let inline = gate.as_ref().is_some_and(|gate| !gate.is_authenticated());
+                        if pipeline.process(&processor, message, inline).await.is_err() {
+                            break;
+                        }
+                        if gate.as_ref().is_some_and(|gate| gate.should_close()) {
+                            break;
+                        }
+                    }
                     Err(e) => {
-                        tracing::debug!(error = %e, "json-rpc parse failed");
+                        crate::rejection::record(
+                            crate::rejection::Rejection::new(
+                                crate::rejection::RejectionReason::ParseError,
+                            )
+                            .detail(e.to_string()),
+                        );
                         let error_response = crate::ResponseBuilder::new()
                             .error(
                                 crate::ErrorBuilder::new(
@@ -300,17 +673,29 @@ where
                             .build();
 
                         if let Ok(error_json) = serde_json::to_string(&error_response)
-                            && tx.send(error_json).await.is_err()
+                            && pipeline.send(error_json).await.is_err()
                         {
                             break;
                         }
                     }
                 }
             }
-            Err(_) => break,
+            Err(e) => {
+                let Some(reply) = super::framing::decode_failure(&e) else {
+                    break;
+                };
+                codec.observe(false);
+                if pipeline.send(reply).await.is_err() {
+                    break;
+                }
+            }
         }
     }
 
+    // let in-flight and queued responses and the closing notification reach
+    // the client
+    drop(pipeline);
+    super::lifetime::drain(writer_task, &security_config, remote_addr).await;
     Ok(())
 }
 
@@ -323,6 +708,15 @@ impl TcpStreamTlsClient {
     /// Connect to a TLS server (for testing - accepts self-signed certs)
     pub async fn connect_insecure(
         addr: impl AsRef<str>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::connect_insecure_with(addr, &super::socket::SocketOptions::default()).await
+    }
+
+    /// [`connect_insecure`](Self::connect_insecure) with TCP options for the
+    /// underlying connection
+    pub async fn connect_insecure_with(
+        addr: impl AsRef<str>,
+        socket_options: &super::socket::SocketOptions,
     ) -> Result<Self, Box<dyn std::error::Error>> {
         use tokio_rustls::TlsConnector;
         use tokio_rustls::rustls::ClientConfig;
@@ -335,6 +729,7 @@ impl TcpStreamTlsClient {
 
         let connector = TlsConnector::from(Arc::new(config));
         let stream = TcpStream::connect(addr.as_ref()).await?;
+        socket_options.apply(&stream)?;
 
         let domain = tokio_rustls::rustls::pki_types::ServerName::try_from("localhost")?;
         let tls_stream = connector.connect(domain.to_owned(), stream).await?;
@@ -483,6 +878,7 @@ b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2
             max_request_size: 1024,
             request_timeout: std::time::Duration::from_secs(30),
             idle_timeout: std::time::Duration::from_secs(60),
+            ..Default::default()
         };
         let builder = TcpStreamTlsServerBuilder::new("127.0.0.1:8443")
             .security_config(security_config.clone());
@@ -591,6 +987,7 @@ b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2
             max_request_size: 8192,
             request_timeout: std::time::Duration::from_secs(60),
             idle_timeout: std::time::Duration::from_secs(120),
+            ..Default::default()
         };
 
         let builder = TcpStreamTlsServerBuilder::new("127.0.0.1:8443")
@@ -647,6 +1044,15 @@ b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_validate_reports_missing_tls_config() {
+        let report = TcpStreamTlsServerBuilder::new("127.0.0.1:0")
+            .processor(MockProcessor)
+            .validate();
+        assert!(!report.is_ok());
+        assert!(report.errors().any(|p| p.component == "tls"));
+    }
+
     #[test]
     fn test_security_config_defaults_with_tls() {
         let config = SecurityConfig::default();
@@ -677,6 +1083,7 @@ b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2
             max_request_size: 4096,
             request_timeout: std::time::Duration::from_secs(30),
             idle_timeout: timeout,
+            ..Default::default()
         };
 
         let builder = TcpStreamTlsServerBuilder::new("127.0.0.1:8443")
@@ -685,4 +1092,12 @@ b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2
 
         assert_eq!(builder.security_config.idle_timeout, timeout);
     }
+
+    #[test]
+    fn test_peer_dn_from_client_certificate() {
+        let cert = rcgen::generate_simple_self_signed(vec!["billing".to_string()]).unwrap();
+        let dn = peer_dn(cert.cert.der()).unwrap();
+        assert!(dn.starts_with("CN="), "{dn}");
+        assert!(peer_dn(b"not a certificate").is_none());
+    }
 }
@@ -0,0 +1,296 @@
+//! Per-transport connection and traffic statistics
+//!
+//! Servers look up the [`TransportCounters`] of their transport with
+//! [`transport`] and report every accepted or rejected connection, the bytes
+//! moved over it, failed handshakes and idle-timeout closes. Like
+//! [`codec_stats`](super::codec_stats) the counters are process-wide;
+//! [`snapshot`] backs the Prometheus `transport_*` metrics.
+//!
+//! Servers that know their [`ListenerId`] count under [`listener`] instead,
+//! so each named listener gets its own counters and `listener` label.
+//!
+//! Custom transports can report under their own name the same way, wrapping
+//! their sockets in [`Metered`] to count bytes.
+
+use super::listener::ListenerId;
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// One request per connection over TCP
+pub const TCP: &str = "tcp";
+
+/// Persistent line-delimited TCP connections
+pub const TCP_STREAM: &str = "tcp-stream";
+
+/// Persistent line-delimited connections over TLS
+pub const TLS: &str = "tls";
+
+pub const WEBSOCKET: &str = "websocket";
+
+/// Live counters of one transport
+#[derive(Debug, Default)]
+pub struct TransportCounters {
+    accepted: AtomicU64,
+    rejected: AtomicU64,
+    active: AtomicU64,
+    bytes_received: AtomicU64,
+    bytes_sent: AtomicU64,
+    handshake_failures: AtomicU64,
+    idle_timeouts: AtomicU64,
+}
+
+impl TransportCounters {
+    /// Count an accepted connection, active until the guard is dropped
+    pub fn accepted(self: &Arc<Self>) -> ActiveConnection {
+        self.accepted.fetch_add(1, Ordering::Relaxed);
+        self.active.fetch_add(1, Ordering::Relaxed);
+        ActiveConnection(Arc::clone(self))
+    }
+
+    /// Count a connection closed right after accept, e.g. over the
+    /// connection limit
+    pub fn rejected(&self) {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn received(&self, bytes: usize) {
+        self.bytes_received
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn sent(&self, bytes: usize) {
+        self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Count a failed TLS handshake or WebSocket upgrade
+    pub fn handshake_failed(&self) {
+        self.handshake_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a connection closed for staying silent too long
+    pub fn idle_timeout(&self) {
+        self.idle_timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Keeps a connection counted as active, see [`TransportCounters::accepted`]
+#[derive(Debug)]
+pub struct ActiveConnection(Arc<TransportCounters>);
+
+impl Drop for ActiveConnection {
+    fn drop(&mut self) {
+        self.0.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Point-in-time statistics of one transport listener
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransportStats {
+    pub transport: String,
+    /// Listener name, empty for unnamed listeners
+    #[serde(default)]
+    pub listener: String,
+    pub accepted: u64,
+    pub rejected: u64,
+    pub active: u64,
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+    pub handshake_failures: u64,
+    pub idle_timeouts: u64,
+}
+
+type Transports = RwLock<BTreeMap<(String, String), Arc<TransportCounters>>>;
+
+fn transports() -> &'static Transports {
+    static TRANSPORTS: OnceLock<Transports> = OnceLock::new();
+    TRANSPORTS.get_or_init(Default::default)
+}
+
+/// Counters of `name`, created on first use
+pub fn transport(name: &str) -> Arc<TransportCounters> {
+    counters(name, "")
+}
+
+/// Counters of the listener `id`, under its protocol and name
+pub fn listener(id: &ListenerId) -> Arc<TransportCounters> {
+    counters(id.protocol, id.label())
+}
+
+fn counters(transport: &str, listener: &str) -> Arc<TransportCounters> {
+    let key = (transport.to_string(), listener.to_string());
+    if let Some(counters) = transports()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&key)
+    {
+        return Arc::clone(counters);
+    }
+    let mut transports = transports().write().unwrap_or_else(|e| e.into_inner());
+    Arc::clone(transports.entry(key).or_default())
+}
+
+/// Statistics of every transport listener seen so far, sorted by transport
+/// and listener name
+pub fn snapshot() -> Vec<TransportStats> {
+    transports()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|((transport, listener), counters)| TransportStats {
+            transport: transport.clone(),
+            listener: listener.clone(),
+            accepted: counters.accepted.load(Ordering::Relaxed),
+            rejected: counters.rejected.load(Ordering::Relaxed),
+            active: counters.active.load(Ordering::Relaxed),
+            bytes_received: counters.bytes_received.load(Ordering::Relaxed),
+            bytes_sent: counters.bytes_sent.load(Ordering::Relaxed),
+            handshake_failures: counters.handshake_failures.load(Ordering::Relaxed),
+            idle_timeouts: counters.idle_timeouts.load(Ordering::Relaxed),
+        })
+        .collect()
+}
+
+#[cfg(any(
+    feature = "tcp",
+    feature = "tcp-stream",
+    feature = "tcp-stream-tls",
+    feature = "websocket"
+))]
+pub use metered::Metered;
+
+#[cfg(any(
+    feature = "tcp",
+    feature = "tcp-stream",
+    feature = "tcp-stream-tls",
+    feature = "websocket"
+))]
+mod metered {
+    use super::TransportCounters;
+    use std::io;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+    use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+    /// Socket, or half of one, counting the bytes read from and written to it
+    #[derive(Debug)]
+    pub struct Metered<S> {
+        inner: S,
+        counters: Arc<TransportCounters>,
+    }
+
+    impl<S> Metered<S> {
+        pub fn new(inner: S, counters: Arc<TransportCounters>) -> Self {
+            Self { inner, counters }
+        }
+
+        pub fn get_ref(&self) -> &S {
+            &self.inner
+        }
+    }
+
+    impl<S: AsyncRead + Unpin> AsyncRead for Metered<S> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            let before = buf.filled().len();
+            let polled = Pin::new(&mut self.inner).poll_read(cx, buf);
+            if let Poll::Ready(Ok(())) = polled {
+                self.counters.received(buf.filled().len() - before);
+            }
+            polled
+        }
+    }
+
+    impl<S: AsyncWrite + Unpin> AsyncWrite for Metered<S> {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            let polled = Pin::new(&mut self.inner).poll_write(cx, buf);
+            if let Poll::Ready(Ok(written)) = polled {
+                self.counters.sent(written);
+            }
+            polled
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.inner).poll_flush(cx)
+        }
+
+        fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.inner).poll_shutdown(cx)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(name: &str) -> TransportStats {
+        snapshot()
+            .into_iter()
+            .find(|s| s.transport == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_connection_counts() {
+        let counters = transport("test-transport-a");
+        let first = counters.accepted();
+        let second = counters.accepted();
+        counters.rejected();
+        counters.handshake_failed();
+        drop(first);
+
+        let a = stats("test-transport-a");
+        assert_eq!((a.accepted, a.active, a.rejected), (2, 1, 1));
+        assert_eq!((a.handshake_failures, a.idle_timeouts), (1, 0));
+        drop(second);
+        assert_eq!(stats("test-transport-a").active, 0);
+    }
+
+    #[cfg(any(
+        feature = "tcp",
+        feature = "tcp-stream",
+        feature = "tcp-stream-tls",
+        feature = "websocket"
+    ))]
+    #[tokio::test]
+    async fn test_metered_counts_bytes() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (client, server) = tokio::io::duplex(64);
+        let mut server = Metered::new(server, transport("test-transport-b"));
+        let mut client = client;
+        client.write_all(b"hello").await.unwrap();
+        let mut buf = [0u8; 5];
+        server.read_exact(&mut buf).await.unwrap();
+        server.write_all(b"hi").await.unwrap();
+
+        let b = stats("test-transport-b");
+        assert_eq!((b.bytes_received, b.bytes_sent), (5, 2));
+    }
+
+    #[test]
+    fn test_listeners_counted_apart() {
+        let public = ListenerId::new("test-transport-c", None).name(Some("public".into()));
+        let _public = listener(&public).accepted();
+        let _unnamed = transport("test-transport-c").accepted();
+        let _again = listener(&public).accepted();
+
+        let counts: Vec<_> = snapshot()
+            .into_iter()
+            .filter(|s| s.transport == "test-transport-c")
+            .map(|s| (s.listener, s.accepted))
+            .collect();
+        assert_eq!(counts, vec![(String::new(), 1), ("public".to_string(), 2)]);
+    }
+}
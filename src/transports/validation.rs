@@ -0,0 +1,299 @@
+//! Startup configuration validation
+//!
+//! Server builders expose a `validate()` method that checks the configured
+//! components without serving traffic: the processor is set, the listen
+//! address resolves and can be bound, TLS material is present and the
+//! security limits are sane. The result is a [`ConfigReport`] that can be
+//! printed, logged or turned into a non-zero exit code for `--check-config`
+//! style dry runs.
+
+use super::security::SecurityConfig;
+use std::fmt;
+use std::net::ToSocketAddrs;
+
+/// Command line flag conventionally used to request a dry run
+pub const CHECK_CONFIG_FLAG: &str = "--check-config";
+
+/// Severity of a configuration problem
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProblemSeverity {
+    /// The server will start but the configuration is likely a mistake
+    Warning,
+    /// The server cannot start with this configuration
+    Error,
+}
+
+impl fmt::Display for ProblemSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProblemSeverity::Warning => write!(f, "warning"),
+            ProblemSeverity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A single problem found during validation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigProblem {
+    /// Component the problem belongs to (e.g. "listener", "tls", "auth")
+    pub component: String,
+    /// Human readable description
+    pub message: String,
+    pub severity: ProblemSeverity,
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}] {}: {}",
+            self.severity, self.component, self.message
+        )
+    }
+}
+
+/// Structured result of a configuration dry run
+#[derive(Debug, Clone, Default)]
+pub struct ConfigReport {
+    problems: Vec<ConfigProblem>,
+}
+
+impl ConfigReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an error-level problem
+    pub fn error(&mut self, component: impl Into<String>, message: impl Into<String>) {
+        self.push(component, message, ProblemSeverity::Error);
+    }
+
+    /// Record a warning-level problem
+    pub fn warning(&mut self, component: impl Into<String>, message: impl Into<String>) {
+        self.push(component, message, ProblemSeverity::Warning);
+    }
+
+    /// Record the outcome of an application-specific check.
+    ///
+    /// Useful for components the transport does not own, such as loading
+    /// an auth policy or reaching an audit backend.
+    pub fn check<E: fmt::Display>(&mut self, component: impl Into<String>, result: Result<(), E>) {
+        if let Err(e) = result {
+            self.error(component, e.to_string());
+        }
+    }
+
+    /// Append all problems from another report
+    pub fn merge(&mut self, other: ConfigReport) {
+        self.problems.extend(other.problems);
+    }
+
+    pub fn problems(&self) -> &[ConfigProblem] {
+        &self.problems
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ConfigProblem> {
+        self.problems
+            .iter()
+            .filter(|p| p.severity == ProblemSeverity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ConfigProblem> {
+        self.problems
+            .iter()
+            .filter(|p| p.severity == ProblemSeverity::Warning)
+    }
+
+    /// True when no error-level problems were found
+    pub fn is_ok(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    /// Convert into a `Result`, failing if any error-level problem exists
+    pub fn into_result(self) -> Result<Self, Self> {
+        if self.is_ok() { Ok(self) } else { Err(self) }
+    }
+
+    fn push(
+        &mut self,
+        component: impl Into<String>,
+        message: impl Into<String>,
+        severity: ProblemSeverity,
+    ) {
+        self.problems.push(ConfigProblem {
+            component: component.into(),
+            message: message.into(),
+            severity,
+        });
+    }
+}
+
+impl fmt::Display for ConfigReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.problems.is_empty() {
+            return write!(f, "configuration ok");
+        }
+        for (i, problem) in self.problems.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{problem}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns true if the process was started with [`CHECK_CONFIG_FLAG`]
+pub fn check_config_requested() -> bool {
+    std::env::args().any(|arg| arg == CHECK_CONFIG_FLAG)
+}
+
+/// Check that `addr` resolves and every resolved address can be bound.
+///
+/// The probe socket is bound with `SO_REUSEADDR` (the std listener default on
+/// Unix) and dropped immediately, so a later real bind is not blocked by
+/// lingering TIME_WAIT state.
+pub fn probe_bind(addr: &str, report: &mut ConfigReport) {
+    let addrs = match addr.to_socket_addrs() {
+        Ok(addrs) => addrs.collect::<Vec<_>>(),
+        Err(e) => {
+            report.error("listener", format!("cannot resolve address '{addr}': {e}"));
+            return;
+        }
+    };
+
+    if addrs.is_empty() {
+        report.error("listener", format!("address '{addr}' resolved to nothing"));
+        return;
+    }
+
+    for socket_addr in addrs {
+        if let Err(e) = std::net::TcpListener::bind(socket_addr) {
+            report.error("listener", format!("cannot bind {socket_addr}: {e}"));
+        }
+    }
+}
+
+/// Sanity-check security limits
+pub fn check_security_config(config: &SecurityConfig, report: &mut ConfigReport) {
+    if config.max_connections == 0 {
+        report.warning("security", "max_connections is unlimited");
+    }
+    if config.max_request_size == 0 {
+        report.warning("security", "max_request_size is unlimited");
+    }
+    if config.request_timeout.is_zero() {
+        report.error("security", "request_timeout must be greater than zero");
+    }
+    if config.idle_timeout.is_zero() {
+        report.error("security", "idle_timeout must be greater than zero");
+    }
+    if config.max_json_depth == 0 {
+        report.warning("security", "max_json_depth is unlimited");
+    }
+}
+
+/// Checks every socket server builder runs: a processor is set, the
+/// address can be bound and the security limits are sane, with the
+/// transport's own checks after the processor's
+#[cfg(any(feature = "tcp", feature = "tcp-stream", feature = "tcp-stream-tls"))]
+pub(crate) fn check_server(
+    has_processor: bool,
+    addr: &str,
+    security_config: &SecurityConfig,
+    transport: impl FnOnce(&mut ConfigReport),
+) -> ConfigReport {
+    let mut report = ConfigReport::new();
+    if !has_processor {
+        report.error("processor", "Processor not set");
+    }
+    transport(&mut report);
+    probe_bind(addr, &mut report);
+    check_security_config(security_config, &mut report);
+    report
+}
+
+/// Check that `codec` can be used with `framing`
+#[cfg(any(feature = "tcp-stream", feature = "tcp-stream-tls"))]
+pub(crate) fn check_wire(
+    framing: super::framing::Framing,
+    codec: &std::sync::Arc<dyn crate::codec::Codec>,
+    report: &mut ConfigReport,
+) {
+    if let Err(e) = super::framing::Wire::new(framing, std::sync::Arc::clone(codec)) {
+        report.error("codec", e.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_empty_report_is_ok() {
+        let report = ConfigReport::new();
+        assert!(report.is_ok());
+        assert_eq!(report.to_string(), "configuration ok");
+    }
+
+    #[test]
+    fn test_warnings_do_not_fail_report() {
+        let mut report = ConfigReport::new();
+        report.warning("security", "something odd");
+        assert!(report.is_ok());
+        assert_eq!(report.warnings().count(), 1);
+        assert!(report.into_result().is_ok());
+    }
+
+    #[test]
+    fn test_check_records_errors() {
+        let mut report = ConfigReport::new();
+        report.check("auth", Ok::<(), String>(()));
+        report.check("audit", Err("backend unreachable"));
+        assert!(!report.is_ok());
+        assert_eq!(report.errors().count(), 1);
+        assert_eq!(
+            report.to_string(),
+            "[error] audit: backend unreachable".to_string()
+        );
+    }
+
+    #[test]
+    fn test_probe_bind_in_use() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let mut report = ConfigReport::new();
+        probe_bind(&addr, &mut report);
+        assert!(!report.is_ok());
+
+        let mut report = ConfigReport::new();
+        probe_bind("127.0.0.1:0", &mut report);
+        assert!(report.is_ok());
+    }
+
+    #[test]
+    fn test_probe_bind_unresolvable() {
+        let mut report = ConfigReport::new();
+        probe_bind("not an address", &mut report);
+        assert_eq!(report.errors().count(), 1);
+    }
+
+    #[test]
+    fn test_check_security_config() {
+        let mut report = ConfigReport::new();
+        check_security_config(&SecurityConfig::default(), &mut report);
+        assert!(report.problems().is_empty());
+
+        let config = SecurityConfig {
+            max_connections: 0,
+            request_timeout: Duration::ZERO,
+            ..Default::default()
+        };
+        let mut report = ConfigReport::new();
+        check_security_config(&config, &mut report);
+        assert_eq!(report.warnings().count(), 1);
+        assert_eq!(report.errors().count(), 1);
+    }
+}
@@ -0,0 +1,1872 @@
+//! WebSocket transport for JSON-RPC servers and clients.
+//!
+//! Each JSON-RPC message travels as one text message over a persistent
+//! RFC 6455 connection. [`SecurityConfig`] is honored as on the TCP
+//! transports: `max_request_size` caps a whole (possibly fragmented)
+//! message, `idle_timeout` closes connections that stay silent, and the
+//! connection lifetime and request budgets recycle connections.
+//!
+//! With the `streaming` feature, a server given a
+//! [`StreamManager`](crate::streaming::StreamManager) also accepts
+//! subscription requests for the manager's methods and pushes their
+//! [`StreamEvent`](crate::streaming::StreamEvent)s to the connection that
+//! subscribed. Streams still open when a connection goes away are
+//! unsubscribed. A manager must be served by a single server, which takes
+//! over its event queue.
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "streaming")]
+//! # async fn example(registry: ash_rpc::MethodRegistry) -> Result<(), Box<dyn std::error::Error>> {
+//! use ash_rpc::streaming::StreamManager;
+//! use ash_rpc::transports::WebSocketServer;
+//! use std::sync::Arc;
+//!
+//! let streams = Arc::new(StreamManager::new());
+//! let server = WebSocketServer::builder("127.0.0.1:8080")
+//!     .processor(registry)
+//!     .path("/rpc")
+//!     .streams(streams)
+//!     .build()?;
+//! server.run().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Servers given binary [`Codec`](crate::codec::Codec)s offer them as
+//! subprotocols; a client that asks for one with `Sec-WebSocket-Protocol`
+//! gets binary messages in that encoding, see
+//! [`WebSocketClient::connect_with_codec`].
+//!
+//! Only plain `ws://` is supported; terminate TLS in front of the server.
+
+use super::security::SecurityConfig;
+use crate::codec::{Codec, CodecError, JsonCodec};
+use crate::{Message, MessageProcessor};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::net::TcpStream;
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::sync::mpsc;
+
+/// Errors of the WebSocket layer
+#[derive(Debug)]
+pub enum WebSocketError {
+    Io(io::Error),
+    /// The HTTP upgrade was refused or malformed
+    Handshake(String),
+    /// The peer broke the framing rules
+    Protocol(String),
+    /// A message exceeded the size limit
+    MessageTooLarge {
+        limit: usize,
+    },
+    /// A message could not be encoded or decoded as JSON
+    Json(serde_json::Error),
+    /// A binary message did not match the negotiated codec
+    Codec(CodecError),
+}
+
+impl fmt::Display for WebSocketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebSocketError::Io(e) => write!(f, "websocket I/O error: {e}"),
+            WebSocketError::Handshake(e) => write!(f, "websocket handshake failed: {e}"),
+            WebSocketError::Protocol(e) => write!(f, "websocket protocol error: {e}"),
+            WebSocketError::MessageTooLarge { limit } => {
+                write!(f, "websocket message exceeds {limit} bytes")
+            }
+            WebSocketError::Json(e) => write!(f, "invalid JSON message: {e}"),
+            WebSocketError::Codec(e) => write!(f, "invalid binary message: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for WebSocketError {}
+
+impl From<io::Error> for WebSocketError {
+    fn from(e: io::Error) -> Self {
+        WebSocketError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for WebSocketError {
+    fn from(e: serde_json::Error) -> Self {
+        WebSocketError::Json(e)
+    }
+}
+
+impl From<CodecError> for WebSocketError {
+    fn from(e: CodecError) -> Self {
+        WebSocketError::Codec(e)
+    }
+}
+
+const CONTINUATION: u8 = 0x0;
+const TEXT: u8 = 0x1;
+const BINARY: u8 = 0x2;
+const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xA;
+
+const CLOSE_NORMAL: u16 = 1000;
+const CLOSE_GOING_AWAY: u16 = 1001;
+const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+const CLOSE_TOO_BIG: u16 = 1009;
+
+/// Upper bound of the HTTP upgrade request or response head
+const MAX_HANDSHAKE_SIZE: u64 = 8 * 1024;
+/// Largest message a client accepts from a server
+const MAX_CLIENT_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// `Sec-WebSocket-Accept` value answering `key`
+fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{key}{ACCEPT_GUID}").as_bytes()))
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64).wrapping_mul(8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut w = [0u32; 80];
+        for (word, bytes) in w.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (bytes, state) in digest.chunks_exact_mut(4).zip(h) {
+        bytes.copy_from_slice(&state.to_be_bytes());
+    }
+    digest
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, byte)| n | u32::from(*byte) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    for chunk in bytes.chunks_mut(16) {
+        let random = uuid::Uuid::new_v4();
+        chunk.copy_from_slice(&random.as_bytes()[..chunk.len()]);
+    }
+    bytes
+}
+
+/// Read an HTTP head up to the blank line, one entry per line
+async fn read_http_head<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+) -> Result<Vec<String>, WebSocketError> {
+    let mut lines = Vec::new();
+    let mut used = 0;
+    loop {
+        let mut line = String::new();
+        let read = (&mut *reader)
+            .take(MAX_HANDSHAKE_SIZE - used)
+            .read_line(&mut line)
+            .await?;
+        if read == 0 {
+            return Err(WebSocketError::Handshake(
+                "connection closed during handshake".into(),
+            ));
+        }
+        used += read as u64;
+        if !line.ends_with('\n') {
+            return Err(WebSocketError::Handshake("handshake too large".into()));
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            return Ok(lines);
+        }
+        lines.push(line.to_string());
+    }
+}
+
+/// Header values keyed by lowercase name
+fn parse_headers<'a>(lines: impl Iterator<Item = &'a String>) -> HashMap<String, String> {
+    lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect()
+}
+
+fn has_token(headers: &HashMap<String, String>, name: &str, token: &str) -> bool {
+    headers.get(name).is_some_and(|value| {
+        value
+            .split(',')
+            .any(|part| part.trim().eq_ignore_ascii_case(token))
+    })
+}
+
+/// A valid upgrade request
+struct Upgrade {
+    path: String,
+    key: String,
+    /// `Sec-WebSocket-Protocol` values, in the client's order of preference
+    protocols: Vec<String>,
+}
+
+/// Validate an upgrade request
+async fn read_upgrade_request<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+) -> Result<Upgrade, WebSocketError> {
+    let head = read_http_head(reader).await?;
+    let mut request_line = head.first().map(|line| line.split_whitespace());
+    let (Some("GET"), Some(path)) = (
+        request_line.as_mut().and_then(Iterator::next),
+        request_line.as_mut().and_then(Iterator::next),
+    ) else {
+        return Err(WebSocketError::Handshake("expected a GET request".into()));
+    };
+    let headers = parse_headers(head.iter().skip(1));
+    if !has_token(&headers, "upgrade", "websocket") || !has_token(&headers, "connection", "upgrade")
+    {
+        return Err(WebSocketError::Handshake("not a websocket upgrade".into()));
+    }
+    if headers.get("sec-websocket-version").map(String::as_str) != Some("13") {
+        return Err(WebSocketError::Handshake(
+            "unsupported websocket version".into(),
+        ));
+    }
+    let key = headers
+        .get("sec-websocket-key")
+        .ok_or_else(|| WebSocketError::Handshake("missing Sec-WebSocket-Key".into()))?;
+    let protocols = headers
+        .get("sec-websocket-protocol")
+        .map(|value| value.split(',').map(|p| p.trim().to_string()).collect())
+        .unwrap_or_default();
+    Ok(Upgrade {
+        path: path.to_string(),
+        key: key.clone(),
+        protocols,
+    })
+}
+
+/// The first of the client's `protocols` naming one of `codecs`
+fn negotiate(protocols: &[String], codecs: &[Arc<dyn Codec>]) -> Option<Arc<dyn Codec>> {
+    protocols
+        .iter()
+        .find_map(|protocol| codecs.iter().find(|codec| codec.name() == protocol))
+        .cloned()
+}
+
+fn http_error(status: &str) -> String {
+    format!("HTTP/1.1 {status}\r\nConnection: close\r\nContent-Length: 0\r\n\r\n")
+}
+
+/// One message, or a control frame, received from the peer
+#[derive(Debug, PartialEq)]
+enum Incoming {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong,
+    Close,
+}
+
+impl Incoming {
+    /// Payload of a data message
+    fn data(&self) -> Option<&[u8]> {
+        match self {
+            Incoming::Text(text) => Some(text.as_bytes()),
+            Incoming::Binary(payload) => Some(payload),
+            _ => None,
+        }
+    }
+}
+
+/// Reads frames and reassembles fragmented messages
+struct FrameReader<R> {
+    reader: R,
+    /// Limit of a whole message, 0 for none
+    max_message_size: usize,
+    /// Whether frames from the peer must be masked (clients mask, servers don't)
+    masked: bool,
+    /// Opcode and payload so far of a fragmented message
+    partial: Option<(u8, Vec<u8>)>,
+}
+
+impl<R: AsyncRead + Unpin> FrameReader<R> {
+    fn new(reader: R, max_message_size: usize, masked: bool) -> Self {
+        Self {
+            reader,
+            max_message_size,
+            masked,
+            partial: None,
+        }
+    }
+
+    async fn next(&mut self) -> Result<Incoming, WebSocketError> {
+        loop {
+            let (fin, opcode, payload) = self.read_frame().await?;
+            match opcode {
+                PING => return Ok(Incoming::Ping(payload)),
+                PONG => return Ok(Incoming::Pong),
+                CLOSE => return Ok(Incoming::Close),
+                TEXT | BINARY => {
+                    if self.partial.is_some() {
+                        return Err(WebSocketError::Protocol(
+                            "new message inside a fragmented one".into(),
+                        ));
+                    }
+                    if fin {
+                        return message(opcode, payload);
+                    }
+                    self.partial = Some((opcode, payload));
+                }
+                CONTINUATION => {
+                    let Some((_, partial)) = &mut self.partial else {
+                        return Err(WebSocketError::Protocol("unexpected continuation".into()));
+                    };
+                    if self.max_message_size > 0
+                        && partial.len() + payload.len() > self.max_message_size
+                    {
+                        return Err(WebSocketError::MessageTooLarge {
+                            limit: self.max_message_size,
+                        });
+                    }
+                    partial.extend_from_slice(&payload);
+                    if fin {
+                        let (opcode, payload) = self.partial.take().unwrap_or_default();
+                        return message(opcode, payload);
+                    }
+                }
+                other => {
+                    return Err(WebSocketError::Protocol(format!("unknown opcode {other}")));
+                }
+            }
+        }
+    }
+
+    async fn read_frame(&mut self) -> Result<(bool, u8, Vec<u8>), WebSocketError> {
+        let mut head = [0u8; 2];
+        self.reader.read_exact(&mut head).await?;
+        let fin = head[0] & 0x80 != 0;
+        let opcode = head[0] & 0x0F;
+        if head[0] & 0x70 != 0 {
+            return Err(WebSocketError::Protocol("reserved bits set".into()));
+        }
+        if (head[1] & 0x80 != 0) != self.masked {
+            return Err(WebSocketError::Protocol("wrong frame masking".into()));
+        }
+        let len = match head[1] & 0x7F {
+            126 => u64::from(self.reader.read_u16().await?),
+            127 => self.reader.read_u64().await?,
+            n => u64::from(n),
+        };
+        if opcode >= CLOSE && (len > 125 || !fin) {
+            return Err(WebSocketError::Protocol("invalid control frame".into()));
+        }
+        if self.max_message_size > 0 && len > self.max_message_size as u64 {
+            return Err(WebSocketError::MessageTooLarge {
+                limit: self.max_message_size,
+            });
+        }
+        let mut mask = [0u8; 4];
+        if self.masked {
+            self.reader.read_exact(&mut mask).await?;
+        }
+        // grows with the bytes that arrive rather than the length the peer
+        // claims, which is unchecked when there is no size limit
+        let mut payload = Vec::new();
+        (&mut self.reader)
+            .take(len)
+            .read_to_end(&mut payload)
+            .await?;
+        if (payload.len() as u64) < len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        if self.masked {
+            for (i, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask[i % 4];
+            }
+        }
+        Ok((fin, opcode, payload))
+    }
+}
+
+fn message(opcode: u8, payload: Vec<u8>) -> Result<Incoming, WebSocketError> {
+    if opcode == BINARY {
+        return Ok(Incoming::Binary(payload));
+    }
+    String::from_utf8(payload)
+        .map(Incoming::Text)
+        .map_err(|_| WebSocketError::Protocol("message is not valid UTF-8".into()))
+}
+
+fn encode_frame(opcode: u8, payload: &[u8], mask: Option<[u8; 4]>) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 14);
+    frame.push(0x80 | opcode);
+    let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+    match payload.len() {
+        len if len < 126 => frame.push(mask_bit | len as u8),
+        len if len <= usize::from(u16::MAX) => {
+            frame.push(mask_bit | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(mask_bit | 127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    match mask {
+        Some(key) => {
+            frame.extend_from_slice(&key);
+            frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ key[i % 4]));
+        }
+        None => frame.extend_from_slice(payload),
+    }
+    frame
+}
+
+fn close_payload(code: u16, reason: &str) -> Vec<u8> {
+    let mut payload = code.to_be_bytes().to_vec();
+    payload.extend_from_slice(reason.as_bytes());
+    payload
+}
+
+/// Frames queued for a server connection's writer
+enum Outgoing {
+    Text(String),
+    Pong(Vec<u8>),
+    Close(u16, &'static str),
+}
+
+impl From<String> for Outgoing {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+/// Write queued frames, JSON text as binary messages under a binary `codec`
+async fn write_frames<W: AsyncWrite + Unpin>(
+    mut writer: W,
+    mut queue: mpsc::Receiver<Outgoing>,
+    codec: Arc<dyn Codec>,
+    tap: Option<super::tap::ConnectionTap>,
+) {
+    while let Some(outgoing) = queue.recv().await {
+        let (frame, last) = match outgoing {
+            Outgoing::Text(text) => {
+                let (opcode, payload) = if codec.is_binary() {
+                    match codec.encode_json(&text) {
+                        Ok(payload) => (BINARY, payload),
+                        Err(e) => {
+                            tracing::warn!(codec = codec.name(), error = %e, "dropping unencodable message");
+                            continue;
+                        }
+                    }
+                } else {
+                    (TEXT, text.into_bytes())
+                };
+                if let Some(tap) = &tap {
+                    tap.capture(super::tap::TapDirection::Outbound, &payload);
+                }
+                (encode_frame(opcode, &payload, None), false)
+            }
+            Outgoing::Pong(payload) => (encode_frame(PONG, &payload, None), false),
+            Outgoing::Close(code, reason) => (
+                encode_frame(CLOSE, &close_payload(code, reason), None),
+                true,
+            ),
+        };
+        if writer.write_all(&frame).await.is_err() || writer.flush().await.is_err() {
+            return;
+        }
+        if last {
+            let _ = writer.shutdown().await;
+            return;
+        }
+    }
+}
+
+/// `fut`, abandoned after `limit` unless `limit` is zero
+async fn within<F: Future>(limit: Duration, fut: F) -> Option<F::Output> {
+    if limit.is_zero() {
+        Some(fut.await)
+    } else {
+        tokio::time::timeout(limit, fut).await.ok()
+    }
+}
+
+pub struct WebSocketServerBuilder {
+    addr: String,
+    processor: Option<Arc<dyn MessageProcessor + Send + Sync>>,
+    security_config: SecurityConfig,
+    supervision: super::supervisor::SupervisionPolicy,
+    socket_options: super::socket::SocketOptions,
+    name: Option<String>,
+    method_filter: super::listener::MethodFilter,
+    json_format: crate::serialization::JsonFormat,
+    path: Option<String>,
+    codecs: Vec<Arc<dyn Codec>>,
+    tap: Option<Arc<super::tap::WireTap>>,
+    #[cfg(feature = "streaming")]
+    streams: Option<Arc<crate::streaming::StreamManager>>,
+}
+
+impl WebSocketServerBuilder {
+    pub fn new(addr: impl Into<String>) -> Self {
+        Self {
+            addr: addr.into(),
+            processor: None,
+            security_config: SecurityConfig::default(),
+            supervision: super::supervisor::SupervisionPolicy::default(),
+            socket_options: super::socket::SocketOptions::default(),
+            name: None,
+            method_filter: super::listener::MethodFilter::default(),
+            json_format: crate::serialization::JsonFormat::default(),
+            path: None,
+            codecs: Vec::new(),
+            tap: None,
+            #[cfg(feature = "streaming")]
+            streams: None,
+        }
+    }
+
+    pub fn processor<P>(mut self, processor: P) -> Self
+    where
+        P: MessageProcessor + Send + Sync + 'static,
+    {
+        self.processor = Some(Arc::new(processor));
+        self
+    }
+
+    pub fn security_config(mut self, config: SecurityConfig) -> Self {
+        self.security_config = config;
+        self
+    }
+
+    pub fn max_connections(mut self, max: usize) -> Self {
+        self.security_config.max_connections = max;
+        self
+    }
+
+    /// Largest message accepted, fragments included
+    pub fn max_request_size(mut self, size: usize) -> Self {
+        self.security_config.max_request_size = size;
+        self
+    }
+
+    /// How to recover from failing `accept()` calls
+    pub fn supervision(mut self, policy: super::supervisor::SupervisionPolicy) -> Self {
+        self.supervision = policy;
+        self
+    }
+
+    /// TCP options applied to every accepted connection
+    pub fn socket_options(mut self, options: super::socket::SocketOptions) -> Self {
+        self.socket_options = options;
+        self
+    }
+
+    /// Name this listener; the name is exposed as `ConnectionContext::origin`
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Restrict which methods may be called through this listener
+    pub fn method_filter(mut self, filter: super::listener::MethodFilter) -> Self {
+        self.method_filter = filter;
+        self
+    }
+
+    /// Output format handlers convert results with; frames are always
+    /// written compact
+    pub fn json_format(mut self, format: crate::serialization::JsonFormat) -> Self {
+        self.json_format = format;
+        self
+    }
+
+    /// Only accept upgrades for `path`; any path is accepted by default
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Offer `codec` as a subprotocol; JSON text is always accepted
+    pub fn codec(mut self, codec: impl Codec) -> Self {
+        self.codecs.push(Arc::new(codec));
+        self
+    }
+
+    /// Mirror the messages of sampled connections to `tap`
+    pub fn tap(mut self, tap: super::tap::WireTap) -> Self {
+        self.tap = Some(Arc::new(tap));
+        self
+    }
+
+    /// Serve subscriptions of `manager` and push its events to subscribers
+    #[cfg(feature = "streaming")]
+    pub fn streams(mut self, manager: Arc<crate::streaming::StreamManager>) -> Self {
+        self.streams = Some(manager);
+        self
+    }
+
+    pub fn build(self) -> Result<WebSocketServer, io::Error> {
+        let processor = self
+            .processor
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Processor not set"))?;
+        let processor = super::listener::ListenerProcessor::wrap(
+            processor,
+            self.name.clone(),
+            self.method_filter,
+        );
+
+        Ok(WebSocketServer {
+            addr: self.addr,
+            name: self.name,
+            json_format: self.json_format,
+            processor,
+            security_config: crate::reload::Reloadable::new(self.security_config),
+            supervision: self.supervision,
+            socket_options: self.socket_options,
+            path: self.path.map(Arc::from),
+            codecs: Arc::from(self.codecs),
+            tap: self.tap,
+            #[cfg(feature = "streaming")]
+            streams: self.streams,
+            active_connections: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+}
+
+pub struct WebSocketServer {
+    addr: String,
+    name: Option<String>,
+    json_format: crate::serialization::JsonFormat,
+    processor: Arc<dyn MessageProcessor + Send + Sync>,
+    security_config: crate::reload::Reloadable<SecurityConfig>,
+    supervision: super::supervisor::SupervisionPolicy,
+    socket_options: super::socket::SocketOptions,
+    path: Option<Arc<str>>,
+    codecs: Arc<[Arc<dyn Codec>]>,
+    tap: Option<Arc<super::tap::WireTap>>,
+    #[cfg(feature = "streaming")]
+    streams: Option<Arc<crate::streaming::StreamManager>>,
+    active_connections: Arc<AtomicUsize>,
+}
+
+/// Per-connection settings handed to [`handle_connection`]
+#[derive(Clone)]
+struct ConnectionConfig {
+    processor: Arc<dyn MessageProcessor + Send + Sync>,
+    security_config: SecurityConfig,
+    path: Option<Arc<str>>,
+    codecs: Arc<[Arc<dyn Codec>]>,
+    tap: Option<Arc<super::tap::WireTap>>,
+    listener: Option<super::listener::ListenerId>,
+    json_format: crate::serialization::JsonFormat,
+    #[cfg(feature = "streaming")]
+    streams: Option<Arc<super::stream_router::StreamRouter<Outgoing>>>,
+}
+
+impl WebSocketServer {
+    pub fn builder(addr: impl Into<String>) -> WebSocketServerBuilder {
+        WebSocketServerBuilder::new(addr)
+    }
+
+    /// Handle to the live security configuration.
+    ///
+    /// Storing a new config affects connections accepted afterwards.
+    pub fn security_handle(&self) -> crate::reload::Reloadable<SecurityConfig> {
+        self.security_config.clone()
+    }
+
+    pub async fn run(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let listener = tokio::net::TcpListener::bind(&self.addr).await?;
+        let initial = self.security_config.load();
+        let id = super::listener::ListenerId::new(
+            super::transport_stats::WEBSOCKET,
+            listener.local_addr().ok(),
+        )
+        .name(self.name.clone());
+        tracing::info!(
+            addr = %self.addr,
+            listener = %id,
+            protocol = "websocket",
+            max_connections = initial.max_connections,
+            max_request_size = initial.max_request_size,
+            "server listening"
+        );
+
+        #[cfg(feature = "streaming")]
+        let streams = self
+            .streams
+            .clone()
+            .map(super::stream_router::StreamRouter::start);
+
+        let mut supervisor = super::supervisor::AcceptSupervisor::new(self.supervision.clone());
+        let transport = super::transport_stats::listener(&id);
+        loop {
+            let (stream, addr) = match listener.accept().await {
+                Ok(accepted) => {
+                    supervisor.on_success();
+                    accepted
+                }
+                Err(e) => {
+                    supervisor.on_error(e).await?;
+                    continue;
+                }
+            };
+
+            let current_connections = self.active_connections.load(Ordering::Relaxed);
+            let security_config = self.security_config.load();
+            if security_config.max_connections > 0
+                && current_connections >= security_config.max_connections
+            {
+                crate::rejection::record(
+                    crate::rejection::Rejection::new(
+                        crate::rejection::RejectionReason::ConnectionLimit,
+                    )
+                    .remote_addr(Some(addr))
+                    .detail(format!(
+                        "{current_connections} of {} connections in use",
+                        security_config.max_connections
+                    )),
+                );
+                transport.rejected();
+                drop(stream);
+                continue;
+            }
+
+            self.socket_options.apply_accepted(&stream, addr);
+            self.active_connections.fetch_add(1, Ordering::Relaxed);
+            tracing::debug!(remote_addr = %addr, active_connections = current_connections + 1, "new connection");
+
+            let config = ConnectionConfig {
+                processor: Arc::clone(&self.processor),
+                security_config: SecurityConfig::clone(&security_config),
+                path: self.path.clone(),
+                codecs: Arc::clone(&self.codecs),
+                tap: self.tap.clone(),
+                listener: Some(id.clone()),
+                json_format: self.json_format,
+                #[cfg(feature = "streaming")]
+                streams: streams.clone(),
+            };
+            let active_connections = Arc::clone(&self.active_connections);
+            let active = transport.accepted();
+            let span = tracing::info_span!("connection", listener = %id, remote_addr = %addr);
+            let connection = async move {
+                let result = handle_connection(stream, config).await;
+                active_connections.fetch_sub(1, Ordering::Relaxed);
+                drop(active);
+                if let Err(e) = result {
+                    tracing::debug!(remote_addr = %addr, error = %e, "websocket connection failed");
+                }
+            };
+            tokio::spawn(tracing::Instrument::instrument(connection, span));
+        }
+    }
+}
+
+async fn handle_connection(
+    stream: TcpStream,
+    config: ConnectionConfig,
+) -> Result<(), WebSocketError> {
+    let remote_addr = stream.peer_addr().ok();
+    let transport = config.listener.as_ref().map_or_else(
+        || super::transport_stats::transport(super::transport_stats::WEBSOCKET),
+        super::transport_stats::listener,
+    );
+    let (reader, writer) = stream.into_split();
+    let mut reader = BufReader::new(super::transport_stats::Metered::new(
+        reader,
+        Arc::clone(&transport),
+    ));
+    let mut writer = super::transport_stats::Metered::new(writer, Arc::clone(&transport));
+
+    let upgrade = match read_upgrade_request(&mut reader).await {
+        Ok(upgrade) if config.path.as_deref().is_some_and(|p| p != upgrade.path) => Err((
+            "404 Not Found",
+            WebSocketError::Handshake(format!("unknown path {}", upgrade.path)),
+        )),
+        Ok(upgrade) => Ok(upgrade),
+        Err(e) => Err(("400 Bad Request", e)),
+    };
+    let upgrade = match upgrade {
+        Ok(upgrade) => upgrade,
+        Err((status, e)) => {
+            crate::rejection::record(
+                crate::rejection::Rejection::new(crate::rejection::RejectionReason::ParseError)
+                    .remote_addr(remote_addr)
+                    .detail(e.to_string()),
+            );
+            transport.handshake_failed();
+            let _ = writer.write_all(http_error(status).as_bytes()).await;
+            return Err(e);
+        }
+    };
+    let negotiated = negotiate(&upgrade.protocols, &config.codecs);
+    let protocol = negotiated
+        .as_ref()
+        .map(|codec| format!("Sec-WebSocket-Protocol: {}\r\n", codec.name()))
+        .unwrap_or_default();
+    let response = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n{protocol}\r\n",
+        accept_key(&upgrade.key)
+    );
+    writer.write_all(response.as_bytes()).await?;
+
+    let codec = match (&negotiated, upgrade.protocols.first()) {
+        (Some(negotiated), _) => super::codec_stats::negotiated(negotiated.name()),
+        (None, Some(requested)) => {
+            super::codec_stats::fallback(requested, super::codec_stats::WEBSOCKET)
+        }
+        (None, None) => super::codec_stats::negotiated(super::codec_stats::WEBSOCKET),
+    };
+    let encoding = negotiated.unwrap_or_else(|| Arc::new(JsonCodec));
+    let (tx, rx) = mpsc::channel::<Outgoing>(100);
+    let tap = config
+        .tap
+        .as_ref()
+        .and_then(|tap| tap.connection(super::transport_stats::WEBSOCKET, remote_addr));
+    let writer_task = tokio::spawn(write_frames(writer, rx, Arc::clone(&encoding), tap.clone()));
+    let security_config = &config.security_config;
+    let mut connection = remote_addr
+        .map(crate::auth::ConnectionContext::with_addr)
+        .unwrap_or_default();
+    if let Some(listener) = config.listener.clone() {
+        connection = connection.with_listener(listener);
+    }
+    if config.json_format != crate::serialization::JsonFormat::default() {
+        connection = connection.with_json_format(config.json_format);
+    }
+    let processor: Arc<dyn MessageProcessor + Send + Sync> = Arc::new(
+        super::connection::ConnectionProcessor::new(Arc::clone(&config.processor), connection),
+    );
+    let mut frames = FrameReader::new(reader, security_config.max_request_size, true);
+    let mut budget = super::lifetime::ConnectionBudget::new(security_config);
+    #[cfg(feature = "streaming")]
+    let mut owned = config
+        .streams
+        .clone()
+        .map(super::stream_router::OwnedStreams::new);
+
+    let close = loop {
+        if let Some(reason) = budget.exhausted() {
+            if let Some(notification) = budget.recycle(reason, remote_addr) {
+                let _ = tx.send(Outgoing::Text(notification)).await;
+            }
+            break Some((CLOSE_GOING_AWAY, "connection recycled"));
+        }
+
+        let Some(next) = budget
+            .guard(within(security_config.idle_timeout, frames.next()))
+            .await
+        else {
+            continue;
+        };
+        let Some(next) = next else {
+            crate::rejection::record(
+                crate::rejection::Rejection::new(crate::rejection::RejectionReason::Timeout)
+                    .remote_addr(remote_addr),
+            );
+            transport.idle_timeout();
+            break Some((CLOSE_GOING_AWAY, "idle timeout"));
+        };
+        if let (Some(tap), Ok(incoming)) = (&tap, &next)
+            && let Some(data) = incoming.data()
+        {
+            tap.capture(super::tap::TapDirection::Inbound, data);
+        }
+        let text = match next {
+            Ok(Incoming::Text(text)) => text,
+            Ok(Incoming::Binary(payload)) => match encoding.decode_json(&payload) {
+                Ok(text) => text,
+                Err(e) => {
+                    codec.observe(false);
+                    crate::rejection::record(
+                        crate::rejection::Rejection::new(
+                            crate::rejection::RejectionReason::ParseError,
+                        )
+                        .remote_addr(remote_addr)
+                        .detail(e.to_string()),
+                    );
+                    let error_response = crate::Response::error(
+                        crate::ErrorBuilder::new(crate::error_codes::PARSE_ERROR, e.to_string())
+                            .build(),
+                        None,
+                    );
+                    if tx
+                        .send(Outgoing::Text(serde_json::to_string(&error_response)?))
+                        .await
+                        .is_err()
+                    {
+                        break None;
+                    }
+                    continue;
+                }
+            },
+            Ok(Incoming::Ping(payload)) => {
+                let _ = tx.send(Outgoing::Pong(payload)).await;
+                continue;
+            }
+            Ok(Incoming::Pong) => continue,
+            Ok(Incoming::Close) => break Some((CLOSE_NORMAL, "")),
+            Err(WebSocketError::MessageTooLarge { limit }) => {
+                crate::rejection::record(
+                    crate::rejection::Rejection::new(
+                        crate::rejection::RejectionReason::RequestTooLarge,
+                    )
+                    .remote_addr(remote_addr)
+                    .detail(format!("message exceeds {limit} bytes")),
+                );
+                break Some((CLOSE_TOO_BIG, "message too large"));
+            }
+            Err(WebSocketError::Protocol(e)) => {
+                tracing::debug!(remote_addr = ?remote_addr, error = %e, "websocket protocol error");
+                break Some((CLOSE_PROTOCOL_ERROR, "protocol error"));
+            }
+            Err(_) => break None,
+        };
+
+        if let Err(e) = security_config.check_json_limits(&text) {
+            crate::rejection::record(
+                crate::rejection::Rejection::new(crate::rejection::RejectionReason::JsonTooComplex)
+                    .remote_addr(remote_addr)
+                    .detail(e.to_string()),
+            );
+            let error_response = crate::Response::error(
+                crate::ErrorBuilder::new(crate::error_codes::INVALID_REQUEST, e.to_string())
+                    .build(),
+                None,
+            );
+            if tx
+                .send(Outgoing::Text(serde_json::to_string(&error_response)?))
+                .await
+                .is_err()
+            {
+                break None;
+            }
+            continue;
+        }
+
+        budget.on_request();
+        #[cfg(feature = "streaming")]
+        if let Some(owned) = &mut owned
+            && let Some(reply) = owned.handle(&text, processor.as_ref(), &tx).await
+        {
+            if tx.send(Outgoing::Text(reply)).await.is_err() {
+                break None;
+            }
+            continue;
+        }
+
+        let prepared = crate::borrowed::prepare(&text, processor.as_ref());
+        codec.observe(prepared.is_ok());
+        let reply = match prepared {
+            Ok(crate::borrowed::Prepared::Cached(json)) => Some(json),
+            Ok(crate::borrowed::Prepared::Batch(entries)) => {
+                super::batch::respond(processor.as_ref(), entries, remote_addr).await?
+            }
+            Ok(crate::borrowed::Prepared::Message(message)) => {
+                match processor.process_message(message).await {
+                    Some(response) => Some(serde_json::to_string(&response)?),
+                    None => None,
+                }
+            }
+            Err(e) => {
+                crate::rejection::record(
+                    crate::rejection::Rejection::new(crate::rejection::RejectionReason::ParseError)
+                        .remote_addr(remote_addr)
+                        .detail(e.to_string()),
+                );
+                let error_response = crate::Response::error(
+                    crate::ErrorBuilder::new(
+                        crate::error_codes::PARSE_ERROR,
+                        format!("Parse error: {e}"),
+                    )
+                    .build(),
+                    None,
+                );
+                Some(serde_json::to_string(&error_response)?)
+            }
+        };
+        if let Some(reply) = reply
+            && tx.send(Outgoing::Text(reply)).await.is_err()
+        {
+            break None;
+        }
+    };
+
+    #[cfg(feature = "streaming")]
+    if let Some(owned) = owned {
+        owned.release().await;
+    }
+    if let Some((code, reason)) = close {
+        let _ = tx.send(Outgoing::Close(code, reason)).await;
+    }
+    drop(tx);
+    let _ = writer_task.await;
+    Ok(())
+}
+
+/// WebSocket client for JSON-RPC servers
+///
+/// A background task reads the connection and answers pings; it stops when
+/// the client is dropped.
+pub struct WebSocketClient {
+    incoming: mpsc::Receiver<Result<String, WebSocketError>>,
+    writer: Arc<ClientWriter>,
+    reader: tokio::task::JoinHandle<()>,
+    codec: Arc<dyn Codec>,
+    closed: bool,
+    #[cfg(feature = "streaming")]
+    subscriptions: Arc<super::subscription::SubscriptionRoutes>,
+    #[cfg(feature = "streaming")]
+    next_id: u64,
+}
+
+/// Write half of a client connection
+struct ClientWriter {
+    half: tokio::sync::Mutex<OwnedWriteHalf>,
+    tap: Option<super::tap::ConnectionTap>,
+}
+
+/// Write one masked frame from the client side
+async fn write_client_frame(
+    writer: &ClientWriter,
+    opcode: u8,
+    payload: &[u8],
+) -> Result<(), WebSocketError> {
+    if let Some(tap) = &writer.tap
+        && (opcode == TEXT || opcode == BINARY)
+    {
+        tap.capture(super::tap::TapDirection::Outbound, payload);
+    }
+    let frame = encode_frame(opcode, payload, Some(random_bytes::<4>()));
+    let mut writer = writer.half.lock().await;
+    writer.write_all(&frame).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Write JSON text as one message in `codec`
+async fn write_client_json(
+    writer: &ClientWriter,
+    codec: &dyn Codec,
+    json: &str,
+) -> Result<(), WebSocketError> {
+    if codec.is_binary() {
+        write_client_frame(writer, BINARY, &codec.encode_json(json)?).await
+    } else {
+        write_client_frame(writer, TEXT, json.as_bytes()).await
+    }
+}
+
+pub struct WebSocketClientBuilder {
+    url: String,
+    codec: Option<Arc<dyn Codec>>,
+    tap: Option<super::tap::WireTap>,
+}
+
+impl WebSocketClientBuilder {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            codec: None,
+            tap: None,
+        }
+    }
+
+    /// Ask the server for `codec` as subprotocol
+    ///
+    /// Messages use `codec` if the server accepts it and JSON text
+    /// otherwise; [`WebSocketClient::codec_name`] tells which.
+    pub fn codec(mut self, codec: impl Codec) -> Self {
+        self.codec = Some(Arc::new(codec));
+        self
+    }
+
+    /// Mirror the connection's messages to `tap`, if sampled
+    pub fn tap(mut self, tap: super::tap::WireTap) -> Self {
+        self.tap = Some(tap);
+        self
+    }
+
+    pub async fn connect(self) -> Result<WebSocketClient, WebSocketError> {
+        WebSocketClient::open(&self.url, self.codec, self.tap).await
+    }
+}
+
+impl WebSocketClient {
+    pub fn builder(url: impl Into<String>) -> WebSocketClientBuilder {
+        WebSocketClientBuilder::new(url)
+    }
+
+    /// Connect to `url`, e.g. `ws://127.0.0.1:8080/rpc`
+    pub async fn connect(url: &str) -> Result<Self, WebSocketError> {
+        Self::builder(url).connect().await
+    }
+
+    /// Connect asking the server for `codec` as subprotocol, see
+    /// [`WebSocketClientBuilder::codec`]
+    pub async fn connect_with_codec(url: &str, codec: impl Codec) -> Result<Self, WebSocketError> {
+        Self::builder(url).codec(codec).connect().await
+    }
+
+    async fn open(
+        url: &str,
+        codec: Option<Arc<dyn Codec>>,
+        tap: Option<super::tap::WireTap>,
+    ) -> Result<Self, WebSocketError> {
+        let rest = url.strip_prefix("ws://").ok_or_else(|| {
+            WebSocketError::Handshake(format!("unsupported URL {url}, expected ws://"))
+        })?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+
+        let stream = TcpStream::connect(authority).await?;
+        let address = stream.peer_addr()?;
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        let key = base64(&random_bytes::<16>());
+        let protocol = codec
+            .as_ref()
+            .map(|codec| format!("Sec-WebSocket-Protocol: {}\r\n", codec.name()))
+            .unwrap_or_default();
+        let request = format!(
+            "GET {path} HTTP/1.1\r\nHost: {authority}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: {key}\r\nSec-WebSocket-Version: 13\r\n{protocol}\r\n"
+        );
+        writer.write_all(request.as_bytes()).await?;
+
+        let head = read_http_head(&mut reader).await?;
+        let status = head.first().map(String::as_str).unwrap_or_default();
+        if status.split_whitespace().nth(1) != Some("101") {
+            return Err(WebSocketError::Handshake(format!(
+                "server answered {status:?}"
+            )));
+        }
+        let headers = parse_headers(head.iter().skip(1));
+        if headers.get("sec-websocket-accept") != Some(&accept_key(&key)) {
+            return Err(WebSocketError::Handshake(
+                "invalid Sec-WebSocket-Accept".into(),
+            ));
+        }
+        let codec = match (headers.get("sec-websocket-protocol"), codec) {
+            (None, _) => Arc::new(JsonCodec),
+            (Some(selected), Some(codec)) if *selected == codec.name() => codec,
+            (Some(selected), _) => {
+                return Err(WebSocketError::Handshake(format!(
+                    "server selected unrequested subprotocol {selected}"
+                )));
+            }
+        };
+
+        let mut frames = FrameReader::new(reader, MAX_CLIENT_MESSAGE_SIZE, false);
+        let tap = tap.and_then(|tap| {
+            Arc::new(tap).connection(super::transport_stats::WEBSOCKET, Some(address))
+        });
+        let writer = Arc::new(ClientWriter {
+            half: tokio::sync::Mutex::new(writer),
+            tap: tap.clone(),
+        });
+        let (incoming_tx, incoming) = mpsc::channel(100);
+        #[cfg(feature = "streaming")]
+        let subscriptions = Arc::new(super::subscription::SubscriptionRoutes::default());
+        #[cfg(feature = "streaming")]
+        let routes = Arc::clone(&subscriptions);
+        let pong_writer = Arc::clone(&writer);
+        let decoder = Arc::clone(&codec);
+        let reader = tokio::spawn(async move {
+            loop {
+                let next = frames.next().await;
+                if let (Some(tap), Ok(incoming)) = (&tap, &next)
+                    && let Some(data) = incoming.data()
+                {
+                    tap.capture(super::tap::TapDirection::Inbound, data);
+                }
+                let text = match next {
+                    Ok(Incoming::Text(text)) => text,
+                    Ok(Incoming::Binary(payload)) => match decoder.decode_json(&payload) {
+                        Ok(text) => text,
+                        Err(e) => {
+                            if incoming_tx.send(Err(e.into())).await.is_err() {
+                                break;
+                            }
+                            continue;
+                        }
+                    },
+                    Ok(Incoming::Ping(payload)) => {
+                        match write_client_frame(&pong_writer, PONG, &payload).await {
+                            Ok(()) => continue,
+                            Err(e) => {
+                                let _ = incoming_tx.send(Err(e)).await;
+                                break;
+                            }
+                        }
+                    }
+                    Ok(Incoming::Pong) => continue,
+                    Ok(Incoming::Close) => {
+                        let _ = write_client_frame(
+                            &pong_writer,
+                            CLOSE,
+                            &close_payload(CLOSE_NORMAL, ""),
+                        )
+                        .await;
+                        break;
+                    }
+                    Err(WebSocketError::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                        break;
+                    }
+                    Err(e) => {
+                        let _ = incoming_tx.send(Err(e)).await;
+                        break;
+                    }
+                };
+                #[cfg(feature = "streaming")]
+                if routes.dispatch(&text) {
+                    continue;
+                }
+                if incoming_tx.send(Ok(text)).await.is_err() {
+                    break;
+                }
+            }
+            #[cfg(feature = "streaming")]
+            routes.close_all();
+        });
+
+        Ok(Self {
+            incoming,
+            writer,
+            reader,
+            codec,
+            closed: false,
+            #[cfg(feature = "streaming")]
+            subscriptions,
+            #[cfg(feature = "streaming")]
+            next_id: 1,
+        })
+    }
+
+    async fn send_frame(&mut self, opcode: u8, payload: &[u8]) -> Result<(), WebSocketError> {
+        write_client_frame(&self.writer, opcode, payload).await
+    }
+
+    async fn send_json(&mut self, json: &str) -> Result<(), WebSocketError> {
+        write_client_json(&self.writer, self.codec.as_ref(), json).await
+    }
+
+    /// Codec of the connection's messages, `json` unless one was negotiated
+    pub fn codec_name(&self) -> &'static str {
+        self.codec.name()
+    }
+
+    /// Send one text message
+    pub async fn send_text(&mut self, text: &str) -> Result<(), WebSocketError> {
+        self.send_frame(TEXT, text.as_bytes()).await
+    }
+
+    /// Next message as JSON text; `None` once the server closed the
+    /// connection
+    ///
+    /// Pings are answered while waiting.
+    pub async fn recv_text(&mut self) -> Result<Option<String>, WebSocketError> {
+        if self.closed {
+            return Ok(None);
+        }
+        match self.incoming.recv().await {
+            Some(message) => message.map(Some),
+            None => {
+                self.closed = true;
+                Ok(None)
+            }
+        }
+    }
+
+    pub async fn send_message(&mut self, message: &Message) -> Result<(), WebSocketError> {
+        self.send_json(&serde_json::to_string(message)?).await
+    }
+
+    pub async fn recv_message(&mut self) -> Result<Option<Message>, WebSocketError> {
+        match self.recv_text().await? {
+            Some(text) => Ok(Some(serde_json::from_str(&text)?)),
+            None => Ok(None),
+        }
+    }
+
+    #[cfg(feature = "streaming")]
+    pub async fn send_stream_message(
+        &mut self,
+        message: &crate::streaming::StreamMessage,
+    ) -> Result<(), WebSocketError> {
+        self.send_json(&serde_json::to_string(message)?).await
+    }
+
+    /// Next subscription response or event
+    #[cfg(feature = "streaming")]
+    pub async fn recv_stream_message(
+        &mut self,
+    ) -> Result<Option<crate::streaming::StreamMessage>, WebSocketError> {
+        match self.recv_text().await? {
+            Some(text) => Ok(Some(serde_json::from_str(&text)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Open a stream of `method` on the server
+    ///
+    /// The stream's events are taken off the connection and yielded by the
+    /// returned [`Subscription`](super::subscription::Subscription) instead
+    /// of [`recv_text`](Self::recv_text). Other messages arriving before the
+    /// server confirms are discarded.
+    #[cfg(feature = "streaming")]
+    pub async fn subscribe(
+        &mut self,
+        method: &str,
+        params: Option<serde_json::Value>,
+    ) -> Result<super::subscription::Subscription, Box<dyn std::error::Error>> {
+        let id = serde_json::json!(self.next_id);
+        self.next_id += 1;
+
+        let mut request = crate::streaming::StreamRequest::new(method, id.clone());
+        if let Some(params) = params {
+            request = request.with_params(params);
+        }
+        let stream_id = request.stream_id();
+        let request = request.with_stream_id(stream_id.clone());
+        // route first, events may overtake the confirmation
+        let events = self.subscriptions.route(&stream_id);
+        let outcome = match self.send_json(&serde_json::to_string(&request)?).await {
+            Ok(()) => loop {
+                match self.recv_text().await {
+                    Ok(Some(text)) => {
+                        if let Some(outcome) = super::subscription::subscribe_outcome(&text, &id) {
+                            break outcome;
+                        }
+                    }
+                    Ok(None) => break Err("connection closed before response".into()),
+                    Err(e) => break Err(e.into()),
+                }
+            },
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = outcome {
+            self.subscriptions.remove(&stream_id);
+            return Err(e);
+        }
+
+        let writer = Arc::clone(&self.writer);
+        let codec = Arc::clone(&self.codec);
+        let send: super::subscription::SendText = Arc::new(move |text| {
+            let writer = Arc::clone(&writer);
+            let codec = Arc::clone(&codec);
+            Box::pin(async move {
+                write_client_json(&writer, codec.as_ref(), &text)
+                    .await
+                    .is_ok()
+            })
+        });
+        Ok(super::subscription::Subscription::new(
+            stream_id,
+            events,
+            Arc::clone(&self.subscriptions),
+            send,
+        ))
+    }
+
+    /// Close the connection, waiting for the server to acknowledge
+    pub async fn close(mut self) -> Result<(), WebSocketError> {
+        if !self.closed {
+            self.send_frame(CLOSE, &close_payload(CLOSE_NORMAL, ""))
+                .await?;
+            while self.recv_text().await?.is_some() {}
+        }
+        Ok(())
+    }
+}
+
+impl Drop for WebSocketClient {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tokio::net::TcpListener;
+
+    struct Echo;
+
+    #[async_trait::async_trait]
+    impl MessageProcessor for Echo {
+        async fn process_message(&self, message: Message) -> Option<crate::Response> {
+            let request = message.into_request()?;
+            Some(crate::Response::success(
+                request.params.unwrap_or_default(),
+                request.id,
+            ))
+        }
+    }
+
+    async fn serve(config: ConnectionConfig) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(handle_connection(stream, config.clone()));
+            }
+        });
+        format!("ws://{addr}/rpc")
+    }
+
+    fn config(security_config: SecurityConfig) -> ConnectionConfig {
+        ConnectionConfig {
+            processor: Arc::new(Echo),
+            security_config,
+            path: Some(Arc::from("/rpc")),
+            codecs: Arc::from(Vec::new()),
+            tap: None,
+            listener: None,
+            json_format: Default::default(),
+            #[cfg(feature = "streaming")]
+            streams: None,
+        }
+    }
+
+    #[test]
+    fn test_accept_key() {
+        // example from RFC 6455, section 1.3
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert_eq!(base64(b"ab"), "YWI=");
+    }
+
+    #[tokio::test]
+    async fn test_frames_round_trip() {
+        let (client, server) = tokio::io::duplex(1 << 20);
+        let (_client_read, mut client_write) = tokio::io::split(client);
+        let mut frames = FrameReader::new(server, 100_000, true);
+
+        let long = "x".repeat(70_000);
+        for text in ["hi", &"y".repeat(300), &long] {
+            let frame = encode_frame(TEXT, text.as_bytes(), Some([1, 2, 3, 4]));
+            client_write.write_all(&frame).await.unwrap();
+            assert_eq!(
+                frames.next().await.unwrap(),
+                Incoming::Text(text.to_string())
+            );
+        }
+
+        // fragmented message with a ping in between
+        let mut first = encode_frame(TEXT, b"frag", Some([9, 9, 9, 9]));
+        first[0] &= 0x7F;
+        client_write.write_all(&first).await.unwrap();
+        let ping = encode_frame(PING, b"p", Some([5, 5, 5, 5]));
+        client_write.write_all(&ping).await.unwrap();
+        let last = encode_frame(CONTINUATION, b"ment", Some([7, 7, 7, 7]));
+        client_write.write_all(&last).await.unwrap();
+        assert_eq!(frames.next().await.unwrap(), Incoming::Ping(b"p".to_vec()));
+        assert_eq!(
+            frames.next().await.unwrap(),
+            Incoming::Text("fragment".to_string())
+        );
+
+        let unmasked = encode_frame(TEXT, b"hi", None);
+        client_write.write_all(&unmasked).await.unwrap();
+        assert!(matches!(
+            frames.next().await,
+            Err(WebSocketError::Protocol(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unlimited_frames_allocate_what_arrives() {
+        let (mut client, server) = tokio::io::duplex(1 << 16);
+        let mut frames = FrameReader::new(server, 0, true);
+
+        // claims an exabyte, sends a few bytes and hangs up
+        let mut frame = vec![0x80 | TEXT, 0x80 | 127];
+        frame.extend_from_slice(&(1u64 << 60).to_be_bytes());
+        frame.extend_from_slice(&[1, 2, 3, 4]);
+        frame.extend_from_slice(b"short");
+        client.write_all(&frame).await.unwrap();
+        drop(client);
+
+        match frames.next().await {
+            Err(WebSocketError::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof),
+            other => panic!("expected a truncated frame, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_response_and_limits() {
+        let url = serve(config(SecurityConfig {
+            max_request_size: 256,
+            ..Default::default()
+        }))
+        .await;
+
+        let mut client = WebSocketClient::connect(&url).await.unwrap();
+        let request = crate::Request::new("echo")
+            .with_params(json!({"hello": "world"}))
+            .with_id(json!(1));
+        client
+            .send_message(&Message::Request(request))
+            .await
+            .unwrap();
+        let response = client.recv_message().await.unwrap().unwrap();
+        let response = response.as_response().unwrap();
+        assert_eq!(response.result, Some(json!({"hello": "world"})));
+
+        client.send_text("not json").await.unwrap();
+        let response = client.recv_message().await.unwrap().unwrap();
+        assert_eq!(
+            response.as_response().unwrap().error.as_ref().unwrap().code,
+            crate::error_codes::PARSE_ERROR
+        );
+
+        client.send_text(&"x".repeat(300)).await.unwrap();
+        assert!(client.recv_text().await.unwrap().is_none());
+
+        let wrong_path = url.replace("/rpc", "/other");
+        assert!(matches!(
+            WebSocketClient::connect(&wrong_path).await,
+            Err(WebSocketError::Handshake(_))
+        ));
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[tokio::test]
+    async fn test_msgpack_subprotocol() {
+        let url = serve(ConnectionConfig {
+            codecs: Arc::from(vec![Arc::new(crate::codec::MsgPackCodec) as Arc<dyn Codec>]),
+            ..config(SecurityConfig::default())
+        })
+        .await;
+
+        let mut client = WebSocketClient::connect_with_codec(&url, crate::codec::MsgPackCodec)
+            .await
+            .unwrap();
+        assert_eq!(client.codec_name(), "msgpack");
+        let request = crate::Request::new("echo")
+            .with_params(json!({"samples": [1.5, 2, -3]}))
+            .with_id(json!(1));
+        client
+            .send_message(&Message::Request(request))
+            .await
+            .unwrap();
+        let response = client.recv_message().await.unwrap().unwrap();
+        assert_eq!(
+            response.as_response().unwrap().result,
+            Some(json!({"samples": [1.5, 2, -3]}))
+        );
+
+        // a server without the codec keeps talking JSON
+        let url = serve(config(SecurityConfig::default())).await;
+        let client = WebSocketClient::connect_with_codec(&url, crate::codec::MsgPackCodec)
+            .await
+            .unwrap();
+        assert_eq!(client.codec_name(), "json");
+    }
+
+    #[tokio::test]
+    async fn test_idle_connections_closed() {
+        let idle_timeouts = || {
+            crate::transports::transport_stats::snapshot()
+                .into_iter()
+                .find(|s| s.transport == crate::transports::transport_stats::WEBSOCKET)
+                .map_or(0, |s| s.idle_timeouts)
+        };
+        let before = idle_timeouts();
+        let url = serve(config(SecurityConfig {
+            idle_timeout: Duration::from_millis(50),
+            ..Default::default()
+        }))
+        .await;
+        let mut client = WebSocketClient::connect(&url).await.unwrap();
+        let closed = tokio::time::timeout(Duration::from_secs(5), client.recv_text()).await;
+        assert!(closed.unwrap().unwrap().is_none());
+        assert!(idle_timeouts() > before);
+    }
+
+    #[cfg(feature = "streaming")]
+    #[tokio::test]
+    async fn test_subscriptions_pushed_to_subscriber() {
+        use crate::streaming::*;
+
+        struct Count;
+
+        #[async_trait::async_trait]
+        impl StreamingMethod for Count {
+            fn method_name(&self) -> &'static str {
+                "count"
+            }
+
+            async fn open(
+                &self,
+                params: Option<serde_json::Value>,
+            ) -> Result<ResultStream, crate::Error> {
+                let to = params.and_then(|p| p.as_u64()).unwrap_or(0);
+                let (tx, stream) = result_channel(4);
+                tokio::spawn(async move {
+                    for n in 0..to {
+                        if tx.send(json!(n)).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+                Ok(stream)
+            }
+        }
+
+        let manager = Arc::new(StreamManager::new());
+        manager
+            .register_handler(StreamingMethodHandler::new(Count))
+            .await;
+        let mut config = config(SecurityConfig::default());
+        config.streams = Some(super::super::stream_router::StreamRouter::start(
+            Arc::clone(&manager),
+        ));
+        let url = serve(config).await;
+
+        let mut client = WebSocketClient::connect(&url).await.unwrap();
+        let mut other = WebSocketClient::connect(&url).await.unwrap();
+
+        let subscribe = StreamRequest::new("count", json!(1))
+            .with_params(json!(3))
+            .with_stream_id("s-1");
+        client
+            .send_stream_message(&StreamMessage::StreamRequest(subscribe.clone()))
+            .await
+            .unwrap();
+        let mut received = Vec::new();
+        loop {
+            match client.recv_stream_message().await.unwrap().unwrap() {
+                StreamMessage::StreamResponse(response) => assert!(response.error.is_none()),
+                StreamMessage::StreamEvent(event) if event.status.is_some() => break,
+                StreamMessage::StreamEvent(event) => received.push(event.params),
+                other => panic!("unexpected {other:?}"),
+            }
+        }
+        assert_eq!(received, vec![json!(0), json!(1), json!(2)]);
+
+        // stream ids of other connections cannot be taken over or cancelled
+        let unsubscribe = UnsubscribeRequest::new("s-1".into(), json!(2));
+        other
+            .send_stream_message(&StreamMessage::UnsubscribeRequest(unsubscribe))
+            .await
+            .unwrap();
+        let response = other.recv_stream_message().await.unwrap().unwrap();
+        let response = response.as_stream_response().unwrap();
+        assert_eq!(
+            response.error.as_ref().unwrap().code,
+            crate::error_codes::INVALID_PARAMS
+        );
+
+        // regular calls still reach the processor
+        let request = crate::Request::new("echo")
+            .with_params(json!(5))
+            .with_id(json!(3));
+        other
+            .send_message(&Message::Request(request))
+            .await
+            .unwrap();
+        let response = other.recv_message().await.unwrap().unwrap();
+        assert_eq!(response.as_response().unwrap().result, Some(json!(5)));
+
+        client.close().await.unwrap();
+    }
+
+    #[cfg(feature = "streaming")]
+    #[tokio::test]
+    async fn test_subscriptions_pass_filter_and_auth() {
+        use crate::streaming::*;
+
+        struct Idle;
+
+        #[async_trait::async_trait]
+        impl StreamingMethod for Idle {
+            fn method_name(&self) -> &'static str {
+                "ticks"
+            }
+
+            async fn open(
+                &self,
+                _params: Option<serde_json::Value>,
+            ) -> Result<ResultStream, crate::Error> {
+                let (_tx, stream) = result_channel(1);
+                Ok(stream)
+            }
+        }
+
+        let manager = Arc::new(StreamManager::new());
+        manager
+            .register_handler(StreamingMethodHandler::new(Idle))
+            .await;
+        let router = super::super::stream_router::StreamRouter::start(Arc::clone(&manager));
+        let filtered: Arc<dyn MessageProcessor + Send + Sync> =
+            Arc::new(super::super::listener::ListenerProcessor::new(
+                Arc::new(Echo),
+                None,
+                super::super::listener::MethodFilter::new().deny("ticks"),
+            ));
+        let denied: Arc<dyn MessageProcessor + Send + Sync> =
+            Arc::new(crate::MethodRegistry::empty().with_auth(crate::auth::DenyAll));
+
+        for (processor, code) in [
+            (filtered, crate::error_codes::METHOD_NOT_FOUND),
+            (denied, crate::error_codes::INTERNAL_ERROR),
+        ] {
+            let mut config = config(SecurityConfig::default());
+            config.processor = processor;
+            config.streams = Some(Arc::clone(&router));
+            let mut client = WebSocketClient::connect(&serve(config).await)
+                .await
+                .unwrap();
+            let subscribe = StreamRequest::new("ticks", json!(1)).with_stream_id("s-1");
+            client
+                .send_stream_message(&StreamMessage::StreamRequest(subscribe))
+                .await
+                .unwrap();
+            let response = client.recv_message().await.unwrap().unwrap();
+            let response = response.as_response().unwrap();
+            assert_eq!(response.error.as_ref().unwrap().code, code);
+            assert_eq!(manager.active_count().await, 0);
+            client.close().await.unwrap();
+        }
+    }
+
+    #[cfg(feature = "streaming")]
+    #[tokio::test]
+    async fn test_client_subscription_stream() {
+        use crate::streaming::*;
+
+        struct Count;
+
+        #[async_trait::async_trait]
+        impl StreamingMethod for Count {
+            fn method_name(&self) -> &'static str {
+                "count"
+            }
+
+            async fn open(
+                &self,
+                params: Option<serde_json::Value>,
+            ) -> Result<ResultStream, crate::Error> {
+                let to = params.and_then(|p| p.as_u64()).unwrap_or(u64::MAX);
+                let (tx, stream) = result_channel(4);
+                tokio::spawn(async move {
+                    for n in 0..to {
+                        if tx.send(json!(n)).await.is_err() {
+                            break;
+                        }
+                        tokio::time::sleep(Duration::from_millis(5)).await;
+                    }
+                });
+                Ok(stream)
+            }
+        }
+
+        let manager = Arc::new(StreamManager::new());
+        manager
+            .register_handler(StreamingMethodHandler::new(Count))
+            .await;
+        let mut config = config(SecurityConfig::default());
+        config.streams = Some(super::super::stream_router::StreamRouter::start(
+            Arc::clone(&manager),
+        ));
+        let url = serve(config).await;
+        let mut client = WebSocketClient::connect(&url).await.unwrap();
+
+        let mut finite = client.subscribe("count", Some(json!(3))).await.unwrap();
+        let mut endless = client.subscribe("count", None).await.unwrap();
+        let mut received = Vec::new();
+        while let Some(event) = finite.next().await {
+            assert_eq!(event.stream_id(), finite.stream_id());
+            if event.status.is_none() {
+                received.push(event.params);
+            }
+        }
+        assert_eq!(received, [json!(0), json!(1), json!(2)]);
+        assert!(endless.next().await.is_some());
+
+        // dropping an open subscription cancels it on the server
+        let stream_id = endless.stream_id().to_string();
+        drop(endless);
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while manager.get_stream_info(&stream_id).await.is_some() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        let request = crate::Request::new("echo")
+            .with_params(json!(5))
+            .with_id(json!(9));
+        client
+            .send_message(&Message::Request(request))
+            .await
+            .unwrap();
+        let response = client.recv_message().await.unwrap().unwrap();
+        assert_eq!(response.as_response().unwrap().id, Some(json!(9)));
+        client.close().await.unwrap();
+    }
+}
@@ -16,6 +16,18 @@ pub struct Request {
     pub id: Option<RequestId>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub correlation_id: Option<String>,
+    /// Non-standard members, omitted unless the caller sets them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ext: Option<RequestExtensions>,
+}
+
+/// Extension envelope carried in the `ext` member of a request
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequestExtensions {
+    /// How the batch this request opens is executed, see
+    /// [`BatchPolicy`](crate::batch_policy::BatchPolicy)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_policy: Option<crate::batch_policy::BatchPolicy>,
 }
 
 impl Request {
@@ -27,6 +39,7 @@ impl Request {
             params: None,
             id: None,
             correlation_id: Some(uuid::Uuid::new_v4().to_string()),
+            ext: None,
         }
     }
 
@@ -84,6 +97,25 @@ pub struct Response {
     pub id: Option<RequestId>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub correlation_id: Option<String>,
+    /// Non-standard members, omitted unless a feature fills them in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ext: Option<ResponseExtensions>,
+}
+
+/// Extension envelope carried in the `ext` member of a response
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResponseExtensions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch: Option<BatchItemMeta>,
+}
+
+/// How one entry of a batch was processed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchItemMeta {
+    /// Position of the request in the batch, notifications included
+    pub index: usize,
+    /// Processing time in microseconds
+    pub duration_us: u64,
 }
 
 impl Response {
@@ -95,6 +127,7 @@ impl Response {
             error: None,
             id,
             correlation_id: None,
+            ext: None,
         }
     }
 
@@ -106,6 +139,7 @@ impl Response {
             error: Some(error),
             id,
             correlation_id: None,
+            ext: None,
         }
     }
 
@@ -253,6 +287,14 @@ impl Error {
     }
 }
 
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "JSON-RPC error {}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Notification {
     pub jsonrpc: String,
@@ -397,6 +439,24 @@ pub mod error_codes {
 
     /// Internal error - Internal JSON-RPC error.
     pub const INTERNAL_ERROR: i32 = -32603;
+
+    /// Retry later - The method is temporarily unavailable.
+    /// `data.retry_after` holds the suggested delay in seconds.
+    pub const RETRY_LATER: i32 = -32001;
+
+    /// Dependent failure - The request was not executed, or was rolled
+    /// back, because another entry of its batch failed.
+    /// `data.failed_index` holds the position of that entry when known.
+    pub const DEPENDENT_FAILURE: i32 = -32002;
+
+    /// Request timeout - The method did not complete within its timeout
+    /// or its caller's deadline.
+    /// `data.timeout_ms` holds the time it was given.
+    pub const REQUEST_TIMEOUT: i32 = -32003;
+
+    /// Request cancelled - The request was cancelled by the client before
+    /// it completed, see [`crate::cancellation`].
+    pub const REQUEST_CANCELLED: i32 = -32800;
 }
 
 #[cfg(test)]
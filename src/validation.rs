@@ -0,0 +1,109 @@
+//! Params validation against per-method JSON Schemas.
+//!
+//! A [`ParamsValidator`] holds a schema for each method it guards. Attached
+//! to a registry with
+//! [`MethodRegistry::with_params_validation`](crate::MethodRegistry::with_params_validation),
+//! it checks the params of every call before the method runs and answers
+//! mismatches with `INVALID_PARAMS`, listing each offending path in
+//! `data.violations`. The same schemas fill in the `params` of methods that
+//! document none in the generated OpenAPI spec.
+//!
+//! Schemas use the subset of JSON Schema supported by [`crate::schema`].
+//!
+//! ```rust
+//! use ash_rpc::validation::ParamsValidator;
+//! use serde_json::json;
+//!
+//! let validator = ParamsValidator::new().method(
+//!     "transfer",
+//!     json!({"type": "object", "required": ["amount"],
+//!            "properties": {"amount": {"type": "integer", "minimum": 1}}}),
+//! );
+//!
+//! assert!(validator.check("transfer", Some(&json!({"amount": 5}))).is_ok());
+//! let error = validator.check("transfer", Some(&json!({"amount": 0}))).unwrap_err();
+//! assert_eq!(error.data().unwrap()["violations"][0]["path"], "/amount");
+//! ```
+
+use crate::schema::SchemaMismatch;
+use crate::{Error, ErrorBuilder, error_codes};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// JSON Schemas for the params of each guarded method
+#[derive(Debug, Clone, Default)]
+pub struct ParamsValidator {
+    schemas: HashMap<String, Value>,
+}
+
+impl ParamsValidator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check the params of `method` against `schema`
+    pub fn method(mut self, method: impl Into<String>, schema: Value) -> Self {
+        self.schemas.insert(method.into(), schema);
+        self
+    }
+
+    /// Check the params of `method` against the schema `schemars` derives
+    /// for `T`
+    #[cfg(feature = "schemars")]
+    pub fn method_for<T: schemars::JsonSchema>(self, method: impl Into<String>) -> Self {
+        self.method(method, schemars::schema_for!(T).to_value())
+    }
+
+    pub fn schema(&self, method: &str) -> Option<&Value> {
+        self.schemas.get(method)
+    }
+
+    /// Validate the params of a call to `method`
+    ///
+    /// Methods without a schema always pass. Missing params are checked as
+    /// `null`, so a schema that requires an object also requires params.
+    pub fn check(&self, method: &str, params: Option<&Value>) -> Result<(), Error> {
+        let Some(schema) = self.schemas.get(method) else {
+            return Ok(());
+        };
+        crate::schema::validate(params.unwrap_or(&Value::Null), schema)
+            .map_err(|mismatch| invalid_params(&mismatch))
+    }
+}
+
+/// `INVALID_PARAMS` error listing every violation of `mismatch`
+pub fn invalid_params(mismatch: &SchemaMismatch) -> Error {
+    ErrorBuilder::new(
+        error_codes::INVALID_PARAMS,
+        format!("Invalid params: {mismatch}"),
+    )
+    .data(serde_json::json!({ "violations": mismatch.violations }))
+    .build()
+}
+
+#[cfg(all(test, feature = "schemars"))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_schemars_params() {
+        #[allow(dead_code)]
+        #[derive(schemars::JsonSchema)]
+        struct Transfer {
+            to: String,
+            amount: u32,
+        }
+
+        let validator = ParamsValidator::new().method_for::<Transfer>("transfer");
+        assert!(
+            validator
+                .check("transfer", Some(&json!({"to": "acc-2", "amount": 3})))
+                .is_ok()
+        );
+        let error = validator
+            .check("transfer", Some(&json!({"to": "acc-2", "amount": "3"})))
+            .unwrap_err();
+        assert_eq!(error.data().unwrap()["violations"][0]["path"], "/amount");
+    }
+}
@@ -0,0 +1,238 @@
+//! Protocol version negotiation and version-specific methods.
+//!
+//! Every request names its protocol version in the `jsonrpc` member.
+//! Registries answer versions they do not serve with `INVALID_REQUEST`,
+//! listing the supported ones in the error data; without further setup only
+//! `"2.0"` is served. [`ProtocolVersions`] adds versions and lets a method
+//! behave differently for one of them:
+//!
+//! ```rust
+//! use ash_rpc::versioning::ProtocolVersions;
+//! use ash_rpc::MethodRegistry;
+//! # use ash_rpc::*;
+//! # struct ListV3;
+//! # #[async_trait::async_trait]
+//! # impl JsonRPCMethod for ListV3 {
+//! #     fn method_name(&self) -> &'static str { "items.list" }
+//! #     async fn call(&self, _: Option<serde_json::Value>, id: Option<RequestId>) -> Response {
+//! #         rpc_success!(["item"], id)
+//! #     }
+//! # }
+//!
+//! let registry = MethodRegistry::empty().with_protocol_versions(
+//!     ProtocolVersions::new().method("2.0-ext.3", Box::new(ListV3)),
+//! );
+//! assert!(registry.get_capabilities().supported_versions.contains(&"2.0-ext.3".to_string()));
+//! ```
+//!
+//! Clients learn which version to use from the `rpc.negotiate` built-in
+//! (`discovery` group), typically as the first call on a connection: it takes
+//! the versions the client speaks in order of preference,
+//! `{"versions": ["2.0-ext.3", "2.0"]}`, and answers with the first one the
+//! server serves. Responses carry the version of their request.
+
+use crate::{JsonRPCMethod, RequestId, Response, error_codes};
+use std::collections::HashMap;
+
+/// The version every registry serves
+pub const DEFAULT_VERSION: &str = "2.0";
+
+/// Served protocol versions and their version-specific methods
+pub struct ProtocolVersions {
+    versions: Vec<String>,
+    /// Version-specific methods by version, then method name
+    methods: HashMap<String, HashMap<String, Box<dyn JsonRPCMethod>>>,
+}
+
+impl ProtocolVersions {
+    /// Serve only [`DEFAULT_VERSION`]
+    pub fn new() -> Self {
+        Self {
+            versions: vec![DEFAULT_VERSION.to_string()],
+            methods: HashMap::new(),
+        }
+    }
+
+    /// Serve `version` as well
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        let version = version.into();
+        if !self.is_supported(&version) {
+            self.versions.push(version);
+        }
+        self
+    }
+
+    /// Answer calls of `method` made with `version` with this implementation
+    ///
+    /// Other versions keep using the method registered with the registry.
+    /// `version` is served from now on.
+    pub fn method(mut self, version: impl Into<String>, method: Box<dyn JsonRPCMethod>) -> Self {
+        let version = version.into();
+        self = self.version(version.clone());
+        self.methods
+            .entry(version)
+            .or_default()
+            .insert(method.method_name().to_string(), method);
+        self
+    }
+
+    pub fn is_supported(&self, version: &str) -> bool {
+        self.versions.iter().any(|v| v == version)
+    }
+
+    /// Served versions, in registration order
+    pub fn supported(&self) -> &[String] {
+        &self.versions
+    }
+
+    /// First of the client's `offered` versions that is served
+    pub fn negotiate<'a>(&self, offered: &'a [String]) -> Option<&'a str> {
+        offered
+            .iter()
+            .find(|version| self.is_supported(version))
+            .map(String::as_str)
+    }
+
+    /// Version-specific implementation of `method`, if any
+    pub(crate) fn method_for(&self, version: &str, method: &str) -> Option<&dyn JsonRPCMethod> {
+        self.methods
+            .get(version)?
+            .get(method)
+            .map(|method| method.as_ref())
+    }
+
+    /// Error answering a request made with an unserved `version`
+    pub fn unsupported(&self, version: &str, id: Option<RequestId>) -> Response {
+        self.refusal(format!("Unsupported protocol version '{version}'"), id)
+    }
+
+    fn refusal(&self, reason: String, id: Option<RequestId>) -> Response {
+        Response::error(
+            crate::ErrorBuilder::new(
+                error_codes::INVALID_REQUEST,
+                format!("{reason}, supported: {}", self.versions.join(", ")),
+            )
+            .data(serde_json::json!({ "supported": self.versions }))
+            .build(),
+            id,
+        )
+    }
+
+    /// Answer to `rpc.negotiate`
+    pub(crate) fn negotiate_response(
+        &self,
+        params: Option<&serde_json::Value>,
+        id: Option<RequestId>,
+    ) -> Response {
+        let offered: Vec<String> = match params
+            .and_then(|params| params.get("versions"))
+            .map(|versions| serde_json::from_value(versions.clone()))
+        {
+            Some(Ok(offered)) => offered,
+            _ => {
+                return crate::rpc_error!(
+                    error_codes::INVALID_PARAMS,
+                    "Expected {\"versions\": [<version>, ...]}",
+                    id
+                );
+            }
+        };
+        match self.negotiate(&offered) {
+            Some(version) => crate::rpc_success!(
+                serde_json::json!({ "version": version, "supported": self.versions }),
+                id
+            ),
+            None => {
+                tracing::debug!(?offered, "no common protocol version");
+                self.refusal("No common protocol version".to_string(), id)
+            }
+        }
+    }
+}
+
+impl Default for ProtocolVersions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Message, MessageProcessor, MethodRegistry, Request};
+    use serde_json::json;
+
+    struct Echo(&'static str);
+
+    #[async_trait::async_trait]
+    impl JsonRPCMethod for Echo {
+        fn method_name(&self) -> &'static str {
+            "echo"
+        }
+
+        async fn call(
+            &self,
+            _params: Option<serde_json::Value>,
+            id: Option<RequestId>,
+        ) -> Response {
+            Response::success(json!(self.0), id)
+        }
+    }
+
+    fn request(version: &str) -> Message {
+        let mut request = Request::new("echo").with_id(json!(1));
+        request.jsonrpc = version.to_string();
+        Message::Request(request)
+    }
+
+    #[tokio::test]
+    async fn test_version_specific_methods_and_rejection() {
+        let registry = MethodRegistry::new(crate::register_methods![Echo("v2")])
+            .with_protocol_versions(ProtocolVersions::new().method("2.1", Box::new(Echo("v2.1"))));
+
+        let response = registry.process_message(request("2.0")).await.unwrap();
+        assert_eq!(response.result, Some(json!("v2")));
+
+        let response = registry.process_message(request("2.1")).await.unwrap();
+        assert_eq!(response.result, Some(json!("v2.1")));
+        assert_eq!(response.jsonrpc, "2.1");
+
+        let response = registry.process_message(request("1.0")).await.unwrap();
+        let error = response.error.unwrap();
+        assert_eq!(error.code, error_codes::INVALID_REQUEST);
+        assert_eq!(error.data, Some(json!({"supported": ["2.0", "2.1"]})));
+
+        // registries without extra versions serve 2.0 only
+        let plain = MethodRegistry::new(crate::register_methods![Echo("v2")]);
+        let response = plain.process_message(request("2.1")).await.unwrap();
+        assert!(response.is_error());
+    }
+
+    #[tokio::test]
+    async fn test_negotiate_builtin() {
+        use crate::builtins::{BuiltinConfig, BuiltinMethods};
+
+        let registry = MethodRegistry::empty()
+            .with_builtins(BuiltinConfig::new(BuiltinMethods::DISCOVERY))
+            .with_protocol_versions(ProtocolVersions::new().version("2.1"));
+        assert!(registry.has_method("rpc.negotiate"));
+
+        let response = registry
+            .call(
+                "rpc.negotiate",
+                Some(json!({"versions": ["3.0", "2.1", "2.0"]})),
+                Some(json!(1)),
+            )
+            .await;
+        assert_eq!(response.result.unwrap()["version"], "2.1");
+
+        let response = registry
+            .call(
+                "rpc.negotiate",
+                Some(json!({"versions": ["3.0"]})),
+                Some(json!(2)),
+            )
+            .await;
+        assert_eq!(response.error.unwrap().code, error_codes::INVALID_REQUEST);
+    }
+}
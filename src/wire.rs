@@ -0,0 +1,226 @@
+//! Wire-format compatibility checks against captured traffic.
+//!
+//! A message is compatible when ash-rpc can decode it and encode it again
+//! without changing it: no member dropped, renamed or rewritten. Run the
+//! checker over traffic recorded from existing clients and servers before
+//! upgrading to catch format changes that would otherwise surface as subtle
+//! interop bugs.
+//!
+//! The crate's own format is pinned by the golden files under
+//! `tests/golden`, which are checked with the same rules.
+//!
+//! ```rust
+//! use ash_rpc::wire;
+//!
+//! let captured = r#"{"jsonrpc":"2.0","method":"ping","id":1}
+//! {"jsonrpc":"2.0","result":"pong","id":1}
+//! "#;
+//! let report = wire::check_traffic(captured.as_bytes()).unwrap();
+//! assert_eq!(report.checked, 2);
+//! assert!(report.is_compatible());
+//! ```
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::fmt;
+use std::io::{self, BufRead};
+
+/// Why a captured message does not survive a decode/encode round trip
+#[derive(Debug, Clone, PartialEq)]
+pub enum WireIncompatibility {
+    /// The message could not be decoded at all
+    Unparseable(String),
+    /// Re-encoding changed the message at `path` (a JSON pointer)
+    Mismatch {
+        path: String,
+        captured: Option<Box<Value>>,
+        reencoded: Option<Box<Value>>,
+    },
+}
+
+impl fmt::Display for WireIncompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |v: &Option<Box<Value>>| {
+            v.as_ref()
+                .map_or("<missing>".to_string(), |v| v.to_string())
+        };
+        match self {
+            WireIncompatibility::Unparseable(e) => write!(f, "cannot decode message: {e}"),
+            WireIncompatibility::Mismatch {
+                path,
+                captured,
+                reencoded,
+            } => write!(
+                f,
+                "{path}: captured {} but re-encoded as {}",
+                show(captured),
+                show(reencoded)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WireIncompatibility {}
+
+/// Check that `json` round-trips through `T` unchanged
+pub fn check_as<T: Serialize + DeserializeOwned>(json: &str) -> Result<(), WireIncompatibility> {
+    let captured: Value =
+        serde_json::from_str(json).map_err(|e| WireIncompatibility::Unparseable(e.to_string()))?;
+    check_value_as::<T>(&captured)
+}
+
+fn check_value_as<T: Serialize + DeserializeOwned>(
+    captured: &Value,
+) -> Result<(), WireIncompatibility> {
+    let decoded: T = serde_json::from_value(captured.clone())
+        .map_err(|e| WireIncompatibility::Unparseable(e.to_string()))?;
+    let reencoded = serde_json::to_value(&decoded)
+        .map_err(|e| WireIncompatibility::Unparseable(e.to_string()))?;
+    match first_difference(String::new(), Some(captured), Some(&reencoded)) {
+        Some(mismatch) => Err(mismatch),
+        None => Ok(()),
+    }
+}
+
+/// Check a JSON-RPC request, response or notification, or a batch of them
+pub fn check_message(json: &str) -> Result<(), WireIncompatibility> {
+    let captured: Value =
+        serde_json::from_str(json).map_err(|e| WireIncompatibility::Unparseable(e.to_string()))?;
+    match &captured {
+        Value::Array(batch) => batch.iter().enumerate().try_for_each(|(i, item)| {
+            check_value_as::<crate::Message>(item).map_err(|e| prefix_path(e, i))
+        }),
+        single => check_value_as::<crate::Message>(single),
+    }
+}
+
+/// Check a subscription request, response, event or unsubscribe message
+#[cfg(feature = "streaming")]
+pub fn check_stream_message(json: &str) -> Result<(), WireIncompatibility> {
+    check_as::<crate::streaming::StreamMessage>(json)
+}
+
+fn prefix_path(e: WireIncompatibility, index: usize) -> WireIncompatibility {
+    match e {
+        WireIncompatibility::Mismatch {
+            path,
+            captured,
+            reencoded,
+        } => WireIncompatibility::Mismatch {
+            path: format!("/{index}{path}"),
+            captured,
+            reencoded,
+        },
+        WireIncompatibility::Unparseable(e) => {
+            WireIncompatibility::Unparseable(format!("batch entry {index}: {e}"))
+        }
+    }
+}
+
+fn first_difference(
+    path: String,
+    captured: Option<&Value>,
+    reencoded: Option<&Value>,
+) -> Option<WireIncompatibility> {
+    match (captured, reencoded) {
+        (Some(Value::Object(a)), Some(Value::Object(b))) => {
+            let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            keys.into_iter().find_map(|key| {
+                let escaped = key.replace('~', "~0").replace('/', "~1");
+                first_difference(format!("{path}/{escaped}"), a.get(key), b.get(key))
+            })
+        }
+        (Some(Value::Array(a)), Some(Value::Array(b))) => (0..a.len().max(b.len()))
+            .find_map(|i| first_difference(format!("{path}/{i}"), a.get(i), b.get(i))),
+        (a, b) if a == b => None,
+        (a, b) => Some(WireIncompatibility::Mismatch {
+            path: if path.is_empty() {
+                "/".to_string()
+            } else {
+                path
+            },
+            captured: a.cloned().map(Box::new),
+            reencoded: b.cloned().map(Box::new),
+        }),
+    }
+}
+
+/// Outcome of [`check_traffic`]
+#[derive(Debug, Default)]
+pub struct TrafficReport {
+    /// Messages examined, blank lines excluded
+    pub checked: usize,
+    /// 1-based line number and problem for every failing message
+    pub failures: Vec<(usize, WireIncompatibility)>,
+}
+
+impl TrafficReport {
+    pub fn is_compatible(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Check newline-delimited captured traffic with [`check_message`]
+pub fn check_traffic<R: BufRead>(reader: R) -> io::Result<TrafficReport> {
+    let mut report = TrafficReport::default();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        report.checked += 1;
+        if let Err(e) = check_message(&line) {
+            report.failures.push((index + 1, e));
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_detects_dropped_members() {
+        assert!(check_message(r#"{"jsonrpc":"2.0","method":"a","params":[1],"id":"x"}"#).is_ok());
+        assert!(
+            check_message(
+                r#"[{"jsonrpc":"2.0","method":"a"},{"jsonrpc":"2.0","result":1,"id":2}]"#
+            )
+            .is_ok()
+        );
+
+        let err = check_message(r#"{"jsonrpc":"2.0","method":"a","id":1,"meta":{"trace":"t"}}"#)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            WireIncompatibility::Mismatch {
+                path: "/meta".to_string(),
+                captured: Some(Box::new(json!({"trace": "t"}))),
+                reencoded: None,
+            }
+        );
+
+        let err = check_message(
+            r#"[{"jsonrpc":"2.0","method":"a"},{"jsonrpc":"2.0","result":null,"id":2}]"#,
+        )
+        .unwrap_err();
+        assert!(
+            matches!(err, WireIncompatibility::Mismatch { ref path, .. } if path == "/1/result")
+        );
+    }
+
+    #[test]
+    fn test_check_traffic_reports_lines() {
+        let captured = "{\"jsonrpc\":\"2.0\",\"method\":\"a\"}\n\nnot json\n{\"id\":1}\n";
+        let report = check_traffic(captured.as_bytes()).unwrap();
+        assert_eq!(report.checked, 3);
+        let lines: Vec<usize> = report.failures.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, vec![3, 4]);
+        assert!(!report.is_compatible());
+    }
+}
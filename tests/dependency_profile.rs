@@ -0,0 +1,68 @@
+//! Keeps the `minimal` feature profile free of heavy dependencies.
+//!
+//! Resolves the normal (non-dev) dependency tree of `--features minimal`
+//! with `cargo tree` and fails if anything from the HTTP, metrics, TLS or
+//! proc-macro-heavy parts of the stack sneaks in through a feature change.
+
+use std::process::Command;
+
+const FORBIDDEN: &[&str] = &[
+    "axum",
+    "hyper",
+    "tower",
+    "prometheus",
+    "opentelemetry",
+    "tokio-rustls",
+    "rustls",
+    "tokio-macros",
+    "signal-hook-registry",
+    "tracing-attributes",
+    "futures-core",
+    "schemars",
+];
+
+fn minimal_tree() -> Vec<String> {
+    let output = Command::new(env!("CARGO"))
+        .args([
+            "tree",
+            "--offline",
+            "--manifest-path",
+            concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"),
+            "--edges",
+            "normal",
+            "--no-default-features",
+            "--features",
+            "minimal",
+            "--prefix",
+            "none",
+            "--format",
+            "{p}",
+        ])
+        .output()
+        .expect("failed to run cargo tree");
+    assert!(
+        output.status.success(),
+        "cargo tree failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn minimal_profile_stays_lean() {
+    let tree = minimal_tree();
+    assert!(tree.iter().any(|name| name == "tokio"));
+
+    let unexpected: Vec<_> = tree
+        .iter()
+        .filter(|name| FORBIDDEN.contains(&name.as_str()))
+        .collect();
+    assert!(
+        unexpected.is_empty(),
+        "minimal profile pulls in {unexpected:?}"
+    );
+}
@@ -0,0 +1,122 @@
+//! `#[rpc_method]` expansions driven through a registry.
+//!
+//! ```text
+//! cargo test --test derive --features derive,schemars
+//! ```
+
+#![cfg(feature = "derive")]
+
+use ash_rpc::derive::rpc_method;
+use ash_rpc::*;
+use serde_json::json;
+
+/// Greet someone.
+///
+/// Falls back to the caller's address
+/// when no name is given.
+#[rpc_method(name = "greet")]
+async fn greet(name: Option<String>, ctx: &CallContext<'_>) -> String {
+    let who = name
+        .or_else(|| ctx.connection.remote_addr.map(|addr| addr.to_string()))
+        .unwrap_or_else(|| "stranger".to_string());
+    format!("hello {who}")
+}
+
+#[derive(Debug)]
+struct Overdrawn(u64);
+
+impl From<Overdrawn> for Error {
+    fn from(e: Overdrawn) -> Self {
+        Error::new(-32010, format!("overdrawn by {}", e.0))
+    }
+}
+
+#[rpc_method]
+fn withdraw(balance: u64, amount: u64) -> Result<u64, Overdrawn> {
+    balance
+        .checked_sub(amount)
+        .ok_or_else(|| Overdrawn(amount - balance))
+}
+
+async fn call(registry: &MethodRegistry, method: &str, params: serde_json::Value) -> Response {
+    registry.call(method, Some(params), Some(json!(1))).await
+}
+
+#[tokio::test]
+async fn test_params_by_position_and_name() {
+    let registry = MethodRegistry::new(register_methods![Greet, Withdraw]);
+
+    let response = call(&registry, "withdraw", json!([10, 4])).await;
+    assert_eq!(response.result, Some(json!(6)));
+    let response = call(&registry, "withdraw", json!({"amount": 4, "balance": 10})).await;
+    assert_eq!(response.result, Some(json!(6)));
+    let response = call(&registry, "greet", json!({"name": "ann"})).await;
+    assert_eq!(response.result, Some(json!("hello ann")));
+    let response = registry.call("greet", None, Some(json!(1))).await;
+    assert_eq!(response.result, Some(json!("hello stranger")));
+
+    let error = call(&registry, "withdraw", json!([1, 4]))
+        .await
+        .error
+        .unwrap();
+    assert_eq!(
+        (error.code, error.message.as_str()),
+        (-32010, "overdrawn by 3")
+    );
+    for params in [
+        json!([10]),
+        json!([1, 2, 3]),
+        json!({"balance": "ten", "amount": 1}),
+        json!(5),
+    ] {
+        let error = call(&registry, "withdraw", params).await.error.unwrap();
+        assert_eq!(error.code, error_codes::INVALID_PARAMS);
+    }
+}
+
+#[test]
+fn test_spec_from_docs_and_signature() {
+    let spec = JsonRPCMethod::openapi_components(&Greet);
+    assert_eq!(spec.summary.as_deref(), Some("Greet someone."));
+    assert_eq!(
+        spec.description.as_deref(),
+        Some("Falls back to the caller's address\nwhen no name is given.")
+    );
+    assert_eq!(
+        spec.parameters,
+        Some(json!({"type": "object", "properties": {"name": {}}, "required": []}))
+    );
+
+    let spec = JsonRPCMethod::openapi_components(&Withdraw);
+    assert_eq!(
+        spec.parameters.unwrap()["required"],
+        json!(["balance", "amount"])
+    );
+    assert!(spec.summary.is_none() && spec.result.is_none());
+}
+
+#[cfg(feature = "schemars")]
+#[test]
+fn test_schemars_schemas() {
+    #[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+    struct Point {
+        x: f64,
+        y: f64,
+    }
+
+    #[rpc_method(name = "geo.mirror", schema)]
+    async fn mirror(point: Point) -> Point {
+        Point {
+            x: -point.x,
+            y: point.y,
+        }
+    }
+
+    let spec = JsonRPCMethod::openapi_components(&Mirror);
+    let parameters = spec.parameters.unwrap();
+    assert_eq!(
+        parameters["properties"]["point"]["properties"]["x"]["type"],
+        "number"
+    );
+    assert_eq!(spec.result.unwrap()["required"], json!(["x", "y"]));
+}
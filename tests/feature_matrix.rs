@@ -0,0 +1,618 @@
+//! End-to-end checks for representative feature combinations.
+//!
+//! Every module runs a real server on a loopback socket and only compiles
+//! when its features are enabled, so the matrix is driven from the command
+//! line:
+//!
+//! ```text
+//! cargo test --test feature_matrix --features tcp
+//! cargo test --test feature_matrix --features tcp-stream,shutdown
+//! cargo test --test feature_matrix --features tcp-stream,streaming
+//! cargo test --test feature_matrix --features stateful,axum
+//! cargo test --test feature_matrix --features streaming,tcp-stream-tls
+//! cargo test --test feature_matrix --all-features
+//! ```
+
+// Not every combination uses every helper
+#[allow(dead_code)]
+mod common {
+    use ash_rpc::*;
+    use serde_json::json;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    pub struct Echo;
+
+    #[async_trait]
+    impl JsonRPCMethod for Echo {
+        fn method_name(&self) -> &'static str {
+            "echo"
+        }
+
+        async fn call(&self, params: Option<serde_json::Value>, id: Option<RequestId>) -> Response {
+            rpc_success!(params.unwrap_or_default(), id)
+        }
+    }
+
+    /// `bump` increments, `bumps` reports the count; used to observe notifications
+    pub struct Bump(pub Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl JsonRPCMethod for Bump {
+        fn method_name(&self) -> &'static str {
+            "bump"
+        }
+
+        async fn call(
+            &self,
+            _params: Option<serde_json::Value>,
+            id: Option<RequestId>,
+        ) -> Response {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            rpc_success!(true, id)
+        }
+    }
+
+    pub struct Bumps(pub Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl JsonRPCMethod for Bumps {
+        fn method_name(&self) -> &'static str {
+            "bumps"
+        }
+
+        async fn call(
+            &self,
+            _params: Option<serde_json::Value>,
+            id: Option<RequestId>,
+        ) -> Response {
+            rpc_success!(self.0.load(Ordering::SeqCst), id)
+        }
+    }
+
+    pub struct AdminSecret;
+
+    #[async_trait]
+    impl JsonRPCMethod for AdminSecret {
+        fn method_name(&self) -> &'static str {
+            "admin.secret"
+        }
+
+        async fn call(
+            &self,
+            _params: Option<serde_json::Value>,
+            id: Option<RequestId>,
+        ) -> Response {
+            rpc_success!("hunter2", id)
+        }
+    }
+
+    /// Denies the `admin.` namespace
+    pub struct DenyAdmin;
+
+    impl auth::AuthPolicy for DenyAdmin {
+        fn can_access(
+            &self,
+            method: &str,
+            _params: Option<&serde_json::Value>,
+            _ctx: &auth::ConnectionContext,
+        ) -> bool {
+            !method.starts_with("admin.")
+        }
+    }
+
+    pub fn registry() -> MethodRegistry {
+        let bumps = Arc::new(AtomicUsize::new(0));
+        MethodRegistry::new(register_methods![
+            Echo,
+            Bump(bumps.clone()),
+            Bumps(bumps),
+            AdminSecret
+        ])
+        .allow_reserved_namespaces()
+        .with_auth(DenyAdmin)
+    }
+
+    /// A loopback address nothing listens on yet
+    pub fn free_addr() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().to_string()
+    }
+
+    /// Wait until a server accepts connections on `addr`
+    pub async fn wait_for(addr: &str) {
+        for _ in 0..200 {
+            if tokio::net::TcpStream::connect(addr).await.is_ok() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("server on {addr} did not come up");
+    }
+
+    pub fn request(method: &str, params: serde_json::Value, id: u64) -> Message {
+        Message::Request(
+            RequestBuilder::new(method)
+                .params(params)
+                .id(json!(id))
+                .build(),
+        )
+    }
+
+    pub fn notification(method: &str) -> Message {
+        Message::Notification(Notification::new(method))
+    }
+
+    /// Line-delimited client speaking raw JSON, for transports without a
+    /// dedicated client type
+    pub struct LineClient {
+        reader: tokio::io::BufReader<tokio::net::TcpStream>,
+    }
+
+    impl LineClient {
+        pub async fn connect(addr: &str) -> Self {
+            let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            Self {
+                reader: tokio::io::BufReader::new(stream),
+            }
+        }
+
+        pub async fn send(&mut self, message: &Message) {
+            use tokio::io::AsyncWriteExt;
+            let mut line = serde_json::to_vec(message).unwrap();
+            line.push(b'\n');
+            self.reader.get_mut().write_all(&line).await.unwrap();
+        }
+
+        pub async fn recv(&mut self) -> Response {
+            use tokio::io::AsyncBufReadExt;
+            let mut line = String::new();
+            tokio::time::timeout(Duration::from_secs(5), self.reader.read_line(&mut line))
+                .await
+                .expect("timed out waiting for response")
+                .unwrap();
+            serde_json::from_str(&line).unwrap()
+        }
+
+        /// Next response carrying an id.
+        ///
+        /// Line transports read an id-less message as a request without an
+        /// id and still write a reply for it, so those are skipped.
+        pub async fn recv_with_id(&mut self) -> Response {
+            loop {
+                let response = self.recv().await;
+                if response.id.is_some() {
+                    return response;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tcp")]
+mod tcp {
+    use super::common::*;
+    use ash_rpc::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_request_notification_and_auth_denial() {
+        let addr = free_addr();
+        let server = TcpServer::builder(addr.clone())
+            .processor(registry())
+            .build()
+            .unwrap();
+        std::thread::spawn(move || server.run());
+        wait_for(&addr).await;
+
+        let mut client = LineClient::connect(&addr).await;
+        client.send(&request("echo", json!({"a": 1}), 1)).await;
+        assert_eq!(client.recv().await.result, Some(json!({"a": 1})));
+
+        client.send(&notification("bump")).await;
+        client.send(&request("bumps", json!(null), 2)).await;
+        let response = client.recv_with_id().await;
+        assert_eq!(response.id, Some(json!(2)));
+        assert_eq!(response.result, Some(json!(1)));
+
+        client.send(&request("admin.secret", json!(null), 3)).await;
+        assert!(client.recv().await.is_error());
+    }
+}
+
+#[cfg(all(feature = "tcp-stream", feature = "shutdown"))]
+mod tcp_stream_shutdown {
+    use super::common::*;
+    use ash_rpc::*;
+    use serde_json::json;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_persistent_connection_then_shutdown() {
+        let addr = free_addr();
+        let server = TcpStreamServer::builder(addr.clone())
+            .processor(registry())
+            .build()
+            .unwrap();
+
+        let manager = ShutdownManager::new(
+            ShutdownConfigBuilder::new()
+                .handle_signals(false)
+                .grace_period(Duration::from_millis(50))
+                .build(),
+        );
+        let hook_ran = Arc::new(AtomicBool::new(false));
+        let flag = hook_ran.clone();
+        manager
+            .register_hook(move || {
+                let flag = flag.clone();
+                async move { flag.store(true, Ordering::SeqCst) }
+            })
+            .await;
+        let handle = manager.handle();
+
+        let server_task = tokio::spawn(async move {
+            tokio::select! {
+                result = server.run() => result.map_err(|e| e.to_string()),
+                _ = manager.wait_for_shutdown() => Ok(()),
+            }
+        });
+        wait_for(&addr).await;
+
+        let mut client = TcpStreamClientBuilder::new(addr.clone())
+            .connect()
+            .await
+            .unwrap();
+        client
+            .send_message(&request("echo", json!([1, 2]), 1))
+            .await
+            .unwrap();
+        client.send_message(&notification("bump")).await.unwrap();
+        client
+            .send_message(&request("bumps", json!(null), 2))
+            .await
+            .unwrap();
+        client
+            .send_message(&request("admin.secret", json!(null), 3))
+            .await
+            .unwrap();
+
+        // The denial carries no id, so collect until it arrives
+        let mut responses = Vec::new();
+        loop {
+            let message = tokio::time::timeout(Duration::from_secs(5), client.recv_message())
+                .await
+                .expect("timed out waiting for response")
+                .unwrap();
+            let response = match message {
+                Some(Message::Response(response)) => response,
+                other => panic!("unexpected message: {other:?}"),
+            };
+            let denied = response.is_error();
+            responses.push(response);
+            if denied {
+                break;
+            }
+        }
+        let result = |id: u64| {
+            responses
+                .iter()
+                .find(|r| r.id == Some(json!(id)))
+                .and_then(|r| r.result.clone())
+        };
+        assert_eq!(result(1), Some(json!([1, 2])));
+        assert_eq!(result(2), Some(json!(1)));
+
+        handle.shutdown().await;
+        let result = tokio::time::timeout(Duration::from_secs(5), server_task)
+            .await
+            .expect("server did not stop")
+            .unwrap();
+        assert!(result.is_ok());
+        assert!(hook_ran.load(Ordering::SeqCst));
+        assert!(tokio::net::TcpStream::connect(&addr).await.is_err());
+    }
+}
+
+#[cfg(all(feature = "tcp-stream", feature = "streaming"))]
+mod tcp_stream_streaming {
+    use super::common::*;
+    use ash_rpc::*;
+    use serde_json::json;
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
+
+    struct Ticks;
+
+    #[async_trait]
+    impl StreamHandler for Ticks {
+        fn subscription_method(&self) -> &'static str {
+            "ticks"
+        }
+
+        async fn subscribe(
+            &self,
+            _params: Option<serde_json::Value>,
+            stream_id: StreamId,
+        ) -> Result<StreamResponse, Error> {
+            Ok(StreamResponse::success(stream_id, json!(0)))
+        }
+
+        async fn unsubscribe(&self, _stream_id: &str) -> Result<(), Error> {
+            Ok(())
+        }
+
+        async fn start_stream(
+            &self,
+            stream_id: StreamId,
+            _params: Option<serde_json::Value>,
+            sender: mpsc::UnboundedSender<StreamEvent>,
+        ) -> Result<(), Error> {
+            let _ = sender.send(StreamEvent::new(stream_id, "ticks", json!(1)));
+            Ok(())
+        }
+
+        async fn is_active(&self, _stream_id: &str) -> bool {
+            true
+        }
+    }
+
+    /// `stream.subscribe` / `stream.unsubscribe` bridged onto a manager
+    struct Subscribe(Arc<StreamManager>);
+
+    #[async_trait]
+    impl JsonRPCMethod for Subscribe {
+        fn method_name(&self) -> &'static str {
+            "stream.subscribe"
+        }
+
+        async fn call(&self, params: Option<serde_json::Value>, id: Option<RequestId>) -> Response {
+            let method = params
+                .as_ref()
+                .and_then(|p| p["method"].as_str())
+                .unwrap_or_default();
+            let request = StreamRequest::new(method, id.clone().unwrap_or_default());
+            match self.0.subscribe(request).await {
+                Ok(response) => rpc_success!(json!({"stream_id": response.stream_id}), id),
+                Err(error) => rpc_error!(error.code, error.message, id),
+            }
+        }
+    }
+
+    struct Unsubscribe(Arc<StreamManager>);
+
+    #[async_trait]
+    impl JsonRPCMethod for Unsubscribe {
+        fn method_name(&self) -> &'static str {
+            "stream.unsubscribe"
+        }
+
+        async fn call(&self, params: Option<serde_json::Value>, id: Option<RequestId>) -> Response {
+            let stream_id = params
+                .as_ref()
+                .and_then(|p| p["stream_id"].as_str())
+                .unwrap_or_default();
+            match self.0.unsubscribe(stream_id).await {
+                Ok(()) => rpc_success!(true, id),
+                Err(error) => rpc_error!(error.code, error.message, id),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_and_unsubscribe_over_stream() {
+        let manager = Arc::new(StreamManager::new());
+        manager.register_handler(Ticks).await;
+
+        let addr = free_addr();
+        let server = TcpStreamServer::builder(addr.clone())
+            .processor(MethodRegistry::new(register_methods![
+                Subscribe(manager.clone()),
+                Unsubscribe(manager.clone())
+            ]))
+            .build()
+            .unwrap();
+        tokio::spawn(async move { server.run().await.map_err(|e| e.to_string()) });
+        wait_for(&addr).await;
+
+        let mut client = LineClient::connect(&addr).await;
+        client
+            .send(&request("stream.subscribe", json!({"method": "ticks"}), 1))
+            .await;
+        let response = client.recv().await;
+        let stream_id = response.result.unwrap()["stream_id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert!(manager.is_active(&stream_id).await);
+
+        let event = manager.next_event().await.unwrap();
+        assert_eq!(event.stream_id(), stream_id);
+
+        client
+            .send(&request(
+                "stream.unsubscribe",
+                json!({"stream_id": stream_id}),
+                2,
+            ))
+            .await;
+        assert_eq!(client.recv().await.result, Some(json!(true)));
+        assert_eq!(manager.active_count().await, 0);
+
+        client
+            .send(&request(
+                "stream.subscribe",
+                json!({"method": "missing"}),
+                3,
+            ))
+            .await;
+        let error = client.recv().await.error.unwrap();
+        assert_eq!(error.code, error_codes::METHOD_NOT_FOUND);
+    }
+}
+
+#[cfg(all(feature = "stateful", feature = "axum"))]
+mod stateful_axum {
+    use ash_rpc::axum::{AxumRpcLayer, handle_rpc_batch};
+    use ash_rpc::*;
+    use http_body_util::{BodyExt, Full};
+    use hyper::body::Bytes;
+    use hyper_util::client::legacy::Client;
+    use hyper_util::rt::TokioExecutor;
+    use serde_json::json;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[derive(Debug)]
+    struct NeverFails;
+
+    impl std::fmt::Display for NeverFails {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("never fails")
+        }
+    }
+
+    impl std::error::Error for NeverFails {}
+
+    struct Counter {
+        hits: AtomicU64,
+    }
+
+    impl ServiceContext for Counter {
+        type Error = NeverFails;
+    }
+
+    struct Hit;
+
+    #[async_trait]
+    impl StatefulJsonRPCMethod<Counter> for Hit {
+        fn method_name(&self) -> &'static str {
+            "hit"
+        }
+
+        async fn call(
+            &self,
+            context: &Counter,
+            _params: Option<serde_json::Value>,
+            id: Option<RequestId>,
+        ) -> Result<Response, NeverFails> {
+            let hits = context.hits.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(rpc_success!(hits, id))
+        }
+    }
+
+    async fn post(addr: &str, path: &str, body: serde_json::Value) -> serde_json::Value {
+        let client = Client::builder(TokioExecutor::new()).build_http::<Full<Bytes>>();
+        let request = hyper::Request::post(format!("http://{addr}{path}"))
+            .header("content-type", "application/json")
+            .body(Full::new(Bytes::from(body.to_string())))
+            .unwrap();
+        let response = client.request(request).await.unwrap();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_stateful_processor_over_http() {
+        // The layer only routes single messages; `/batch` gets its own
+        // processor and therefore its own counter
+        let batch_processor: Arc<dyn MessageProcessor + Send + Sync> = Arc::new(
+            StatefulProcessor::builder(Counter {
+                hits: AtomicU64::new(0),
+            })
+            .registry(StatefulMethodRegistry::new().register(Hit))
+            .build()
+            .unwrap(),
+        );
+
+        let router = AxumRpcLayer::builder()
+            .processor(StatefulProcessor::new(
+                Counter {
+                    hits: AtomicU64::new(0),
+                },
+                StatefulMethodRegistry::new().register(Hit),
+            ))
+            .path("/rpc")
+            .build()
+            .unwrap()
+            .into_router()
+            .merge(
+                ::axum::Router::new()
+                    .route("/batch", ::axum::routing::post(handle_rpc_batch))
+                    .with_state(batch_processor),
+            );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move { ::axum::serve(listener, router).await });
+
+        let single = json!({"jsonrpc": "2.0", "method": "hit", "id": 1});
+        assert_eq!(post(&addr, "/rpc", single.clone()).await["result"], 1);
+        assert_eq!(post(&addr, "/rpc", single).await["result"], 2);
+
+        let batch = json!([
+            {"jsonrpc": "2.0", "method": "hit", "id": 1},
+            {"jsonrpc": "2.0", "method": "hit"},
+            {"jsonrpc": "2.0", "method": "missing", "id": 3}
+        ]);
+        let responses = post(&addr, "/batch", batch).await;
+        let responses: Vec<_> = responses
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|response| !response["id"].is_null())
+            .collect();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["result"], 1);
+        assert_eq!(
+            responses[1]["error"]["code"],
+            json!(error_codes::METHOD_NOT_FOUND)
+        );
+    }
+}
+
+#[cfg(all(feature = "streaming", feature = "tcp-stream-tls"))]
+mod streaming_tls {
+    use super::common::*;
+    use ash_rpc::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_tls_request_and_auth_denial() {
+        let _ = tokio_rustls::rustls::crypto::aws_lc_rs::default_provider().install_default();
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let tls = TlsConfig::from_pem_bytes(
+            cert.cert.pem().as_bytes(),
+            cert.signing_key.serialize_pem().as_bytes(),
+        )
+        .unwrap();
+
+        let addr = free_addr();
+        let server = TcpStreamTlsServer::builder(addr.clone())
+            .processor(registry())
+            .tls_config(tls)
+            .build()
+            .unwrap();
+        tokio::spawn(async move { server.run().await.map_err(|e| e.to_string()) });
+        wait_for(&addr).await;
+
+        let mut client = TcpStreamTlsClient::connect_insecure(&addr).await.unwrap();
+        let Message::Request(echo) = request("echo", json!("over tls"), 1) else {
+            unreachable!()
+        };
+        client.send_request(&echo).await.unwrap();
+        assert_eq!(
+            client.recv_response().await.unwrap().result,
+            Some(json!("over tls"))
+        );
+
+        let Message::Request(secret) = request("admin.secret", json!(null), 2) else {
+            unreachable!()
+        };
+        client.send_request(&secret).await.unwrap();
+        assert!(client.recv_response().await.unwrap().is_error());
+    }
+}
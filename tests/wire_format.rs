@@ -0,0 +1,155 @@
+//! Golden-file tests pinning the JSON encoding of public message types.
+//!
+//! Each case is serialized and compared with `tests/golden/<name>.json`, and
+//! the checked-in file must decode and re-encode unchanged, so both
+//! directions of the wire format are covered. After an intentional format
+//! change, regenerate the files and review the diff:
+//!
+//! ```text
+//! UPDATE_GOLDEN=1 cargo test --test wire_format --all-features
+//! ```
+
+use ash_rpc::*;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::path::PathBuf;
+
+fn assert_golden<T: Serialize + DeserializeOwned>(name: &str, value: &T) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.json"));
+    let actual = serde_json::to_value(value).unwrap();
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        let text = serde_json::to_string_pretty(&actual).unwrap();
+        std::fs::write(&path, text + "\n").unwrap();
+        return;
+    }
+
+    let text = std::fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "missing golden file {}: {e}; run with UPDATE_GOLDEN=1",
+            path.display()
+        )
+    });
+    let expected: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(
+        actual,
+        expected,
+        "{name} no longer encodes as {}",
+        path.display()
+    );
+    if let Err(e) = wire::check_as::<T>(&text) {
+        panic!("{name} golden file does not round-trip: {e}");
+    }
+}
+
+#[test]
+fn request_golden() {
+    let mut request = Request::new("user.get")
+        .with_params(json!({"id": 42}))
+        .with_id(json!(1));
+    request.correlation_id = Some("corr-1".to_string());
+    assert_golden("request", &request);
+
+    let mut bare = Request::new("ping").with_id(json!("abc"));
+    bare.correlation_id = None;
+    assert_golden("request_minimal", &bare);
+}
+
+#[test]
+fn response_golden() {
+    assert_golden(
+        "response_success",
+        &Response::success(json!({"name": "ada"}), Some(json!(1))),
+    );
+    assert_golden(
+        "response_error",
+        &Response::error(
+            Error::new(error_codes::INVALID_PARAMS, "Invalid params")
+                .with_data(json!({"field": "id"})),
+            Some(json!(1)),
+        ),
+    );
+
+    let mut batch_item = Response::success(json!(true), Some(json!(2)));
+    batch_item.correlation_id = Some("corr-2".to_string());
+    batch_item.ext = Some(ResponseExtensions {
+        batch: Some(BatchItemMeta {
+            index: 3,
+            duration_us: 150,
+        }),
+    });
+    assert_golden("response_batch_item", &batch_item);
+}
+
+#[test]
+fn error_golden() {
+    assert_golden(
+        "error",
+        &Error::new(error_codes::METHOD_NOT_FOUND, "Method not found"),
+    );
+}
+
+#[test]
+fn notification_golden() {
+    assert_golden(
+        "notification",
+        &Notification::new("log.append").with_params(json!(["line"])),
+    );
+}
+
+#[test]
+fn capabilities_golden() {
+    assert_golden("capabilities", &ProcessorCapabilities::default());
+}
+
+#[cfg(feature = "streaming")]
+#[test]
+fn stream_messages_golden() {
+    assert_golden(
+        "stream_request",
+        &StreamMessage::StreamRequest(
+            StreamRequest::new("ticks", json!(1))
+                .with_params(json!({"symbol": "ASH"}))
+                .with_stream_id("s-1"),
+        ),
+    );
+    assert_golden(
+        "stream_response",
+        &StreamMessage::StreamResponse(StreamResponse::success("s-1".to_string(), json!(1))),
+    );
+    assert_golden(
+        "stream_event",
+        &StreamMessage::StreamEvent(
+            StreamEvent::new("s-1".to_string(), "ticks", json!({"price": 10})).with_sequence(7),
+        ),
+    );
+    assert_golden(
+        "stream_event_closed",
+        &StreamMessage::StreamEvent(StreamEvent::closed("s-1".to_string(), "ticks")),
+    );
+    assert_golden(
+        "unsubscribe_request",
+        &StreamMessage::UnsubscribeRequest(UnsubscribeRequest::new("s-1".to_string(), json!(2))),
+    );
+}
+
+#[cfg(feature = "audit-logging")]
+#[test]
+fn audit_event_golden() {
+    let mut event = AuditEvent::builder()
+        .event_type(AuditEventType::MethodInvocation)
+        .correlation_id("corr-3")
+        .remote_addr("127.0.0.1:9000".parse().unwrap())
+        .principal("alice")
+        .method("account.debit")
+        .result(AuditResult::Denied)
+        .metadata("policy", "scope")
+        .params(json!({"amount": "[REDACTED]"}))
+        .error("missing scope")
+        .build();
+    event.timestamp = std::time::UNIX_EPOCH + std::time::Duration::new(1_700_000_000, 5);
+    assert_golden("audit_event", &event);
+}